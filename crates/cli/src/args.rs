@@ -1,8 +1,9 @@
 use clap_cryo::Parser;
 use color_print::cstr;
 use colored::Colorize;
+use cryo_freeze::ParseError;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::{default::Default, path::PathBuf};
 
 /// Command line arguments
@@ -26,7 +27,7 @@ pub struct Args {
     #[arg(short, long, allow_negative_numbers = true, help_heading = "Content Options", num_args(1..))]
     pub blocks: Option<Vec<String>>,
 
-    /// Timestamps in unix, see syntax below
+    /// Timestamps in unix or dates, see syntax below
     #[arg(long, allow_negative_numbers = true, help_heading = "Content Options", num_args(0..))]
     pub timestamps: Option<Vec<String>>,
 
@@ -39,6 +40,21 @@ pub struct Args {
     )]
     pub txs: Option<Vec<String>>,
 
+    /// File of transaction hashes, one hash per line, or a csv,
+    /// parquet, or ipc file with a --txs-column column
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Content Options",
+        conflicts_with = "txs",
+        verbatim_doc_comment
+    )]
+    pub txs_file: Option<String>,
+
+    /// Column of --txs-file with hashes [default: transaction_hash]
+    #[arg(long, value_name = "COLUMN", help_heading = "Content Options")]
+    pub txs_column: Option<String>,
+
     /// Align chunk boundaries to regular intervals,
     /// e.g. (1000 2000 3000), not (1106 2106 3106)
     #[arg(short, long, help_heading = "Content Options", verbatim_doc_comment)]
@@ -55,13 +71,41 @@ pub struct Args {
     )]
     pub reorg_buffer: u64,
 
+    /// Keep collecting new blocks after reaching the chain tip,
+    /// holding back blocks within the reorg buffer
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub follow: bool,
+
+    /// Seconds between polls for new blocks in follow mode, ws urls also subscribe to new blocks
+    #[arg(long, default_value_t = 12, value_name = "SECONDS", help_heading = "Content Options")]
+    pub poll_interval: u64,
+
+    /// Write partial chunks in follow mode after
+    /// this many seconds without writing a chunk
+    #[arg(long, value_name = "SECONDS", help_heading = "Content Options", verbatim_doc_comment)]
+    pub flush_interval: Option<u64>,
+
+    /// Check that block hashes link within and across chunks,
+    /// failing chunks or warning on mismatch [default: error]
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args(0..=1),
+        default_missing_value = "error",
+        help_heading = "Content Options",
+        verbatim_doc_comment
+    )]
+    pub verify_hashes: Option<String>,
+
     /// Columns to include alongside the defaults,
-    /// use `all` to include all available columns
+    /// use `all` to include all available columns,
+    /// use DATASET:COL1,COL2 to scope columns to a dataset,
+    /// use timestamp or datetime to join block times
     #[arg(short, long, value_name="COLS", num_args(0..), verbatim_doc_comment, help_heading="Content Options")]
     pub include_columns: Option<Vec<String>>,
 
     /// Columns to exclude from the defaults
-    #[arg(short, long, value_name="COLS", num_args(0..), help_heading="Content Options")]
+    #[arg(short, long, visible_alias="exclude", value_name="COLS", num_args(0..), help_heading="Content Options")]
     pub exclude_columns: Option<Vec<String>>,
 
     /// Columns to use instead of the defaults,
@@ -69,40 +113,123 @@ pub struct Args {
     #[arg(long, value_name="COLS", num_args(0..), verbatim_doc_comment, help_heading="Content Options")]
     pub columns: Option<Vec<String>>,
 
-    /// Set output datatype(s) of U256 integers
+    /// Set output datatype(s) of U256 integers, e.g. binary,string,f64
     /// [default: binary, string, f64]
-    #[arg(long, num_args(1..), help_heading = "Content Options", verbatim_doc_comment)]
+    #[arg(
+        long,
+        num_args(1..),
+        value_delimiter = ',',
+        help_heading = "Content Options",
+        verbatim_doc_comment
+    )]
     pub u256_types: Option<Vec<String>>,
 
     /// Use hex string encoding for binary columns
     #[arg(long, help_heading = "Content Options")]
     pub hex: bool,
 
+    /// Use EIP-55 checksums for hex address columns
+    #[arg(long, help_heading = "Content Options")]
+    pub checksum_addresses: bool,
+
+    /// Set output datatype of block numbers, u32 or u64
+    /// [default: u32]
+    #[arg(long, value_name = "TYPE", verbatim_doc_comment, help_heading = "Content Options")]
+    pub block_number_type: Option<String>,
+
+    /// Constant columns to add to every dataset, as KEY=VALUE
+    #[arg(long, value_name = "KEY=VALUE", num_args(1..), help_heading = "Content Options")]
+    pub tag: Option<Vec<String>>,
+
+    /// Rename output columns, as OLD=NEW,
+    /// use DATASET:OLD=NEW to rename columns of a dataset
+    #[arg(long, value_name = "OLD=NEW", num_args(1..), verbatim_doc_comment, help_heading = "Content Options")]
+    pub rename: Option<Vec<String>>,
+
     /// Columns(s) to sort by, `none` for unordered
-    #[arg(short, long, num_args(0..), help_heading="Content Options")]
+    #[arg(short, long, num_args(0..), value_delimiter = ',', help_heading="Content Options")]
     pub sort: Option<Vec<String>>,
 
+    /// Write rows in collection order instead of sorting them
+    #[arg(long, help_heading = "Content Options", conflicts_with = "sort")]
+    pub no_sort: bool,
+
     /// Exclude items from failed transactions
     #[arg(long, help_heading = "Content Options")]
     pub exclude_failed: bool,
 
-    /// RPC url [default: ETH_RPC_URL env var]
-    #[arg(short, long, help_heading = "Source Options")]
-    pub rpc: Option<String>,
+    /// RPC url, ws url, or ipc path, repeat to balance requests [default: ETH_RPC_URL env var]
+    /// label as NAME=URL to collect several chains, e.g. mainnet=URL,base=URL
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help_heading = "Source Options",
+        verbatim_doc_comment
+    )]
+    pub rpc: Option<Vec<String>>,
+
+    /// File of RPC urls, one per line
+    #[arg(long, value_name = "PATH", help_heading = "Source Options")]
+    pub rpc_file: Option<String>,
+
+    /// How requests are balanced across RPC urls (round-robin or least-in-flight)
+    #[arg(long, value_name = "STRATEGY", help_heading = "Source Options")]
+    pub rpc_strategy: Option<String>,
+
+    /// Header sent with RPC requests, repeatable, values expand ${ENV_VAR}
+    #[arg(long, value_name = "\"NAME: VALUE\"", help_heading = "Source Options")]
+    pub rpc_header: Option<Vec<String>>,
+
+    /// Basic auth of RPC requests, expands ${ENV_VAR}
+    #[arg(long, value_name = "USER:PASS", help_heading = "Source Options")]
+    pub rpc_auth: Option<String>,
+
+    /// Seconds an erroring RPC url is quarantined before it is probed again
+    #[arg(long, default_value_t = 30, value_name = "S", help_heading = "Source Options")]
+    pub rpc_cooldown: u64,
+
+    /// Number of concurrent requests of each RPC url
+    #[arg(long, value_name = "M", help_heading = "Source Options")]
+    pub rpc_concurrency: Option<u64>,
+
+    /// Number of calls sent together in each JSON-RPC batch request
+    #[arg(long, value_name = "N", help_heading = "Source Options")]
+    pub rpc_batch_size: Option<u64>,
+
+    /// Directory caching responses for final blocks, see cryo cache
+    #[arg(long, value_name = "PATH", help_heading = "Source Options")]
+    pub cache_dir: Option<String>,
+
+    /// Maximum size of response cache, e.g. 512MB [default: 10GB]
+    #[arg(long, value_name = "SIZE", help_heading = "Source Options")]
+    pub cache_size: Option<String>,
+
+    /// Methods of traces and state diffs (auto, parity, or geth) [default: auto]
+    #[arg(long, value_name = "BACKEND", help_heading = "Source Options")]
+    pub trace_backend: Option<String>,
 
     /// Network name [default: name of eth_getChainId]
     #[arg(long, help_heading = "Source Options")]
     pub network_name: Option<String>,
 
+    /// Chain name of chain_name column [default: name of chain in registry]
+    #[arg(long, value_name = "NAME", help_heading = "Source Options")]
+    pub chain_name_override: Option<String>,
+
+    /// Beacon node url, used for blob sidecars
+    #[arg(long, help_heading = "Source Options")]
+    pub beacon_url: Option<String>,
+
     /// Ratelimit on requests per second
     #[arg(short('l'), long, value_name = "limit", help_heading = "Acquisition Options")]
     pub requests_per_second: Option<u32>,
 
-    /// Max retries for provider errors
+    /// Max retries of rate limited or transient rpc errors
     #[arg(long, default_value_t = 5, value_name = "R", help_heading = "Acquisition Options")]
     pub max_retries: u32,
 
-    /// Initial retry backoff time (ms)
+    /// Initial retry backoff time (ms), doubled each retry
     #[arg(long, default_value_t = 500, value_name = "B", help_heading = "Acquisition Options")]
     pub initial_backoff: u64,
 
@@ -110,6 +237,16 @@ pub struct Args {
     #[arg(long, value_name = "M", help_heading = "Acquisition Options")]
     pub max_concurrent_requests: Option<u64>,
 
+    /// Adapt concurrency to rate limits and latency
+    #[arg(long, help_heading = "Acquisition Options")]
+    pub autoscale: bool,
+
+    /// When the request of a block fails: fail its chunk, skip the request
+    /// and record it in the report, or also record a placeholder in an
+    /// errors file in .cryo/errors, fail, skip, or null [default: fail]
+    #[arg(long, value_name = "MODE", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub on_block_error: Option<String>,
+
     /// Number of chunks processed concurrently
     #[arg(long, value_name = "M", help_heading = "Acquisition Options")]
     pub max_concurrent_chunks: Option<u64>,
@@ -118,10 +255,36 @@ pub struct Args {
     #[arg(long, help_heading = "Acquisition Options")]
     pub chunk_order: Option<String>,
 
+    /// Collect chunks from newest to oldest, same as --chunk-order reverse,
+    /// with --follow the chain tip is followed during the backfill
+    #[arg(long, help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub reverse: bool,
+
+    /// Collect the failed or missing chunks of the run of a report,
+    /// arguments of the current command override those of the report
+    #[arg(long, value_name = "REPORT", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub resume: Option<PathBuf>,
+
+    /// Resume even if the options change the schemas of the resumed run
+    #[arg(long, help_heading = "Acquisition Options", requires = "resume")]
+    pub force: bool,
+
     /// Dry run, collect no data
-    #[arg(short, long, help_heading = "Acquisition Options")]
+    #[arg(short, long, visible_alias = "dry-run", help_heading = "Acquisition Options")]
     pub dry: bool,
 
+    /// Blocks collected to estimate size of a dry run, 0 to disable
+    #[arg(long, default_value_t = 5, value_name = "N", help_heading = "Acquisition Options")]
+    pub dry_run_samples: u64,
+
+    /// Read arguments from a toml file, flags of the command override its values
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Profile of --config to apply over its top-level values
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// Remember current command for future use
     #[arg(long)]
     pub remember: bool,
@@ -142,19 +305,82 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub n_chunks: Option<u64>,
 
+    /// One file per day, hour, or week of block timestamps in UTC
+    /// (alternative to --chunk-size)
+    #[arg(long, value_name = "UNIT", help_heading = "Output Options", verbatim_doc_comment)]
+    pub chunk_by: Option<String>,
+
     /// Dimensions to partition by
     #[arg(long, help_heading = "Output Options")]
     pub partition_by: Option<Vec<String>>,
 
-    /// Directory for output files
+    /// Directory for output files, or s3://, gs://, or az:// url to upload them to
     #[arg(short, long, default_value = ".", help_heading = "Output Options")]
     pub output_dir: String,
 
+    /// Write the files of each chain of labeled --rpc endpoints
+    /// to a subdirectory of --output-dir named by its label
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub chain_subdirs: bool,
+
     /// Subdirectories for output files
     /// can be `datatype`, `network`, or custom string
     #[arg(long, help_heading = "Output Options", verbatim_doc_comment, num_args(1..))]
     pub subdirs: Vec<String>,
 
+    /// Split output into hive partition directories by keys
+    /// chain_id, date, block_bucket[:SIZE], or address
+    /// [default: chain_id]
+    #[arg(
+        long,
+        value_name = "KEYS",
+        help_heading = "Output Options",
+        verbatim_doc_comment,
+        num_args(0..),
+        value_delimiter = ','
+    )]
+    pub hive_partitioning: Option<Vec<String>>,
+
+    /// Split chunks into numbered files of at most this many rows
+    #[arg(long, value_name = "N", help_heading = "Output Options")]
+    pub max_rows_per_file: Option<usize>,
+
+    /// Chunks without rows, write files carrying the schema or skip them
+    /// with an empty chunk marker, write or skip [default: write]
+    #[arg(long, value_name = "MODE", help_heading = "Output Options", verbatim_doc_comment)]
+    pub empty_chunks: Option<String>,
+
+    /// Flush the rows of each chunk to its parquet file as row groups once
+    /// they take about this much memory, e.g. 512MB
+    #[arg(long, value_name = "SIZE", help_heading = "Output Options", verbatim_doc_comment)]
+    pub memory_budget: Option<String>,
+
+    /// Flush the rows of each chunk to its parquet file as row groups every N rows
+    #[arg(long, value_name = "N", help_heading = "Output Options")]
+    pub flush_rows: Option<u64>,
+
+    /// Number of threads encoding and writing output files,
+    /// defaults to the number of cpus
+    #[arg(long, value_name = "N", help_heading = "Output Options", verbatim_doc_comment)]
+    pub write_threads: Option<usize>,
+
+    /// Append the sha256, row count, and block range of each output file
+    /// to manifest.jsonl of the output directory
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub checksum_manifest: bool,
+
+    /// Maximum rows of files merged by cryo compact [default: 5_000_000]
+    #[arg(long, value_name = "N", help_heading = "Output Options")]
+    pub target_rows: Option<String>,
+
+    /// Maximum total size of files merged by cryo compact, e.g. 512MB
+    #[arg(long, value_name = "SIZE", help_heading = "Output Options")]
+    pub target_size: Option<String>,
+
+    /// Drop columns named after hive partition keys from output files
+    #[arg(long, help_heading = "Output Options")]
+    pub drop_partition_columns: bool,
+
     /// Label to add to each filename
     #[arg(long, help_heading = "Output Options")]
     pub label: Option<String>,
@@ -163,14 +389,66 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub overwrite: bool,
 
+    /// Extend existing datasets, starting ranges without a start block
+    /// after the last collected block and erroring on overlapping files
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub append: bool,
+
     /// Save as csv instead of parquet
     #[arg(long, help_heading = "Output Options")]
     pub csv: bool,
 
+    /// Compression of csv files, gzip or zstd
+    #[arg(long, value_name = "NAME", help_heading = "Output Options")]
+    pub csv_compression: Option<String>,
+
     /// Save as json instead of parquet
     #[arg(long, help_heading = "Output Options")]
     pub json: bool,
 
+    /// Compression of json and ndjson files, gzip or zstd
+    #[arg(long, value_name = "NAME", help_heading = "Output Options")]
+    pub json_compression: Option<String>,
+
+    /// Save as arrow ipc (feather v2) instead of parquet
+    #[arg(long, help_heading = "Output Options")]
+    pub ipc: bool,
+
+    /// Compression of arrow ipc files, lz4 or zstd
+    #[arg(long, value_name = "NAME", help_heading = "Output Options")]
+    pub ipc_compression: Option<String>,
+
+    /// Save as newline-delimited json in DIR instead of parquet,
+    /// or stream rows to stdout when DIR is -
+    #[arg(long, value_name = "DIR", help_heading = "Output Options", verbatim_doc_comment)]
+    pub ndjson: Option<String>,
+
+    /// Save as avro instead of parquet
+    #[arg(long, help_heading = "Output Options")]
+    pub avro: bool,
+
+    /// Compression codec of avro files, deflate or snappy
+    #[arg(long, value_name = "NAME", help_heading = "Output Options")]
+    pub avro_codec: Option<String>,
+
+    /// Load chunks into tables of a DuckDB database instead of
+    /// keeping output files, requires the duckdb feature
+    #[arg(long, value_name = "PATH", help_heading = "Output Options", verbatim_doc_comment)]
+    pub duckdb: Option<String>,
+
+    /// Load chunks into tables of a PostgreSQL database using
+    /// binary COPY, requires the postgres feature
+    #[arg(long, value_name = "URL", help_heading = "Output Options", verbatim_doc_comment)]
+    pub postgres: Option<String>,
+
+    /// Schema of PostgreSQL tables
+    #[arg(long, value_name = "NAME", default_value = "public", help_heading = "Output Options")]
+    pub postgres_schema: String,
+
+    /// Prefix of PostgreSQL table names
+    #[arg(long, value_name = "PREFIX", default_value = "", help_heading = "Output Options")]
+    pub postgres_table_prefix: String,
+
     /// Number of rows per row group in parquet file
     #[arg(long, value_name = "GROUP_SIZE", help_heading = "Output Options")]
     pub row_group_size: Option<usize>,
@@ -183,23 +461,77 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub no_stats: bool,
 
-    /// Compression algorithm and level
+    /// Statistics to write to parquet files, none, chunk, or page
+    #[arg(long, value_name = "LEVEL", help_heading = "Output Options")]
+    pub statistics: Option<String>,
+
+    /// Compression algorithm and level, e.g. zstd:7
     #[arg(long, help_heading="Output Options", value_name="NAME [#]", num_args(1..=2), default_value = "lz4")]
     pub compression: Vec<String>,
 
+    /// Compression of individual parquet columns, e.g. data=uncompressed
+    #[arg(long, value_name = "COLUMN=NAME", help_heading = "Output Options", num_args(1..))]
+    pub compression_columns: Vec<String>,
+
+    /// Write parquet bloom filters for columns, or for hash and address columns if none given
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        help_heading = "Output Options",
+        num_args(0..),
+        value_delimiter = ','
+    )]
+    pub bloom_filter_columns: Option<Vec<String>>,
+
+    /// False positive probability of bloom filters
+    #[arg(long, value_name = "FPP", help_heading = "Output Options", default_value_t = 0.01)]
+    pub bloom_filter_fpp: f64,
+
+    /// Distinct values per row group to size bloom filters for [default: counted]
+    #[arg(long, value_name = "N", help_heading = "Output Options")]
+    pub bloom_filter_ndv: Option<u64>,
+
     /// Directory to save summary report
     /// [default: {output_dir}/.cryo/reports]
     #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
     pub report_dir: Option<PathBuf>,
 
+    /// Path to save summary report, instead of a timestamped file
+    /// in --report-dir
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Output Options",
+        conflicts_with = "no_report",
+        verbatim_doc_comment
+    )]
+    pub report_file: Option<PathBuf>,
+
     /// Avoid saving a summary report
     #[arg(long, help_heading = "Output Options")]
     pub no_report: bool,
 
+    /// Serve prometheus metrics on address during collection,
+    /// requires building with --features metrics
+    #[arg(long, help_heading = "Output Options", value_name = "ADDR", verbatim_doc_comment)]
+    pub metrics_addr: Option<String>,
+
     /// Address(es)
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub address: Option<Vec<String>>,
 
+    /// File of addresses, one per line or in the first column of a
+    /// csv or parquet file, with optional labels in a second column,
+    /// joined on the address column of each dataset, e.g. erc20 of
+    /// erc20_transfers
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub address_file: Option<String>,
+
     /// To Address(es)
     #[arg(long, help_heading = "Dataset-specific Options", value_name="address", num_args(1..))]
     pub to_address: Option<Vec<String>>,
@@ -212,42 +544,145 @@ pub struct Args {
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub call_data: Option<Vec<String>>,
 
-    /// Function(s) to use for eth_calls
-    #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
+    /// Function(s) to use for eth_calls, as 4byte selectors
+    /// or signatures such as balanceOf(address)
+    #[arg(long, help_heading = "Dataset-specific Options", num_args(1..), verbatim_doc_comment)]
     pub function: Option<Vec<String>>,
 
     /// Input(s) to use for eth_calls
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub inputs: Option<Vec<String>>,
 
+    /// File of eth_calls arguments, one comma-separated argument
+    /// list per line, encoded using the --function signature
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub args_file: Option<String>,
+
+    /// Return type(s) used to decode eth_calls outputs into
+    /// the decoded_output column, e.g. uint256 or (address,uint256)
+    #[arg(
+        long,
+        value_name = "TYPES",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub return_type: Option<String>,
+
+    /// Pack this many eth_calls, erc20_balances, or erc4626_snapshots
+    /// calls into each Multicall3 request, on chains where Multicall3 is
+    /// deployed
+    #[arg(long, value_name = "N", help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub multicall_batch: Option<usize>,
+
     /// Slot(s)
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub slot: Option<Vec<String>>,
 
+    /// File of slots that storage_diffs and storage_reads are restricted to,
+    /// one hex slot per line, lines ending in * match slots by prefix
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub slots_file: Option<String>,
+
     /// Contract address(es)
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub contract: Option<Vec<String>>,
 
-    /// Topic0(s)
-    #[arg(long, visible_alias = "event", help_heading = "Dataset-specific Options", num_args(1..))]
+    /// File of contract addresses, one per line or in the first column
+    /// of a csv or parquet file, with optional labels in a second column,
+    /// joined like the labels of --address-file
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub contract_file: Option<String>,
+
+    /// Side of trace entries matched against --contract for traces, vm_traces,
+    /// and geth_calls: from, to, or either [default: either]
+    #[arg(
+        long,
+        value_name = "SIDE",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub trace_filter_side: Option<String>,
+
+    /// Geth style state override set json applied to eth_calls and trace_calls
+    #[arg(long, value_name = "PATH", help_heading = "Dataset-specific Options")]
+    pub state_override: Option<String>,
+
+    /// Rows of four_byte_counts: transaction for one row per
+    /// selector of each transaction, or range for one row per
+    /// selector of each chunk [default: transaction]
+    #[arg(
+        long,
+        value_name = "MODE",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub agg: Option<String>,
+
+    /// Only emit balances that changed since the previous
+    /// block of the chunk, the first block of each chunk
+    /// is always emitted
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub changes_only: bool,
+
+    /// Topic0(s), or event signature(s) hashed into topic0,
+    /// e.g. --event "Transfer(address,address,uint256)"
+    #[arg(
+        long,
+        visible_alias = "event",
+        help_heading = "Dataset-specific Options",
+        num_args(1..),
+        verbatim_doc_comment
+    )]
     pub topic0: Option<Vec<String>>,
 
-    /// Topic1(s)
+    /// Topic1(s), addresses are left-padded to 32 bytes
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub topic1: Option<Vec<String>>,
 
-    /// Topic2(s)
+    /// Topic2(s), addresses are left-padded to 32 bytes
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub topic2: Option<Vec<String>>,
 
-    /// Topic3(s)
+    /// Topic3(s), addresses are left-padded to 32 bytes
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub topic3: Option<Vec<String>>,
 
+    /// Group of space-separated log filters matched together, repeat
+    /// to collect logs matching any group, e.g.
+    /// --filter-set "address=0x.. event=Transfer(address,address,uint256)"
+    /// --filter-set "address=0x.. topic0=0x.. topic0=0x.."
+    #[arg(
+        long,
+        value_name = "FILTERS",
+        help_heading = "Dataset-specific Options",
+        action = clap_cryo::ArgAction::Append,
+        verbatim_doc_comment
+    )]
+    pub filter_set: Option<Vec<String>>,
+
     /// Event signature for log decoding
     #[arg(long, value_name = "SIG", help_heading = "Dataset-specific Options", num_args(1..))]
     pub event_signature: Option<String>,
 
+    /// Contract abi json for decoded datasets
+    #[arg(long, value_name = "PATH", help_heading = "Dataset-specific Options")]
+    pub abi: Option<String>,
+
     /// Blocks per request (eth_getLogs)
     #[arg(
         long,
@@ -257,9 +692,34 @@ pub struct Args {
     )]
     pub inner_request_size: u64,
 
-    /// Event signature for log decoding
+    /// Javascript tracer code, or path to a file containing it
     #[arg(long, value_name = "tracer", help_heading = "Dataset-specific Options")]
     pub js_tracer: Option<String>,
+
+    /// Csv of selector,signature rows used for 4byte signature_text
+    #[arg(long, value_name = "PATH", help_heading = "Dataset-specific Options")]
+    pub sig_db: Option<String>,
+
+    /// Resolve 4byte selectors missing from --sig-db using openchain api,
+    /// results are cached in {output_dir}/.cryo/signatures.json
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub sig_api: bool,
+
+    /// Block at which static block rewards end [default: merge block of chain]
+    #[arg(long, value_name = "BLOCK", help_heading = "Dataset-specific Options")]
+    pub merge_block: Option<u64>,
+
+    /// Precompile addresses used by precompile_calls [default: precompiles of chain's fork]
+    #[arg(long, value_name = "ADDRESS", help_heading = "Dataset-specific Options", num_args(1..))]
+    pub precompiles: Option<Vec<String>>,
+
+    /// Time between snapshots of snapshot datasets, e.g. 500ms 5s 1m
+    #[arg(long, value_name = "DURATION", help_heading = "Dataset-specific Options")]
+    pub interval: Option<String>,
+
+    /// Number of snapshots taken by snapshot datasets
+    #[arg(long, value_name = "N", default_value_t = 1, help_heading = "Dataset-specific Options")]
+    pub snapshots: u64,
 }
 
 impl Args {
@@ -284,6 +744,44 @@ impl Args {
 
         serde_json::from_value(s1_value).expect("Failed to deserialize from JSON")
     }
+
+    /// arguments that differ from the defaults of the cli, keyed by field name
+    pub(crate) fn non_default_args(&self) -> Result<Map<String, Value>, ParseError> {
+        let defaults = Args::try_parse_from(["cryo"])
+            .map_err(|_| ParseError::ParseError("could not parse default args".to_string()))?;
+        match (serde_json::to_value(self), serde_json::to_value(defaults)) {
+            (Ok(Value::Object(args)), Ok(Value::Object(defaults))) => Ok(args
+                .into_iter()
+                .filter(|(key, value)| defaults.get(key) != Some(value))
+                .collect()),
+            _ => Err(ParseError::ParseError("could not serialize args".to_string())),
+        }
+    }
+}
+
+/// attach timestamp values to their flag, so that relative timestamps such as -7d: are not read
+/// as short flags
+pub(crate) fn join_relative_timestamps<I, T>(args: I) -> Vec<String>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<String>,
+{
+    let mut joined = Vec::new();
+    let mut in_timestamps = false;
+    for arg in args.into_iter().map(|arg| arg.into()) {
+        let is_relative = arg.len() > 1 &&
+            arg.starts_with('-') &&
+            arg[1..].starts_with(|c: char| c.is_ascii_digit());
+        if in_timestamps && (is_relative || !arg.starts_with('-')) {
+            joined.push(format!("--timestamps={}", arg));
+            continue
+        }
+        if arg.starts_with('-') {
+            in_timestamps = arg == "--timestamps";
+        }
+        joined.push(arg);
+    }
+    joined
 }
 
 pub(crate) fn get_styles() -> clap_cryo::builder::Styles {
@@ -318,7 +816,62 @@ fn get_after_str() -> String {
       <white><bold>cryo help</bold></white>"#
     );
     let post_subcommands = " <DATASET(S)>         display info about a dataset";
-    format!("{}{}{}", header, subcommands, post_subcommands)
+    let meta = cstr!(
+        r#"
+      <white><bold>cryo meta</bold></white>"#
+    );
+    let post_meta = " <FILE(S)>            display collection metadata of output files";
+    let schema = cstr!(
+        r#"
+      <white><bold>cryo schema</bold></white>"#
+    );
+    let post_schema = " <DATASET(S)>       display output schema of datasets, --json for json";
+    let compact = cstr!(
+        r#"
+      <white><bold>cryo compact</bold></white>"#
+    );
+    let post_compact = " <DIR>             merge small parquet files of a directory";
+    let verify = cstr!(
+        r#"
+      <white><bold>cryo verify</bold></white>"#
+    );
+    let post_verify = " <DIR>              check that files of a directory hold canonical blocks";
+    let verify_manifest = cstr!(
+        r#"
+      <white><bold>cryo verify-manifest</bold></white>"#
+    );
+    let post_verify_manifest =
+        " <DIR>     verify files against the checksum manifest of a directory";
+    let cache = cstr!(
+        r#"
+      <white><bold>cryo cache</bold></white>"#
+    );
+    let post_cache = " <stats|prune>       display or prune the response cache of --cache-dir";
+    let config = cstr!(
+        r#"
+      <white><bold>cryo config init</bold></white>"#
+    );
+    let post_config = " [PATH]        write a commented config template for --config";
+    format!(
+        "{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}",
+        header,
+        subcommands,
+        post_subcommands,
+        meta,
+        post_meta,
+        schema,
+        post_schema,
+        compact,
+        post_compact,
+        verify,
+        post_verify,
+        verify_manifest,
+        post_verify_manifest,
+        cache,
+        post_cache,
+        config,
+        post_config
+    )
 }
 
 fn get_datatype_help() -> &'static str {
@@ -0,0 +1,222 @@
+// labeled rpc endpoints collect the same datasets from several chains in one run
+// - endpoints are labeled as --rpc mainnet=https://... --rpc base=https://...
+// - block ranges are labeled the same way, as --blocks mainnet=18M:19M base=10M:11M
+// - unlabeled block ranges are used by chains without labeled block ranges
+// - each chain has its own endpoints and concurrency limits, and chains are collected concurrently
+// - files of all chains are written to --output-dir, named by label, or with --chain-subdirs to a
+//   subdirectory of each label
+// - without --chain-subdirs, a --network-name is suffixed with the label so that chains never share
+//   file names
+// - if any chain fails, the collection of the other chains is aborted
+
+use crate::{args::Args, follow, parse};
+use cryo_freeze::{err, CollectError, EventHandler, ExecutionEnv, FreezeSummary, ParseError};
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+    time::SystemTime,
+};
+
+/// args of each chain of the labeled --rpc endpoints of args, None if endpoints are unlabeled
+pub(crate) fn parse_chains(args: &Args) -> Result<Option<Vec<(String, Args)>>, ParseError> {
+    let rpcs = args.rpc.clone().unwrap_or_default();
+    let labeled: Vec<_> = rpcs.iter().filter_map(|rpc| split_label(rpc)).collect();
+    if labeled.is_empty() {
+        return Ok(None)
+    }
+    if labeled.len() < rpcs.len() {
+        return Err(ParseError::ParseError(
+            "either label every --rpc endpoint or none of them".to_string(),
+        ))
+    }
+    if args.follow {
+        return Err(ParseError::ParseError(
+            "--follow cannot be used with labeled --rpc endpoints".to_string(),
+        ))
+    }
+
+    let mut labels: Vec<&str> = Vec::new();
+    for (label, _) in labeled.iter() {
+        if !labels.contains(label) {
+            labels.push(label);
+        }
+    }
+    let blocks = labeled_values(&args.blocks, &labels, "--blocks")?;
+    let timestamps = labeled_values(&args.timestamps, &labels, "--timestamps")?;
+
+    let mut chains = Vec::new();
+    for label in labels.iter() {
+        let rpc = labeled
+            .iter()
+            .filter(|(rpc_label, _)| rpc_label == label)
+            .map(|(_, url)| url.to_string())
+            .collect();
+        let output_dir = match args.chain_subdirs {
+            true => format!("{}/{}", args.output_dir.trim_end_matches('/'), label),
+            false => args.output_dir.clone(),
+        };
+        let report_file = args.report_file.as_ref().and_then(|path| {
+            path.file_name()
+                .map(|name| path.with_file_name(format!("{}_{}", label, name.to_string_lossy())))
+        });
+        let chain = Args {
+            rpc: Some(rpc),
+            blocks: blocks.get(label).cloned().flatten(),
+            timestamps: timestamps.get(label).cloned().flatten(),
+            network_name: Some(chain_network_name(args, label)),
+            output_dir,
+            report_file,
+            ..args.clone()
+        };
+        chains.push((label.to_string(), chain));
+    }
+    Ok(Some(chains))
+}
+
+/// network name of chain, which names the files of the chain unless they have their own
+/// subdirectory
+fn chain_network_name(args: &Args, label: &str) -> String {
+    match (&args.network_name, args.chain_subdirs) {
+        (Some(network_name), true) => network_name.clone(),
+        (Some(network_name), false) => format!("{}_{}", network_name, label),
+        (None, _) => label.to_string(),
+    }
+}
+
+/// collect each chain concurrently, summarizing the chunks of every chain
+pub(crate) async fn freeze_chains(
+    chains: Vec<(String, Args)>,
+    t_start_parse: Option<SystemTime>,
+    events: Option<Arc<dyn EventHandler>>,
+    cancel: Option<Arc<AtomicBool>>,
+) -> Result<Option<FreezeSummary>, CollectError> {
+    // dropping the set when returning early aborts the chains that are still collecting
+    let mut tasks = tokio::task::JoinSet::new();
+    for (label, args) in chains.into_iter() {
+        let (query, source, sink, env) = parse::parse_args(&args)
+            .await
+            .map_err(|e| err(&format!("could not parse options of chain {}: {}", label, e)))?;
+        // progress bars of concurrent chains would overwrite each other
+        let (events, cancel) = (events.clone(), cancel.clone());
+        let env = ExecutionEnv { t_start_parse, bar: None, events, cancel, ..env }.set_start_time();
+        let source = Arc::new(source);
+        tasks
+            .spawn(async move { (label, cryo_freeze::freeze(&query, &source, &sink, &env).await) });
+    }
+
+    let mut summary: Option<FreezeSummary> = None;
+    while let Some(result) = tasks.join_next().await {
+        let (label, result) = result.map_err(|_| err("error joining collection of chain"))?;
+        let chain_summary =
+            result.map_err(|e| err(&format!("could not collect chain {}: {}", label, e)))?;
+        if let Some(chain_summary) = chain_summary {
+            follow::extend_summary(
+                summary.get_or_insert_with(Default::default),
+                Some(chain_summary),
+            );
+        }
+    }
+    Ok(summary)
+}
+
+/// label and url of a labeled endpoint, e.g. base=https://...
+fn split_label(value: &str) -> Option<(&str, &str)> {
+    let (label, value) = value.split_once('=')?;
+    let is_label = !label.is_empty() &&
+        label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    is_label.then_some((label, value))
+}
+
+/// values of each label, with unlabeled values used for labels without labeled values
+fn labeled_values<'a>(
+    values: &Option<Vec<String>>,
+    labels: &[&'a str],
+    flag: &str,
+) -> Result<HashMap<&'a str, Option<Vec<String>>>, ParseError> {
+    let values = match values {
+        Some(values) => values,
+        None => return Ok(labels.iter().map(|label| (*label, None)).collect()),
+    };
+    let mut unlabeled = Vec::new();
+    let mut labeled: HashMap<&str, Vec<String>> = Default::default();
+    for value in values.iter() {
+        match split_label(value) {
+            Some((label, value)) => match labels.iter().find(|known| **known == label) {
+                Some(label) => labeled.entry(label).or_default().push(value.to_string()),
+                None => {
+                    return Err(ParseError::ParseError(format!(
+                        "{} uses label {} that has no --rpc endpoint",
+                        flag, label
+                    )))
+                }
+            },
+            None => unlabeled.push(value.clone()),
+        }
+    }
+    Ok(labels
+        .iter()
+        .map(|label| {
+            let values = labeled.remove(label).unwrap_or_else(|| unlabeled.clone());
+            (*label, (!values.is_empty()).then_some(values))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap_cryo::Parser;
+
+    #[test]
+    fn test_parse_chains() {
+        let args = Args::parse_from(["cryo", "blocks", "--rpc", "http://localhost:8545?key=a=b"]);
+        assert!(parse_chains(&args).unwrap().is_none());
+
+        let args = Args::parse_from([
+            "cryo",
+            "blocks",
+            "--rpc",
+            "mainnet=https://a,base=https://b,base=https://c",
+            "--chain-subdirs",
+            "-o",
+            "data",
+            "-b",
+            "mainnet=18M:19M",
+            "0:10",
+        ]);
+        let chains = parse_chains(&args).unwrap().unwrap();
+        let (label, mainnet) = &chains[0];
+        assert_eq!(label, "mainnet");
+        assert_eq!(mainnet.rpc, Some(vec!["https://a".to_string()]));
+        assert_eq!(mainnet.blocks, Some(vec!["18M:19M".to_string()]));
+        assert_eq!(
+            (mainnet.output_dir.as_str(), mainnet.network_name.as_deref()),
+            ("data/mainnet", Some("mainnet"))
+        );
+        let (label, base) = &chains[1];
+        assert_eq!(label, "base");
+        assert_eq!(base.rpc, Some(vec!["https://b".to_string(), "https://c".to_string()]));
+        assert_eq!(base.blocks, Some(vec!["0:10".to_string()]));
+
+        // chains writing to the same directory have distinct file names
+        let args = Args::parse_from([
+            "cryo",
+            "blocks",
+            "--rpc",
+            "mainnet=https://a,base=https://b",
+            "--network-name",
+            "prod",
+        ]);
+        let chains = parse_chains(&args).unwrap().unwrap();
+        let names: Vec<_> = chains.iter().map(|(_, chain)| chain.network_name.clone()).collect();
+        assert_eq!(names, vec![Some("prod_mainnet".to_string()), Some("prod_base".to_string())]);
+        let args = Args { chain_subdirs: true, ..args };
+        let chains = parse_chains(&args).unwrap().unwrap();
+        assert_eq!(chains[0].1.network_name.as_deref(), Some("prod"));
+
+        let args = Args::parse_from(["cryo", "blocks", "--rpc", "mainnet=https://a,https://b"]);
+        assert!(parse_chains(&args).is_err());
+        let args = Args::parse_from(["cryo", "blocks", "--rpc", "a=https://a", "-b", "b=1:2"]);
+        assert!(parse_chains(&args).is_err());
+    }
+}
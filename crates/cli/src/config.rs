@@ -0,0 +1,194 @@
+// using --config <PATH> reads arguments from a toml file, so that long commands can be reused
+// - keys are the long names of cli options, e.g. chunk-size or chunk_size
+// - datasets are set with datatype, e.g. datatype = ["blocks", "transactions"]
+// - flags of the current command override values of the file
+// - --profile <NAME> applies the [profile.NAME] table of the file over its top-level values
+// - `cryo config init` writes a commented template
+
+use crate::args::Args;
+use clap_cryo::Parser;
+use cryo_freeze::ParseError;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_CONFIG_PATH: &str = "cryo.toml";
+
+const CONFIG_TEMPLATE: &str = r#"# cryo config, used with `cryo --config cryo.toml`
+#
+# keys are the long names of cli options, with - or _ between words
+# flags of a command override the values of this file
+
+# datasets to collect
+# datatype = ["blocks", "transactions"]
+
+# blocks to collect, see `cryo help syntax`
+# blocks = ["18M:18.1M"]
+
+# rpc endpoint, or several endpoints to spread requests over
+# rpc = "https://eth.llamarpc.com"
+# requests-per-second = 50
+# max-concurrent-requests = 20
+
+# schema options
+# include-columns = ["all"]
+# exclude-columns = []
+# u256-types = ["binary", "string", "f64"]
+# hex = false
+
+# output options
+# output-dir = "data"
+# chunk-size = 1000
+# csv = false
+# json = false
+
+# profiles are selected with --profile, their values override the values above
+#
+# [profile.traces-mainnet]
+# datatype = ["traces"]
+# blocks = ["17M:18M"]
+# output-dir = "data/traces"
+"#;
+
+/// args set by the config file of args, overridden by every flag set in args
+pub(crate) fn apply_config(args: Args) -> Result<Args, ParseError> {
+    let path = match (&args.config, &args.profile) {
+        (Some(path), _) => path.clone(),
+        (None, Some(_)) => {
+            return Err(ParseError::ParseError("--profile requires --config".to_string()))
+        }
+        (None, None) => return Ok(args),
+    };
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|_| ParseError::ParseError(format!("could not read config {}", path.display())))?;
+    let config: toml::Table = toml::from_str(&contents).map_err(|e| {
+        ParseError::ParseError(format!("could not parse config {}: {}", path.display(), e))
+    })?;
+
+    let defaults = Args::try_parse_from(["cryo"])
+        .map_err(|_| ParseError::ParseError("could not parse default args".to_string()))?;
+    let mut merged = match serde_json::to_value(defaults) {
+        Ok(Value::Object(merged)) => merged,
+        _ => return Err(ParseError::ParseError("could not serialize args".to_string())),
+    };
+    for (key, value) in config_values(config, args.profile.as_deref())? {
+        let key = key.replace('-', "_");
+        if !merged.contains_key(&key) {
+            return Err(ParseError::ParseError(format!("unknown option in config: {}", key)))
+        }
+        let value = serde_json::to_value(value)
+            .map_err(|_| ParseError::ParseError(format!("invalid value of {} in config", key)))?;
+        let value = match (is_valid(&merged, &key, &value), value) {
+            (true, value) => value,
+            // single values are accepted for options that take several values
+            (false, value) if is_valid(&merged, &key, &Value::Array(vec![value.clone()])) => {
+                Value::Array(vec![value])
+            }
+            (false, _) => {
+                return Err(ParseError::ParseError(format!("invalid value of {} in config", key)))
+            }
+        };
+        merged.insert(key, value);
+    }
+    merged.extend(args.non_default_args()?);
+    serde_json::from_value(Value::Object(merged))
+        .map_err(|_| ParseError::ParseError("could not merge args with config".to_string()))
+}
+
+/// top-level values of config, followed by the values of profile
+fn config_values(
+    mut config: toml::Table,
+    profile: Option<&str>,
+) -> Result<Vec<(String, toml::Value)>, ParseError> {
+    let profiles = config.remove("profile");
+    let mut values: Vec<_> = config.into_iter().collect();
+    if let Some(profile) = profile {
+        match profiles.as_ref().and_then(|profiles| profiles.get(profile)) {
+            Some(toml::Value::Table(table)) => values.extend(table.clone()),
+            _ => {
+                return Err(ParseError::ParseError(format!("profile {} is not in config", profile)))
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// whether args can hold value as the value of key
+fn is_valid(args: &serde_json::Map<String, Value>, key: &str, value: &Value) -> bool {
+    let mut args = args.clone();
+    args.insert(key.to_string(), value.clone());
+    serde_json::from_value::<Args>(Value::Object(args)).is_ok()
+}
+
+/// write a commented config template to the path of `cryo config init [PATH]`
+pub(crate) fn init_config(args: &Args) -> Result<PathBuf, ParseError> {
+    let path = match &args.datatype[1..] {
+        [action] if action == "init" => PathBuf::from(DEFAULT_CONFIG_PATH),
+        [action, path] if action == "init" => PathBuf::from(path),
+        _ => return Err(ParseError::ParseError("usage: cryo config init [PATH]".to_string())),
+    };
+    if Path::new(&path).exists() {
+        return Err(ParseError::ParseError(format!("{} already exists", path.display())))
+    }
+    std::fs::write(&path, CONFIG_TEMPLATE)
+        .map_err(|_| ParseError::ParseError(format!("could not write {}", path.display())))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_config() {
+        let dir = std::env::temp_dir().join(format!("cryo_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cryo.toml");
+        let config = r#"
+            datatype = ["blocks"]
+            blocks = "0:100"
+            chunk-size = 10
+            hex = true
+
+            [profile.traces]
+            datatype = ["traces"]
+            max_concurrent_chunks = 2
+        "#;
+        std::fs::write(&path, config).unwrap();
+        let config = path.to_str().unwrap();
+
+        let args = apply_config(Args::parse_from(["cryo", "--config", config])).unwrap();
+        assert_eq!(args.datatype, vec!["blocks".to_string()]);
+        assert_eq!(args.blocks, Some(vec!["0:100".to_string()]));
+        assert_eq!((args.chunk_size, args.hex, args.max_concurrent_chunks), (10, true, None));
+
+        let args = Args::parse_from(["cryo", "--config", config, "--profile", "traces", "-c", "5"]);
+        let args = apply_config(args).unwrap();
+        assert_eq!(args.datatype, vec!["traces".to_string()]);
+        assert_eq!((args.chunk_size, args.max_concurrent_chunks), (5, Some(2)));
+
+        let args = Args::parse_from(["cryo", "--config", config, "--profile", "missing"]);
+        assert!(apply_config(args).is_err());
+        std::fs::write(&path, "chunk-size = \"big\"").unwrap();
+        assert!(apply_config(Args::parse_from(["cryo", "--config", config])).is_err());
+        std::fs::write(&path, "not-an-option = 1").unwrap();
+        assert!(apply_config(Args::parse_from(["cryo", "--config", config])).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_template() {
+        let config: toml::Table = toml::from_str(CONFIG_TEMPLATE).unwrap();
+        assert!(config.is_empty());
+        let uncommented: String = CONFIG_TEMPLATE
+            .lines()
+            .take_while(|line| !line.starts_with("# profiles"))
+            .filter(|line| line.starts_with("# ") && line.contains(" = "))
+            .map(|line| format!("{}\n", &line[2..]))
+            .collect();
+        let config: toml::Table = toml::from_str(&uncommented).unwrap();
+        for (key, _) in config_values(config, None).unwrap() {
+            let args = serde_json::to_value(Args::default()).unwrap();
+            assert!(args.get(key.replace('-', "_")).is_some(), "{}", key);
+        }
+    }
+}
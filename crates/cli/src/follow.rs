@@ -0,0 +1,445 @@
+use crate::{args::Args, parse};
+use cryo_freeze::{
+    err, CollectError, Dim, EventHandler, ExecutionEnv, Fetcher, FileOutput, FreezeSummary,
+    Partition, Source,
+};
+use ethers::prelude::*;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// number of written chunks whose last block hash is checked for reorgs
+const MAX_TRACKED_CHUNKS: usize = 256;
+
+/// granularity at which sleeping between polls checks for interrupts
+const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// consecutive failed attempts to collect new blocks before following stops
+const MAX_CHUNK_ATTEMPTS: usize = 5;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// chunk written in follow mode, with the hash of its last block at the time of collection
+#[derive(Clone, Copy, Debug)]
+struct CommittedChunk {
+    start: u64,
+    end: u64,
+    hash: H256,
+}
+
+/// collect historical blocks up to the chain tip, then keep collecting blocks as they are mined,
+/// until interrupted or cancelled
+pub(crate) async fn follow(
+    args: Args,
+    t_start_parse: Option<SystemTime>,
+    events: Option<Arc<dyn EventHandler>>,
+    cancel: Option<Arc<AtomicBool>>,
+) -> Result<Option<FreezeSummary>, CollectError> {
+    // blocks within the reorg buffer are held back until they are old enough, instead of being
+    // dropped along with the rest of their chunk
+    let parse_args = Args { reorg_buffer: 0, ..args.clone() };
+    let (query, source, sink, env) = parse::parse_args(&parse_args).await?;
+    let env = ExecutionEnv { events, cancel, ..env };
+    if !query.partitioned_by.contains(&Dim::BlockNumber) {
+        return Err(err("--follow requires datasets collected by block"))
+    }
+    if sink.duckdb.is_some() ||
+        sink.postgres.is_some() ||
+        sink.stdout ||
+        sink.object_store.is_some()
+    {
+        return Err(err("--follow requires local output files"))
+    }
+    let source = Arc::new(source);
+    let fetcher = source.fetcher.clone();
+    let mut summary = FreezeSummary::default();
+
+    // collect historical blocks, newest first alongside the chain tip if reversed
+    let safe_tip = get_safe_tip(&fetcher, args.reorg_buffer).await?;
+    let kept: Vec<Partition> = query
+        .partitions
+        .iter()
+        .filter_map(|partition| parse::trim_partition(partition.clone(), 0, safe_tip))
+        .collect();
+    let next_block = match first_pending_block(&query.partitions, &kept) {
+        Some(next_block) => next_block,
+        None => return Err(err("--follow requires blocks to start from")),
+    };
+    let last_chunk = parse::partition_ranges(&kept).into_iter().max_by_key(|(_, end)| *end);
+    let historical = {
+        let query = cryo_freeze::Query { partitions: kept, ..query };
+        let env = ExecutionEnv { t_start_parse, ..env.clone() }.set_start_time();
+        let (source, sink) = (source.clone(), sink.clone());
+        async move {
+            match query.partitions.is_empty() {
+                true => Ok(None),
+                false => cryo_freeze::freeze(&query, &source, &sink, &env).await,
+            }
+        }
+    };
+    let mut committed = VecDeque::new();
+    let concurrent = parse::parse_chunk_order(&args)? == "reverse" && !env.dry;
+    let historical = match concurrent {
+        true => {
+            // the last chunk is checked for reorgs against its hash before collection
+            if let Some((start, end)) = last_chunk {
+                if let Some(hash) = get_block_hash(&fetcher, end).await? {
+                    committed.push_back(CommittedChunk { start, end, hash });
+                }
+            }
+            Some(tokio::spawn(historical))
+        }
+        false => {
+            extend_summary(&mut summary, historical.await?);
+            if let Some((start, end)) = last_chunk {
+                if let Some(hash) = get_block_hash(&fetcher, end).await? {
+                    committed.push_back(CommittedChunk { start, end, hash });
+                }
+            }
+            None
+        }
+    };
+    if env.dry {
+        return Ok(Some(summary))
+    }
+
+    // collect new blocks, metrics are served by the concurrent historical collection
+    install_interrupt_handler();
+    let tip_env = match historical {
+        Some(_) => ExecutionEnv { metrics_addr: None, ..env.clone() },
+        None => env.clone(),
+    };
+    let followed =
+        follow_tip(&args, &source, &sink, &tip_env, next_block, committed, &mut summary).await;
+    followed?;
+
+    // an interrupted backfill is resumed by running the same command again
+    if let Some(historical) = historical {
+        if !historical.is_finished() {
+            print_message(&env, "stopping historical collection, run again to resume".to_string());
+            historical.abort();
+        }
+        if let Ok(historical) = historical.await {
+            extend_summary(&mut summary, historical?);
+        }
+    }
+    Ok(Some(summary))
+}
+
+/// collect blocks as they are mined until interrupted
+async fn follow_tip(
+    args: &Args,
+    source: &Arc<Source>,
+    sink: &FileOutput,
+    env: &ExecutionEnv,
+    mut next_block: u64,
+    mut committed: VecDeque<CommittedChunk>,
+    summary: &mut FreezeSummary,
+) -> Result<(), CollectError> {
+    let fetcher = source.fetcher.clone();
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    let flush_interval = args.flush_interval.map(Duration::from_secs);
+    let mut last_write = Instant::now();
+    let mut n_failures = 0;
+    let mut new_heads = fetcher.provider.as_ref().subscribe_new_heads();
+    if env.verbose >= 1 {
+        let message =
+            format!("following chain tip from block {}, press ctrl-c to stop", next_block);
+        print_message(env, message);
+    }
+    loop {
+        // cancelled chunks cannot be flushed, so cancelling stops without collecting pending blocks
+        if env.is_cancelled() {
+            return Ok(())
+        }
+        let interrupted = INTERRUPTED.load(Ordering::SeqCst);
+        let safe_tip = get_safe_tip(&fetcher, args.reorg_buffer).await?;
+
+        // re-collect chunks whose blocks were replaced by a reorg
+        let reorged = reorged_chunks(&mut committed, &fetcher).await?;
+        for chunk in reorged.into_iter().rev() {
+            if env.verbose >= 1 {
+                let message = format!(
+                    "reorg detected, collecting blocks {} to {} again",
+                    chunk.start, chunk.end
+                );
+                print_message(env, message);
+            }
+            let recollected =
+                collect_chunk(args, chunk.start, chunk.end, true, source, sink, env).await?;
+            extend_summary(summary, recollected.1);
+            match recollected.0 {
+                Some(chunk) => track_chunk(&mut committed, chunk),
+                // errors of reorged chunks are reported by the summary of the run
+                None => {
+                    let message = format!(
+                        "could not collect blocks {} to {} again after reorg, stopping",
+                        chunk.start, chunk.end
+                    );
+                    print_message(env, message);
+                    return Ok(())
+                }
+            }
+        }
+
+        let flush =
+            interrupted || flush_interval.map(|t| last_write.elapsed() >= t).unwrap_or(false);
+        match next_range(next_block, safe_tip, args.chunk_size, flush) {
+            Some((start, end)) => {
+                match collect_chunk(args, start, end, false, source, sink, env).await? {
+                    (Some(chunk), chunk_summary) => {
+                        if env.verbose >= 1 {
+                            print_message(env, format!("collected blocks {} to {}", start, end));
+                        }
+                        extend_summary(summary, chunk_summary);
+                        track_chunk(&mut committed, chunk);
+                        next_block = end + 1;
+                        last_write = Instant::now();
+                        n_failures = 0;
+                    }
+                    // errored chunks are retried at the next poll, until the errors of the last
+                    // attempt are reported by the summary of the run
+                    (None, chunk_summary) => {
+                        n_failures += 1;
+                        if n_failures >= MAX_CHUNK_ATTEMPTS {
+                            extend_summary(summary, chunk_summary);
+                            let message = format!(
+                                "could not collect blocks {} to {} after {} attempts, stopping",
+                                start, end, n_failures
+                            );
+                            print_message(env, message);
+                            return Ok(())
+                        }
+                        if env.verbose >= 1 {
+                            let message =
+                                format!("could not collect blocks {} to {}, retrying", start, end);
+                            print_message(env, message);
+                        }
+                        sleep_unless_interrupted(poll_interval, env).await;
+                    }
+                }
+            }
+            None if interrupted => return Ok(()),
+            None => wait_for_new_head(&mut new_heads, poll_interval, env).await,
+        }
+    }
+}
+
+/// print above the progress bar of a concurrent historical collection, if any
+fn print_message(env: &ExecutionEnv, message: String) {
+    match &env.bar {
+        Some(bar) if !bar.is_finished() && !bar.is_hidden() => bar.println(message),
+        _ => println!("{}", message),
+    }
+}
+
+/// collect a block range, returning the chunk to track if every partition succeeded
+async fn collect_chunk(
+    args: &Args,
+    start: u64,
+    end: u64,
+    overwrite: bool,
+    source: &Arc<Source>,
+    sink: &FileOutput,
+    env: &ExecutionEnv,
+) -> Result<(Option<CommittedChunk>, Option<FreezeSummary>), CollectError> {
+    let hash = get_block_hash(&source.fetcher, end).await?;
+    let chunk_args = Args {
+        blocks: Some(vec![format!("{}:{}", start, end + 1)]),
+        timestamps: None,
+        reorg_buffer: 0,
+        align: false,
+        n_chunks: None,
+        append: false,
+        ..args.clone()
+    };
+    let query = parse::parse_query(&chunk_args, source.fetcher.clone()).await?;
+    let sink = FileOutput { overwrite: overwrite || sink.overwrite, ..sink.clone() };
+    let env = ExecutionEnv { verbose: 0, bar: None, ..env.clone() }.set_start_time();
+    let chunk_summary = cryo_freeze::freeze(&query, source, &sink, &env).await?;
+    let succeeded = chunk_summary
+        .as_ref()
+        .map(|s| s.errored.is_empty() && s.unsupported.is_empty() && s.cancelled.is_empty())
+        .unwrap_or(true);
+    match (succeeded, hash) {
+        (true, Some(hash)) => Ok((Some(CommittedChunk { start, end, hash }), chunk_summary)),
+        _ => Ok((None, chunk_summary)),
+    }
+}
+
+/// tracked chunks whose last block is no longer canonical, newest first
+async fn reorged_chunks<P: JsonRpcClient>(
+    committed: &mut VecDeque<CommittedChunk>,
+    fetcher: &Fetcher<P>,
+) -> Result<Vec<CommittedChunk>, CollectError> {
+    let mut reorged = Vec::new();
+    while let Some(chunk) = committed.back().copied() {
+        if get_block_hash(fetcher, chunk.end).await? == Some(chunk.hash) {
+            return Ok(reorged)
+        }
+        committed.pop_back();
+        reorged.push(chunk);
+    }
+    match reorged.last() {
+        Some(oldest) => Err(err(&format!(
+            "reorg extends before the tracked chunks, collect blocks before {} again with \
+             --overwrite",
+            oldest.start
+        ))),
+        None => Ok(reorged),
+    }
+}
+
+fn track_chunk(committed: &mut VecDeque<CommittedChunk>, chunk: CommittedChunk) {
+    committed.push_back(chunk);
+    if committed.len() > MAX_TRACKED_CHUNKS {
+        committed.pop_front();
+    }
+}
+
+/// first block not covered by the kept partitions, or the first requested block if none are kept
+fn first_pending_block(requested: &[Partition], kept: &[Partition]) -> Option<u64> {
+    match parse::partition_ranges(kept).into_iter().map(|(_, end)| end).max() {
+        Some(end) => Some(end + 1),
+        None => parse::partition_ranges(requested).into_iter().map(|(start, _)| start).min(),
+    }
+}
+
+/// next block range to collect, partial ranges are only collected when flushing
+fn next_range(next_block: u64, safe_tip: u64, chunk_size: u64, flush: bool) -> Option<(u64, u64)> {
+    if safe_tip < next_block {
+        return None
+    }
+    let n_available = safe_tip - next_block + 1;
+    if n_available >= chunk_size {
+        Some((next_block, next_block + chunk_size - 1))
+    } else if flush {
+        Some((next_block, safe_tip))
+    } else {
+        None
+    }
+}
+
+async fn get_safe_tip<P: JsonRpcClient>(
+    fetcher: &Fetcher<P>,
+    reorg_buffer: u64,
+) -> Result<u64, CollectError> {
+    let latest_block = fetcher.get_block_number().await?.as_u64();
+    Ok(latest_block.saturating_sub(reorg_buffer))
+}
+
+async fn get_block_hash<P: JsonRpcClient>(
+    fetcher: &Fetcher<P>,
+    block_number: u64,
+) -> Result<Option<H256>, CollectError> {
+    Ok(fetcher.get_block(block_number).await?.and_then(|block| block.hash))
+}
+
+async fn sleep_unless_interrupted(duration: Duration, env: &ExecutionEnv) {
+    let t_start = Instant::now();
+    while t_start.elapsed() < duration && !INTERRUPTED.load(Ordering::SeqCst) && !env.is_cancelled()
+    {
+        tokio::time::sleep(INTERRUPT_CHECK_INTERVAL.min(duration)).await;
+    }
+}
+
+/// wait for the next block of a newHeads subscription, polling again after the poll interval
+/// if no block arrives or if there is no subscription
+async fn wait_for_new_head(
+    new_heads: &mut Option<UnboundedReceiver<u64>>,
+    poll_interval: Duration,
+    env: &ExecutionEnv,
+) {
+    let closed = match new_heads.as_mut() {
+        Some(receiver) => tokio::select! {
+            head = receiver.recv() => {
+                while receiver.try_recv().is_ok() {}
+                head.is_none()
+            }
+            _ = sleep_unless_interrupted(poll_interval, env) => false,
+        },
+        None => {
+            sleep_unless_interrupted(poll_interval, env).await;
+            false
+        }
+    };
+    // subscriptions that end fall back to polling
+    if closed {
+        *new_heads = None;
+    }
+}
+
+/// flush the pending blocks on the first interrupt instead of exiting
+fn install_interrupt_handler() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            // a second interrupt exits without flushing
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(130)
+            }
+        }
+    });
+}
+
+pub(crate) fn extend_summary(summary: &mut FreezeSummary, other: Option<FreezeSummary>) {
+    if let Some(other) = other {
+        summary.completed.extend(other.completed);
+        summary.skipped.extend(other.skipped);
+        summary.errored.extend(other.errored);
+        summary.cancelled.extend(other.cancelled);
+        for unsupported in other.unsupported {
+            match summary.unsupported.iter_mut().find(|u| u.dataset == unsupported.dataset) {
+                Some(existing) => existing.partitions.extend(unsupported.partitions),
+                None => summary.unsupported.push(unsupported),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryo_freeze::BlockChunk;
+
+    fn partition(chunk: BlockChunk) -> Partition {
+        Partition { block_numbers: Some(vec![chunk]), ..Default::default() }
+    }
+
+    #[test]
+    fn test_first_pending_block() {
+        let partitions = vec![
+            partition(BlockChunk::Range(0, 999)),
+            partition(BlockChunk::Range(1000, 1999)),
+            partition(BlockChunk::Range(2000, 2999)),
+        ];
+        let hold_back = |partitions: &[Partition], safe_tip| -> Vec<Partition> {
+            partitions
+                .iter()
+                .filter_map(|p| parse::trim_partition(p.clone(), 0, safe_tip))
+                .collect()
+        };
+        let kept = hold_back(&partitions, 1500);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(first_pending_block(&partitions, &kept), Some(1501));
+        let kept = hold_back(&partitions, 0);
+        assert_eq!(first_pending_block(&partitions, &kept), Some(1));
+        let partitions = vec![partition(BlockChunk::Range(2000, 2999))];
+        let kept = hold_back(&partitions, 1500);
+        assert!(kept.is_empty());
+        assert_eq!(first_pending_block(&partitions, &kept), Some(2000));
+    }
+
+    #[test]
+    fn test_next_range() {
+        assert_eq!(next_range(1000, 2500, 1000, false), Some((1000, 1999)));
+        assert_eq!(next_range(2000, 2500, 1000, false), None);
+        assert_eq!(next_range(2000, 2500, 1000, true), Some((2000, 2500)));
+        assert_eq!(next_range(2501, 2500, 1000, true), None);
+    }
+}
@@ -8,8 +8,12 @@
 ))]
 
 mod args;
+mod chains;
+mod config;
+mod follow;
 mod parse;
 mod remember;
+mod resume;
 mod run;
 
 // used in main.rs but not lib.rs
@@ -18,4 +22,4 @@ use tokio as _;
 
 pub use args::Args;
 pub use parse::{parse_args, parse_query, parse_str};
-pub use run::run;
+pub use run::{run, run_with_hooks};
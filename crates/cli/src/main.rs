@@ -3,8 +3,12 @@
 use clap_cryo::Parser;
 
 mod args;
+mod chains;
+mod config;
+mod follow;
 mod parse;
 mod remember;
+mod resume;
 mod run;
 
 pub use args::Args;
@@ -14,9 +18,19 @@ use eyre::Result;
 #[allow(unreachable_code)]
 #[allow(clippy::needless_return)]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = Args::parse_from(args::join_relative_timestamps(std::env::args()));
     match run::run(args).await {
-        Ok(Some(freeze_summary)) if freeze_summary.errored.is_empty() => Ok(()),
+        Ok(Some(freeze_summary))
+            if freeze_summary.errored.is_empty() && freeze_summary.unsupported.is_empty() =>
+        {
+            Ok(())
+        }
+        // some chunks failed, while others were collected or already existed
+        Ok(Some(freeze_summary))
+            if !freeze_summary.completed.is_empty() || !freeze_summary.skipped.is_empty() =>
+        {
+            std::process::exit(2)
+        }
         Ok(Some(_freeze_summary)) => std::process::exit(1),
         Ok(None) => Ok(()),
         Err(e) => {
@@ -0,0 +1,157 @@
+use crate::args::Args;
+use cryo_freeze::{BlockChunk, CoveredFile, Dim, FileOutput, ParseError, Partition, Query};
+
+/// number of conflicting files listed by overlap errors
+const MAX_LISTED_CONFLICTS: usize = 10;
+
+/// extend existing datasets, starting open-ended block ranges after the last collected block
+pub(crate) fn apply_append(
+    args: &Args,
+    mut query: Query,
+    sink: &FileOutput,
+) -> Result<Query, ParseError> {
+    if !query.partitioned_by.contains(&Dim::BlockNumber) {
+        return Err(ParseError::ParseError(
+            "--append requires datasets collected by block".to_string(),
+        ))
+    }
+    if sink.duckdb.is_some() ||
+        sink.postgres.is_some() ||
+        sink.stdout ||
+        sink.object_store.is_some()
+    {
+        return Err(ParseError::ParseError("--append requires local output files".to_string()))
+    }
+
+    let mut covered = Vec::new();
+    let mut last_blocks = Vec::new();
+    for datatype in query.datatypes.iter().flat_map(|datatype| datatype.datatypes()) {
+        let files = cryo_freeze::covered_files(sink, &datatype)
+            .map_err(|e| ParseError::ParseError(e.to_string()))?;
+        last_blocks.push(files.iter().map(|file| file.end).max());
+        covered.extend(files);
+    }
+
+    // ranges without a start block continue after the last block shared by every dataset
+    let open_ended = match &args.blocks {
+        Some(blocks) => blocks.iter().all(|block| block.starts_with(':')),
+        None => false,
+    };
+    let last_block = last_blocks
+        .into_iter()
+        .collect::<Option<Vec<u64>>>()
+        .and_then(|last| last.into_iter().min());
+    if let (true, Some(last_block)) = (open_ended, last_block) {
+        query.partitions = query
+            .partitions
+            .into_iter()
+            .filter_map(|partition| trim_partition(partition, last_block + 1, u64::MAX))
+            .collect();
+    }
+
+    if !args.overwrite {
+        let conflicts = overlapping_files(&query.partitions, &covered);
+        if !conflicts.is_empty() {
+            return Err(ParseError::ParseError(conflict_message(&conflicts)))
+        }
+    }
+    Ok(query)
+}
+
+/// remove blocks before start or after end from partition, None if no blocks remain
+pub(crate) fn trim_partition(mut partition: Partition, start: u64, end: u64) -> Option<Partition> {
+    let chunks: Vec<BlockChunk> = partition
+        .block_numbers?
+        .into_iter()
+        .filter_map(|chunk| match chunk {
+            BlockChunk::Range(chunk_start, chunk_end) if chunk_end < start || chunk_start > end => {
+                None
+            }
+            BlockChunk::Range(chunk_start, chunk_end) => {
+                Some(BlockChunk::Range(chunk_start.max(start), chunk_end.min(end)))
+            }
+            BlockChunk::Numbers(numbers) => {
+                let numbers: Vec<u64> =
+                    numbers.into_iter().filter(|n| *n >= start && *n <= end).collect();
+                (!numbers.is_empty()).then_some(BlockChunk::Numbers(numbers))
+            }
+        })
+        .collect();
+    if chunks.is_empty() {
+        return None
+    }
+    partition.block_numbers = Some(chunks);
+    Some(partition)
+}
+
+/// first and last block of each partition
+pub(crate) fn partition_ranges(partitions: &[Partition]) -> Vec<(u64, u64)> {
+    partitions
+        .iter()
+        .filter_map(|partition| partition.stats().block_numbers)
+        .filter_map(|stats| stats.min_value.zip(stats.max_value))
+        .collect()
+}
+
+/// existing files whose block ranges intersect the blocks of the partitions
+fn overlapping_files<'a>(
+    partitions: &[Partition],
+    covered: &'a [CoveredFile],
+) -> Vec<&'a CoveredFile> {
+    let ranges = partition_ranges(partitions);
+    covered
+        .iter()
+        .filter(|file| ranges.iter().any(|(start, end)| file.start <= *end && file.end >= *start))
+        .collect()
+}
+
+fn conflict_message(conflicts: &[&CoveredFile]) -> String {
+    let mut message =
+        "requested blocks overlap existing files, use --overwrite to collect them again:"
+            .to_string();
+    for file in conflicts.iter().take(MAX_LISTED_CONFLICTS) {
+        message.push_str(&format!("\n    {}", file.path.display()));
+    }
+    if conflicts.len() > MAX_LISTED_CONFLICTS {
+        message.push_str(&format!("\n    ...and {} more", conflicts.len() - MAX_LISTED_CONFLICTS));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn partition(chunk: BlockChunk) -> Partition {
+        Partition { block_numbers: Some(vec![chunk]), ..Default::default() }
+    }
+
+    #[test]
+    fn test_trim_partition() {
+        let trim = |chunk, start| trim_partition(partition(chunk), start, u64::MAX);
+        let trimmed = trim(BlockChunk::Range(1000, 1999), 1500).unwrap();
+        assert!(matches!(trimmed.block_numbers.as_deref(), Some([BlockChunk::Range(1500, 1999)])));
+        assert!(trim(BlockChunk::Range(0, 999), 1500).is_none());
+        let trimmed = trim(BlockChunk::Numbers(vec![1, 5, 9]), 5).unwrap();
+        assert!(
+            matches!(trimmed.block_numbers.as_deref(), Some([BlockChunk::Numbers(n)]) if n == &[5, 9])
+        );
+        let trimmed = trim_partition(partition(BlockChunk::Range(1000, 1999)), 0, 1500).unwrap();
+        assert!(matches!(trimmed.block_numbers.as_deref(), Some([BlockChunk::Range(1000, 1500)])));
+        assert!(trim_partition(partition(BlockChunk::Range(2000, 2999)), 0, 1500).is_none());
+    }
+
+    #[test]
+    fn test_overlapping_files() {
+        let covered = vec![
+            CoveredFile { path: PathBuf::from("a"), start: 0, end: 999 },
+            CoveredFile { path: PathBuf::from("b"), start: 1000, end: 1999 },
+        ];
+        let partitions = vec![partition(BlockChunk::Range(1500, 2499))];
+        let conflicts = overlapping_files(&partitions, &covered);
+        assert_eq!(conflicts, vec![&covered[1]]);
+        let partitions = vec![partition(BlockChunk::Range(2000, 2999))];
+        assert!(overlapping_files(&partitions, &covered).is_empty());
+    }
+}
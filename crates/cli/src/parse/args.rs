@@ -1,25 +1,65 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use cryo_freeze::{ExecutionEnv, FileOutput, ParseError, Query, Source};
+use cryo_freeze::{CompactTarget, ExecutionEnv, FileOutput, ParseError, Query, Source};
 
 use crate::args::Args;
 use clap_cryo::Parser;
 
-use super::{execution, file_output, query, source};
+use super::{append, execution, file_output, query, source};
 
 /// parse options for running freeze
 pub async fn parse_args(
     args: &Args,
 ) -> Result<(Query, Source, FileOutput, ExecutionEnv), ParseError> {
     let source = source::parse_source(args).await?;
-    let query = query::parse_query(args, Arc::clone(&source.fetcher)).await?;
+    let mut query = query::parse_query(args, Arc::clone(&source.fetcher)).await?;
+    super::schemas::parse_chain_name(args, &mut query.schemas, source.chain_id);
     let sink = file_output::parse_file_output(args, &source)?;
+    let query = match args.append {
+        true => append::apply_append(args, query, &sink)?,
+        false => query,
+    };
     let env = execution::parse_execution_env(args, query.n_tasks() as u64)?;
     Ok((query, source, sink, env))
 }
 
+/// parse options for compacting the output files of a directory
+pub(crate) fn parse_compact_args(
+    args: &Args,
+) -> Result<(PathBuf, FileOutput, CompactTarget), ParseError> {
+    let dir = match &args.datatype[1..] {
+        [dir] => PathBuf::from(dir),
+        _ => return Err(ParseError::ParseError("usage: cryo compact <DIR>".to_string())),
+    };
+    let sink = file_output::parse_compact_output(args, &dir)?;
+    let target = file_output::parse_compact_target(args)?;
+    Ok((dir, sink, target))
+}
+
+/// parse options for checking the files of a directory against the canonical chain
+pub(crate) async fn parse_verify_args(args: &Args) -> Result<(PathBuf, Source), ParseError> {
+    let dir = match &args.datatype[1..] {
+        [dir] => PathBuf::from(dir),
+        _ => return Err(ParseError::ParseError("usage: cryo verify <DIR>".to_string())),
+    };
+    let source = source::parse_source(args).await?;
+    Ok((dir, source))
+}
+
+/// parse action, directory, and size cap of the response cache
+pub(crate) fn parse_cache_args(args: &Args) -> Result<(String, PathBuf, u64), ParseError> {
+    let usage =
+        || ParseError::ParseError("usage: cryo cache <stats|prune> --cache-dir <PATH>".to_string());
+    let action = match &args.datatype[1..] {
+        [action] if action == "stats" || action == "prune" => action.clone(),
+        _ => return Err(usage()),
+    };
+    let dir = args.cache_dir.as_ref().map(PathBuf::from).ok_or_else(usage)?;
+    Ok((action, dir, source::parse_cache_size(args)?))
+}
+
 /// parse command string
 #[allow(dead_code)]
 pub async fn parse_str(command: &str) -> Result<Args, ParseError> {
-    Ok(Args::parse_from(command.split_whitespace()))
+    Ok(Args::parse_from(crate::args::join_relative_timestamps(command.split_whitespace())))
 }
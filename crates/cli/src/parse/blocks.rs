@@ -21,7 +21,7 @@ pub(crate) async fn parse_blocks<P: JsonRpcClient>(
         for path in files {
             let column = if path.contains(':') {
                 path.split(':')
-                    .last()
+                    .next_back()
                     .ok_or(ParseError::ParseError("could not parse txs path column".to_string()))?
             } else {
                 "block_number"
@@ -32,7 +32,8 @@ pub(crate) async fn parse_blocks<P: JsonRpcClient>(
             let chunk_label = path
                 .split("__")
                 .last()
-                .and_then(|s| s.strip_suffix(".parquet").map(|s| s.to_string()));
+                .and_then(|s| s.strip_suffix(".parquet").or(s.strip_suffix(".arrow")))
+                .map(|s| s.to_string());
             file_labels.push(chunk_label);
             file_chunks.push(chunk);
         }
@@ -69,13 +70,7 @@ pub(crate) async fn parse_blocks<P: JsonRpcClient>(
 }
 
 fn read_integer_column(path: &str, column: &str) -> Result<Vec<u64>, ParseError> {
-    let file = std::fs::File::open(path)
-        .map_err(|_e| ParseError::ParseError("could not open file path".to_string()))?;
-
-    let df = ParquetReader::new(file)
-        .with_columns(Some(vec![column.to_string()]))
-        .finish()
-        .map_err(|_e| ParseError::ParseError("could not read data from column".to_string()))?;
+    let df = cryo_freeze::read_column(path, column)?;
 
     let series = df
         .column(column)
@@ -409,7 +404,14 @@ mod tests {
 
     async fn block_token_test_helper(tests: Vec<(BlockTokenTest<'_>, bool)>) {
         let (provider, mock) = Provider::mocked();
-        let fetcher = Fetcher { provider, semaphore: None, rate_limiter: None };
+        let fetcher = Fetcher {
+            provider,
+            semaphore: None,
+            rate_limiter: None,
+            request_counts: Default::default(),
+            block_timestamps: Default::default(),
+            shared_fetches: Default::default(),
+        };
         for (test, res) in tests {
             match test {
                 BlockTokenTest::WithMock((token, expected, latest)) => {
@@ -455,7 +457,14 @@ mod tests {
 
     async fn block_input_test_helper(tests: Vec<(BlockInputTest<'_>, bool)>) {
         let (provider, mock) = Provider::mocked();
-        let fetcher = Fetcher { provider, semaphore: None, rate_limiter: None };
+        let fetcher = Fetcher {
+            provider,
+            semaphore: None,
+            rate_limiter: None,
+            request_counts: Default::default(),
+            block_timestamps: Default::default(),
+            shared_fetches: Default::default(),
+        };
         for (test, res) in tests {
             match test {
                 BlockInputTest::WithMock((inputs, expected, latest)) => {
@@ -516,7 +525,14 @@ mod tests {
 
     async fn block_number_test_helper(tests: Vec<(BlockNumberTest<'_>, bool)>) {
         let (provider, mock) = Provider::mocked();
-        let fetcher = Fetcher { provider, semaphore: None, rate_limiter: None };
+        let fetcher = Fetcher {
+            provider,
+            semaphore: None,
+            rate_limiter: None,
+            request_counts: Default::default(),
+            block_timestamps: Default::default(),
+            shared_fetches: Default::default(),
+        };
         for (test, res) in tests {
             match test {
                 BlockNumberTest::WithMock((block_ref, range_position, expected, latest)) => {
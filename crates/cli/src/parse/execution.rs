@@ -1,12 +1,18 @@
+use super::source::redact_rpc_secrets;
 use crate::args::Args;
 use cryo_freeze::{ExecutionEnv, ExecutionEnvBuilder, ParseError};
+use std::net::SocketAddr;
 
 pub(crate) fn parse_execution_env(args: &Args, n_tasks: u64) -> Result<ExecutionEnv, ParseError> {
+    let (redacted_args, cli_command) = redact_rpc_secrets(args, std::env::args().collect());
     let args_str =
-        serde_json::to_string(args).map_err(|e| ParseError::ParseError(e.to_string()))?;
+        serde_json::to_string(&redacted_args).map_err(|e| ParseError::ParseError(e.to_string()))?;
 
+    // rows streamed to stdout must not be interleaved with summaries
+    let stdout = args.ndjson.as_deref() == Some("-");
     let verbose = match (args.no_verbose, args.verbose) {
         (true, true) => return Err(ParseError::ParseError("".to_string())),
+        _ if stdout => 0,
         (true, false) => 0,
         (false, true) => 2, // future: allow arbitrary numbers
         (false, false) => 1,
@@ -14,11 +20,27 @@ pub(crate) fn parse_execution_env(args: &Args, n_tasks: u64) -> Result<Execution
 
     let builder = ExecutionEnvBuilder::new()
         .dry(args.dry)
+        .dry_run_samples(args.dry_run_samples)
         .verbose(verbose)
         .report(!args.no_report)
-        .report_dir(args.report_dir.clone())
+        .report_dir(match (&args.report_dir, stdout) {
+            (None, true) => Some(std::path::Path::new(&args.output_dir).join(".cryo/reports")),
+            (report_dir, _) => report_dir.clone(),
+        })
+        .report_file(args.report_file.clone())
+        .resumed_from(args.resume.clone())
+        .metrics_addr(parse_metrics_addr(args)?)
+        .cli_command(cli_command)
         .args(args_str);
 
+    let builder = match args.write_threads {
+        Some(0) => {
+            return Err(ParseError::ParseError("--write-threads must be at least 1".to_string()))
+        }
+        Some(write_threads) => builder.write_threads(write_threads),
+        None => builder,
+    };
+
     let builder = if !args.no_verbose {
         builder
             .bar(n_tasks)
@@ -29,3 +51,17 @@ pub(crate) fn parse_execution_env(args: &Args, n_tasks: u64) -> Result<Execution
 
     Ok(builder.build())
 }
+
+fn parse_metrics_addr(args: &Args) -> Result<Option<SocketAddr>, ParseError> {
+    match &args.metrics_addr {
+        None => Ok(None),
+        Some(_) if !cfg!(feature = "metrics") => Err(ParseError::ParseError(
+            "cryo was built without the metrics feature, rebuild with --features metrics to use \
+             --metrics-addr"
+                .to_string(),
+        )),
+        Some(addr) => addr.parse().map(Some).map_err(|_| {
+            ParseError::ParseError(format!("invalid metrics address {}, use HOST:PORT", addr))
+        }),
+    }
+}
@@ -1,13 +1,36 @@
 use crate::args::Args;
-use cryo_freeze::{FileFormat, FileOutput, ParseError, Source, SubDir};
+use cryo_freeze::{
+    AvroCodec, BloomFilterOptions, CompactTarget, EmptyChunks, FileFormat, FileOutput,
+    FlushThreshold, HiveKey, HivePartitioning, ObjectStoreOutput, ParquetStatistics, ParseError,
+    PostgresOutput, Source, SubDir, TextCompression, CHECKSUM_MANIFEST,
+};
 use polars::prelude::*;
-use std::fs;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutput, ParseError> {
-    // process output directory
-    std::fs::create_dir_all(args.output_dir.clone())
+    // process output directory, chunks streamed to stdout or uploaded to an object store are
+    // staged in a temporary directory
+    let stdout = args.ndjson.as_deref() == Some("-");
+    let object_store = parse_object_store(args)?;
+    let output_dir = match (args.ndjson.as_deref(), &object_store) {
+        (Some("-"), _) => std::env::temp_dir()
+            .join(format!("cryo_stdout_{}", std::process::id()))
+            .to_string_lossy()
+            .to_string(),
+        (Some(dir), _) => dir.to_string(),
+        (None, Some(_)) => std::env::temp_dir()
+            .join(format!("cryo_upload_{}", std::process::id()))
+            .to_string_lossy()
+            .to_string(),
+        (None, None) => args.output_dir.clone(),
+    };
+    std::fs::create_dir_all(&output_dir)
         .map_err(|_| ParseError::ParseError("could not create dir".to_string()))?;
-    let output_dir = std::fs::canonicalize(args.output_dir.clone()).map_err(|_e| {
+    let output_dir = std::fs::canonicalize(output_dir).map_err(|_e| {
         ParseError::ParseError("Failed to canonicalize output directory".to_string())
     })?;
     match fs::create_dir_all(&output_dir) {
@@ -18,6 +41,10 @@ pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutp
     let label = &args.label;
 
     let parquet_compression = parse_compression(&args.compression)?;
+    let parquet_column_compression = parse_column_compression(&args.compression_columns)?;
+    let parquet_statistics = parse_statistics(args)?;
+    let ipc_compression = parse_ipc_compression(&args.ipc_compression)?;
+    let avro_codec = parse_avro_codec(&args.avro_codec)?;
 
     let row_group_size = parse_row_group_size(
         args.row_group_size,
@@ -26,25 +53,122 @@ pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutp
     );
 
     let format = parse_output_format(args)?;
+    let bloom_filters = parse_bloom_filters(args, &format)?;
+    let text_compression = parse_text_compression(args, &format)?;
     let file_prefix = parse_network_name(args, source.chain_id);
 
     let subdirs = parse_subdirs(args);
+    let duckdb = parse_duckdb(args)?;
+    let postgres = parse_postgres(args)?;
+    let hive_partitioning = parse_hive_partitioning(args, source.chain_id)?;
+    let max_rows_per_file = parse_max_rows_per_file(args)?;
+    let checksum_manifest = parse_checksum_manifest(args, &output_dir)?;
+    let flush_threshold = parse_flush_threshold(args, &format)?;
+    let empty_chunks = parse_empty_chunks(args)?;
 
     let output = FileOutput {
         output_dir,
         subdirs,
-        parquet_statistics: !args.no_stats,
+        parquet_statistics,
         overwrite: args.overwrite,
         prefix: file_prefix,
         format,
         suffix: label.clone(),
         parquet_compression,
+        parquet_column_compression,
+        bloom_filters,
+        ipc_compression,
+        avro_codec,
+        text_compression,
+        duckdb,
+        postgres,
+        stdout,
+        hive_partitioning,
+        object_store,
+        max_rows_per_file,
+        key_value_metadata: vec![],
+        checksum_manifest,
+        flush_threshold,
+        empty_chunks,
         row_group_size,
     };
 
     Ok(output)
 }
 
+/// parquet write options of files merged by compaction
+pub(crate) fn parse_compact_output(args: &Args, dir: &Path) -> Result<FileOutput, ParseError> {
+    if !dir.is_dir() {
+        return Err(ParseError::ParseError(format!("{} is not a directory", dir.display())))
+    }
+    let format = FileFormat::Parquet;
+    Ok(FileOutput {
+        output_dir: dir.to_path_buf(),
+        subdirs: vec![],
+        parquet_statistics: parse_statistics(args)?,
+        overwrite: true,
+        prefix: String::new(),
+        suffix: None,
+        parquet_compression: parse_compression(&args.compression)?,
+        parquet_column_compression: parse_column_compression(&args.compression_columns)?,
+        bloom_filters: parse_bloom_filters(args, &format)?,
+        format,
+        ipc_compression: None,
+        avro_codec: AvroCodec::default(),
+        text_compression: None,
+        duckdb: None,
+        postgres: None,
+        stdout: false,
+        hive_partitioning: None,
+        object_store: None,
+        max_rows_per_file: None,
+        key_value_metadata: vec![],
+        // compacted files are recorded in the checksum manifest of the directory, if it has one
+        checksum_manifest: Some(dir.join(CHECKSUM_MANIFEST)).filter(|path| path.exists()),
+        flush_threshold: None,
+        empty_chunks: EmptyChunks::Write,
+        row_group_size: args.row_group_size,
+    })
+}
+
+/// size of files merged by compaction, 5M rows when no target is given
+pub(crate) fn parse_compact_target(args: &Args) -> Result<CompactTarget, ParseError> {
+    let rows = match &args.target_rows {
+        Some(rows) => Some(rows.replace('_', "").parse::<u64>().map_err(|_| {
+            ParseError::ParseError("--target-rows must be a number of rows".to_string())
+        })?),
+        None => None,
+    };
+    let bytes = match &args.target_size {
+        Some(size) => Some(parse_byte_size(size)?),
+        None => None,
+    };
+    match (rows, bytes) {
+        (Some(0), _) | (_, Some(0)) => {
+            Err(ParseError::ParseError("compaction targets must be positive".to_string()))
+        }
+        (None, None) => Ok(CompactTarget { rows: Some(5_000_000), bytes: None }),
+        (rows, bytes) => Ok(CompactTarget { rows, bytes }),
+    }
+}
+
+/// parse size such as 512MB or 1GB into bytes
+pub(crate) fn parse_byte_size(size: &str) -> Result<u64, ParseError> {
+    let size = size.trim().to_uppercase().replace('_', "");
+    let digits = size.find(|c: char| !c.is_ascii_digit()).unwrap_or(size.len());
+    let multiplier = match size[digits..].trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return Err(ParseError::ParseError(format!("invalid size: {}", size))),
+    };
+    size[..digits]
+        .parse::<u64>()
+        .map(|value| value * multiplier)
+        .map_err(|_| ParseError::ParseError(format!("invalid size: {}", size)))
+}
+
 pub(crate) fn parse_subdirs(args: &Args) -> Vec<SubDir> {
     let mut subdirs = Vec::new();
     for arg in args.subdirs.iter() {
@@ -62,44 +186,306 @@ pub(crate) fn parse_subdirs(args: &Args) -> Vec<SubDir> {
 pub(crate) fn parse_network_name(args: &Args, chain_id: u64) -> String {
     match &args.network_name {
         Some(name) => name.clone(),
-        None => match chain_id {
-            1 => "ethereum".to_string(),
-            5 => "goerli".to_string(),
-            10 => "optimism".to_string(),
-            56 => "bnb".to_string(),
-            69 => "optimism_kovan".to_string(),
-            100 => "gnosis".to_string(),
-            137 => "polygon".to_string(),
-            420 => "optimism_goerli".to_string(),
-            1101 => "polygon_zkevm".to_string(),
-            1442 => "polygon_zkevm_testnet".to_string(),
-            8453 => "base".to_string(),
-            10200 => "gnosis_chidao".to_string(),
-            17000 => "holesky".to_string(),
-            42161 => "arbitrum".to_string(),
-            42170 => "arbitrum_nova".to_string(),
-            43114 => "avalanche".to_string(),
-            80001 => "polygon_mumbai".to_string(),
-            84531 => "base_goerli".to_string(),
-            7777777 => "zora".to_string(),
-            11155111 => "sepolia".to_string(),
-            chain_id => "network_".to_string() + chain_id.to_string().as_str(),
-        },
+        None => cryo_freeze::chain_name(chain_id),
     }
 }
 
 pub(crate) fn parse_output_format(args: &Args) -> Result<FileFormat, ParseError> {
-    match (args.csv, args.json) {
-        (true, true) => {
-            Err(ParseError::ParseError("choose one of parquet, csv, or json".to_string()))
+    match (args.csv, args.json, args.ipc, args.avro, args.ndjson.is_some()) {
+        (true, false, false, false, false) => Ok(FileFormat::Csv),
+        (false, true, false, false, false) => Ok(FileFormat::Json),
+        (false, false, true, false, false) => Ok(FileFormat::Ipc),
+        (false, false, false, true, false) => Ok(FileFormat::Avro),
+        (false, false, false, false, true) => Ok(FileFormat::Ndjson),
+        (false, false, false, false, false) => Ok(FileFormat::Parquet),
+        _ => Err(ParseError::ParseError(
+            "choose one of parquet, csv, json, ndjson, ipc, or avro".to_string(),
+        )),
+    }
+}
+
+fn parse_duckdb(args: &Args) -> Result<Option<std::path::PathBuf>, ParseError> {
+    match &args.duckdb {
+        // chunks are staged as parquet files before being loaded
+        Some(_) if args.csv || args.json || args.ipc || args.avro || args.ndjson.is_some() => {
+            Err(ParseError::ParseError("duckdb output is staged as parquet".to_string()))
+        }
+        Some(path) => Ok(Some(std::path::PathBuf::from(path))),
+        None => Ok(None),
+    }
+}
+
+fn parse_postgres(args: &Args) -> Result<Option<PostgresOutput>, ParseError> {
+    match &args.postgres {
+        Some(_) if args.duckdb.is_some() => {
+            Err(ParseError::ParseError("choose one of duckdb or postgres".to_string()))
+        }
+        Some(_) if args.csv || args.json || args.ipc || args.avro || args.ndjson.is_some() => {
+            Err(ParseError::ParseError("postgres output is staged as parquet".to_string()))
+        }
+        Some(url) => Ok(Some(PostgresOutput {
+            url: url.clone(),
+            schema: args.postgres_schema.clone(),
+            table_prefix: args.postgres_table_prefix.clone(),
+        })),
+        None => Ok(None),
+    }
+}
+
+fn parse_object_store(args: &Args) -> Result<Option<ObjectStoreOutput>, ParseError> {
+    match ObjectStoreOutput::parse(&args.output_dir)? {
+        Some(_) if args.duckdb.is_some() || args.postgres.is_some() || args.ndjson.is_some() => {
+            Err(ParseError::ParseError(
+                "object store output cannot be combined with database or ndjson output".to_string(),
+            ))
+        }
+        Some(_) if args.hive_partitioning.is_some() => Err(ParseError::ParseError(
+            "hive partitioning cannot be used with object store output".to_string(),
+        )),
+        object_store => Ok(object_store),
+    }
+}
+
+fn parse_max_rows_per_file(args: &Args) -> Result<Option<usize>, ParseError> {
+    match args.max_rows_per_file {
+        Some(0) => Err(ParseError::ParseError("--max-rows-per-file must be positive".to_string())),
+        // split chunks are written as several files of the output directory
+        Some(_)
+            if args.duckdb.is_some() ||
+                args.postgres.is_some() ||
+                args.ndjson.as_deref() == Some("-") =>
+        {
+            Err(ParseError::ParseError(
+                "--max-rows-per-file cannot be combined with database or stdout output".to_string(),
+            ))
+        }
+        Some(_) if ObjectStoreOutput::parse(&args.output_dir)?.is_some() => {
+            Err(ParseError::ParseError(
+                "--max-rows-per-file cannot be used with object store output".to_string(),
+            ))
+        }
+        max_rows_per_file => Ok(max_rows_per_file),
+    }
+}
+
+/// flushing of chunks to parquet files while collecting, options that need whole chunks are
+/// excluded
+fn parse_flush_threshold(
+    args: &Args,
+    format: &FileFormat,
+) -> Result<Option<FlushThreshold>, ParseError> {
+    let threshold = match (&args.memory_budget, args.flush_rows) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => {
+            return Err(ParseError::ParseError(
+                "--memory-budget and --flush-rows cannot be combined".to_string(),
+            ))
+        }
+        (None, Some(0)) => {
+            return Err(ParseError::ParseError("--flush-rows must be positive".to_string()))
+        }
+        (None, Some(n_rows)) => FlushThreshold::Rows(n_rows),
+        (Some(size), None) => match parse_byte_size(size)? {
+            0 => return Err(ParseError::ParseError("--memory-budget must be positive".to_string())),
+            n_bytes => FlushThreshold::Bytes(n_bytes),
+        },
+    };
+    let incompatible = [
+        (*format != FileFormat::Parquet, "output formats other than parquet"),
+        (args.hive_partitioning.is_some(), "--hive-partitioning"),
+        (args.max_rows_per_file.is_some(), "--max-rows-per-file"),
+        (args.bloom_filter_columns.is_some(), "--bloom-filter-columns"),
+        (args.checksum_manifest, "--checksum-manifest"),
+        (args.verify_hashes.is_some(), "--verify-hashes"),
+    ];
+    match incompatible.iter().find(|(used, _)| *used) {
+        Some((_, option)) => Err(ParseError::ParseError(format!(
+            "chunks can only be flushed while collecting into parquet files, not with {}",
+            option
+        ))),
+        None => Ok(Some(threshold)),
+    }
+}
+
+fn parse_checksum_manifest(args: &Args, output_dir: &Path) -> Result<Option<PathBuf>, ParseError> {
+    if !args.checksum_manifest {
+        return Ok(None)
+    }
+    // checksums are only recorded for files that remain in the output directory
+    if args.duckdb.is_some() ||
+        args.postgres.is_some() ||
+        args.ndjson.as_deref() == Some("-") ||
+        ObjectStoreOutput::parse(&args.output_dir)?.is_some()
+    {
+        return Err(ParseError::ParseError(
+            "--checksum-manifest requires local output files".to_string(),
+        ))
+    }
+    Ok(Some(output_dir.join(CHECKSUM_MANIFEST)))
+}
+
+fn parse_hive_partitioning(
+    args: &Args,
+    chain_id: u64,
+) -> Result<Option<HivePartitioning>, ParseError> {
+    let keys = match &args.hive_partitioning {
+        None if args.drop_partition_columns => {
+            return Err(ParseError::ParseError(
+                "--drop-partition-columns requires --hive-partitioning".to_string(),
+            ))
+        }
+        None => return Ok(None),
+        // partitioned files are written to the output directory
+        Some(_) if args.duckdb.is_some() || args.postgres.is_some() || args.ndjson.is_some() => {
+            return Err(ParseError::ParseError(
+                "hive partitioning can only be used when writing files to --output-dir".to_string(),
+            ))
+        }
+        Some(_) if !args.subdirs.is_empty() => {
+            return Err(ParseError::ParseError(
+                "choose one of --hive-partitioning or --subdirs".to_string(),
+            ))
+        }
+        Some(keys) if keys.is_empty() => vec![HiveKey::ChainId],
+        Some(keys) => keys
+            .iter()
+            .map(|key| match key.split_once(':') {
+                None if key == "chain_id" => Ok(HiveKey::ChainId),
+                None if key == "date" => Ok(HiveKey::Date),
+                None if key == "block_bucket" => Ok(HiveKey::BlockBucket(args.chunk_size)),
+                None if key == "address" => Ok(HiveKey::Address),
+                Some(("block_bucket", size)) => match size.parse::<u64>() {
+                    Ok(size) if size > 0 => Ok(HiveKey::BlockBucket(size)),
+                    _ => {
+                        Err(ParseError::ParseError(format!("invalid block bucket size: {}", size)))
+                    }
+                },
+                _ => Err(ParseError::ParseError(format!(
+                    "hive partition key must be chain_id, date, block_bucket, or address, got {}",
+                    key
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    Ok(Some(HivePartitioning { keys, chain_id, drop_columns: args.drop_partition_columns }))
+}
+
+fn parse_ipc_compression(input: &Option<String>) -> Result<Option<IpcCompression>, ParseError> {
+    match input.as_deref() {
+        None | Some("uncompressed") => Ok(None),
+        Some("lz4") => Ok(Some(IpcCompression::LZ4)),
+        Some("zstd") => Ok(Some(IpcCompression::ZSTD)),
+        Some(_) => Err(ParseError::ParseError("ipc compression must be lz4 or zstd".to_string())),
+    }
+}
+
+fn parse_avro_codec(input: &Option<String>) -> Result<AvroCodec, ParseError> {
+    match input.as_deref() {
+        None | Some("null") => Ok(AvroCodec::Null),
+        Some("deflate") => Ok(AvroCodec::Deflate),
+        Some("snappy") => Ok(AvroCodec::Snappy),
+        Some(_) => Err(ParseError::ParseError("avro codec must be deflate or snappy".to_string())),
+    }
+}
+
+fn parse_text_compression(
+    args: &Args,
+    format: &FileFormat,
+) -> Result<Option<TextCompression>, ParseError> {
+    let (name, flag) = match format {
+        _ if args.csv_compression.is_some() && format != &FileFormat::Csv => {
+            return Err(ParseError::ParseError("--csv-compression requires --csv".to_string()))
         }
-        (true, _) => Ok(FileFormat::Csv),
-        (_, true) => Ok(FileFormat::Json),
-        (false, false) => Ok(FileFormat::Parquet),
+        FileFormat::Csv => (&args.csv_compression, "--csv-compression"),
+        FileFormat::Json | FileFormat::Ndjson => (&args.json_compression, "--json-compression"),
+        _ if args.json_compression.is_some() => {
+            return Err(ParseError::ParseError(
+                "--json-compression requires --json or --ndjson".to_string(),
+            ))
+        }
+        _ => return Ok(None),
+    };
+    let compression = match name.as_deref() {
+        None => return Ok(None),
+        Some("gzip") => TextCompression::Gzip,
+        Some("zstd") => TextCompression::Zstd,
+        Some(_) => return Err(ParseError::ParseError(format!("{} must be gzip or zstd", flag))),
+    };
+    // compressed files are written to disk rather than staged for other outputs
+    if args.duckdb.is_some() || args.postgres.is_some() || args.ndjson.as_deref() == Some("-") {
+        return Err(ParseError::ParseError(format!(
+            "{} cannot be combined with database or stdout output",
+            flag
+        )))
+    }
+    Ok(Some(compression))
+}
+
+fn parse_statistics(args: &Args) -> Result<ParquetStatistics, ParseError> {
+    match (args.statistics.as_deref(), args.no_stats) {
+        (None, false) | (Some("page"), false) => Ok(ParquetStatistics::Page),
+        (None, true) | (Some("none"), false) => Ok(ParquetStatistics::None),
+        (Some("chunk"), false) => Ok(ParquetStatistics::Chunk),
+        (Some(_), true) => {
+            Err(ParseError::ParseError("choose one of --statistics or --no-stats".to_string()))
+        }
+        (Some(_), false) => {
+            Err(ParseError::ParseError("statistics must be none, chunk, or page".to_string()))
+        }
+    }
+}
+
+fn parse_empty_chunks(args: &Args) -> Result<EmptyChunks, ParseError> {
+    match args.empty_chunks.as_deref() {
+        None | Some("write") => Ok(EmptyChunks::Write),
+        Some("skip") => Ok(EmptyChunks::Skip),
+        Some(_) => Err(ParseError::ParseError("empty chunks must be write or skip".to_string())),
+    }
+}
+
+fn parse_bloom_filters(
+    args: &Args,
+    format: &FileFormat,
+) -> Result<Option<BloomFilterOptions>, ParseError> {
+    let columns = match (&args.bloom_filter_columns, format) {
+        (None, _) => return Ok(None),
+        (Some(columns), FileFormat::Parquet) => columns.clone(),
+        (Some(_), _) => {
+            return Err(ParseError::ParseError(
+                "bloom filters can only be written to parquet files".to_string(),
+            ))
+        }
+    };
+    if !(args.bloom_filter_fpp > 0.0 && args.bloom_filter_fpp < 1.0) {
+        return Err(ParseError::ParseError("bloom filter fpp must be between 0 and 1".to_string()))
     }
+    let columns = if columns.is_empty() { None } else { Some(columns) };
+    Ok(Some(BloomFilterOptions { columns, fpp: args.bloom_filter_fpp, ndv: args.bloom_filter_ndv }))
 }
 
-fn parse_compression(input: &Vec<String>) -> Result<ParquetCompression, ParseError> {
+fn parse_column_compression(
+    input: &[String],
+) -> Result<HashMap<String, ParquetCompression>, ParseError> {
+    input
+        .iter()
+        .map(|item| match item.split_once('=') {
+            Some((column, compression)) => {
+                Ok((column.to_string(), parse_compression(&[compression.to_string()])?))
+            }
+            None => Err(ParseError::ParseError(format!(
+                "column compression must be COLUMN=NAME, got {}",
+                item
+            ))),
+        })
+        .collect()
+}
+
+fn parse_compression(input: &[String]) -> Result<ParquetCompression, ParseError> {
+    // levels can be given as a separate argument or after a colon
+    let input: Vec<String> = match input {
+        [algorithm] => algorithm.split(':').map(|x| x.to_string()).collect(),
+        _ => input.to_vec(),
+    };
     match input.as_slice() {
         [algorithm] if algorithm.as_str() == "uncompressed" => Ok(ParquetCompression::Uncompressed),
         [algorithm] if algorithm.as_str() == "snappy" => Ok(ParquetCompression::Snappy),
@@ -142,7 +528,7 @@ fn parse_row_group_size(
 ) -> Option<usize> {
     match (row_group_size, n_row_groups, chunk_size) {
         (Some(row_group_size), _, _) => Some(row_group_size),
-        (_, Some(n_row_groups), Some(cs)) => Some((cs + n_row_groups - 1) / n_row_groups),
+        (_, Some(n_row_groups), Some(cs)) => Some(cs.div_ceil(n_row_groups)),
         _ => None,
     }
 }
@@ -1,3 +1,4 @@
+mod append;
 mod args;
 mod blocks;
 mod execution;
@@ -9,7 +10,10 @@ pub(crate) mod schemas;
 mod source;
 mod timestamps;
 
+pub(crate) use append::{partition_ranges, trim_partition};
 pub use args::*;
+pub(crate) use partitions::parse_chunk_order;
 #[allow(unused_imports)]
 pub use query::*;
 use schemas::*;
+pub(crate) use source::REDACTED;
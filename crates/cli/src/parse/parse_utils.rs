@@ -1,7 +1,8 @@
 use cryo_freeze::ParseError;
+use polars::prelude::*;
 use std::collections::HashMap;
 
-pub(crate) fn hex_string_to_binary(hex_string: &String) -> Result<Vec<u8>, ParseError> {
+pub(crate) fn hex_string_to_binary(hex_string: &str) -> Result<Vec<u8>, ParseError> {
     let hex_string = hex_string.strip_prefix("0x").unwrap_or(hex_string);
     hex::decode(hex_string)
         .map_err(|_| ParseError::ParseError("could not parse data as hex".to_string()))
@@ -97,3 +98,129 @@ fn parse_file_column_reference(
 
     Ok(parsed)
 }
+
+/// number of invalid lines listed by list file errors
+const MAX_LISTED_LINES: usize = 10;
+
+/// entry of a list file
+pub(crate) struct ListEntry {
+    /// line number of entry
+    pub(crate) line: usize,
+    /// value of entry, binary values are converted to hex
+    pub(crate) value: Option<String>,
+    /// label of entry
+    pub(crate) label: Option<String>,
+}
+
+/// read entries of a list file, one per line of a text file or one per row of a csv, parquet, or
+/// ipc file
+///
+/// values are read from the given column, or from the first column or field of each line along
+/// with a label from the second
+pub(crate) fn read_list_file(
+    path: &str,
+    column: Option<&str>,
+) -> Result<Vec<ListEntry>, ParseError> {
+    let read_error = || ParseError::ParseError(format!("could not read file: {}", path));
+    let extension = Path::new(path).extension().and_then(|ex| ex.to_str());
+    let df = match extension {
+        Some("parquet") | Some("arrow") | Some("ipc") | Some("feather") => match column {
+            Some(column) => cryo_freeze::read_column(path, column)?,
+            None => {
+                let file = std::fs::File::open(path).map_err(|_| read_error())?;
+                match extension {
+                    Some("parquet") => ParquetReader::new(file).finish(),
+                    _ => IpcReader::new(file).finish(),
+                }
+                .map_err(|_| read_error())?
+            }
+        },
+        Some("csv") => {
+            let file = std::fs::File::open(path).map_err(|_| read_error())?;
+            CsvReader::new(file)
+                .has_header(true)
+                .infer_schema(Some(0))
+                .finish()
+                .map_err(|_| read_error())?
+        }
+        _ => {
+            let contents = std::fs::read_to_string(path).map_err(|_| read_error())?;
+            return Ok(contents
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| {
+                    let mut fields = line.splitn(2, [',', '\t']).map(|field| field.trim());
+                    let value = fields.next().map(|value| value.to_string());
+                    let label = match column {
+                        Some(_) => None,
+                        None => fields.next().filter(|label| !label.is_empty()).map(String::from),
+                    };
+                    ListEntry { line: index + 1, value, label }
+                })
+                .collect())
+        }
+    };
+
+    let columns = df.get_columns();
+    let (values, labels) = match column {
+        Some(column) => (df.column(column).ok(), None),
+        None => (columns.first(), columns.get(1)),
+    };
+    let values = values.ok_or_else(|| {
+        ParseError::ParseError(format!("no column {} in {}", column.unwrap_or_default(), path))
+    })?;
+    let values: Vec<Option<String>> = match values.dtype() {
+        DataType::Binary => values
+            .binary()
+            .map_err(|_| read_error())?
+            .into_iter()
+            .map(|value| value.map(|value| format!("0x{}", hex::encode(value))))
+            .collect(),
+        DataType::Utf8 => values
+            .utf8()
+            .map_err(|_| read_error())?
+            .into_iter()
+            .map(|value| value.map(|value| value.trim().to_string()))
+            .collect(),
+        _ => {
+            return Err(ParseError::ParseError(format!(
+                "column {} of {} must be binary or hex strings",
+                values.name(),
+                path
+            )))
+        }
+    };
+    let labels: Vec<Option<String>> = match labels {
+        Some(labels) => labels
+            .cast(&DataType::Utf8)
+            .map_err(|_| read_error())?
+            .utf8()
+            .map_err(|_| read_error())?
+            .into_iter()
+            .map(|label| label.map(String::from))
+            .collect(),
+        None => vec![None; values.len()],
+    };
+
+    // csv files have a header line
+    let first_line = if extension == Some("csv") { 2 } else { 1 };
+    Ok(values
+        .into_iter()
+        .zip(labels)
+        .enumerate()
+        .map(|(index, (value, label))| ListEntry { line: index + first_line, value, label })
+        .collect())
+}
+
+/// error listing the invalid lines of a list file
+pub(crate) fn invalid_lines_error(kind: &str, path: &str, lines: &[usize]) -> ParseError {
+    let listed: Vec<String> = lines.iter().take(MAX_LISTED_LINES).map(|n| n.to_string()).collect();
+    ParseError::ParseError(format!(
+        "{} invalid {} in {}, on lines {}",
+        lines.len(),
+        kind,
+        path,
+        listed.join(", ")
+    ))
+}
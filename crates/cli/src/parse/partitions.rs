@@ -1,44 +1,70 @@
 use super::{
     blocks,
-    parse_utils::{hex_string_to_binary, hex_strings_to_binary, parse_binary_arg},
+    parse_utils::{
+        hex_string_to_binary, hex_strings_to_binary, invalid_lines_error, parse_binary_arg,
+        read_list_file,
+    },
     timestamps,
 };
 use crate::args::Args;
+use ::rand::{seq::SliceRandom, thread_rng};
 use cryo_freeze::{
-    AddressChunk, CallDataChunk, Datatype, Dim, Fetcher, ParseError, Partition, PartitionLabels,
-    SlotChunk, Table, TimeDimension, TopicChunk, TransactionChunk,
+    AddressChunk, CallDataChunk, CollectWarnings, Datatype, Dim, Fetcher, ParseError, Partition,
+    PartitionLabels, SlotChunk, Table, TimeBucket, TimeDimension, TimestampRange, TopicChunk,
+    TransactionChunk,
 };
 use ethers::prelude::*;
-use rand::{seq::SliceRandom, thread_rng};
 use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 type ChunkLabels = Vec<Option<String>>;
+type Partitions = (Vec<Partition>, Vec<Dim>, TimeDimension, Vec<TimestampRange>, Vec<TimeBucket>);
 
 pub(crate) async fn parse_partitions<P: JsonRpcClient>(
     args: &Args,
     fetcher: Arc<Fetcher<P>>,
     schemas: &HashMap<Datatype, Table>,
-) -> Result<(Vec<Partition>, Vec<Dim>, TimeDimension), ParseError> {
+    warnings: &CollectWarnings,
+) -> Result<Partitions, ParseError> {
+    // snapshot datasets are collected from current node state rather than from blocks
+    if let Some(snapshot_partition) = parse_snapshot_partition(schemas)? {
+        let partition_by = vec![Dim::BlockNumber];
+        let time_dimension = TimeDimension::Snapshots;
+        return Ok((vec![snapshot_partition], partition_by, time_dimension, Vec::new(), Vec::new()))
+    }
+
     // TODO: if wanting to chunk these non-block dimensions, do it in parse_binary_arg()
     // TODO: map from args to dim is not exhaustive
 
     // parse chunk data
     let (block_number_labels, block_numbers) = blocks::parse_blocks(args, fetcher.clone()).await?;
-    let (block_number_labels, block_numbers) = if block_numbers.is_none() {
+    let (block_number_labels, block_numbers, timestamp_ranges) = if block_numbers.is_none() {
         timestamps::parse_timestamps(args, fetcher.clone()).await?
     } else {
-        (block_number_labels, block_numbers)
+        (block_number_labels, block_numbers, Vec::new())
     };
-    let (transaction_hash_labels, transactions) =
-        parse_transaction_chunks(&args.txs, "transaction_hash")?;
-    let call_datas = parse_call_datas(&args.call_data, &args.function, &args.inputs)?;
+    let (transaction_hash_labels, transactions) = match &args.txs_file {
+        Some(path) => parse_txs_file(args, path, warnings)?,
+        None => parse_transaction_chunks(&args.txs, "transaction_hash")?,
+    };
+    let call_datas =
+        parse_call_datas(&args.call_data, &args.function, &args.inputs, &args.args_file)?;
     let call_data_labels = None;
     let (address_labels, addresses) = parse_address_chunks(&args.address, "address")?;
-    let (contract_labels, contracts) = parse_address_chunks(&args.contract, "contract_address")?;
+    // datasets without a contract parameter use --contract as a filter instead
+    let (contract_labels, contracts) = if uses_parameter(schemas, Dim::Contract) {
+        parse_address_chunks(&args.contract, "contract_address")?
+    } else {
+        (None, None)
+    };
     let (from_address_labels, from_addresses) =
         parse_address_chunks(&args.from_address, "from_address")?;
     let (to_address_labels, to_addresses) = parse_address_chunks(&args.to_address, "to_address")?;
-    let (slot_labels, slots) = parse_slot_chunks(&args.slot, "slot")?;
+    // datasets without a slot parameter use --slot as a filter instead of a request dimension
+    let (slot_labels, slots) = if uses_parameter(schemas, Dim::Slot) {
+        parse_slot_chunks(&args.slot, "slot")?
+    } else {
+        (None, None)
+    };
     let (topic0_labels, topic0s) = parse_topic(&args.topic0, "topic0")?;
     let (topic1_labels, topic1s) = parse_topic(&args.topic1, "topic1")?;
     let (topic2_labels, topic2s) = parse_topic(&args.topic2, "topic2")?;
@@ -46,11 +72,29 @@ pub(crate) async fn parse_partitions<P: JsonRpcClient>(
 
     // set default blocks
     let block_numbers = if block_numbers.is_none() && transactions.is_none() {
-        Some(blocks::get_default_block_chunks(args, fetcher, schemas).await?)
+        Some(blocks::get_default_block_chunks(args, fetcher.clone(), schemas).await?)
     } else {
         block_numbers
     };
 
+    // align chunks to calendar buckets of block timestamps
+    let (block_number_labels, block_numbers, time_buckets) = match (&args.chunk_by, block_numbers) {
+        (Some(_), _) if args.n_chunks.is_some() || block_number_labels.is_some() => {
+            return Err(ParseError::ParseError(
+                "--chunk-by cannot be combined with --n-chunks or block files".to_string(),
+            ))
+        }
+        (Some(unit), Some(block_numbers)) => {
+            let (labels, block_numbers, time_buckets) =
+                timestamps::parse_time_buckets(unit, block_numbers, args, &fetcher).await?;
+            (Some(labels), Some(block_numbers), time_buckets)
+        }
+        (Some(_), None) => {
+            return Err(ParseError::ParseError("--chunk-by requires block ranges".to_string()))
+        }
+        (None, block_numbers) => (block_number_labels, block_numbers, Vec::new()),
+    };
+
     // aggregate chunk data
     let chunk = Partition {
         label: None,
@@ -95,7 +139,7 @@ pub(crate) async fn parse_partitions<P: JsonRpcClient>(
                 .filter(|dim| labels.dim_labeled(dim) && chunk.n_chunks(dim) > 1)
                 .cloned()
                 .collect();
-            if args.txs.is_some() {
+            if args.txs.is_some() || args.txs_file.is_some() {
                 vec![Dim::TransactionHash]
             } else if multichunk_dims.is_empty() {
                 vec![Dim::BlockNumber]
@@ -108,22 +152,58 @@ pub(crate) async fn parse_partitions<P: JsonRpcClient>(
         .partition_with_labels(labels, partition_by.clone())
         .map_err(|e| ParseError::ParseError(format!("could not partition labels ({})", e)))?;
 
-    match args.chunk_order.as_deref() {
-        None => {}
-        Some("normal") => {}
-        Some("reverse") => partitions.reverse(),
-        Some("random") => {
+    match parse_chunk_order(args)? {
+        "reverse" => partitions.reverse(),
+        "random" => {
             let mut rng = thread_rng();
             partitions.shuffle(&mut rng);
         }
-        _ => {
-            return Err(ParseError::ParseError(
-                "invalid --chunk-order, use normal, reverse, or random".to_string(),
-            ))
-        }
+        _ => {}
     };
 
-    Ok((partitions, partition_by, time_dimension))
+    Ok((partitions, partition_by, time_dimension, timestamp_ranges, time_buckets))
+}
+
+/// order of chunk collection, reverse collects the newest chunks first
+pub(crate) fn parse_chunk_order(args: &Args) -> Result<&'static str, ParseError> {
+    match (args.chunk_order.as_deref(), args.reverse) {
+        (None, true) | (Some("reverse"), _) => Ok("reverse"),
+        (_, true) => Err(ParseError::ParseError(
+            "--reverse cannot be combined with another --chunk-order".to_string(),
+        )),
+        (None, false) | (Some("normal"), false) => Ok("normal"),
+        (Some("random"), false) => Ok("random"),
+        _ => Err(ParseError::ParseError(
+            "invalid --chunk-order, use normal, reverse, or random".to_string(),
+        )),
+    }
+}
+
+fn uses_parameter(schemas: &HashMap<Datatype, Table>, dim: Dim) -> bool {
+    schemas.keys().any(|datatype| {
+        datatype.required_parameters().contains(&dim) ||
+            datatype.optional_parameters().contains(&dim)
+    })
+}
+
+/// single partition labeled by collection time, so that repeated runs write separate files
+fn parse_snapshot_partition(
+    schemas: &HashMap<Datatype, Table>,
+) -> Result<Option<Partition>, ParseError> {
+    let n_snapshot = schemas.keys().filter(|datatype| datatype.can_collect_by_snapshot()).count();
+    if n_snapshot == 0 {
+        return Ok(None)
+    } else if n_snapshot < schemas.len() {
+        return Err(ParseError::ParseError(
+            "snapshot datasets cannot be collected together with other datasets".to_string(),
+        ))
+    }
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|_| ParseError::ParseError("could not get current time".to_string()))?
+        .as_secs();
+    let label = Some(vec![Some(format!("snapshot_{}", timestamp))]);
+    Ok(Some(Partition { label, ..Default::default() }))
 }
 
 fn parse_time_dimension(partition: &Partition) -> TimeDimension {
@@ -138,16 +218,38 @@ fn parse_call_datas(
     call_datas: &Option<Vec<String>>,
     function: &Option<Vec<String>>,
     inputs: &Option<Vec<String>>,
+    args_file: &Option<String>,
 ) -> Result<Option<Vec<CallDataChunk>>, ParseError> {
+    if let Some(path) = args_file {
+        let message = match (call_datas, function, inputs) {
+            (None, Some(function), None) => {
+                let contents = std::fs::read_to_string(path).map_err(|_| {
+                    ParseError::ParseError(format!("could not read args file: {}", path))
+                })?;
+                let mut call_datas = Vec::new();
+                for f in function.iter() {
+                    call_datas.extend(encode_args_file(&parse_function_signature(f)?, &contents)?);
+                }
+                return Ok(Some(vec![CallDataChunk::Values(call_datas)]))
+            }
+            (_, None, _) => "must specify function if specifying args file",
+            (Some(_), _, _) => "cannot specify both call_data and args file",
+            (None, Some(_), Some(_)) => "cannot specify both inputs and args file",
+        };
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+
     let call_datas = match (call_datas, function, inputs) {
         (None, None, None) => return Ok(None),
         (Some(call_data), None, None) => hex_strings_to_binary(call_data)?,
-        (None, Some(function), None) => hex_strings_to_binary(function)?,
+        (None, Some(function), None) => {
+            function.iter().map(|f| parse_function_selector(f)).collect::<Result<_, _>>()?
+        }
         (None, Some(function), Some(inputs)) => {
             let mut call_datas = Vec::new();
             for f in function.iter() {
                 for i in inputs.iter() {
-                    let mut call_data = hex_string_to_binary(f)?.clone();
+                    let mut call_data = parse_function_selector(f)?;
                     call_data.extend(hex_string_to_binary(i)?);
                     call_datas.push(call_data);
                 }
@@ -174,6 +276,50 @@ fn parse_call_datas(
     Ok(Some(vec![CallDataChunk::Values(call_datas)]))
 }
 
+/// parse function given either as hex selector or as signature like balanceOf(address)
+fn parse_function_selector(function: &str) -> Result<Vec<u8>, ParseError> {
+    if function.contains('(') {
+        Ok(parse_function_signature(function)?.short_signature().to_vec())
+    } else {
+        hex_string_to_binary(function)
+    }
+}
+
+/// parse topic0 of event given as signature like Transfer(address,address,uint256)
+pub(crate) fn parse_event_topic(signature: &str) -> Result<Vec<u8>, ParseError> {
+    let declaration = match signature.trim().starts_with("event ") {
+        true => signature.trim().to_string(),
+        false => format!("event {}", signature.trim()),
+    };
+    abi::HumanReadableParser::parse_event(&declaration)
+        .map(|event| event.signature().as_bytes().to_vec())
+        .map_err(|_| ParseError::ParseError(format!("could not parse event: {}", signature)))
+}
+
+fn parse_function_signature(signature: &str) -> Result<abi::Function, ParseError> {
+    abi::AbiParser::default()
+        .parse_function(signature)
+        .map_err(|_| ParseError::ParseError(format!("could not parse function: {}", signature)))
+}
+
+/// encode call data for each non-empty line of comma-separated arguments
+fn encode_args_file(function: &abi::Function, contents: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+    use abi::token::{LenientTokenizer, Tokenizer};
+    let param_type =
+        abi::ParamType::Tuple(function.inputs.iter().map(|input| input.kind.clone()).collect());
+    let mut call_datas = Vec::new();
+    for line in contents.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let invalid =
+            || ParseError::ParseError(format!("invalid arguments in args file: {}", line));
+        let tokens = match LenientTokenizer::tokenize(&param_type, &format!("({})", line)) {
+            Ok(abi::Token::Tuple(tokens)) => tokens,
+            _ => return Err(invalid()),
+        };
+        call_datas.push(function.encode_input(&tokens).map_err(|_| invalid())?);
+    }
+    Ok(call_datas)
+}
+
 pub(crate) fn parse_transaction_chunks(
     input: &Option<Vec<String>>,
     default_column: &str,
@@ -188,6 +334,49 @@ pub(crate) fn parse_transaction_chunks(
     }
 }
 
+/// parse transaction hashes of a txs file into sorted, de-duplicated transaction chunks
+fn parse_txs_file(
+    args: &Args,
+    path: &str,
+    warnings: &CollectWarnings,
+) -> Result<(Option<ChunkLabels>, Option<Vec<TransactionChunk>>), ParseError> {
+    let column = args.txs_column.as_deref().unwrap_or("transaction_hash");
+    let mut hashes = Vec::new();
+    let mut invalid_lines = Vec::new();
+    for entry in read_list_file(path, Some(column))?.into_iter() {
+        match entry.value.and_then(|value| hex_string_to_binary(&value).ok()) {
+            Some(hash) if hash.len() == 32 => hashes.push(hash),
+            _ => invalid_lines.push(entry.line),
+        }
+    }
+    if !invalid_lines.is_empty() {
+        return Err(invalid_lines_error("transaction hashes", path, &invalid_lines))
+    }
+    if hashes.is_empty() {
+        return Err(ParseError::ParseError(format!("no transaction hashes in {}", path)))
+    }
+
+    // sorted hashes give chunks disjoint hash ranges, so that file names are stable across runs
+    let n_hashes = hashes.len();
+    hashes.sort();
+    hashes.dedup();
+    if hashes.len() < n_hashes {
+        warnings.add(
+            "duplicate transaction hashes removed from --txs-file",
+            (n_hashes - hashes.len()) as u64,
+        );
+    }
+    let chunk_size = match args.n_chunks {
+        Some(n_chunks) => (hashes.len() as u64).div_ceil(n_chunks.max(1)),
+        None => args.chunk_size.max(1),
+    };
+    let chunks: Vec<TransactionChunk> = hashes
+        .chunks(chunk_size as usize)
+        .map(|chunk| TransactionChunk::Values(chunk.to_vec()))
+        .collect();
+    Ok((Some(vec![None; chunks.len()]), Some(chunks)))
+}
+
 pub(crate) fn parse_address_chunks(
     input: &Option<Vec<String>>,
     default_column: &str,
@@ -229,3 +418,55 @@ fn parse_topic(
         Ok((None, None))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap_cryo::Parser;
+    use cryo_freeze::ChunkData;
+
+    #[test]
+    fn test_parse_txs_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_txs_file_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let hash = |byte: u8| format!("0x{}", hex::encode([byte; 32]));
+        let text = dir.join("txs.txt");
+        std::fs::write(&text, [hash(3), hash(1), String::new(), hash(2), hash(1)].join("\n"))
+            .unwrap();
+        let path = text.to_str().unwrap();
+        let args =
+            Args::parse_from(["cryo", "transactions", "--txs-file", path, "--chunk-size", "2"]);
+        let warnings = CollectWarnings::default();
+        let (labels, chunks) = parse_txs_file(&args, path, &warnings).unwrap();
+        let chunks: Vec<Vec<Vec<u8>>> = chunks.unwrap().iter().map(|c| c.values()).collect();
+        assert_eq!(chunks, vec![vec![vec![1; 32], vec![2; 32]], vec![vec![3; 32]]]);
+        assert_eq!(labels, Some(vec![None, None]));
+        assert_eq!(warnings.counts().values().collect::<Vec<_>>(), vec![&1]);
+
+        // invalid entries are reported by line number, counting csv headers
+        let csv = dir.join("txs.csv");
+        std::fs::write(&csv, format!("block_number,hash\n1,{}\n2,0x1234\n3,zz\n", hash(1)))
+            .unwrap();
+        let path = csv.to_str().unwrap();
+        let args =
+            Args::parse_from(["cryo", "transactions", "--txs-file", path, "--txs-column", "hash"]);
+        match parse_txs_file(&args, path, &warnings) {
+            Err(ParseError::ParseError(message)) => assert!(message.ends_with("on lines 3, 4")),
+            _ => panic!("expected invalid lines"),
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_chunk_order() {
+        let order = |command: &str| {
+            parse_chunk_order(&Args::parse_from(command.split_whitespace())).map_err(|_| ())
+        };
+        assert_eq!(order("cryo blocks"), Ok("normal"));
+        assert_eq!(order("cryo blocks --reverse"), Ok("reverse"));
+        assert_eq!(order("cryo blocks --reverse --chunk-order reverse"), Ok("reverse"));
+        assert_eq!(order("cryo blocks --chunk-order random"), Ok("random"));
+        assert_eq!(order("cryo blocks --reverse --chunk-order random"), Err(()));
+        assert_eq!(order("cryo blocks --chunk-order sideways"), Err(()));
+    }
+}
@@ -1,8 +1,24 @@
-use super::{parse_schemas, partitions};
+use super::{
+    parse_four_byte_aggregation, parse_schemas,
+    parse_utils::{
+        hex_string_to_binary, hex_strings_to_binary, invalid_lines_error, parse_binary_arg,
+        read_list_file, BinaryInputList,
+    },
+    partitions,
+};
 use crate::args::Args;
-use cryo_freeze::{Dim, Fetcher, ParseError, Query, QueryLabels, Schemas};
+use cryo_freeze::{
+    AddressLabels, CollectWarnings, Datatype, Dim, Fetcher, HashVerification, LogFilter,
+    OnBlockError, ParseError, Query, QueryLabels, ResolvedTopic, Schemas, SignatureResolver,
+    SlotFilter, TraceContractFilter, TraceFilterSide,
+};
 use ethers::prelude::*;
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 /// parse Query struct from cli Args
 pub async fn parse_query<P: JsonRpcClient>(
@@ -11,15 +27,33 @@ pub async fn parse_query<P: JsonRpcClient>(
 ) -> Result<Query, ParseError> {
     let (datatypes, schemas) = parse_schemas(args)?;
 
+    let warnings = CollectWarnings::default();
+    let (file_args, address_labels) = parse_address_files(args, &warnings)?;
+    let args = file_args.as_ref().unwrap_or(args);
+    let (topic_args, resolved_topics) = parse_topic_args(args)?;
+    let args = topic_args.as_ref().unwrap_or(args);
+
     let arg_aliases = find_arg_aliases(args, &schemas);
     let new_args =
         if !arg_aliases.is_empty() { Some(apply_arg_aliases(args, arg_aliases)?) } else { None };
     let args = new_args.as_ref().unwrap_or(args);
+    let mut resolved_topics = resolved_topics;
+    let (log_args, log_filters) = parse_log_filters(args, &schemas, &mut resolved_topics)?;
+    let args = log_args.as_ref().unwrap_or(args);
 
-    let (partitions, partitioned_by, time_dimension) =
-        partitions::parse_partitions(args, fetcher, &schemas).await?;
+    let (partitions, partitioned_by, time_dimension, timestamp_ranges, time_buckets) =
+        partitions::parse_partitions(args, fetcher, &schemas, &warnings).await?;
     let datatypes = cryo_freeze::cluster_datatypes(datatypes);
-    let labels = QueryLabels { align: args.align, reorg_buffer: args.reorg_buffer };
+    let labels = QueryLabels {
+        align: args.align,
+        reorg_buffer: args.reorg_buffer,
+        chunk_order: partitions::parse_chunk_order(args)?.to_string(),
+        timestamp_ranges,
+        time_buckets,
+        resolved_topics,
+    };
+    let signature_resolver = parse_signature_resolver(args, &schemas)?;
+    let trace_contract_filter = parse_trace_contract_filter(args, &schemas)?;
     Ok(Query {
         datatypes,
         schemas,
@@ -27,11 +61,437 @@ pub async fn parse_query<P: JsonRpcClient>(
         partitions,
         partitioned_by,
         exclude_failed: args.exclude_failed,
-        js_tracer: args.js_tracer.clone(),
+        js_tracer: parse_js_tracer(args)?,
+        signature_resolver,
+        merge_block: args.merge_block,
+        precompiles: parse_precompiles(args)?,
+        four_byte_aggregation: parse_four_byte_aggregation(args)?,
+        changes_only: args.changes_only,
+        snapshot_interval: parse_interval(&args.interval)?,
+        n_snapshots: args.snapshots,
+        slot_filter: parse_slot_filter(args)?,
+        trace_contract_filter,
+        log_filters,
+        address_labels,
+        state_override: parse_state_override(args)?,
+        eth_call_return_types: parse_return_types(args)?,
+        multicall_batch: parse_multicall_batch(args)?,
+        verify_hashes: parse_verify_hashes(args)?,
+        block_hashes: Default::default(),
+        warnings,
+        token_decimals: Default::default(),
+        tracer_error: Default::default(),
+        on_block_error: parse_on_block_error(args)?,
+        block_errors: Default::default(),
+        timings: Default::default(),
         labels,
     })
 }
 
+fn parse_verify_hashes(args: &Args) -> Result<Option<HashVerification>, ParseError> {
+    match args.verify_hashes.as_deref() {
+        None => Ok(None),
+        Some("error") => Ok(Some(HashVerification::Error)),
+        Some("warn") => Ok(Some(HashVerification::Warn)),
+        Some(_) => Err(ParseError::ParseError("--verify-hashes must be error or warn".to_string())),
+    }
+}
+
+fn parse_on_block_error(args: &Args) -> Result<OnBlockError, ParseError> {
+    match args.on_block_error.as_deref() {
+        None | Some("fail") => Ok(OnBlockError::Fail),
+        Some("skip") => Ok(OnBlockError::Skip),
+        Some("null") => Ok(OnBlockError::Null),
+        Some(_) => {
+            Err(ParseError::ParseError("--on-block-error must be fail, skip, or null".to_string()))
+        }
+    }
+}
+
+fn parse_precompiles(args: &Args) -> Result<Option<Vec<Vec<u8>>>, ParseError> {
+    let precompiles = match &args.precompiles {
+        Some(precompiles) => hex_strings_to_binary(precompiles)?,
+        None => return Ok(None),
+    };
+    if precompiles.iter().any(|address| address.len() != 20) {
+        return Err(ParseError::ParseError("precompiles must be 20 byte addresses".to_string()))
+    }
+    Ok(Some(precompiles))
+}
+
+/// add the addresses of --address-file and --contract-file to args, along with their labels
+fn parse_address_files(
+    args: &Args,
+    warnings: &CollectWarnings,
+) -> Result<(Option<Args>, Option<AddressLabels>), ParseError> {
+    if args.address_file.is_none() && args.contract_file.is_none() {
+        return Ok((None, None))
+    }
+    let mut args = args.clone();
+    let mut labels = HashMap::new();
+    if let Some(path) = &args.address_file {
+        let addresses = read_address_file(path, &mut labels, warnings)?;
+        args.address.get_or_insert_with(Vec::new).extend(addresses);
+    }
+    if let Some(path) = &args.contract_file {
+        let contracts = read_address_file(path, &mut labels, warnings)?;
+        args.contract.get_or_insert_with(Vec::new).extend(contracts);
+    }
+    let labels = if labels.is_empty() { None } else { Some(AddressLabels::new(labels)) };
+    Ok((Some(args), labels))
+}
+
+/// read distinct addresses of an address file as hex strings, recording their labels
+fn read_address_file(
+    path: &str,
+    labels: &mut HashMap<Vec<u8>, String>,
+    warnings: &CollectWarnings,
+) -> Result<Vec<String>, ParseError> {
+    let mut addresses = Vec::new();
+    let mut seen = HashSet::new();
+    let mut invalid_lines = Vec::new();
+    for entry in read_list_file(path, None)?.into_iter() {
+        let address = match entry.value.as_deref().and_then(parse_address) {
+            Some(address) => address,
+            None => {
+                invalid_lines.push(entry.line);
+                continue
+            }
+        };
+        if let Some(label) = entry.label {
+            labels.insert(address.clone(), label);
+        }
+        if seen.insert(address.clone()) {
+            addresses.push(format!("0x{}", hex::encode(address)));
+        } else {
+            warnings.increment("duplicate addresses removed from address file");
+        }
+    }
+    if !invalid_lines.is_empty() {
+        return Err(invalid_lines_error("addresses", path, &invalid_lines))
+    }
+    if addresses.is_empty() {
+        return Err(ParseError::ParseError(format!("no addresses in {}", path)))
+    }
+    Ok(addresses)
+}
+
+/// parse address given in lowercase, uppercase, or checksummed form
+fn parse_address(value: &str) -> Option<Vec<u8>> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    let address = hex::decode(digits).ok().filter(|address| address.len() == 20)?;
+    let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) &&
+        digits.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case &&
+        ethers::utils::to_checksum(&H160::from_slice(&address), None) != format!("0x{}", digits)
+    {
+        return None
+    }
+    Some(address)
+}
+
+/// replace event signatures given as topic0 with their hashes, and addresses given as other
+/// topics with their left-padded forms
+fn parse_topic_args(args: &Args) -> Result<(Option<Args>, Vec<ResolvedTopic>), ParseError> {
+    let mut new_args = args.clone();
+    let mut resolved_topics = Vec::new();
+    for (name, topics) in [
+        ("topic0", &mut new_args.topic0),
+        ("topic1", &mut new_args.topic1),
+        ("topic2", &mut new_args.topic2),
+        ("topic3", &mut new_args.topic3),
+    ] {
+        for input in topics.iter_mut().flatten() {
+            if let Some(value) = resolve_topic(name, input)? {
+                let value = format!("0x{}", hex::encode(value));
+                let input = std::mem::replace(input, value.clone());
+                resolved_topics.push(ResolvedTopic { topic: name.to_string(), input, value });
+            }
+        }
+    }
+    match resolved_topics.is_empty() {
+        true => Ok((None, resolved_topics)),
+        false => Ok((Some(new_args), resolved_topics)),
+    }
+}
+
+/// resolve event signature given as topic0, or address given as another topic, into a 32 byte
+/// topic
+fn resolve_topic(name: &str, input: &str) -> Result<Option<Vec<u8>>, ParseError> {
+    if name == "topic0" && input.contains('(') {
+        return Ok(Some(partitions::parse_event_topic(input)?))
+    }
+    match hex_string_to_binary(input) {
+        Ok(address) if address.len() == 20 && name != "topic0" => {
+            Ok(Some([vec![0; 12], address].concat()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// translate address and topic filters of logs into filter sets, so that multiple values of each
+/// position are matched server-side by array filters instead of by one request per value
+fn parse_log_filters(
+    args: &Args,
+    schemas: &Schemas,
+    resolved_topics: &mut Vec<ResolvedTopic>,
+) -> Result<(Option<Args>, Option<Vec<LogFilter>>), ParseError> {
+    let logs_only =
+        !schemas.is_empty() && schemas.keys().all(|datatype| *datatype == Datatype::Logs);
+    let inputs = [
+        (&args.address, "address"),
+        (&args.topic0, "topic0"),
+        (&args.topic1, "topic1"),
+        (&args.topic2, "topic2"),
+        (&args.topic3, "topic3"),
+    ];
+    if let Some(filter_sets) = &args.filter_set {
+        if !logs_only {
+            return Err(ParseError::ParseError("--filter-set is only supported by logs".to_string()))
+        }
+        if inputs.iter().any(|(input, _)| input.is_some()) {
+            return Err(ParseError::ParseError(
+                "--filter-set cannot be combined with --address or --topic filters".to_string(),
+            ))
+        }
+        let filter_sets = filter_sets
+            .iter()
+            .map(|filter_set| parse_filter_set(filter_set, resolved_topics))
+            .collect::<Result<_, _>>()?;
+        return Ok((None, Some(filter_sets)))
+    }
+    if !logs_only || inputs.iter().all(|(input, _)| input.is_none()) {
+        return Ok((None, None))
+    }
+
+    let mut values = Vec::new();
+    for (input, column) in inputs.iter() {
+        let parsed = match input {
+            Some(input) => parse_binary_arg(input, column)?,
+            None => HashMap::new(),
+        };
+        // outputs of inputs read from files are partitioned by file
+        if parsed.keys().any(|key| key != &BinaryInputList::Explicit) {
+            return Ok((None, None))
+        }
+        values.push(parsed.into_values().flatten().collect::<Vec<_>>());
+    }
+    let mut values = values.into_iter();
+    let mut next = || values.next().unwrap_or_default();
+    let filter = LogFilter { addresses: next(), topics: [next(), next(), next(), next()] };
+    validate_log_filter(&filter)?;
+    let args = Args {
+        address: None,
+        topic0: None,
+        topic1: None,
+        topic2: None,
+        topic3: None,
+        ..args.clone()
+    };
+    Ok((Some(args), Some(vec![filter])))
+}
+
+/// parse filter set of space-separated KEY=VALUE entries, entries of the same key match any of
+/// their values
+fn parse_filter_set(
+    filter_set: &str,
+    resolved_topics: &mut Vec<ResolvedTopic>,
+) -> Result<LogFilter, ParseError> {
+    let mut filter = LogFilter::default();
+    for token in filter_set.split_whitespace() {
+        let (key, value) = token.split_once('=').ok_or_else(|| {
+            ParseError::ParseError(format!("filter set entries must be KEY=VALUE: {}", token))
+        })?;
+        let value = value.trim_matches('"');
+        let name = match key {
+            "address" | "contract" => {
+                filter.addresses.push(hex_string_to_binary(value)?);
+                continue
+            }
+            "topic0" | "event" => "topic0",
+            "topic1" | "topic2" | "topic3" => key,
+            _ => {
+                return Err(ParseError::ParseError(format!(
+                "invalid filter set key {}, use address, event, topic0, topic1, topic2, or topic3",
+                key
+            )))
+            }
+        };
+        let topic = match resolve_topic(name, value)? {
+            Some(topic) => {
+                let resolved = format!("0x{}", hex::encode(&topic));
+                let input = value.to_string();
+                resolved_topics.push(ResolvedTopic {
+                    topic: name.to_string(),
+                    input,
+                    value: resolved,
+                });
+                topic
+            }
+            None => hex_string_to_binary(value)?,
+        };
+        let position = match name {
+            "topic0" => 0,
+            "topic1" => 1,
+            "topic2" => 2,
+            _ => 3,
+        };
+        filter.topics[position].push(topic);
+    }
+    validate_log_filter(&filter)?;
+    Ok(filter)
+}
+
+fn validate_log_filter(filter: &LogFilter) -> Result<(), ParseError> {
+    if filter.addresses.iter().any(|address| address.len() != 20) {
+        return Err(ParseError::ParseError("log filter addresses must be 20 bytes".to_string()))
+    }
+    if filter.topics.iter().flatten().any(|topic| topic.len() != 32) {
+        return Err(ParseError::ParseError("log filter topics must be 32 bytes".to_string()))
+    }
+    Ok(())
+}
+
+fn parse_slot_filter(args: &Args) -> Result<Option<SlotFilter>, ParseError> {
+    let mut slots = Vec::new();
+    let mut prefixes = Vec::new();
+    if let Some(slot) = &args.slot {
+        slots.extend(parse_binary_arg(slot, "slot")?.into_values().flatten());
+    }
+    if let Some(path) = &args.slots_file {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_| ParseError::ParseError(format!("could not read slots file: {}", path)))?;
+        for line in contents.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            match line.strip_suffix('*') {
+                Some(prefix) => prefixes.push(hex_string_to_binary(prefix)?),
+                None => slots.push(hex_string_to_binary(line)?),
+            }
+        }
+    }
+    if slots.is_empty() && prefixes.is_empty() {
+        return Ok(None)
+    }
+    Ok(Some(SlotFilter::new(slots, prefixes)))
+}
+
+fn parse_trace_contract_filter(
+    args: &Args,
+    schemas: &Schemas,
+) -> Result<Option<TraceContractFilter>, ParseError> {
+    let side = match args.trace_filter_side.as_deref() {
+        None | Some("either") => TraceFilterSide::Either,
+        Some("from") => TraceFilterSide::From,
+        Some("to") => TraceFilterSide::To,
+        Some(_) => {
+            return Err(ParseError::ParseError(
+                "trace filter side must be one of from, to, either".to_string(),
+            ))
+        }
+    };
+    let traced = [Datatype::Traces, Datatype::VmTraces, Datatype::GethCalls];
+    let contract = match &args.contract {
+        Some(contract) if traced.iter().any(|datatype| schemas.contains_key(datatype)) => contract,
+        _ => return Ok(None),
+    };
+    let contracts: Vec<Vec<u8>> =
+        parse_binary_arg(contract, "contract_address")?.into_values().flatten().collect();
+    if contracts.iter().any(|contract| contract.len() != 20) {
+        return Err(ParseError::ParseError("contracts must be 20 byte addresses".to_string()))
+    }
+    Ok(Some(TraceContractFilter { contracts: contracts.into_iter().collect(), side }))
+}
+
+/// tracers given as a path to an existing file are read from that file
+fn parse_js_tracer(args: &Args) -> Result<Option<String>, ParseError> {
+    match &args.js_tracer {
+        Some(tracer) if std::path::Path::new(tracer).is_file() => std::fs::read_to_string(tracer)
+            .map(Some)
+            .map_err(|_| ParseError::ParseError(format!("could not read js tracer: {}", tracer))),
+        tracer => Ok(tracer.clone()),
+    }
+}
+
+fn parse_multicall_batch(args: &Args) -> Result<Option<usize>, ParseError> {
+    match args.multicall_batch {
+        Some(0) => Err(ParseError::ParseError("--multicall-batch must be positive".to_string())),
+        batch => Ok(batch),
+    }
+}
+
+fn parse_return_types(args: &Args) -> Result<Option<Vec<abi::ParamType>>, ParseError> {
+    let return_type = match &args.return_type {
+        Some(return_type) => return_type.trim(),
+        None => return Ok(None),
+    };
+    // a type list is read as a tuple so that multiple return values can be given
+    let return_type = format!("({})", return_type);
+    match abi::param_type::Reader::read(&return_type) {
+        Ok(abi::ParamType::Tuple(types)) => Ok(Some(types)),
+        _ => Err(ParseError::ParseError(format!("could not parse return type: {}", return_type))),
+    }
+}
+
+fn parse_state_override(args: &Args) -> Result<Option<spoof::State>, ParseError> {
+    let path = match &args.state_override {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let contents = std::fs::read_to_string(path)
+        .map_err(|_| ParseError::ParseError(format!("could not read state override: {}", path)))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| ParseError::ParseError(format!("invalid state override ({})", e)))
+}
+
+fn parse_interval(interval: &Option<String>) -> Result<Option<Duration>, ParseError> {
+    let interval = match interval {
+        Some(interval) => interval.trim(),
+        None => return Ok(None),
+    };
+    let (number, unit_ms) = if let Some(number) = interval.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = interval.strip_suffix('s') {
+        (number, 1_000.0)
+    } else if let Some(number) = interval.strip_suffix('m') {
+        (number, 60_000.0)
+    } else if let Some(number) = interval.strip_suffix('h') {
+        (number, 3_600_000.0)
+    } else {
+        (interval, 1_000.0)
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| ParseError::ParseError(format!("invalid interval: {}", interval)))?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(ParseError::ParseError(format!("invalid interval: {}", interval)))
+    }
+    Ok(Some(Duration::from_millis((number * unit_ms) as u64)))
+}
+
+fn parse_signature_resolver(
+    args: &Args,
+    schemas: &Schemas,
+) -> Result<Option<Arc<SignatureResolver>>, ParseError> {
+    let needs_resolver = schemas
+        .get(&Datatype::FourByteCounts)
+        .map(|schema| schema.has_column("signature_text"))
+        .unwrap_or(false);
+    if !needs_resolver || (args.sig_db.is_none() && !args.sig_api) {
+        return Ok(None)
+    }
+    let resolver = match &args.sig_db {
+        Some(path) => SignatureResolver::from_csv(path)?,
+        None => SignatureResolver::default(),
+    };
+    let resolver = if args.sig_api {
+        let cache_path = PathBuf::from(&args.output_dir).join(".cryo").join("signatures.json");
+        resolver.with_api_cache(cache_path)?
+    } else {
+        resolver
+    };
+    Ok(Some(Arc::new(resolver)))
+}
+
 fn find_arg_aliases(args: &Args, schemas: &Schemas) -> Vec<(Dim, Dim)> {
     // does not currently handle optional args, just required args
     let mut swaps = Vec::new();
@@ -123,3 +583,92 @@ fn apply_arg_aliases(args: &Args, arg_aliases: Vec<(Dim, Dim)>) -> Result<Args,
     }
     Ok(args)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_address_file() {
+        let dir =
+            std::env::temp_dir().join(format!("cryo_address_file_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let lowercase = "0xdac17f958d2ee523a2206206994597c13d831ec7";
+        let path = dir.join("addresses.csv");
+        std::fs::write(&path, format!("address,name\n{},alice\n{},\n", checksummed, lowercase))
+            .unwrap();
+        let mut labels = HashMap::new();
+        let warnings = CollectWarnings::default();
+        let addresses = read_address_file(path.to_str().unwrap(), &mut labels, &warnings).unwrap();
+        assert_eq!(addresses, vec![checksummed.to_lowercase(), lowercase.to_string()]);
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels.get(&hex_string_to_binary(checksummed).unwrap()).unwrap(), "alice");
+
+        // malformed addresses and invalid checksums are rejected by line number
+        let path = dir.join("addresses.txt");
+        let bad_checksum = checksummed.replace('a', "A");
+        std::fs::write(&path, format!("{}\n\n0x1234\n{}\n", lowercase, bad_checksum)).unwrap();
+        match read_address_file(path.to_str().unwrap(), &mut labels, &warnings) {
+            Err(ParseError::ParseError(message)) => assert!(message.ends_with("on lines 3, 4")),
+            _ => panic!("expected invalid lines"),
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_topic_args() {
+        let address = "0xdac17f958d2ee523a2206206994597c13d831ec7";
+        let args = Args {
+            topic0: Some(vec!["Transfer(address,address,uint256)".to_string()]),
+            topic2: Some(vec![address.to_string()]),
+            ..Default::default()
+        };
+        let (args, resolved) = parse_topic_args(&args).unwrap();
+        let transfer = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+        assert_eq!(args.as_ref().unwrap().topic0, Some(vec![transfer.to_string()]));
+        let padded = format!("0x000000000000000000000000{}", &address[2..]);
+        assert_eq!(args.unwrap().topic2, Some(vec![padded]));
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].input, "Transfer(address,address,uint256)");
+
+        // 32 byte topics are unchanged
+        let args = Args { topic1: Some(vec![transfer.to_string()]), ..Default::default() };
+        assert!(parse_topic_args(&args).unwrap().0.is_none());
+    }
+
+    #[test]
+    fn test_parse_log_filters() {
+        let address = "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string();
+        let topic = format!("0x{}", hex::encode([1; 32]));
+        let args = Args {
+            datatype: vec!["logs".to_string()],
+            address: Some(vec![address.clone()]),
+            topic0: Some(vec![topic.clone(), format!("0x{}", hex::encode([2; 32]))]),
+            ..Default::default()
+        };
+        let (_, schemas) = parse_schemas(&args).unwrap();
+        let mut resolved = Vec::new();
+        let (args, filters) = parse_log_filters(&args, &schemas, &mut resolved).unwrap();
+        // multiple values of a position are matched by one filter instead of one request each
+        let filters = filters.unwrap();
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].addresses.len(), 1);
+        assert_eq!(filters[0].topics[0].len(), 2);
+        assert!(args.unwrap().topic0.is_none());
+
+        let filter_set = format!(
+            "address={} topic2={} event=Transfer(address,address,uint256)",
+            address, address
+        );
+        let filter = parse_filter_set(&filter_set, &mut resolved).unwrap();
+        assert_eq!(
+            filter.topics[2],
+            vec![[vec![0; 12], hex_string_to_binary(&address).unwrap()].concat()]
+        );
+        assert_eq!(filter.topics[0].len(), 1);
+        assert_eq!(resolved.len(), 2);
+        assert!(parse_filter_set("topic1=0x1234", &mut resolved).is_err());
+        assert!(parse_filter_set("block=1", &mut resolved).is_err());
+    }
+}
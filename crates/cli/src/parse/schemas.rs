@@ -1,7 +1,8 @@
 use std::collections::HashMap;
 
 use cryo_freeze::{
-    ColumnEncoding, Datatype, FileFormat, LogDecoder, MultiDatatype, ParseError, Table,
+    AbiDecoder, BlockNumberType, ColumnEncoding, Datatype, FileFormat, FourByteAggregation,
+    LogDecoder, MultiDatatype, ParseError, Table,
 };
 
 use super::file_output;
@@ -31,10 +32,14 @@ pub(crate) fn parse_schemas(
 ) -> Result<(Vec<Datatype>, HashMap<Datatype, Table>), ParseError> {
     // parse inputs
     let datatypes = parse_datatypes(&args.datatype)?;
-    let sort = parse_sort_columns(&args.sort, &datatypes)?;
+    let sort = parse_sort_columns(&args.sort, args.no_sort, &datatypes)?;
     let u256_types = parse_u256_types(args)?;
+    let block_number_type = parse_block_number_type(args)?;
     let output_format = file_output::parse_output_format(args)?;
-    let binary_column_format = match args.hex | (output_format != FileFormat::Parquet) {
+    // csv and json files cannot store raw binary
+    let binary_column_format = match args.hex ||
+        !matches!(output_format, FileFormat::Parquet | FileFormat::Ipc | FileFormat::Avro)
+    {
         true => ColumnEncoding::Hex,
         false => ColumnEncoding::Binary,
     };
@@ -47,21 +52,80 @@ pub(crate) fn parse_schemas(
         None => None,
     };
 
+    let abi_decoder = match args.abi {
+        Some(ref path) => Some(AbiDecoder::from_file(path)?),
+        None if datatypes
+            .iter()
+            .any(|d| [Datatype::DecodedEvents, Datatype::DecodedCalls].contains(d)) =>
+        {
+            return Err(ParseError::ParseError(
+                "decoded_events and decoded_calls require --abi".to_string(),
+            ))
+        }
+        None => None,
+    };
+
+    // dataset-scoped entries of column args only apply to their dataset
+    let columns = parse_scoped_columns("--columns", &args.columns, &datatypes)?;
+    let include = parse_scoped_columns("--include-columns", &args.include_columns, &datatypes)?;
+    let exclude = parse_scoped_columns("--exclude-columns", &args.exclude_columns, &datatypes)?;
+
+    // per-transaction columns of four_byte_counts are empty when aggregating by range
+    let range_columns: Option<Vec<String>> = match parse_four_byte_aggregation(args)? {
+        FourByteAggregation::Range => Some(
+            ["signature", "total_count", "n_transactions", "first_block", "last_block", "chain_id"]
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+        ),
+        _ => None,
+    };
+
     // create schemas
     let schemas: Result<HashMap<Datatype, Table>, ParseError> = datatypes
         .iter()
         .map(|datatype| {
+            let dynamic_columns: Vec<String> = abi_decoder
+                .as_ref()
+                .map(|decoder| decoder.columns(datatype).into_keys().collect())
+                .unwrap_or_default();
+            for scoped in [&columns, &include, &exclude].iter() {
+                scoped.validate(datatype, &dynamic_columns)?;
+            }
+            let dt_columns = match (datatype, columns.for_datatype(datatype)) {
+                (Datatype::FourByteCounts, None) if range_columns.is_some() => {
+                    range_columns.clone()
+                }
+                (_, dt_columns) => dt_columns,
+            };
+            let mut dt_include = include.for_datatype(datatype);
+            // decoded outputs of eth_calls are included whenever there is a return type to
+            // decode, and logs are tagged by the filter set they matched whenever there are
+            // filter sets
+            let extra_column = match datatype {
+                Datatype::EthCalls if args.return_type.is_some() => Some("decoded_output"),
+                Datatype::Logs if args.filter_set.is_some() => Some("filter_set_index"),
+                _ => None,
+            };
+            if let Some(extra_column) = extra_column {
+                dt_include.get_or_insert_with(Vec::new).push(extra_column.to_string());
+            }
             datatype
                 .table_schema(
                     &u256_types,
                     &binary_column_format,
-                    &args.include_columns,
-                    &args.exclude_columns,
-                    &args.columns,
+                    &dt_include,
+                    &exclude.for_datatype(datatype),
+                    &dt_columns,
                     sort[datatype].clone(),
                     log_decoder.clone(),
+                    abi_decoder.clone(),
                 )
-                .map(|schema| (*datatype, schema))
+                .map(|mut schema| {
+                    schema.checksum_addresses = args.checksum_addresses;
+                    schema.set_block_number_type(block_number_type);
+                    (*datatype, schema)
+                })
                 .map_err(|e| {
                     ParseError::ParseError(format!(
                         "Failed to get schema for datatype: {:?}, {:?}",
@@ -72,16 +136,255 @@ pub(crate) fn parse_schemas(
         .collect();
 
     // make sure all included columns ended up in at least one schema
-    if let (Ok(schemas), Some(include_columns)) = (&schemas, &args.include_columns) {
+    if let (Ok(schemas), Some(include_columns)) = (&schemas, &include.unscoped) {
         ensure_included_columns(include_columns, schemas)?
     };
 
     // make sure all excluded columns are excluded from at least one schema
-    if let (Ok(schemas), Some(exclude_columns)) = (&schemas, &args.exclude_columns) {
+    if let (Ok(schemas), Some(exclude_columns)) = (&schemas, &exclude.unscoped) {
         ensure_excluded_columns(exclude_columns, schemas)?
     };
 
-    Ok((datatypes, schemas?))
+    // tags are constant columns of every dataset
+    let mut schemas = schemas?;
+    for (key, value) in parse_tags(args)?.iter() {
+        for schema in schemas.values_mut() {
+            if schema.has_column(key) || schema.datatype.column_types().contains_key(key.as_str()) {
+                return Err(ParseError::ParseError(format!(
+                    "--tag {} conflicts with a column of {}",
+                    key,
+                    schema.datatype.name()
+                )))
+            }
+            schema.set_constant_column(key, value);
+        }
+    }
+
+    // renames apply to output columns, so they are parsed once all columns are known
+    parse_column_renames(args, &datatypes, &mut schemas)?;
+
+    // avro has no datetime, decimal, or struct types
+    if matches!(output_format, FileFormat::Avro) {
+        for datatype in datatypes.iter() {
+            schemas[datatype].check_avro_output().map_err(|e| {
+                ParseError::ParseError(format!("cannot write {} as avro: {}", datatype.name(), e))
+            })?;
+        }
+    }
+
+    Ok((datatypes, schemas))
+}
+
+fn parse_column_renames(
+    args: &Args,
+    datatypes: &[Datatype],
+    schemas: &mut HashMap<Datatype, Table>,
+) -> Result<(), ParseError> {
+    let renames = parse_scoped_columns("--rename", &args.rename, datatypes)?;
+    let parse_entry = |entry: &str| match entry.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(ParseError::ParseError(format!("--rename must be OLD=NEW, got {}", entry))),
+    };
+    let unscoped: Vec<(String, String)> = renames
+        .unscoped
+        .iter()
+        .flatten()
+        .flat_map(|entry| entry.split(','))
+        .filter(|entry| !entry.is_empty())
+        .map(parse_entry)
+        .collect::<Result<_, _>>()?;
+
+    let mut output_columns: HashMap<Datatype, Vec<String>> = HashMap::new();
+    for (datatype, schema) in schemas.iter() {
+        let output_schema = schema.output_schema().map_err(|e| {
+            ParseError::ParseError(format!(
+                "could not get output columns of {}: {}",
+                datatype.name(),
+                e
+            ))
+        })?;
+        let columns = output_schema.columns.into_iter().map(|column| column.name).collect();
+        output_columns.insert(*datatype, columns);
+    }
+    for (from, _) in unscoped.iter() {
+        if !output_columns.values().any(|columns| columns.contains(from)) {
+            return Err(ParseError::ParseError(format!(
+                "--rename column {} is not an output column of any dataset",
+                from
+            )))
+        }
+    }
+
+    for (datatype, schema) in schemas.iter_mut() {
+        let columns = &output_columns[datatype];
+        let mut column_renames: Vec<(String, String)> =
+            unscoped.iter().filter(|(from, _)| columns.contains(from)).cloned().collect();
+        // scoped renames take precedence over unscoped renames of the same column
+        for entry in renames.scoped.get(datatype).into_iter().flatten() {
+            let (from, to) = parse_entry(entry)?;
+            if !columns.contains(&from) {
+                return Err(ParseError::ParseError(format!(
+                    "--rename has unknown column for {}: {}\noutput columns of {}: {}",
+                    datatype.name(),
+                    from,
+                    datatype.name(),
+                    columns.join(", ")
+                )))
+            }
+            column_renames.retain(|(renamed, _)| renamed != &from);
+            column_renames.push((from, to));
+        }
+        if args.hive_partitioning.is_some() {
+            let keys = ["timestamp", "block_number", "address"];
+            if let Some((from, _)) =
+                column_renames.iter().find(|(from, _)| keys.contains(&from.as_str()))
+            {
+                return Err(ParseError::ParseError(format!(
+                    "--rename cannot rename {}, which is used for hive partitioning",
+                    from
+                )))
+            }
+        }
+        schema.column_renames = column_renames;
+
+        let mut names = std::collections::HashSet::new();
+        for column in columns.iter() {
+            let name = schema.output_name(column);
+            if !names.insert(name) {
+                return Err(ParseError::ParseError(format!(
+                    "--rename gives {} multiple columns named {}",
+                    datatype.name(),
+                    name
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_tags(args: &Args) -> Result<Vec<(String, String)>, ParseError> {
+    args.tag
+        .iter()
+        .flatten()
+        .map(|tag| match tag.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => Err(ParseError::ParseError(format!("--tag must be KEY=VALUE, got {}", tag))),
+        })
+        .collect()
+}
+
+/// set the value of chain_name columns, which is only known once the source is parsed
+pub(crate) fn parse_chain_name(args: &Args, schemas: &mut HashMap<Datatype, Table>, chain_id: u64) {
+    let chain_name = match &args.chain_name_override {
+        Some(chain_name) => chain_name.clone(),
+        None => cryo_freeze::chain_name(chain_id),
+    };
+    for schema in schemas.values_mut() {
+        if schema.constant_columns.iter().any(|(column, _)| column == "chain_name") {
+            schema.set_constant_column("chain_name", &chain_name);
+        }
+    }
+}
+
+/// entries of a column arg, split into entries for every dataset and `DATASET:COL1,COL2` entries
+/// for one dataset
+#[derive(Debug, Default, PartialEq)]
+struct ScopedColumns {
+    name: &'static str,
+    unscoped: Option<Vec<String>>,
+    scoped: HashMap<Datatype, Vec<String>>,
+}
+
+impl ScopedColumns {
+    /// entries that apply to a datatype, scoped entries replace unscoped entries of --columns
+    fn for_datatype(&self, datatype: &Datatype) -> Option<Vec<String>> {
+        match (&self.unscoped, self.scoped.get(datatype)) {
+            (_, Some(scoped)) if self.name == "--columns" => Some(scoped.clone()),
+            (None, None) => None,
+            (unscoped, scoped) => Some(
+                unscoped.iter().flatten().chain(scoped.into_iter().flatten()).cloned().collect(),
+            ),
+        }
+    }
+
+    /// fail on scoped columns that are not columns of their dataset
+    fn validate(&self, datatype: &Datatype, dynamic_columns: &[String]) -> Result<(), ParseError> {
+        let column_types = datatype.column_types();
+        let added_columns = datatype.added_columns();
+        let is_valid = |column: &&String| {
+            column.as_str() == "all" ||
+                column_types.contains_key(column.as_str()) ||
+                added_columns.contains(&column.as_str()) ||
+                dynamic_columns.contains(column)
+        };
+        let unknown: Vec<&String> =
+            self.scoped.get(datatype).into_iter().flatten().filter(|c| !is_valid(c)).collect();
+        if unknown.is_empty() {
+            return Ok(())
+        }
+        let mut valid: Vec<&str> = column_types.keys().copied().collect();
+        valid.extend(added_columns);
+        valid.extend(dynamic_columns.iter().map(|column| column.as_str()));
+        Err(ParseError::ParseError(format!(
+            "{} has unknown columns for {}: {}\nvalid columns of {}: {}",
+            self.name,
+            datatype.name(),
+            unknown.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", "),
+            datatype.name(),
+            valid.join(", ")
+        )))
+    }
+}
+
+fn parse_scoped_columns(
+    name: &'static str,
+    raw: &Option<Vec<String>>,
+    datatypes: &[Datatype],
+) -> Result<ScopedColumns, ParseError> {
+    let raw = match raw {
+        Some(raw) => raw,
+        None => return Ok(ScopedColumns { name, ..Default::default() }),
+    };
+    let mut unscoped = Vec::new();
+    let mut scoped: HashMap<Datatype, Vec<String>> = HashMap::new();
+    for entry in raw.iter() {
+        let (dataset, columns) = match entry.split_once(':') {
+            Some(split) => split,
+            None => {
+                unscoped.push(entry.clone());
+                continue
+            }
+        };
+        let datatype = Datatype::from_str(dataset)?;
+        if !datatypes.contains(&datatype) {
+            return Err(ParseError::ParseError(format!(
+                "{} refers to {}, which is not being collected",
+                name, dataset
+            )))
+        }
+        scoped
+            .entry(datatype)
+            .or_default()
+            .extend(columns.split(',').filter(|c| !c.is_empty()).map(|c| c.to_string()));
+    }
+    // args given only with scoped entries do not apply to other datasets
+    let unscoped = match unscoped.is_empty() && !scoped.is_empty() {
+        true => None,
+        false => Some(unscoped),
+    };
+    Ok(ScopedColumns { name, unscoped, scoped })
+}
+
+pub(crate) fn parse_four_byte_aggregation(args: &Args) -> Result<FourByteAggregation, ParseError> {
+    match args.agg.as_deref() {
+        None | Some("transaction") => Ok(FourByteAggregation::Transaction),
+        Some("range") => Ok(FourByteAggregation::Range),
+        Some(_) => {
+            Err(ParseError::ParseError("--agg must be one of transaction, range".to_string()))
+        }
+    }
 }
 
 fn parse_u256_types(args: &Args) -> Result<Vec<U256Type>, ParseError> {
@@ -113,6 +416,16 @@ fn parse_u256_types(args: &Args) -> Result<Vec<U256Type>, ParseError> {
     }
 }
 
+fn parse_block_number_type(args: &Args) -> Result<BlockNumberType, ParseError> {
+    match args.block_number_type.as_ref().map(|raw| raw.to_lowercase()).as_deref() {
+        None | Some("u32") | Some("uint32") => Ok(BlockNumberType::U32),
+        Some("u64") | Some("uint64") => Ok(BlockNumberType::U64),
+        Some(_) => {
+            Err(ParseError::ParseError("--block-number-type must be one of u32, u64".to_string()))
+        }
+    }
+}
+
 fn ensure_included_columns(
     include_columns: &[String],
     schemas: &cryo_freeze::Schemas,
@@ -171,9 +484,13 @@ fn ensure_excluded_columns(
 
 fn parse_sort_columns(
     raw_sort: &Option<Vec<String>>,
-    datatypes: &Vec<Datatype>,
+    no_sort: bool,
+    datatypes: &[Datatype],
 ) -> Result<HashMap<Datatype, Option<Vec<String>>>, ParseError> {
     match raw_sort {
+        None if no_sort => {
+            Ok(HashMap::from_iter(datatypes.iter().map(|datatype| (*datatype, None))))
+        }
         None => Ok(HashMap::from_iter(
             datatypes.iter().map(|datatype| (*datatype, Some(datatype.default_sort()))),
         )),
@@ -197,3 +514,115 @@ fn parse_sort_columns(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scoped_columns() {
+        let datatypes = vec![Datatype::Blocks, Datatype::Logs];
+        let raw = Some(vec!["chain_id".to_string(), "blocks:gas_used,size".to_string()]);
+        let include = parse_scoped_columns("--include-columns", &raw, &datatypes).unwrap();
+        assert_eq!(
+            include.for_datatype(&Datatype::Blocks),
+            Some(vec!["chain_id".to_string(), "gas_used".to_string(), "size".to_string()])
+        );
+        assert_eq!(include.for_datatype(&Datatype::Logs), Some(vec!["chain_id".to_string()]));
+
+        // scoped entries of --columns replace unscoped entries
+        let raw = Some(vec!["logs:all".to_string()]);
+        let columns = parse_scoped_columns("--columns", &raw, &datatypes).unwrap();
+        assert_eq!(columns.for_datatype(&Datatype::Blocks), None);
+        assert_eq!(columns.for_datatype(&Datatype::Logs), Some(vec!["all".to_string()]));
+
+        // unknown columns and datasets fail
+        let raw = Some(vec!["blocks:number".to_string()]);
+        let columns = parse_scoped_columns("--columns", &raw, &datatypes).unwrap();
+        assert!(columns.validate(&Datatype::Blocks, &[]).is_err());
+        assert!(columns.validate(&Datatype::Logs, &[]).is_ok());
+        let raw = Some(vec!["transactions:input".to_string()]);
+        assert!(parse_scoped_columns("--exclude-columns", &raw, &datatypes).is_err());
+    }
+
+    #[test]
+    fn test_parse_constant_columns() {
+        use clap_cryo::Parser;
+        let args = Args::parse_from([
+            "cryo",
+            "logs",
+            "-i",
+            "chain_name",
+            "--tag",
+            "campaign=a=b",
+            "--chain-name-override",
+            "devnet",
+        ]);
+        let (_, mut schemas) = parse_schemas(&args).unwrap();
+        parse_chain_name(&args, &mut schemas, 1);
+        let schema = &schemas[&Datatype::Logs];
+        assert_eq!(
+            schema.constant_columns,
+            vec![
+                ("chain_name".to_string(), "devnet".to_string()),
+                ("campaign".to_string(), "a=b".to_string())
+            ]
+        );
+        assert!(schema.dynamic_columns().is_empty());
+
+        let args = Args::parse_from(["cryo", "logs", "--tag", "address=0"]);
+        assert!(parse_schemas(&args).is_err());
+        let args = Args::parse_from(["cryo", "logs", "--tag", "=0"]);
+        assert!(parse_schemas(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_column_renames() {
+        use clap_cryo::Parser;
+        let args = Args::parse_from([
+            "cryo",
+            "transactions",
+            "logs",
+            "--rename",
+            "from_address=sender,block_number=blockNumber",
+            "logs:block_number=block",
+        ]);
+        let (_, schemas) = parse_schemas(&args).unwrap();
+        let transactions = &schemas[&Datatype::Transactions];
+        assert_eq!(transactions.output_name("from_address"), "sender");
+        assert_eq!(transactions.output_name("block_number"), "blockNumber");
+        assert_eq!(schemas[&Datatype::Logs].output_name("block_number"), "block");
+        let output_schema = transactions.output_schema().unwrap();
+        let column = output_schema.columns.iter().find(|c| c.name == "sender").unwrap();
+        assert_eq!(column.renamed_from, Some("from_address".to_string()));
+
+        // renames that collide with other columns or that match no column fail
+        let args =
+            Args::parse_from(["cryo", "transactions", "--rename", "from_address=to_address"]);
+        assert!(parse_schemas(&args).is_err());
+        let args = Args::parse_from(["cryo", "transactions", "--rename", "address=contract"]);
+        assert!(parse_schemas(&args).is_err());
+        let args = Args::parse_from(["cryo", "transactions", "--rename", "from_address"]);
+        assert!(parse_schemas(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_u256_types() {
+        use clap_cryo::Parser;
+        let args = Args::parse_from(["cryo", "blocks", "--u256-types", "binary,F64", "u64"]);
+        let u256_types = parse_u256_types(&args).unwrap();
+        assert_eq!(u256_types, vec![U256Type::Binary, U256Type::F64, U256Type::U64]);
+    }
+
+    #[test]
+    fn test_parse_block_number_type() {
+        use clap_cryo::Parser;
+        let args = Args::parse_from(["cryo", "four_byte_counts", "--block-number-type", "u64"]);
+        let (_, schemas) = parse_schemas(&args).unwrap();
+        let schema = &schemas[&Datatype::FourByteCounts];
+        assert_eq!(schema.block_number_type, BlockNumberType::U64);
+        assert_eq!(schema.column_type("block_number"), Some(cryo_freeze::ColumnType::UInt64));
+        let args = Args::parse_from(["cryo", "four_byte_counts", "--block-number-type", "u16"]);
+        assert!(parse_block_number_type(&args).is_err());
+    }
+}
@@ -1,21 +1,52 @@
-use std::env;
+use std::{env, path::Path};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
 
 use ethers::prelude::*;
 use governor::{Quota, RateLimiter};
 use polars::prelude::*;
 use std::num::NonZeroU32;
 
-use cryo_freeze::{Fetcher, ParseError, Source, SourceLabels};
+use cryo_freeze::{
+    normalize_rpc_url, CollectError, EndpointOptions, EndpointPool, EndpointStrategy, Fetcher,
+    ParseError, ResponseCache, Source, SourceLabels, TraceBackend,
+};
 
+use super::file_output::parse_byte_size;
 use crate::args::Args;
 
+const DEFAULT_CACHE_SIZE: u64 = 10 << 30;
+
+/// replacement of rpc header values and auth in reports
+pub(crate) const REDACTED: &str = "<redacted>";
+
 pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
     // parse network info
-    let rpc_url = parse_rpc_url(args);
-    let provider =
-        Provider::<RetryClient<Http>>::new_client(&rpc_url, args.max_retries, args.initial_backoff)
-            .map_err(|_e| ParseError::ParseError("could not connect to provider".to_string()))?;
+    let rpc_urls = parse_rpc_urls(args)?;
+    let max_concurrent_requests = args.max_concurrent_requests.unwrap_or(100);
+    let options = EndpointOptions {
+        strategy: parse_rpc_strategy(args)?,
+        cooldown: std::time::Duration::from_secs(args.rpc_cooldown),
+        max_concurrent_requests: args.rpc_concurrency,
+        batch_size: args.rpc_batch_size,
+        autoscale: args.autoscale.then_some(max_concurrent_requests),
+        headers: parse_rpc_headers(args)?,
+    };
+    let pool = EndpointPool::connect(&rpc_urls, args.max_retries, args.initial_backoff, &options)
+        .await
+        .map_err(|e| ParseError::ParseError(e.to_string()))?;
+    let provider = Provider::new(pool);
     let chain_id = provider.get_chainid().await.map_err(ParseError::ProviderError)?.as_u64();
+    if let Some(cache_dir) = &args.cache_dir {
+        let cache = ResponseCache::open(
+            Path::new(cache_dir),
+            chain_id,
+            parse_cache_size(args)?,
+            args.reorg_buffer,
+        )
+        .map_err(|e| ParseError::ParseError(e.to_string()))?;
+        provider.as_ref().set_cache(cache);
+    }
 
     let rate_limiter = match args.requests_per_second {
         Some(rate_limit) => match (NonZeroU32::new(1), NonZeroU32::new(rate_limit)) {
@@ -29,7 +60,6 @@ pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
     };
 
     // process concurrency info
-    let max_concurrent_requests = args.max_concurrent_requests.unwrap_or(100);
     let max_concurrent_chunks = match args.max_concurrent_chunks {
         Some(0) => None,
         Some(max) => Some(max),
@@ -39,13 +69,25 @@ pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
     let semaphore = tokio::sync::Semaphore::new(max_concurrent_requests as usize);
     let semaphore = Some(semaphore);
 
-    let fetcher = Fetcher { provider, semaphore, rate_limiter };
+    let fetcher = Fetcher {
+        provider,
+        semaphore,
+        rate_limiter,
+        request_counts: Default::default(),
+        block_timestamps: Default::default(),
+        shared_fetches: Default::default(),
+    };
     let output = Source {
         fetcher: Arc::new(fetcher),
         chain_id,
         inner_request_size: args.inner_request_size,
         max_concurrent_chunks,
-        rpc_url,
+        rpc_url: rpc_urls[0].clone(),
+        beacon_url: args.beacon_url.clone(),
+        multicall_support: Default::default(),
+        beacon_genesis: Default::default(),
+        capabilities: Default::default(),
+        trace_backend: parse_trace_backend(args)?,
         labels: SourceLabels {
             max_concurrent_requests: args.requests_per_second.map(|x| x as u64),
             max_requests_per_second: args.requests_per_second.map(|x| x as u64),
@@ -57,19 +99,183 @@ pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
     Ok(output)
 }
 
-fn parse_rpc_url(args: &Args) -> String {
-    let mut url = match &args.rpc {
-        Some(url) => url.clone(),
-        _ => match env::var("ETH_RPC_URL") {
-            Ok(url) => url,
+pub(crate) fn parse_cache_size(args: &Args) -> Result<u64, ParseError> {
+    match &args.cache_size {
+        Some(size) => parse_byte_size(size),
+        None => Ok(DEFAULT_CACHE_SIZE),
+    }
+}
+
+fn parse_rpc_urls(args: &Args) -> Result<Vec<String>, ParseError> {
+    let mut urls = args.rpc.clone().unwrap_or_default();
+    if let Some(path) = &args.rpc_file {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_| ParseError::ParseError(format!("could not read rpc file {}", path)))?;
+        urls.extend(
+            contents
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.to_string()),
+        );
+    }
+    if urls.is_empty() {
+        match env::var("ETH_RPC_URL") {
+            Ok(url) => urls.push(url),
             Err(_e) => {
                 println!("must provide --rpc or set ETH_RPC_URL");
                 std::process::exit(0);
             }
-        },
-    };
-    if !url.starts_with("http") {
-        url = "http://".to_string() + url.as_str();
+        }
     };
-    url
+    let mut parsed: Vec<String> = Vec::new();
+    for url in urls.into_iter() {
+        let url = normalize_rpc_url(&url);
+        if !parsed.contains(&url) {
+            parsed.push(url);
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_rpc_headers(args: &Args) -> Result<HeaderMap, ParseError> {
+    let mut headers = HeaderMap::new();
+    for header in args.rpc_header.iter().flatten() {
+        let (name, value) = match header.split_once(':') {
+            Some((name, value)) => (name.trim(), expand_env_vars(value.trim())?),
+            None => {
+                return Err(ParseError::ParseError(format!(
+                    "invalid --rpc-header {}, use \"NAME: VALUE\"",
+                    header
+                )))
+            }
+        };
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| ParseError::ParseError(format!("invalid --rpc-header name {}", name)))?;
+        headers.append(name, sensitive_header_value(&value)?);
+    }
+    if let Some(auth) = &args.rpc_auth {
+        let auth = expand_env_vars(auth)?;
+        let (user, pass) = auth.split_once(':').unwrap_or((&auth, ""));
+        let value = Authorization::basic(user, pass).to_string();
+        headers.insert(AUTHORIZATION, sensitive_header_value(&value)?);
+    }
+    Ok(headers)
+}
+
+/// header value that is hidden from debug output
+fn sensitive_header_value(value: &str) -> Result<HeaderValue, ParseError> {
+    let mut value = HeaderValue::from_str(value)
+        .map_err(|_| ParseError::ParseError("invalid --rpc-header value".to_string()))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// replace each `${NAME}` with the value of environment variable NAME
+fn expand_env_vars(value: &str) -> Result<String, ParseError> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err(ParseError::ParseError(format!("unclosed ${{ in {}", value))),
+        };
+        let name = &rest[start + 2..end];
+        let var = env::var(name).map_err(|_| {
+            ParseError::ParseError(format!("environment variable {} is not set", name))
+        })?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// command line and args with the values of rpc headers and auth replaced, for reports
+pub(crate) fn redact_rpc_secrets(args: &Args, cli_command: Vec<String>) -> (Args, Vec<String>) {
+    let mut args = args.clone();
+    args.rpc_header =
+        args.rpc_header.map(|headers| headers.iter().map(|header| redact_header(header)).collect());
+    args.rpc_auth = args.rpc_auth.map(|_| REDACTED.to_string());
+
+    let mut redacted = Vec::new();
+    let mut options = cli_command.into_iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--rpc-header" => {
+                redacted.push(option);
+                redacted.extend(options.next().map(|header| redact_header(&header)));
+            }
+            "--rpc-auth" => {
+                redacted.push(option);
+                redacted.extend(options.next().map(|_| REDACTED.to_string()));
+            }
+            _ if option.starts_with("--rpc-header=") => {
+                redacted.push(format!("--rpc-header={}", redact_header(&option[13..])))
+            }
+            _ if option.starts_with("--rpc-auth=") => {
+                redacted.push(format!("--rpc-auth={}", REDACTED))
+            }
+            _ => redacted.push(option),
+        }
+    }
+    (args, redacted)
+}
+
+fn redact_header(header: &str) -> String {
+    match header.split_once(':') {
+        Some((name, _)) => format!("{}: {}", name, REDACTED),
+        None => REDACTED.to_string(),
+    }
+}
+
+fn parse_rpc_strategy(args: &Args) -> Result<EndpointStrategy, ParseError> {
+    match args.rpc_strategy.as_deref() {
+        None | Some("round-robin") => Ok(EndpointStrategy::RoundRobin),
+        Some("least-in-flight") => Ok(EndpointStrategy::LeastInFlight),
+        Some(strategy) => Err(ParseError::ParseError(format!(
+            "invalid --rpc-strategy {}, use round-robin or least-in-flight",
+            strategy
+        ))),
+    }
+}
+
+fn parse_trace_backend(args: &Args) -> Result<TraceBackend, ParseError> {
+    match &args.trace_backend {
+        Some(backend) => {
+            backend.parse().map_err(|e: CollectError| ParseError::ParseError(e.to_string()))
+        }
+        None => Ok(TraceBackend::Auto),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_env_vars() {
+        env::set_var("CRYO_TEST_RPC_TOKEN", "abc");
+        assert_eq!(expand_env_vars("Bearer ${CRYO_TEST_RPC_TOKEN}").unwrap(), "Bearer abc");
+        assert_eq!(expand_env_vars("${CRYO_TEST_RPC_TOKEN}:x").unwrap(), "abc:x");
+        assert!(expand_env_vars("${CRYO_TEST_UNSET_TOKEN}").is_err());
+        assert!(expand_env_vars("${CRYO_TEST_RPC_TOKEN").is_err());
+    }
+
+    #[test]
+    fn test_redact_rpc_secrets() {
+        let args = Args {
+            rpc_header: Some(vec!["Authorization: Bearer abc".to_string()]),
+            rpc_auth: Some("user:pass".to_string()),
+            ..Default::default()
+        };
+        let cli_command = ["cryo", "--rpc-header", "X-Key: abc", "--rpc-auth=user:pass", "-v"];
+        let (args, cli_command) =
+            redact_rpc_secrets(&args, cli_command.iter().map(|x| x.to_string()).collect());
+        assert_eq!(args.rpc_header, Some(vec!["Authorization: <redacted>".to_string()]));
+        assert_eq!(args.rpc_auth, Some("<redacted>".to_string()));
+        let expected = ["cryo", "--rpc-header", "X-Key: <redacted>", "--rpc-auth=<redacted>", "-v"];
+        assert_eq!(cli_command, expected);
+    }
 }
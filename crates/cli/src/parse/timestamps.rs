@@ -1,6 +1,7 @@
-use cryo_freeze::{BlockChunk, Fetcher, ParseError};
+use cryo_freeze::{BlockChunk, ChunkData, Fetcher, ParseError, TimeBucket, TimestampRange};
 use ethers::prelude::*;
 use polars::prelude::*;
+use std::collections::BTreeMap;
 
 use crate::{
     parse::blocks::{block_range_to_block_chunk, postprocess_block_chunks},
@@ -9,13 +10,15 @@ use crate::{
 
 use super::blocks::get_latest_block_number;
 
+type TimestampChunks = (Option<Vec<Option<String>>>, Option<Vec<BlockChunk>>, Vec<TimestampRange>);
+
 pub(crate) async fn parse_timestamps<P: JsonRpcClient>(
     args: &Args,
     fetcher: Arc<Fetcher<P>>,
-) -> Result<(Option<Vec<Option<String>>>, Option<Vec<BlockChunk>>), ParseError> {
+) -> Result<TimestampChunks, ParseError> {
     let (files, explicit_numbers): (Vec<&String>, Vec<&String>) = match &args.timestamps {
         Some(timestamp) => timestamp.iter().partition(|tx| std::path::Path::new(tx).exists()),
-        None => return Ok((None, None)),
+        None => return Ok((None, None, Vec::new())),
     };
 
     let (file_labels, file_chunks) = if !files.is_empty() {
@@ -24,7 +27,7 @@ pub(crate) async fn parse_timestamps<P: JsonRpcClient>(
         for path in files {
             let column = if path.contains(':') {
                 path.split(':')
-                    .last()
+                    .next_back()
                     .ok_or(ParseError::ParseError("could not parse txs path column".to_string()))?
             } else {
                 "timestamp"
@@ -35,7 +38,8 @@ pub(crate) async fn parse_timestamps<P: JsonRpcClient>(
             let chunk_label = path
                 .split("__")
                 .last()
-                .and_then(|s| s.strip_suffix(".parquet").map(|s| s.to_string()));
+                .and_then(|s| s.strip_suffix(".parquet").or(s.strip_suffix(".arrow")))
+                .map(|s| s.to_string());
             file_labels.push(chunk_label);
             file_chunks.push(chunk);
         }
@@ -44,12 +48,17 @@ pub(crate) async fn parse_timestamps<P: JsonRpcClient>(
         (None, None)
     };
 
+    let mut timestamp_ranges = Vec::new();
     let explicit_chunks = if !explicit_numbers.is_empty() {
         // parse inputs into BlockChunks
+        let mut cache = BlockTimestamps::new(&fetcher, args.reorg_buffer).await?;
         let mut block_chunks = Vec::new();
         for explicit_number in explicit_numbers {
-            let outputs = parse_timestamp_inputs(explicit_number, &fetcher).await?;
-            block_chunks.extend(outputs);
+            let outputs = parse_timestamp_inputs(explicit_number, &mut cache, &fetcher).await?;
+            for (block_chunk, timestamp_range) in outputs.into_iter() {
+                block_chunks.push(block_chunk);
+                timestamp_ranges.push(timestamp_range);
+            }
         }
         postprocess_block_chunks(block_chunks, args, fetcher).await?
     } else {
@@ -68,17 +77,159 @@ pub(crate) async fn parse_timestamps<P: JsonRpcClient>(
         _ => None,
     };
     block_chunks.extend(explicit_chunks);
-    Ok((labels, Some(block_chunks)))
+    Ok((labels, Some(block_chunks), timestamp_ranges))
 }
 
-fn read_integer_column(path: &str, column: &str) -> Result<Vec<u64>, ParseError> {
-    let file = std::fs::File::open(path)
-        .map_err(|_e| ParseError::ParseError("could not open file path".to_string()))?;
+type BucketChunks = (Vec<Option<String>>, Vec<BlockChunk>, Vec<TimeBucket>);
+
+/// split block ranges into one chunk per calendar day, hour, or week of block timestamps in UTC,
+/// labeled with the date of the bucket and whether the range only covers part of it
+pub(crate) async fn parse_time_buckets<P: JsonRpcClient>(
+    unit: &str,
+    block_chunks: Vec<BlockChunk>,
+    args: &Args,
+    fetcher: &Fetcher<P>,
+) -> Result<BucketChunks, ParseError> {
+    let unit = match unit {
+        "day" | "hour" | "week" => unit,
+        _ => {
+            return Err(ParseError::ParseError(
+                "invalid --chunk-by, use day, hour, or week".to_string(),
+            ))
+        }
+    };
+    let mut cache = BlockTimestamps::new(fetcher, args.reorg_buffer).await?;
+    let latest_timestamp = get_latest_timestamp(&mut cache, fetcher).await?;
+    let mut labels = Vec::new();
+    let mut chunks = Vec::new();
+    let mut buckets = Vec::new();
+    for (first_block, last_block) in contiguous_ranges(&block_chunks)? {
+        let mut start = bucket_start(cache.timestamp(first_block, fetcher).await?, unit);
+        let last_timestamp = cache.timestamp(last_block, fetcher).await?;
+        let mut bucket_first_block =
+            first_block_after_timestamp(start, &mut cache, fetcher).await?;
+        while start <= last_timestamp {
+            let end = next_bucket_start(start, unit);
+            let next_first_block = first_block_after_timestamp(end, &mut cache, fetcher).await?;
+            if let Some(bucket_last_block) = next_first_block.checked_sub(1) {
+                let start_block = bucket_first_block.max(first_block);
+                let end_block = bucket_last_block.min(last_block);
+                if start_block <= end_block {
+                    // buckets are partial if cut by the block range or not over yet
+                    let partial = start_block > bucket_first_block ||
+                        end_block < bucket_last_block ||
+                        end > latest_timestamp;
+                    let bucket = TimeBucket {
+                        bucket: bucket_name(start, unit),
+                        partial,
+                        start_timestamp: start,
+                        end_timestamp: end - 1,
+                        start_block,
+                        end_block,
+                    };
+                    let chunk = BlockChunk::Range(start_block, end_block);
+                    let stub = chunk
+                        .stub()
+                        .map_err(|_| ParseError::ParseError("invalid block chunk".to_string()))?;
+                    labels.push(Some(format!("{}__{}", bucket.label(), stub)));
+                    chunks.push(chunk);
+                    buckets.push(bucket);
+                }
+            }
+            start = end;
+            bucket_first_block = next_first_block;
+        }
+    }
+    Ok((labels, chunks, buckets))
+}
+
+/// merge adjacent block ranges into spans of consecutive blocks
+fn contiguous_ranges(block_chunks: &[BlockChunk]) -> Result<Vec<(u64, u64)>, ParseError> {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for chunk in block_chunks.iter() {
+        let (start, end) = match chunk {
+            BlockChunk::Range(start, end) => (*start, *end),
+            BlockChunk::Numbers(_) => {
+                return Err(ParseError::ParseError("--chunk-by requires block ranges".to_string()))
+            }
+        };
+        match ranges.last_mut() {
+            Some((_, last_end)) if *last_end + 1 == start => *last_end = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    Ok(ranges)
+}
+
+fn bucket_start(timestamp: u64, unit: &str) -> u64 {
+    match unit {
+        "hour" => timestamp - timestamp % 3600,
+        // unix time starts on a thursday, weeks start on monday
+        "week" => timestamp - (timestamp + 3 * 86400) % (7 * 86400),
+        _ => timestamp - timestamp % 86400,
+    }
+}
+
+fn next_bucket_start(start: u64, unit: &str) -> u64 {
+    match unit {
+        "hour" => start + 3600,
+        "week" => start + 7 * 86400,
+        _ => start + 86400,
+    }
+}
+
+/// name of the bucket starting at a timestamp, e.g. 2024-01-31, 2024-01-31T13, or 2024-W05
+fn bucket_name(start: u64, unit: &str) -> String {
+    let datetime = chrono::DateTime::from_timestamp(start as i64, 0).unwrap_or_default();
+    match unit {
+        "hour" => datetime.format("%Y-%m-%dT%H").to_string(),
+        "week" => datetime.format("%G-W%V").to_string(),
+        _ => datetime.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// timestamps of the blocks probed while resolving timestamps, shared by all inputs of a query
+struct BlockTimestamps {
+    /// last block that timestamps resolve to, the latest block minus the reorg buffer
+    last_block: u64,
+    /// timestamp of each probed block
+    probes: BTreeMap<u64, u64>,
+}
 
-    let df = ParquetReader::new(file)
-        .with_columns(Some(vec![column.to_string()]))
-        .finish()
-        .map_err(|_e| ParseError::ParseError("could not read data from column".to_string()))?;
+impl BlockTimestamps {
+    async fn new<P: JsonRpcClient>(
+        fetcher: &Fetcher<P>,
+        reorg_buffer: u64,
+    ) -> Result<BlockTimestamps, ParseError> {
+        let latest_block_number = get_latest_block_number(fetcher).await?;
+        Ok(BlockTimestamps {
+            last_block: latest_block_number.saturating_sub(reorg_buffer),
+            probes: BTreeMap::new(),
+        })
+    }
+
+    async fn timestamp<P: JsonRpcClient>(
+        &mut self,
+        block_number: u64,
+        fetcher: &Fetcher<P>,
+    ) -> Result<u64, ParseError> {
+        if let Some(timestamp) = self.probes.get(&block_number) {
+            return Ok(*timestamp)
+        }
+        let timestamp = fetcher
+            .get_block(block_number)
+            .await
+            .map_err(|_e| ParseError::ParseError("Error fetching block for timestamp".to_string()))?
+            .ok_or_else(|| ParseError::ParseError("block for timestamp not found".to_string()))?
+            .timestamp
+            .as_u64();
+        self.probes.insert(block_number, timestamp);
+        Ok(timestamp)
+    }
+}
+
+fn read_integer_column(path: &str, column: &str) -> Result<Vec<u64>, ParseError> {
+    let df = cryo_freeze::read_column(path, column)?;
 
     let series = df
         .column(column)
@@ -109,20 +260,21 @@ fn read_integer_column(path: &str, column: &str) -> Result<Vec<u64>, ParseError>
 /// parse timestamp numbers to freeze
 async fn parse_timestamp_inputs<P: JsonRpcClient>(
     inputs: &str,
+    cache: &mut BlockTimestamps,
     fetcher: &Fetcher<P>,
-) -> Result<Vec<BlockChunk>, ParseError> {
+) -> Result<Vec<(BlockChunk, TimestampRange)>, ParseError> {
     let parts: Vec<&str> = inputs.split(' ').collect();
     match parts.len() {
         1 => {
             let first_input = parts.first().ok_or_else(|| {
                 ParseError::ParseError("Failed to get the first input".to_string())
             })?;
-            parse_timestamp_token(first_input, true, fetcher).await.map(|x| vec![x])
+            parse_timestamp_token(first_input, true, cache, fetcher).await.map(|x| vec![x])
         }
         _ => {
             let mut chunks = Vec::new();
             for part in parts {
-                chunks.push(parse_timestamp_token(part, false, fetcher).await?);
+                chunks.push(parse_timestamp_token(part, false, cache, fetcher).await?);
             }
             Ok(chunks)
         }
@@ -137,18 +289,26 @@ enum RangePosition {
 async fn parse_timestamp_token<P: JsonRpcClient>(
     s: &str,
     as_range: bool,
+    cache: &mut BlockTimestamps,
     fetcher: &Fetcher<P>,
-) -> Result<BlockChunk, ParseError> {
+) -> Result<(BlockChunk, TimestampRange), ParseError> {
+    let input = s.to_string();
     let s = s.replace('_', "");
 
     let parts: Vec<&str> = s.split(':').collect();
     match parts.as_slice() {
         [timestamp_ref] => {
             let timestamp =
-                parse_timestamp_number(timestamp_ref, RangePosition::None, fetcher).await?;
-            let block = timestamp_to_block_number(timestamp, fetcher).await?;
-
-            Ok(BlockChunk::Numbers(vec![block]))
+                parse_timestamp_number(timestamp_ref, RangePosition::None, cache, fetcher).await?;
+            let block = timestamp_to_block_number(timestamp, cache, fetcher).await?;
+            let timestamp_range = TimestampRange {
+                input,
+                start_timestamp: timestamp,
+                end_timestamp: timestamp,
+                start_block: block,
+                end_block: block,
+            };
+            Ok((BlockChunk::Numbers(vec![block]), timestamp_range))
         }
         [first_ref, second_ref] => {
             let parts: Vec<_> = second_ref.split('/').collect();
@@ -162,12 +322,21 @@ async fn parse_timestamp_token<P: JsonRpcClient>(
             };
 
             let (start_timestamp, end_timestamp) =
-                parse_timestamp_range(first_ref, second_ref, fetcher).await?;
+                parse_timestamp_range(first_ref, second_ref, cache, fetcher).await?;
             let (start_block, end_block) = (
-                timestamp_to_block_number(start_timestamp, fetcher).await?,
-                timestamp_to_block_number(end_timestamp, fetcher).await?,
+                first_block_after_timestamp(start_timestamp, cache, fetcher).await?,
+                timestamp_to_block_number(end_timestamp, cache, fetcher).await?,
             );
-            block_range_to_block_chunk(start_block, end_block, as_range, None, n_keep)
+            if end_block < start_block {
+                return Err(ParseError::ParseError(format!(
+                    "no blocks between the timestamps of {}",
+                    input
+                )))
+            }
+            let timestamp_range =
+                TimestampRange { input, start_timestamp, end_timestamp, start_block, end_block };
+            let chunk = block_range_to_block_chunk(start_block, end_block, as_range, None, n_keep)?;
+            Ok((chunk, timestamp_range))
         }
         _ => Err(ParseError::ParseError(
             "timestamps must be in format timestamp or start_timestamp:end_timestamp".to_string(),
@@ -178,6 +347,7 @@ async fn parse_timestamp_token<P: JsonRpcClient>(
 async fn parse_timestamp_range<P>(
     first_ref: &str,
     second_ref: &str,
+    cache: &mut BlockTimestamps,
     fetcher: &Fetcher<P>,
 ) -> Result<(u64, u64), ParseError>
 where
@@ -186,11 +356,12 @@ where
     let (start_timestamp, end_timestamp) = match (first_ref, second_ref) {
         _ if first_ref.starts_with('-') => {
             let end_timestamp =
-                parse_timestamp_number(second_ref, RangePosition::Last, fetcher).await?;
+                parse_timestamp_number(second_ref, RangePosition::Last, cache, fetcher).await?;
 
             let start_timestamp = end_timestamp
                 .checked_sub(
-                    parse_timestamp_number(&first_ref[1..], RangePosition::None, fetcher).await?,
+                    parse_timestamp_number(&first_ref[1..], RangePosition::None, cache, fetcher)
+                        .await?,
                 )
                 .ok_or_else(|| ParseError::ParseError("start_timestamp underflow".to_string()))?;
 
@@ -198,11 +369,12 @@ where
         }
         _ if second_ref.starts_with('+') => {
             let start_timestamp =
-                parse_timestamp_number(first_ref, RangePosition::First, fetcher).await?;
+                parse_timestamp_number(first_ref, RangePosition::First, cache, fetcher).await?;
 
             let end_timestamp = start_timestamp
                 .checked_add(
-                    parse_timestamp_number(&second_ref[1..], RangePosition::None, fetcher).await?,
+                    parse_timestamp_number(&second_ref[1..], RangePosition::None, cache, fetcher)
+                        .await?,
                 )
                 .ok_or_else(|| ParseError::ParseError("end_timestamp overflow".to_string()))?;
 
@@ -210,10 +382,10 @@ where
         }
         _ => {
             let start_timestamp =
-                parse_timestamp_number(first_ref, RangePosition::First, fetcher).await?;
+                parse_timestamp_number(first_ref, RangePosition::First, cache, fetcher).await?;
 
             let end_timestamp =
-                parse_timestamp_number(second_ref, RangePosition::Last, fetcher).await?;
+                parse_timestamp_number(second_ref, RangePosition::Last, cache, fetcher).await?;
 
             (start_timestamp, end_timestamp)
         }
@@ -232,12 +404,13 @@ where
 async fn parse_timestamp_number<P: JsonRpcClient>(
     timestamp_ref: &str,
     range_position: RangePosition,
+    cache: &mut BlockTimestamps,
     fetcher: &Fetcher<P>,
 ) -> Result<u64, ParseError> {
     match (timestamp_ref, range_position) {
-        ("latest", _) => get_latest_timestamp(fetcher).await,
+        ("latest", _) => get_latest_timestamp(cache, fetcher).await,
         ("", RangePosition::First) => Ok(0),
-        ("", RangePosition::Last) => get_latest_timestamp(fetcher).await,
+        ("", RangePosition::Last) => get_latest_timestamp(cache, fetcher).await,
         ("", RangePosition::None) => Err(ParseError::ParseError("invalid input".to_string())),
         _ if timestamp_ref.ends_with('m') => scale_timestamp_str_by_metric_unit(timestamp_ref, 60),
         _ if timestamp_ref.ends_with('h') => {
@@ -255,6 +428,7 @@ async fn parse_timestamp_number<P: JsonRpcClient>(
         _ if timestamp_ref.ends_with('y') => {
             scale_timestamp_str_by_metric_unit(timestamp_ref, 86400 * 365)
         }
+        _ if timestamp_ref.contains('-') => parse_date(timestamp_ref),
         _ => timestamp_ref
             .parse::<f64>()
             .map_err(|_e| ParseError::ParseError("Error parsing timestamp ref".to_string()))
@@ -262,6 +436,17 @@ async fn parse_timestamp_number<P: JsonRpcClient>(
     }
 }
 
+/// unix timestamp of the start of a calendar date in UTC, e.g. 2024-01-31
+fn parse_date(date: &str) -> Result<u64, ParseError> {
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_e| ParseError::ParseError(format!("dates must be YYYY-MM-DD, got {}", date)))?;
+    let timestamp = date.and_hms_opt(0, 0, 0).map(|datetime| datetime.and_utc().timestamp());
+    match timestamp {
+        Some(timestamp) if timestamp >= 0 => Ok(timestamp as u64),
+        _ => Err(ParseError::ParseError("dates must not be before 1970-01-01".to_string())),
+    }
+}
+
 fn scale_timestamp_str_by_metric_unit(
     timestamp_ref: &str,
     metric_scale: u64,
@@ -276,54 +461,64 @@ fn scale_timestamp_str_by_metric_unit(
 // timestamp
 async fn timestamp_to_block_number<P: JsonRpcClient>(
     timestamp: u64,
+    cache: &mut BlockTimestamps,
     fetcher: &Fetcher<P>,
 ) -> Result<u64, ParseError> {
-    let latest_block_number = get_latest_block_number(fetcher).await?;
-
-    let mut l = 0;
-    let mut r = latest_block_number;
-    let mut mid = (l + r) / 2;
-    let mut block = fetcher
-        .get_block(mid)
-        .await
-        .map_err(|_e| ParseError::ParseError("Error fetching block for timestamp".to_string()))?
-        .unwrap();
-
-    while l <= r {
-        mid = (l + r) / 2;
-        block = fetcher
-            .get_block(mid)
-            .await
-            .map_err(|_e| ParseError::ParseError("Error fetching block for timestamp".to_string()))?
-            .unwrap();
+    // timestamps before genesis resolve to the genesis block
+    Ok(last_block_before_or_at(timestamp, cache, fetcher).await?.unwrap_or(0))
+}
 
-        #[allow(clippy::comparison_chain)]
-        if block.timestamp == timestamp.into() {
-            return Ok(mid);
-        } else if block.timestamp < timestamp.into() {
-            l = mid + 1;
-        } else {
-            r = mid - 1;
-        }
+/// first block whose timestamp is greater than or equal to a given timestamp
+async fn first_block_after_timestamp<P: JsonRpcClient>(
+    timestamp: u64,
+    cache: &mut BlockTimestamps,
+    fetcher: &Fetcher<P>,
+) -> Result<u64, ParseError> {
+    match timestamp.checked_sub(1) {
+        Some(previous) => match last_block_before_or_at(previous, cache, fetcher).await? {
+            Some(block) => Ok(block + 1),
+            None => Ok(0),
+        },
+        None => Ok(0),
     }
+}
 
-    // If timestamp is between two different blocks, return the lower block.
-    if mid > 0 && block.timestamp > ethers::types::U256::from(timestamp) {
-        Ok(mid - 1)
-    } else {
-        Ok(mid)
+/// last block whose timestamp is less than or equal to a given timestamp, None if before genesis
+async fn last_block_before_or_at<P: JsonRpcClient>(
+    timestamp: u64,
+    cache: &mut BlockTimestamps,
+    fetcher: &Fetcher<P>,
+) -> Result<Option<u64>, ParseError> {
+    // start from the closest blocks probed by previous searches
+    let l = cache.probes.range(..=cache.last_block).rev().find(|(_, t)| **t <= timestamp);
+    let mut l = l.map(|(block, _)| *block).unwrap_or(0);
+    let r = cache.probes.range(l..=cache.last_block).find(|(_, t)| **t > timestamp);
+    let mut r = r.map(|(block, _)| *block).unwrap_or(cache.last_block);
+
+    if cache.timestamp(l, fetcher).await? > timestamp {
+        return Ok(None)
+    }
+    if cache.timestamp(r, fetcher).await? <= timestamp {
+        return Ok(Some(r))
+    }
+    // the timestamp of l is at most timestamp, the timestamp of r is greater
+    while r - l > 1 {
+        let mid = l + (r - l) / 2;
+        if cache.timestamp(mid, fetcher).await? <= timestamp {
+            l = mid;
+        } else {
+            r = mid;
+        }
     }
+    Ok(Some(l))
 }
 
-async fn get_latest_timestamp<P: JsonRpcClient>(fetcher: &Fetcher<P>) -> Result<u64, ParseError> {
-    let latest_block_number = get_latest_block_number(fetcher).await?;
-    let latest_block = fetcher
-        .get_block(latest_block_number)
-        .await
-        .map_err(|_e| ParseError::ParseError("Error fetching latest block".to_string()))?
-        .unwrap();
-
-    Ok(latest_block.timestamp.as_u64())
+async fn get_latest_timestamp<P: JsonRpcClient>(
+    cache: &mut BlockTimestamps,
+    fetcher: &Fetcher<P>,
+) -> Result<u64, ParseError> {
+    let last_block = cache.last_block;
+    cache.timestamp(last_block, fetcher).await
 }
 
 #[cfg(test)]
@@ -349,26 +544,72 @@ mod tests {
         let rate_limiter = Some(RateLimiter::direct(quota));
         let semaphore = tokio::sync::Semaphore::new(max_concurrent_requests as usize);
 
-        Fetcher { provider, semaphore: Some(semaphore), rate_limiter }
+        Fetcher {
+            provider,
+            semaphore: Some(semaphore),
+            rate_limiter,
+            request_counts: Default::default(),
+            block_timestamps: Default::default(),
+            shared_fetches: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_time_buckets() {
+        // 2024-01-31 is a wednesday
+        let timestamp = parse_date("2024-01-31").unwrap() + 13 * 3600 + 5;
+        assert_eq!(bucket_name(bucket_start(timestamp, "day"), "day"), "2024-01-31");
+        assert_eq!(bucket_name(bucket_start(timestamp, "hour"), "hour"), "2024-01-31T13");
+        assert_eq!(bucket_start(timestamp, "week"), parse_date("2024-01-29").unwrap());
+        assert_eq!(bucket_name(bucket_start(timestamp, "week"), "week"), "2024-W05");
+        assert_eq!(
+            next_bucket_start(bucket_start(timestamp, "day"), "day"),
+            timestamp + 11 * 3600 - 5
+        );
+
+        let chunks =
+            vec![BlockChunk::Range(0, 9), BlockChunk::Range(10, 19), BlockChunk::Range(30, 39)];
+        assert_eq!(contiguous_ranges(&chunks).unwrap(), vec![(0, 19), (30, 39)]);
+        assert!(contiguous_ranges(&[BlockChunk::Numbers(vec![1])]).is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2024-01-01").unwrap(), 1704067200);
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert!(parse_date("1969-12-31").is_err());
+        assert!(parse_date("2024-13-01").is_err());
+    }
+
+    #[test]
+    fn test_relative_timestamp_args() {
+        use clap_cryo::Parser;
+        let command = "cryo blocks --timestamps -7d: 2024-01-01:2024-01-02 -3h:latest --csv";
+        let args = Args::parse_from(crate::args::join_relative_timestamps(command.split(' ')));
+        let expected = vec!["-7d:", "2024-01-01:2024-01-02", "-3h:latest"];
+        assert_eq!(args.timestamps, Some(expected.into_iter().map(String::from).collect()));
+        assert!(args.csv);
     }
 
     #[tokio::test]
     async fn test_extrema_timestamp_to_block_number() {
         let fetcher = setup_fetcher().await;
+        let mut cache = BlockTimestamps::new(&fetcher, 0).await.unwrap();
 
         // Before genesis block
-        assert!(timestamp_to_block_number(1438260000, &fetcher).await.unwrap() == 0);
+        assert!(timestamp_to_block_number(1438260000, &mut cache, &fetcher).await.unwrap() == 0);
     }
 
     #[tokio::test]
     async fn test_latest_timestamp_to_block_number() {
         let fetcher = setup_fetcher().await;
-        let latest_block_number = get_latest_block_number(&fetcher).await.unwrap();
+        let mut cache = BlockTimestamps::new(&fetcher, 0).await.unwrap();
+        let latest_block_number = cache.last_block;
         let latest_block = fetcher.get_block(latest_block_number).await.unwrap().unwrap();
         let latest_timestamp = latest_block.timestamp.as_u64();
 
         assert_eq!(
-            timestamp_to_block_number(latest_timestamp, &fetcher).await.unwrap(),
+            timestamp_to_block_number(latest_timestamp, &mut cache, &fetcher).await.unwrap(),
             latest_block_number
         );
     }
@@ -376,67 +617,83 @@ mod tests {
     #[tokio::test]
     async fn test_timestamp_between_blocks() {
         let fetcher = setup_fetcher().await;
+        let mut cache = BlockTimestamps::new(&fetcher, 0).await.unwrap();
 
         // Block 1000, and the timestamp surrounding block 1020
-        assert!(timestamp_to_block_number(1438272177, &fetcher).await.unwrap() == 1020);
-        assert!(timestamp_to_block_number(1438272178, &fetcher).await.unwrap() == 1020);
+        assert!(timestamp_to_block_number(1438272177, &mut cache, &fetcher).await.unwrap() == 1020);
+        assert!(timestamp_to_block_number(1438272178, &mut cache, &fetcher).await.unwrap() == 1020);
 
         // Timestamp 1438272176 is 1 seconds after block 1019 and 1 second before block 1020. Lower
         // block is returned
-        assert!(timestamp_to_block_number(1438272176, &fetcher).await.unwrap() == 1019);
+        assert!(timestamp_to_block_number(1438272176, &mut cache, &fetcher).await.unwrap() == 1019);
 
         // Timestamp 1438272187 is 1 seconds after block 1024 and 1 second before block 1025. Lower
         // block is returned
-        assert!(timestamp_to_block_number(1438272187, &fetcher).await.unwrap() == 1024);
+        assert!(timestamp_to_block_number(1438272187, &mut cache, &fetcher).await.unwrap() == 1024);
 
         // Timestamp 1438272169 is 4 seconds after block 1016 and 4 seconds before block 1017. Lower
         // block is returned
-        assert!(timestamp_to_block_number(1438272169, &fetcher).await.unwrap() == 1016);
+        assert!(timestamp_to_block_number(1438272169, &mut cache, &fetcher).await.unwrap() == 1016);
     }
 
     #[tokio::test]
     async fn test_parse_timestamp_number() {
         let fetcher = setup_fetcher().await;
+        let mut cache = BlockTimestamps::new(&fetcher, 0).await.unwrap();
         let latest_timestamp =
-            parse_timestamp_number("latest", RangePosition::None, &fetcher).await.unwrap();
-        assert_eq!(latest_timestamp, get_latest_timestamp(&fetcher).await.unwrap());
+            parse_timestamp_number("latest", RangePosition::None, &mut cache, &fetcher)
+                .await
+                .unwrap();
+        assert_eq!(latest_timestamp, get_latest_timestamp(&mut cache, &fetcher).await.unwrap());
 
-        assert_eq!(parse_timestamp_number("", RangePosition::First, &fetcher).await.unwrap(), 0);
+        assert_eq!(
+            parse_timestamp_number("", RangePosition::First, &mut cache, &fetcher).await.unwrap(),
+            0
+        );
 
         assert_eq!(
-            parse_timestamp_number("", RangePosition::Last, &fetcher).await.unwrap(),
-            get_latest_timestamp(&fetcher).await.unwrap()
+            parse_timestamp_number("", RangePosition::Last, &mut cache, &fetcher).await.unwrap(),
+            get_latest_timestamp(&mut cache, &fetcher).await.unwrap()
         );
 
         assert_eq!(
-            parse_timestamp_number("1700000000", RangePosition::None, &fetcher).await.unwrap(),
+            parse_timestamp_number("1700000000", RangePosition::None, &mut cache, &fetcher)
+                .await
+                .unwrap(),
             1700000000
         );
 
-        assert_eq!(parse_timestamp_number("1m", RangePosition::None, &fetcher).await.unwrap(), 60);
+        assert_eq!(
+            parse_timestamp_number("1m", RangePosition::None, &mut cache, &fetcher).await.unwrap(),
+            60
+        );
 
         assert_eq!(
-            parse_timestamp_number("8760h", RangePosition::None, &fetcher).await.unwrap(),
+            parse_timestamp_number("8760h", RangePosition::None, &mut cache, &fetcher)
+                .await
+                .unwrap(),
             8760 * 3600
         );
 
         assert_eq!(
-            parse_timestamp_number("365d", RangePosition::None, &fetcher).await.unwrap(),
+            parse_timestamp_number("365d", RangePosition::None, &mut cache, &fetcher)
+                .await
+                .unwrap(),
             365 * 86400
         );
 
         assert_eq!(
-            parse_timestamp_number("52w", RangePosition::None, &fetcher).await.unwrap(),
+            parse_timestamp_number("52w", RangePosition::None, &mut cache, &fetcher).await.unwrap(),
             52 * 86400 * 7
         );
 
         assert_eq!(
-            parse_timestamp_number("12M", RangePosition::None, &fetcher).await.unwrap(),
+            parse_timestamp_number("12M", RangePosition::None, &mut cache, &fetcher).await.unwrap(),
             12 * 86400 * 30
         );
 
         assert_eq!(
-            parse_timestamp_number("1y", RangePosition::None, &fetcher).await.unwrap(),
+            parse_timestamp_number("1y", RangePosition::None, &mut cache, &fetcher).await.unwrap(),
             86400 * 365
         );
     }
@@ -444,33 +701,34 @@ mod tests {
     #[tokio::test]
     async fn test_parse_timestamp_range_to_block_number_range() {
         let fetcher = setup_fetcher().await;
+        let mut cache = BlockTimestamps::new(&fetcher, 0).await.unwrap();
 
         let (start_timestamp, end_timestamp) =
-            parse_timestamp_range("1700000000", "1700000015", &fetcher).await.unwrap();
+            parse_timestamp_range("1700000000", "1700000015", &mut cache, &fetcher).await.unwrap();
         assert_eq!(
             (
-                timestamp_to_block_number(start_timestamp, &fetcher).await.unwrap(),
-                timestamp_to_block_number(end_timestamp, &fetcher).await.unwrap()
+                timestamp_to_block_number(start_timestamp, &mut cache, &fetcher).await.unwrap(),
+                timestamp_to_block_number(end_timestamp, &mut cache, &fetcher).await.unwrap()
             ),
             (18573050, 18573051)
         );
 
         let (start_timestamp, end_timestamp) =
-            parse_timestamp_range("-15", "1700000015", &fetcher).await.unwrap();
+            parse_timestamp_range("-15", "1700000015", &mut cache, &fetcher).await.unwrap();
         assert_eq!(
             (
-                timestamp_to_block_number(start_timestamp, &fetcher).await.unwrap(),
-                timestamp_to_block_number(end_timestamp, &fetcher).await.unwrap()
+                timestamp_to_block_number(start_timestamp, &mut cache, &fetcher).await.unwrap(),
+                timestamp_to_block_number(end_timestamp, &mut cache, &fetcher).await.unwrap()
             ),
             (18573050, 18573052)
         );
 
         let (start_timestamp, end_timestamp) =
-            parse_timestamp_range("1700000000", "+15", &fetcher).await.unwrap();
+            parse_timestamp_range("1700000000", "+15", &mut cache, &fetcher).await.unwrap();
         assert_eq!(
             (
-                timestamp_to_block_number(start_timestamp, &fetcher).await.unwrap(),
-                timestamp_to_block_number(end_timestamp, &fetcher).await.unwrap()
+                timestamp_to_block_number(start_timestamp, &mut cache, &fetcher).await.unwrap(),
+                timestamp_to_block_number(end_timestamp, &mut cache, &fetcher).await.unwrap()
             ),
             (18573050, 18573051)
         );
@@ -0,0 +1,112 @@
+// using --resume <REPORT> collects the chunks that a previous run failed to collect
+// - the arguments of the previous run are loaded from its report
+// - arguments of the current command override the arguments of the report
+// - chunks whose files already exist are skipped, so only failed or missing chunks are collected,
+//   along with chunks that were written without some of their blocks
+// - resuming is refused if the schemas of the datasets would change, unless --force is used
+
+use crate::{args::Args, parse::REDACTED};
+use cryo_freeze::{ParseError, Query};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path};
+
+/// the parts of a previous report needed to resume its run
+#[derive(Deserialize)]
+pub(crate) struct ResumedReport {
+    args: Option<String>,
+    #[serde(default)]
+    schemas: BTreeMap<String, serde_json::Value>,
+}
+
+pub(crate) fn load_resumed_report(path: &Path) -> Result<ResumedReport, ParseError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|_| ParseError::ParseError(format!("could not read report {}", path.display())))?;
+    serde_json::from_str(&contents)
+        .map_err(|_| ParseError::ParseError(format!("could not parse report {}", path.display())))
+}
+
+/// arguments of the resumed run, overridden by the arguments of the current command
+pub(crate) fn resume_args(args: Args, report: &ResumedReport) -> Result<Args, ParseError> {
+    let resumed: Args = match &report.args {
+        Some(resumed) => serde_json::from_str(resumed).map_err(|_| {
+            ParseError::ParseError("could not parse arguments of resumed report".to_string())
+        })?,
+        None => return Err(ParseError::ParseError("report has no arguments to resume".to_string())),
+    };
+    let mut merged = match serde_json::to_value(resumed) {
+        Ok(Value::Object(merged)) => merged,
+        _ => return Err(ParseError::ParseError("could not serialize args".to_string())),
+    };
+    merged.extend(args.non_default_args()?);
+    let args: Args = serde_json::from_value(Value::Object(merged))
+        .map_err(|_| ParseError::ParseError("could not merge args with report".to_string()))?;
+    let args = Args { remember: false, ..args };
+    if args.follow {
+        return Err(ParseError::ParseError("--resume cannot be used with --follow".to_string()))
+    }
+    let headers = args.rpc_header.iter().flatten();
+    if args.rpc_auth.iter().chain(headers).any(|value| value.contains(REDACTED)) {
+        return Err(ParseError::ParseError(
+            "credentials are redacted in reports, pass --rpc-auth or --rpc-header again to resume"
+                .to_string(),
+        ))
+    }
+    // existing files are kept so that only failed or missing chunks are collected
+    Ok(Args { overwrite: false, ..args })
+}
+
+/// check that the datasets of the resumed run are written with the same schemas
+pub(crate) fn check_schemas(query: &Query, report: &ResumedReport) -> Result<(), ParseError> {
+    let mut schemas = BTreeMap::new();
+    for (datatype, table) in query.schemas.iter() {
+        let schema = table.output_schema().map_err(|e| ParseError::ParseError(e.to_string()))?;
+        let schema = serde_json::to_value(schema)
+            .map_err(|_| ParseError::ParseError("could not serialize schema".to_string()))?;
+        schemas.insert(datatype.name(), schema);
+    }
+    for name in schemas.keys().chain(report.schemas.keys()) {
+        if schemas.get(name) != report.schemas.get(name) {
+            return Err(ParseError::ParseError(format!(
+                "options would change the schema of {} from the resumed run, use --force to \
+                 resume anyway",
+                name
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap_cryo::Parser;
+
+    #[test]
+    fn test_resume_args() {
+        let resumed = Args {
+            datatype: vec!["logs".to_string()],
+            blocks: Some(vec!["0:100".to_string()]),
+            overwrite: true,
+            ..Default::default()
+        };
+        let report = ResumedReport {
+            args: Some(serde_json::to_string(&resumed).unwrap()),
+            schemas: BTreeMap::new(),
+        };
+        let args = Args::parse_from(["cryo", "--max-concurrent-chunks", "2"]);
+        let args = resume_args(args, &report).unwrap();
+        assert_eq!(args.datatype, vec!["logs".to_string()]);
+        assert_eq!(args.blocks, Some(vec!["0:100".to_string()]));
+        assert_eq!(args.max_concurrent_chunks, Some(2));
+        assert!(!args.overwrite);
+
+        let args = Args { rpc_auth: Some(REDACTED.to_string()), ..Default::default() };
+        let report = ResumedReport { args: Some(serde_json::to_string(&args).unwrap()), ..report };
+        let defaults = Args::parse_from(["cryo"]);
+        assert!(resume_args(defaults.clone(), &report).is_err());
+        let args = Args::parse_from(["cryo", "--rpc-auth", "user:pass"]);
+        assert!(resume_args(args, &report).is_ok());
+        assert!(resume_args(defaults, &ResumedReport { args: None, ..report }).is_err());
+    }
+}
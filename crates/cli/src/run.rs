@@ -1,20 +1,70 @@
-use crate::{args, parse, remember};
+use crate::{args, chains, config, follow, parse, remember, resume};
 use clap_cryo::Parser;
 use color_print::cstr;
 use colored::Colorize;
-use cryo_freeze::{err, CollectError, ExecutionEnv, FreezeSummary};
-use std::{sync::Arc, time::SystemTime};
+use cryo_freeze::{
+    err, format_bytes, CanonicalCheck, CollectError, EventHandler, ExecutionEnv, FreezeSummary,
+    ManifestCheck,
+};
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::SystemTime,
+};
 
 /// run cli
 pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    run_with_hooks(args, None, None).await
+}
+
+/// run cli, sending the events of collections to events and starting no further chunks once
+/// cancel is set
+pub async fn run_with_hooks(
+    args: args::Args,
+    events: Option<Arc<dyn EventHandler>>,
+    cancel: Option<Arc<AtomicBool>>,
+) -> Result<Option<FreezeSummary>, CollectError> {
     // handle subcommands
     if args.datatype.first() == Some(&"help".to_string()) {
         return handle_help_subcommands(args).await
     }
+    if args.datatype.first() == Some(&"meta".to_string()) {
+        return handle_meta_subcommand(args)
+    }
+    if args.datatype.first() == Some(&"schema".to_string()) {
+        return handle_schema_subcommand(args)
+    }
+    if args.datatype.first() == Some(&"compact".to_string()) {
+        return handle_compact_subcommand(args)
+    }
+    if args.datatype.first() == Some(&"cache".to_string()) {
+        return handle_cache_subcommand(args)
+    }
+    if args.datatype.first() == Some(&"verify".to_string()) {
+        return handle_verify_subcommand(args).await
+    }
+    if args.datatype.first() == Some(&"verify-manifest".to_string()) {
+        return handle_verify_manifest_subcommand(args)
+    }
+
+    if args.datatype.first() == Some(&"config".to_string()) {
+        return handle_config_subcommand(args)
+    }
+
+    // read arguments from config file
+    let args = config::apply_config(args)?;
 
     let cryo_dir: std::path::PathBuf = args.output_dir.clone().into();
     let cryo_dir = cryo_dir.join(".cryo");
 
+    // resume the run of a previous report
+    let (args, resumed_report) = match &args.resume {
+        Some(report_path) => {
+            let report = resume::load_resumed_report(report_path)?;
+            (resume::resume_args(args, &report)?, Some(report))
+        }
+        None => (args, None),
+    };
+
     // remember previous command
     let args = if args.datatype.is_empty() {
         let remembered = remember::load_remembered_command(cryo_dir.clone())?;
@@ -43,17 +93,220 @@ pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, CollectError
 
     // handle regular flow
     let t_start_parse = Some(SystemTime::now());
+    if let Some(chains) = chains::parse_chains(&args)? {
+        return chains::freeze_chains(chains, t_start_parse, events, cancel).await
+    }
+    if args.follow {
+        return follow::follow(args, t_start_parse, events, cancel).await
+    }
     let (query, source, sink, env) = match parse::parse_args(&args).await {
         Ok(opts) => opts,
         Err(e) => return Err(e.into()),
     };
 
+    if let (Some(report), false) = (&resumed_report, args.force) {
+        resume::check_schemas(&query, report)?;
+    }
+
     let source = Arc::new(source);
-    let env = ExecutionEnv { t_start_parse, ..env };
+    let env = ExecutionEnv { t_start_parse, events, cancel, ..env };
     let env = env.set_start_time();
     cryo_freeze::freeze(&query, &source, &sink, &env).await
 }
 
+/// print the collection metadata of output files as json
+fn handle_meta_subcommand(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    let paths = &args.datatype[1..];
+    if paths.is_empty() {
+        return Err(err("usage: cryo meta <FILE>..."))
+    }
+    let mut metadata = serde_json::Map::new();
+    for path in paths.iter() {
+        let file_metadata = cryo_freeze::read_file_metadata(std::path::Path::new(path))?;
+        let file_metadata = serde_json::to_value(file_metadata)
+            .map_err(|_| err("could not serialize file metadata"))?;
+        metadata.insert(path.clone(), file_metadata);
+    }
+    // metadata of several files is keyed by path
+    let output = match metadata.len() {
+        1 => metadata.into_iter().map(|(_, file_metadata)| file_metadata).next(),
+        _ => Some(serde_json::Value::Object(metadata)),
+    };
+    let output = serde_json::to_string_pretty(&output)
+        .map_err(|_| err("could not serialize file metadata"))?;
+    println!("{}", output);
+    Ok(None)
+}
+
+/// print the columns and dtypes of the files that would be written for datasets
+fn handle_schema_subcommand(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    if args.datatype.len() < 2 {
+        return Err(err("usage: cryo schema <DATASET(S)> [--json]"))
+    }
+    // --json selects json output of the schema rather than json files
+    let json = args.json;
+    let args = args::Args { datatype: args.datatype[1..].to_vec(), json: false, ..args };
+    let (datatypes, schemas) = parse::schemas::parse_schemas(&args)?;
+    let mut output_schemas = Vec::new();
+    for datatype in datatypes.iter() {
+        let schema = schemas.get(datatype).ok_or(err("missing schema for datatype"))?;
+        output_schemas.push(schema.output_schema()?);
+    }
+    if json {
+        let output = serde_json::to_string_pretty(&output_schemas)
+            .map_err(|_| err("could not serialize schemas"))?;
+        println!("{}", output);
+        return Ok(None)
+    }
+    for (i, schema) in output_schemas.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", schema.dataset.white().bold());
+        for column in schema.columns.iter() {
+            let mut flags = Vec::new();
+            if column.nullable {
+                flags.push("nullable".to_string());
+            }
+            if !column.default {
+                flags.push("non-default".to_string());
+            }
+            if let Some(renamed_from) = &column.renamed_from {
+                flags.push(format!("renamed from {}", renamed_from));
+            }
+            match flags.is_empty() {
+                true => println!("- {}: {}", column.name.bold(), column.polars_type),
+                false => println!(
+                    "- {}: {} ({})",
+                    column.name.bold(),
+                    column.polars_type,
+                    flags.join(", ")
+                ),
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// write a commented template of a config file
+fn handle_config_subcommand(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    let path = config::init_config(&args)?;
+    println!("wrote config template to {}", path.display());
+    Ok(None)
+}
+
+/// merge small parquet files of a directory into larger files
+fn handle_compact_subcommand(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    let (dir, sink, target) = parse::parse_compact_args(&args)?;
+    let compacted = cryo_freeze::compact(&dir, &sink, &target)?;
+    for file in compacted.iter() {
+        println!(
+            "merged {} files into {} ({} rows)",
+            file.inputs.len(),
+            file.path.display(),
+            file.n_rows
+        );
+    }
+    let n_inputs: usize = compacted.iter().map(|file| file.inputs.len()).sum();
+    println!("compacted {} files into {} files", n_inputs, compacted.len());
+    Ok(None)
+}
+
+/// print the contents of the response cache, or prune it to its size cap
+fn handle_cache_subcommand(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    let (action, dir, max_bytes) = parse::parse_cache_args(&args)?;
+    if action == "prune" {
+        let removed = cryo_freeze::prune_cache(&dir, max_bytes)?;
+        println!(
+            "removed {} cached responses ({})",
+            removed.n_entries,
+            format_bytes(removed.n_bytes)
+        );
+    }
+    let stats = cryo_freeze::cache_stats(&dir)?;
+    for ((chain_id, method), entries) in stats.methods.iter() {
+        println!(
+            "- chain {} {}: {} responses ({})",
+            chain_id,
+            method.bold(),
+            entries.n_entries,
+            format_bytes(entries.n_bytes)
+        );
+    }
+    println!(
+        "{} cached responses ({} of {})",
+        stats.n_entries,
+        format_bytes(stats.n_bytes),
+        format_bytes(max_bytes)
+    );
+    Ok(None)
+}
+
+/// re-fetch canonical block hashes for the files of a directory
+async fn handle_verify_subcommand(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    let (dir, source) = parse::parse_verify_args(&args).await?;
+    let checks = cryo_freeze::verify_canonical(&dir, &source).await?;
+    let (mut n_orphaned, mut n_unverified) = (0, 0);
+    for check in checks.iter() {
+        match check {
+            CanonicalCheck::Canonical(_) => {}
+            CanonicalCheck::Orphaned(path, block_number) => {
+                n_orphaned += 1;
+                println!(
+                    "{} {} (block {} is not canonical)",
+                    "orphaned".red().bold(),
+                    path.display(),
+                    block_number
+                );
+            }
+            CanonicalCheck::Unverified(path) => {
+                n_unverified += 1;
+                println!("{} {} (no block hashes)", "unverified".yellow().bold(), path.display());
+            }
+        }
+    }
+    println!(
+        "{} canonical, {} orphaned, {} unverified",
+        checks.len() - n_orphaned - n_unverified,
+        n_orphaned,
+        n_unverified
+    );
+    match n_orphaned {
+        0 => Ok(None),
+        _ => Err(err(&format!("{} files contain non-canonical blocks", n_orphaned))),
+    }
+}
+
+/// recompute checksums of the files listed in the manifest of a directory
+fn handle_verify_manifest_subcommand(
+    args: args::Args,
+) -> Result<Option<FreezeSummary>, CollectError> {
+    let dir = match &args.datatype[1..] {
+        [dir] => std::path::PathBuf::from(dir),
+        _ => return Err(err("usage: cryo verify-manifest <DIR>")),
+    };
+    let checks = cryo_freeze::verify_manifest(&dir)?;
+    let mut n_failed = 0;
+    for check in checks.iter() {
+        match check {
+            ManifestCheck::Verified(_) => {}
+            ManifestCheck::Missing(path) => {
+                n_failed += 1;
+                println!("{} {}", "missing".red().bold(), path.display());
+            }
+            ManifestCheck::Modified(path, fields) => {
+                n_failed += 1;
+                println!("{} {} ({})", "modified".red().bold(), path.display(), fields.join(", "));
+            }
+        }
+    }
+    println!("verified {} of {} files", checks.len() - n_failed, checks.len());
+    match n_failed {
+        0 => Ok(None),
+        _ => Err(err(&format!("{} files do not match the checksum manifest", n_failed))),
+    }
+}
+
 async fn handle_help_subcommands(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
     if args.datatype.len() == 1 {
         args::Args::parse_from(vec!["cryo", "-h"]);
@@ -76,7 +329,9 @@ async fn handle_help_subcommands(args: args::Args) -> Result<Option<FreezeSummar
 - can use transaction hashes         <white><bold>--txs TX_HASH1 TX_HASH2 TX_HASH3</bold></white>
 - can use a parquet file             <white><bold>--txs ./path/to/file.parquet[:COLUMN_NAME]</bold></white>
                                      (default column name is <white><bold>transaction_hash</bold></white>)
-- can use multiple parquet files     <white><bold>--txs ./path/to/ethereum__logs*.parquet</bold></white>"#
+- can use multiple parquet files     <white><bold>--txs ./path/to/ethereum__logs*.parquet</bold></white>
+- can use a file of hashes           <white><bold>--txs-file ./path/to/txs.txt</bold></white>
+- can use a csv column               <white><bold>--txs-file ./path/to/txs.csv --txs-column hash</bold></white>"#
         );
         println!("{}", content);
     } else if args.datatype.len() == 2 && args.datatype.contains(&"datasets".to_string()) {
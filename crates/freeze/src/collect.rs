@@ -1,24 +1,23 @@
-use crate::{collect_partition, CollectError, Query, Source};
+use crate::{
+    collect_partition, err, verify, CollectError, Datatype, MetaDatatype, Partition, Query, Source,
+};
+use ethers::types::H256;
+use futures::{Stream, StreamExt};
 use polars::prelude::*;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// dataframe of one datatype of a collected chunk
+pub type CollectedChunk = (Partition, Datatype, DataFrame);
 
 /// collect single dataframe
 pub async fn collect(query: Arc<Query>, source: Arc<Source>) -> Result<DataFrame, CollectError> {
-    query.is_valid()?;
-    let datatype = if query.datatypes.len() != 1 {
-        return Err(CollectError::CollectError(
-            "collect() can only collect a single datatype".to_string(),
-        ))
-    } else {
-        query.datatypes[0].clone()
-    };
-    let partition = if query.partitions.len() != 1 {
+    if query.datatypes.len() != 1 {
         return Err(CollectError::CollectError(
             "collect() can only collect a single datatype".to_string(),
         ))
-    } else {
-        query.partitions[0].clone()
-    };
-    let results = collect_partition(datatype, partition, query, source).await?;
+    }
+    let results = collect_dataframes(query, source).await?;
     if results.len() > 1 {
         Err(CollectError::CollectError("collect() only returns single dataframes".to_string()))
     } else {
@@ -28,3 +27,123 @@ pub async fn collect(query: Arc<Query>, source: Arc<Source>) -> Result<DataFrame
         }
     }
 }
+
+/// collect every chunk of query without writing files, concatenating the dataframes of each
+/// datatype in chunk order
+pub async fn collect_dataframes(
+    query: Arc<Query>,
+    source: Arc<Source>,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    query.is_valid()?;
+    let mut chunks = chunk_results(query, source, true);
+    let mut output: HashMap<Datatype, DataFrame> = HashMap::new();
+    while let Some((_, dfs)) = chunks.next().await {
+        for (datatype, df) in dfs? {
+            match output.get_mut(&datatype) {
+                Some(output_df) => {
+                    output_df.vstack_mut(&df).map_err(CollectError::PolarsError)?;
+                }
+                None => {
+                    output.insert(datatype, df);
+                }
+            }
+        }
+    }
+    for df in output.values_mut() {
+        df.align_chunks();
+    }
+    Ok(output)
+}
+
+/// collect every chunk of query without writing files, receiving the dataframes of each chunk
+/// as soon as the chunk completes. collection stops once the receiver is dropped
+pub fn stream_dataframes(
+    query: Arc<Query>,
+    source: Arc<Source>,
+) -> mpsc::Receiver<Result<CollectedChunk, CollectError>> {
+    let (sender, receiver) = mpsc::channel(1);
+    tokio::spawn(async move {
+        if let Err(e) = query.is_valid() {
+            let _ = sender.send(Err(e)).await;
+            return
+        }
+        let mut chunks = chunk_results(query, source, false);
+        while let Some((partition, dfs)) = chunks.next().await {
+            let results: Vec<_> = match dfs {
+                Ok(dfs) => dfs
+                    .into_iter()
+                    .map(|(datatype, df)| Ok((partition.clone(), datatype, df)))
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            };
+            for result in results {
+                if sender.send(result).await.is_err() {
+                    return
+                }
+            }
+        }
+    });
+    receiver
+}
+
+type ChunkResult = (Partition, Result<HashMap<Datatype, DataFrame>, CollectError>);
+
+/// collect the datasets of each partition of query, at most max_concurrent_chunks at a time,
+/// yielding chunks in the order of the query if ordered, or as they complete otherwise
+fn chunk_results(
+    query: Arc<Query>,
+    source: Arc<Source>,
+    ordered: bool,
+) -> std::pin::Pin<Box<dyn Stream<Item = ChunkResult> + Send>> {
+    let max_concurrent_chunks =
+        source.max_concurrent_chunks.map(|n| n.max(1) as usize).unwrap_or(usize::MAX);
+    let payloads: Vec<(Partition, MetaDatatype)> = query
+        .partitions
+        .iter()
+        .flat_map(|partition| {
+            query.datatypes.iter().map(move |datatype| (partition.clone(), datatype.clone()))
+        })
+        .collect();
+    let chunks = futures::stream::iter(payloads).map(move |(partition, datatype)| {
+        let (query, source) = (query.clone(), source.clone());
+        async move {
+            let chunk = collect_chunk(datatype, partition.clone(), query, source);
+            let mut task = ChunkTask(tokio::spawn(chunk));
+            let dfs = match (&mut task.0).await {
+                Ok(result) => result.map(|(dfs, _max_block_hash)| dfs),
+                Err(_) => Err(err("error joining chunks")),
+            };
+            (partition, dfs)
+        }
+    });
+    match ordered {
+        true => Box::pin(chunks.buffered(max_concurrent_chunks)),
+        false => Box::pin(chunks.buffer_unordered(max_concurrent_chunks)),
+    }
+}
+
+/// spawned chunk that is aborted when dropped, so that dropping a stream of chunks stops the
+/// collection of its chunks in flight
+struct ChunkTask<T>(tokio::task::JoinHandle<T>);
+
+impl<T> Drop for ChunkTask<T> {
+    fn drop(&mut self) {
+        self.0.abort()
+    }
+}
+
+/// collect the dataframes of a chunk, verifying the block hashes of its dataframes if
+/// query.verify_hashes is set, along with the hash of the last block of the chunk
+pub(crate) async fn collect_chunk(
+    datatype: MetaDatatype,
+    partition: Partition,
+    query: Arc<Query>,
+    source: Arc<Source>,
+) -> Result<(HashMap<Datatype, DataFrame>, Option<H256>), CollectError> {
+    let dfs = collect_partition(datatype, partition.clone(), query.clone(), source.clone()).await?;
+    let max_block_hash = match query.verify_hashes {
+        Some(mode) => verify::verify_block_hashes(&partition, &dfs, &query, &source, mode).await?,
+        None => None,
+    };
+    Ok((dfs, max_block_hash))
+}
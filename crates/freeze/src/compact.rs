@@ -0,0 +1,285 @@
+use crate::{
+    coverage::{list_range_files, RangeFile},
+    dataframes, err, BlockChunk, ChunkData, CollectError, FileError, FileOutput,
+};
+use polars::prelude::*;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// size that compacted files grow to, files stop growing once either limit would be exceeded
+#[derive(Clone, Debug, Default)]
+pub struct CompactTarget {
+    /// maximum number of rows of a compacted file
+    pub rows: Option<u64>,
+    /// maximum total size in bytes of the files merged into a compacted file
+    pub bytes: Option<u64>,
+}
+
+/// file written by compaction
+#[derive(Clone, Debug)]
+pub struct CompactedFile {
+    /// path of compacted file
+    pub path: PathBuf,
+    /// files merged into compacted file, which have been deleted
+    pub inputs: Vec<PathBuf>,
+    /// number of rows of compacted file
+    pub n_rows: u64,
+}
+
+/// merge contiguous parquet files of each dataset in a directory into larger files
+///
+/// files are only merged with neighbors of the same dataset whose block ranges are adjacent and
+/// whose schemas are identical, and originals are only deleted after the row count of the merged
+/// file has been verified
+pub fn compact(
+    dir: &Path,
+    sink: &FileOutput,
+    target: &CompactTarget,
+) -> Result<Vec<CompactedFile>, CollectError> {
+    let mut groups: BTreeMap<String, Vec<RangeFile>> = BTreeMap::new();
+    for file in list_range_files(dir).into_iter().filter(|file| file.extension == "parquet") {
+        groups.entry(file.group.clone()).or_default().push(file);
+    }
+    let mut compacted = Vec::new();
+    for (group, mut files) in groups.into_iter() {
+        files.sort_by_key(|file| (file.start, file.end));
+        for run in plan_runs(files, target)? {
+            if run.len() < 2 {
+                continue
+            }
+            let (start, end) = (run[0].file.start, run[run.len() - 1].file.end);
+            let label = BlockChunk::Range(start, end)
+                .stub()
+                .map_err(|_| err("could not determine name of compacted file"))?;
+            let path = dir.join(format!("{}__{}.parquet", group, label));
+            // a file of the same name outside of the run would be overwritten
+            if path.exists() {
+                continue
+            }
+            compacted.push(merge_run(&run, &path, sink)?);
+        }
+    }
+    Ok(compacted)
+}
+
+struct RunFile {
+    file: RangeFile,
+    n_rows: u64,
+    n_bytes: u64,
+    schema: Vec<polars::export::arrow::datatypes::Field>,
+    key_value_metadata: Vec<(String, String)>,
+}
+
+/// split sorted files of a dataset into runs of files that can be merged
+fn plan_runs(
+    files: Vec<RangeFile>,
+    target: &CompactTarget,
+) -> Result<Vec<Vec<RunFile>>, CollectError> {
+    let mut runs: Vec<Vec<RunFile>> = Vec::new();
+    let mut run: Vec<RunFile> = Vec::new();
+    let (mut run_rows, mut run_bytes) = (0, 0);
+    for file in files.into_iter() {
+        let file = read_run_file(file)?;
+        let fits = match run.last() {
+            Some(last) => {
+                last.file.end.checked_add(1) == Some(file.file.start) &&
+                    last.schema == file.schema &&
+                    target.rows.map(|rows| run_rows + file.n_rows <= rows).unwrap_or(true) &&
+                    target.bytes.map(|bytes| run_bytes + file.n_bytes <= bytes).unwrap_or(true)
+            }
+            None => true,
+        };
+        if !fits {
+            runs.push(std::mem::take(&mut run));
+            (run_rows, run_bytes) = (0, 0);
+        }
+        run_rows += file.n_rows;
+        run_bytes += file.n_bytes;
+        run.push(file);
+    }
+    runs.push(run);
+    Ok(runs)
+}
+
+fn read_run_file(file: RangeFile) -> Result<RunFile, CollectError> {
+    let message = format!("could not read parquet metadata of {}", file.path.display());
+    let mut reader = std::fs::File::open(&file.path).map_err(|_| err(&message))?;
+    let n_bytes = reader.metadata().map_err(|_| err(&message))?.len();
+    let metadata = polars_parquet::read::read_metadata(&mut reader).map_err(|_| err(&message))?;
+    let schema = polars_parquet::read::infer_schema(&metadata).map_err(|_| err(&message))?;
+    let key_value_metadata = metadata
+        .key_value_metadata
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.key.starts_with(dataframes::METADATA_PREFIX))
+        .filter_map(|entry| Some((entry.key, entry.value?)))
+        .collect();
+    Ok(RunFile {
+        file,
+        n_rows: metadata.num_rows as u64,
+        n_bytes,
+        schema: schema.fields,
+        key_value_metadata,
+    })
+}
+
+/// write run of files into one file, then delete the originals
+fn merge_run(
+    run: &[RunFile],
+    path: &Path,
+    sink: &FileOutput,
+) -> Result<CompactedFile, CollectError> {
+    let mut merged: Option<DataFrame> = None;
+    for input in run.iter() {
+        let file = std::fs::File::open(&input.file.path)
+            .map_err(|_| err(&format!("could not open {}", input.file.path.display())))?;
+        let df = ParquetReader::new(file)
+            .finish()
+            .map_err(|_| err(&format!("could not read {}", input.file.path.display())))?;
+        merged = match merged {
+            Some(mut merged) => {
+                merged.vstack_mut(&df).map_err(|_| err("incompatible schemas in compaction"))?;
+                Some(merged)
+            }
+            None => Some(df),
+        };
+    }
+    let mut df = merged.ok_or(err("no files to compact"))?;
+    df.align_chunks();
+
+    // keep collection metadata of the first file, with the block range of the merged file
+    let (start, end) = (run[0].file.start, run[run.len() - 1].file.end);
+    let is_range_key = |key: &str| {
+        key.ends_with(".min_block") ||
+            key.ends_with(".max_block") ||
+            key.ends_with(".max_block_hash")
+    };
+    let mut sink = sink.clone();
+    sink.key_value_metadata =
+        run[0].key_value_metadata.iter().filter(|(key, _)| !is_range_key(key)).cloned().collect();
+    if sink.key_value_metadata.iter().any(|(key, _)| key.ends_with(".version")) {
+        let prefix = dataframes::METADATA_PREFIX;
+        sink.key_value_metadata.push((format!("{}min_block", prefix), start.to_string()));
+        sink.key_value_metadata.push((format!("{}max_block", prefix), end.to_string()));
+    }
+    let last_hash = run[run.len() - 1]
+        .key_value_metadata
+        .iter()
+        .find(|(key, _)| key.ends_with(".max_block_hash"));
+    if let Some(last_hash) = last_hash {
+        sink.key_value_metadata.push(last_hash.clone());
+    }
+    dataframes::df_to_file(&mut df, path, &sink).map_err(|e| match e {
+        FileError::UnsupportedColumn(message) => CollectError::CollectError(message),
+        _ => err("error writing compacted file"),
+    })?;
+
+    // verify row count before removing originals
+    let expected_rows: u64 = run.iter().map(|input| input.n_rows).sum();
+    let n_rows = std::fs::File::open(path)
+        .ok()
+        .and_then(|mut file| polars_parquet::read::read_metadata(&mut file).ok())
+        .map(|metadata| metadata.num_rows as u64);
+    if n_rows != Some(expected_rows) {
+        let _ = std::fs::remove_file(path);
+        return Err(err(&format!("row count mismatch when compacting into {}", path.display())))
+    }
+    let mut inputs = Vec::new();
+    for input in run.iter() {
+        std::fs::remove_file(&input.file.path)
+            .map_err(|_| err(&format!("could not remove {}", input.file.path.display())))?;
+        inputs.push(input.file.path.clone());
+    }
+    if let Some(manifest_path) = &sink.checksum_manifest {
+        dataframes::remove_manifest_entries(manifest_path, &inputs)
+            .map_err(|_| err("could not update checksum manifest"))?;
+    }
+    Ok(CompactedFile { path: path.to_path_buf(), inputs, n_rows: expected_rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coverage::CompactedRanges;
+
+    fn file_output(dir: &Path) -> FileOutput {
+        FileOutput { overwrite: false, ..FileOutput::test_output(dir) }
+    }
+
+    fn write(dir: &Path, name: &str, mut df: DataFrame) {
+        let file = std::fs::File::create(dir.join(name)).unwrap();
+        ParquetWriter::new(file).finish(&mut df).unwrap();
+    }
+
+    #[test]
+    fn test_compact() {
+        let dir = std::env::temp_dir().join(format!("cryo_compact_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let blocks = |start: u32| df!("block_number" => [start, start + 1]).unwrap();
+        write(&dir, "ethereum__blocks__00000000_to_00000001.parquet", blocks(0));
+        write(&dir, "ethereum__blocks__00000002_to_00000003.parquet", blocks(2));
+        write(&dir, "ethereum__blocks__00000004_to_00000005.parquet", blocks(4));
+        // gap in block range
+        write(&dir, "ethereum__blocks__00000008_to_00000009.parquet", blocks(8));
+        // incompatible schema
+        let other = df!("block_number" => [10u64, 11]).unwrap();
+        write(&dir, "ethereum__blocks__00000010_to_00000011.parquet", other);
+        write(&dir, "ethereum__logs__00000000_to_00000001.parquet", blocks(0));
+
+        let target = CompactTarget { rows: Some(4), bytes: None };
+        let compacted = compact(&dir, &file_output(&dir), &target).unwrap();
+        assert_eq!(compacted.len(), 1);
+        assert_eq!(compacted[0].inputs.len(), 2);
+        assert_eq!(compacted[0].n_rows, 4);
+
+        let mut names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "ethereum__blocks__00000000_to_00000003.parquet",
+                "ethereum__blocks__00000004_to_00000005.parquet",
+                "ethereum__blocks__00000008_to_00000009.parquet",
+                "ethereum__blocks__00000010_to_00000011.parquet",
+                "ethereum__logs__00000000_to_00000001.parquet",
+            ]
+        );
+        let file = std::fs::File::open(dir.join(&names[0])).unwrap();
+        let df = ParquetReader::new(file).finish().unwrap();
+        assert!(df.frame_equal(&df!("block_number" => [0u32, 1, 2, 3]).unwrap()));
+
+        // compacted files cover the chunks they were merged from
+        let ranges = CompactedRanges::default();
+        assert!(ranges.covers(&dir.join("ethereum__blocks__00000002_to_00000003.parquet")));
+        assert!(!ranges.covers(&dir.join("ethereum__blocks__00000006_to_00000007.parquet")));
+        assert!(!ranges.covers(&dir.join("ethereum__blocks__00000002_to_00000003.csv")));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_checksum_manifest() {
+        let dir =
+            std::env::temp_dir().join(format!("cryo_compact_manifest_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest_path = dir.join(dataframes::CHECKSUM_MANIFEST);
+        let sink = FileOutput { checksum_manifest: Some(manifest_path), ..file_output(&dir) };
+        for start in [0u32, 2] {
+            let path =
+                dir.join(format!("ethereum__blocks__{:08}_to_{:08}.parquet", start, start + 1));
+            let mut df = df!("block_number" => [start, start + 1]).unwrap();
+            dataframes::df_to_file(&mut df, &path, &sink).unwrap();
+        }
+
+        let compacted = compact(&dir, &sink, &CompactTarget::default()).unwrap();
+        assert_eq!(compacted.len(), 1);
+        let checks = dataframes::verify_manifest(&dir).unwrap();
+        let expected = PathBuf::from("ethereum__blocks__00000000_to_00000003.parquet");
+        assert_eq!(checks, vec![dataframes::ManifestCheck::Verified(expected)]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{err, CollectError, Datatype, FileOutput};
+use polars::prelude::*;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// existing output file and the block range that it covers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoveredFile {
+    /// path of file, or of the manifest of a partitioned or split chunk
+    pub path: PathBuf,
+    /// first block of file
+    pub start: u64,
+    /// last block of file
+    pub end: u64,
+}
+
+/// existing chunk files of a datatype, with block ranges given by their filenames or, for chunks
+/// labeled by other dimensions, by the block numbers of parquet files
+pub fn covered_files(
+    sink: &FileOutput,
+    datatype: &Datatype,
+) -> Result<Vec<CoveredFile>, CollectError> {
+    let dir = sink.chunk_dir(datatype);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(vec![]),
+    };
+    let prefix = format!("{}__", sink.chunk_prefix(datatype));
+    let extension = format!(".{}", sink.chunk_extension());
+    let mut covered = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let filename = entry.file_name().to_string_lossy().to_string();
+        // skipped chunks without rows are covered by their empty chunk marker
+        let (filename, is_marker) = match filename.strip_suffix(".empty") {
+            Some(filename) => (filename.to_string(), true),
+            None => (filename, false),
+        };
+        if !filename.starts_with(&prefix) || !filename.ends_with(&extension) {
+            continue
+        }
+        let range = match RangeFile::parse(&path.with_file_name(&filename)) {
+            Some(file) if file.group == sink.chunk_prefix(datatype) => Some((file.start, file.end)),
+            _ if extension == ".parquet" && !is_marker => read_block_range(&path)?,
+            _ => None,
+        };
+        if let Some((start, end)) = range {
+            covered.push(CoveredFile { path, start, end });
+        }
+    }
+    covered.sort_by_key(|file| (file.start, file.end));
+    Ok(covered)
+}
+
+/// minimum and maximum block number of a parquet file, None for files without block numbers
+fn read_block_range(path: &Path) -> Result<Option<(u64, u64)>, CollectError> {
+    let message = format!("could not read block numbers of {}", path.display());
+    let file = std::fs::File::open(path).map_err(|_| err(&message))?;
+    let has_block_numbers = ParquetReader::new(file)
+        .schema()
+        .map(|schema| schema.fields.iter().any(|field| field.name == "block_number"))
+        .map_err(|_| err(&message))?;
+    if !has_block_numbers {
+        return Ok(None)
+    }
+    let file = std::fs::File::open(path).map_err(|_| err(&message))?;
+    let df = ParquetReader::new(file)
+        .with_columns(Some(vec!["block_number".to_string()]))
+        .finish()
+        .map_err(|_| err(&message))?;
+    let blocks = df
+        .column("block_number")
+        .and_then(|column| column.cast(&DataType::UInt64))
+        .map_err(|_| err(&message))?;
+    let blocks = blocks.u64().map_err(|_| err(&message))?;
+    Ok(blocks.min().zip(blocks.max()))
+}
+
+/// output file named after the block range of its rows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RangeFile {
+    pub(crate) path: PathBuf,
+    /// filename without the block range and extension, e.g. ethereum__blocks
+    pub(crate) group: String,
+    pub(crate) extension: String,
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+}
+
+impl RangeFile {
+    /// parse filename of the form {group}__{start}_to_{end}.{extension}
+    pub(crate) fn parse(path: &Path) -> Option<RangeFile> {
+        let (group, label) = path.file_name()?.to_str()?.rsplit_once("__")?;
+        // extensions of compressed files have several parts, e.g. csv.gz
+        let (label, extension) = label.split_once('.')?;
+        let extension = extension.to_string();
+        let (start, end) = label.split_once("_to_")?;
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        if !is_number(start) || !is_number(end) || !group.contains("__") {
+            return None
+        }
+        let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+        let path = path.to_path_buf();
+        (start <= end).then_some(RangeFile {
+            path,
+            group: group.to_string(),
+            extension,
+            start,
+            end,
+        })
+    }
+
+    fn covers(&self, other: &RangeFile) -> bool {
+        self.group == other.group &&
+            self.extension == other.extension &&
+            self.start <= other.start &&
+            self.end >= other.end
+    }
+}
+
+/// cached block ranges of files in output directories, used to recognize compacted chunks
+#[derive(Default)]
+pub(crate) struct CompactedRanges {
+    dirs: RefCell<HashMap<PathBuf, Vec<RangeFile>>>,
+}
+
+impl CompactedRanges {
+    /// whether a file in the directory of path covers the block range of path
+    pub(crate) fn covers(&self, path: &Path) -> bool {
+        let (target, dir) = match (RangeFile::parse(path), path.parent()) {
+            (Some(target), Some(dir)) => (target, dir),
+            _ => return false,
+        };
+        let mut dirs = self.dirs.borrow_mut();
+        let files = dirs.entry(dir.to_path_buf()).or_insert_with(|| list_range_files(dir));
+        files.iter().any(|file| file.covers(&target))
+    }
+
+    /// another file in the directory of path that covers the block range of path, e.g. a
+    /// compacted file that would duplicate the rows of path if path were written again
+    pub(crate) fn covering_file(&self, path: &Path) -> Option<PathBuf> {
+        let (target, dir) = match (RangeFile::parse(path), path.parent()) {
+            (Some(target), Some(dir)) => (target, dir),
+            _ => return None,
+        };
+        let mut dirs = self.dirs.borrow_mut();
+        let files = dirs.entry(dir.to_path_buf()).or_insert_with(|| list_range_files(dir));
+        files
+            .iter()
+            .find(|file| file.path != path && file.covers(&target))
+            .map(|file| file.path.clone())
+    }
+}
+
+pub(crate) fn list_range_files(dir: &Path) -> Vec<RangeFile> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|entry| RangeFile::parse(&entry.path()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covered_files() {
+        let dir = std::env::temp_dir().join(format!("cryo_coverage_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, blocks: Vec<u32>| {
+            let mut df = df!("block_number" => blocks).unwrap();
+            ParquetWriter::new(std::fs::File::create(dir.join(name)).unwrap())
+                .finish(&mut df)
+                .unwrap();
+        };
+        write("ethereum__blocks__00000000_to_00000999.parquet", vec![0, 999]);
+        // chunks labeled by another dimension are covered by their block numbers
+        write("ethereum__blocks__00001000_to_00001999__0x01.parquet", vec![1200, 1300]);
+        write("ethereum__logs__00002000_to_00002999.parquet", vec![2000]);
+        std::fs::write(dir.join("ethereum__blocks__00003000_to_00003999.parquet.empty"), "{}")
+            .unwrap();
+        let sink = FileOutput { overwrite: false, ..FileOutput::test_output(&dir) };
+        let covered = covered_files(&sink, &Datatype::Blocks).unwrap();
+        let ranges: Vec<_> = covered.iter().map(|file| (file.start, file.end)).collect();
+        assert_eq!(ranges, vec![(0, 999), (1200, 1300), (3000, 3999)]);
+
+        // chunks of a compacted file are covered by it, but not by themselves
+        let compacted_ranges = CompactedRanges::default();
+        let compacted = dir.join("ethereum__blocks__00000000_to_00000999.parquet");
+        let chunk = dir.join("ethereum__blocks__00000100_to_00000199.parquet");
+        assert!(compacted_ranges.covers(&chunk));
+        assert_eq!(compacted_ranges.covering_file(&chunk), Some(compacted.clone()));
+        assert_eq!(compacted_ranges.covering_file(&compacted), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
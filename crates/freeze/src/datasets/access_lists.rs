@@ -0,0 +1,131 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for access lists
+#[cryo_to_df::to_df(Datatype::AccessLists)]
+#[derive(Default)]
+pub struct AccessLists {
+    n_rows: u64,
+    block_number: Vec<Option<u64>>,
+    transaction_hash: Vec<Vec<u8>>,
+    transaction_index: Vec<Option<u64>>,
+    address: Vec<Vec<u8>>,
+    storage_key: Vec<Option<Vec<u8>>>,
+    entry_index: Vec<u32>,
+    transaction_type: Vec<Option<u32>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for AccessLists {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index", "entry_index"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for AccessLists {
+    type Response = Vec<Transaction>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .fetcher
+            .get_block_with_txs(request.block_number()?)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        Ok(block.transactions)
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::AccessLists)?;
+        process_access_lists(response, columns, schema)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for AccessLists {
+    type Response = Vec<Transaction>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let transaction = source
+            .fetcher
+            .get_transaction(request.ethers_transaction_hash()?)
+            .await?
+            .ok_or(CollectError::CollectError("transaction not found".to_string()))?;
+        Ok(vec![transaction])
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::AccessLists)?;
+        process_access_lists(response, columns, schema)
+    }
+}
+
+/// expand access list entries into rows, one row per storage key or one row for address-only
+/// entries. transactions without an access list have no rows
+fn process_access_lists(
+    transactions: Vec<Transaction>,
+    columns: &mut AccessLists,
+    schema: &Table,
+) -> R<()> {
+    for tx in transactions.into_iter() {
+        let access_list = match tx.access_list {
+            Some(access_list) => access_list,
+            None => continue,
+        };
+        for (entry_index, item) in access_list.0.into_iter().enumerate() {
+            let storage_keys: Vec<Option<H256>> = if item.storage_keys.is_empty() {
+                vec![None]
+            } else {
+                item.storage_keys.into_iter().map(Some).collect()
+            };
+            for storage_key in storage_keys.into_iter() {
+                columns.n_rows += 1;
+                store!(schema, columns, block_number, tx.block_number.map(|x| x.as_u64()));
+                store!(schema, columns, transaction_hash, tx.hash.as_bytes().to_vec());
+                store!(
+                    schema,
+                    columns,
+                    transaction_index,
+                    tx.transaction_index.map(|x| x.as_u64())
+                );
+                store!(schema, columns, address, item.address.as_bytes().to_vec());
+                store!(schema, columns, storage_key, storage_key.map(|x| x.as_bytes().to_vec()));
+                store!(schema, columns, entry_index, entry_index as u32);
+                store!(schema, columns, transaction_type, tx.transaction_type.map(|x| x.as_u32()));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::transaction::eip2930::{AccessList, AccessListItem};
+
+    #[test]
+    fn test_process_access_lists() {
+        let schema = Datatype::AccessLists
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None, None)
+            .unwrap();
+        let access_list = AccessList(vec![
+            AccessListItem { address: Address::repeat_byte(1), storage_keys: vec![] },
+            AccessListItem {
+                address: Address::repeat_byte(2),
+                storage_keys: vec![H256::repeat_byte(3), H256::repeat_byte(4)],
+            },
+        ]);
+        let transactions = vec![
+            Transaction { access_list: Some(access_list), ..Default::default() },
+            Transaction::default(),
+        ];
+        let mut columns = AccessLists::default();
+        process_access_lists(transactions, &mut columns, &schema).unwrap();
+        assert_eq!(columns.n_rows, 3);
+        assert_eq!(columns.entry_index, vec![0, 1, 1]);
+        assert_eq!(columns.storage_key[0], None);
+        assert_eq!(columns.storage_key[2], Some(H256::repeat_byte(4).as_bytes().to_vec()));
+    }
+}
@@ -1,18 +1,25 @@
 use crate::*;
 use ethers::prelude::*;
 use polars::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::AddressAppearances)]
 #[derive(Default)]
 pub struct AddressAppearances {
     n_rows: usize,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Vec<u8>>,
-    transaction_hash: Vec<Vec<u8>>,
+    transaction_hash: Vec<Option<Vec<u8>>>,
     address: Vec<Vec<u8>>,
     relationship: Vec<String>,
+    n_as_tx_from: Vec<u32>,
+    n_as_tx_to: Vec<u32>,
+    n_in_trace_from: Vec<u32>,
+    n_in_trace_to: Vec<u32>,
+    n_in_log_topics: Vec<u32>,
+    n_in_log_data: Vec<u32>,
+    n_as_withdrawal_recipient: Vec<u32>,
     chain_id: Vec<u64>,
 }
 
@@ -51,8 +58,12 @@ impl CollectByBlock for AddressAppearances {
             },
             ..Default::default()
         };
-        let logs = source.fetcher.get_logs(&filter).await?;
-        let traces = source.fetcher.trace_block(request.block_number()?.into()).await?;
+        // logs of block receipts avoid a log query on nodes with eth_getBlockReceipts
+        let logs = match source.get_block_receipts(request.block_number()?).await? {
+            Some(receipts) => receipts.into_iter().flat_map(|receipt| receipt.logs).collect(),
+            None => source.fetcher.get_logs(&filter).await?,
+        };
+        let traces = source.trace_block(request.block_number()?).await?;
         Ok((block, logs, traces))
     }
 
@@ -92,9 +103,10 @@ impl CollectByTransaction for AddressAppearances {
             .logs;
 
         // traces
-        let traces = source.fetcher.trace_transaction(request.ethers_transaction_hash()?).await?;
+        let traces = source.trace_transaction(request.ethers_transaction_hash()?).await?;
 
-        Ok((block, logs, traces))
+        // withdrawals are not part of any transaction
+        Ok((Block { withdrawals: None, ..block }, logs, traces))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -106,7 +118,7 @@ impl CollectByTransaction for AddressAppearances {
 fn name(log: &Log) -> Option<&'static str> {
     let event = log.topics[0];
     if event == *EVENT_ERC20_TRANSFER {
-        if log.data.len() > 0 {
+        if !log.data.is_empty() {
             Some("erc20_transfer")
         } else if log.topics.len() == 4 {
             Some("erc721_transfer")
@@ -126,10 +138,19 @@ impl AddressAppearances {
         schema: &Table,
         tx_hash: H256,
         logs_by_tx: &HashMap<H256, Vec<Log>>,
+        source_counts: &SourceCounts,
     ) {
-        let block_number = trace.block_number as u32;
+        let block_number = trace.block_number;
         let block_hash = trace.block_hash.as_bytes().to_vec();
-        self.process_address(block_author, "miner_fee", block_number, &block_hash, tx_hash, schema);
+        self.process_address(
+            block_author,
+            "miner_fee",
+            block_number,
+            &block_hash,
+            Some(tx_hash),
+            schema,
+            source_counts,
+        );
 
         if let Some(logs) = logs_by_tx.get(&tx_hash) {
             for log in logs.iter() {
@@ -138,26 +159,26 @@ impl AddressAppearances {
                         let mut from: [u8; 20] = [0; 20];
                         from.copy_from_slice(&log.topics[1].to_fixed_bytes()[12..32]);
 
-                        let name = &(name.to_string() + "_from");
                         self.process_address(
                             H160(from),
-                            name,
+                            &(name.to_string() + "_from"),
                             block_number,
                             &block_hash,
-                            tx_hash,
+                            Some(tx_hash),
                             schema,
+                            source_counts,
                         );
 
                         let mut to: [u8; 20] = [0; 20];
-                        to.copy_from_slice(&log.topics[1].to_fixed_bytes()[12..32]);
-                        let name = &(name.to_string() + "_to");
+                        to.copy_from_slice(&log.topics[2].to_fixed_bytes()[12..32]);
                         self.process_address(
                             H160(to),
-                            name,
+                            &(name.to_string() + "_to"),
                             block_number,
                             &block_hash,
-                            tx_hash,
+                            Some(tx_hash),
                             schema,
+                            source_counts,
                         );
                     }
                 }
@@ -171,16 +192,18 @@ impl AddressAppearances {
                     "tx_from",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
                 self.process_address(
                     action.to,
                     "tx_to",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
             }
             Action::Create(action) => {
@@ -189,8 +212,9 @@ impl AddressAppearances {
                     "tx_from",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
             }
             _ => {}
@@ -202,14 +226,21 @@ impl AddressAppearances {
                 "tx_to",
                 block_number,
                 &block_hash,
-                tx_hash,
+                Some(tx_hash),
                 schema,
+                source_counts,
             );
         }
     }
 
-    fn process_trace(&mut self, trace: &Trace, schema: &Table, tx_hash: H256) {
-        let block_number = trace.block_number as u32;
+    fn process_trace(
+        &mut self,
+        trace: &Trace,
+        schema: &Table,
+        tx_hash: H256,
+        source_counts: &SourceCounts,
+    ) {
+        let block_number = trace.block_number;
         let block_hash = trace.block_hash.as_bytes().to_vec();
         match &trace.action {
             Action::Call(action) => {
@@ -218,16 +249,18 @@ impl AddressAppearances {
                     "call_from",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
                 self.process_address(
                     action.to,
                     "call_to",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
             }
             Action::Create(action) => {
@@ -236,8 +269,9 @@ impl AddressAppearances {
                     "factory",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
             }
             Action::Suicide(action) => {
@@ -246,16 +280,18 @@ impl AddressAppearances {
                     "suicide",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
                 self.process_address(
                     action.refund_address,
                     "suicide_refund",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
             }
             Action::Reward(action) => {
@@ -264,8 +300,9 @@ impl AddressAppearances {
                     "author",
                     block_number,
                     &block_hash,
-                    tx_hash,
+                    Some(tx_hash),
                     schema,
+                    source_counts,
                 );
             }
         }
@@ -276,28 +313,127 @@ impl AddressAppearances {
                 "create",
                 block_number,
                 &block_hash,
-                tx_hash,
+                Some(tx_hash),
                 schema,
+                source_counts,
             );
         };
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn process_address(
         &mut self,
         address: H160,
         relationship: &str,
-        block_number: u32,
+        block_number: u64,
         block_hash: &[u8],
-        transaction_hash: H256,
+        transaction_hash: Option<H256>,
         schema: &Table,
+        source_counts: &SourceCounts,
     ) {
         self.n_rows += 1;
         store!(schema, self, address, address.as_bytes().to_vec());
         store!(schema, self, relationship, relationship.to_string());
         store!(schema, self, block_number, block_number);
         store!(schema, self, block_hash, block_hash.to_vec());
-        store!(schema, self, transaction_hash, transaction_hash.as_bytes().to_vec());
+        store!(schema, self, transaction_hash, transaction_hash.map(|x| x.as_bytes().to_vec()));
+
+        let counts = source_counts.get(&(transaction_hash, address)).cloned().unwrap_or_default();
+        store!(schema, self, n_as_tx_from, counts.tx_from);
+        store!(schema, self, n_as_tx_to, counts.tx_to);
+        store!(schema, self, n_in_trace_from, counts.trace_from);
+        store!(schema, self, n_in_trace_to, counts.trace_to);
+        store!(schema, self, n_in_log_topics, counts.log_topics);
+        store!(schema, self, n_in_log_data, counts.log_data);
+        store!(schema, self, n_as_withdrawal_recipient, counts.withdrawal_recipient);
+    }
+}
+
+/// number of times an address appears in each part of a transaction, or of the block itself for
+/// withdrawals and block rewards
+#[derive(Clone, Default, Debug, PartialEq)]
+struct AppearanceCounts {
+    tx_from: u32,
+    tx_to: u32,
+    trace_from: u32,
+    trace_to: u32,
+    log_topics: u32,
+    log_data: u32,
+    withdrawal_recipient: u32,
+}
+
+type SourceCounts = BTreeMap<(Option<H256>, H160), AppearanceCounts>;
+
+const SOURCE_COUNT_COLUMNS: [&str; 7] = [
+    "n_as_tx_from",
+    "n_as_tx_to",
+    "n_in_trace_from",
+    "n_in_trace_to",
+    "n_in_log_topics",
+    "n_in_log_data",
+    "n_as_withdrawal_recipient",
+];
+
+/// tally appearance sources of every address in each transaction of block, where log topics
+/// count as appearances when they equal an address padded to 32 bytes, and words of log data when
+/// they equal a padded address that appears elsewhere in the block, since small integers in log
+/// data are also padded with zeros
+fn count_appearance_sources(
+    withdrawals: &[Withdrawal],
+    logs: &[Log],
+    traces: &[Trace],
+) -> SourceCounts {
+    let mut counts = SourceCounts::new();
+    let mut current_tx_hash = None;
+    for trace in traces.iter() {
+        let tx_hash = trace.transaction_hash;
+        let first_in_tx = tx_hash.is_some() && tx_hash != current_tx_hash;
+        current_tx_hash = tx_hash;
+        let (from, to) = match (&trace.action, &trace.result) {
+            (Action::Call(action), _) => (Some(action.from), Some(action.to)),
+            (Action::Create(action), Some(Res::Create(result))) => {
+                (Some(action.from), Some(result.address))
+            }
+            (Action::Create(action), _) => (Some(action.from), None),
+            (Action::Suicide(action), _) => (Some(action.address), Some(action.refund_address)),
+            (Action::Reward(action), _) => (None, Some(action.author)),
+        };
+        if let Some(from) = from {
+            let entry = counts.entry((tx_hash, from)).or_default();
+            entry.trace_from += 1;
+            entry.tx_from += first_in_tx as u32;
+        }
+        if let Some(to) = to {
+            let entry = counts.entry((tx_hash, to)).or_default();
+            entry.trace_to += 1;
+            entry.tx_to += first_in_tx as u32;
+        }
+    }
+    for withdrawal in withdrawals.iter() {
+        counts.entry((None, withdrawal.address)).or_default().withdrawal_recipient += 1;
+    }
+
+    let padded_address = |word: &[u8]| match word[..12].iter().all(|byte| *byte == 0) {
+        true => Some(H160::from_slice(&word[12..])),
+        false => None,
+    };
+    for log in logs.iter() {
+        for topic in log.topics.iter().skip(1) {
+            if let Some(address) = padded_address(topic.as_bytes()) {
+                counts.entry((log.transaction_hash, address)).or_default().log_topics += 1;
+            }
+        }
+    }
+    let block_addresses: std::collections::HashSet<H160> =
+        counts.keys().map(|(_, address)| *address).collect();
+    for log in logs.iter() {
+        for word in log.data.chunks_exact(32) {
+            if let Some(address) = padded_address(word).filter(|x| block_addresses.contains(x)) {
+                counts.entry((log.transaction_hash, address)).or_default().log_data += 1;
+            }
+        }
     }
+    counts
 }
 
 fn process_appearances(
@@ -313,22 +449,162 @@ fn process_appearances(
         }
     }
 
-    let (_block_number, block_author) = match (block.number, block.author) {
+    let (block_number, block_author) = match (block.number, block.author) {
         (Some(number), Some(author)) => (number.as_u64(), author),
         _ => return Ok(()),
     };
 
+    // breakdowns are only tallied when one of their columns is requested
+    let source_counts = if SOURCE_COUNT_COLUMNS.iter().any(|column| schema.has_column(column)) {
+        let logs: Vec<Log> = logs_by_tx.values().flatten().cloned().collect();
+        count_appearance_sources(block.withdrawals.as_deref().unwrap_or_default(), &logs, &traces)
+    } else {
+        SourceCounts::new()
+    };
+
     let mut current_tx_hash = H256([0; 32]);
     for trace in traces.iter() {
         if let (Some(tx_hash), Some(_tx_pos)) = (trace.transaction_hash, trace.transaction_position)
         {
             if tx_hash != current_tx_hash {
-                columns.process_first_transaction(block_author, trace, schema, tx_hash, &logs_by_tx)
+                columns.process_first_transaction(
+                    block_author,
+                    trace,
+                    schema,
+                    tx_hash,
+                    &logs_by_tx,
+                    &source_counts,
+                )
             }
-            columns.process_trace(trace, schema, tx_hash);
+            columns.process_trace(trace, schema, tx_hash, &source_counts);
             current_tx_hash = tx_hash;
         }
     }
 
+    let block_hash = block.hash.map(|x| x.as_bytes().to_vec()).unwrap_or_default();
+    for withdrawal in block.withdrawals.iter().flatten() {
+        columns.process_address(
+            withdrawal.address,
+            "withdrawal",
+            block_number,
+            &block_hash,
+            None,
+            schema,
+            &source_counts,
+        );
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call_trace(from: H160, to: H160, tx: u8) -> Trace {
+        Trace {
+            action: Action::Call(ethers::types::Call { from, to, ..Default::default() }),
+            result: None,
+            trace_address: vec![],
+            subtraces: 0,
+            transaction_position: Some(tx as usize),
+            transaction_hash: Some(H256::repeat_byte(tx)),
+            block_number: 0,
+            block_hash: H256::zero(),
+            action_type: ActionType::Call,
+            error: None,
+        }
+    }
+
+    fn schema() -> Table {
+        let include = Some(SOURCE_COUNT_COLUMNS.iter().map(|x| x.to_string()).collect());
+        Datatype::AddressAppearances
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap()
+    }
+
+    fn block(withdrawals: Vec<Withdrawal>) -> Block<TxHash> {
+        Block {
+            number: Some(1.into()),
+            author: Some(H160::repeat_byte(9)),
+            withdrawals: Some(withdrawals),
+            ..Default::default()
+        }
+    }
+
+    fn transfer_log(from: H160, to: H160, tx: u8) -> Log {
+        Log {
+            topics: vec![*EVENT_ERC20_TRANSFER, H256::from(from), H256::from(to)],
+            data: H256::from(to).as_bytes().to_vec().into(),
+            transaction_hash: Some(H256::repeat_byte(tx)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_count_appearance_sources() {
+        let (alice, bob, token) =
+            (H160::repeat_byte(1), H160::repeat_byte(2), H160::repeat_byte(3));
+        let traces = vec![call_trace(alice, token, 1), call_trace(token, bob, 1)];
+        let withdrawal = Withdrawal { address: bob, ..Default::default() };
+        let counts =
+            count_appearance_sources(&[withdrawal], &[transfer_log(alice, bob, 1)], &traces);
+
+        let tx = Some(H256::repeat_byte(1));
+        let expected_alice =
+            AppearanceCounts { tx_from: 1, trace_from: 1, log_topics: 1, ..Default::default() };
+        let expected_bob =
+            AppearanceCounts { trace_to: 1, log_topics: 1, log_data: 1, ..Default::default() };
+        assert_eq!(counts[&(tx, alice)], expected_alice);
+        assert_eq!(counts[&(tx, bob)], expected_bob);
+        assert_eq!(counts[&(None, bob)].withdrawal_recipient, 1);
+        assert_eq!(counts[&(tx, token)].tx_to, 1);
+        assert_eq!(counts[&(tx, token)].trace_from, 1);
+    }
+
+    #[test]
+    fn test_counts_per_transaction() {
+        let (alice, token) = (H160::repeat_byte(1), H160::repeat_byte(3));
+        let traces = vec![call_trace(alice, token, 1), call_trace(alice, token, 2)];
+        let mut columns = AddressAppearances::default();
+        process_appearances((block(vec![]), vec![], traces), &mut columns, &schema()).unwrap();
+
+        let alice_counts: Vec<u32> = columns
+            .address
+            .iter()
+            .zip(columns.n_as_tx_from.iter())
+            .filter(|(address, _)| address.as_slice() == alice.as_bytes())
+            .map(|(_, count)| *count)
+            .collect();
+        assert_eq!(alice_counts, vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_counts_log_topics_of_unseen_addresses() {
+        let (alice, bob, token) =
+            (H160::repeat_byte(1), H160::repeat_byte(2), H160::repeat_byte(3));
+        let traces = vec![call_trace(alice, token, 1)];
+        let logs = vec![transfer_log(alice, bob, 1)];
+        let mut columns = AddressAppearances::default();
+        process_appearances((block(vec![]), logs, traces), &mut columns, &schema()).unwrap();
+
+        let index = columns.relationship.iter().position(|x| x == "erc20_transfer_to").unwrap();
+        assert_eq!(columns.address[index], bob.as_bytes().to_vec());
+        assert_eq!(columns.n_in_log_topics[index], 1);
+    }
+
+    #[test]
+    fn test_withdrawal_recipients() {
+        let carol = H160::repeat_byte(4);
+        let withdrawal = Withdrawal { address: carol, ..Default::default() };
+        let mut columns = AddressAppearances::default();
+        process_appearances((block(vec![withdrawal]), vec![], vec![]), &mut columns, &schema())
+            .unwrap();
+
+        assert_eq!(columns.n_rows, 1);
+        assert_eq!(columns.address, vec![carol.as_bytes().to_vec()]);
+        assert_eq!(columns.relationship, vec!["withdrawal".to_string()]);
+        assert_eq!(columns.transaction_hash, vec![None]);
+        assert_eq!(columns.n_as_withdrawal_recipient, vec![1]);
+    }
+}
@@ -7,8 +7,8 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct BalanceDiffs {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
-    pub(crate) transaction_index: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
+    pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) address: Vec<Vec<u8>>,
     pub(crate) from_value: Vec<U256>,
@@ -19,7 +19,8 @@ pub struct BalanceDiffs {
 #[async_trait::async_trait]
 impl Dataset for BalanceDiffs {}
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for BalanceDiffs {
@@ -28,11 +29,13 @@ impl CollectByBlock for BalanceDiffs {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::BalanceDiffs).ok_or(err("schema not provided"))?;
-        let include_txs = schema.has_column("transaction_hash");
-        source.fetcher.trace_block_state_diffs(request.block_number()? as u32, include_txs).await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source.trace_block_state_diffs(request.block_number()?, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::BalanceDiffs, &response.1);
         process_balance_diffs(&response, columns, &query.schemas)
     }
 }
@@ -41,11 +44,15 @@ impl CollectByBlock for BalanceDiffs {
 impl CollectByTransaction for BalanceDiffs {
     type Response = BlockTxsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::BalanceDiffs)?;
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
+        source.trace_transaction_state_diffs(request.transaction_hash()?, include_transaction).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::BalanceDiffs, &response.1);
         process_balance_diffs(&response, columns, &query.schemas)
     }
 }
@@ -56,8 +63,9 @@ pub(crate) fn process_balance_diffs(
     schemas: &Schemas,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::BalanceDiffs).ok_or(err("schema not provided"))?;
-    let (block_number, txs, traces) = response;
-    for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
+    let (block_number, indices, txs, traces) = response;
+    for (position, ((trace, tx), index)) in traces.iter().zip(txs).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
         if let Some(ethers::types::StateDiff(state_diffs)) = &trace.state_diff {
             for (addr, diff) in state_diffs.iter() {
                 process_balance_diff(addr, &diff.balance, block_number, tx, index, columns, schema);
@@ -70,9 +78,9 @@ pub(crate) fn process_balance_diffs(
 pub(crate) fn process_balance_diff(
     addr: &H160,
     diff: &Diff<U256>,
-    block_number: &Option<u32>,
+    block_number: &Option<u64>,
     transaction_hash: &Option<Vec<u8>>,
-    transaction_index: usize,
+    transaction_index: u64,
     columns: &mut BalanceDiffs,
     schema: &Table,
 ) {
@@ -84,7 +92,7 @@ pub(crate) fn process_balance_diff(
     };
     columns.n_rows += 1;
     store!(schema, columns, block_number, *block_number);
-    store!(schema, columns, transaction_index, Some(transaction_index as u32));
+    store!(schema, columns, transaction_index, Some(transaction_index));
     store!(schema, columns, transaction_hash, transaction_hash.clone());
     store!(schema, columns, address, addr.as_bytes().to_vec());
     store!(schema, columns, from_value, from);
@@ -1,13 +1,14 @@
 use crate::*;
 use ethers::prelude::*;
 use polars::prelude::*;
+use tokio::sync::mpsc;
 
 /// columns for balances
 #[cryo_to_df::to_df(Datatype::Balances)]
 #[derive(Default)]
 pub struct Balances {
     n_rows: usize,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     address: Vec<Vec<u8>>,
     balance: Vec<U256>,
     chain_id: Vec<u64>,
@@ -26,9 +27,13 @@ impl Dataset for Balances {
     fn default_blocks() -> Option<String> {
         Some("latest".to_string())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("address")
+    }
 }
 
-type BlockTxAddressOutput = (u32, Option<Vec<u8>>, Vec<u8>, U256);
+type BlockTxAddressOutput = (u64, Option<Vec<u8>>, Vec<u8>, U256);
 
 #[async_trait::async_trait]
 impl CollectByBlock for Balances {
@@ -36,7 +41,7 @@ impl CollectByBlock for Balances {
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let address = request.address()?;
-        let block_number = request.block_number()? as u32;
+        let block_number = request.block_number()?;
         let balance =
             source.fetcher.get_balance(H160::from_slice(&address), block_number.into()).await?;
         Ok((block_number, None, address, balance))
@@ -46,6 +51,65 @@ impl CollectByBlock for Balances {
         let schema = query.schemas.get(&Datatype::Balances).ok_or(err("schema not provided"))?;
         process_balance(columns, response, schema)
     }
+
+    async fn transform_channel(
+        receiver: mpsc::Receiver<R<Self::Response>>,
+        query: &Arc<Query>,
+    ) -> R<Self> {
+        transform_balances::<Self, _>(receiver, query, |(block, _, address, balance)| {
+            (*block, address.clone(), Some(*balance))
+        })
+        .await
+    }
+
+    // changes_only compares each balance with the previous block of the whole chunk
+    fn can_flush() -> bool {
+        false
+    }
+}
+
+/// transform balance responses of a chunk, streaming them as they arrive unless changes_only is
+/// set, in which case every response of the chunk is needed to compare consecutive blocks
+pub(crate) async fn transform_balances<T: CollectByBlock, K: Eq + std::hash::Hash>(
+    mut receiver: mpsc::Receiver<R<T::Response>>,
+    query: &Arc<Query>,
+    block_key_balance: impl Fn(&T::Response) -> (u64, K, Option<U256>),
+) -> R<T> {
+    let mut columns = T::default();
+    if query.changes_only {
+        for response in receive_changed_balances(receiver, block_key_balance).await?.into_iter() {
+            T::transform(response, &mut columns, query)?;
+        }
+    } else {
+        while let Some(message) = receiver.recv().await {
+            T::transform(message?, &mut columns, query)?;
+        }
+    }
+    Ok(columns)
+}
+
+/// receive all balance responses of a chunk, keeping only responses whose balance differs from
+/// the previous sampled block of the same key
+///
+/// responses arrive out of order so they are sorted by block first, and the first block of each
+/// key in a chunk is always kept so that every chunk is usable on its own
+async fn receive_changed_balances<T, K: Eq + std::hash::Hash>(
+    mut receiver: mpsc::Receiver<R<T>>,
+    block_key_balance: impl Fn(&T) -> (u64, K, Option<U256>),
+) -> R<Vec<T>> {
+    let mut responses = Vec::new();
+    while let Some(message) = receiver.recv().await {
+        responses.push(message?);
+    }
+    responses.sort_by_key(|response| block_key_balance(response).0);
+    let mut previous = std::collections::HashMap::new();
+    Ok(responses
+        .into_iter()
+        .filter(|response| {
+            let (_, key, balance) = block_key_balance(response);
+            previous.insert(key, balance) != Some(balance)
+        })
+        .collect())
 }
 
 #[async_trait::async_trait]
@@ -61,3 +125,34 @@ fn process_balance(columns: &mut Balances, data: BlockTxAddressOutput, schema: &
     store!(schema, columns, balance, balance);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_receive_changed_balances() {
+        let (sender, receiver) = mpsc::channel::<R<BlockTxAddressOutput>>(8);
+        let (alice, bob) = (vec![1u8; 20], vec![2u8; 20]);
+        let responses = [
+            (3, alice.clone(), 5),
+            (1, alice.clone(), 5),
+            (2, alice.clone(), 5),
+            (4, alice.clone(), 7),
+            (2, bob.clone(), 0),
+            (3, bob.clone(), 0),
+        ];
+        for (block, address, balance) in responses.into_iter() {
+            sender.send(Ok((block, None, address, U256::from(balance)))).await.unwrap();
+        }
+        drop(sender);
+        let kept = receive_changed_balances(receiver, |(block, _, address, balance)| {
+            (*block, address.clone(), Some(*balance))
+        })
+        .await
+        .unwrap();
+        let kept: Vec<_> =
+            kept.into_iter().map(|(block, _, address, _)| (block, address)).collect();
+        assert_eq!(kept, vec![(1, alice.clone()), (2, bob), (4, alice)]);
+    }
+}
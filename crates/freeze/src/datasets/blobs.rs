@@ -0,0 +1,110 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for blobs
+#[cryo_to_df::to_df(Datatype::Blobs)]
+#[derive(Default)]
+pub struct Blobs {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    transaction_hash: Vec<Vec<u8>>,
+    blob_index: Vec<u32>,
+    versioned_hash: Vec<Vec<u8>>,
+    commitment: Vec<Option<Vec<u8>>>,
+    proof: Vec<Option<Vec<u8>>>,
+    blob_data: Vec<Option<Vec<u8>>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Blobs {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "transaction_hash",
+            "blob_index",
+            "versioned_hash",
+            "commitment",
+            "chain_id",
+        ])
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "blob_index"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for Blobs {
+    type Response = (Block<Transaction>, Vec<BlobSidecar>);
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let block = source
+            .fetcher
+            .get_block_with_txs(block_number)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+
+        // sidecars are only needed for columns not stored in the transactions themselves,
+        // proofs are only served alongside blob bodies so they also require the full sidecars
+        let schema = query.schemas.get_schema(&Datatype::Blobs)?;
+        let include_blobs = schema.has_column("blob_data") || schema.has_column("proof");
+        let has_blobs = block.transactions.iter().any(|tx| !blob_versioned_hashes(tx).is_empty());
+        let sidecars = if has_blobs && (include_blobs || schema.has_column("commitment")) {
+            let timestamp = block.timestamp.as_u64();
+            source.get_blob_sidecars(block_number, timestamp, include_blobs).await?
+        } else {
+            Vec::new()
+        };
+        Ok((block, sidecars))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Blobs)?;
+        let (block, sidecars) = response;
+        let block_number = block.number.ok_or(err("block number not found"))?.as_u64();
+        let sidecars: std::collections::HashMap<u64, BlobSidecar> =
+            sidecars.into_iter().map(|sidecar| (sidecar.index, sidecar)).collect();
+
+        // blobs are indexed in order of appearance of versioned hashes within the block
+        let mut blob_index = 0;
+        for tx in block.transactions.iter() {
+            for versioned_hash in blob_versioned_hashes(tx).into_iter() {
+                let sidecar = sidecars.get(&blob_index);
+                columns.n_rows += 1;
+                store!(schema, columns, block_number, block_number);
+                store!(schema, columns, transaction_hash, tx.hash.as_bytes().to_vec());
+                store!(schema, columns, blob_index, blob_index as u32);
+                store!(schema, columns, versioned_hash, versioned_hash.as_bytes().to_vec());
+                store!(schema, columns, commitment, sidecar.map(|s| s.kzg_commitment.to_vec()));
+                store!(
+                    schema,
+                    columns,
+                    proof,
+                    sidecar.and_then(|s| s.kzg_proof.as_ref().map(|x| x.to_vec()))
+                );
+                store!(
+                    schema,
+                    columns,
+                    blob_data,
+                    sidecar.and_then(|s| s.blob.as_ref().map(|x| x.to_vec()))
+                );
+                blob_index += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn blob_versioned_hashes(tx: &Transaction) -> Vec<H256> {
+    match tx.other.get_deserialized::<Vec<H256>>("blobVersionedHashes") {
+        Some(Ok(hashes)) => hashes,
+        _ => Vec::new(),
+    }
+}
+
+impl CollectByTransaction for Blobs {
+    type Response = ();
+}
@@ -0,0 +1,144 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for block rewards
+#[cryo_to_df::to_df(Datatype::BlockRewards)]
+#[derive(Default)]
+pub struct BlockRewards {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    fee_recipient: Vec<Option<Vec<u8>>>,
+    static_reward: Vec<Option<U256>>,
+    uncle_inclusion_reward: Vec<Option<U256>>,
+    priority_fees: Vec<U256>,
+    base_fee_burned: Vec<Option<U256>>,
+    blob_fee_burned: Vec<Option<U256>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for BlockRewards {}
+
+/// block, receipts of its transactions, and chain id
+type BlockReceipts = (Block<Transaction>, Vec<TransactionReceipt>, u64);
+
+#[async_trait::async_trait]
+impl CollectByBlock for BlockRewards {
+    type Response = BlockReceipts;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .fetcher
+            .get_block_with_txs(request.block_number()?)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        let receipts = source.get_tx_receipts_in_block(&block).await?;
+        Ok((block, receipts, source.chain_id))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::BlockRewards)?;
+        let (block, receipts, chain_id) = response;
+        let block_number = block.number.ok_or(err("block number not found"))?.as_u64();
+
+        let static_reward = static_block_reward(chain_id, block_number, query.merge_block);
+        let uncle_inclusion_reward =
+            static_reward.map(|reward| reward / 32 * U256::from(block.uncles.len()));
+
+        // without a base fee (pre-london) the entire gas price is paid to the fee recipient
+        let base_fee = block.base_fee_per_gas;
+        let mut priority_fees = U256::zero();
+        for (tx, receipt) in block.transactions.iter().zip(receipts.iter()) {
+            let gas_used = receipt.gas_used.unwrap_or_default();
+            let gas_price = receipt
+                .effective_gas_price
+                .or_else(|| effective_gas_price(tx, base_fee))
+                .unwrap_or_default();
+            let priority_fee = gas_price.saturating_sub(base_fee.unwrap_or_default());
+            priority_fees += gas_used * priority_fee;
+        }
+        let base_fee_burned = base_fee.map(|base_fee| base_fee * block.gas_used);
+
+        // blob gas fields are only present in post-dencun blocks
+        let blob_fee_burned = match block.other.get("blobGasUsed") {
+            Some(_) => {
+                let mut burned = U256::zero();
+                for receipt in receipts.iter() {
+                    let blob_gas_used = receipt_field(receipt, "blobGasUsed");
+                    let blob_gas_price = receipt_field(receipt, "blobGasPrice");
+                    burned += blob_gas_used * blob_gas_price;
+                }
+                Some(burned)
+            }
+            None => None,
+        };
+
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, block_number);
+        store!(schema, columns, fee_recipient, block.author.map(|x| x.as_bytes().to_vec()));
+        store!(schema, columns, static_reward, static_reward);
+        store!(schema, columns, uncle_inclusion_reward, uncle_inclusion_reward);
+        store!(schema, columns, priority_fees, priority_fees);
+        store!(schema, columns, base_fee_burned, base_fee_burned);
+        store!(schema, columns, blob_fee_burned, blob_fee_burned);
+        Ok(())
+    }
+}
+
+impl CollectByTransaction for BlockRewards {
+    type Response = ();
+}
+
+fn receipt_field(receipt: &TransactionReceipt, key: &str) -> U256 {
+    match receipt.other.get_deserialized::<U256>(key) {
+        Some(Ok(value)) => value,
+        _ => U256::zero(),
+    }
+}
+
+/// first proof of stake block of chains with a known merge
+fn known_merge_block(chain_id: u64) -> Option<u64> {
+    match chain_id {
+        1 => Some(15_537_394),
+        5 => Some(7_382_819),
+        17000 => Some(0),
+        11155111 => Some(1_735_371),
+        _ => None,
+    }
+}
+
+/// static reward paid to the miner of a block, None if the reward schedule of chain is unknown
+pub(crate) fn static_block_reward(
+    chain_id: u64,
+    block_number: u64,
+    merge_block: Option<u64>,
+) -> Option<U256> {
+    let eth = U256::exp10(18);
+    match merge_block.or(known_merge_block(chain_id)) {
+        Some(merge_block) if block_number >= merge_block => Some(U256::zero()),
+        _ if chain_id == 1 => match block_number {
+            n if n < 4_370_000 => Some(eth * 5),
+            n if n < 7_280_000 => Some(eth * 3),
+            _ => Some(eth * 2),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_block_reward() {
+        let eth = U256::exp10(18);
+        assert_eq!(static_block_reward(1, 1_000_000, None), Some(eth * 5));
+        assert_eq!(static_block_reward(1, 5_000_000, None), Some(eth * 3));
+        assert_eq!(static_block_reward(1, 15_537_393, None), Some(eth * 2));
+        assert_eq!(static_block_reward(1, 15_537_394, None), Some(U256::zero()));
+        assert_eq!(static_block_reward(1, 1_000_000, Some(100)), Some(U256::zero()));
+        assert_eq!(static_block_reward(999, 100, None), None);
+        assert_eq!(static_block_reward(999, 100, Some(50)), Some(U256::zero()));
+    }
+}
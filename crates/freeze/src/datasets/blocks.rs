@@ -13,7 +13,7 @@ pub struct Blocks {
     state_root: Vec<Vec<u8>>,
     transactions_root: Vec<Vec<u8>>,
     receipts_root: Vec<Vec<u8>>,
-    block_number: Vec<Option<u32>>,
+    block_number: Vec<Option<u64>>,
     gas_used: Vec<u64>,
     extra_data: Vec<Vec<u8>>,
     logs_bloom: Vec<Option<Vec<u8>>>,
@@ -21,6 +21,11 @@ pub struct Blocks {
     total_difficulty: Vec<Option<U256>>,
     size: Vec<Option<u32>>,
     base_fee_per_gas: Vec<Option<u64>>,
+    withdrawals_root: Vec<Option<Vec<u8>>>,
+    blob_gas_used: Vec<Option<u64>>,
+    excess_blob_gas: Vec<Option<u64>>,
+    blob_base_fee: Vec<Option<u64>>,
+    parent_beacon_block_root: Vec<Option<Vec<u8>>>,
     chain_id: Vec<u64>,
 }
 
@@ -42,7 +47,7 @@ impl Dataset for Blocks {
 
 #[async_trait::async_trait]
 impl CollectByBlock for Blocks {
-    type Response = Block<TxHash>;
+    type Response = (Block<TxHash>, u64);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let block = source
@@ -50,18 +55,19 @@ impl CollectByBlock for Blocks {
             .get_block(request.block_number()?)
             .await?
             .ok_or(CollectError::CollectError("block not found".to_string()))?;
-        Ok(block)
+        Ok((block, source.chain_id))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Blocks)?;
-        process_block(response, columns, schema)
+        let (block, chain_id) = response;
+        process_block(block, chain_id, columns, schema)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for Blocks {
-    type Response = Block<TxHash>;
+    type Response = (Block<TxHash>, u64);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let transaction = source
@@ -74,26 +80,41 @@ impl CollectByTransaction for Blocks {
             .get_block_by_hash(transaction.block_hash.ok_or(err("no block block_hash found"))?)
             .await?
             .ok_or(CollectError::CollectError("block not found".to_string()))?;
-        Ok(block)
+        Ok((block, source.chain_id))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Blocks)?;
-        process_block(response, columns, schema)
+        let (block, chain_id) = response;
+        process_block(block, chain_id, columns, schema)
     }
 }
 
 /// process block into columns
-pub(crate) fn process_block<TX>(block: Block<TX>, columns: &mut Blocks, schema: &Table) -> R<()> {
+pub(crate) fn process_block<TX>(
+    block: Block<TX>,
+    chain_id: u64,
+    columns: &mut Blocks,
+    schema: &Table,
+) -> R<()> {
     columns.n_rows += 1;
 
+    // ethers does not know the fields added in dencun, so they are read from the extra fields
+    let blob_gas_used = block_field::<U256>(&block.other, "blobGasUsed").map(|x| x.as_u64());
+    let excess_blob_gas = block_field::<U256>(&block.other, "excessBlobGas").map(|x| x.as_u64());
+    let blob_base_fee = excess_blob_gas.and_then(|excess_blob_gas| {
+        let update_fraction = blob_base_fee_update_fraction(chain_id, block.timestamp.as_u64());
+        blob_base_fee(excess_blob_gas, update_fraction).try_into().ok()
+    });
+    let parent_beacon_block_root = block_field::<H256>(&block.other, "parentBeaconBlockRoot");
+
     store!(schema, columns, block_hash, block.hash.map(|x| x.0.to_vec()));
     store!(schema, columns, parent_hash, block.parent_hash.0.to_vec());
     store!(schema, columns, author, block.author.map(|x| x.0.to_vec()));
     store!(schema, columns, state_root, block.state_root.0.to_vec());
     store!(schema, columns, transactions_root, block.transactions_root.0.to_vec());
     store!(schema, columns, receipts_root, block.receipts_root.0.to_vec());
-    store!(schema, columns, block_number, block.number.map(|x| x.as_u32()));
+    store!(schema, columns, block_number, block.number.map(|x| x.as_u64()));
     store!(schema, columns, gas_used, block.gas_used.as_u64());
     store!(schema, columns, extra_data, block.extra_data.to_vec());
     store!(schema, columns, logs_bloom, block.logs_bloom.map(|x| x.0.to_vec()));
@@ -101,5 +122,121 @@ pub(crate) fn process_block<TX>(block: Block<TX>, columns: &mut Blocks, schema:
     store!(schema, columns, total_difficulty, block.total_difficulty);
     store!(schema, columns, base_fee_per_gas, block.base_fee_per_gas.map(|x| x.as_u64()));
     store!(schema, columns, size, block.size.map(|x| x.as_u32()));
+    store!(schema, columns, withdrawals_root, block.withdrawals_root.map(|x| x.0.to_vec()));
+    store!(schema, columns, blob_gas_used, blob_gas_used);
+    store!(schema, columns, excess_blob_gas, excess_blob_gas);
+    store!(schema, columns, blob_base_fee, blob_base_fee);
+    store!(
+        schema,
+        columns,
+        parent_beacon_block_root,
+        parent_beacon_block_root.map(|x| x.0.to_vec())
+    );
     Ok(())
 }
+
+fn block_field<T: serde::de::DeserializeOwned>(other: &OtherFields, key: &str) -> Option<T> {
+    match other.get_deserialized::<T>(key) {
+        Some(Ok(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// minimum blob base fee in wei
+const MIN_BLOB_BASE_FEE: u64 = 1;
+
+/// update fraction of the blob base fee, raised along with the blob target by later forks
+fn blob_base_fee_update_fraction(chain_id: u64, timestamp: u64) -> u64 {
+    let prague_timestamp = match chain_id {
+        1 => {
+            // blob parameter only forks of osaka
+            if timestamp >= 1_767_747_671 {
+                return 11_684_671
+            } else if timestamp >= 1_765_290_071 {
+                return 8_346_193
+            }
+            1_746_612_311
+        }
+        17000 => 1_740_434_112,
+        11155111 => 1_741_159_776,
+        _ => u64::MAX,
+    };
+    if timestamp >= prague_timestamp {
+        5_007_716
+    } else {
+        3_338_477
+    }
+}
+
+/// blob base fee given the excess blob gas of a block, approximating
+/// MIN_BLOB_BASE_FEE * e ** (excess_blob_gas / update_fraction) as specified in eip-4844
+pub(crate) fn blob_base_fee(excess_blob_gas: u64, update_fraction: u64) -> U256 {
+    let factor = U256::from(MIN_BLOB_BASE_FEE);
+    let numerator = U256::from(excess_blob_gas);
+    let denominator = U256::from(update_fraction);
+    let mut i = U256::one();
+    let mut output = U256::zero();
+    let mut numerator_accum = factor * denominator;
+    while !numerator_accum.is_zero() {
+        output += numerator_accum;
+        numerator_accum = numerator_accum * numerator / (denominator * i);
+        i += U256::one();
+    }
+    output / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_base_fee() {
+        assert_eq!(blob_base_fee(0, 3_338_477), U256::from(1));
+        assert_eq!(blob_base_fee(3_338_477, 3_338_477), U256::from(2));
+        assert_eq!(blob_base_fee(10 * 3_338_477, 3_338_477), U256::from(22026));
+        assert_eq!(blob_base_fee_update_fraction(1, 1_710_338_135), 3_338_477);
+        assert_eq!(blob_base_fee_update_fraction(1, 1_746_612_311), 5_007_716);
+        assert_eq!(blob_base_fee_update_fraction(10, 1_746_612_311), 3_338_477);
+    }
+
+    #[test]
+    fn test_process_block_blob_fields() {
+        let schema = Datatype::Blocks
+            .table_schema(
+                &[],
+                &ColumnEncoding::Binary,
+                &Some(vec!["blob_gas_used".to_string(), "blob_base_fee".to_string()]),
+                &None,
+                &None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let mut columns = Blocks::default();
+        process_block(Block::<TxHash>::default(), 1, &mut columns, &schema).unwrap();
+        let mut block = Block::<TxHash>::default();
+        block.other.insert("blobGasUsed".to_string(), serde_json::json!("0x20000"));
+        block.other.insert("excessBlobGas".to_string(), serde_json::json!("0x0"));
+        process_block(block, 1, &mut columns, &schema).unwrap();
+        assert_eq!(columns.blob_gas_used, vec![None, Some(131072)]);
+        assert_eq!(columns.blob_base_fee, vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn test_process_block_london_boundary() {
+        let schema = Datatype::Blocks
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None, None)
+            .unwrap();
+        let mut columns = Blocks::default();
+        for block_number in [46147, 4369999, 12964999, 12965000] {
+            let block = crate::datasets::fixtures::synthetic_block(block_number).block;
+            process_block(block, 1, &mut columns, &schema).unwrap();
+        }
+        assert_eq!(
+            columns.block_number,
+            vec![Some(46147), Some(4369999), Some(12964999), Some(12965000)]
+        );
+        assert_eq!(columns.base_fee_per_gas, vec![None, None, None, Some(1_000_000_000)]);
+    }
+}
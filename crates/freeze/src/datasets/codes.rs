@@ -7,7 +7,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Codes {
     n_rows: usize,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     address: Vec<Vec<u8>>,
     code: Vec<Vec<u8>>,
     chain_id: Vec<u64>,
@@ -30,9 +30,13 @@ impl Dataset for Codes {
     fn default_blocks() -> Option<String> {
         Some("latest".to_string())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("address")
+    }
 }
 
-type BlockTxAddressOutput = (u32, Option<Vec<u8>>, Vec<u8>, Vec<u8>);
+type BlockTxAddressOutput = (u64, Option<Vec<u8>>, Vec<u8>, Vec<u8>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for Codes {
@@ -40,7 +44,7 @@ impl CollectByBlock for Codes {
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let address = request.address()?;
-        let block_number = request.block_number()? as u32;
+        let block_number = request.block_number()?;
         let output =
             source.fetcher.get_code(H160::from_slice(&address), block_number.into()).await?;
         Ok((block_number, None, address, output.to_vec()))
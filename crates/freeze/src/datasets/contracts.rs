@@ -3,13 +3,14 @@ use crate::*;
 use ethers::prelude::*;
 use ethers_core::utils::keccak256;
 use polars::prelude::*;
+use std::collections::HashMap;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Contracts)]
 #[derive(Default)]
 pub struct Contracts {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Vec<u8>>,
     create_index: Vec<u32>,
     transaction_hash: Vec<Option<Vec<u8>>>,
@@ -20,11 +21,34 @@ pub struct Contracts {
     code: Vec<Vec<u8>>,
     init_code_hash: Vec<Vec<u8>>,
     code_hash: Vec<Vec<u8>>,
+    create_type: Vec<Option<String>>,
+    /// null for CREATE, and for CREATE2 salts computed inside the factory
+    salt: Vec<Option<Vec<u8>>>,
+    metadata_ipfs_hash: Vec<Option<String>>,
+    solc_version: Vec<Option<String>>,
+    has_metadata: Vec<bool>,
     chain_id: Vec<u64>,
 }
 
 #[async_trait::async_trait]
 impl Dataset for Contracts {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "block_hash",
+            "create_index",
+            "transaction_hash",
+            "contract_address",
+            "deployer",
+            "factory",
+            "init_code",
+            "code",
+            "init_code_hash",
+            "code_hash",
+            "chain_id",
+        ])
+    }
+
     fn default_sort() -> Option<Vec<&'static str>> {
         Some(vec!["block_number", "create_index"])
     }
@@ -32,41 +56,54 @@ impl Dataset for Contracts {
 
 #[async_trait::async_trait]
 impl CollectByBlock for Contracts {
-    type Response = Vec<Trace>;
+    type Response = (Vec<Trace>, CreationMethods);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_block(request.ethers_block_number()?).await
+        source.trace_block_with_creation_methods(request.block_number()?).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let (traces, creation_methods) = response;
         let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_contracts(&traces, columns, &query.schemas)
+            if query.exclude_failed { traces::filter_failed_traces(traces) } else { traces };
+        process_contracts(&traces, &creation_methods, columns, &query.schemas)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for Contracts {
-    type Response = Vec<Trace>;
+    type Response = (Vec<Trace>, CreationMethods);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction(request.ethers_transaction_hash()?).await
+        let tx_hash = request.ethers_transaction_hash()?;
+        source.trace_transaction_with_creation_methods(tx_hash).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let (traces, creation_methods) = response;
         let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_contracts(&traces, columns, &query.schemas)
+            if query.exclude_failed { traces::filter_failed_traces(traces) } else { traces };
+        process_contracts(&traces, &creation_methods, columns, &query.schemas)
     }
 }
 
-/// process block into columns
+/// process block into columns, using creation methods reported by the node where available
 pub(crate) fn process_contracts(
     traces: &[Trace],
+    creation_methods: &CreationMethods,
     columns: &mut Contracts,
     schemas: &Schemas,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::Contracts).ok_or(err("schema not provided"))?;
+    let frames: HashMap<(Option<usize>, &[usize]), &Trace> =
+        if schema.has_column("create_type") || schema.has_column("salt") {
+            traces
+                .iter()
+                .map(|trace| ((trace.transaction_position, trace.trace_address.as_slice()), trace))
+                .collect()
+        } else {
+            HashMap::new()
+        };
     let mut deployer = H160([0; 20]);
     let mut create_index = 0;
     for trace in traces.iter() {
@@ -82,7 +119,7 @@ pub(crate) fn process_contracts(
         if let (Action::Create(create), Some(Res::Create(result))) = (&trace.action, &trace.result)
         {
             columns.n_rows += 1;
-            store!(schema, columns, block_number, trace.block_number as u32);
+            store!(schema, columns, block_number, trace.block_number);
             store!(schema, columns, block_hash, trace.block_hash.as_bytes().to_vec());
             store!(schema, columns, create_index, create_index);
             create_index += 1;
@@ -93,9 +130,299 @@ pub(crate) fn process_contracts(
             store!(schema, columns, factory, create.from.as_bytes().into());
             store!(schema, columns, init_code, create.init.to_vec());
             store!(schema, columns, code, result.code.to_vec());
-            store!(schema, columns, code_hash, keccak256(create.init.clone()).into());
-            store!(schema, columns, init_code_hash, keccak256(result.code.clone()).into());
+            store!(schema, columns, code_hash, keccak256(result.code.clone()).into());
+            store!(schema, columns, init_code_hash, keccak256(create.init.clone()).into());
+            if schema.has_column("metadata_ipfs_hash") ||
+                schema.has_column("solc_version") ||
+                schema.has_column("has_metadata")
+            {
+                let metadata = parse_code_metadata(&result.code);
+                store!(schema, columns, has_metadata, metadata.is_some());
+                let metadata = metadata.unwrap_or_default();
+                store!(schema, columns, metadata_ipfs_hash, metadata.ipfs_hash);
+                store!(schema, columns, solc_version, metadata.solc_version);
+            }
+
+            if schema.has_column("create_type") || schema.has_column("salt") {
+                let key = (trace.transaction_position, trace.trace_address.clone());
+                let (create_type, salt) = match trace.trace_address.split_last() {
+                    // transactions can only deploy contracts with CREATE
+                    None => (Some("create".to_string()), None),
+                    Some((_, parent)) => {
+                        let parent = frames.get(&(trace.transaction_position, parent));
+                        let salt =
+                            parent.and_then(|parent| read_create2_salt(create, result, parent));
+                        let create_type = match (creation_methods.get(&key), salt) {
+                            (Some(creation_method), _) => Some(creation_method.clone()),
+                            (None, Some(_)) => Some("create2".to_string()),
+                            (None, None) => None,
+                        };
+                        (create_type, salt)
+                    }
+                };
+                let salt = if create_type.as_deref() == Some("create") { None } else { salt };
+                store!(schema, columns, create_type, create_type);
+                store!(schema, columns, salt, salt.map(|salt| salt.to_vec()));
+            }
         }
     }
     Ok(())
 }
+
+/// read the CREATE2 salt from the abi encoded arguments of the input to the frame that created
+/// the contract, i.e. the words after the selector of calls, or the constructor arguments at the
+/// end of init code. the argument that derives the address of the contract is the salt
+fn read_create2_salt(create: &Create, result: &CreateResult, parent: &Trace) -> Option<[u8; 32]> {
+    let arguments: Vec<&[u8]> = match &parent.action {
+        Action::Call(call) => call.input.get(4..)?.chunks_exact(32).collect(),
+        Action::Create(create) => create.init.rchunks_exact(32).collect(),
+        _ => return None,
+    };
+    let init_code_hash = keccak256(&create.init);
+    arguments.into_iter().filter_map(|argument| <[u8; 32]>::try_from(argument).ok()).find(|salt| {
+        ethers::utils::get_create2_address_from_hash(create.from, salt, init_code_hash) ==
+            result.address
+    })
+}
+
+/// fields of the cbor metadata that compilers append to deployed code
+#[derive(Debug, Default, PartialEq)]
+struct CodeMetadata {
+    ipfs_hash: Option<String>,
+    solc_version: Option<String>,
+}
+
+/// parse the cbor map at the end of deployed code, which is followed by its 2-byte length.
+/// code without a well-formed map, such as vyper code with array metadata, gives None
+fn parse_code_metadata(code: &[u8]) -> Option<CodeMetadata> {
+    let length_start = code.len().checked_sub(2)?;
+    let length = u16::from_be_bytes([code[length_start], code[length_start + 1]]) as usize;
+    let blob = &code[length_start.checked_sub(length)?..length_start];
+
+    let mut reader = CborReader { data: blob, position: 0 };
+    let (major_type, n_entries) = reader.read_header()?;
+    if major_type != 5 || length == 0 {
+        return None
+    }
+    let mut metadata = CodeMetadata::default();
+    for _ in 0..n_entries {
+        let key = match reader.read_value(0)? {
+            CborValue::Text(key) => key,
+            _ => return None,
+        };
+        match (key, reader.read_value(0)?) {
+            ("ipfs", CborValue::Bytes(hash)) => {
+                metadata.ipfs_hash = Some(bs58::encode(hash).into_string())
+            }
+            ("solc", CborValue::Bytes([major, minor, patch])) => {
+                metadata.solc_version = Some(format!("{}.{}.{}", major, minor, patch))
+            }
+            ("solc", CborValue::Text(version)) => metadata.solc_version = Some(version.to_string()),
+            _ => {}
+        }
+    }
+    (reader.position == blob.len()).then_some(metadata)
+}
+
+enum CborValue<'a> {
+    Bytes(&'a [u8]),
+    Text(&'a str),
+    Other,
+}
+
+struct CborReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> CborReader<'a> {
+    fn read_bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position.checked_add(n)?)?;
+        self.position += n;
+        Some(bytes)
+    }
+
+    /// read major type and argument of the next item, indefinite lengths are not supported
+    fn read_header(&mut self) -> Option<(u8, u64)> {
+        let initial = self.read_bytes(1)?[0];
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => info as u64,
+            24 => self.read_bytes(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.read_bytes(2)?.try_into().ok()?) as u64,
+            26 => u32::from_be_bytes(self.read_bytes(4)?.try_into().ok()?) as u64,
+            27 => u64::from_be_bytes(self.read_bytes(8)?.try_into().ok()?),
+            _ => return None,
+        };
+        Some((initial >> 5, argument))
+    }
+
+    fn read_value(&mut self, depth: usize) -> Option<CborValue<'a>> {
+        if depth > 8 {
+            return None
+        }
+        let (major_type, argument) = self.read_header()?;
+        match major_type {
+            0 | 1 | 7 => Some(CborValue::Other),
+            2 => Some(CborValue::Bytes(self.read_bytes(argument.try_into().ok()?)?)),
+            3 => {
+                let text = self.read_bytes(argument.try_into().ok()?)?;
+                Some(CborValue::Text(std::str::from_utf8(text).ok()?))
+            }
+            4 | 5 => {
+                let n_items = if major_type == 5 { argument.checked_mul(2)? } else { argument };
+                for _ in 0..n_items {
+                    self.read_value(depth + 1)?;
+                }
+                Some(CborValue::Other)
+            }
+            6 => self.read_value(depth + 1).map(|_| CborValue::Other),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_trace(trace_address: Vec<usize>, from: H160, init: Vec<u8>, address: H160) -> Trace {
+        Trace {
+            action: Action::Create(Create { from, init: init.into(), ..Default::default() }),
+            result: Some(Res::Create(CreateResult { address, ..Default::default() })),
+            trace_address,
+            subtraces: 0,
+            transaction_position: Some(0),
+            transaction_hash: None,
+            block_number: 1,
+            block_hash: H256::zero(),
+            action_type: ActionType::Create,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_create_type_and_salt() {
+        let outer_factory = H160::repeat_byte(1);
+        let inner_factory = H160::repeat_byte(2);
+        let salt = [7u8; 32];
+        let call_salt = [8u8; 32];
+        let init_code = vec![0x60, 0x00];
+        let child = ethers::utils::get_create2_address(inner_factory, salt, init_code.clone());
+        let called_child =
+            ethers::utils::get_create2_address(outer_factory, call_salt, init_code.clone());
+
+        // outer factory is called with a salt as second argument, deploying called child, and
+        // deploys inner factory, whose constructor deploys child with a salt passed as
+        // constructor argument
+        let mut input = vec![0xab; 4];
+        input.extend([0u8; 32]);
+        input.extend(call_salt);
+        let mut inner_init = vec![0xfe; 10];
+        inner_init.extend(salt);
+        let traces = vec![
+            Trace {
+                action: Action::Call(ethers::types::Call {
+                    from: H160::repeat_byte(9),
+                    to: outer_factory,
+                    input: input.into(),
+                    ..Default::default()
+                }),
+                result: None,
+                trace_address: vec![],
+                subtraces: 2,
+                transaction_position: Some(0),
+                transaction_hash: None,
+                block_number: 1,
+                block_hash: H256::zero(),
+                action_type: ActionType::Call,
+                error: None,
+            },
+            create_trace(vec![0], outer_factory, inner_init, inner_factory),
+            create_trace(vec![0, 0], inner_factory, init_code.clone(), child),
+            create_trace(vec![1], outer_factory, init_code, called_child),
+        ];
+
+        let include = Some(vec!["create_type".to_string(), "salt".to_string()]);
+        let schema = Datatype::Contracts
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        let schemas = [(Datatype::Contracts, schema)].into_iter().collect();
+        let creation_methods = [
+            ((Some(0), vec![0]), "create".to_string()),
+            ((Some(0), vec![0, 0]), "create2".to_string()),
+            ((Some(0), vec![1]), "create2".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let mut columns = Contracts::default();
+        process_contracts(&traces, &creation_methods, &mut columns, &schemas).unwrap();
+
+        let create2 = Some("create2".to_string());
+        assert_eq!(columns.create_type, vec![Some("create".into()), create2.clone(), create2]);
+        assert_eq!(columns.salt, vec![None, Some(salt.to_vec()), Some(call_salt.to_vec())]);
+        let factories =
+            vec![outer_factory.as_bytes(), inner_factory.as_bytes(), outer_factory.as_bytes()];
+        assert_eq!(columns.factory, factories);
+        assert_eq!(columns.deployer, vec![H160::repeat_byte(9).as_bytes(); 3]);
+    }
+
+    #[test]
+    fn test_code_hashes() {
+        // example 5 of eip-1014
+        let factory: H160 = "0x00000000000000000000000000000000deadbeef".parse().unwrap();
+        let salt: H256 =
+            "0x00000000000000000000000000000000000000000000000000000000cafebabe".parse().unwrap();
+        let address: H160 = "0x60f3f640a8508fC6a86d45DF051962668E1e8AC7".parse().unwrap();
+        let init_code = vec![0xde, 0xad, 0xbe, 0xef];
+        let code = vec![0x60, 0x00];
+        let mut trace = create_trace(vec![0], factory, init_code.clone(), address);
+        trace.result = Some(Res::Create(CreateResult {
+            address,
+            code: code.clone().into(),
+            gas_used: 0.into(),
+        }));
+
+        let include = Some(vec!["code_hash".to_string(), "init_code_hash".to_string()]);
+        let schema = Datatype::Contracts
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        let schemas = [(Datatype::Contracts, schema)].into_iter().collect();
+        let mut columns = Contracts::default();
+        process_contracts(&[trace], &CreationMethods::new(), &mut columns, &schemas).unwrap();
+
+        assert_eq!(columns.code_hash, vec![keccak256(code).to_vec()]);
+        assert_eq!(columns.init_code_hash, vec![keccak256(init_code).to_vec()]);
+        let init_code_hash = H256::from_slice(&columns.init_code_hash[0]);
+        assert_eq!(
+            ethers::utils::get_create2_address_from_hash(factory, salt, init_code_hash),
+            address
+        );
+    }
+
+    #[test]
+    fn test_parse_code_metadata() {
+        // solc 0.8.19 metadata: {"ipfs": <34 byte multihash>, "solc": 0x000813}
+        let mut metadata = vec![0xa2, 0x64];
+        metadata.extend(b"ipfs");
+        metadata.extend([0x58, 0x22, 0x12, 0x20]);
+        metadata.extend([0u8; 32]);
+        metadata.push(0x64);
+        metadata.extend(b"solc");
+        metadata.extend([0x43, 0x00, 0x08, 0x13]);
+        let mut code = vec![0x60, 0x80, 0x60, 0x40, 0xfe];
+        code.extend(&metadata);
+        code.extend((metadata.len() as u16).to_be_bytes());
+        let expected = CodeMetadata {
+            ipfs_hash: Some("QmNLei78zWmzUdbeRB3CiUfAizWUrbeeZh5K1rhAQKCh51".to_string()),
+            solc_version: Some("0.8.19".to_string()),
+        };
+        assert_eq!(parse_code_metadata(&code), Some(expected));
+
+        // truncated metadata, missing metadata, and vyper array metadata
+        let truncated = [&code[..10], &code[code.len() - 2..]].concat();
+        assert_eq!(parse_code_metadata(&truncated), None);
+        assert_eq!(parse_code_metadata(&[0x60, 0x80, 0x00, 0x00]), None);
+        assert_eq!(parse_code_metadata(&[0x00]), None);
+        assert_eq!(parse_code_metadata(&[0x60, 0x80, 0x83, 0x00, 0x03, 0x0a, 0x00, 0x04]), None);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for decoded calls
+#[cryo_to_df::to_df(Datatype::DecodedCalls)]
+#[derive(Default)]
+pub struct DecodedCalls {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    transaction_index: Vec<u64>,
+    transaction_hash: Vec<Vec<u8>>,
+    function_name: Vec<String>,
+    dynamic_cols: DynamicColumns,
+    success: Vec<Option<bool>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for DecodedCalls {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index"])
+    }
+
+    fn optional_parameters() -> Vec<Dim> {
+        vec![Dim::Address]
+    }
+
+    fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
+        Some([(Dim::Contract, Dim::Address)].into_iter().collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for DecodedCalls {
+    type Response = Vec<TransactionAndReceipt>;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .fetcher
+            .get_block_with_txs(request.block_number()?)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        let transactions: Vec<Transaction> = match &request.address {
+            Some(address) => block
+                .transactions
+                .into_iter()
+                .filter(|tx| tx.to.as_ref().is_some_and(|to| to.as_bytes() == address))
+                .collect(),
+            None => block.transactions,
+        };
+
+        // receipts are only needed for transactions that will be decoded
+        let schema = query.schemas.get_schema(&Datatype::DecodedCalls)?;
+        let decoder = get_abi_decoder(&query)?;
+        let mut receipts = if schema.has_column("success") {
+            let decodable: Vec<Transaction> =
+                transactions.iter().filter(|tx| decoder.has_function(&tx.input)).cloned().collect();
+            source.get_tx_receipts(&decodable).await?.into_iter()
+        } else {
+            Vec::new().into_iter()
+        };
+        let mut output = Vec::new();
+        for tx in transactions.into_iter() {
+            let receipt = if schema.has_column("success") && decoder.has_function(&tx.input) {
+                receipts.next()
+            } else {
+                None
+            };
+            output.push((tx, receipt));
+        }
+        Ok(output)
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_decoded_calls(response, columns, query)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for DecodedCalls {
+    type Response = Vec<TransactionAndReceipt>;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        let transaction = source
+            .fetcher
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or(CollectError::CollectError("transaction not found".to_string()))?;
+        let schema = query.schemas.get_schema(&Datatype::DecodedCalls)?;
+        let receipt = if schema.has_column("success") {
+            source.fetcher.get_transaction_receipt(tx_hash).await?
+        } else {
+            None
+        };
+        Ok(vec![(transaction, receipt)])
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_decoded_calls(response, columns, query)
+    }
+}
+
+fn get_abi_decoder(query: &Query) -> R<&AbiDecoder> {
+    let schema = query.schemas.get_schema(&Datatype::DecodedCalls)?;
+    schema.abi_decoder.as_ref().ok_or(err("decoded_calls requires an abi"))
+}
+
+/// process transactions into columns, transactions that do not match a function of the abi are
+/// skipped and counted as warnings
+fn process_decoded_calls(
+    transactions: Vec<TransactionAndReceipt>,
+    columns: &mut DecodedCalls,
+    query: &Query,
+) -> R<()> {
+    let schema = query.schemas.get_schema(&Datatype::DecodedCalls)?;
+    let decoder = get_abi_decoder(query)?;
+    for (tx, receipt) in transactions.into_iter() {
+        let (function_name, values) = match decoder.decode_call(&tx.input) {
+            Some(Ok(decoded)) => decoded,
+            Some(Err(_)) => {
+                query.warnings.increment("decoded_calls: skipped call that failed to decode");
+                continue
+            }
+            None => {
+                query.warnings.increment("decoded_calls: skipped call with unknown selector");
+                continue
+            }
+        };
+        if let (Some(bn), Some(ti)) = (tx.block_number, tx.transaction_index) {
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, bn.as_u64());
+            store!(schema, columns, transaction_index, ti.as_u64());
+            store!(schema, columns, transaction_hash, tx.hash.as_bytes().to_vec());
+            store!(schema, columns, function_name, function_name);
+            store!(schema, columns, success, tx_success(&receipt).ok().flatten());
+            columns.dynamic_cols.push_row(schema, values);
+        }
+    }
+    Ok(())
+}
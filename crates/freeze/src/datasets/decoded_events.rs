@@ -0,0 +1,103 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for decoded events
+#[cryo_to_df::to_df(Datatype::DecodedEvents)]
+#[derive(Default)]
+pub struct DecodedEvents {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    transaction_hash: Vec<Vec<u8>>,
+    log_index: Vec<u32>,
+    contract_address: Vec<Vec<u8>>,
+    event_name: Vec<String>,
+    dynamic_cols: DynamicColumns,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for DecodedEvents {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "log_index"])
+    }
+
+    fn optional_parameters() -> Vec<Dim> {
+        vec![Dim::Address]
+    }
+
+    fn use_block_ranges() -> bool {
+        true
+    }
+
+    fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
+        Some([(Dim::Contract, Dim::Address)].into_iter().collect())
+    }
+
+    fn label_column() -> Option<&'static str> {
+        Some("contract_address")
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for DecodedEvents {
+    type Response = Vec<Log>;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let decoder = get_abi_decoder(&query)?;
+        let topic0 = decoder.event_topics().into_iter().map(Some).collect();
+        let topics = [Some(ValueOrArray::Array(topic0)), None, None, None];
+        let filter = Filter { topics, ..request.ethers_log_filter()? };
+        source.fetcher.get_logs(&filter).await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_decoded_events(response, columns, query)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for DecodedEvents {
+    type Response = Vec<Log>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        source.fetcher.get_transaction_logs(request.transaction_hash()?).await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_decoded_events(response, columns, query)
+    }
+}
+
+fn get_abi_decoder(query: &Query) -> R<&AbiDecoder> {
+    let schema = query.schemas.get_schema(&Datatype::DecodedEvents)?;
+    schema.abi_decoder.as_ref().ok_or(err("decoded_events requires an abi"))
+}
+
+/// process logs into columns, logs that fail to decode are skipped and counted as warnings
+fn process_decoded_events(logs: Vec<Log>, columns: &mut DecodedEvents, query: &Query) -> R<()> {
+    let schema = query.schemas.get_schema(&Datatype::DecodedEvents)?;
+    let decoder = get_abi_decoder(query)?;
+    for log in logs.into_iter() {
+        let (event_name, values) = match decoder.decode_log(&log) {
+            Some(Ok(decoded)) => decoded,
+            Some(Err(_)) => {
+                query.warnings.increment("decoded_events: skipped log that failed to decode");
+                continue
+            }
+            None => continue,
+        };
+        if let (Some(bn), Some(tx), Some(li)) =
+            (log.block_number, log.transaction_hash, log.log_index)
+        {
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, bn.as_u64());
+            store!(schema, columns, transaction_hash, tx.as_bytes().to_vec());
+            store!(schema, columns, log_index, li.as_u32());
+            store!(schema, columns, contract_address, log.address.as_bytes().to_vec());
+            store!(schema, columns, event_name, event_name);
+            columns.dynamic_cols.push_row(schema, values);
+        }
+    }
+    Ok(())
+}
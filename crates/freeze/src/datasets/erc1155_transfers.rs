@@ -0,0 +1,224 @@
+use crate::*;
+use ethers::prelude::*;
+use ethers_core::abi::{self, ParamType, Token};
+use polars::prelude::*;
+
+/// columns for erc1155 transfers
+#[cryo_to_df::to_df(Datatype::Erc1155Transfers)]
+#[derive(Default)]
+pub struct Erc1155Transfers {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    block_hash: Vec<Option<Vec<u8>>>,
+    transaction_index: Vec<u64>,
+    log_index: Vec<u32>,
+    transaction_hash: Vec<Vec<u8>>,
+    erc1155: Vec<Vec<u8>>,
+    operator: Vec<Vec<u8>>,
+    from_address: Vec<Vec<u8>>,
+    to_address: Vec<Vec<u8>>,
+    token_id: Vec<U256>,
+    amount: Vec<U256>,
+    batch_index: Vec<Option<u32>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Erc1155Transfers {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "erc1155",
+            "operator",
+            "from_address",
+            "to_address",
+            "token_id",
+            "amount",
+            "batch_index",
+            "chain_id",
+        ])
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "log_index", "batch_index"])
+    }
+
+    fn optional_parameters() -> Vec<Dim> {
+        vec![Dim::Address, Dim::FromAddress, Dim::ToAddress]
+    }
+
+    fn use_block_ranges() -> bool {
+        true
+    }
+
+    fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
+        Some([(Dim::Contract, Dim::Address)].into_iter().collect())
+    }
+
+    fn label_column() -> Option<&'static str> {
+        Some("erc1155")
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for Erc1155Transfers {
+    type Response = Vec<Log>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let topic0 =
+            vec![Some(*EVENT_ERC1155_TRANSFER_SINGLE), Some(*EVENT_ERC1155_TRANSFER_BATCH)];
+        let mut topics = [Some(ValueOrArray::Array(topic0)), None, None, None];
+        if let Some(from_address) = &request.from_address {
+            let mut v = vec![0u8; 12];
+            v.append(&mut from_address.to_owned());
+            topics[2] = Some(ValueOrArray::Value(Some(H256::from_slice(&v[..]))));
+        }
+        if let Some(to_address) = &request.to_address {
+            let mut v = vec![0u8; 12];
+            v.append(&mut to_address.to_owned());
+            topics[3] = Some(ValueOrArray::Value(Some(H256::from_slice(&v[..]))));
+        }
+        let filter = Filter { topics, ..request.ethers_log_filter()? };
+        source.fetcher.get_logs(&filter).await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Erc1155Transfers)?;
+        process_erc1155_transfers(response, columns, schema, query)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for Erc1155Transfers {
+    type Response = Vec<Log>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let logs = source.fetcher.get_transaction_logs(request.transaction_hash()?).await?;
+        Ok(logs.into_iter().filter(is_erc1155_transfer).collect())
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Erc1155Transfers)?;
+        process_erc1155_transfers(response, columns, schema, query)
+    }
+}
+
+fn is_erc1155_transfer(log: &Log) -> bool {
+    !log.topics.is_empty() &&
+        (log.topics[0] == *EVENT_ERC1155_TRANSFER_SINGLE ||
+            log.topics[0] == *EVENT_ERC1155_TRANSFER_BATCH)
+}
+
+/// decode (token_id, amount) pairs of a transfer log, None if log is malformed
+fn decode_transfers(log: &Log) -> Option<Vec<(U256, U256)>> {
+    if log.topics.len() != 4 {
+        return None
+    }
+    if log.topics[0] == *EVENT_ERC1155_TRANSFER_SINGLE {
+        if log.data.len() != 64 {
+            return None
+        }
+        Some(vec![(U256::from(&log.data[..32]), U256::from(&log.data[32..]))])
+    } else {
+        let uint_array = ParamType::Array(Box::new(ParamType::Uint(256)));
+        let tokens = abi::decode(&[uint_array.clone(), uint_array], &log.data).ok()?;
+        match (tokens.first(), tokens.get(1)) {
+            (Some(Token::Array(ids)), Some(Token::Array(values))) if ids.len() == values.len() => {
+                ids.iter()
+                    .zip(values.iter())
+                    .map(|(id, value)| Some((id.clone().into_uint()?, value.clone().into_uint()?)))
+                    .collect()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// process logs into columns, skipping malformed logs
+fn process_erc1155_transfers(
+    logs: Vec<Log>,
+    columns: &mut Erc1155Transfers,
+    schema: &Table,
+    query: &Query,
+) -> R<()> {
+    for log in logs.iter() {
+        let transfers = match decode_transfers(log) {
+            Some(transfers) => transfers,
+            None => {
+                query.warnings.increment("erc1155_transfers: skipped malformed log");
+                continue
+            }
+        };
+        let is_batch = log.topics[0] == *EVENT_ERC1155_TRANSFER_BATCH;
+        if let (Some(bn), Some(tx), Some(ti), Some(li)) =
+            (log.block_number, log.transaction_hash, log.transaction_index, log.log_index)
+        {
+            for (index, (token_id, amount)) in transfers.into_iter().enumerate() {
+                columns.n_rows += 1;
+                store!(schema, columns, block_number, bn.as_u64());
+                store!(
+                    schema,
+                    columns,
+                    block_hash,
+                    log.block_hash.map(|bh| bh.as_bytes().to_vec())
+                );
+                store!(schema, columns, transaction_index, ti.as_u64());
+                store!(schema, columns, log_index, li.as_u32());
+                store!(schema, columns, transaction_hash, tx.as_bytes().to_vec());
+                store!(schema, columns, erc1155, log.address.as_bytes().to_vec());
+                store!(schema, columns, operator, log.topics[1].as_bytes()[12..].to_vec());
+                store!(schema, columns, from_address, log.topics[2].as_bytes()[12..].to_vec());
+                store!(schema, columns, to_address, log.topics[3].as_bytes()[12..].to_vec());
+                store!(schema, columns, token_id, token_id);
+                store!(schema, columns, amount, amount);
+                store!(schema, columns, batch_index, is_batch.then_some(index as u32));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer_log(topic0: H256, data: Vec<u8>) -> Log {
+        Log {
+            topics: vec![topic0, H256::zero(), H256::zero(), H256::zero()],
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_event_hashes() {
+        let single =
+            ethers::utils::keccak256("TransferSingle(address,address,address,uint256,uint256)");
+        let batch =
+            ethers::utils::keccak256("TransferBatch(address,address,address,uint256[],uint256[])");
+        assert_eq!(*EVENT_ERC1155_TRANSFER_SINGLE, H256(single));
+        assert_eq!(*EVENT_ERC1155_TRANSFER_BATCH, H256(batch));
+    }
+
+    #[test]
+    fn test_decode_batch_transfer() {
+        let ids = Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]);
+        let values = Token::Array(vec![Token::Uint(10.into()), Token::Uint(20.into())]);
+        let log = transfer_log(*EVENT_ERC1155_TRANSFER_BATCH, abi::encode(&[ids, values]));
+        let expected = vec![(U256::from(1), U256::from(10)), (U256::from(2), U256::from(20))];
+        assert_eq!(decode_transfers(&log), Some(expected));
+    }
+
+    #[test]
+    fn test_decode_malformed_transfer() {
+        let ids = Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]);
+        let values = Token::Array(vec![Token::Uint(10.into())]);
+        let log = transfer_log(*EVENT_ERC1155_TRANSFER_BATCH, abi::encode(&[ids, values]));
+        assert_eq!(decode_transfers(&log), None);
+        let log = transfer_log(*EVENT_ERC1155_TRANSFER_SINGLE, vec![0u8; 32]);
+        assert_eq!(decode_transfers(&log), None);
+    }
+}
@@ -1,13 +1,14 @@
 use crate::*;
 use ethers::prelude::*;
 use polars::prelude::*;
+use tokio::sync::mpsc;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Erc20Balances)]
 #[derive(Default)]
 pub struct Erc20Balances {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     erc20: Vec<Vec<u8>>,
     address: Vec<Vec<u8>>,
     balance: Vec<Option<U256>>,
@@ -23,22 +24,42 @@ impl Dataset for Erc20Balances {
     fn required_parameters() -> Vec<Dim> {
         vec![Dim::Contract, Dim::Address]
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("address")
+    }
 }
 
+/// block, erc20, address, and balance
+type Erc20BalanceResponse = (u64, Vec<u8>, Vec<u8>, Option<U256>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for Erc20Balances {
-    type Response = (u32, Vec<u8>, Vec<u8>, Option<U256>);
+    type Response = Erc20BalanceResponse;
+
+    async fn collect_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+    ) -> R<std::collections::HashMap<Datatype, DataFrame>> {
+        let calls = StaticCalls {
+            calls: |request| Ok(vec![(request.ethers_contract()?, balance_of_call_data(request)?)]),
+            response: |request, outputs| {
+                balance_response(request, outputs.into_iter().next().flatten())
+            },
+            state_override: false,
+        };
+        collect_static_calls_by_block::<Self>(partition, source, query, inner_request_size, calls)
+            .await
+    }
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let signature = FUNCTION_ERC20_BALANCE_OF.clone();
-        let mut call_data = signature.clone();
-        call_data.extend(vec![0; 12]);
-        call_data.extend(request.address()?);
+        let call_data = balance_of_call_data(&request)?;
         let block_number = request.ethers_block_number()?;
         let contract = request.ethers_contract()?;
         let balance = source.fetcher.call2(contract, call_data, block_number).await.ok();
-        let balance = balance.map(|x| x.to_vec().as_slice().into());
-        Ok((request.block_number()? as u32, request.contract()?, request.address()?, balance))
+        balance_response(request, balance)
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -51,8 +72,35 @@ impl CollectByBlock for Erc20Balances {
         store!(schema, columns, balance, balance);
         Ok(())
     }
+
+    async fn transform_channel(
+        receiver: mpsc::Receiver<R<Self::Response>>,
+        query: &Arc<Query>,
+    ) -> R<Self> {
+        transform_balances::<Self, _>(receiver, query, |(block, erc20, address, balance)| {
+            (*block, (erc20.clone(), address.clone()), *balance)
+        })
+        .await
+    }
+
+    // changes_only compares each balance with the previous block of the whole chunk
+    fn can_flush() -> bool {
+        false
+    }
 }
 
 impl CollectByTransaction for Erc20Balances {
     type Response = ();
 }
+
+fn balance_of_call_data(request: &Params) -> R<Vec<u8>> {
+    let mut call_data = FUNCTION_ERC20_BALANCE_OF.clone();
+    call_data.extend(vec![0; 12]);
+    call_data.extend(request.address()?);
+    Ok(call_data)
+}
+
+fn balance_response(request: Params, output: Option<Bytes>) -> R<Erc20BalanceResponse> {
+    let balance = output.map(|x| x.to_vec().as_slice().into());
+    Ok((request.block_number()?, request.contract()?, request.address()?, balance))
+}
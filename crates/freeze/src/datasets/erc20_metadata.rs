@@ -1,4 +1,6 @@
 use crate::*;
+use ethers::prelude::*;
+use ethers_core::abi::{self, ParamType, Token};
 use polars::prelude::*;
 
 /// columns for transactions
@@ -6,11 +8,12 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Erc20Metadata {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     erc20: Vec<Vec<u8>>,
     name: Vec<Option<String>>,
     symbol: Vec<Option<String>>,
     decimals: Vec<Option<u32>>,
+    total_supply: Vec<Option<U256>>,
     chain_id: Vec<u64>,
 }
 
@@ -31,6 +34,10 @@ impl Dataset for Erc20Metadata {
     fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
         Some([(Dim::Contract, Dim::Address)].into_iter().collect())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("erc20")
+    }
 }
 
 pub(crate) fn remove_control_characters(s: &str) -> String {
@@ -38,9 +45,31 @@ pub(crate) fn remove_control_characters(s: &str) -> String {
     re.replace_all(s, "").to_string()
 }
 
+/// decode output of name() or symbol(), which legacy tokens such as MKR return as a right padded
+/// bytes32 rather than a string
+fn decode_string_output(output: &[u8]) -> Option<String> {
+    let text = match abi::decode(&[ParamType::String], output) {
+        Ok(tokens) => match tokens.into_iter().next() {
+            Some(Token::String(text)) => text,
+            _ => return None,
+        },
+        Err(_) if output.len() == 32 => {
+            let end = output.iter().rposition(|byte| *byte != 0).map_or(0, |index| index + 1);
+            String::from_utf8(output[..end].to_vec()).ok()?
+        }
+        Err(_) => return None,
+    };
+    let text = text.replace(|c: char| c.is_control(), "");
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// block, token, name, symbol, decimals, total supply
+type TokenMetadata = (u64, Vec<u8>, Option<String>, Option<String>, Option<u32>, Option<U256>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for Erc20Metadata {
-    type Response = (u32, Vec<u8>, Option<String>, Option<String>, Option<u32>);
+    type Response = TokenMetadata;
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let block_number = request.ethers_block_number()?;
@@ -49,18 +78,14 @@ impl CollectByBlock for Erc20Metadata {
         // name
         let call_data = FUNCTION_ERC20_NAME.clone();
         let name = match source.fetcher.call2(address, call_data, block_number).await {
-            Ok(output) => {
-                String::from_utf8(output.to_vec()).ok().map(|s| remove_control_characters(&s))
-            }
+            Ok(output) => decode_string_output(&output),
             Err(_) => None,
         };
 
         // symbol
         let call_data = FUNCTION_ERC20_SYMBOL.clone();
         let symbol = match source.fetcher.call2(address, call_data, block_number).await {
-            Ok(output) => {
-                String::from_utf8(output.to_vec()).ok().map(|s| remove_control_characters(&s))
-            }
+            Ok(output) => decode_string_output(&output),
             Err(_) => None,
         };
 
@@ -71,18 +96,27 @@ impl CollectByBlock for Erc20Metadata {
             Err(_) => None,
         };
 
-        Ok((request.block_number()? as u32, request.address()?, name, symbol, decimals))
+        // total supply
+        let call_data = FUNCTION_ERC20_TOTAL_SUPPLY.clone();
+        let total_supply = match source.fetcher.call2(address, call_data, block_number).await {
+            Ok(output) if output.len() == 32 => Some(U256::from_big_endian(&output)),
+            _ => None,
+        };
+
+        let block = request.block_number()?;
+        Ok((block, request.address()?, name, symbol, decimals, total_supply))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Erc20Metadata)?;
-        let (block, address, name, symbol, decimals) = response;
+        let (block, address, name, symbol, decimals, total_supply) = response;
         columns.n_rows += 1;
         store!(schema, columns, block_number, block);
         store!(schema, columns, erc20, address);
         store!(schema, columns, name, name);
         store!(schema, columns, symbol, symbol);
         store!(schema, columns, decimals, decimals);
+        store!(schema, columns, total_supply, total_supply);
         Ok(())
     }
 }
@@ -90,3 +124,31 @@ impl CollectByBlock for Erc20Metadata {
 impl CollectByTransaction for Erc20Metadata {
     type Response = ();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(data: &str) -> Vec<u8> {
+        prefix_hex::decode(data).unwrap()
+    }
+
+    #[test]
+    fn test_decode_string_output() {
+        // USDC name()
+        let usdc = hex("0x0000000000000000000000000000000000000000000000000000000000000020\
+                        0000000000000000000000000000000000000000000000000000000000000008\
+                        55534420436f696e000000000000000000000000000000000000000000000000");
+        assert_eq!(decode_string_output(&usdc), Some("USD Coin".to_string()));
+
+        // MKR name() and symbol()
+        let mkr_name = hex("0x4d616b6572000000000000000000000000000000000000000000000000000000");
+        let mkr_symbol = hex("0x4d4b520000000000000000000000000000000000000000000000000000000000");
+        assert_eq!(decode_string_output(&mkr_name), Some("Maker".to_string()));
+        assert_eq!(decode_string_output(&mkr_symbol), Some("MKR".to_string()));
+
+        assert_eq!(decode_string_output(&[0u8; 32]), None);
+        assert_eq!(decode_string_output(&[0xff; 32]), None);
+        assert_eq!(decode_string_output(&[]), None);
+    }
+}
@@ -7,7 +7,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Erc20Supplies {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     erc20: Vec<Vec<u8>>,
     total_supply: Vec<Option<U256>>,
     chain_id: Vec<u64>,
@@ -30,11 +30,15 @@ impl Dataset for Erc20Supplies {
     fn default_blocks() -> Option<String> {
         Some("latest".to_string())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("erc20")
+    }
 }
 
 #[async_trait::async_trait]
 impl CollectByBlock for Erc20Supplies {
-    type Response = (u32, Vec<u8>, Option<U256>);
+    type Response = (u64, Vec<u8>, Option<U256>);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let signature: Vec<u8> = FUNCTION_ERC20_TOTAL_SUPPLY.clone();
@@ -44,7 +48,7 @@ impl CollectByBlock for Erc20Supplies {
         let contract = request.ethers_address()?;
         let output = source.fetcher.call2(contract, call_data, block_number).await.ok();
         let output = output.map(|x| x.to_vec().as_slice().into());
-        Ok((request.block_number()? as u32, request.address()?, output))
+        Ok((request.block_number()?, request.address()?, output))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
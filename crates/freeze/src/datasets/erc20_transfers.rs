@@ -7,15 +7,19 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Erc20Transfers {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Option<Vec<u8>>>,
-    transaction_index: Vec<u32>,
+    transaction_index: Vec<u64>,
     log_index: Vec<u32>,
     transaction_hash: Vec<Vec<u8>>,
     erc20: Vec<Vec<u8>>,
     from_address: Vec<Vec<u8>>,
     to_address: Vec<Vec<u8>>,
     value: Vec<U256>,
+    transfer_kind: Vec<String>,
+    /// value divided by 10^decimals of token, approximate since f64 keeps only ~15-17 significant
+    /// digits
+    amount_scaled: Vec<Option<f64>>,
     chain_id: Vec<u64>,
 }
 
@@ -47,13 +51,17 @@ impl Dataset for Erc20Transfers {
     fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
         Some([(Dim::Contract, Dim::Address)].into_iter().collect())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("erc20")
+    }
 }
 
 #[async_trait::async_trait]
 impl CollectByBlock for Erc20Transfers {
     type Response = Vec<Log>;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let mut topics = [Some(ValueOrArray::Value(Some(*EVENT_ERC20_TRANSFER))), None, None, None];
         if let Some(from_address) = &request.from_address {
             let mut v = vec![0u8; 12];
@@ -68,12 +76,15 @@ impl CollectByBlock for Erc20Transfers {
         let filter = Filter { topics, ..request.ethers_log_filter()? };
         let logs = source.fetcher.get_logs(&filter).await?;
 
-        Ok(logs.into_iter().filter(|x| x.topics.len() == 3 && x.data.len() == 32).collect())
+        let logs: Vec<Log> =
+            logs.into_iter().filter(|x| x.topics.len() == 3 && x.data.len() == 32).collect();
+        fetch_token_decimals(&logs, &source, &query).await?;
+        Ok(logs)
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Erc20Transfers)?;
-        process_erc20_transfers(response, columns, schema)
+        process_erc20_transfers(response, columns, schema, &query.token_decimals)
     }
 }
 
@@ -81,14 +92,16 @@ impl CollectByBlock for Erc20Transfers {
 impl CollectByTransaction for Erc20Transfers {
     type Response = Vec<Log>;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let logs = source.fetcher.get_transaction_logs(request.transaction_hash()?).await?;
-        Ok(logs.into_iter().filter(is_erc20_transfer).collect())
+        let logs: Vec<Log> = logs.into_iter().filter(is_erc20_transfer).collect();
+        fetch_token_decimals(&logs, &source, &query).await?;
+        Ok(logs)
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Erc20Transfers)?;
-        process_erc20_transfers(response, columns, schema)
+        process_erc20_transfers(response, columns, schema, &query.token_decimals)
     }
 }
 
@@ -96,23 +109,129 @@ fn is_erc20_transfer(log: &Log) -> bool {
     log.topics.len() == 3 && log.data.len() == 32 && log.topics[0] == *EVENT_ERC20_TRANSFER
 }
 
+/// fetch decimals of tokens not yet in the per-run cache, only needed for amount_scaled
+async fn fetch_token_decimals(logs: &[Log], source: &Source, query: &Query) -> R<()> {
+    if !query.schemas.get_schema(&Datatype::Erc20Transfers)?.has_column("amount_scaled") {
+        return Ok(())
+    }
+    let mut fetched = std::collections::HashSet::new();
+    for log in logs.iter() {
+        let token = log.address.as_bytes().to_vec();
+        if query.token_decimals.get(&token).is_some() || !fetched.insert(token.clone()) {
+            continue
+        }
+        let block_number = log.block_number.map(|x| x.into()).unwrap_or(BlockNumber::Latest);
+        let call_data = FUNCTION_ERC20_DECIMALS.clone();
+        let decimals = match source.fetcher.call2(log.address, call_data, block_number).await {
+            Ok(output) => bytes_to_u32(output).ok(),
+            // reverts are cached as null, other errors such as rate limits fail the chunk so that
+            // they are retried instead of nulling the amounts of the token for the whole run
+            Err(e) if e.is_revert() => None,
+            Err(e) => return Err(e),
+        };
+        query.token_decimals.insert(token, decimals);
+    }
+    Ok(())
+}
+
 /// process block into columns
-fn process_erc20_transfers(logs: Vec<Log>, columns: &mut Erc20Transfers, schema: &Table) -> R<()> {
+fn process_erc20_transfers(
+    logs: Vec<Log>,
+    columns: &mut Erc20Transfers,
+    schema: &Table,
+    token_decimals: &TokenDecimals,
+) -> R<()> {
     for log in logs.iter() {
         if let (Some(bn), Some(tx), Some(ti), Some(li)) =
             (log.block_number, log.transaction_hash, log.transaction_index, log.log_index)
         {
             columns.n_rows += 1;
-            store!(schema, columns, block_number, bn.as_u32());
+            store!(schema, columns, block_number, bn.as_u64());
             store!(schema, columns, block_hash, log.block_hash.map(|bh| bh.as_bytes().to_vec()));
-            store!(schema, columns, transaction_index, ti.as_u32());
+            store!(schema, columns, transaction_index, ti.as_u64());
             store!(schema, columns, log_index, li.as_u32());
             store!(schema, columns, transaction_hash, tx.as_bytes().to_vec());
             store!(schema, columns, erc20, log.address.as_bytes().to_vec());
             store!(schema, columns, from_address, log.topics[1].as_bytes()[12..].to_vec());
             store!(schema, columns, to_address, log.topics[2].as_bytes()[12..].to_vec());
-            store!(schema, columns, value, log.data.to_vec().as_slice().into());
+            store!(
+                schema,
+                columns,
+                transfer_kind,
+                transfer_kind(&log.topics[1].as_bytes()[12..], &log.topics[2].as_bytes()[12..])
+                    .to_string()
+            );
+            let value: U256 = log.data.to_vec().as_slice().into();
+            store!(schema, columns, amount_scaled, {
+                let decimals = token_decimals.get(log.address.as_bytes()).flatten();
+                decimals.and_then(|decimals| {
+                    let value = value.to_string().parse::<f64>().ok()?;
+                    Some(value / 10f64.powi(decimals as i32))
+                })
+            });
+            store!(schema, columns, value, value);
         }
     }
     Ok(())
 }
+
+/// classify transfer by its endpoints, transfers from an address to itself (including the zero
+/// address) leave supply unchanged so are classified as self
+pub(crate) fn transfer_kind(from_address: &[u8], to_address: &[u8]) -> &'static str {
+    let is_zero = |address: &[u8]| address.iter().all(|byte| *byte == 0);
+    if from_address == to_address {
+        "self"
+    } else if is_zero(from_address) {
+        "mint"
+    } else if is_zero(to_address) {
+        "burn"
+    } else {
+        "transfer"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_kind() {
+        let zero = [0u8; 20];
+        let alice = [1u8; 20];
+        let bob = [2u8; 20];
+        assert_eq!(transfer_kind(&zero, &alice), "mint");
+        assert_eq!(transfer_kind(&alice, &zero), "burn");
+        assert_eq!(transfer_kind(&alice, &bob), "transfer");
+        assert_eq!(transfer_kind(&alice, &alice), "self");
+        // some tokens emit transfers from zero to zero, e.g. when minting zero tokens
+        assert_eq!(transfer_kind(&zero, &zero), "self");
+    }
+
+    #[test]
+    fn test_process_erc20_transfers_amount_scaled() {
+        let include = Some(vec!["amount_scaled".to_string()]);
+        let schema = Datatype::Erc20Transfers
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        let transfer_log = |token: Address| Log {
+            address: token,
+            topics: vec![*EVENT_ERC20_TRANSFER, H256::zero(), H256::zero()],
+            data: H256::from_uint(&U256::from(1_500_000)).as_bytes().to_vec().into(),
+            block_number: Some(1.into()),
+            transaction_hash: Some(H256::zero()),
+            transaction_index: Some(0.into()),
+            log_index: Some(0.into()),
+            ..Default::default()
+        };
+        let (usdc, reverting) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let token_decimals = TokenDecimals::default();
+        token_decimals.insert(usdc.as_bytes().to_vec(), Some(6));
+        token_decimals.insert(reverting.as_bytes().to_vec(), None);
+
+        let mut columns = Erc20Transfers::default();
+        let logs = vec![transfer_log(usdc), transfer_log(reverting)];
+        process_erc20_transfers(logs, &mut columns, &schema, &token_decimals).unwrap();
+        assert_eq!(columns.amount_scaled, vec![Some(1.5), None]);
+        assert_eq!(columns.value, vec![U256::from(1_500_000), U256::from(1_500_000)]);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for erc4626 snapshots
+#[cryo_to_df::to_df(Datatype::Erc4626Snapshots)]
+#[derive(Default)]
+pub struct Erc4626Snapshots {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    vault_address: Vec<Vec<u8>>,
+    asset_address: Vec<Option<Vec<u8>>>,
+    total_assets: Vec<Option<U256>>,
+    total_supply: Vec<Option<U256>>,
+    share_price: Vec<Option<U256>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Erc4626Snapshots {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["vault_address", "block_number"])
+    }
+
+    fn required_parameters() -> Vec<Dim> {
+        vec![Dim::Address]
+    }
+
+    fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
+        Some([(Dim::Contract, Dim::Address)].into_iter().collect())
+    }
+
+    fn default_blocks() -> Option<String> {
+        Some("latest".to_string())
+    }
+
+    fn label_column() -> Option<&'static str> {
+        Some("vault_address")
+    }
+}
+
+/// block, vault, and outputs of asset(), totalAssets(), totalSupply(), convertToAssets(1e18)
+type VaultSnapshot = (u64, Vec<u8>, Option<Bytes>, Option<Bytes>, Option<Bytes>, Option<Bytes>);
+
+#[async_trait::async_trait]
+impl CollectByBlock for Erc4626Snapshots {
+    type Response = VaultSnapshot;
+
+    async fn collect_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+    ) -> R<std::collections::HashMap<Datatype, DataFrame>> {
+        let calls = StaticCalls {
+            calls: snapshot_calls,
+            response: snapshot_response,
+            state_override: false,
+        };
+        collect_static_calls_by_block::<Self>(partition, source, query, inner_request_size, calls)
+            .await
+    }
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        // the calls of a snapshot are packed into one multicall where Multicall3 is deployed
+        let calls = snapshot_calls(&request)?;
+        let outputs = source.multicall(calls, request.ethers_block_number()?, None).await?;
+        snapshot_response(request, outputs)
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Erc4626Snapshots)?;
+        let (block, vault, asset, total_assets, total_supply, share_price) = response;
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, block);
+        store!(schema, columns, vault_address, vault);
+        store!(schema, columns, asset_address, asset.and_then(word_to_address));
+        store!(schema, columns, total_assets, total_assets.and_then(word_to_u256));
+        store!(schema, columns, total_supply, total_supply.and_then(word_to_u256));
+        store!(schema, columns, share_price, share_price.and_then(word_to_u256));
+        Ok(())
+    }
+
+    // calls are batched into multicalls by collect_by_block
+    fn can_flush() -> bool {
+        false
+    }
+}
+
+fn snapshot_calls(request: &Params) -> R<Vec<StaticCall>> {
+    let vault = request.ethers_address()?;
+    let mut convert_to_assets = FUNCTION_ERC4626_CONVERT_TO_ASSETS.clone();
+    convert_to_assets.extend(H256::from_uint(&U256::exp10(18)).as_bytes());
+    Ok(vec![
+        (vault, FUNCTION_ERC4626_ASSET.clone()),
+        (vault, FUNCTION_ERC4626_TOTAL_ASSETS.clone()),
+        (vault, FUNCTION_ERC20_TOTAL_SUPPLY.clone()),
+        (vault, convert_to_assets),
+    ])
+}
+
+/// vaults that are not deployed yet revert, which is recorded as null values, other errors such
+/// as rate limits fail the request before reaching this point
+fn snapshot_response(request: Params, outputs: Vec<Option<Bytes>>) -> R<VaultSnapshot> {
+    let mut outputs = outputs.into_iter();
+    let mut next = || outputs.next().flatten();
+    Ok((request.block_number()?, request.address()?, next(), next(), next(), next()))
+}
+
+impl CollectByTransaction for Erc4626Snapshots {
+    type Response = ();
+}
+
+fn word_to_u256(output: Bytes) -> Option<U256> {
+    (output.len() == 32).then(|| U256::from_big_endian(&output))
+}
+
+fn word_to_address(output: Bytes) -> Option<Vec<u8>> {
+    (output.len() == 32 && output[..12].iter().all(|b| *b == 0)).then(|| output[12..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_signatures() {
+        let selector = |signature: &str| ethers::utils::id(signature).to_vec();
+        assert_eq!(*FUNCTION_ERC4626_ASSET, selector("asset()"));
+        assert_eq!(*FUNCTION_ERC4626_TOTAL_ASSETS, selector("totalAssets()"));
+        assert_eq!(*FUNCTION_ERC4626_CONVERT_TO_ASSETS, selector("convertToAssets(uint256)"));
+    }
+
+    #[test]
+    fn test_snapshot_calls() {
+        let vault = H160::repeat_byte(1);
+        let request = Params {
+            block_number: Some(100),
+            address: Some(vault.as_bytes().to_vec()),
+            ..Default::default()
+        };
+        let calls = snapshot_calls(&request).unwrap();
+        assert_eq!(calls.len(), 4);
+        assert!(calls.iter().all(|(contract, _)| *contract == vault));
+        assert_eq!(calls[3].1.len(), 36);
+
+        let asset = Bytes::from(H256::from(H160::repeat_byte(2)).as_bytes().to_vec());
+        let outputs = vec![Some(asset.clone()), None, None, None];
+        let snapshot = snapshot_response(request, outputs).unwrap();
+        assert_eq!(snapshot, (100, vault.as_bytes().to_vec(), Some(asset), None, None, None));
+    }
+}
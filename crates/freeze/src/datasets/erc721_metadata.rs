@@ -7,7 +7,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Erc721Metadata {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     erc721: Vec<Vec<u8>>,
     name: Vec<Option<String>>,
     symbol: Vec<Option<String>>,
@@ -26,11 +26,15 @@ impl Dataset for Erc721Metadata {
     fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
         Some([(Dim::Contract, Dim::Address)].into_iter().collect())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("erc721")
+    }
 }
 
 #[async_trait::async_trait]
 impl CollectByBlock for Erc721Metadata {
-    type Response = (u32, Vec<u8>, Option<String>, Option<String>);
+    type Response = (u64, Vec<u8>, Option<String>, Option<String>);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let block_number = request.ethers_block_number()?;
@@ -54,7 +58,7 @@ impl CollectByBlock for Erc721Metadata {
             Err(_) => None,
         };
 
-        Ok((request.block_number()? as u32, request.address()?, name, symbol))
+        Ok((request.block_number()?, request.address()?, name, symbol))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -7,15 +7,16 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Erc721Transfers {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Option<Vec<u8>>>,
-    transaction_index: Vec<u32>,
+    transaction_index: Vec<u64>,
     log_index: Vec<u32>,
     transaction_hash: Vec<Vec<u8>>,
     erc20: Vec<Vec<u8>>,
     from_address: Vec<Vec<u8>>,
     to_address: Vec<Vec<u8>>,
     token_id: Vec<U256>,
+    transfer_kind: Vec<String>,
     chain_id: Vec<u64>,
 }
 
@@ -47,6 +48,10 @@ impl Dataset for Erc721Transfers {
     fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
         Some([(Dim::Contract, Dim::Address)].into_iter().collect())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("erc20")
+    }
 }
 
 #[async_trait::async_trait]
@@ -69,7 +74,7 @@ impl CollectByBlock for Erc721Transfers {
         let filter = Filter { topics, ..request.ethers_log_filter()? };
         let logs = source.fetcher.get_logs(&filter).await?;
 
-        Ok(logs.into_iter().filter(|x| x.topics.len() == 4 && x.data.len() == 0).collect())
+        Ok(logs.into_iter().filter(|x| x.topics.len() == 4 && x.data.is_empty()).collect())
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -94,7 +99,7 @@ impl CollectByTransaction for Erc721Transfers {
 }
 
 fn is_erc721_transfer(log: &Log) -> bool {
-    log.topics.len() == 4 && log.data.len() == 0 && log.topics[0] == *EVENT_ERC721_TRANSFER
+    log.topics.len() == 4 && log.data.is_empty() && log.topics[0] == *EVENT_ERC721_TRANSFER
 }
 
 /// process block into columns
@@ -108,16 +113,56 @@ fn process_erc721_transfers(
             (log.block_number, log.transaction_hash, log.transaction_index, log.log_index)
         {
             columns.n_rows += 1;
-            store!(schema, columns, block_number, bn.as_u32());
+            store!(schema, columns, block_number, bn.as_u64());
             store!(schema, columns, block_hash, log.block_hash.map(|bh| bh.as_bytes().to_vec()));
-            store!(schema, columns, transaction_index, ti.as_u32());
+            store!(schema, columns, transaction_index, ti.as_u64());
             store!(schema, columns, log_index, li.as_u32());
             store!(schema, columns, transaction_hash, tx.as_bytes().to_vec());
             store!(schema, columns, erc20, log.address.as_bytes().to_vec());
             store!(schema, columns, from_address, log.topics[1].as_bytes()[12..].to_vec());
             store!(schema, columns, to_address, log.topics[2].as_bytes()[12..].to_vec());
+            store!(
+                schema,
+                columns,
+                transfer_kind,
+                transfer_kind(&log.topics[1].as_bytes()[12..], &log.topics[2].as_bytes()[12..])
+                    .to_string()
+            );
             store!(schema, columns, token_id, log.topics[3].as_bytes().into());
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer_log(from_address: H256, to_address: H256) -> Log {
+        Log {
+            topics: vec![*EVENT_ERC721_TRANSFER, from_address, to_address, H256::zero()],
+            block_number: Some(1.into()),
+            transaction_hash: Some(H256::zero()),
+            transaction_index: Some(0.into()),
+            log_index: Some(0.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_process_erc721_transfer_kinds() {
+        let include = Some(vec!["transfer_kind".to_string()]);
+        let schema = Datatype::Erc721Transfers
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        let holder = H256::from(Address::repeat_byte(1));
+        let logs = vec![
+            transfer_log(H256::zero(), holder),
+            transfer_log(holder, H256::zero()),
+            transfer_log(H256::zero(), H256::zero()),
+        ];
+        let mut columns = Erc721Transfers::default();
+        process_erc721_transfers(logs, &mut columns, &schema).unwrap();
+        assert_eq!(columns.transfer_kind, vec!["mint", "burn", "self"]);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::*;
 use ethers::prelude::*;
+use ethers_core::abi::{ParamType, Token};
 use polars::prelude::*;
 
 /// columns for transactions
@@ -7,19 +8,28 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct EthCalls {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     contract_address: Vec<Vec<u8>>,
     call_data: Vec<Vec<u8>>,
     call_data_hash: Vec<Vec<u8>>,
-    output_data: Vec<Vec<u8>>,
-    output_data_hash: Vec<Vec<u8>>,
+    output_data: Vec<Option<Vec<u8>>>,
+    output_data_hash: Vec<Option<Vec<u8>>>,
+    success: Vec<bool>,
+    decoded_output: Vec<Option<String>>,
     chain_id: Vec<u64>,
 }
 
 #[async_trait::async_trait]
 impl Dataset for EthCalls {
     fn default_columns() -> Option<Vec<&'static str>> {
-        Some(vec!["block_number", "contract_address", "call_data", "output_data", "chain_id"])
+        Some(vec![
+            "block_number",
+            "contract_address",
+            "call_data",
+            "output_data",
+            "success",
+            "chain_id",
+        ])
     }
 
     fn default_sort() -> Option<Vec<&'static str>> {
@@ -37,43 +47,156 @@ impl Dataset for EthCalls {
     fn required_parameters() -> Vec<Dim> {
         vec![Dim::Contract, Dim::CallData]
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("contract_address")
+    }
 }
 
-type EthCallsResponse = (u32, Vec<u8>, Vec<u8>, Vec<u8>);
+/// block, contract, call data, and output, which is None for reverted calls
+type EthCallsResponse = (u64, Vec<u8>, Vec<u8>, Option<Vec<u8>>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for EthCalls {
     type Response = EthCallsResponse;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn collect_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+    ) -> R<std::collections::HashMap<Datatype, DataFrame>> {
+        let calls = StaticCalls {
+            calls: |request| Ok(vec![(request.ethers_contract()?, request.call_data()?)]),
+            response: |request, outputs| {
+                let block_number = request.block_number()?;
+                let output = outputs.into_iter().next().flatten().map(|output| output.to_vec());
+                Ok((block_number, request.contract()?, request.call_data()?, output))
+            },
+            state_override: true,
+        };
+        collect_static_calls_by_block::<Self>(partition, source, query, inner_request_size, calls)
+            .await
+    }
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let transaction = TransactionRequest {
             to: Some(request.ethers_contract()?.into()),
             data: Some(request.call_data()?.into()),
             ..Default::default()
         };
         let number = request.block_number()?;
-        let output = source.fetcher.call(transaction, number.into()).await?;
-        Ok((number as u32, request.contract()?, request.call_data()?, output.to_vec()))
+        let output = match &query.state_override {
+            Some(state_override) => {
+                source
+                    .fetcher
+                    .call_with_state_override(transaction, number.into(), state_override)
+                    .await
+            }
+            None => source.fetcher.call(transaction, number.into()).await,
+        };
+        let output = match output {
+            Ok(output) => Some(output.to_vec()),
+            Err(e) if e.is_revert() => None,
+            Err(e) => return Err(e),
+        };
+        Ok((number, request.contract()?, request.call_data()?, output))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::EthCalls)?;
-        process_eth_call(response, columns, schema);
+        process_eth_call(response, columns, schema, &query.eth_call_return_types);
         Ok(())
     }
+
+    // calls are batched into multicalls by collect_by_block
+    fn can_flush() -> bool {
+        false
+    }
 }
 
 impl CollectByTransaction for EthCalls {
     type Response = ();
 }
 
-fn process_eth_call(response: EthCallsResponse, columns: &mut EthCalls, schema: &Table) {
+fn process_eth_call(
+    response: EthCallsResponse,
+    columns: &mut EthCalls,
+    schema: &Table,
+    return_types: &Option<Vec<ParamType>>,
+) {
     let (block_number, contract_address, call_data, output_data) = response;
     columns.n_rows += 1;
     store!(schema, columns, block_number, block_number);
     store!(schema, columns, contract_address, contract_address);
     store!(schema, columns, call_data, call_data.clone());
     store!(schema, columns, call_data_hash, ethers_core::utils::keccak256(call_data).into());
-    store!(schema, columns, output_data, output_data.to_vec());
-    store!(schema, columns, output_data_hash, ethers_core::utils::keccak256(output_data).into());
+    store!(
+        schema,
+        columns,
+        output_data_hash,
+        output_data.as_ref().map(|output| ethers_core::utils::keccak256(output).into())
+    );
+    store!(schema, columns, success, output_data.is_some());
+    store!(
+        schema,
+        columns,
+        decoded_output,
+        match (return_types, &output_data) {
+            (Some(return_types), Some(output)) => decode_output(return_types, output),
+            _ => None,
+        }
+    );
+    store!(schema, columns, output_data, output_data);
+}
+
+/// decode call output as text, None if output does not match return types
+fn decode_output(return_types: &[ParamType], output: &[u8]) -> Option<String> {
+    let tokens = ethers_core::abi::decode(return_types, output).ok()?;
+    match tokens.as_slice() {
+        [token] => Some(token_to_string(token)),
+        tokens => Some(format!("({})", tokens_to_string(tokens))),
+    }
+}
+
+fn tokens_to_string(tokens: &[Token]) -> String {
+    tokens.iter().map(token_to_string).collect::<Vec<_>>().join(",")
+}
+
+/// format token with integers in decimal and bytes as 0x-prefixed hex
+fn token_to_string(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => value.clone(),
+        Token::Bytes(value) | Token::FixedBytes(value) => prefix_hex::encode(value.clone()),
+        Token::Array(tokens) | Token::FixedArray(tokens) => {
+            format!("[{}]", tokens_to_string(tokens))
+        }
+        Token::Tuple(tokens) => format!("({})", tokens_to_string(tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_output() {
+        let output = ethers_core::abi::encode(&[Token::Uint(1_000_000.into())]);
+        assert_eq!(decode_output(&[ParamType::Uint(256)], &output), Some("1000000".to_string()));
+
+        let address = Address::repeat_byte(0xab);
+        let output = ethers_core::abi::encode(&[
+            Token::Address(address),
+            Token::Int(I256::from(-5).into_raw()),
+        ]);
+        let return_types = [ParamType::Address, ParamType::Int(256)];
+        assert_eq!(decode_output(&return_types, &output), Some(format!("({:?},-5)", address)));
+
+        // outputs too short for their return types are left undecoded
+        assert_eq!(decode_output(&[ParamType::Uint(256)], &[0u8; 4]), None);
+    }
 }
@@ -0,0 +1,25 @@
+use ethers::prelude::*;
+
+/// synthetic rpc responses of blocks at fork boundaries, with one or two transactions each.
+/// they are written by hand to have the fields that nodes return for blocks of each fork, and are
+/// not captured from a node: hashes, roots, and signatures are placeholders, and other values are
+/// not guaranteed to match the mainnet blocks of the same numbers
+#[derive(serde::Deserialize)]
+pub(crate) struct SyntheticBlock {
+    pub(crate) block: Block<Transaction>,
+    pub(crate) receipts: Vec<TransactionReceipt>,
+}
+
+/// synthetic block and receipts at one of the block numbers: 46147 (first transaction), 4369999
+/// (last block before byzantium), 12964999 (last block before london), or 12965000 (first block
+/// of london)
+pub(crate) fn synthetic_block(block_number: u64) -> SyntheticBlock {
+    let json = match block_number {
+        46147 => include_str!("../../tests/fixtures/synthetic_block_46147.json"),
+        4369999 => include_str!("../../tests/fixtures/synthetic_block_4369999.json"),
+        12964999 => include_str!("../../tests/fixtures/synthetic_block_12964999.json"),
+        12965000 => include_str!("../../tests/fixtures/synthetic_block_12965000.json"),
+        _ => panic!("no fixture for block {}", block_number),
+    };
+    serde_json::from_str(json).unwrap()
+}
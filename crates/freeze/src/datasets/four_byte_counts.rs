@@ -1,18 +1,24 @@
 use crate::*;
 use polars::prelude::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::sync::mpsc;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::FourByteCounts)]
 #[derive(Default)]
 pub struct FourByteCounts {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
-    pub(crate) transaction_index: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
+    pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) signature: Vec<Vec<u8>>,
-    pub(crate) size: Vec<u64>,
+    pub(crate) signature_text: Vec<Option<String>>,
+    pub(crate) size: Vec<Option<u64>>,
     pub(crate) count: Vec<u64>,
+    pub(crate) total_count: Vec<u64>,
+    pub(crate) n_transactions: Vec<u64>,
+    pub(crate) first_block: Vec<Option<u64>>,
+    pub(crate) last_block: Vec<Option<u64>>,
     pub(crate) chain_id: Vec<u64>,
 }
 
@@ -21,84 +27,311 @@ impl Dataset for FourByteCounts {
     fn aliases() -> Vec<&'static str> {
         vec!["4byte_counts"]
     }
+
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "transaction_index",
+            "transaction_hash",
+            "signature",
+            "size",
+            "count",
+            "chain_id",
+        ])
+    }
 }
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<String, u64>>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<String, u64>>);
+
+type SignatureTexts = HashMap<Vec<u8>, String>;
 
 #[async_trait::async_trait]
 impl CollectByBlock for FourByteCounts {
-    type Response = BlockTxsTraces;
+    type Response = (BlockTxsTraces, SignatureTexts);
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::FourByteCounts).ok_or(err("schema not provided"))?;
-        let include_txs = schema.has_column("transaction_hash");
-        source
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        let traces = source
             .fetcher
-            .geth_debug_trace_block_4byte_traces(request.block_number()? as u32, include_txs)
-            .await
+            .geth_debug_trace_block_4byte_traces(request.block_number()?, include_txs)
+            .await?;
+        let signature_texts = resolve_signatures(&traces, schema, &query).await?;
+        Ok((traces, signature_texts))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_reads(&response, columns, &query.schemas)
+        let (traces, signature_texts) = response;
+        query.note_trace_positions(&Datatype::FourByteCounts, &traces.1);
+        process_storage_reads(&traces, &signature_texts, columns, &query.schemas)
+    }
+
+    async fn transform_channel(
+        receiver: mpsc::Receiver<R<Self::Response>>,
+        query: &Arc<Query>,
+    ) -> R<Self> {
+        transform_four_byte_counts(receiver, query).await
+    }
+
+    // range aggregation folds every response of the chunk into one row per selector
+    fn can_flush() -> bool {
+        false
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for FourByteCounts {
-    type Response = BlockTxsTraces;
+    type Response = (BlockTxsTraces, SignatureTexts);
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::FourByteCounts).ok_or(err("schema not provided"))?;
-        let include_block_number = schema.has_column("block_number");
+        let include_transaction =
+            ["block_number", "transaction_index", "first_block", "last_block"]
+                .iter()
+                .any(|column| schema.has_column(column));
         let tx = request.transaction_hash()?;
-        source.fetcher.geth_debug_trace_transaction_4byte_traces(tx, include_block_number).await
+        let traces = source
+            .fetcher
+            .geth_debug_trace_transaction_4byte_traces(tx, include_transaction)
+            .await?;
+        let signature_texts = resolve_signatures(&traces, schema, &query).await?;
+        Ok((traces, signature_texts))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_reads(&response, columns, &query.schemas)
+        let (traces, signature_texts) = response;
+        query.note_trace_positions(&Datatype::FourByteCounts, &traces.1);
+        process_storage_reads(&traces, &signature_texts, columns, &query.schemas)
+    }
+
+    async fn transform_channel(
+        receiver: mpsc::Receiver<R<Self::Response>>,
+        query: &Arc<Query>,
+    ) -> R<Self> {
+        transform_four_byte_counts(receiver, query).await
+    }
+}
+
+/// resolve text of each unique selector, skipped unless signature_text column is requested
+async fn resolve_signatures(
+    traces: &BlockTxsTraces,
+    schema: &Table,
+    query: &Query,
+) -> R<SignatureTexts> {
+    let resolver = match (&query.signature_resolver, schema.has_column("signature_text")) {
+        (Some(resolver), true) => resolver,
+        _ => return Ok(HashMap::new()),
+    };
+    let mut selectors = HashSet::new();
+    for trace in traces.3.iter() {
+        for signature_size in trace.keys() {
+            selectors.insert(parse_signature_size(signature_size)?.0);
+        }
+    }
+    resolver.resolve(&selectors).await
+}
+
+/// transform responses of a chunk, folding them into one row per selector when aggregating by
+/// range
+async fn transform_four_byte_counts(
+    mut receiver: mpsc::Receiver<R<(BlockTxsTraces, SignatureTexts)>>,
+    query: &Arc<Query>,
+) -> R<FourByteCounts> {
+    let mut columns = FourByteCounts::default();
+    let mut totals = BTreeMap::new();
+    let mut signature_texts = SignatureTexts::new();
+    while let Some(message) = receiver.recv().await {
+        let (traces, texts) = message?;
+        match query.four_byte_aggregation {
+            FourByteAggregation::Transaction => {
+                query.note_trace_positions(&Datatype::FourByteCounts, &traces.1);
+                process_storage_reads(&traces, &texts, &mut columns, &query.schemas)?
+            }
+            FourByteAggregation::Range => {
+                fold_selector_totals(&traces, &mut totals)?;
+                signature_texts.extend(texts);
+            }
+        }
+    }
+    if query.four_byte_aggregation == FourByteAggregation::Range {
+        let schema = query.schemas.get_schema(&Datatype::FourByteCounts)?;
+        process_selector_totals(totals, &signature_texts, &mut columns, schema);
+    }
+    Ok(columns)
+}
+
+/// counts of a selector summed over a chunk
+#[derive(Default)]
+struct SelectorTotals {
+    total_count: u64,
+    n_transactions: u64,
+    first_block: Option<u64>,
+    last_block: Option<u64>,
+}
+
+fn fold_selector_totals(
+    response: &BlockTxsTraces,
+    totals: &mut BTreeMap<Vec<u8>, SelectorTotals>,
+) -> R<()> {
+    let (block_number, _, _, traces) = response;
+    for trace in traces.iter() {
+        // a selector called with several call data sizes still counts as one transaction
+        let mut selector_counts: BTreeMap<Vec<u8>, u64> = BTreeMap::new();
+        for (signature_size, count) in trace.iter() {
+            *selector_counts.entry(parse_signature_size(signature_size)?.0).or_default() += count;
+        }
+        for (selector, count) in selector_counts.into_iter() {
+            let entry = totals.entry(selector).or_default();
+            entry.total_count += count;
+            entry.n_transactions += 1;
+            if let Some(block_number) = *block_number {
+                entry.first_block =
+                    Some(entry.first_block.unwrap_or(block_number).min(block_number));
+                entry.last_block = entry.last_block.max(Some(block_number));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn process_selector_totals(
+    totals: BTreeMap<Vec<u8>, SelectorTotals>,
+    signature_texts: &SignatureTexts,
+    columns: &mut FourByteCounts,
+    schema: &Table,
+) {
+    for (signature, totals) in totals.into_iter() {
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, None);
+        store!(schema, columns, transaction_index, None);
+        store!(schema, columns, transaction_hash, None);
+        store!(schema, columns, signature_text, signature_texts.get(&signature).cloned());
+        store!(schema, columns, signature, signature);
+        store!(schema, columns, size, None);
+        store!(schema, columns, count, totals.total_count);
+        store!(schema, columns, total_count, totals.total_count);
+        store!(schema, columns, n_transactions, totals.n_transactions);
+        store!(schema, columns, first_block, totals.first_block);
+        store!(schema, columns, last_block, totals.last_block);
     }
 }
 
 pub(crate) fn process_storage_reads(
     response: &BlockTxsTraces,
+    signature_texts: &SignatureTexts,
     columns: &mut FourByteCounts,
     schemas: &Schemas,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::FourByteCounts).ok_or(err("schema not provided"))?;
-    let (block_number, txs, traces) = response;
-    for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
+    let (block_number, indices, txs, traces) = response;
+    for (position, ((trace, tx), index)) in traces.iter().zip(txs).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
         for (signature_size, count) in trace.iter() {
             let (signature, size) = parse_signature_size(signature_size)?;
             columns.n_rows += 1;
             store!(schema, columns, block_number, *block_number);
-            store!(schema, columns, transaction_index, Some(index as u32));
+            store!(schema, columns, transaction_index, Some(index));
             store!(schema, columns, transaction_hash, tx.clone());
-            store!(schema, columns, signature, signature.clone());
+            store!(schema, columns, signature_text, signature_texts.get(&signature).cloned());
+            store!(schema, columns, signature, signature);
             store!(schema, columns, size, size);
             store!(schema, columns, count, *count);
+            store!(schema, columns, total_count, *count);
+            store!(schema, columns, n_transactions, 1);
+            store!(schema, columns, first_block, *block_number);
+            store!(schema, columns, last_block, *block_number);
         }
     }
     Ok(())
 }
 
-fn parse_signature_size(signature_size: &str) -> R<(Vec<u8>, u64)> {
-    let parts: Vec<&str> = signature_size.splitn(2, '-').collect();
-    if parts.len() != 2 {
-        return Err(err("could not parse 4byte-size pair"))
+/// parse 4byte tracer key, either `{selector}-{size}` or a full function signature such as
+/// `transfer(address,uint256)`, optionally suffixed with `-{size}`
+fn parse_signature_size(signature_size: &str) -> R<(Vec<u8>, Option<u64>)> {
+    let (signature, size) = match signature_size.rsplit_once('-') {
+        Some((signature, size)) => {
+            let size = size.parse::<u64>().map_err(|_| err("could not parse call data size"))?;
+            (signature, Some(size))
+        }
+        None => (signature_size, None),
+    };
+
+    // full function signatures are hashed into their selector
+    if signature.contains('(') {
+        return Ok((ethers::utils::id(signature).to_vec(), size))
     }
+    let size = size.ok_or(err("could not parse 4byte-size pair"))?;
 
     // Parse the hexadecimal part (assuming there's no "0x" prefix as in the example given)
-    let hex_part = parts[0].trim_start_matches("0x");
+    let hex_part = signature.trim_start_matches("0x");
+    if hex_part.len() % 2 != 0 {
+        return Err(err("could not parse signature bytes"))
+    }
     let bytes = (0..hex_part.len())
         .step_by(2)
         .map(|i| u8::from_str_radix(&hex_part[i..i + 2], 16))
         .collect::<Result<Vec<u8>, _>>()
         .map_err(|_| err("could not parse signature bytes"))?;
 
-    // Parse the number as u64
-    let number = parts[1].parse::<u64>().map_err(|_| err("could not parse call data size"))?;
+    Ok((bytes, Some(size)))
+}
 
-    Ok((bytes, number))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+    #[test]
+    fn test_parse_selector_size() {
+        let (selector, size) = parse_signature_size("0xa9059cbb-68").unwrap();
+        assert_eq!(selector, TRANSFER_SELECTOR.to_vec());
+        assert_eq!(size, Some(68));
+    }
+
+    #[test]
+    fn test_parse_full_signature_with_size() {
+        let (selector, size) = parse_signature_size("transfer(address,uint256)-68").unwrap();
+        assert_eq!(selector, TRANSFER_SELECTOR.to_vec());
+        assert_eq!(size, Some(68));
+    }
+
+    #[test]
+    fn test_parse_full_signature_without_size() {
+        let (selector, size) = parse_signature_size("transfer(address,uint256)").unwrap();
+        assert_eq!(selector, TRANSFER_SELECTOR.to_vec());
+        assert_eq!(size, None);
+    }
+
+    #[test]
+    fn test_fold_selector_totals() {
+        let tx = |entries: &[(&str, u64)]| -> BTreeMap<String, u64> {
+            entries.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+        };
+        let mut totals = BTreeMap::new();
+        let block_5 = (
+            Some(5),
+            vec![Some(0), Some(1)],
+            vec![None, None],
+            vec![tx(&[("0xa9059cbb-68", 2), ("0xa9059cbb-100", 1)]), tx(&[("0x095ea7b3-68", 1)])],
+        );
+        let block_3 = (Some(3), vec![Some(0)], vec![None], vec![tx(&[("0xa9059cbb-68", 4)])]);
+        fold_selector_totals(&block_5, &mut totals).unwrap();
+        fold_selector_totals(&block_3, &mut totals).unwrap();
+
+        let transfer = &totals[&TRANSFER_SELECTOR.to_vec()];
+        assert_eq!(transfer.total_count, 7);
+        assert_eq!(transfer.n_transactions, 2);
+        assert_eq!((transfer.first_block, transfer.last_block), (Some(3), Some(5)));
+        assert_eq!(totals.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_selector_without_size() {
+        assert!(parse_signature_size("0xa9059cbb").is_err());
+    }
 }
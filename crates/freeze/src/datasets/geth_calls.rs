@@ -16,9 +16,9 @@ pub struct GethCalls {
     input: Vec<Vec<u8>>,
     output: Vec<Option<Vec<u8>>>,
     error: Vec<Option<String>>,
-    block_number: Vec<Option<u32>>,
+    block_number: Vec<Option<u64>>,
     transaction_hash: Vec<Option<Vec<u8>>>,
-    transaction_index: Vec<u32>,
+    transaction_index: Vec<u64>,
     trace_address: Vec<String>,
     chain_id: Vec<u64>,
 }
@@ -26,87 +26,123 @@ pub struct GethCalls {
 #[async_trait::async_trait]
 impl Dataset for GethCalls {}
 
+type BlockTxsTraces = (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<CallFrame>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for GethCalls {
-    type Response = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>);
+    type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::GethCalls)?;
-        let include_transaction = schema.has_column("block_number");
-        let block_number = request.block_number()? as u32;
-        source.fetcher.geth_debug_trace_block_calls(block_number, include_transaction).await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        let block_number = request.block_number()?;
+        source.fetcher.geth_debug_trace_block_calls(block_number, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_geth_traces(response, columns, &query.schemas)
+        query.note_trace_positions(&Datatype::GethCalls, &response.1);
+        process_geth_traces(response, columns, &query.schemas, &query.trace_contract_filter)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for GethCalls {
-    type Response = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>);
+    type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::GethCalls)?;
-        let include_block_number = schema.has_column("block_number");
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
         source
             .fetcher
-            .geth_debug_trace_transaction_calls(request.transaction_hash()?, include_block_number)
+            .geth_debug_trace_transaction_calls(request.transaction_hash()?, include_transaction)
             .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_geth_traces(response, columns, &query.schemas)
+        query.note_trace_positions(&Datatype::GethCalls, &response.1);
+        process_geth_traces(response, columns, &query.schemas, &query.trace_contract_filter)
     }
 }
 
 fn process_geth_traces(
-    traces: (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>),
+    traces: BlockTxsTraces,
     columns: &mut GethCalls,
     schemas: &Schemas,
+    contract_filter: &Option<TraceContractFilter>,
 ) -> R<()> {
-    let (block_number, txs, traces) = traces;
+    let (block_number, indices, txs, traces) = traces;
     let schema = schemas.get(&Datatype::GethCalls).ok_or(err("schema for geth_traces missing"))?;
-    for (tx_index, (tx, trace)) in txs.into_iter().zip(traces).enumerate() {
-        process_trace(trace, columns, schema, &block_number, &tx, tx_index as u32, vec![])?
+    for (position, ((tx, trace), index)) in txs.into_iter().zip(traces).zip(indices).enumerate() {
+        process_trace(
+            trace,
+            columns,
+            schema,
+            &block_number,
+            &tx,
+            index.unwrap_or(position as u64),
+            vec![],
+            contract_filter,
+        )?
     }
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_trace(
-    trace: CallFrame,
+    mut trace: CallFrame,
     columns: &mut GethCalls,
     schema: &Table,
-    block_number: &Option<u32>,
+    block_number: &Option<u64>,
     tx: &Option<Vec<u8>>,
-    tx_index: u32,
+    tx_index: u64,
     trace_address: Vec<u32>,
+    contract_filter: &Option<TraceContractFilter>,
 ) -> R<()> {
-    columns.n_rows += 1;
-    store!(schema, columns, typ, trace.typ);
-    store!(schema, columns, from_address, trace.from.as_bytes().to_vec());
-    store!(schema, columns, to_address, noa_to_vec_u8(trace.to)?);
-    store!(schema, columns, value, trace.value);
-    store!(schema, columns, gas, trace.gas);
-    store!(schema, columns, gas_used, trace.gas_used);
-    store!(schema, columns, input, trace.input.0.to_vec());
-    store!(schema, columns, output, trace.output.map(|x| x.0.to_vec()));
-    store!(schema, columns, error, trace.error);
-    store!(schema, columns, block_number, *block_number);
-    store!(schema, columns, transaction_hash, tx.clone());
-    store!(schema, columns, transaction_index, tx_index);
-    store!(
-        schema,
-        columns,
-        trace_address,
-        trace_address.iter().map(|&n| n.to_string()).collect::<Vec<_>>().join(" ")
-    );
+    let subcalls = trace.calls.take();
+    let to = noa_to_vec_u8(trace.to.clone())?;
+    let keep = match contract_filter {
+        Some(filter) => filter.matches(Some(trace.from.as_bytes()), to.as_deref()),
+        None => true,
+    };
+    if keep {
+        columns.n_rows += 1;
+        store!(schema, columns, typ, trace.typ);
+        store!(schema, columns, from_address, trace.from.as_bytes().to_vec());
+        store!(schema, columns, to_address, to);
+        store!(schema, columns, value, trace.value);
+        store!(schema, columns, gas, trace.gas);
+        store!(schema, columns, gas_used, trace.gas_used);
+        store!(schema, columns, input, trace.input.0.to_vec());
+        store!(schema, columns, output, trace.output.map(|x| x.0.to_vec()));
+        store!(schema, columns, error, trace.error);
+        store!(schema, columns, block_number, *block_number);
+        store!(schema, columns, transaction_hash, tx.clone());
+        store!(schema, columns, transaction_index, tx_index);
+        store!(
+            schema,
+            columns,
+            trace_address,
+            trace_address.iter().map(|&n| n.to_string()).collect::<Vec<_>>().join(" ")
+        );
+    }
 
-    if let Some(subcalls) = trace.calls {
+    // subcalls of filtered out calls are still visited
+    if let Some(subcalls) = subcalls {
         for (s, subcall) in subcalls.into_iter().enumerate() {
             let mut sub_trace_address = trace_address.clone();
             sub_trace_address.push(s as u32);
-            process_trace(subcall, columns, schema, block_number, tx, tx_index, sub_trace_address)?
+            process_trace(
+                subcall,
+                columns,
+                schema,
+                block_number,
+                tx,
+                tx_index,
+                sub_trace_address,
+                contract_filter,
+            )?
         }
     }
 
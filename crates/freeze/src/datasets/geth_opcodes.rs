@@ -7,9 +7,9 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct GethOpcodes {
     n_rows: u64,
-    block_number: Vec<Option<u32>>,
+    block_number: Vec<Option<u64>>,
     transaction_hash: Vec<Option<Vec<u8>>>,
-    transaction_index: Vec<u32>,
+    transaction_index: Vec<u64>,
     trace_address: Vec<String>,
     depth: Vec<u64>,
     error: Vec<Option<String>>,
@@ -34,9 +34,11 @@ impl Dataset for GethOpcodes {
     }
 }
 
+type BlockTxsTraces = (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for GethOpcodes {
-    type Response = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>);
+    type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::GethOpcodes)?;
@@ -47,22 +49,21 @@ impl CollectByBlock for GethOpcodes {
             enable_return_data: Some(schema.has_column("return_data")),
             ..Default::default()
         };
-        let include_transaction = schema.has_column("block_number");
-        let block_number = request.block_number()? as u32;
-        source
-            .fetcher
-            .geth_debug_trace_block_opcodes(block_number, include_transaction, options)
-            .await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        let block_number = request.block_number()?;
+        source.fetcher.geth_debug_trace_block_opcodes(block_number, include_txs, options).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::GethOpcodes, &response.1);
         process_geth_opcodes(response, columns, &query.schemas)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for GethOpcodes {
-    type Response = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>);
+    type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::GethOpcodes)?;
@@ -73,32 +74,35 @@ impl CollectByTransaction for GethOpcodes {
             enable_return_data: Some(schema.has_column("return_data")),
             ..Default::default()
         };
-        let include_block_number = schema.has_column("block_number");
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
         source
             .fetcher
             .geth_debug_trace_transaction_opcodes(
                 request.transaction_hash()?,
-                include_block_number,
+                include_transaction,
                 options,
             )
             .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::GethOpcodes, &response.1);
         process_geth_opcodes(response, columns, &query.schemas)
     }
 }
 
 fn process_geth_opcodes(
-    traces: (Option<u32>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>),
+    traces: BlockTxsTraces,
     columns: &mut GethOpcodes,
     schemas: &Schemas,
 ) -> R<()> {
-    let (block_number, txs, traces) = traces;
+    let (block_number, indices, txs, traces) = traces;
     let schema =
         schemas.get(&Datatype::GethOpcodes).ok_or(err("schema for geth_traces missing"))?;
-    for (tx_index, (tx, trace)) in txs.into_iter().zip(traces).enumerate() {
-        process_trace(trace, columns, schema, &block_number, &tx, tx_index as u32, vec![])?
+    for (position, ((tx, trace), index)) in txs.into_iter().zip(traces).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
+        process_trace(trace, columns, schema, &block_number, &tx, index, vec![])?
     }
     Ok(())
 }
@@ -107,9 +111,9 @@ fn process_trace(
     trace: DefaultFrame,
     columns: &mut GethOpcodes,
     schema: &Table,
-    block_number: &Option<u32>,
+    block_number: &Option<u64>,
     tx: &Option<Vec<u8>>,
-    tx_index: u32,
+    tx_index: u64,
     trace_address: Vec<u32>,
 ) -> R<()> {
     let n_struct_logs = trace.struct_logs.len();
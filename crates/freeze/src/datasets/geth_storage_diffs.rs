@@ -6,7 +6,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct GethStorageDiffs {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
     pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) address: Vec<Vec<u8>>,
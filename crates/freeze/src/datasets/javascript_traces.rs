@@ -1,4 +1,5 @@
 use crate::*;
+use ethers::prelude::*;
 use polars::prelude::*;
 
 /// columns for transactions
@@ -6,8 +7,8 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct JavascriptTraces {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
-    pub(crate) transaction_index: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
+    pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) output: Vec<String>,
     pub(crate) chain_id: Vec<u64>,
@@ -20,7 +21,7 @@ impl Dataset for JavascriptTraces {
     }
 }
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<serde_json::Value>);
+type BlockTxsTraces = (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<serde_json::Value>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for JavascriptTraces {
@@ -29,19 +30,17 @@ impl CollectByBlock for JavascriptTraces {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::JavascriptTraces).ok_or(err("schema not provided"))?;
-        let include_txs = schema.has_column("transaction_hash");
-        let block = request.block_number()? as u32;
-        if let Some(js_tracer) = &query.js_tracer {
-            source
-                .fetcher
-                .geth_debug_trace_block_javascript_traces(js_tracer.clone(), block, include_txs)
-                .await
-        } else {
-            Err(err("javascript tracer not provided (try --js_tracer <TRACER>)"))
-        }
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        let block = request.block_number()?;
+        let js_tracer = get_js_tracer(&query)?;
+        let traces =
+            source.fetcher.geth_debug_trace_block_javascript_traces(js_tracer, block, include_txs);
+        record_tracer_compile_error(traces.await, &query)
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::JavascriptTraces, &response.1);
         process_javascript_traces(&response, columns, &query.schemas)
     }
 }
@@ -53,40 +52,77 @@ impl CollectByTransaction for JavascriptTraces {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::JavascriptTraces).ok_or(err("schema not provided"))?;
-        let include_block_number = schema.has_column("block_number");
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
         let tx = request.transaction_hash()?;
-        if let Some(js_tracer) = &query.js_tracer {
-            source
-                .fetcher
-                .geth_debug_trace_transaction_javascript_traces(
-                    js_tracer.clone(),
-                    tx,
-                    include_block_number,
-                )
-                .await
-        } else {
-            Err(err("javascript tracer not provided (try --js_tracer <TRACER>)"))
-        }
+        let js_tracer = get_js_tracer(&query)?;
+        let traces = source.fetcher.geth_debug_trace_transaction_javascript_traces(
+            js_tracer,
+            tx,
+            include_transaction,
+        );
+        record_tracer_compile_error(traces.await, &query)
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::JavascriptTraces, &response.1);
         process_javascript_traces(&response, columns, &query.schemas)
     }
 }
 
+/// get tracer, failing without a request if an earlier chunk found that it does not compile
+fn get_js_tracer(query: &Query) -> R<String> {
+    if let Some(message) = query.tracer_error.get() {
+        return Err(err(&message))
+    }
+    query.js_tracer.clone().ok_or(err("javascript tracer not provided (try --js-tracer <TRACER>)"))
+}
+
+/// compile errors recur for every chunk, so the node's message is recorded for reuse
+fn record_tracer_compile_error(result: R<BlockTxsTraces>, query: &Query) -> R<BlockTxsTraces> {
+    let message = match &result {
+        Err(CollectError::ProviderError(e)) => match RpcError::as_error_response(e) {
+            Some(response) if is_tracer_compile_error(&response.message) => {
+                format!("javascript tracer failed to compile: {}", response.message)
+            }
+            _ => return result,
+        },
+        _ => return result,
+    };
+    query.tracer_error.set(&message);
+    Err(err(&query.tracer_error.get().unwrap_or(message)))
+}
+
+/// geth reports syntax errors of the tracer and tracers missing required methods
+fn is_tracer_compile_error(message: &str) -> bool {
+    message.contains("SyntaxError") || message.contains("must expose")
+}
+
 pub(crate) fn process_javascript_traces(
     response: &BlockTxsTraces,
     columns: &mut JavascriptTraces,
     schemas: &Schemas,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::JavascriptTraces).ok_or(err("schema not provided"))?;
-    let (block_number, txs, traces) = response;
-    for (index, (value, tx)) in traces.iter().zip(txs).enumerate() {
+    let (block_number, indices, txs, traces) = response;
+    for (position, ((value, tx), index)) in traces.iter().zip(txs).zip(indices).enumerate() {
         columns.n_rows += 1;
         store!(schema, columns, block_number, *block_number);
-        store!(schema, columns, transaction_index, Some(index as u32));
+        store!(schema, columns, transaction_index, Some(index.unwrap_or(position as u64)));
         store!(schema, columns, transaction_hash, tx.clone());
         store!(schema, columns, output, value.to_string());
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_tracer_compile_error() {
+        assert!(is_tracer_compile_error("SyntaxError: (anonymous): Line 1:2 Unexpected token"));
+        assert!(is_tracer_compile_error("trace object must expose a function result()"));
+        assert!(!is_tracer_compile_error("execution timeout"));
+    }
+}
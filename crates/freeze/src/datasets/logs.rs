@@ -7,9 +7,9 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Logs {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Option<Vec<u8>>>,
-    transaction_index: Vec<u32>,
+    transaction_index: Vec<u64>,
     log_index: Vec<u32>,
     transaction_hash: Vec<Vec<u8>>,
     address: Vec<Vec<u8>>,
@@ -18,6 +18,13 @@ pub struct Logs {
     topic2: Vec<Option<Vec<u8>>>,
     topic3: Vec<Option<Vec<u8>>>,
     data: Vec<Vec<u8>>,
+    /// null if logs were filtered and the node does not report it
+    transaction_log_index: Vec<Option<u32>>,
+    n_topics: Vec<u32>,
+    /// index of the --filter-set that matched the log
+    filter_set_index: Vec<Option<u32>>,
+    /// true when the log has no topics, which only anonymous events can emit
+    is_anonymous_candidate: Vec<bool>,
     event_cols: indexmap::IndexMap<String, Vec<ethers_core::abi::Token>>,
     chain_id: Vec<u64>,
 }
@@ -57,14 +64,30 @@ impl Dataset for Logs {
     fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
         Some([(Dim::Contract, Dim::Address)].into_iter().collect())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("address")
+    }
 }
 
+/// logs, whether they include every log of their transactions, and the index of the filter set
+/// matching each log
+type LogsResponse = (Vec<Log>, bool, Option<Vec<u32>>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for Logs {
-    type Response = Vec<Log>;
+    type Response = LogsResponse;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.get_logs(&request.ethers_log_filter()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        if let Some(filter_sets) = &query.log_filters {
+            return fetch_filter_sets(&request, filter_sets, &source).await
+        }
+        let logs = source.fetcher.get_logs(&request.ethers_log_filter()?).await?;
+        let unfiltered = request.address.is_none() &&
+            [&request.topic0, &request.topic1, &request.topic2, &request.topic3]
+                .iter()
+                .all(|topic| topic.is_none());
+        Ok((logs, unfiltered, None))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -75,10 +98,10 @@ impl CollectByBlock for Logs {
 
 #[async_trait::async_trait]
 impl CollectByTransaction for Logs {
-    type Response = Vec<Log>;
+    type Response = LogsResponse;
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.get_transaction_logs(request.transaction_hash()?).await
+        Ok((source.fetcher.get_transaction_logs(request.transaction_hash()?).await?, true, None))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -87,8 +110,44 @@ impl CollectByTransaction for Logs {
     }
 }
 
+/// request logs of each filter set, merging logs matched by several sets into the first of them
+async fn fetch_filter_sets(
+    request: &Params,
+    filter_sets: &[LogFilter],
+    source: &Source,
+) -> R<LogsResponse> {
+    let filters: Vec<Filter> = filter_sets
+        .iter()
+        .map(|filter_set| request.ethers_log_filter_set(filter_set))
+        .collect::<R<_>>()?;
+    let responses =
+        futures::future::try_join_all(filters.iter().map(|filter| source.fetcher.get_logs(filter)))
+            .await?;
+    Ok(merge_filter_sets(responses, filter_sets.iter().all(|filter_set| filter_set.is_empty())))
+}
+
+fn merge_filter_sets(responses: Vec<Vec<Log>>, unfiltered: bool) -> LogsResponse {
+    let mut logs: Vec<(Log, u32)> = responses
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, logs)| logs.into_iter().map(move |log| (log, index as u32)))
+        .collect();
+    // stable sort keeps the first matching set of each log first
+    logs.sort_by_key(|(log, _)| (log.block_number, log.log_index));
+    logs.dedup_by_key(|(log, _)| (log.block_number, log.log_index));
+    let (logs, indices) = logs.into_iter().unzip();
+    (logs, unfiltered, Some(indices))
+}
+
 /// process block into columns
-fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
+fn process_logs(response: LogsResponse, columns: &mut Logs, schema: &Table) -> R<()> {
+    let (logs, unfiltered, filter_set_indices) = response;
+    let transaction_log_indices = if schema.has_column("transaction_log_index") {
+        transaction_log_indices(&logs, unfiltered)
+    } else {
+        Vec::new()
+    };
+
     let decode_keys = match &schema.log_decoder {
         None => None,
         Some(decoder) => {
@@ -103,7 +162,7 @@ fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
         }
     };
 
-    for log in logs.iter() {
+    for (i, log) in logs.iter().enumerate() {
         if let (Some(bn), Some(tx), Some(ti), Some(li)) =
             (log.block_number, log.transaction_hash, log.transaction_index, log.log_index)
         {
@@ -122,13 +181,22 @@ fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
             };
 
             columns.n_rows += 1;
-            store!(schema, columns, block_number, bn.as_u32());
+            store!(schema, columns, block_number, bn.as_u64());
             store!(schema, columns, block_hash, log.block_hash.map(|bh| bh.as_bytes().to_vec()));
-            store!(schema, columns, transaction_index, ti.as_u32());
+            store!(schema, columns, transaction_index, ti.as_u64());
             store!(schema, columns, log_index, li.as_u32());
             store!(schema, columns, transaction_hash, tx.as_bytes().to_vec());
             store!(schema, columns, address, log.address.as_bytes().to_vec());
             store!(schema, columns, data, log.data.to_vec());
+            store!(schema, columns, transaction_log_index, transaction_log_indices[i]);
+            store!(schema, columns, n_topics, log.topics.len() as u32);
+            store!(schema, columns, is_anonymous_candidate, log.topics.is_empty());
+            store!(
+                schema,
+                columns,
+                filter_set_index,
+                filter_set_indices.as_ref().map(|indices| indices[i])
+            );
 
             // topics
             for i in 0..4 {
@@ -150,3 +218,81 @@ fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
 
     Ok(())
 }
+
+/// position of each log within its transaction, as reported by the node or else counted from
+/// the block's logs if none of them were filtered out
+fn transaction_log_indices(logs: &[Log], unfiltered: bool) -> Vec<Option<u32>> {
+    let mut log_indices_by_tx: std::collections::HashMap<H256, Vec<U256>> = Default::default();
+    if unfiltered {
+        for log in logs.iter() {
+            if let (Some(tx), Some(log_index)) = (log.transaction_hash, log.log_index) {
+                log_indices_by_tx.entry(tx).or_default().push(log_index)
+            }
+        }
+        log_indices_by_tx.values_mut().for_each(|log_indices| log_indices.sort());
+    }
+    logs.iter()
+        .map(|log| match (log.transaction_log_index, log.transaction_hash, log.log_index) {
+            (Some(transaction_log_index), _, _) => Some(transaction_log_index.as_u32()),
+            (None, Some(tx), Some(log_index)) => log_indices_by_tx
+                .get(&tx)
+                .and_then(|log_indices| log_indices.binary_search(&log_index).ok())
+                .map(|position| position as u32),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(tx: u64, log_index: u64, n_topics: usize) -> Log {
+        Log {
+            block_number: Some(1.into()),
+            transaction_hash: Some(H256::from_low_u64_be(tx)),
+            transaction_index: Some(tx.into()),
+            log_index: Some(log_index.into()),
+            topics: vec![H256::zero(); n_topics],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_log_positions() {
+        let include = Some(
+            ["transaction_log_index", "n_topics", "is_anonymous_candidate"]
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+        );
+        let schema = Datatype::Logs
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        let logs = vec![log(1, 0, 3), log(1, 1, 1), log(2, 2, 0), log(1, 3, 4)];
+
+        let mut columns = Logs::default();
+        process_logs((logs.clone(), true, None), &mut columns, &schema).unwrap();
+        assert_eq!(columns.transaction_log_index, vec![Some(0), Some(1), Some(0), Some(2)]);
+        assert_eq!(columns.n_topics, vec![3, 1, 0, 4]);
+        assert_eq!(columns.is_anonymous_candidate, vec![false, false, true, false]);
+
+        // positions of filtered logs are only known when reported by the node
+        let mut logs = logs;
+        logs[3].transaction_log_index = Some(5.into());
+        let mut columns = Logs::default();
+        process_logs((logs, false, None), &mut columns, &schema).unwrap();
+        assert_eq!(columns.transaction_log_index, vec![None, None, None, Some(5)]);
+    }
+
+    #[test]
+    fn test_merge_filter_sets() {
+        // log 1 matches both filter sets, and is attributed to the first
+        let responses = vec![vec![log(1, 1, 1), log(2, 4, 1)], vec![log(1, 0, 1), log(1, 1, 1)]];
+        let (logs, unfiltered, indices) = merge_filter_sets(responses, false);
+        let log_indices: Vec<_> = logs.iter().map(|log| log.log_index.unwrap().as_u64()).collect();
+        assert_eq!(log_indices, vec![0, 1, 4]);
+        assert_eq!(indices, Some(vec![1, 0, 0]));
+        assert!(!unfiltered);
+    }
+}
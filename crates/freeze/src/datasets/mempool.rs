@@ -0,0 +1,115 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for mempool
+#[cryo_to_df::to_df(Datatype::Mempool)]
+#[derive(Default)]
+pub struct Mempool {
+    n_rows: u64,
+    snapshot_timestamp: Vec<u32>,
+    pool: Vec<String>,
+    sender: Vec<Vec<u8>>,
+    nonce: Vec<u64>,
+    transaction_hash: Vec<Vec<u8>>,
+    gas_limit: Vec<u64>,
+    gas_price: Vec<Option<u64>>,
+    max_fee_per_gas: Vec<Option<u64>>,
+    max_priority_fee_per_gas: Vec<Option<u64>>,
+    to_address: Vec<Option<Vec<u8>>>,
+    value: Vec<U256>,
+    input_selector: Vec<Option<Vec<u8>>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Mempool {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["snapshot_timestamp", "sender", "nonce"])
+    }
+}
+
+impl CollectByBlock for Mempool {
+    type Response = ();
+}
+
+impl CollectByTransaction for Mempool {
+    type Response = ();
+}
+
+/// how long pending transaction hashes are gathered when txpool_content is unavailable
+const PENDING_FILTER_WAIT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// snapshot timestamp and (pool, transaction) pairs
+type MempoolSnapshot = (u32, Vec<(&'static str, Transaction)>);
+
+#[async_trait::async_trait]
+impl CollectBySnapshot for Mempool {
+    type Response = MempoolSnapshot;
+
+    async fn extract(source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| err("could not compute snapshot timestamp"))?
+            .as_secs() as u32;
+        let transactions = match source.fetcher.txpool_content().await {
+            Ok(content) => {
+                let pending = content.pending.into_values().flat_map(|txs| txs.into_values());
+                let queued = content.queued.into_values().flat_map(|txs| txs.into_values());
+                pending.map(|tx| ("pending", tx)).chain(queued.map(|tx| ("queued", tx))).collect()
+            }
+            // nodes without the txpool api can still report pending transactions via filters
+            Err(CollectError::MethodNotFound(_)) => get_filtered_pending_transactions(&source)
+                .await?
+                .into_iter()
+                .map(|tx| ("pending", tx))
+                .collect(),
+            Err(e) => return Err(e),
+        };
+        Ok((timestamp, transactions))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Mempool)?;
+        let (timestamp, transactions) = response;
+        for (pool, tx) in transactions.into_iter() {
+            columns.n_rows += 1;
+            store!(schema, columns, snapshot_timestamp, timestamp);
+            store!(schema, columns, pool, pool.to_string());
+            store!(schema, columns, sender, tx.from.as_bytes().to_vec());
+            store!(schema, columns, nonce, tx.nonce.as_u64());
+            store!(schema, columns, transaction_hash, tx.hash.as_bytes().to_vec());
+            store!(schema, columns, gas_limit, tx.gas.as_u64());
+            store!(schema, columns, gas_price, tx.gas_price.map(|x| x.as_u64()));
+            store!(schema, columns, max_fee_per_gas, tx.max_fee_per_gas.map(|x| x.as_u64()));
+            store!(
+                schema,
+                columns,
+                max_priority_fee_per_gas,
+                tx.max_priority_fee_per_gas.map(|x| x.as_u64())
+            );
+            store!(schema, columns, to_address, tx.to.map(|x| x.as_bytes().to_vec()));
+            store!(schema, columns, value, tx.value);
+            store!(schema, columns, input_selector, tx.input.get(..4).map(|x| x.to_vec()));
+        }
+        Ok(())
+    }
+}
+
+/// gather pending transactions received by a pending transaction filter
+async fn get_filtered_pending_transactions(source: &Source) -> R<Vec<Transaction>> {
+    let filter_id = source.fetcher.new_pending_transaction_filter().await?;
+    tokio::time::sleep(PENDING_FILTER_WAIT).await;
+    let hashes = source.fetcher.get_pending_transaction_filter_changes(filter_id).await;
+    let _ = source.fetcher.uninstall_filter(filter_id).await;
+    let mut transactions = Vec::new();
+    for hash in hashes?.into_iter() {
+        // transactions that were mined or dropped since being received are skipped
+        if let Some(tx) = source.fetcher.get_transaction(hash).await? {
+            if tx.block_number.is_none() {
+                transactions.push(tx)
+            }
+        }
+    }
+    Ok(transactions)
+}
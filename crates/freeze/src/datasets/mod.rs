@@ -1,3 +1,5 @@
+/// access lists
+pub mod access_lists;
 /// address appearances
 pub mod address_appearances;
 /// balance diffs
@@ -6,6 +8,10 @@ pub mod balance_diffs;
 pub mod balance_reads;
 /// balances
 pub mod balances;
+/// blobs
+pub mod blobs;
+/// block rewards
+pub mod block_rewards;
 /// blocks
 pub mod blocks;
 /// code diffs
@@ -16,6 +22,14 @@ pub mod code_reads;
 pub mod codes;
 /// contracts
 pub mod contracts;
+/// decoded calls
+pub mod decoded_calls;
+/// decoded events
+pub mod decoded_events;
+/// erc1155 transfers
+pub mod erc1155_transfers;
+/// erc20 approvals
+pub mod erc20_approvals;
 /// erc20 balances
 pub mod erc20_balances;
 /// erc20 metadata
@@ -24,12 +38,16 @@ pub mod erc20_metadata;
 pub mod erc20_supplies;
 /// erc20 transfers
 pub mod erc20_transfers;
+/// erc4626 snapshots
+pub mod erc4626_snapshots;
 /// erc721 metadata
 pub mod erc721_metadata;
 /// erc721 transfers
 pub mod erc721_transfers;
 /// eth calls
 pub mod eth_calls;
+#[cfg(test)]
+pub(crate) mod fixtures;
 /// four byte counts
 pub mod four_byte_counts;
 /// geth balance diffs
@@ -48,6 +66,8 @@ pub mod geth_storage_diffs;
 pub mod javascript_traces;
 /// logs
 pub mod logs;
+/// mempool
+pub mod mempool;
 /// native transfers
 pub mod native_transfers;
 /// nonce diffs
@@ -56,6 +76,16 @@ pub mod nonce_diffs;
 pub mod nonce_reads;
 /// nonces
 pub mod nonces;
+/// opcode counts
+pub mod opcode_counts;
+/// precompile calls
+pub mod precompile_calls;
+/// receipts
+pub mod receipts;
+/// revert reasons
+pub mod revert_reasons;
+/// selfdestructs
+pub mod selfdestructs;
 /// slots
 pub mod slots;
 /// storage diffs
@@ -66,24 +96,38 @@ pub mod storage_reads;
 pub mod trace_calls;
 /// traces
 pub mod traces;
+/// transaction fees
+pub mod transaction_fees;
 /// transactions
 pub mod transactions;
+/// uncles
+pub mod uncles;
 /// vm traces
 pub mod vm_traces;
+/// withdrawals
+pub mod withdrawals;
 
+pub use access_lists::*;
 pub use address_appearances::*;
 pub use balance_diffs::*;
 pub use balance_reads::*;
 pub use balances::*;
+pub use blobs::*;
+pub use block_rewards::*;
 pub use blocks::*;
 pub use code_diffs::*;
 pub use code_reads::*;
 pub use codes::*;
 pub use contracts::*;
+pub use decoded_calls::*;
+pub use decoded_events::*;
+pub use erc1155_transfers::*;
+pub use erc20_approvals::*;
 pub use erc20_balances::*;
 pub use erc20_metadata::*;
 pub use erc20_supplies::*;
 pub use erc20_transfers::*;
+pub use erc4626_snapshots::*;
 pub use erc721_metadata::*;
 pub use erc721_transfers::*;
 pub use eth_calls::*;
@@ -96,14 +140,23 @@ pub use geth_opcodes::*;
 pub use geth_storage_diffs::*;
 pub use javascript_traces::*;
 pub use logs::*;
+pub use mempool::*;
 pub use native_transfers::*;
 pub use nonce_diffs::*;
 pub use nonce_reads::*;
 pub use nonces::*;
+pub use opcode_counts::*;
+pub use precompile_calls::*;
+pub use receipts::*;
+pub use revert_reasons::*;
+pub use selfdestructs::*;
 pub use slots::*;
 pub use storage_diffs::*;
 pub use storage_reads::*;
 pub use trace_calls::*;
 pub use traces::*;
+pub use transaction_fees::*;
 pub use transactions::*;
+pub use uncles::*;
 pub use vm_traces::*;
+pub use withdrawals::*;
@@ -7,19 +7,36 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct NativeTransfers {
     n_rows: u64,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Vec<u8>>,
-    transaction_index: Vec<Option<u32>>,
+    transaction_index: Vec<Option<u64>>,
     transfer_index: Vec<u32>,
     transaction_hash: Vec<Option<Vec<u8>>>,
     from_address: Vec<Vec<u8>>,
     to_address: Vec<Vec<u8>>,
     value: Vec<U256>,
+    trace_address: Vec<Option<String>>,
+    depth: Vec<Option<u32>>,
+    call_type: Vec<String>,
     chain_id: Vec<u64>,
 }
 
 #[async_trait::async_trait]
 impl Dataset for NativeTransfers {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "block_hash",
+            "transaction_index",
+            "transfer_index",
+            "transaction_hash",
+            "from_address",
+            "to_address",
+            "value",
+            "chain_id",
+        ])
+    }
+
     fn optional_parameters() -> Vec<Dim> {
         vec![Dim::FromAddress, Dim::ToAddress]
     }
@@ -30,7 +47,7 @@ impl CollectByBlock for NativeTransfers {
     type Response = Vec<Trace>;
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let traces = source.fetcher.trace_block(request.block_number()?.into()).await?;
+        let traces = source.trace_block(request.block_number()?).await?;
         Ok(filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address))
     }
 
@@ -46,7 +63,7 @@ impl CollectByTransaction for NativeTransfers {
     type Response = Vec<Trace>;
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let traces = source.fetcher.trace_transaction(request.ethers_transaction_hash()?).await?;
+        let traces = source.trace_transaction(request.ethers_transaction_hash()?).await?;
         Ok(filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address))
     }
 
@@ -66,8 +83,8 @@ pub(crate) fn process_native_transfers(
     let schema = schemas.get(&Datatype::NativeTransfers).ok_or(err("schema not provided"))?;
     for (transfer_index, trace) in traces.iter().enumerate() {
         columns.n_rows += 1;
-        store!(schema, columns, block_number, trace.block_number as u32);
-        store!(schema, columns, transaction_index, trace.transaction_position.map(|x| x as u32));
+        store!(schema, columns, block_number, trace.block_number);
+        store!(schema, columns, transaction_index, trace.transaction_position.map(|x| x as u64));
         store!(schema, columns, block_hash, trace.block_hash.as_bytes().to_vec());
         store!(schema, columns, transfer_index, transfer_index as u32);
         store!(
@@ -77,11 +94,25 @@ pub(crate) fn process_native_transfers(
             trace.transaction_hash.map(|x| x.as_bytes().to_vec())
         );
 
+        // block rewards are not part of any call tree
+        let (trace_address, depth) = match &trace.action {
+            Action::Reward(_) => (None, None),
+            _ => (
+                Some(
+                    trace.trace_address.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("_"),
+                ),
+                Some(trace.trace_address.len() as u32),
+            ),
+        };
+        store!(schema, columns, trace_address, trace_address);
+        store!(schema, columns, depth, depth);
+
         match &trace.action {
             Action::Call(action) => {
                 store!(schema, columns, from_address, action.from.as_bytes().to_vec());
                 store!(schema, columns, to_address, action.to.as_bytes().to_vec());
                 store!(schema, columns, value, action.value);
+                store!(schema, columns, call_type, action_call_type_to_string(&action.call_type));
             }
             Action::Create(action) => {
                 store!(schema, columns, from_address, action.from.as_bytes().to_vec());
@@ -92,18 +123,67 @@ pub(crate) fn process_native_transfers(
                     _ => store!(schema, columns, to_address, vec![0; 32]),
                 }
                 store!(schema, columns, value, action.value);
+                store!(schema, columns, call_type, "create".to_string());
             }
             Action::Suicide(action) => {
                 store!(schema, columns, from_address, action.address.as_bytes().to_vec());
                 store!(schema, columns, to_address, action.refund_address.as_bytes().to_vec());
                 store!(schema, columns, value, action.balance);
+                store!(schema, columns, call_type, "selfdestruct".to_string());
             }
             Action::Reward(action) => {
                 store!(schema, columns, from_address, vec![0; 20]);
                 store!(schema, columns, to_address, action.author.as_bytes().to_vec());
                 store!(schema, columns, value, action.value);
+                store!(schema, columns, call_type, "reward".to_string());
             }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_tree_columns() {
+        let include = Some(vec!["trace_address".to_string(), "call_type".to_string()]);
+        let schema = Datatype::NativeTransfers
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        let mut schemas = Schemas::new();
+        schemas.insert(Datatype::NativeTransfers, schema);
+
+        let call = Trace {
+            action: Action::Call(ethers::types::Call {
+                call_type: CallType::DelegateCall,
+                ..Default::default()
+            }),
+            result: None,
+            trace_address: vec![0, 2, 1],
+            subtraces: 0,
+            transaction_position: Some(0),
+            transaction_hash: None,
+            block_number: 1,
+            block_hash: H256::zero(),
+            action_type: ActionType::Call,
+            error: None,
+        };
+        let reward = Trace {
+            action: Action::Reward(Reward {
+                author: Address::zero(),
+                value: U256::zero(),
+                reward_type: RewardType::Block,
+            }),
+            trace_address: vec![],
+            action_type: ActionType::Reward,
+            ..call.clone()
+        };
+        let mut columns = NativeTransfers::default();
+        process_native_transfers(&[call, reward], &mut columns, &schemas).unwrap();
+        assert_eq!(columns.trace_address, vec![Some("0_2_1".to_string()), None]);
+        assert_eq!(columns.call_type, vec!["delegate_call".to_string(), "reward".to_string()]);
+        assert!(columns.depth.is_empty());
+    }
+}
@@ -7,8 +7,8 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct NonceDiffs {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
-    pub(crate) transaction_index: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
+    pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) address: Vec<Vec<u8>>,
     pub(crate) from_value: Vec<u64>,
@@ -19,7 +19,8 @@ pub struct NonceDiffs {
 #[async_trait::async_trait]
 impl Dataset for NonceDiffs {}
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for NonceDiffs {
@@ -27,11 +28,13 @@ impl CollectByBlock for NonceDiffs {
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::NonceDiffs)?;
-        let include_txs = schema.has_column("transaction_hash");
-        source.fetcher.trace_block_state_diffs(request.block_number()? as u32, include_txs).await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source.trace_block_state_diffs(request.block_number()?, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::NonceDiffs, &response.1);
         process_nonce_diffs(&response, columns, &query.schemas)
     }
 }
@@ -40,11 +43,15 @@ impl CollectByBlock for NonceDiffs {
 impl CollectByTransaction for NonceDiffs {
     type Response = BlockTxsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::NonceDiffs)?;
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
+        source.trace_transaction_state_diffs(request.transaction_hash()?, include_transaction).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::NonceDiffs, &response.1);
         process_nonce_diffs(&response, columns, &query.schemas)
     }
 }
@@ -55,8 +62,9 @@ pub(crate) fn process_nonce_diffs(
     schemas: &Schemas,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::NonceDiffs).ok_or(err("schema not provided"))?;
-    let (block_number, txs, traces) = response;
-    for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
+    let (block_number, indices, txs, traces) = response;
+    for (position, ((trace, tx), index)) in traces.iter().zip(txs).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
         if let Some(ethers::types::StateDiff(state_diffs)) = &trace.state_diff {
             for (addr, diff) in state_diffs.iter() {
                 process_nonce_diff(addr, &diff.nonce, block_number, tx, index, columns, schema);
@@ -69,9 +77,9 @@ pub(crate) fn process_nonce_diffs(
 pub(crate) fn process_nonce_diff(
     addr: &H160,
     diff: &Diff<U256>,
-    block_number: &Option<u32>,
+    block_number: &Option<u64>,
     transaction_hash: &Option<Vec<u8>>,
-    transaction_index: usize,
+    transaction_index: u64,
     columns: &mut NonceDiffs,
     schema: &Table,
 ) {
@@ -83,7 +91,7 @@ pub(crate) fn process_nonce_diff(
     };
     columns.n_rows += 1;
     store!(schema, columns, block_number, *block_number);
-    store!(schema, columns, transaction_index, Some(transaction_index as u32));
+    store!(schema, columns, transaction_index, Some(transaction_index));
     store!(schema, columns, transaction_hash, transaction_hash.clone());
     store!(schema, columns, address, addr.as_bytes().to_vec());
     store!(schema, columns, from_value, from.as_u64());
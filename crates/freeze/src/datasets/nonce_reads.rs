@@ -8,8 +8,8 @@ use std::collections::BTreeMap;
 #[derive(Default)]
 pub struct NonceReads {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
-    pub(crate) transaction_index: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
+    pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) address: Vec<Vec<u8>>,
     pub(crate) nonce: Vec<u64>,
@@ -19,7 +19,8 @@ pub struct NonceReads {
 #[async_trait::async_trait]
 impl Dataset for NonceReads {}
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<H160, AccountState>>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<H160, AccountState>>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for NonceReads {
@@ -27,14 +28,13 @@ impl CollectByBlock for NonceReads {
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get(&Datatype::NonceReads).ok_or(err("schema not provided"))?;
-        let include_txs = schema.has_column("transaction_hash");
-        source
-            .fetcher
-            .geth_debug_trace_block_prestate(request.block_number()? as u32, include_txs)
-            .await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source.fetcher.geth_debug_trace_block_prestate(request.block_number()?, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::NonceReads, &response.1);
         process_nonce_reads(&response, columns, &query.schemas)
     }
 }
@@ -45,12 +45,14 @@ impl CollectByTransaction for NonceReads {
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get(&Datatype::NonceReads).ok_or(err("schema not provided"))?;
-        let include_block_number = schema.has_column("block_number");
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
         let tx = request.transaction_hash()?;
-        source.fetcher.geth_debug_trace_transaction_prestate(tx, include_block_number).await
+        source.fetcher.geth_debug_trace_transaction_prestate(tx, include_transaction).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        query.note_trace_positions(&Datatype::NonceReads, &response.1);
         process_nonce_reads(&response, columns, &query.schemas)
     }
 }
@@ -61,8 +63,9 @@ pub(crate) fn process_nonce_reads(
     schemas: &Schemas,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::NonceReads).ok_or(err("schema not provided"))?;
-    let (block_number, txs, traces) = response;
-    for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
+    let (block_number, indices, txs, traces) = response;
+    for (position, ((trace, tx), index)) in traces.iter().zip(txs).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
         for (addr, account_state) in trace.iter() {
             process_nonce_read(addr, account_state, block_number, tx, index, columns, schema);
         }
@@ -73,16 +76,16 @@ pub(crate) fn process_nonce_reads(
 pub(crate) fn process_nonce_read(
     addr: &H160,
     account_state: &AccountState,
-    block_number: &Option<u32>,
+    block_number: &Option<u64>,
     transaction_hash: &Option<Vec<u8>>,
-    transaction_index: usize,
+    transaction_index: u64,
     columns: &mut NonceReads,
     schema: &Table,
 ) {
     if let Some(nonce) = &account_state.nonce {
         columns.n_rows += 1;
         store!(schema, columns, block_number, *block_number);
-        store!(schema, columns, transaction_index, Some(transaction_index as u32));
+        store!(schema, columns, transaction_index, Some(transaction_index));
         store!(schema, columns, transaction_hash, transaction_hash.clone());
         store!(schema, columns, address, addr.as_bytes().to_vec());
         store!(schema, columns, nonce, nonce.as_u64());
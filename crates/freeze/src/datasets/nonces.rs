@@ -7,7 +7,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Nonces {
     n_rows: usize,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     address: Vec<Vec<u8>>,
     nonce: Vec<u64>,
     chain_id: Vec<u64>,
@@ -26,9 +26,13 @@ impl Dataset for Nonces {
     fn default_blocks() -> Option<String> {
         Some("latest".to_string())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("address")
+    }
 }
 
-type BlockTxAddressOutput = (u32, Option<Vec<u8>>, Vec<u8>, u64);
+type BlockTxAddressOutput = (u64, Option<Vec<u8>>, Vec<u8>, u64);
 
 #[async_trait::async_trait]
 impl CollectByBlock for Nonces {
@@ -36,7 +40,7 @@ impl CollectByBlock for Nonces {
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let address = request.address()?;
-        let block_number = request.block_number()? as u32;
+        let block_number = request.block_number()?;
         let output = source
             .fetcher
             .get_transaction_count(H160::from_slice(&address), block_number.into())
@@ -0,0 +1,146 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// columns for opcode counts
+#[cryo_to_df::to_df(Datatype::OpcodeCounts)]
+#[derive(Default)]
+pub struct OpcodeCounts {
+    n_rows: u64,
+    block_number: Vec<Option<u64>>,
+    transaction_index: Vec<u64>,
+    transaction_hash: Vec<Option<Vec<u8>>>,
+    opcode: Vec<String>,
+    count: Vec<u64>,
+    total_gas: Vec<u64>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for OpcodeCounts {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index", "opcode"])
+    }
+}
+
+type BlockTxsFrames = (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>);
+
+/// only opcodes and their gas costs are needed, so memory, stack, and storage are not captured
+fn opcode_tracing_options() -> GethDebugTracingOptions {
+    GethDebugTracingOptions {
+        disable_storage: Some(true),
+        disable_stack: Some(true),
+        enable_memory: Some(false),
+        enable_return_data: Some(false),
+        ..Default::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for OpcodeCounts {
+    type Response = BlockTxsFrames;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::OpcodeCounts)?;
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source
+            .fetcher
+            .geth_debug_trace_block_opcodes(
+                request.block_number()?,
+                include_txs,
+                opcode_tracing_options(),
+            )
+            .await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::OpcodeCounts)?;
+        query.note_trace_positions(&Datatype::OpcodeCounts, &response.1);
+        process_opcode_counts(response, columns, schema)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for OpcodeCounts {
+    type Response = BlockTxsFrames;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::OpcodeCounts)?;
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
+        source
+            .fetcher
+            .geth_debug_trace_transaction_opcodes(
+                request.transaction_hash()?,
+                include_transaction,
+                opcode_tracing_options(),
+            )
+            .await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::OpcodeCounts)?;
+        query.note_trace_positions(&Datatype::OpcodeCounts, &response.1);
+        process_opcode_counts(response, columns, schema)
+    }
+}
+
+/// aggregate (count, total gas) of each opcode of a trace
+fn count_opcodes(frame: &DefaultFrame) -> BTreeMap<String, (u64, u64)> {
+    let mut counts: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for struct_log in frame.struct_logs.iter() {
+        let entry = counts.entry(struct_log.op.clone()).or_default();
+        entry.0 += 1;
+        entry.1 += struct_log.gas_cost;
+    }
+    counts
+}
+
+fn process_opcode_counts(
+    response: BlockTxsFrames,
+    columns: &mut OpcodeCounts,
+    schema: &Table,
+) -> R<()> {
+    let (block_number, indices, txs, frames) = response;
+    for (position, ((tx, frame), index)) in txs.into_iter().zip(frames).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
+        for (opcode, (count, total_gas)) in count_opcodes(&frame).into_iter() {
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, block_number);
+            store!(schema, columns, transaction_index, index);
+            store!(schema, columns, transaction_hash, tx.clone());
+            store!(schema, columns, opcode, opcode);
+            store!(schema, columns, count, count);
+            store!(schema, columns, total_gas, total_gas);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_opcodes() {
+        let struct_log = |op: &str, gas_cost: u64| StructLog {
+            op: op.to_string(),
+            gas_cost,
+            ..Default::default()
+        };
+        let frame = DefaultFrame {
+            struct_logs: vec![
+                struct_log("PUSH1", 3),
+                struct_log("SLOAD", 2100),
+                struct_log("PUSH1", 3),
+            ],
+            ..Default::default()
+        };
+        let counts = count_opcodes(&frame);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["PUSH1"], (2, 6));
+        assert_eq!(counts["SLOAD"], (1, 2100));
+    }
+}
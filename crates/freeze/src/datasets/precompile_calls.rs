@@ -0,0 +1,127 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for precompile calls
+#[cryo_to_df::to_df(Datatype::PrecompileCalls)]
+#[derive(Default)]
+pub struct PrecompileCalls {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    transaction_hash: Vec<Option<Vec<u8>>>,
+    transaction_index: Vec<Option<u64>>,
+    trace_address: Vec<String>,
+    precompile_address: Vec<Vec<u8>>,
+    input_size: Vec<u32>,
+    output_size: Vec<Option<u32>>,
+    gas_used: Vec<Option<u64>>,
+    success: Vec<bool>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for PrecompileCalls {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index", "trace_address"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for PrecompileCalls {
+    type Response = (Vec<Trace>, u64);
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let traces = source.trace_block(request.block_number()?).await?;
+        Ok((traces, source.chain_id))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_precompile_calls(response, columns, query)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for PrecompileCalls {
+    type Response = (Vec<Trace>, u64);
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let traces = source.trace_transaction(request.ethers_transaction_hash()?).await?;
+        Ok((traces, source.chain_id))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_precompile_calls(response, columns, query)
+    }
+}
+
+/// precompile addresses active at block, for chains without a known fork schedule all precompiles
+/// up to prague are used
+fn precompile_addresses(chain_id: u64, block_number: u64) -> Vec<Address> {
+    let last = match (chain_id, block_number) {
+        (1, n) if n < 4_370_000 => 0x04,
+        (1, n) if n < 9_069_000 => 0x08,
+        (1, n) if n < 19_426_587 => 0x09,
+        (1, n) if n < 22_431_084 => 0x0a,
+        _ => 0x11,
+    };
+    (0x01..=last).map(Address::from_low_u64_be).collect()
+}
+
+fn process_precompile_calls(
+    response: (Vec<Trace>, u64),
+    columns: &mut PrecompileCalls,
+    query: &Query,
+) -> R<()> {
+    let schema = query.schemas.get_schema(&Datatype::PrecompileCalls)?;
+    let (traces, chain_id) = response;
+    // traces of a response all belong to the same block
+    let precompiles: Vec<Address> = match (&query.precompiles, traces.first()) {
+        (Some(addresses), _) => addresses.iter().map(|a| Address::from_slice(a)).collect(),
+        (None, Some(trace)) => precompile_addresses(chain_id, trace.block_number),
+        (None, None) => Vec::new(),
+    };
+    for trace in traces.iter() {
+        let action = match &trace.action {
+            Action::Call(action) if precompiles.contains(&action.to) => action,
+            _ => continue,
+        };
+        let result = match &trace.result {
+            Some(Res::Call(result)) => Some(result),
+            _ => None,
+        };
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, trace.block_number);
+        store!(
+            schema,
+            columns,
+            transaction_hash,
+            trace.transaction_hash.map(|x| x.as_bytes().to_vec())
+        );
+        store!(schema, columns, transaction_index, trace.transaction_position.map(|x| x as u64));
+        store!(
+            schema,
+            columns,
+            trace_address,
+            trace.trace_address.iter().map(|n| n.to_string()).collect::<Vec<String>>().join("_")
+        );
+        store!(schema, columns, precompile_address, action.to.as_bytes().to_vec());
+        store!(schema, columns, input_size, action.input.len() as u32);
+        store!(schema, columns, output_size, result.map(|r| r.output.len() as u32));
+        store!(schema, columns, gas_used, result.map(|r| r.gas_used.as_u64()));
+        store!(schema, columns, success, trace.error.is_none());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precompile_addresses() {
+        assert_eq!(precompile_addresses(1, 1_000_000).len(), 4);
+        assert_eq!(precompile_addresses(1, 15_000_000).len(), 9);
+        assert_eq!(precompile_addresses(1, 20_000_000).last(), Some(&Address::from_low_u64_be(10)));
+        assert_eq!(precompile_addresses(10, 1).len(), 17);
+    }
+}
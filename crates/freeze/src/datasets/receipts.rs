@@ -0,0 +1,243 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for receipts
+#[cryo_to_df::to_df(Datatype::Receipts)]
+#[derive(Default)]
+pub struct Receipts {
+    n_rows: u64,
+    block_number: Vec<Option<u64>>,
+    transaction_index: Vec<u64>,
+    transaction_hash: Vec<Vec<u8>>,
+    from_address: Vec<Vec<u8>>,
+    to_address: Vec<Option<Vec<u8>>>,
+    cumulative_gas_used: Vec<u64>,
+    gas_used: Vec<Option<u64>>,
+    effective_gas_price: Vec<Option<u64>>,
+    logs_bloom: Vec<Vec<u8>>,
+    contract_address: Vec<Option<Vec<u8>>>,
+    status: Vec<Option<u64>>,
+    state_root: Vec<Option<Vec<u8>>>,
+    transaction_type: Vec<Option<u32>>,
+    blob_gas_used: Vec<Option<u64>>,
+    blob_gas_price: Vec<Option<u64>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Receipts {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "transaction_index",
+            "transaction_hash",
+            "from_address",
+            "to_address",
+            "cumulative_gas_used",
+            "gas_used",
+            "effective_gas_price",
+            "contract_address",
+            "status",
+            "transaction_type",
+            "blob_gas_used",
+            "blob_gas_price",
+            "chain_id",
+        ])
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index"])
+    }
+}
+
+/// receipts, with the block of their transactions if the node omits effectiveGasPrice
+type ReceiptsAndBlock = (Vec<TransactionReceipt>, Option<Block<Transaction>>);
+
+#[async_trait::async_trait]
+impl CollectByBlock for Receipts {
+    type Response = ReceiptsAndBlock;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        // block is only fetched when eth_getBlockReceipts is not supported by the node
+        let block_number = request.block_number()?;
+        let (receipts, block) = match source.get_block_receipts(block_number).await? {
+            Some(receipts) => (receipts, None),
+            None => {
+                let block = get_block_with_txs(&source, block_number).await?;
+                (source.get_tx_receipts(&block.transactions).await?, Some(block))
+            }
+        };
+        let block = match block {
+            None if needs_gas_prices(&receipts, &query)? => {
+                Some(get_block_with_txs(&source, block_number).await?)
+            }
+            block => block,
+        };
+        Ok((receipts, block))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Receipts)?;
+        process_receipts(response, columns, schema);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for Receipts {
+    type Response = ReceiptsAndBlock;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let receipt = source
+            .fetcher
+            .get_transaction_receipt(request.ethers_transaction_hash()?)
+            .await?
+            .ok_or(CollectError::CollectError("could not find tx receipt".to_string()))?;
+        let receipts = vec![receipt];
+        let block = match receipts[0].block_number {
+            Some(block_number) if needs_gas_prices(&receipts, &query)? => {
+                Some(get_block_with_txs(&source, block_number.as_u64()).await?)
+            }
+            _ => None,
+        };
+        Ok((receipts, block))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Receipts)?;
+        process_receipts(response, columns, schema);
+        Ok(())
+    }
+}
+
+async fn get_block_with_txs(source: &Source, block_number: u64) -> R<Block<Transaction>> {
+    source
+        .fetcher
+        .get_block_with_txs(block_number)
+        .await?
+        .ok_or(CollectError::CollectError("block not found".to_string()))
+}
+
+/// whether effective gas prices are collected but missing from receipts, as with nodes that
+/// predate the field
+fn needs_gas_prices(receipts: &[TransactionReceipt], query: &Query) -> R<bool> {
+    let schema = query.schemas.get_schema(&Datatype::Receipts)?;
+    Ok(schema.has_column("effective_gas_price") &&
+        receipts.iter().any(|receipt| receipt.effective_gas_price.is_none()))
+}
+
+/// effective gas price of receipt, computed from its transaction if the node omits it
+fn receipt_gas_price(
+    receipt: &TransactionReceipt,
+    block: Option<&Block<Transaction>>,
+) -> Option<U256> {
+    receipt.effective_gas_price.or_else(|| {
+        let block = block?;
+        let tx = block.transactions.iter().find(|tx| tx.hash == receipt.transaction_hash)?;
+        effective_gas_price(tx, block.base_fee_per_gas)
+    })
+}
+
+fn receipt_u64_field(receipt: &TransactionReceipt, key: &str) -> Option<u64> {
+    match receipt.other.get_deserialized::<U64>(key) {
+        Some(Ok(value)) => Some(value.as_u64()),
+        _ => None,
+    }
+}
+
+fn process_receipts(response: ReceiptsAndBlock, columns: &mut Receipts, schema: &Table) {
+    let (receipts, block) = response;
+    for receipt in receipts.into_iter() {
+        let effective_gas_price = receipt_gas_price(&receipt, block.as_ref());
+        process_receipt(receipt, effective_gas_price, columns, schema)
+    }
+}
+
+fn process_receipt(
+    receipt: TransactionReceipt,
+    effective_gas_price: Option<U256>,
+    columns: &mut Receipts,
+    schema: &Table,
+) {
+    columns.n_rows += 1;
+    store!(schema, columns, block_number, receipt.block_number.map(|x| x.as_u64()));
+    store!(schema, columns, transaction_index, receipt.transaction_index.as_u64());
+    store!(schema, columns, transaction_hash, receipt.transaction_hash.as_bytes().to_vec());
+    store!(schema, columns, from_address, receipt.from.as_bytes().to_vec());
+    store!(schema, columns, to_address, receipt.to.map(|x| x.as_bytes().to_vec()));
+    store!(schema, columns, cumulative_gas_used, receipt.cumulative_gas_used.as_u64());
+    store!(schema, columns, gas_used, receipt.gas_used.map(|x| x.as_u64()));
+    store!(schema, columns, effective_gas_price, effective_gas_price.map(|x| x.as_u64()));
+    store!(schema, columns, logs_bloom, receipt.logs_bloom.as_bytes().to_vec());
+    store!(
+        schema,
+        columns,
+        contract_address,
+        receipt.contract_address.map(|x| x.as_bytes().to_vec())
+    );
+    // pre-byzantium receipts have the state root after the transaction instead of a status
+    store!(schema, columns, status, receipt.status.map(|x| x.as_u64()));
+    store!(schema, columns, state_root, receipt.root.map(|x| x.as_bytes().to_vec()));
+    store!(schema, columns, transaction_type, receipt.transaction_type.map(|x| x.as_u32()));
+    store!(schema, columns, blob_gas_used, receipt_u64_field(&receipt, "blobGasUsed"));
+    store!(schema, columns, blob_gas_price, receipt_u64_field(&receipt, "blobGasPrice"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::fixtures::synthetic_block;
+
+    fn process_fixture(block_number: u64, with_gas_prices: bool) -> Receipts {
+        let columns = ["block_number", "effective_gas_price", "status", "state_root"];
+        let schema = Datatype::Receipts
+            .table_schema(
+                &[],
+                &ColumnEncoding::Binary,
+                &None,
+                &None,
+                &Some(columns.iter().map(|x| x.to_string()).collect()),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let fixture = synthetic_block(block_number);
+        let mut receipts = fixture.receipts;
+        if !with_gas_prices {
+            receipts.iter_mut().for_each(|receipt| receipt.effective_gas_price = None);
+        }
+        let mut columns = Receipts::default();
+        process_receipts((receipts, Some(fixture.block)), &mut columns, &schema);
+        columns
+    }
+
+    #[test]
+    fn test_pre_byzantium_receipts() {
+        for block_number in [46147, 4369999] {
+            let columns = process_fixture(block_number, true);
+            assert!(columns.status.iter().all(Option::is_none));
+            assert!(columns.state_root.iter().all(|root| root.as_ref().map(Vec::len) == Some(32)));
+        }
+        let columns = process_fixture(12964999, true);
+        assert_eq!(columns.status, vec![Some(1), Some(0)]);
+        assert_eq!(columns.state_root, vec![None, None]);
+    }
+
+    #[test]
+    fn test_computed_effective_gas_price() {
+        let gwei = 1_000_000_000;
+        assert_eq!(process_fixture(46147, false).effective_gas_price, vec![Some(50_000 * gwei)]);
+        for block_number in [12964999, 12965000] {
+            let columns = process_fixture(block_number, false);
+            assert_eq!(
+                columns.effective_gas_price,
+                process_fixture(block_number, true).effective_gas_price
+            );
+        }
+        // dynamic fee transaction pays base fee of 1 gwei plus priority fee of 1 gwei
+        let columns = process_fixture(12965000, false);
+        assert_eq!(columns.effective_gas_price, vec![Some(2 * gwei), Some(5 * gwei)]);
+    }
+}
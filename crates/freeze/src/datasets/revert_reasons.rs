@@ -0,0 +1,102 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for revert reasons
+#[cryo_to_df::to_df(Datatype::RevertReasons)]
+#[derive(Default)]
+pub struct RevertReasons {
+    n_rows: u64,
+    block_number: Vec<Option<u64>>,
+    transaction_index: Vec<Option<u64>>,
+    transaction_hash: Vec<Option<Vec<u8>>>,
+    revert_selector: Vec<Option<Vec<u8>>>,
+    revert_data: Vec<Vec<u8>>,
+    revert_message: Vec<Option<String>>,
+    panic_code: Vec<Option<u64>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for RevertReasons {}
+
+/// block number, transaction indices, transaction hashes, and replayed call traces
+type BlockTxsReplays = (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>);
+
+#[async_trait::async_trait]
+impl CollectByBlock for RevertReasons {
+    type Response = BlockTxsReplays;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let schema = query.schemas.get_schema(&Datatype::RevertReasons)?;
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source.fetcher.trace_block_call_replays(block_number, include_txs).await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::RevertReasons)?;
+        process_revert_reasons(response, columns, schema)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for RevertReasons {
+    type Response = BlockTxsReplays;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        let transaction = source
+            .fetcher
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or(CollectError::CollectError("transaction not found".to_string()))?;
+        let replay =
+            source.fetcher.trace_replay_transaction(tx_hash, vec![TraceType::Trace]).await?;
+        Ok((
+            transaction.block_number.map(|x| x.as_u64()),
+            vec![transaction.transaction_index.map(|x| x.as_u64())],
+            vec![Some(tx_hash.as_bytes().to_vec())],
+            vec![replay],
+        ))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::RevertReasons)?;
+        process_revert_reasons(response, columns, schema)
+    }
+}
+
+/// whether top level call of replayed transaction failed
+fn is_reverted(replay: &BlockTrace) -> bool {
+    match &replay.trace {
+        Some(traces) => {
+            traces.iter().any(|trace| trace.trace_address.is_empty() && trace.error.is_some())
+        }
+        None => false,
+    }
+}
+
+fn process_revert_reasons(
+    response: BlockTxsReplays,
+    columns: &mut RevertReasons,
+    schema: &Table,
+) -> R<()> {
+    let (block_number, indices, txs, replays) = response;
+    for ((index, tx), replay) in indices.into_iter().zip(txs).zip(replays) {
+        if !is_reverted(&replay) {
+            continue
+        }
+        let reason = RevertReason::decode(&replay.output);
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, block_number);
+        store!(schema, columns, transaction_index, index);
+        store!(schema, columns, transaction_hash, tx);
+        store!(schema, columns, revert_selector, reason.selector);
+        store!(schema, columns, revert_data, replay.output.to_vec());
+        store!(schema, columns, revert_message, reason.message);
+        store!(schema, columns, panic_code, reason.panic_code);
+    }
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for selfdestructs
+#[cryo_to_df::to_df(Datatype::Selfdestructs)]
+#[derive(Default)]
+pub struct Selfdestructs {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    transaction_hash: Vec<Option<Vec<u8>>>,
+    transaction_index: Vec<Option<u64>>,
+    trace_address: Vec<String>,
+    contract_address: Vec<Vec<u8>>,
+    refund_address: Vec<Vec<u8>>,
+    balance_transferred: Vec<U256>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Selfdestructs {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index", "trace_address"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for Selfdestructs {
+    type Response = Vec<Trace>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        match source.fetcher.trace_block(block_number.into()).await {
+            Ok(traces) => Ok(traces),
+            // nodes without the parity trace api can still provide selfdestructs via callTracer
+            Err(CollectError::MethodNotFound(_)) => {
+                let (_, indices, txs, calls) =
+                    source.fetcher.geth_debug_trace_block_calls(block_number, true).await?;
+                Ok(call_frames_to_selfdestruct_traces(block_number, indices, txs, calls))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let traces =
+            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
+        process_selfdestructs(&traces, columns, &query.schemas)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for Selfdestructs {
+    type Response = Vec<Trace>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        source.trace_transaction(request.ethers_transaction_hash()?).await
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let traces =
+            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
+        process_selfdestructs(&traces, columns, &query.schemas)
+    }
+}
+
+/// convert SELFDESTRUCT frames of geth call traces into parity style suicide traces
+fn call_frames_to_selfdestruct_traces(
+    block_number: u64,
+    indices: Vec<Option<u64>>,
+    txs: Vec<Option<Vec<u8>>>,
+    calls: Vec<CallFrame>,
+) -> Vec<Trace> {
+    fn visit(
+        frame: &CallFrame,
+        trace_address: Vec<usize>,
+        template: &Trace,
+        traces: &mut Vec<Trace>,
+    ) {
+        if frame.typ == "SELFDESTRUCT" {
+            let refund_address = match &frame.to {
+                Some(NameOrAddress::Address(address)) => *address,
+                _ => Address::zero(),
+            };
+            traces.push(Trace {
+                action: Action::Suicide(Suicide {
+                    address: frame.from,
+                    refund_address,
+                    balance: frame.value.unwrap_or_default(),
+                }),
+                trace_address: trace_address.clone(),
+                error: frame.error.clone(),
+                ..template.clone()
+            });
+        }
+        for (index, child) in frame.calls.iter().flatten().enumerate() {
+            let mut child_address = trace_address.clone();
+            child_address.push(index);
+            visit(child, child_address, template, traces);
+        }
+    }
+
+    let mut traces = Vec::new();
+    for (position, ((tx, call), index)) in txs.into_iter().zip(calls).zip(indices).enumerate() {
+        let template = Trace {
+            action: Action::Suicide(Suicide {
+                address: Address::zero(),
+                refund_address: Address::zero(),
+                balance: U256::zero(),
+            }),
+            result: None,
+            trace_address: Vec::new(),
+            subtraces: 0,
+            transaction_position: Some(index.map_or(position, |index| index as usize)),
+            transaction_hash: tx.map(|tx| H256::from_slice(&tx)),
+            block_number,
+            block_hash: H256::zero(),
+            action_type: ActionType::Suicide,
+            error: None,
+        };
+        visit(&call, Vec::new(), &template, &mut traces);
+    }
+    traces
+}
+
+/// process suicide traces into columns, other trace types are ignored
+pub(crate) fn process_selfdestructs(
+    traces: &[Trace],
+    columns: &mut Selfdestructs,
+    schemas: &Schemas,
+) -> R<()> {
+    let schema = schemas.get_schema(&Datatype::Selfdestructs)?;
+    for trace in traces.iter() {
+        if let Action::Suicide(action) = &trace.action {
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, trace.block_number);
+            store!(
+                schema,
+                columns,
+                transaction_hash,
+                trace.transaction_hash.map(|x| x.as_bytes().to_vec())
+            );
+            store!(
+                schema,
+                columns,
+                transaction_index,
+                trace.transaction_position.map(|x| x as u64)
+            );
+            store!(
+                schema,
+                columns,
+                trace_address,
+                trace
+                    .trace_address
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<String>>()
+                    .join("_")
+            );
+            store!(schema, columns, contract_address, action.address.as_bytes().to_vec());
+            store!(schema, columns, refund_address, action.refund_address.as_bytes().to_vec());
+            store!(schema, columns, balance_transferred, action.balance);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call_frame(typ: &str, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            typ: typ.to_string(),
+            from: Address::repeat_byte(1),
+            to: Some(NameOrAddress::Address(Address::repeat_byte(2))),
+            value: Some(7.into()),
+            calls: Some(calls),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_call_frames_to_selfdestruct_traces() {
+        let nested = call_frame(
+            "CALL",
+            vec![call_frame("CALL", vec![]), call_frame("SELFDESTRUCT", vec![])],
+        );
+        let calls = vec![call_frame("CALL", vec![]), call_frame("CALL", vec![nested])];
+        let tx = Some(H256::repeat_byte(3).as_bytes().to_vec());
+        let traces =
+            call_frames_to_selfdestruct_traces(10, vec![Some(6), Some(7)], vec![None, tx], calls);
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].trace_address, vec![0, 1]);
+        assert_eq!(traces[0].transaction_position, Some(7));
+        assert_eq!(traces[0].transaction_hash, Some(H256::repeat_byte(3)));
+        let expected = Suicide {
+            address: Address::repeat_byte(1),
+            refund_address: Address::repeat_byte(2),
+            balance: 7.into(),
+        };
+        assert_eq!(traces[0].action, Action::Suicide(expected));
+    }
+}
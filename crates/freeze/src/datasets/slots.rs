@@ -7,7 +7,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Slots {
     n_rows: usize,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     address: Vec<Vec<u8>>,
     slot: Vec<Vec<u8>>,
     value: Vec<Vec<u8>>,
@@ -35,9 +35,13 @@ impl Dataset for Slots {
     fn default_blocks() -> Option<String> {
         Some("latest".to_string())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("address")
+    }
 }
 
-type BlockTxAddressOutput = (u32, Option<Vec<u8>>, Vec<u8>, Vec<u8>, Vec<u8>);
+type BlockTxAddressOutput = (u64, Option<Vec<u8>>, Vec<u8>, Vec<u8>, Vec<u8>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for Slots {
@@ -45,7 +49,7 @@ impl CollectByBlock for Slots {
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
         let address = request.address()?;
-        let block_number = request.block_number()? as u32;
+        let block_number = request.block_number()?;
         let slot = request.slot()?;
         let output = source
             .fetcher
@@ -7,8 +7,8 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct StorageDiffs {
     pub(crate) n_rows: u64,
-    pub(crate) block_number: Vec<Option<u32>>,
-    pub(crate) transaction_index: Vec<Option<u32>>,
+    pub(crate) block_number: Vec<Option<u64>>,
+    pub(crate) transaction_index: Vec<Option<u64>>,
     pub(crate) transaction_hash: Vec<Option<Vec<u8>>>,
     pub(crate) address: Vec<Vec<u8>>,
     pub(crate) slot: Vec<Vec<u8>>,
@@ -24,7 +24,8 @@ impl Dataset for StorageDiffs {
     }
 }
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for StorageDiffs {
@@ -32,12 +33,14 @@ impl CollectByBlock for StorageDiffs {
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::StorageDiffs)?;
-        let include_txs = schema.has_column("transaction_hash");
-        source.fetcher.trace_block_state_diffs(request.block_number()? as u32, include_txs).await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source.trace_block_state_diffs(request.block_number()?, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_diffs(&response, columns, &query.schemas)
+        query.note_trace_positions(&Datatype::StorageDiffs, &response.1);
+        process_storage_diffs(&response, columns, &query.schemas, &query.slot_filter)
     }
 }
 
@@ -45,12 +48,16 @@ impl CollectByBlock for StorageDiffs {
 impl CollectByTransaction for StorageDiffs {
     type Response = BlockTxsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::StorageDiffs)?;
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
+        source.trace_transaction_state_diffs(request.transaction_hash()?, include_transaction).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_diffs(&response, columns, &query.schemas)
+        query.note_trace_positions(&Datatype::StorageDiffs, &response.1);
+        process_storage_diffs(&response, columns, &query.schemas, &query.slot_filter)
     }
 }
 
@@ -58,29 +65,45 @@ pub(crate) fn process_storage_diffs(
     response: &BlockTxsTraces,
     columns: &mut StorageDiffs,
     schemas: &Schemas,
+    slot_filter: &Option<SlotFilter>,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::StorageDiffs).ok_or(err("schema not provided"))?;
-    let (block_number, txs, traces) = response;
-    for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
+    let (block_number, indices, txs, traces) = response;
+    for (position, ((trace, tx), index)) in traces.iter().zip(txs).zip(indices).enumerate() {
+        let index = index.unwrap_or(position as u64);
         if let Some(ethers::types::StateDiff(state_diffs)) = &trace.state_diff {
             for (addr, diff) in state_diffs.iter() {
-                process_storage_diff(addr, &diff.storage, block_number, tx, index, columns, schema);
+                process_storage_diff(
+                    addr,
+                    &diff.storage,
+                    block_number,
+                    tx,
+                    index,
+                    columns,
+                    schema,
+                    slot_filter,
+                );
             }
         }
     }
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn process_storage_diff(
     addr: &H160,
     diff: &std::collections::BTreeMap<H256, Diff<H256>>,
-    block_number: &Option<u32>,
+    block_number: &Option<u64>,
     transaction_hash: &Option<Vec<u8>>,
-    transaction_index: usize,
+    transaction_index: u64,
     columns: &mut StorageDiffs,
     schema: &Table,
+    slot_filter: &Option<SlotFilter>,
 ) {
     for (s, sub_diff) in diff.iter() {
+        if slot_filter.as_ref().is_some_and(|filter| !filter.matches(s.as_bytes())) {
+            continue
+        }
         let (from, to) = match sub_diff {
             Diff::Same => continue,
             Diff::Born(value) => (H256::zero(), *value),
@@ -89,7 +112,7 @@ pub(crate) fn process_storage_diff(
         };
         columns.n_rows += 1;
         store!(schema, columns, block_number, *block_number);
-        store!(schema, columns, transaction_index, Some(transaction_index as u32));
+        store!(schema, columns, transaction_index, Some(transaction_index));
         store!(schema, columns, transaction_hash, transaction_hash.clone());
         store!(schema, columns, slot, s.as_bytes().to_vec());
         store!(schema, columns, address, addr.as_bytes().to_vec());
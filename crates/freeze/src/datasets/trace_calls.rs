@@ -8,11 +8,11 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct TraceCalls {
     n_rows: u64,
-    block_number: Vec<u32>,
-    transaction_index: Vec<u32>,
+    block_number: Vec<u64>,
+    transaction_index: Vec<u64>,
     action_from: Vec<Option<Vec<u8>>>,
     action_to: Vec<Option<Vec<u8>>>,
-    action_value: Vec<String>,
+    action_value: Vec<U256>,
     action_gas: Vec<Option<u32>>,
     action_input: Vec<Option<Vec<u8>>>,
     action_call_type: Vec<Option<String>>,
@@ -43,13 +43,31 @@ impl Dataset for TraceCalls {
     fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
         Some([(Dim::Address, Dim::Contract), (Dim::ToAddress, Dim::Contract)].into_iter().collect())
     }
+
+    fn label_column() -> Option<&'static str> {
+        Some("tx_to_address")
+    }
 }
 
 #[async_trait::async_trait]
 impl CollectByBlock for TraceCalls {
-    type Response = (u32, Vec<u8>, Vec<u8>, Vec<TransactionTrace>);
+    type Response = (u64, Vec<u8>, Vec<u8>, Vec<TransactionTrace>);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        // parity trace_call takes no state overrides, so overridden calls are traced by geth
+        if let Some(state_override) = &query.state_override {
+            let frame = source
+                .fetcher
+                .geth_debug_trace_call_with_state_override(
+                    request.ethers_contract()?,
+                    request.call_data()?,
+                    request.ethers_block_number()?,
+                    state_override,
+                )
+                .await?;
+            let traces = call_frame_to_transaction_traces(frame);
+            return Ok((request.block_number()?, request.contract()?, request.call_data()?, traces))
+        }
         let traces: Vec<TransactionTrace> = source
             .fetcher
             .trace_call2(
@@ -61,7 +79,7 @@ impl CollectByBlock for TraceCalls {
             .await?
             .trace
             .ok_or(CollectError::CollectError("traces missing".to_string()))?;
-        Ok((request.block_number()? as u32, request.contract()?, request.call_data()?, traces))
+        Ok((request.block_number()?, request.contract()?, request.call_data()?, traces))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -75,8 +93,77 @@ impl CollectByTransaction for TraceCalls {
     type Response = ();
 }
 
+/// convert geth call tracer frames into parity style transaction traces
+fn call_frame_to_transaction_traces(frame: CallFrame) -> Vec<TransactionTrace> {
+    fn visit(frame: CallFrame, trace_address: Vec<usize>, traces: &mut Vec<TransactionTrace>) {
+        let to = match &frame.to {
+            Some(NameOrAddress::Address(address)) => *address,
+            _ => Address::zero(),
+        };
+        let value = frame.value.unwrap_or_default();
+        let output = frame.output.clone().unwrap_or_default();
+        let (action, action_type, result) = match frame.typ.as_str() {
+            "CREATE" | "CREATE2" => (
+                Action::Create(Create {
+                    from: frame.from,
+                    value,
+                    gas: frame.gas,
+                    init: frame.input,
+                }),
+                ActionType::Create,
+                Res::Create(CreateResult { gas_used: frame.gas_used, code: output, address: to }),
+            ),
+            "SELFDESTRUCT" => (
+                Action::Suicide(Suicide {
+                    address: frame.from,
+                    refund_address: to,
+                    balance: value,
+                }),
+                ActionType::Suicide,
+                Res::None,
+            ),
+            typ => {
+                let call_type = match typ {
+                    "DELEGATECALL" => CallType::DelegateCall,
+                    "STATICCALL" => CallType::StaticCall,
+                    "CALLCODE" => CallType::CallCode,
+                    _ => CallType::Call,
+                };
+                let call = ethers::types::Call {
+                    from: frame.from,
+                    to,
+                    value,
+                    gas: frame.gas,
+                    input: frame.input,
+                    call_type,
+                };
+                let result = Res::Call(CallResult { gas_used: frame.gas_used, output });
+                (Action::Call(call), ActionType::Call, result)
+            }
+        };
+        let calls = frame.calls.unwrap_or_default();
+        traces.push(TransactionTrace {
+            trace_address: trace_address.clone(),
+            subtraces: calls.len(),
+            action,
+            action_type,
+            result: if frame.error.is_some() { None } else { Some(result) },
+            error: frame.error,
+        });
+        for (index, call) in calls.into_iter().enumerate() {
+            let mut child_address = trace_address.clone();
+            child_address.push(index);
+            visit(call, child_address, traces);
+        }
+    }
+
+    let mut traces = Vec::new();
+    visit(frame, Vec::new(), &mut traces);
+    traces
+}
+
 fn process_transaction_traces(
-    response: (u32, Vec<u8>, Vec<u8>, Vec<TransactionTrace>),
+    response: (u64, Vec<u8>, Vec<u8>, Vec<TransactionTrace>),
     columns: &mut TraceCalls,
     schema: &Table,
 ) {
@@ -94,7 +181,7 @@ fn process_transaction_traces(
             trace.trace_address.iter().map(|n| n.to_string()).collect::<Vec<String>>().join("_")
         );
         store!(schema, columns, subtraces, trace.subtraces as u32);
-        store!(schema, columns, transaction_index, transaction_index as u32);
+        store!(schema, columns, transaction_index, transaction_index as u64);
         store!(schema, columns, block_number, block_number);
         store!(schema, columns, error, trace.error.clone());
         store!(schema, columns, tx_to_address, contract.clone());
@@ -107,7 +194,7 @@ fn process_action(action: &Action, columns: &mut TraceCalls, schema: &Table) {
         Action::Call(action) => {
             store!(schema, columns, action_from, Some(action.from.as_bytes().to_vec()));
             store!(schema, columns, action_to, Some(action.to.as_bytes().to_vec()));
-            store!(schema, columns, action_value, action.value.to_string());
+            store!(schema, columns, action_value, action.value);
             store!(schema, columns, action_gas, Some(action.gas.as_u32()));
             store!(schema, columns, action_input, Some(action.input.to_vec()));
             store!(
@@ -122,7 +209,7 @@ fn process_action(action: &Action, columns: &mut TraceCalls, schema: &Table) {
         Action::Create(action) => {
             store!(schema, columns, action_from, Some(action.from.as_bytes().to_vec()));
             store!(schema, columns, action_to, None);
-            store!(schema, columns, action_value, action.value.to_string());
+            store!(schema, columns, action_value, action.value);
             store!(schema, columns, action_gas, Some(action.gas.as_u32()));
             store!(schema, columns, action_input, None);
             store!(schema, columns, action_call_type, None);
@@ -132,7 +219,7 @@ fn process_action(action: &Action, columns: &mut TraceCalls, schema: &Table) {
         Action::Suicide(action) => {
             store!(schema, columns, action_from, Some(action.address.as_bytes().to_vec()));
             store!(schema, columns, action_to, Some(action.refund_address.as_bytes().to_vec()));
-            store!(schema, columns, action_value, action.balance.to_string());
+            store!(schema, columns, action_value, action.balance);
             store!(schema, columns, action_gas, None);
             store!(schema, columns, action_input, None);
             store!(schema, columns, action_call_type, None);
@@ -142,7 +229,7 @@ fn process_action(action: &Action, columns: &mut TraceCalls, schema: &Table) {
         Action::Reward(action) => {
             store!(schema, columns, action_from, Some(action.author.as_bytes().to_vec()));
             store!(schema, columns, action_to, None);
-            store!(schema, columns, action_value, action.value.to_string());
+            store!(schema, columns, action_value, action.value);
             store!(schema, columns, action_gas, None);
             store!(schema, columns, action_input, None);
             store!(schema, columns, action_call_type, None);
@@ -179,3 +266,43 @@ fn process_result(result: &Option<Res>, columns: &mut TraceCalls, schema: &Table
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_frame_to_transaction_traces() {
+        let create = CallFrame {
+            typ: "CREATE2".to_string(),
+            to: Some(NameOrAddress::Address(Address::repeat_byte(3))),
+            ..Default::default()
+        };
+        let failed = CallFrame {
+            typ: "STATICCALL".to_string(),
+            error: Some("execution reverted".to_string()),
+            ..Default::default()
+        };
+        let frame = CallFrame {
+            typ: "CALL".to_string(),
+            to: Some(NameOrAddress::Address(Address::repeat_byte(2))),
+            calls: Some(vec![create, failed]),
+            ..Default::default()
+        };
+        let traces = call_frame_to_transaction_traces(frame);
+        assert_eq!(traces.len(), 3);
+        assert_eq!(traces[0].subtraces, 2);
+        assert_eq!(traces[1].trace_address, vec![0]);
+        assert_eq!(traces[1].action_type, ActionType::Create);
+        match &traces[1].result {
+            Some(Res::Create(result)) => assert_eq!(result.address, Address::repeat_byte(3)),
+            _ => panic!("expected create result"),
+        }
+        assert_eq!(traces[2].trace_address, vec![1]);
+        assert_eq!(traces[2].result, None);
+        match &traces[2].action {
+            Action::Call(call) => assert_eq!(call.call_type, CallType::StaticCall),
+            _ => panic!("expected call action"),
+        }
+    }
+}
@@ -9,7 +9,7 @@ pub struct Traces {
     n_rows: u64,
     action_from: Vec<Option<Vec<u8>>>,
     action_to: Vec<Option<Vec<u8>>>,
-    action_value: Vec<String>,
+    action_value: Vec<U256>,
     action_gas: Vec<Option<u32>>,
     action_input: Vec<Option<Vec<u8>>>,
     action_call_type: Vec<Option<String>>,
@@ -22,11 +22,12 @@ pub struct Traces {
     result_address: Vec<Option<Vec<u8>>>,
     trace_address: Vec<String>,
     subtraces: Vec<u32>,
-    transaction_index: Vec<Option<u32>>,
+    transaction_index: Vec<Option<u64>>,
     transaction_hash: Vec<Option<Vec<u8>>>,
-    block_number: Vec<u32>,
+    block_number: Vec<u64>,
     block_hash: Vec<Vec<u8>>,
     error: Vec<Option<String>>,
+    revert_message: Vec<Option<String>>,
     chain_id: Vec<u64>,
 }
 
@@ -41,15 +42,28 @@ impl Dataset for Traces {
 impl CollectByBlock for Traces {
     type Response = Vec<Trace>;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let traces = source.fetcher.trace_block(request.block_number()?.into()).await?;
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let traces = match &query.trace_contract_filter {
+            // nodes without trace_filter have their traces filtered in transform instead
+            Some(filter) if source.resolved_trace_backend() != Some(TraceBackend::Geth) => {
+                match trace_filter_block(block_number, filter, &source).await {
+                    Ok(traces) => traces,
+                    Err(CollectError::MethodNotFound(_)) => {
+                        source.trace_block(block_number).await?
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => source.trace_block(block_number).await?,
+        };
         Ok(filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let traces =
             if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_traces(&traces, columns, &query.schemas)
+        process_traces(&traces, columns, &query.schemas, &query.trace_contract_filter)
     }
 }
 
@@ -58,17 +72,79 @@ impl CollectByTransaction for Traces {
     type Response = Vec<Trace>;
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let traces = source.fetcher.trace_transaction(request.ethers_transaction_hash()?).await?;
+        let traces = source.trace_transaction(request.ethers_transaction_hash()?).await?;
         Ok(filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let traces =
             if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_traces(&traces, columns, &query.schemas)
+        process_traces(&traces, columns, &query.schemas, &query.trace_contract_filter)
+    }
+}
+
+/// fetch traces of block from or to filtered contracts using trace_filter
+async fn trace_filter_block(
+    block_number: u64,
+    filter: &TraceContractFilter,
+    source: &Source,
+) -> R<Vec<Trace>> {
+    let contracts: Vec<H160> = filter.contracts.iter().map(|x| H160::from_slice(x)).collect();
+    let block_filter = TraceFilter::default().from_block(block_number).to_block(block_number);
+    let mut traces = Vec::new();
+    if filter.side != TraceFilterSide::To {
+        let from_filter = block_filter.clone().from_address(contracts.clone());
+        traces.extend(source.fetcher.trace_filter(from_filter).await?);
+    }
+    if filter.side != TraceFilterSide::From {
+        traces.extend(source.fetcher.trace_filter(block_filter.to_address(contracts)).await?);
+    }
+    // entries matching on both sides are returned by both requests
+    traces.sort_by(|a, b| {
+        (a.transaction_position.is_none(), a.transaction_position, &a.trace_address).cmp(&(
+            b.transaction_position.is_none(),
+            b.transaction_position,
+            &b.trace_address,
+        ))
+    });
+    traces.dedup();
+    Ok(traces)
+}
+
+/// sender and recipient of trace entry, the recipient of a create is the created contract
+pub(crate) fn trace_from_to(action: &Action, result: &Option<Res>) -> (Option<H160>, Option<H160>) {
+    match action {
+        Action::Call(action) => (Some(action.from), Some(action.to)),
+        Action::Create(action) => match result {
+            Some(Res::Create(result)) => (Some(action.from), Some(result.address)),
+            _ => (Some(action.from), None),
+        },
+        Action::Suicide(action) => (Some(action.address), Some(action.refund_address)),
+        Action::Reward(action) => (None, Some(action.author)),
+    }
+}
+
+/// whether trace entry passes contract filter
+pub(crate) fn trace_matches_contracts(
+    trace: &Trace,
+    contract_filter: &Option<TraceContractFilter>,
+) -> bool {
+    match contract_filter {
+        Some(filter) => action_matches_contracts(&trace.action, &trace.result, filter),
+        None => true,
     }
 }
 
+/// whether trace action passes contract filter
+pub(crate) fn action_matches_contracts(
+    action: &Action,
+    result: &Option<Res>,
+    filter: &TraceContractFilter,
+) -> bool {
+    let (from, to) = trace_from_to(action, result);
+    filter.matches(from.as_ref().map(|x| x.as_bytes()), to.as_ref().map(|x| x.as_bytes()))
+}
+
 pub(crate) fn filter_traces_by_from_to_addresses(
     traces: Vec<Trace>,
     from_address: &Option<Vec<u8>>,
@@ -106,9 +182,14 @@ pub(crate) fn filter_traces_by_from_to_addresses(
 }
 
 /// process block into columns
-pub(crate) fn process_traces(traces: &[Trace], columns: &mut Traces, schemas: &Schemas) -> R<()> {
+pub(crate) fn process_traces(
+    traces: &[Trace],
+    columns: &mut Traces,
+    schemas: &Schemas,
+    contract_filter: &Option<TraceContractFilter>,
+) -> R<()> {
     let schema = schemas.get(&Datatype::Traces).ok_or(err("schema not provided"))?;
-    for trace in traces.iter() {
+    for trace in traces.iter().filter(|trace| trace_matches_contracts(trace, contract_filter)) {
         columns.n_rows += 1;
         process_action(&trace.action, columns, schema);
         process_result(&trace.result, columns, schema);
@@ -120,26 +201,35 @@ pub(crate) fn process_traces(traces: &[Trace], columns: &mut Traces, schemas: &S
             trace.trace_address.iter().map(|n| n.to_string()).collect::<Vec<String>>().join("_")
         );
         store!(schema, columns, subtraces, trace.subtraces as u32);
-        store!(schema, columns, transaction_index, trace.transaction_position.map(|x| x as u32));
+        store!(schema, columns, transaction_index, trace.transaction_position.map(|x| x as u64));
         store!(
             schema,
             columns,
             transaction_hash,
             trace.transaction_hash.map(|x| x.as_bytes().to_vec())
         );
-        store!(schema, columns, block_number, trace.block_number as u32);
+        store!(schema, columns, block_number, trace.block_number);
         store!(schema, columns, block_hash, trace.block_hash.as_bytes().to_vec());
         store!(schema, columns, error, trace.error.clone());
+        store!(schema, columns, revert_message, trace_revert_message(trace));
     }
     Ok(())
 }
 
+/// message of `Error(string)` reverts, for nodes that keep the output of failed calls
+fn trace_revert_message(trace: &Trace) -> Option<String> {
+    match (&trace.error, &trace.result) {
+        (Some(_), Some(Res::Call(result))) => RevertReason::decode(&result.output).message,
+        _ => None,
+    }
+}
+
 fn process_action(action: &Action, columns: &mut Traces, schema: &Table) {
     match action {
         Action::Call(action) => {
             store!(schema, columns, action_from, Some(action.from.as_bytes().to_vec()));
             store!(schema, columns, action_to, Some(action.to.as_bytes().to_vec()));
-            store!(schema, columns, action_value, action.value.to_string());
+            store!(schema, columns, action_value, action.value);
             store!(schema, columns, action_gas, Some(action.gas.as_u32()));
             store!(schema, columns, action_input, Some(action.input.to_vec()));
             store!(
@@ -154,7 +244,7 @@ fn process_action(action: &Action, columns: &mut Traces, schema: &Table) {
         Action::Create(action) => {
             store!(schema, columns, action_from, Some(action.from.as_bytes().to_vec()));
             store!(schema, columns, action_to, None);
-            store!(schema, columns, action_value, action.value.to_string());
+            store!(schema, columns, action_value, action.value);
             store!(schema, columns, action_gas, Some(action.gas.as_u32()));
             store!(schema, columns, action_input, None);
             store!(schema, columns, action_call_type, None);
@@ -164,7 +254,7 @@ fn process_action(action: &Action, columns: &mut Traces, schema: &Table) {
         Action::Suicide(action) => {
             store!(schema, columns, action_from, Some(action.address.as_bytes().to_vec()));
             store!(schema, columns, action_to, Some(action.refund_address.as_bytes().to_vec()));
-            store!(schema, columns, action_value, action.balance.to_string());
+            store!(schema, columns, action_value, action.balance);
             store!(schema, columns, action_gas, None);
             store!(schema, columns, action_input, None);
             store!(schema, columns, action_call_type, None);
@@ -174,7 +264,7 @@ fn process_action(action: &Action, columns: &mut Traces, schema: &Table) {
         Action::Reward(action) => {
             store!(schema, columns, action_from, Some(action.author.as_bytes().to_vec()));
             store!(schema, columns, action_to, None);
-            store!(schema, columns, action_value, action.value.to_string());
+            store!(schema, columns, action_value, action.value);
             store!(schema, columns, action_gas, None);
             store!(schema, columns, action_input, None);
             store!(schema, columns, action_call_type, None);
@@ -271,3 +361,39 @@ pub(crate) fn filter_failed_traces(traces: Vec<Trace>) -> Vec<Trace> {
 
     filtered
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::abi::{self, Token};
+
+    fn call_trace(error: Option<&str>, output: Vec<u8>) -> Trace {
+        Trace {
+            action: Action::Call(ethers::types::Call::default()),
+            result: Some(Res::Call(CallResult { gas_used: 0.into(), output: output.into() })),
+            trace_address: vec![],
+            subtraces: 0,
+            transaction_position: None,
+            transaction_hash: None,
+            block_number: 0,
+            block_hash: H256::zero(),
+            action_type: ActionType::Call,
+            error: error.map(|x| x.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_trace_revert_message() {
+        let mut output = ERROR_STRING_SELECTOR.to_vec();
+        output.extend(abi::encode(&[Token::String("expired".to_string())]));
+        assert_eq!(
+            trace_revert_message(&call_trace(Some("Reverted"), output.clone())),
+            Some("expired".to_string())
+        );
+        // successful calls can also return bytes that look like an error
+        assert_eq!(trace_revert_message(&call_trace(None, output.clone())), None);
+        // truncated payloads are left undecoded
+        output.truncate(40);
+        assert_eq!(trace_revert_message(&call_trace(Some("Reverted"), output)), None);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for transaction fees
+#[cryo_to_df::to_df(Datatype::TransactionFees)]
+#[derive(Default)]
+pub struct TransactionFees {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    transaction_index: Vec<u64>,
+    transaction_hash: Vec<Vec<u8>>,
+    gas_used: Vec<u64>,
+    base_fee_per_gas: Vec<Option<u64>>,
+    max_fee_per_gas: Vec<Option<u64>>,
+    max_priority_fee_per_gas: Vec<Option<u64>>,
+    effective_gas_price: Vec<u64>,
+    priority_fee_paid: Vec<U256>,
+    base_fee_burned: Vec<Option<U256>>,
+    total_fee_paid: Vec<U256>,
+    blob_fee_paid: Vec<Option<U256>>,
+    l1_data_fee: Vec<Option<U256>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for TransactionFees {
+    fn aliases() -> Vec<&'static str> {
+        vec!["fees"]
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index"])
+    }
+}
+
+/// base fee of block and its transactions with receipts
+type BlockFees = (Option<U256>, Vec<(Transaction, TransactionReceipt)>);
+
+#[async_trait::async_trait]
+impl CollectByBlock for TransactionFees {
+    type Response = BlockFees;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .fetcher
+            .get_block_with_txs(request.block_number()?)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        let receipts = source.get_tx_receipts_in_block(&block).await?;
+        Ok((block.base_fee_per_gas, block.transactions.into_iter().zip(receipts).collect()))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::TransactionFees)?;
+        process_transaction_fees(response, columns, schema)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for TransactionFees {
+    type Response = BlockFees;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        let transaction = source
+            .fetcher
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or(CollectError::CollectError("transaction not found".to_string()))?;
+        let receipt = source
+            .fetcher
+            .get_transaction_receipt(tx_hash)
+            .await?
+            .ok_or(CollectError::CollectError("could not find tx receipt".to_string()))?;
+        let block_number = transaction
+            .block_number
+            .ok_or(CollectError::CollectError("no block number for tx".to_string()))?;
+        let block = source
+            .fetcher
+            .get_block(block_number.as_u64())
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        Ok((block.base_fee_per_gas, vec![(transaction, receipt)]))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::TransactionFees)?;
+        process_transaction_fees(response, columns, schema)
+    }
+}
+
+/// decompose fees of each transaction, pre-london blocks have no base fee so the entire gas
+/// price is counted as priority fee
+fn process_transaction_fees(
+    response: BlockFees,
+    columns: &mut TransactionFees,
+    schema: &Table,
+) -> R<()> {
+    let (base_fee, transactions) = response;
+    for (tx, receipt) in transactions.into_iter() {
+        let gas_used = receipt.gas_used.ok_or(err("receipt has no gas used"))?;
+        let effective_gas_price = receipt
+            .effective_gas_price
+            .or_else(|| effective_gas_price(&tx, base_fee))
+            .ok_or(err("could not determine effective gas price"))?;
+        let priority_fee = effective_gas_price.saturating_sub(base_fee.unwrap_or_default());
+        // op stack chains charge the cost of posting transaction data to l1 on top of execution
+        let l1_data_fee = receipt_field(&receipt, "l1Fee");
+        // blob transactions also pay for their blob gas, which is burned
+        let blob_fee_paid =
+            match (receipt_field(&receipt, "blobGasUsed"), receipt_field(&receipt, "blobGasPrice"))
+            {
+                (Some(blob_gas_used), Some(blob_gas_price)) => Some(blob_gas_used * blob_gas_price),
+                _ => None,
+            };
+        let total_fee_paid = gas_used * effective_gas_price +
+            blob_fee_paid.unwrap_or_default() +
+            l1_data_fee.unwrap_or_default();
+
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, receipt.block_number.unwrap_or_default().as_u64());
+        store!(schema, columns, transaction_index, receipt.transaction_index.as_u64());
+        store!(schema, columns, transaction_hash, receipt.transaction_hash.as_bytes().to_vec());
+        store!(schema, columns, gas_used, gas_used.as_u64());
+        store!(schema, columns, base_fee_per_gas, base_fee.map(|x| x.as_u64()));
+        store!(schema, columns, max_fee_per_gas, tx.max_fee_per_gas.map(|x| x.as_u64()));
+        store!(
+            schema,
+            columns,
+            max_priority_fee_per_gas,
+            tx.max_priority_fee_per_gas.map(|x| x.as_u64())
+        );
+        store!(schema, columns, effective_gas_price, effective_gas_price.as_u64());
+        store!(schema, columns, priority_fee_paid, gas_used * priority_fee);
+        store!(schema, columns, base_fee_burned, base_fee.map(|base_fee| gas_used * base_fee));
+        store!(schema, columns, total_fee_paid, total_fee_paid);
+        store!(schema, columns, blob_fee_paid, blob_fee_paid);
+        store!(schema, columns, l1_data_fee, l1_data_fee);
+    }
+    Ok(())
+}
+
+fn receipt_field(receipt: &TransactionReceipt, name: &str) -> Option<U256> {
+    match receipt.other.get_deserialized::<U256>(name) {
+        Some(Ok(value)) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(gas_used: u64, effective_gas_price: Option<u64>) -> TransactionReceipt {
+        TransactionReceipt {
+            gas_used: Some(gas_used.into()),
+            effective_gas_price: effective_gas_price.map(U256::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_process_transaction_fees() {
+        let schema = Datatype::TransactionFees
+            .table_schema(&[], &ColumnEncoding::Hex, &None, &None, &None, None, None, None)
+            .unwrap();
+        let mut columns = TransactionFees::default();
+
+        // pre-london transactions pay their entire gas price to the fee recipient
+        let legacy = Transaction { gas_price: Some(20.into()), ..Default::default() };
+        process_transaction_fees((None, vec![(legacy, receipt(100, None))]), &mut columns, &schema)
+            .unwrap();
+
+        let mut op_receipt = receipt(100, Some(15));
+        op_receipt.other.insert("l1Fee".to_string(), serde_json::json!("0x3e8"));
+        let tx = Transaction { max_fee_per_gas: Some(30.into()), ..Default::default() };
+        process_transaction_fees((Some(10.into()), vec![(tx, op_receipt)]), &mut columns, &schema)
+            .unwrap();
+
+        let mut blob_receipt = receipt(100, Some(15));
+        blob_receipt.other.insert("blobGasUsed".to_string(), serde_json::json!("0x20000"));
+        blob_receipt.other.insert("blobGasPrice".to_string(), serde_json::json!("0x2"));
+        let tx = Transaction { max_fee_per_gas: Some(30.into()), ..Default::default() };
+        process_transaction_fees(
+            (Some(10.into()), vec![(tx, blob_receipt)]),
+            &mut columns,
+            &schema,
+        )
+        .unwrap();
+
+        assert_eq!(columns.n_rows, 3);
+        assert_eq!(columns.base_fee_per_gas, vec![None, Some(10), Some(10)]);
+        assert_eq!(columns.effective_gas_price, vec![20, 15, 15]);
+        assert_eq!(
+            columns.priority_fee_paid,
+            vec![U256::from(2000), U256::from(500), U256::from(500)]
+        );
+        assert_eq!(columns.base_fee_burned, vec![None, Some(U256::from(1000)), Some(1000.into())]);
+        assert_eq!(columns.blob_fee_paid, vec![None, None, Some(U256::from(262144))]);
+        assert_eq!(columns.l1_data_fee, vec![None, Some(U256::from(1000)), None]);
+        assert_eq!(
+            columns.total_fee_paid,
+            vec![U256::from(2000), U256::from(2500), U256::from(263644)]
+        );
+    }
+}
@@ -7,7 +7,7 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct Transactions {
     n_rows: u64,
-    block_number: Vec<Option<u32>>,
+    block_number: Vec<Option<u64>>,
     transaction_index: Vec<Option<u64>>,
     transaction_hash: Vec<Vec<u8>>,
     nonce: Vec<u64>,
@@ -21,10 +21,15 @@ pub struct Transactions {
     transaction_type: Vec<Option<u32>>,
     max_priority_fee_per_gas: Vec<Option<u64>>,
     max_fee_per_gas: Vec<Option<u64>>,
-    success: Vec<bool>,
+    success: Vec<Option<bool>>,
     chain_id: Vec<u64>,
     timestamp: Vec<u32>,
     block_hash: Vec<Vec<u8>>,
+    y_parity: Vec<Option<u32>>,
+    max_fee_per_blob_gas: Vec<Option<u64>>,
+    blob_versioned_hashes: Vec<Option<Vec<u8>>>,
+    blob_count: Vec<Option<u32>>,
+    authorization_list_length: Vec<Option<u32>>,
 }
 
 #[async_trait::async_trait]
@@ -85,7 +90,7 @@ impl CollectByBlock for Transactions {
         // filter by to_address
         let to_filter: Box<dyn Fn(&Transaction) -> bool + Send> =
             if let Some(to_address) = &request.to_address {
-                Box::new(move |tx| tx.to.as_ref().map_or(false, |x| x.as_bytes() == to_address))
+                Box::new(move |tx| tx.to.as_ref().is_some_and(|x| x.as_bytes() == to_address))
             } else {
                 Box::new(|_| true)
             };
@@ -179,17 +184,17 @@ pub(crate) fn process_transaction(
     timestamp: u32,
 ) -> R<()> {
     let success = if exclude_failed | schema.has_column("success") {
-        let success = tx_success(&tx, &receipt)?;
-        if exclude_failed & !success {
+        let success = tx_success(&receipt)?;
+        if exclude_failed & (success == Some(false)) {
             return Ok(())
         }
         success
     } else {
-        false
+        None
     };
 
     columns.n_rows += 1;
-    store!(schema, columns, block_number, tx.block_number.map(|x| x.as_u32()));
+    store!(schema, columns, block_number, tx.block_number.map(|x| x.as_u64()));
     store!(schema, columns, transaction_index, tx.transaction_index.map(|x| x.as_u64()));
     store!(schema, columns, transaction_hash, tx.hash.as_bytes().to_vec());
     store!(schema, columns, from_address, tx.from.as_bytes().to_vec());
@@ -212,21 +217,145 @@ pub(crate) fn process_transaction(
     store!(schema, columns, timestamp, timestamp);
     store!(schema, columns, block_hash, tx.block_hash.unwrap_or_default().as_bytes().to_vec());
 
+    // ethers predates blob and set code transactions, so their fields are read from the extra
+    // fields of the raw transaction
+    let transaction_type = tx.transaction_type.map(|x| x.as_u64());
+    let y_parity = match transaction_type {
+        Some(0) | None => None,
+        Some(_) => match tx.other.get_deserialized::<U64>("yParity") {
+            Some(Ok(y_parity)) => Some(y_parity.as_u32()),
+            _ => Some(tx.v.as_u32()),
+        },
+    };
+    let blob_versioned_hashes = match transaction_type {
+        Some(3) => {
+            tx.other.get_deserialized::<Vec<H256>>("blobVersionedHashes").and_then(Result::ok)
+        }
+        _ => None,
+    };
+    let max_fee_per_blob_gas = match transaction_type {
+        Some(3) => tx.other.get_deserialized::<U256>("maxFeePerBlobGas").and_then(Result::ok),
+        _ => None,
+    };
+    let authorization_list_length = match transaction_type {
+        Some(4) => tx
+            .other
+            .get_deserialized::<Vec<serde_json::Value>>("authorizationList")
+            .and_then(Result::ok)
+            .map(|list| list.len() as u32),
+        _ => None,
+    };
+    store!(schema, columns, y_parity, y_parity);
+    store!(schema, columns, max_fee_per_blob_gas, max_fee_per_blob_gas.map(|x| x.as_u64()));
+    store!(schema, columns, blob_count, blob_versioned_hashes.as_ref().map(|x| x.len() as u32));
+    store!(
+        schema,
+        columns,
+        blob_versioned_hashes,
+        blob_versioned_hashes.map(|hashes| hashes.iter().flat_map(|x| x.0).collect())
+    );
+    store!(schema, columns, authorization_list_length, authorization_list_length);
+
     Ok(())
 }
 
-fn tx_success(tx: &Transaction, receipt: &Option<TransactionReceipt>) -> R<bool> {
-    if let Some(status) = receipt.as_ref().and_then(|x| x.status) {
-        Ok(status.as_u64() == 1)
-    } else if let (Some(1), Some(true)) =
-        (tx.chain_id.map(|x| x.as_u64()), tx.block_number.map(|x| x.as_u64() < 4370000))
-    {
-        if let Some(gas_used) = receipt.as_ref().and_then(|x| x.gas_used.map(|x| x.as_u64())) {
-            Ok(gas_used == 0)
-        } else {
-            return Err(err("could not determine status of transaction"))
+/// whether transaction succeeded, None for pre-byzantium transactions, whose receipts have a
+/// state root instead of a status
+pub(crate) fn tx_success(receipt: &Option<TransactionReceipt>) -> R<Option<bool>> {
+    match receipt {
+        Some(TransactionReceipt { status: Some(status), .. }) => Ok(Some(status.as_u64() == 1)),
+        Some(TransactionReceipt { root: Some(_), .. }) => Ok(None),
+        _ => Err(err("could not determine status of transaction")),
+    }
+}
+
+/// gas price paid by a transaction, for receipts of nodes that omit effectiveGasPrice. dynamic
+/// fee transactions pay the base fee plus their priority fee, capped at their max fee, other
+/// transactions pay their gas price
+pub(crate) fn effective_gas_price(tx: &Transaction, base_fee: Option<U256>) -> Option<U256> {
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas, base_fee) {
+        (Some(max_fee), Some(max_priority_fee), Some(base_fee)) => {
+            Some(max_fee.min(base_fee.saturating_add(max_priority_fee)))
         }
-    } else {
-        return Err(err("could not determine status of transaction"))
+        _ => tx.gas_price,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_transaction_typed_fields() {
+        let columns =
+            ["y_parity", "blob_count", "blob_versioned_hashes", "authorization_list_length"];
+        let schema = Datatype::Transactions
+            .table_schema(
+                &[],
+                &ColumnEncoding::Binary,
+                &None,
+                &None,
+                &Some(columns.iter().map(|x| x.to_string()).collect()),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let mut columns = Transactions::default();
+
+        let legacy =
+            Transaction { transaction_type: Some(0.into()), v: 27.into(), ..Default::default() };
+        process_transaction(legacy, None, &mut columns, &schema, false, 0).unwrap();
+
+        let mut blob =
+            Transaction { transaction_type: Some(3.into()), v: 1.into(), ..Default::default() };
+        let hashes = vec![H256::repeat_byte(1), H256::repeat_byte(2)];
+        blob.other.insert("blobVersionedHashes".to_string(), serde_json::json!(hashes));
+        process_transaction(blob, None, &mut columns, &schema, false, 0).unwrap();
+
+        let mut set_code = Transaction { transaction_type: Some(4.into()), ..Default::default() };
+        set_code.other.insert("yParity".to_string(), serde_json::json!("0x1"));
+        set_code.other.insert("authorizationList".to_string(), serde_json::json!([{}, {}, {}]));
+        process_transaction(set_code, None, &mut columns, &schema, false, 0).unwrap();
+
+        assert_eq!(columns.y_parity, vec![None, Some(1), Some(1)]);
+        assert_eq!(columns.blob_count, vec![None, Some(2), None]);
+        assert_eq!(columns.blob_versioned_hashes[1].as_ref().map(|x| x.len()), Some(64));
+        assert_eq!(columns.authorization_list_length, vec![None, None, Some(3)]);
+    }
+
+    #[test]
+    fn test_process_transaction_success_across_forks() {
+        let schema = Datatype::Transactions
+            .table_schema(
+                &[],
+                &ColumnEncoding::Binary,
+                &None,
+                &None,
+                &Some(vec!["block_number".to_string(), "success".to_string()]),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let process = |block_number: u64, exclude_failed: bool| {
+            let fixture = crate::datasets::fixtures::synthetic_block(block_number);
+            let mut columns = Transactions::default();
+            for (tx, receipt) in fixture.block.transactions.into_iter().zip(fixture.receipts) {
+                process_transaction(tx, Some(receipt), &mut columns, &schema, exclude_failed, 0)
+                    .unwrap();
+            }
+            columns
+        };
+
+        // pre-byzantium receipts have no status, so success is unknown and nothing is excluded
+        assert_eq!(process(46147, false).success, vec![None]);
+        assert_eq!(process(4369999, true).success, vec![None, None]);
+        assert_eq!(process(12964999, false).success, vec![Some(true), Some(false)]);
+        assert_eq!(process(12964999, true).success, vec![Some(true)]);
+
+        let no_receipt = Transaction::default();
+        let mut columns = Transactions::default();
+        assert!(process_transaction(no_receipt, None, &mut columns, &schema, false, 0).is_err());
     }
 }
@@ -0,0 +1,102 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for uncles
+#[cryo_to_df::to_df(Datatype::Uncles)]
+#[derive(Default)]
+pub struct Uncles {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    uncle_index: Vec<u32>,
+    uncle_hash: Vec<Option<Vec<u8>>>,
+    uncle_number: Vec<Option<u32>>,
+    uncle_miner: Vec<Option<Vec<u8>>>,
+    uncle_timestamp: Vec<u32>,
+    uncle_difficulty: Vec<U256>,
+    reward: Vec<Option<U256>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Uncles {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "uncle_index"])
+    }
+}
+
+/// including block number, uncles, and chain id
+type BlockUncles = (u64, Vec<Block<TxHash>>, u64);
+
+#[async_trait::async_trait]
+impl CollectByBlock for Uncles {
+    type Response = BlockUncles;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let block = source
+            .fetcher
+            .get_block(block_number)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+
+        // uncle hashes of header are empty for post-merge blocks, so no uncle requests are made
+        let mut uncles = Vec::new();
+        for index in 0..block.uncles.len() {
+            let uncle = source
+                .fetcher
+                .get_uncle(block_number, index as u64)
+                .await?
+                .ok_or(CollectError::CollectError("uncle not found".to_string()))?;
+            uncles.push(uncle);
+        }
+        Ok((block_number, uncles, source.chain_id))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Uncles)?;
+        let (block_number, uncles, chain_id) = response;
+        let static_reward = static_block_reward(chain_id, block_number, query.merge_block);
+        for (uncle_index, uncle) in uncles.into_iter().enumerate() {
+            let uncle_number = uncle.number.map(|x| x.as_u64());
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, block_number);
+            store!(schema, columns, uncle_index, uncle_index as u32);
+            store!(schema, columns, uncle_hash, uncle.hash.map(|x| x.as_bytes().to_vec()));
+            store!(schema, columns, uncle_number, uncle_number.map(|x| x as u32));
+            store!(schema, columns, uncle_miner, uncle.author.map(|x| x.as_bytes().to_vec()));
+            store!(schema, columns, uncle_timestamp, uncle.timestamp.as_u32());
+            store!(schema, columns, uncle_difficulty, uncle.difficulty);
+            store!(
+                schema,
+                columns,
+                reward,
+                uncle_number.and_then(|n| uncle_reward(static_reward?, n, block_number))
+            );
+        }
+        Ok(())
+    }
+}
+
+impl CollectByTransaction for Uncles {
+    type Response = ();
+}
+
+/// reward paid to the miner of an uncle, decreasing by 1/8 of the static reward per block of depth
+fn uncle_reward(static_reward: U256, uncle_number: u64, block_number: u64) -> Option<U256> {
+    let depth = block_number.checked_sub(uncle_number)?;
+    (depth <= 8).then(|| static_reward * U256::from(8 - depth) / 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncle_reward() {
+        let reward = U256::exp10(18) * 2;
+        assert_eq!(uncle_reward(reward, 99, 100), Some(U256::exp10(17) * 175 / 10));
+        assert_eq!(uncle_reward(reward, 94, 100), Some(U256::exp10(17) * 5));
+        assert_eq!(uncle_reward(reward, 101, 100), None);
+    }
+}
@@ -6,9 +6,9 @@ use polars::prelude::*;
 #[cryo_to_df::to_df(Datatype::VmTraces)]
 #[derive(Default)]
 pub struct VmTraces {
-    block_number: Vec<Option<u32>>,
+    block_number: Vec<Option<u64>>,
     transaction_hash: Vec<Option<Vec<u8>>>,
-    transaction_index: Vec<u32>,
+    transaction_index: Vec<u64>,
     pc: Vec<u64>,
     cost: Vec<u64>,
     used: Vec<Option<u64>>,
@@ -37,40 +37,74 @@ impl Dataset for VmTraces {
     }
 }
 
+type BlockTxTraces =
+    (Option<u64>, Vec<Option<u64>>, Option<Vec<u8>>, Vec<ethers::types::BlockTrace>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for VmTraces {
-    type Response = (Option<u32>, Option<Vec<u8>>, Vec<ethers::types::BlockTrace>);
+    type Response = BlockTxTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_block_vm_traces(request.block_number()? as u32).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::VmTraces)?;
+        let include_call_traces = query.trace_contract_filter.is_some();
+        let include_indices = schema.has_column("transaction_index");
+        source
+            .fetcher
+            .trace_block_vm_traces(request.block_number()?, include_call_traces, include_indices)
+            .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_vm_traces(response, columns, &query.schemas)
+        query.note_trace_positions(&Datatype::VmTraces, &response.1);
+        process_vm_traces(response, columns, &query.schemas, &query.trace_contract_filter)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for VmTraces {
-    type Response = (Option<u32>, Option<Vec<u8>>, Vec<ethers::types::BlockTrace>);
+    type Response = BlockTxTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction_vm_traces(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let schema = query.schemas.get_schema(&Datatype::VmTraces)?;
+        let include_call_traces = query.trace_contract_filter.is_some();
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
+        source
+            .fetcher
+            .trace_transaction_vm_traces(
+                request.transaction_hash()?,
+                include_call_traces,
+                include_transaction,
+            )
+            .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_vm_traces(response, columns, &query.schemas)
+        query.note_trace_positions(&Datatype::VmTraces, &response.1);
+        process_vm_traces(response, columns, &query.schemas, &query.trace_contract_filter)
     }
 }
 
 fn process_vm_traces(
-    response: (Option<u32>, Option<Vec<u8>>, Vec<ethers::types::BlockTrace>),
+    response: BlockTxTraces,
     columns: &mut VmTraces,
     schemas: &Schemas,
+    contract_filter: &Option<TraceContractFilter>,
 ) -> R<()> {
-    let (block_number, tx, block_traces) = response;
+    let (block_number, indices, tx, block_traces) = response;
     let schema = schemas.get(&Datatype::VmTraces).ok_or(err("schema not provided"))?;
-    for (tx_pos, block_trace) in block_traces.into_iter().enumerate() {
+    for (position, (block_trace, index)) in block_traces.into_iter().zip(indices).enumerate() {
+        let tx_pos = index.unwrap_or(position as u64);
+        // vm traces have no addresses, so transactions are kept if any of their calls match
+        if let Some(filter) = contract_filter {
+            let calls = block_trace.trace.as_deref().unwrap_or_default();
+            if !calls
+                .iter()
+                .any(|call| action_matches_contracts(&call.action, &call.result, filter))
+            {
+                continue
+            }
+        }
         if let Some(vm_trace) = block_trace.vm_trace {
             add_ops(vm_trace, schema, columns, block_number, tx.clone(), tx_pos);
         }
@@ -82,16 +116,16 @@ fn add_ops(
     vm_trace: VMTrace,
     schema: &Table,
     columns: &mut VmTraces,
-    number: Option<u32>,
+    number: Option<u64>,
     tx_hash: Option<Vec<u8>>,
-    tx_pos: usize,
+    tx_pos: u64,
 ) {
     for opcode in vm_trace.ops {
         columns.n_rows += 1;
 
         store!(schema, columns, block_number, number);
         store!(schema, columns, transaction_hash, tx_hash.clone());
-        store!(schema, columns, transaction_index, tx_pos as u32);
+        store!(schema, columns, transaction_index, tx_pos);
         store!(schema, columns, pc, opcode.pc as u64);
         store!(schema, columns, cost, opcode.cost);
         if let Some(ex) = opcode.ex {
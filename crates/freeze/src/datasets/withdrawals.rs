@@ -0,0 +1,64 @@
+use crate::*;
+use ethers::prelude::*;
+use polars::prelude::*;
+
+/// columns for withdrawals
+#[cryo_to_df::to_df(Datatype::Withdrawals)]
+#[derive(Default)]
+pub struct Withdrawals {
+    n_rows: u64,
+    block_number: Vec<u64>,
+    withdrawal_index: Vec<u64>,
+    validator_index: Vec<u64>,
+    address: Vec<Vec<u8>>,
+    amount_gwei: Vec<u64>,
+    amount_wei: Vec<U256>,
+    timestamp: Vec<u32>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Withdrawals {
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "withdrawal_index"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for Withdrawals {
+    type Response = Block<TxHash>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .fetcher
+            .get_block(request.block_number()?)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        Ok(block)
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Withdrawals)?;
+        let block_number = response.number.ok_or(err("block number not found"))?.as_u64();
+        let timestamp = response.timestamp.as_u32();
+
+        // pre-shanghai blocks have no withdrawals
+        for withdrawal in response.withdrawals.unwrap_or_default().into_iter() {
+            // rpc reports withdrawal amounts in gwei
+            let amount_gwei = withdrawal.amount.as_u64();
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, block_number);
+            store!(schema, columns, withdrawal_index, withdrawal.index.as_u64());
+            store!(schema, columns, validator_index, withdrawal.validator_index.as_u64());
+            store!(schema, columns, address, withdrawal.address.as_bytes().to_vec());
+            store!(schema, columns, amount_gwei, amount_gwei);
+            store!(schema, columns, amount_wei, U256::from(amount_gwei) * U256::exp10(9));
+            store!(schema, columns, timestamp, timestamp);
+        }
+        Ok(())
+    }
+}
+
+impl CollectByTransaction for Withdrawals {
+    type Response = ();
+}
@@ -0,0 +1,223 @@
+use crate::{
+    collect_partition, dataframes, err, BlockChunk, ChunkData, CollectError, Datatype,
+    ExecutionEnv, FileOutput, MetaDatatype, Partition, Query, Source, TimeDimension,
+};
+use polars::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// size of collecting a dataset, extrapolated from sampled blocks
+#[derive(Clone, Debug)]
+pub(crate) struct DatasetEstimate {
+    pub(crate) datatype: Datatype,
+    pub(crate) n_files: u64,
+    pub(crate) n_rows: u64,
+    pub(crate) n_bytes: u64,
+}
+
+/// size and duration of a dry run, extrapolated from sampled blocks
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DryRunEstimate {
+    pub(crate) n_samples: u64,
+    pub(crate) n_blocks: u64,
+    pub(crate) datasets: Vec<DatasetEstimate>,
+    pub(crate) requests: BTreeMap<String, u64>,
+    pub(crate) request_latency: Option<Duration>,
+    pub(crate) wall_time: Option<Duration>,
+}
+
+/// whether a dry run samples blocks to estimate the size of its collection
+pub(crate) fn is_estimated(query: &Query, env: &ExecutionEnv) -> bool {
+    env.dry && env.dry_run_samples > 0 && matches!(query.time_dimension, TimeDimension::Blocks)
+}
+
+/// collect a few blocks of each dataset and scale the rows, bytes, and requests up to the size
+/// of the whole collection
+pub(crate) async fn estimate_collection(
+    query: &Arc<Query>,
+    source: &Arc<Source>,
+    sink: &FileOutput,
+    chunks: &[(MetaDatatype, Partition, HashMap<Datatype, PathBuf>)],
+    n_samples: u64,
+) -> Result<DryRunEstimate, CollectError> {
+    let mut estimate = DryRunEstimate::default();
+    let (mut n_requests, mut elapsed) = (0, Duration::ZERO);
+    for datatype in query.datatypes.iter() {
+        let chunks: Vec<_> = chunks
+            .iter()
+            .filter(|(chunk_datatype, _, _)| chunk_datatype.datatypes() == datatype.datatypes())
+            .collect();
+        let partitions: Vec<&Partition> =
+            chunks.iter().map(|(_, partition, _)| partition).collect();
+        let samples = sample_blocks(&partitions, n_samples);
+        let n_blocks: u64 = partitions
+            .iter()
+            .flat_map(|p| p.block_numbers.iter().flatten())
+            .map(|c| c.size())
+            .sum();
+        if samples.is_empty() {
+            continue
+        }
+        let scale = n_blocks as f64 / samples.len() as f64;
+        estimate.n_samples = estimate.n_samples.max(samples.len() as u64);
+        estimate.n_blocks = estimate.n_blocks.max(n_blocks);
+
+        // collect sampled blocks one at a time so that request latency can be measured
+        let mut sampled_dfs: HashMap<Datatype, DataFrame> = HashMap::new();
+        let mut requests: BTreeMap<String, u64> = BTreeMap::new();
+        for (partition, block) in samples.into_iter() {
+            let partition = Partition {
+                block_numbers: Some(vec![BlockChunk::Numbers(vec![block])]),
+                ..partition.clone()
+            };
+            let before = source.fetcher.request_counts.counts();
+            let start = Instant::now();
+            let dfs = collect_partition(datatype.clone(), partition, query.clone(), source.clone())
+                .await?;
+            elapsed += start.elapsed();
+            for (method, count) in source.fetcher.request_counts.counts().into_iter() {
+                let delta = count - before.get(&method).copied().unwrap_or(0);
+                n_requests += delta;
+                *requests.entry(method).or_insert(0) += delta;
+            }
+            for (datatype, df) in dfs.into_iter() {
+                match sampled_dfs.get_mut(&datatype) {
+                    Some(sampled) => {
+                        sampled.vstack_mut(&df).map_err(|_| err("could not combine samples"))?;
+                    }
+                    None => {
+                        sampled_dfs.insert(datatype, df);
+                    }
+                }
+            }
+        }
+
+        // requests of datasets that fetch block ranges cover several blocks at once
+        let request_scale = match datatype.datatypes().iter().any(|dt| dt.use_block_ranges()) {
+            true => scale / source.inner_request_size.max(1) as f64,
+            false => scale,
+        };
+        for (method, count) in requests.into_iter() {
+            *estimate.requests.entry(method).or_insert(0) +=
+                (count as f64 * request_scale).ceil() as u64;
+        }
+        for datatype in datatype.datatypes().into_iter() {
+            let mut df = match sampled_dfs.remove(&datatype) {
+                Some(df) => df,
+                None => continue,
+            };
+            let path = chunks.iter().find_map(|(_, _, paths)| paths.get(&datatype));
+            let n_bytes = match path {
+                Some(path) if df.height() > 0 => sample_file_size(&mut df, path, sink)?,
+                _ => df.estimated_size() as u64,
+            };
+            let n_files =
+                chunks.iter().filter(|(_, _, paths)| paths.contains_key(&datatype)).count() as u64;
+            estimate.datasets.push(DatasetEstimate {
+                datatype,
+                n_files,
+                n_rows: (df.height() as f64 * scale).round() as u64,
+                n_bytes: (n_bytes as f64 * scale).round() as u64,
+            });
+        }
+    }
+
+    // requests are limited by both the rate limit and the number of concurrent requests
+    if n_requests > 0 {
+        let latency = elapsed / n_requests as u32;
+        let total_requests: u64 = estimate.requests.values().sum();
+        let concurrency = match &source.fetcher.semaphore {
+            Some(semaphore) => semaphore.available_permits().max(1) as f64,
+            None => chunks.len().max(1) as f64,
+        };
+        let mut seconds = total_requests as f64 * latency.as_secs_f64() / concurrency;
+        if let Some(rate) = source.labels.max_requests_per_second.filter(|rate| *rate > 0) {
+            seconds = seconds.max(total_requests as f64 / rate as f64);
+        }
+        estimate.request_latency = Some(latency);
+        estimate.wall_time = Some(Duration::from_secs_f64(seconds));
+    }
+    Ok(estimate)
+}
+
+/// evenly spaced blocks of partitions, with the partition each block belongs to
+fn sample_blocks<'a>(partitions: &[&'a Partition], n_samples: u64) -> Vec<(&'a Partition, u64)> {
+    let chunks: Vec<(&Partition, &BlockChunk)> = partitions
+        .iter()
+        .flat_map(|partition| {
+            partition.block_numbers.iter().flatten().map(move |chunk| (*partition, chunk))
+        })
+        .collect();
+    let n_blocks: u64 = chunks.iter().map(|(_, chunk)| chunk.size()).sum();
+    let n_samples = n_samples.min(n_blocks);
+    let mut samples = Vec::new();
+    let (mut offset, mut chunks) = (0, chunks.into_iter().peekable());
+    for i in 0..n_samples {
+        // middle of each of n_samples equal spans of blocks
+        let index = (2 * i + 1) * n_blocks / (2 * n_samples);
+        while let Some((partition, chunk)) = chunks.peek() {
+            if index < offset + chunk.size() {
+                let block = match chunk {
+                    BlockChunk::Numbers(numbers) => numbers[(index - offset) as usize],
+                    BlockChunk::Range(start, _) => start + index - offset,
+                };
+                samples.push((*partition, block));
+                break
+            }
+            offset += chunk.size();
+            chunks.next();
+        }
+    }
+    samples
+}
+
+/// size of a sampled dataframe when written in the output format
+fn sample_file_size(
+    df: &mut DataFrame,
+    path: &Path,
+    sink: &FileOutput,
+) -> Result<u64, CollectError> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let extension = name.split_once('.').map(|(_, extension)| extension).unwrap_or("parquet");
+    let path =
+        std::env::temp_dir().join(format!("cryo_dry_run_{}.{}", std::process::id(), extension));
+    let mut sink = sink.clone();
+    sink.key_value_metadata = vec![];
+    sink.checksum_manifest = None;
+    dataframes::df_to_file(df, &path, &sink)
+        .map_err(|_| err("could not write sampled blocks to estimate file size"))?;
+    let n_bytes = std::fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(dataframes::sidecar_path(&path));
+    Ok(n_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_blocks() {
+        let first = Partition {
+            block_numbers: Some(vec![BlockChunk::Range(100, 199)]),
+            ..Default::default()
+        };
+        let second = Partition {
+            block_numbers: Some(vec![BlockChunk::Numbers(vec![500, 600])]),
+            ..Default::default()
+        };
+        let partitions = vec![&first, &second];
+        let blocks = |n| -> Vec<u64> {
+            sample_blocks(&partitions, n).into_iter().map(|(_, block)| block).collect()
+        };
+        assert_eq!(blocks(3), vec![117, 151, 185]);
+        assert_eq!(blocks(1), vec![151]);
+        assert_eq!(blocks(0), Vec::<u64>::new());
+        assert_eq!(blocks(200).len(), 102);
+        assert_eq!(blocks(200)[101], 600);
+    }
+}
@@ -1,15 +1,28 @@
 use crate::{
-    collect_partition, dataframes, err, reports, summaries, CollectError, Datatype, ExecutionEnv,
-    FileOutput, FreezeSummary, MetaDatatype, Partition, Query, Source,
+    collect, coverage::CompactedRanges, dataframes, err, estimate, reports, stream_partition,
+    summaries, CollectError, CollectionEvent, ColumnType, Datatype, ExecutionEnv, FileError,
+    FileOutput, FreezeSummary, MetaDatatype, Partition, Query, SchemaFunctions, Source,
+    TimeDimension, U256Type, UnsupportedDataset,
 };
 use chrono::{DateTime, Local};
-use futures::{stream::FuturesUnordered, StreamExt};
+use ethers::types::H256;
+use futures::StreamExt;
+use polars::prelude::DataFrame;
 use std::{
-    collections::{HashMap, HashSet},
-    path::PathBuf,
-    sync::Arc,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
-use tokio::sync::Semaphore;
+use tokio::sync::{mpsc, Semaphore};
+
+/// parquet metadata key of the columns that rows are sorted by, ascending with nulls last
+const SORT_ORDER_KEY: &str = "cryo.sort_order";
+
+/// concurrent requests when checking which files have already been uploaded
+const MAX_CONCURRENT_CHECKS: usize = 16;
+
+/// time between logs of the settings of adaptive concurrency
+const AUTOSCALE_LOG_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
 
 type PartitionPayload = (
     Partition,
@@ -20,8 +33,12 @@ type PartitionPayload = (
     FileOutput,
     ExecutionEnv,
     Option<std::sync::Arc<Semaphore>>,
+    Option<dataframes::ChunkWriter>,
 );
 
+/// partition of a single dataset
+type DatasetPartition = (Partition, MetaDatatype);
+
 /// collect data and output as files
 pub async fn freeze(
     query: &Query,
@@ -33,44 +50,130 @@ pub async fn freeze(
     query.is_valid()?;
 
     // get partitions
-    let (payloads, skipping) = get_payloads(query, source, sink, env)?;
+    let uploaded_paths = get_uploaded_paths(query, sink).await?;
+    let loaded_chunks = get_loaded_chunks(sink).await?;
+    let (payloads, skipping) =
+        get_payloads(query, source, sink, env, uploaded_paths, loaded_chunks)?;
 
     // print summary
     if env.verbose >= 1 {
-        summaries::print_cryo_intro(query, source, sink, env, payloads.len() as u64)?;
+        let output_paths: Vec<_> =
+            payloads.iter().flat_map(|payload| payload.2.values().cloned()).collect();
+        summaries::print_cryo_intro(
+            query,
+            source,
+            sink,
+            env,
+            payloads.len() as u64,
+            &output_paths,
+        )?;
     }
 
     // check dry run
     if env.dry {
+        if estimate::is_estimated(query, env) {
+            let chunks: Vec<_> = payloads
+                .iter()
+                .map(|payload| (payload.1.clone(), payload.0.clone(), payload.2.clone()))
+                .collect();
+            let estimate = estimate::estimate_collection(
+                &Arc::new(query.clone()),
+                &Arc::new(source.clone()),
+                sink,
+                &chunks,
+                env.dry_run_samples,
+            )
+            .await?;
+            if env.verbose >= 1 {
+                summaries::print_dry_run_estimate(&estimate);
+            }
+        }
         return Ok(None)
     };
 
+    for (partition, _) in skipping.iter() {
+        let chunk = partition.label(&query.partitioned_by).unwrap_or_default();
+        env.emit(CollectionEvent::ChunkSkipped { chunk });
+    }
+
     // check if empty
     if payloads.is_empty() {
         let results = FreezeSummary { skipped: skipping, ..Default::default() };
         if env.verbose >= 1 {
-            summaries::print_cryo_conclusion(&results, query, env)
+            summaries::print_cryo_conclusion(&results, query, source, env)
         }
         return Ok(Some(results))
     }
 
+    // share block responses between the datasets of each chunk
+    if matches!(query.time_dimension, TimeDimension::Blocks) {
+        source.fetcher.shared_fetches.enable(&query.datatypes);
+    }
+    source.fetcher.block_timestamps.enable(&query.schemas);
+    source.subscribe(env.events.clone());
+
     // create initial report
     if env.report {
-        reports::write_report(env, query, sink, None)?;
+        reports::write_report(env, query, source, sink, None)?;
+    };
+
+    // serve metrics during collection
+    #[cfg(feature = "metrics")]
+    let metrics_server = match env.metrics_addr {
+        Some(addr) => {
+            let source = Arc::new(source.clone());
+            Some(crate::metrics::spawn_metrics_server(addr, source, env.counts.clone()).await?)
+        }
+        None => None,
+    };
+
+    // perform collection, with database loads serialized through a single writer task
+    let results = match spawn_chunk_writer(sink)? {
+        Some((writer, handle)) => {
+            let payloads = payloads
+                .into_iter()
+                .map(|mut payload| {
+                    payload.8 = Some(writer.clone());
+                    payload
+                })
+                .collect();
+            drop(writer);
+            let results = freeze_partitions(env, payloads, skipping).await;
+            handle.await.map_err(|_| err("error joining chunk writer"))?;
+            if sink.stdout {
+                let _ = std::fs::remove_dir(&sink.output_dir);
+            }
+            results
+        }
+        None => freeze_partitions(env, payloads, skipping).await,
     };
 
-    // perform collection
-    let results = freeze_partitions(env, payloads, skipping).await;
+    #[cfg(feature = "metrics")]
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+    }
 
     // create summary
     if env.verbose >= 1 {
-        summaries::print_cryo_conclusion(&results, query, env)
+        summaries::print_cryo_conclusion(&results, query, source, env)
     }
 
-    // create final report
-    if env.report {
-        reports::write_report(env, query, sink, Some(&results))?;
+    // record failed block requests, and create final report
+    let errors_path = reports::write_errors_file(query, &sink.output_dir, env)?;
+    let report_path = match env.report {
+        true => Some(reports::write_report(env, query, source, sink, Some(&results))?),
+        false => None,
     };
+    if let Some(store) = &sink.object_store {
+        for path in errors_path.iter().chain(report_path.iter()) {
+            if let Some(key) = store.key(&sink.output_dir, path) {
+                dataframes::upload_file(store, &key, path).await?;
+            }
+        }
+    }
+    if sink.object_store.is_some() {
+        let _ = std::fs::remove_dir_all(&sink.output_dir);
+    }
 
     // return
     Ok(Some(results))
@@ -81,7 +184,9 @@ fn get_payloads(
     source: &Source,
     sink: &FileOutput,
     env: &ExecutionEnv,
-) -> Result<(Vec<PartitionPayload>, Vec<Partition>), CollectError> {
+    uploaded_paths: Option<HashSet<PathBuf>>,
+    loaded_chunks: Option<HashSet<(String, String)>>,
+) -> Result<(Vec<PartitionPayload>, Vec<DatasetPartition>), CollectError> {
     let semaphore = source
         .max_concurrent_chunks
         .map(|x| std::sync::Arc::new(tokio::sync::Semaphore::new(x as usize)));
@@ -90,14 +195,56 @@ fn get_payloads(
     let mut payloads = Vec::new();
     let mut skipping = Vec::new();
     let mut all_paths = HashSet::new();
-    for datatype in query.datatypes.clone().into_iter() {
-        for partition in query.partitions.clone().into_iter() {
+    let compacted_ranges = CompactedRanges::default();
+    // datasets of a partition are scheduled together, in the chunk order of the query
+    for partition in query.partitions.clone().into_iter() {
+        for datatype in query.datatypes.clone().into_iter() {
             let paths = sink.get_paths(query, &partition, Some(vec![datatype.clone()]))?;
-            if !sink.overwrite && paths.values().all(|path| path.exists()) {
-                skipping.push(partition);
+            let exists =
+                |(datatype, path): (&Datatype, &PathBuf)| match (&loaded_chunks, &uploaded_paths) {
+                    (Some(loaded_chunks), _) => loaded_chunks
+                        .contains(&(datatype.name(), dataframes::staged_chunk_name(path))),
+                    (None, Some(uploaded_paths)) => uploaded_paths.contains(path),
+                    // chunks missing blocks are collected again until they are complete
+                    (None, None) => {
+                        !dataframes::incomplete_marker_path(path).exists() &&
+                            (path.exists() ||
+                                dataframes::empty_marker_path(path).exists() ||
+                                compacted_ranges.covers(path))
+                    }
+                };
+            if !sink.overwrite && paths.iter().all(exists) {
+                skipping.push((partition.clone(), datatype.clone()));
                 continue
             }
 
+            // overwritten chunks would duplicate the rows of compacted files that cover them
+            if sink.overwrite && loaded_chunks.is_none() && uploaded_paths.is_none() {
+                for path in paths.values() {
+                    if let Some(compacted) = compacted_ranges.covering_file(path) {
+                        let message = format!(
+                            "{} is covered by compacted file {}, remove the compacted file to \
+                             collect its chunks again with --overwrite",
+                            path.display(),
+                            compacted.display()
+                        );
+                        return Err(err(&message))
+                    }
+                }
+            }
+
+            // rows of chunks loaded without some of their blocks are replaced when loaded again
+            let loaded_incomplete = |(datatype, path): (&Datatype, &PathBuf)| {
+                loaded_chunks.as_ref().is_some_and(|loaded_chunks| {
+                    loaded_chunks
+                        .contains(&(datatype.name(), dataframes::incomplete_chunk_name(path)))
+                })
+            };
+            let sink = FileOutput {
+                overwrite: sink.overwrite || paths.iter().any(loaded_incomplete),
+                ..sink.clone()
+            };
+
             // check for path collisions
             let paths_set: HashSet<_> = paths.clone().into_values().collect();
             if paths_set.intersection(&all_paths).next().is_none() {
@@ -114,9 +261,10 @@ fn get_payloads(
                 paths,
                 arc_query.clone(),
                 source.clone(),
-                sink.clone(),
+                sink,
                 env.clone(),
                 semaphore.clone(),
+                None,
             );
             payloads.push(payload);
         }
@@ -124,71 +272,672 @@ fn get_payloads(
     Ok((payloads, skipping))
 }
 
+/// staged paths of files already in the output object store, None when writing local files
+async fn get_uploaded_paths(
+    query: &Query,
+    sink: &FileOutput,
+) -> Result<Option<HashSet<PathBuf>>, CollectError> {
+    let store = match &sink.object_store {
+        Some(store) if !sink.overwrite => store,
+        Some(_) => return Ok(Some(HashSet::new())),
+        None => return Ok(None),
+    };
+    let mut paths = Vec::new();
+    for partition in query.partitions.iter() {
+        paths.extend(sink.get_paths(query, partition, None)?.into_values());
+    }
+    let checks = futures::stream::iter(paths).map(|path| async move {
+        let key = store.key(&sink.output_dir, &path).ok_or(err("invalid staging path"))?;
+        let marker = dataframes::empty_marker_path(&path);
+        let marker = store.key(&sink.output_dir, &marker).ok_or(err("invalid staging path"))?;
+        let incomplete = dataframes::incomplete_marker_path(&path);
+        let incomplete =
+            store.key(&sink.output_dir, &incomplete).ok_or(err("invalid staging path"))?;
+        let exists = (dataframes::object_exists(store, &key).await? ||
+            dataframes::object_exists(store, &marker).await?) &&
+            !dataframes::object_exists(store, &incomplete).await?;
+        Ok::<_, CollectError>((exists, path))
+    });
+    let mut uploaded_paths = HashSet::new();
+    let mut checks = checks.buffer_unordered(MAX_CONCURRENT_CHECKS);
+    while let Some(result) = checks.next().await {
+        if let (true, path) = result? {
+            uploaded_paths.insert(path);
+        }
+    }
+    Ok(Some(uploaded_paths))
+}
+
+/// chunks already loaded into the output database, None when writing plain files
+async fn get_loaded_chunks(
+    sink: &FileOutput,
+) -> Result<Option<HashSet<(String, String)>>, CollectError> {
+    match (&sink.duckdb, &sink.postgres) {
+        _ if sink.stdout => Ok(Some(HashSet::new())),
+        #[cfg(feature = "duckdb")]
+        (Some(db), _) => Ok(Some(dataframes::duckdb_loaded_chunks(db)?)),
+        #[cfg(not(feature = "duckdb"))]
+        (Some(_), _) => Err(err("duckdb output requires building cryo with the duckdb feature")),
+        #[cfg(feature = "postgres")]
+        (_, Some(postgres)) => Ok(Some(dataframes::postgres_loaded_chunks(postgres).await?)),
+        #[cfg(not(feature = "postgres"))]
+        (_, Some(_)) => {
+            Err(err("postgres output requires building cryo with the postgres feature"))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+fn spawn_chunk_writer(
+    sink: &FileOutput,
+) -> Result<Option<(dataframes::ChunkWriter, tokio::task::JoinHandle<()>)>, CollectError> {
+    match (&sink.duckdb, &sink.postgres) {
+        _ if sink.stdout => Ok(Some(dataframes::ChunkWriter::spawn(dataframes::stream_chunk))),
+        #[cfg(feature = "duckdb")]
+        (Some(db), _) => {
+            let db = db.clone();
+            Ok(Some(dataframes::ChunkWriter::spawn(move |chunk| {
+                dataframes::load_duckdb_chunk(&db, chunk)
+            })))
+        }
+        #[cfg(not(feature = "duckdb"))]
+        (Some(_), _) => Err(err("duckdb output requires building cryo with the duckdb feature")),
+        #[cfg(feature = "postgres")]
+        (_, Some(postgres)) => {
+            let postgres = postgres.clone();
+            Ok(Some(dataframes::ChunkWriter::spawn(move |chunk| {
+                let handle = tokio::runtime::Handle::current();
+                handle.block_on(dataframes::load_postgres_chunk(&postgres, chunk))
+            })))
+        }
+        #[cfg(not(feature = "postgres"))]
+        (_, Some(_)) => {
+            Err(err("postgres output requires building cryo with the postgres feature"))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// output columns of u256 values that are stored as binary or strings
+fn get_u256_columns(query: &Query, datatype: &Datatype) -> Result<Vec<String>, CollectError> {
+    let table = query.schemas.get_schema(datatype)?;
+    let mut columns = Vec::new();
+    for column in table.columns() {
+        if table.column_type(column) == Some(ColumnType::UInt256) {
+            for u256_type in table.u256_types.iter() {
+                if matches!(u256_type, U256Type::Binary | U256Type::String) {
+                    let name = column.to_string() + u256_type.suffix().as_str();
+                    columns.push(table.output_name(&name).to_string());
+                }
+            }
+        }
+    }
+    Ok(columns)
+}
+
 async fn freeze_partitions(
     env: &ExecutionEnv,
     payloads: Vec<PartitionPayload>,
-    skipped: Vec<Partition>,
+    skipped: Vec<DatasetPartition>,
 ) -> FreezeSummary {
+    env.emit(CollectionEvent::CollectionStarted { n_chunks: payloads.len() as u64 });
     if let Some(bar) = &env.bar {
-        bar.set_length(payloads.len() as u64);
         if let Some(payload) = &payloads.first() {
-            let (_, _, _, _, _, _, env, _) = payload;
+            let (_, _, _, _, _, _, env, _, _) = payload;
             let dt_start: DateTime<Local> = env.t_start.into();
             bar.set_message(format!("started at {}", dt_start.format("%Y-%m-%d %H:%M:%S%.3f")));
         }
     }
 
-    // spawn task for each partition
-    let mut futures = FuturesUnordered::new();
-    for payload in payloads.into_iter() {
-        futures.push(tokio::spawn(
-            async move { (payload.0.clone(), freeze_partition(payload).await) },
-        ));
+    // periodically log the settings of adaptive concurrency
+    let autoscale_log = match payloads.first() {
+        Some(payload) if env.verbose >= 1 && payload.4.autoscale_state().is_some() => {
+            Some(tokio::spawn(log_autoscale(payload.4.clone(), env.bar.clone())))
+        }
+        _ => None,
+    };
+
+    // shared block responses of a chunk are released once all of its datasets are collected
+    let source = payloads.first().map(|payload| payload.4.clone());
+    let mut n_pending: HashMap<(u64, u64), usize> = HashMap::new();
+    for payload in payloads.iter() {
+        if let Some(block_range) = block_range(&payload.0) {
+            *n_pending.entry(block_range).or_default() += 1;
+        }
     }
+    let n_pending = Mutex::new(n_pending);
+    let release = |partition: &Partition| {
+        if let (Some(source), Some((start, end))) = (&source, block_range(partition)) {
+            let mut n_pending = n_pending.lock().unwrap_or_else(|e| e.into_inner());
+            let n = n_pending.entry((start, end)).or_default();
+            *n = n.saturating_sub(1);
+            if *n == 0 {
+                source.fetcher.shared_fetches.release(start..=end);
+                source.fetcher.block_timestamps.release(start..=end);
+            }
+        }
+    };
+
+    // spawn task for each partition, starting chunks in the order of the payloads
+    let max_concurrent_chunks = source
+        .as_ref()
+        .and_then(|source| source.max_concurrent_chunks)
+        .map(|n| n.max(1) as usize)
+        .unwrap_or(usize::MAX);
+    let pool = dataframes::WritePool::spawn(env.write_threads);
+    let mut cancelled = Vec::new();
+    let unsupported: Mutex<Vec<UnsupportedDataset>> = Mutex::new(Vec::new());
+    let mut futures = futures::stream::iter(payloads)
+        .filter_map(|payload| {
+            // chunks are started lazily, so cancelling stops the chunks that have not started
+            if env.is_cancelled() {
+                cancelled.push((payload.0, payload.1));
+                return futures::future::ready(None)
+            }
+            // datasets that the endpoint cannot serve are not retried for each chunk
+            if let Ok(mut unsupported) = unsupported.lock() {
+                let dataset = payload.1.name();
+                if let Some(unsupported) = unsupported.iter_mut().find(|u| u.dataset == dataset) {
+                    release(&payload.0);
+                    unsupported.partitions.push(payload.0);
+                    return futures::future::ready(None)
+                }
+            }
+            futures::future::ready(Some(payload))
+        })
+        .map(move |payload| {
+            let pool = pool.clone();
+            let partition = payload.0.clone();
+            let chunk = partition.label(&payload.3.partitioned_by).unwrap_or_default();
+            let datatype = payload.1.clone();
+            let timings = payload.3.timings.clone();
+            let handle = tokio::spawn(async move {
+                let t_chunk = std::time::Instant::now();
+                let result = freeze_partition(payload, pool).await;
+                timings.add("chunk", t_chunk.elapsed());
+                result
+            });
+            async move { (partition, chunk, datatype, handle.await) }
+        })
+        .buffer_unordered(max_concurrent_chunks);
 
     // aggregate results
     let mut completed = Vec::new();
     let mut errored = Vec::new();
-    while let Some(result) = futures.next().await {
+    while let Some((partition, chunk, datatype, result)) = futures.next().await {
+        let result = result.unwrap_or_else(|_| Err(err("error joining chunks")));
+        release(&partition);
+        let dataset = datatype.name();
         match result {
-            Ok((partition, Ok(()))) => completed.push(partition),
-            Ok((partition, Err(e))) => errored.push((Some(partition), e)),
-            Err(_e) => errored.push((None, err("error joining chunks"))),
+            Ok(()) => completed.push((partition, datatype)),
+            Err(CollectError::MethodNotFound(message)) => {
+                let error = CollectError::MethodNotFound(message);
+                record_unsupported(env, &unsupported, partition, chunk, dataset, error)
+            }
+            Err(e) => {
+                env.emit(CollectionEvent::ChunkFailed { chunk, dataset, error: e.to_string() });
+                errored.push((Some((partition, datatype)), e))
+            }
         }
     }
 
+    if let Some(source) = &source {
+        source.fetcher.shared_fetches.clear();
+        source.fetcher.block_timestamps.clear();
+    }
+    if let Some(autoscale_log) = autoscale_log {
+        autoscale_log.abort();
+    }
     if let Some(bar) = &env.bar {
         bar.finish_and_clear();
     }
 
-    FreezeSummary { completed, errored, skipped }
+    drop(futures);
+    let unsupported = unsupported.into_inner().unwrap_or_else(|e| e.into_inner());
+    FreezeSummary { completed, errored, skipped, cancelled, unsupported }
 }
 
-async fn freeze_partition(payload: PartitionPayload) -> Result<(), CollectError> {
-    let (partition, datatype, paths, query, source, sink, env, semaphore) = payload;
+/// record a chunk whose dataset needs a method that the endpoint does not support, so that the
+/// remaining chunks of the dataset are not started
+fn record_unsupported(
+    env: &ExecutionEnv,
+    unsupported: &Mutex<Vec<UnsupportedDataset>>,
+    partition: Partition,
+    chunk: String,
+    dataset: String,
+    error: CollectError,
+) {
+    let mut unsupported = unsupported.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(unsupported) = unsupported.iter_mut().find(|u| u.dataset == dataset) {
+        unsupported.partitions.push(partition);
+        return
+    }
+    env.emit(CollectionEvent::ChunkFailed {
+        chunk,
+        dataset: dataset.clone(),
+        error: error.to_string(),
+    });
+    if env.verbose >= 1 {
+        let message = format!(
+            "{} is not supported by the endpoint, skipping its remaining chunks: {}",
+            dataset, error
+        );
+        match &env.bar {
+            Some(bar) => bar.println(message),
+            None => println!("{}", message),
+        }
+    }
+    unsupported.push(UnsupportedDataset { dataset, error, partitions: vec![partition] });
+}
+
+async fn log_autoscale(source: Arc<Source>, bar: Option<Arc<indicatif::ProgressBar>>) {
+    loop {
+        tokio::time::sleep(AUTOSCALE_LOG_INTERVAL).await;
+        if let Some(state) = source.autoscale_state() {
+            let message = format!(
+                "autoscale: {} concurrent requests, {:.1} requests per second, {:.0}ms latency, {:.1}% errors",
+                state.concurrency, state.requests_per_second, state.latency_ms, state.error_rate,
+            );
+            match &bar {
+                Some(bar) => bar.println(message),
+                None => println!("{}", message),
+            }
+        }
+    }
+}
+
+async fn freeze_partition(
+    payload: PartitionPayload,
+    pool: dataframes::WritePool,
+) -> Result<(), CollectError> {
+    let (partition, datatype, paths, query, source, sink, env, semaphore, writer) = payload;
 
     // acquire chunk semaphore
     let _permit = match &semaphore {
         Some(semaphore) => Some(semaphore.acquire().await),
         None => None,
     };
+    let chunk = partition.label(&query.partitioned_by).unwrap_or_default();
+    let dataset = datatype.name();
+    let t_start = std::time::Instant::now();
+    env.emit(CollectionEvent::ChunkStarted { chunk: chunk.clone(), dataset: dataset.clone() });
 
-    // collect data
-    let dfs = collect_partition(datatype, partition, query, source).await?;
+    // collect data and write dataframes to disk
+    let block_range = block_range(&partition);
+    let written = match sink.flush_threshold {
+        Some(_) => {
+            stream_partition_to_files(datatype, partition, &paths, &query, &source, &sink, &pool)
+                .await?
+        }
+        None => {
+            let (dfs, max_block_hash) =
+                collect::collect_chunk(datatype, partition, query.clone(), source.clone()).await?;
+            let mut chunks = Vec::new();
+            for (datatype, df) in dfs {
+                let path = chunk_path(&paths, &datatype)?.clone();
+                let sink = chunk_sink(
+                    &sink,
+                    &query,
+                    &source,
+                    &datatype,
+                    block_range,
+                    max_block_hash,
+                    true,
+                )?;
+                chunks.push((datatype, df, path, sink));
+            }
+            let timings = query.timings.clone();
+            pool.run(move || timings.time("write", || write_chunk_files(chunks))).await?
+        }
+    };
 
-    // write dataframes to disk
-    for (datatype, mut df) in dfs {
-        let path = paths.get(&datatype).ok_or_else(|| {
-            CollectError::CollectError("could not get path for datatype".to_string())
-        })?;
-        let result = dataframes::df_to_file(&mut df, path, &sink);
-        result.map_err(|_| CollectError::CollectError("error writing file".to_string()))?
-    }
+    let (mut rows, mut bytes) = (0, 0);
+    for (datatype, n_rows) in written {
+        // chunks missing blocks are marked so that they are collected again, loaded chunks are
+        // instead recorded as incomplete in the database
+        let missing_blocks = query.block_errors.missing_blocks(&datatype.name(), &chunk);
+        let data_path = chunk_path(&paths, &datatype)?;
+        let incomplete_marker = dataframes::incomplete_marker_path(data_path);
+        if writer.is_none() {
+            dataframes::write_incomplete_marker(data_path, &missing_blocks).map_err(write_error)?;
+        }
+
+        // skipped chunks without rows are recorded by their empty chunk marker
+        let path = match n_rows == 0 && sink.skips_empty_chunks() {
+            true => dataframes::empty_marker_path(chunk_path(&paths, &datatype)?),
+            false => chunk_path(&paths, &datatype)?.clone(),
+        };
+        let path = &path;
+        let is_manifest = sink.hive_partitioning.is_some() || sink.max_rows_per_file.is_some();
+        if writer.is_none() && sink.object_store.is_none() {
+            remove_superseded_buckets(&query, &sink, &datatype, data_path, block_range);
+        }
+        let n_bytes = dataframes::written_bytes(path, is_manifest);
+        env.emit(CollectionEvent::FileWritten {
+            path: path.clone(),
+            datatype: datatype.name(),
+            rows: n_rows,
+            bytes: n_bytes,
+        });
+        (rows, bytes) = (rows + n_rows, bytes + n_bytes);
 
-    // update progress bar
-    if let Some(bar) = env.bar {
-        bar.inc(1);
+        // upload staged file to object store, with the incomplete marker in place until the
+        // files of a complete chunk are uploaded
+        if let Some(store) = &sink.object_store {
+            let marker_key = store
+                .key(&sink.output_dir, &incomplete_marker)
+                .ok_or(err("invalid staging path"))?;
+            if !missing_blocks.is_empty() {
+                dataframes::upload_file(store, &marker_key, &incomplete_marker).await?;
+            }
+            let key = store.key(&sink.output_dir, path).ok_or(err("invalid staging path"))?;
+            dataframes::upload_file(store, &key, path).await?;
+            let sidecar = dataframes::sidecar_path(path);
+            if sidecar.exists() {
+                let key =
+                    store.key(&sink.output_dir, &sidecar).ok_or(err("invalid staging path"))?;
+                dataframes::upload_file(store, &key, &sidecar).await?;
+            }
+            if missing_blocks.is_empty() {
+                dataframes::delete_object(store, &marker_key).await?;
+            }
+        }
+
+        // load staged file into database
+        if let Some(writer) = &writer {
+            let chunk = dataframes::StagedChunk {
+                table: datatype.name(),
+                path: path.clone(),
+                block_range,
+                overwrite: sink.overwrite,
+                complete: missing_blocks.is_empty(),
+                u256_columns: get_u256_columns(&query, &datatype)?,
+            };
+            writer.load(chunk).await?
+        }
     }
 
+    // update counts and progress bar
+    let duration = t_start.elapsed();
+    env.emit(CollectionEvent::ChunkCompleted {
+        chunk,
+        dataset,
+        rows,
+        bytes,
+        block_range,
+        duration,
+    });
+
     Ok(())
 }
+
+/// first and last block of a partition
+fn block_range(partition: &Partition) -> Option<(u64, u64)> {
+    partition.stats().block_numbers.and_then(|stats| {
+        stats
+            .min_value
+            .and_then(|min_value| stats.max_value.map(|max_value| (min_value, max_value)))
+    })
+}
+
+/// collect partition in batches of rows, appending each batch to the parquet files of the
+/// partition as row groups, returning the number of rows written of each datatype
+async fn stream_partition_to_files(
+    datatype: MetaDatatype,
+    partition: Partition,
+    paths: &HashMap<Datatype, PathBuf>,
+    query: &Arc<Query>,
+    source: &Arc<Source>,
+    sink: &FileOutput,
+    pool: &dataframes::WritePool,
+) -> Result<Vec<(Datatype, u64)>, CollectError> {
+    let threshold = sink.flush_threshold.ok_or(err("flushed chunks require a flush threshold"))?;
+    let block_range = block_range(&partition);
+    let (sender, mut receiver) = mpsc::channel(1);
+    let collect =
+        stream_partition(datatype, partition, query.clone(), source.clone(), threshold, sender);
+
+    // files are written to temporary paths until every batch of the partition is collected
+    type BatchWriter = (Option<dataframes::RowGroupWriter>, PathBuf, u64, u64);
+    let mut writers: HashMap<Datatype, BatchWriter> = HashMap::new();
+    let write = async {
+        while let Some(dfs) = receiver.recv().await {
+            for (datatype, mut df) in dfs {
+                let (writer, tmp_path, n_rows, n_batches) = match writers.entry(datatype) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let tmp_path = chunk_path(paths, &datatype)?.with_extension("_tmp");
+                        entry.insert((None, tmp_path, 0, 0))
+                    }
+                };
+                let (batch_writer, batch_path, sink) =
+                    (writer.take(), tmp_path.clone(), sink.clone());
+                let timings = query.timings.clone();
+                let (batch_writer, batch_rows) = pool
+                    .run(move || {
+                        timings.time("write", || {
+                            let mut writer = match batch_writer {
+                                Some(writer) => writer,
+                                None => dataframes::RowGroupWriter::try_new(
+                                    &batch_path,
+                                    &df.schema(),
+                                    &sink,
+                                )
+                                .map_err(write_error)?,
+                            };
+                            writer.write(&mut df).map_err(write_error)?;
+                            Ok((writer, df.height() as u64))
+                        })
+                    })
+                    .await?;
+                *writer = Some(batch_writer);
+                *n_rows += batch_rows;
+                *n_batches += 1;
+            }
+        }
+        Ok::<(), CollectError>(())
+    };
+    // collection stops as soon as writing fails, rather than waiting on a receiver that is no
+    // longer drained
+    if let Err(e) = tokio::try_join!(collect, write) {
+        for (_, tmp_path, _, _) in writers.values() {
+            let _ = std::fs::remove_file(tmp_path);
+        }
+        return Err(e)
+    }
+
+    let mut written = Vec::new();
+    for (datatype, (writer, tmp_path, n_rows, n_batches)) in writers {
+        let writer = writer.ok_or(err("no file writer for flushed chunk"))?;
+        // rows are only sorted within each batch once a chunk is flushed more than once
+        let sorted = n_batches <= 1;
+        let schema = query.schemas.get_schema(&datatype)?;
+        if !sorted && schema.sort_columns.is_some() {
+            query.warnings.increment(&format!(
+                "{}: rows of chunks flushed in several batches are only sorted within each batch",
+                datatype.name()
+            ));
+        }
+        let chunk_sink = chunk_sink(sink, query, source, &datatype, block_range, None, sorted)?;
+        let path = chunk_path(paths, &datatype)?.clone();
+        let skip = n_rows == 0 && sink.skips_empty_chunks();
+        let timings = query.timings.clone();
+        pool.run(move || {
+            timings.time("write", || {
+                writer.finish().map_err(write_error)?;
+                if skip {
+                    let _ = std::fs::remove_file(&tmp_path);
+                    return dataframes::write_empty_marker(&path, false).map_err(write_error)
+                }
+                dataframes::add_key_value_metadata(&tmp_path, &chunk_sink.key_value_metadata)
+                    .map_err(write_error)?;
+                let _ = std::fs::remove_file(dataframes::empty_marker_path(&path));
+                std::fs::rename(&tmp_path, path).map_err(|_| err("error writing file"))
+            })
+        })
+        .await?;
+        written.push((datatype, n_rows));
+    }
+    Ok(written)
+}
+
+/// output path of the file of a datatype
+fn chunk_path<'a>(
+    paths: &'a HashMap<Datatype, PathBuf>,
+    datatype: &Datatype,
+) -> Result<&'a PathBuf, CollectError> {
+    paths
+        .get(datatype)
+        .ok_or_else(|| CollectError::CollectError("could not get path for datatype".to_string()))
+}
+
+/// output options of the file of a datatype, with the key-value metadata of its chunk
+fn chunk_sink(
+    sink: &FileOutput,
+    query: &Query,
+    source: &Source,
+    datatype: &Datatype,
+    block_range: Option<(u64, u64)>,
+    max_block_hash: Option<H256>,
+    sorted: bool,
+) -> Result<FileOutput, CollectError> {
+    let mut sink = sink.clone();
+    let schema = query.schemas.get_schema(datatype)?;
+    if let (Some(sort_columns), true) = (&schema.sort_columns, sorted) {
+        let sort_columns: Vec<&str> =
+            sort_columns.iter().map(|column| schema.output_name(column)).collect();
+        sink.key_value_metadata.push((SORT_ORDER_KEY.to_string(), sort_columns.join(",")));
+    }
+    sink.key_value_metadata.extend(dataframes::collection_metadata(
+        query,
+        source,
+        datatype,
+        block_range,
+    )?);
+    if let Some(hash) = max_block_hash {
+        let key = format!("{}max_block_hash", dataframes::METADATA_PREFIX);
+        sink.key_value_metadata.push((key, format!("0x{}", hex::encode(hash.as_bytes()))));
+    }
+    Ok(sink)
+}
+
+/// encode and write the dataframes of a chunk, returning the number of rows of each datatype
+fn write_chunk_files(
+    chunks: Vec<(Datatype, DataFrame, PathBuf, FileOutput)>,
+) -> Result<Vec<(Datatype, u64)>, CollectError> {
+    let mut written = Vec::new();
+    for (datatype, mut df, path, sink) in chunks {
+        if df.height() == 0 && sink.skips_empty_chunks() {
+            let is_manifest = sink.hive_partitioning.is_some() || sink.max_rows_per_file.is_some();
+            dataframes::write_empty_marker(&path, is_manifest).map_err(write_error)?;
+            written.push((datatype, 0));
+            continue
+        }
+        let result = match (&sink.hive_partitioning, sink.max_rows_per_file) {
+            (Some(hive), _) => dataframes::df_to_hive_partitions(&mut df, &path, &sink, hive),
+            (None, Some(_)) => dataframes::df_to_split_files(&mut df, &path, &sink),
+            (None, None) => dataframes::df_to_file(&mut df, &path, &sink),
+        };
+        result.map_err(write_error)?;
+        let _ = std::fs::remove_file(dataframes::empty_marker_path(&path));
+        written.push((datatype, df.height() as u64));
+    }
+    Ok(written)
+}
+
+/// remove chunks of earlier runs that only covered part of the time bucket of a written chunk
+/// and whose blocks are covered by it, e.g. the chunk of a day collected before the day was over
+fn remove_superseded_buckets(
+    query: &Query,
+    sink: &FileOutput,
+    datatype: &Datatype,
+    path: &Path,
+    block_range: Option<(u64, u64)>,
+) {
+    let bucket = query
+        .labels
+        .time_buckets
+        .iter()
+        .find(|bucket| block_range == Some((bucket.start_block, bucket.end_block)));
+    let (bucket, (start_block, end_block)) = match (bucket, block_range) {
+        (Some(bucket), Some(block_range)) => (bucket, block_range),
+        _ => return,
+    };
+    let prefix = format!("{}__{}_partial__", sink.chunk_prefix(datatype), bucket.bucket);
+    let extension = format!(".{}", sink.chunk_extension());
+    let entries = match std::fs::read_dir(sink.chunk_dir(datatype)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        // chunks written without rows are only recorded by their empty chunk marker
+        let filename = entry.file_name().to_string_lossy().to_string();
+        let filename = filename.strip_suffix(".empty").unwrap_or(&filename);
+        let range = filename
+            .strip_prefix(&prefix)
+            .and_then(|stub| stub.strip_suffix(&extension))
+            .and_then(|stub| stub.split_once("_to_"));
+        let covered = match range {
+            Some((start, end)) => matches!(
+                (start.parse::<u64>(), end.parse::<u64>()),
+                (Ok(start), Ok(end)) if start_block <= start && end <= end_block
+            ),
+            None => false,
+        };
+        let stale = entry.path().with_file_name(filename);
+        if covered && stale != path {
+            let is_manifest = sink.hive_partitioning.is_some() || sink.max_rows_per_file.is_some();
+            dataframes::remove_chunk_files(&stale, is_manifest);
+        }
+    }
+}
+
+fn write_error(e: FileError) -> CollectError {
+    match e {
+        FileError::UnsupportedColumn(message) => CollectError::CollectError(message),
+        _ => CollectError::CollectError("error writing file".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileFormat, QueryLabels, TimeBucket};
+
+    #[test]
+    fn test_remove_superseded_buckets() {
+        let dir = std::env::temp_dir().join(format!("cryo_buckets_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sink = FileOutput {
+            overwrite: false,
+            format: FileFormat::Csv,
+            ..FileOutput::test_output(&dir)
+        };
+        let bucket = TimeBucket {
+            bucket: "2024-01-31".to_string(),
+            partial: false,
+            start_timestamp: 0,
+            end_timestamp: 0,
+            start_block: 100,
+            end_block: 199,
+        };
+        let labels = QueryLabels { time_buckets: vec![bucket], ..Default::default() };
+        let query = Query { labels, ..Default::default() };
+        let files = [
+            "ethereum__blocks__2024-01-31_partial__00000100_to_00000150.csv",
+            "ethereum__blocks__2024-01-31_partial__00000150_to_00000160.csv.empty",
+            "ethereum__blocks__2024-01-31_partial__00000150_to_00000250.csv",
+            "ethereum__blocks__2024-01-30_partial__00000100_to_00000150.csv",
+            "ethereum__blocks__2024-01-31__00000100_to_00000199.csv",
+        ];
+        for file in files.iter() {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let path = dir.join(files[4]);
+        remove_superseded_buckets(&query, &sink, &Datatype::Blocks, &path, Some((100, 199)));
+        let exists: Vec<bool> = files.iter().map(|file| dir.join(file).exists()).collect();
+        assert_eq!(exists, vec![false, false, true, true, true]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -8,13 +8,20 @@
 ))]
 
 mod collect;
+mod compact;
+mod coverage;
 mod datasets;
+mod estimate;
 mod freeze;
 mod multi_datasets;
 mod types;
+mod verify;
 
-pub use collect::collect;
+pub use collect::{collect, collect_dataframes, stream_dataframes, CollectedChunk};
+pub use compact::{compact, CompactTarget, CompactedFile};
+pub use coverage::{covered_files, CoveredFile};
 pub use datasets::*;
 pub use freeze::freeze;
 pub use multi_datasets::*;
 pub use types::*;
+pub use verify::{verify_canonical, BlockHashes, CanonicalCheck, HashVerification};
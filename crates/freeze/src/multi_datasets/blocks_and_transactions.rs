@@ -18,21 +18,31 @@ impl ToDataFrames for BlocksAndTransactions {
         output.extend(transactions.create_dfs(schemas, chain_id)?);
         Ok(output)
     }
+
+    fn n_rows(&self) -> u64 {
+        self.0.n_rows() + self.1.n_rows()
+    }
+
+    fn datatypes() -> Vec<Datatype> {
+        MultiDatatype::BlocksAndTransactions.datatypes()
+    }
 }
 
 #[async_trait::async_trait]
 impl CollectByBlock for BlocksAndTransactions {
-    type Response = <Transactions as CollectByBlock>::Response;
+    type Response = (<Transactions as CollectByBlock>::Response, u64);
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
-        <Transactions as CollectByBlock>::extract(request, source, query).await
+        let chain_id = source.chain_id;
+        Ok((<Transactions as CollectByBlock>::extract(request, source, query).await?, chain_id))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let BlocksAndTransactions(blocks, transactions) = columns;
+        let (response, chain_id) = response;
         let (block, _, _) = response.clone();
         let schema = query.schemas.get_schema(&Datatype::Blocks)?;
-        blocks::process_block(block, blocks, schema)?;
+        blocks::process_block(block, chain_id, blocks, schema)?;
         <Transactions as CollectByBlock>::transform(response, transactions, query)?;
         Ok(())
     }
@@ -54,14 +64,14 @@ impl CollectByTransaction for BlocksAndTransactions {
             .get_block(block_number)
             .await?
             .ok_or(CollectError::CollectError("block not found".to_string()))?;
-        Ok((block, ((tx, receipt), exclude_failed, timestamp)))
+        Ok(((block, source.chain_id), ((tx, receipt), exclude_failed, timestamp)))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let BlocksAndTransactions(blocks, transactions) = columns;
-        let (block, ((tx, receipt), exclude_failed, timestamp)) = response;
+        let ((block, chain_id), ((tx, receipt), exclude_failed, timestamp)) = response;
         let schema = query.schemas.get_schema(&Datatype::Blocks)?;
-        blocks::process_block(block, blocks, schema)?;
+        blocks::process_block(block, chain_id, blocks, schema)?;
         let schema = query.schemas.get_schema(&Datatype::Transactions)?;
         transactions::process_transaction(
             tx,
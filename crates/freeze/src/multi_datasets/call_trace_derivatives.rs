@@ -8,6 +8,7 @@ use std::collections::HashMap;
 pub struct CallTraceDerivatives(
     contracts::Contracts,
     native_transfers::NativeTransfers,
+    selfdestructs::Selfdestructs,
     traces::Traces,
 );
 
@@ -17,7 +18,7 @@ impl ToDataFrames for CallTraceDerivatives {
         schemas: &HashMap<Datatype, Table>,
         chain_id: u64,
     ) -> R<HashMap<Datatype, DataFrame>> {
-        let CallTraceDerivatives(contracts, native_transfers, traces) = self;
+        let CallTraceDerivatives(contracts, native_transfers, selfdestructs, traces) = self;
         let mut output = HashMap::new();
         if schemas.contains_key(&Datatype::Contracts) {
             output.extend(contracts.create_dfs(schemas, chain_id)?);
@@ -25,57 +26,92 @@ impl ToDataFrames for CallTraceDerivatives {
         if schemas.contains_key(&Datatype::NativeTransfers) {
             output.extend(native_transfers.create_dfs(schemas, chain_id)?);
         }
+        if schemas.contains_key(&Datatype::Selfdestructs) {
+            output.extend(selfdestructs.create_dfs(schemas, chain_id)?);
+        }
         if schemas.contains_key(&Datatype::Traces) {
             output.extend(traces.create_dfs(schemas, chain_id)?);
         }
         Ok(output)
     }
+
+    fn n_rows(&self) -> u64 {
+        self.0.n_rows() + self.1.n_rows() + self.2.n_rows() + self.3.n_rows()
+    }
+
+    fn datatypes() -> Vec<Datatype> {
+        MultiDatatype::CallTraceDerivatives.datatypes()
+    }
 }
 
 #[async_trait::async_trait]
 impl CollectByBlock for CallTraceDerivatives {
-    type Response = Vec<Trace>;
+    type Response = (Vec<Trace>, CreationMethods);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_block(request.block_number()?.into()).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        match uses_creation_methods(&query) {
+            true => source.trace_block_with_creation_methods(block_number).await,
+            false => Ok((source.trace_block(block_number).await?, CreationMethods::new())),
+        }
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let (traces, creation_methods) = response;
         let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_call_trace_derivatives(traces, columns, &query.schemas)
+            if query.exclude_failed { traces::filter_failed_traces(traces) } else { traces };
+        process_call_trace_derivatives(traces, &creation_methods, columns, query)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for CallTraceDerivatives {
-    type Response = Vec<Trace>;
+    type Response = (Vec<Trace>, CreationMethods);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction(request.ethers_transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        match uses_creation_methods(&query) {
+            true => source.trace_transaction_with_creation_methods(tx_hash).await,
+            false => Ok((source.trace_transaction(tx_hash).await?, CreationMethods::new())),
+        }
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let (traces, creation_methods) = response;
         let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_call_trace_derivatives(traces, columns, &query.schemas)
+            if query.exclude_failed { traces::filter_failed_traces(traces) } else { traces };
+        process_call_trace_derivatives(traces, &creation_methods, columns, query)
     }
 }
 
+/// creation methods are only requested for the columns of contracts that use them, otherwise the
+/// traces are shared with the other datasets of the run
+fn uses_creation_methods(query: &Query) -> bool {
+    query
+        .schemas
+        .get(&Datatype::Contracts)
+        .is_some_and(|schema| schema.has_column("create_type") || schema.has_column("salt"))
+}
+
 fn process_call_trace_derivatives(
     response: Vec<Trace>,
+    creation_methods: &CreationMethods,
     columns: &mut CallTraceDerivatives,
-    schemas: &HashMap<Datatype, Table>,
+    query: &Query,
 ) -> R<()> {
-    let CallTraceDerivatives(contracts, native_transfers, traces) = columns;
+    let schemas = &query.schemas;
+    let CallTraceDerivatives(contracts, native_transfers, selfdestructs, traces) = columns;
     if schemas.contains_key(&Datatype::Contracts) {
-        contracts::process_contracts(&response, contracts, schemas)?;
+        contracts::process_contracts(&response, creation_methods, contracts, schemas)?;
     }
     if schemas.contains_key(&Datatype::NativeTransfers) {
         native_transfers::process_native_transfers(&response, native_transfers, schemas)?;
     }
+    if schemas.contains_key(&Datatype::Selfdestructs) {
+        selfdestructs::process_selfdestructs(&response, selfdestructs, schemas)?;
+    }
     if schemas.contains_key(&Datatype::Traces) {
-        traces::process_traces(&response, traces, schemas)?;
+        traces::process_traces(&response, traces, schemas, &query.trace_contract_filter)?;
     }
     Ok(())
 }
@@ -44,21 +44,40 @@ impl ToDataFrames for GethStateDiffs {
         }
         Ok(output)
     }
+
+    fn n_rows(&self) -> u64 {
+        let GethStateDiffs(balance_diffs, code_diffs, nonce_diffs, storage_diffs) = self;
+        balance_diffs.as_ref().map_or(0, |diffs| diffs.n_rows()) +
+            code_diffs.as_ref().map_or(0, |diffs| diffs.n_rows()) +
+            nonce_diffs.as_ref().map_or(0, |diffs| diffs.n_rows()) +
+            storage_diffs.as_ref().map_or(0, |diffs| diffs.n_rows())
+    }
+
+    fn datatypes() -> Vec<Datatype> {
+        MultiDatatype::GethStateDiffs.datatypes()
+    }
 }
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<ethers::types::DiffMode>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<ethers::types::DiffMode>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for GethStateDiffs {
     type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
-        let block_number = request.block_number()? as u32;
-        let include_txs = query.schemas.values().any(|x| x.has_column("transaction_hash"));
+        let block_number = request.block_number()?;
+        let include_txs = query
+            .schemas
+            .values()
+            .any(|x| x.has_column("transaction_hash") || x.has_column("transaction_index"));
         source.fetcher.geth_debug_trace_block_diffs(block_number, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        for datatype in query.schemas.keys() {
+            query.note_trace_positions(datatype, &response.1);
+        }
         let GethStateDiffs(ref mut balances, ref mut codes, ref mut nonces, ref mut storages) =
             columns;
         process_geth_diffs(
@@ -77,14 +96,20 @@ impl CollectByTransaction for GethStateDiffs {
     type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
-        let include_block_number = query.schemas.values().any(|x| x.has_column("transaction_hash"));
+        let include_transaction = query
+            .schemas
+            .values()
+            .any(|x| x.has_column("block_number") || x.has_column("transaction_index"));
         source
             .fetcher
-            .geth_debug_trace_transaction_diffs(request.transaction_hash()?, include_block_number)
+            .geth_debug_trace_transaction_diffs(request.transaction_hash()?, include_transaction)
             .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        for datatype in query.schemas.keys() {
+            query.note_trace_positions(datatype, &response.1);
+        }
         let GethStateDiffs(ref mut balances, ref mut codes, ref mut nonces, ref mut storages) =
             columns;
         process_geth_diffs(
@@ -106,15 +131,15 @@ pub(crate) fn process_geth_diffs(
     mut storages: Option<&mut GethStorageDiffs>,
     schemas: &Schemas,
 ) -> R<()> {
-    let (block_number, txs, traces) = response;
+    let (block_number, indices, txs, traces) = response;
     let balance_schema = schemas.get(&Datatype::GethBalanceDiffs);
     let code_schema = schemas.get(&Datatype::GethCodeDiffs);
     let nonce_schema = schemas.get(&Datatype::GethNonceDiffs);
     let storage_schema = schemas.get(&Datatype::GethStorageDiffs);
 
     let blank = &AccountState::default();
-    for (tx_index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
-        let index = &(*block_number, tx_index as u32, tx.clone());
+    for (position, ((trace, tx), tx_index)) in traces.iter().zip(txs).zip(indices).enumerate() {
+        let index = &(*block_number, tx_index.unwrap_or(position as u64), tx.clone());
         let addresses: Vec<_> = trace
             .pre
             .keys()
@@ -152,13 +177,13 @@ fn add_balances(
     post: Option<U256>,
     columns: &mut GethBalanceDiffs,
     schema: &Table,
-    index: &(Option<u32>, u32, Option<Vec<u8>>),
+    index: &(Option<u64>, u64, Option<Vec<u8>>),
 ) -> R<()> {
     let (from_value, to_value) = parse_pre_post(pre, post, U256::zero);
     let (block_number, transaction_index, transaction_hash) = index;
     columns.n_rows += 1;
     store!(schema, columns, block_number, *block_number);
-    store!(schema, columns, transaction_index, Some(*transaction_index as u64));
+    store!(schema, columns, transaction_index, Some(*transaction_index));
     store!(schema, columns, transaction_hash, transaction_hash.clone());
     store!(schema, columns, address, address.as_bytes().to_vec());
     store!(schema, columns, from_value, from_value);
@@ -172,7 +197,7 @@ fn add_codes(
     post: &Option<String>,
     columns: &mut GethCodeDiffs,
     schema: &Table,
-    index: &(Option<u32>, u32, Option<Vec<u8>>),
+    index: &(Option<u64>, u64, Option<Vec<u8>>),
 ) -> R<()> {
     let blank = String::new();
     let (from_value, to_value) = match (pre, post) {
@@ -184,7 +209,7 @@ fn add_codes(
     let (block_number, transaction_index, transaction_hash) = index;
     columns.n_rows += 1;
     store!(schema, columns, block_number, *block_number);
-    store!(schema, columns, transaction_index, Some(*transaction_index as u64));
+    store!(schema, columns, transaction_index, Some(*transaction_index));
     store!(schema, columns, transaction_hash, transaction_hash.clone());
     store!(schema, columns, address, address.as_bytes().to_vec());
     let from_value = if !from_value.is_empty() {
@@ -208,13 +233,13 @@ fn add_nonces(
     post: Option<U256>,
     columns: &mut GethNonceDiffs,
     schema: &Table,
-    index: &(Option<u32>, u32, Option<Vec<u8>>),
+    index: &(Option<u64>, u64, Option<Vec<u8>>),
 ) -> R<()> {
     let (from_value, to_value) = parse_pre_post(pre, post, U256::zero);
     let (block_number, transaction_index, transaction_hash) = index;
     columns.n_rows += 1;
     store!(schema, columns, block_number, *block_number);
-    store!(schema, columns, transaction_index, Some(*transaction_index as u64));
+    store!(schema, columns, transaction_index, Some(*transaction_index));
     store!(schema, columns, transaction_hash, transaction_hash.clone());
     store!(schema, columns, address, address.as_bytes().to_vec());
     store!(schema, columns, from_value, from_value);
@@ -228,7 +253,7 @@ fn add_storages(
     post: &Option<BTreeMap<H256, H256>>,
     columns: &mut GethStorageDiffs,
     schema: &Table,
-    index: &(Option<u32>, u32, Option<Vec<u8>>),
+    index: &(Option<u64>, u64, Option<Vec<u8>>),
 ) -> R<()> {
     let blank = BTreeMap::new();
     let (pre, post) = match (pre, post) {
@@ -255,7 +280,7 @@ fn add_storages(
         };
         columns.n_rows += 1;
         store!(schema, columns, block_number, *block_number);
-        store!(schema, columns, transaction_index, Some(*transaction_index as u64));
+        store!(schema, columns, transaction_index, Some(*transaction_index));
         store!(schema, columns, transaction_hash, transaction_hash.clone());
         store!(schema, columns, address, address.as_bytes().to_vec());
         store!(schema, columns, slot, slot.as_bytes().to_vec());
@@ -11,7 +11,8 @@ pub struct StateDiffs(
     storage_diffs::StorageDiffs,
 );
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<ethers::types::BlockTrace>);
 
 impl ToDataFrames for StateDiffs {
     fn create_dfs(
@@ -27,6 +28,14 @@ impl ToDataFrames for StateDiffs {
         output.extend(storages.create_dfs(schemas, chain_id)?);
         Ok(output)
     }
+
+    fn n_rows(&self) -> u64 {
+        self.0.n_rows() + self.1.n_rows() + self.2.n_rows() + self.3.n_rows()
+    }
+
+    fn datatypes() -> Vec<Datatype> {
+        MultiDatatype::StateDiffs.datatypes()
+    }
 }
 
 #[async_trait::async_trait]
@@ -34,12 +43,18 @@ impl CollectByBlock for StateDiffs {
     type Response = BlockTxsTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
-        let include_txs = query.schemas.values().any(|x| x.has_column("transaction_hash"));
-        source.fetcher.trace_block_state_diffs(request.block_number()? as u32, include_txs).await
+        let include_txs = query
+            .schemas
+            .values()
+            .any(|x| x.has_column("transaction_hash") || x.has_column("transaction_index"));
+        source.trace_block_state_diffs(request.block_number()?, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_diffs(response, columns, &query.schemas)
+        for datatype in query.schemas.keys() {
+            query.note_trace_positions(datatype, &response.1);
+        }
+        process_state_diffs(response, columns, &query.schemas, &query.slot_filter)
     }
 }
 
@@ -47,12 +62,19 @@ impl CollectByBlock for StateDiffs {
 impl CollectByTransaction for StateDiffs {
     type Response = BlockTxsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.fetcher.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let include_transaction = query
+            .schemas
+            .values()
+            .any(|x| x.has_column("block_number") || x.has_column("transaction_index"));
+        source.trace_transaction_state_diffs(request.transaction_hash()?, include_transaction).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_diffs(response, columns, &query.schemas)
+        for datatype in query.schemas.keys() {
+            query.note_trace_positions(datatype, &response.1);
+        }
+        process_state_diffs(response, columns, &query.schemas, &query.slot_filter)
     }
 }
 
@@ -60,11 +82,12 @@ fn process_state_diffs(
     response: BlockTxsTraces,
     columns: &mut StateDiffs,
     schemas: &HashMap<Datatype, Table>,
+    slot_filter: &Option<SlotFilter>,
 ) -> R<()> {
     let StateDiffs(balances, codes, nonces, storages) = columns;
     balance_diffs::process_balance_diffs(&response, balances, schemas)?;
     code_diffs::process_code_diffs(&response, codes, schemas)?;
     nonce_diffs::process_nonce_diffs(&response, nonces, schemas)?;
-    storage_diffs::process_storage_diffs(&response, storages, schemas)?;
+    storage_diffs::process_storage_diffs(&response, storages, schemas, slot_filter)?;
     Ok(())
 }
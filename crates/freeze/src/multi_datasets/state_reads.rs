@@ -12,7 +12,8 @@ pub struct StateReads(
     storage_reads::StorageReads,
 );
 
-type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<H160, AccountState>>);
+type BlockTxsTraces =
+    (Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<H160, AccountState>>);
 
 impl ToDataFrames for StateReads {
     fn create_dfs(
@@ -28,6 +29,14 @@ impl ToDataFrames for StateReads {
         output.extend(storages.create_dfs(schemas, chain_id)?);
         Ok(output)
     }
+
+    fn n_rows(&self) -> u64 {
+        self.0.n_rows() + self.1.n_rows() + self.2.n_rows() + self.3.n_rows()
+    }
+
+    fn datatypes() -> Vec<Datatype> {
+        MultiDatatype::StateReads.datatypes()
+    }
 }
 
 #[async_trait::async_trait]
@@ -37,15 +46,16 @@ impl CollectByBlock for StateReads {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::StorageReads).ok_or(err("schema not provided"))?;
-        let include_txs = schema.has_column("transaction_hash");
-        source
-            .fetcher
-            .geth_debug_trace_block_prestate(request.block_number()? as u32, include_txs)
-            .await
+        let include_txs =
+            schema.has_column("transaction_hash") || schema.has_column("transaction_index");
+        source.fetcher.geth_debug_trace_block_prestate(request.block_number()?, include_txs).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_reads(response, columns, &query.schemas)
+        for datatype in query.schemas.keys() {
+            query.note_trace_positions(datatype, &response.1);
+        }
+        process_state_reads(response, columns, &query.schemas, &query.slot_filter)
     }
 }
 
@@ -56,13 +66,17 @@ impl CollectByTransaction for StateReads {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema =
             query.schemas.get(&Datatype::StorageReads).ok_or(err("schema not provided"))?;
-        let include_block_number = schema.has_column("block_number");
+        let include_transaction =
+            schema.has_column("block_number") || schema.has_column("transaction_index");
         let tx = request.transaction_hash()?;
-        source.fetcher.geth_debug_trace_transaction_prestate(tx, include_block_number).await
+        source.fetcher.geth_debug_trace_transaction_prestate(tx, include_transaction).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_reads(response, columns, &query.schemas)
+        for datatype in query.schemas.keys() {
+            query.note_trace_positions(datatype, &response.1);
+        }
+        process_state_reads(response, columns, &query.schemas, &query.slot_filter)
     }
 }
 
@@ -70,11 +84,12 @@ fn process_state_reads(
     response: BlockTxsTraces,
     columns: &mut StateReads,
     schemas: &HashMap<Datatype, Table>,
+    slot_filter: &Option<SlotFilter>,
 ) -> R<()> {
     let StateReads(balances, codes, nonces, storages) = columns;
     balance_reads::process_balance_reads(&response, balances, schemas)?;
     code_reads::process_code_reads(&response, codes, schemas)?;
     nonce_reads::process_nonce_reads(&response, nonces, schemas)?;
-    storage_reads::process_storage_reads(&response, storages, schemas)?;
+    storage_reads::process_storage_reads(&response, storages, schemas, slot_filter)?;
     Ok(())
 }
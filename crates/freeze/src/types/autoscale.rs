@@ -0,0 +1,259 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// number of concurrent requests that adaptive concurrency starts from
+const INITIAL_CONCURRENCY: usize = 4;
+
+/// fraction of errored requests of a window above which concurrency is decreased
+const MAX_ERROR_RATE: f64 = 0.05;
+
+/// ratio of the latency to the lowest latency above which concurrency is decreased
+const MAX_LATENCY_RATIO: f64 = 3.0;
+
+/// weight of each new latency in the moving average of latencies
+const LATENCY_WEIGHT: f64 = 0.2;
+
+/// outcome of a request, as seen by adaptive concurrency
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RequestOutcome {
+    Success,
+    RateLimited,
+    Error,
+}
+
+/// current settings of adaptive concurrency
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct AutoscaleState {
+    /// number of concurrent requests
+    pub concurrency: u64,
+    /// maximum number of concurrent requests
+    pub max_concurrency: u64,
+    /// moving average of request latency, in milliseconds
+    pub latency_ms: f64,
+    /// percent of requests of the current window that errored
+    pub error_rate: f64,
+    /// average number of requests per second since the start of collection
+    pub requests_per_second: f64,
+    /// number of times concurrency was decreased
+    pub decreases: u64,
+}
+
+#[derive(Debug)]
+struct Controller {
+    limit: usize,
+    max: usize,
+    /// permits to forget when released, after concurrency is decreased
+    debt: usize,
+    /// concurrency doubles each window until it is first decreased
+    slow_start: bool,
+    window_requests: usize,
+    window_errors: usize,
+    requests_since_decrease: usize,
+    latency: Option<f64>,
+    min_latency: Option<f64>,
+    n_requests: u64,
+    decreases: u64,
+    t_start: Instant,
+}
+
+/// adaptive concurrency, which adds concurrent requests while latencies and error rates stay low
+/// and halves them on rate limits, error bursts, and latency spikes
+#[derive(Debug)]
+pub struct Autoscaler {
+    semaphore: Semaphore,
+    controller: Mutex<Controller>,
+}
+
+/// permit to send a request, which is forgotten on release if concurrency has been decreased
+pub(crate) struct AutoscalePermit<'a> {
+    autoscaler: &'a Autoscaler,
+    permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for AutoscalePermit<'_> {
+    fn drop(&mut self) {
+        if let (Some(permit), Ok(mut controller)) =
+            (self.permit.take(), self.autoscaler.controller.lock())
+        {
+            if controller.debt > 0 {
+                controller.debt -= 1;
+                permit.forget();
+            }
+        }
+    }
+}
+
+impl Autoscaler {
+    /// create adaptive concurrency of at most max_concurrency requests
+    pub fn new(max_concurrency: u64) -> Autoscaler {
+        let max = (max_concurrency as usize).max(1);
+        let limit = INITIAL_CONCURRENCY.min(max);
+        Autoscaler {
+            semaphore: Semaphore::new(limit),
+            controller: Mutex::new(Controller {
+                limit,
+                max,
+                debt: 0,
+                slow_start: true,
+                window_requests: 0,
+                window_errors: 0,
+                requests_since_decrease: 0,
+                latency: None,
+                min_latency: None,
+                n_requests: 0,
+                decreases: 0,
+                t_start: Instant::now(),
+            }),
+        }
+    }
+
+    pub(crate) async fn acquire(&self) -> AutoscalePermit<'_> {
+        AutoscalePermit { autoscaler: self, permit: self.semaphore.acquire().await.ok() }
+    }
+
+    /// adjust concurrency to the outcome of a request
+    pub(crate) fn record(&self, latency: Duration, outcome: RequestOutcome) {
+        let mut controller = match self.controller.lock() {
+            Ok(controller) => controller,
+            Err(_) => return,
+        };
+        controller.n_requests += 1;
+        controller.window_requests += 1;
+        controller.requests_since_decrease += 1;
+        match outcome {
+            RequestOutcome::Success => {
+                let latency = latency.as_secs_f64();
+                let average = match controller.latency {
+                    Some(average) => (1.0 - LATENCY_WEIGHT) * average + LATENCY_WEIGHT * latency,
+                    None => latency,
+                };
+                controller.latency = Some(average);
+                controller.min_latency =
+                    Some(controller.min_latency.unwrap_or(average).min(average));
+            }
+            RequestOutcome::RateLimited | RequestOutcome::Error => controller.window_errors += 1,
+        }
+
+        // concurrency is decreased at most once per window of requests
+        let can_decrease = controller.requests_since_decrease >= controller.limit;
+        let window_done = controller.window_requests >= controller.limit;
+        let error_rate = controller.window_errors as f64 / controller.window_requests as f64;
+        let latency_spike = match (controller.latency, controller.min_latency) {
+            (Some(latency), Some(min_latency)) => latency > MAX_LATENCY_RATIO * min_latency,
+            _ => false,
+        };
+        let congested = outcome == RequestOutcome::RateLimited ||
+            (window_done && (error_rate > MAX_ERROR_RATE || latency_spike));
+        if congested && can_decrease {
+            let limit = (controller.limit / 2).max(1);
+            controller.debt += controller.limit - limit;
+            controller.limit = limit;
+            controller.slow_start = false;
+            controller.requests_since_decrease = 0;
+            controller.decreases += 1;
+            controller.reset_window();
+        } else if window_done && !congested {
+            let limit = match controller.slow_start {
+                true => controller.limit * 2,
+                false => controller.limit + 1,
+            }
+            .min(controller.max);
+            let mut added = limit - controller.limit;
+            controller.limit = limit;
+            let repaid = added.min(controller.debt);
+            controller.debt -= repaid;
+            added -= repaid;
+            self.semaphore.add_permits(added);
+            controller.reset_window();
+        } else if window_done {
+            controller.reset_window();
+        }
+    }
+
+    /// current settings of adaptive concurrency
+    pub fn state(&self) -> AutoscaleState {
+        let controller = match self.controller.lock() {
+            Ok(controller) => controller,
+            Err(_) => return AutoscaleState::default(),
+        };
+        let elapsed = controller.t_start.elapsed().as_secs_f64();
+        AutoscaleState {
+            concurrency: controller.limit as u64,
+            max_concurrency: controller.max as u64,
+            latency_ms: controller.latency.unwrap_or(0.0) * 1000.0,
+            error_rate: match controller.window_requests {
+                0 => 0.0,
+                n => 100.0 * controller.window_errors as f64 / n as f64,
+            },
+            requests_per_second: match elapsed > 0.0 {
+                true => controller.n_requests as f64 / elapsed,
+                false => 0.0,
+            },
+            decreases: controller.decreases,
+        }
+    }
+}
+
+impl Controller {
+    fn reset_window(&mut self) {
+        self.window_requests = 0;
+        self.window_errors = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_autoscaler() {
+        let autoscaler = Autoscaler::new(20);
+        let fast = Duration::from_millis(10);
+        let record_window = |outcome| {
+            let limit = autoscaler.state().concurrency;
+            for _ in 0..limit {
+                autoscaler.record(fast, outcome);
+            }
+        };
+
+        // concurrency doubles until it is first decreased
+        assert_eq!(autoscaler.state().concurrency, 4);
+        record_window(RequestOutcome::Success);
+        assert_eq!(autoscaler.state().concurrency, 8);
+        record_window(RequestOutcome::Success);
+        record_window(RequestOutcome::Success);
+        assert_eq!(autoscaler.state().concurrency, 20);
+
+        // rate limits halve concurrency
+        autoscaler.record(fast, RequestOutcome::RateLimited);
+        assert_eq!(autoscaler.state().concurrency, 10);
+
+        // then concurrency increases additively
+        for _ in 0..10 {
+            autoscaler.record(fast, RequestOutcome::Success);
+        }
+        assert_eq!(autoscaler.state().concurrency, 11);
+
+        // permits beyond the decreased concurrency are forgotten when released
+        let permits: Vec<_> =
+            futures::future::join_all((0..11).map(|_| autoscaler.acquire())).await;
+        assert_eq!(autoscaler.semaphore.available_permits(), 9);
+        drop(permits);
+        assert_eq!(autoscaler.semaphore.available_permits(), 11);
+
+        // latency spikes decrease concurrency
+        for _ in 0..11 {
+            autoscaler.record(Duration::from_millis(100), RequestOutcome::Success);
+        }
+        assert_eq!(autoscaler.state().concurrency, 5);
+
+        // concurrency is decreased at most once per window
+        autoscaler.record(fast, RequestOutcome::RateLimited);
+        assert_eq!(autoscaler.state().concurrency, 5);
+        assert_eq!(autoscaler.state().decreases, 2);
+    }
+}
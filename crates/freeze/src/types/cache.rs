@@ -0,0 +1,396 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{err, CollectError};
+
+/// fewest blocks behind the tip that a response must be to be cached, even without a reorg buffer
+const MIN_REORG_BUFFER: u64 = 64;
+
+/// time that the block number of the tip is reused before it is fetched again
+const TIP_REFRESH: Duration = Duration::from_secs(60);
+
+/// fraction of the size cap that the cache is pruned to once it exceeds the cap
+const PRUNE_FRACTION: f64 = 0.9;
+
+/// where the block number of a request is found, for methods whose responses never change once
+/// their block is final
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BlockParam {
+    /// block number is a parameter at this position
+    Index(usize),
+    /// block number is the toBlock field of a filter
+    Filter,
+    /// block number is in the response, for requests by hash
+    Response,
+}
+
+fn block_param(method: &str) -> Option<BlockParam> {
+    match method {
+        "eth_getBlockByNumber" |
+        "eth_getBlockReceipts" |
+        "eth_getBlobSidecars" |
+        "trace_block" |
+        "trace_replayBlockTransactions" |
+        "debug_traceBlockByNumber" => Some(BlockParam::Index(0)),
+        "eth_call" | "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" => {
+            Some(BlockParam::Index(1))
+        }
+        "eth_getStorageAt" => Some(BlockParam::Index(2)),
+        "eth_getLogs" | "trace_filter" => Some(BlockParam::Filter),
+        "eth_getBlockByHash" |
+        "eth_getTransactionByHash" |
+        "eth_getTransactionReceipt" |
+        "trace_transaction" => Some(BlockParam::Response),
+        _ => None,
+    }
+}
+
+/// block number of a hex quantity, block tags such as latest are not block numbers
+fn parse_block_number(value: &serde_json::Value) -> Option<u64> {
+    let hex = value.as_str()?.strip_prefix("0x")?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// block that a response belongs to, if the response can be cached
+fn response_block(
+    method: &str,
+    params: &Option<serde_json::Value>,
+    response: &serde_json::Value,
+) -> Option<u64> {
+    if response.is_null() {
+        return None
+    }
+    match block_param(method)? {
+        BlockParam::Index(index) => parse_block_number(params.as_ref()?.get(index)?),
+        BlockParam::Filter => parse_block_number(params.as_ref()?.get(0)?.get("toBlock")?),
+        BlockParam::Response => {
+            let item = match response {
+                serde_json::Value::Array(items) => items.last()?,
+                item => item,
+            };
+            parse_block_number(item.get("blockNumber").or(item.get("number"))?)
+        }
+    }
+}
+
+/// on-disk cache of the responses of rpc requests for final blocks
+///
+/// responses are stored as json files under `<dir>/<chain_id>/<method>/`, named by a hash of
+/// their params, once the cache exceeds its size cap the least recently used responses are removed
+#[derive(Debug)]
+pub struct ResponseCache {
+    root: PathBuf,
+    dir: PathBuf,
+    max_bytes: u64,
+    reorg_buffer: u64,
+    n_bytes: Arc<AtomicU64>,
+    tip: Mutex<Option<(u64, Instant)>>,
+    pruning: Arc<AtomicBool>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    /// open the cache of a chain, blocks within reorg_buffer blocks of the tip are not cached
+    pub fn open(
+        root: &Path,
+        chain_id: u64,
+        max_bytes: u64,
+        reorg_buffer: u64,
+    ) -> Result<ResponseCache, CollectError> {
+        let dir = root.join(chain_id.to_string());
+        std::fs::create_dir_all(&dir)
+            .map_err(|_| err(&format!("could not create cache dir {}", dir.display())))?;
+        let n_bytes = cache_stats(root)?.n_bytes;
+        Ok(ResponseCache {
+            root: root.to_path_buf(),
+            dir,
+            max_bytes,
+            reorg_buffer: reorg_buffer.max(MIN_REORG_BUFFER),
+            n_bytes: Arc::new(AtomicU64::new(n_bytes)),
+            tip: Mutex::new(None),
+            pruning: Arc::new(AtomicBool::new(false)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    /// whether responses of a method can be cached
+    pub fn is_cached_method(&self, method: &str) -> bool {
+        block_param(method).is_some()
+    }
+
+    /// number of requests that were answered from the cache and that were not
+    pub fn hits_and_misses(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::SeqCst), self.misses.load(Ordering::SeqCst))
+    }
+
+    fn path(&self, method: &str, params: &Option<serde_json::Value>) -> PathBuf {
+        let params = params.as_ref().map(|params| params.to_string()).unwrap_or_default();
+        let hash = hex::encode(Sha256::digest(params.as_bytes()));
+        self.dir.join(method).join(format!("{}.json", hash))
+    }
+
+    /// cached response of a request, marking it as recently used
+    pub(crate) fn get(
+        &self,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        let path = self.path(method, params);
+        let response =
+            std::fs::read(&path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        match response {
+            Some(response) => {
+                self.hits.fetch_add(1, Ordering::SeqCst);
+                if let Ok(file) = File::options().write(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Some(response)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::SeqCst);
+                None
+            }
+        }
+    }
+
+    /// block number of the tip, if fetched recently
+    pub(crate) fn tip(&self) -> Option<u64> {
+        let tip = self.tip.lock().ok().and_then(|tip| *tip)?;
+        (tip.1.elapsed() < TIP_REFRESH).then_some(tip.0)
+    }
+
+    pub(crate) fn set_tip(&self, tip: u64) {
+        if let Ok(mut current) = self.tip.lock() {
+            *current = Some((tip, Instant::now()));
+        }
+    }
+
+    /// store a response if its block is older than the reorg buffer
+    pub(crate) fn put(
+        &self,
+        method: &str,
+        params: &Option<serde_json::Value>,
+        response: &serde_json::Value,
+        tip: u64,
+    ) {
+        match response_block(method, params, response) {
+            Some(block) if block + self.reorg_buffer <= tip => {}
+            _ => return,
+        }
+        let path = self.path(method, params);
+        let bytes = response.to_string().into_bytes();
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = path.parent().map(std::fs::create_dir_all).unwrap_or(Ok(())).is_ok() &&
+            std::fs::write(&tmp_path, &bytes).is_ok() &&
+            std::fs::rename(&tmp_path, &path).is_ok();
+        if !written {
+            let _ = std::fs::remove_file(&tmp_path);
+            return
+        }
+        let n_bytes = self.n_bytes.fetch_add(bytes.len() as u64, Ordering::SeqCst);
+        if n_bytes + bytes.len() as u64 > self.max_bytes &&
+            !self.pruning.swap(true, Ordering::SeqCst)
+        {
+            let root = self.root.clone();
+            let target = (self.max_bytes as f64 * PRUNE_FRACTION) as u64;
+            let (n_bytes, pruning) = (self.n_bytes.clone(), self.pruning.clone());
+            let prune = move || {
+                if prune_cache(&root, target).is_ok() {
+                    let n_bytes_left = cache_stats(&root).map(|stats| stats.n_bytes).unwrap_or(0);
+                    n_bytes.store(n_bytes_left, Ordering::SeqCst);
+                }
+                pruning.store(false, Ordering::SeqCst);
+            };
+            // pruning lists the whole cache, so it runs outside of the task of the request
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => drop(handle.spawn_blocking(prune)),
+                Err(_) => prune(),
+            }
+        }
+    }
+}
+
+/// number and size of the cached responses of a method
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheEntries {
+    /// number of cached responses
+    pub n_entries: u64,
+    /// total size of cached responses, in bytes
+    pub n_bytes: u64,
+}
+
+/// contents of a response cache
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// number of cached responses
+    pub n_entries: u64,
+    /// total size of cached responses, in bytes
+    pub n_bytes: u64,
+    /// cached responses of each chain id and method
+    pub methods: BTreeMap<(u64, String), CacheEntries>,
+    /// time of the least recent use of a cached response
+    pub oldest: Option<SystemTime>,
+}
+
+/// chain id, method, path, size, and last use of each cached response
+fn list_cache_files(root: &Path) -> Vec<(u64, String, PathBuf, u64, SystemTime)> {
+    let read_dir = |dir: &Path| -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|entry| entry.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default()
+    };
+    let mut files = Vec::new();
+    for chain_dir in read_dir(root).into_iter() {
+        let chain_id = match chain_dir.file_name().and_then(|name| name.to_str()) {
+            Some(name) => match name.parse::<u64>() {
+                Ok(chain_id) => chain_id,
+                Err(_) => continue,
+            },
+            None => continue,
+        };
+        for method_dir in read_dir(&chain_dir).into_iter() {
+            let method = match method_dir.file_name().and_then(|name| name.to_str()) {
+                Some(method) => method.to_string(),
+                None => continue,
+            };
+            for path in read_dir(&method_dir).into_iter() {
+                if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+                    continue
+                }
+                if let Ok(metadata) = std::fs::metadata(&path) {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    files.push((chain_id, method.clone(), path, metadata.len(), modified));
+                }
+            }
+        }
+    }
+    files
+}
+
+/// count the cached responses of a cache directory
+pub fn cache_stats(root: &Path) -> Result<CacheStats, CollectError> {
+    if !root.is_dir() {
+        return Err(err(&format!("cache dir {} does not exist", root.display())))
+    }
+    let mut stats = CacheStats::default();
+    for (chain_id, method, _, n_bytes, modified) in list_cache_files(root).into_iter() {
+        stats.n_entries += 1;
+        stats.n_bytes += n_bytes;
+        let entries = stats.methods.entry((chain_id, method)).or_default();
+        entries.n_entries += 1;
+        entries.n_bytes += n_bytes;
+        stats.oldest = Some(stats.oldest.map(|oldest| oldest.min(modified)).unwrap_or(modified));
+    }
+    Ok(stats)
+}
+
+/// remove the least recently used responses of a cache directory until it is at most max_bytes,
+/// returning the number and size of the removed responses
+pub fn prune_cache(root: &Path, max_bytes: u64) -> Result<CacheEntries, CollectError> {
+    let mut files = list_cache_files(root);
+    let mut n_bytes: u64 = files.iter().map(|file| file.3).sum();
+    files.sort_by_key(|file| file.4);
+    let mut removed = CacheEntries::default();
+    for (_, _, path, size, _) in files.into_iter() {
+        if n_bytes <= max_bytes {
+            break
+        }
+        std::fs::remove_file(&path)
+            .map_err(|_| err(&format!("could not remove {}", path.display())))?;
+        n_bytes -= size;
+        removed.n_entries += 1;
+        removed.n_bytes += size;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_block() {
+        let params = Some(json!(["0x10", false]));
+        let block = json!({"number": "0x10"});
+        assert_eq!(response_block("eth_getBlockByNumber", &params, &block), Some(16));
+        assert_eq!(response_block("eth_getBlockByNumber", &params, &serde_json::Value::Null), None);
+        let params = Some(json!(["latest", false]));
+        assert_eq!(response_block("eth_getBlockByNumber", &params, &block), None);
+
+        let params = Some(json!([{"fromBlock": "0x1", "toBlock": "0x20"}]));
+        assert_eq!(response_block("eth_getLogs", &params, &json!([])), Some(32));
+        let params = Some(json!([{"fromBlock": "0x1"}]));
+        assert_eq!(response_block("eth_getLogs", &params, &json!([])), None);
+
+        let params = Some(json!(["0xabcd"]));
+        let receipt = json!({"blockNumber": "0x5"});
+        assert_eq!(response_block("eth_getTransactionReceipt", &params, &receipt), Some(5));
+        let pending = json!({"blockNumber": null});
+        assert_eq!(response_block("eth_getTransactionByHash", &params, &pending), None);
+        assert_eq!(response_block("eth_blockNumber", &None, &json!("0x5")), None);
+    }
+
+    #[test]
+    fn test_response_cache() {
+        let root = std::env::temp_dir().join(format!("cryo_cache_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let cache = ResponseCache::open(&root, 1, 1 << 20, 0).unwrap();
+        let params = Some(json!(["0x10", false]));
+        let block = json!({"number": "0x10", "hash": "0x01"});
+
+        // blocks within the reorg buffer of the tip are not cached
+        cache.put("eth_getBlockByNumber", &params, &block, 16 + MIN_REORG_BUFFER - 1);
+        assert_eq!(cache.get("eth_getBlockByNumber", &params), None);
+        cache.put("eth_getBlockByNumber", &params, &block, 16 + MIN_REORG_BUFFER);
+        assert_eq!(cache.get("eth_getBlockByNumber", &params), Some(block.clone()));
+        assert_eq!(cache.hits_and_misses(), (1, 1));
+
+        let stats = cache_stats(&root).unwrap();
+        assert_eq!(stats.n_entries, 1);
+        assert_eq!(
+            stats.methods.keys().collect::<Vec<_>>(),
+            vec![&(1, "eth_getBlockByNumber".into())]
+        );
+
+        // least recently used responses are pruned first
+        let params2 = Some(json!(["0x11", false]));
+        cache.put("eth_getBlockByNumber", &params2, &json!({"number": "0x11"}), 1000);
+        let file = File::options().write(true).open(cache.path("eth_getBlockByNumber", &params2));
+        file.unwrap().set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        let removed = prune_cache(&root, block.to_string().len() as u64).unwrap();
+        assert_eq!(removed.n_entries, 1);
+        assert!(cache.get("eth_getBlockByNumber", &params).is_some());
+        assert!(cache.get("eth_getBlockByNumber", &params2).is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_background_pruning() {
+        let root = std::env::temp_dir().join(format!("cryo_prune_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let block = |n: u64| json!({"number": format!("0x{:x}", n)});
+        let cache = ResponseCache::open(&root, 1, block(1).to_string().len() as u64, 0).unwrap();
+        for n in 1..=3 {
+            let params = Some(json!([format!("0x{:x}", n), false]));
+            cache.put("eth_getBlockByNumber", &params, &block(n), 1000);
+        }
+        while cache.pruning.load(Ordering::SeqCst) {
+            tokio::task::yield_now().await;
+        }
+        assert!(cache_stats(&root).unwrap().n_entries < 3);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
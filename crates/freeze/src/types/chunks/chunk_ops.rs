@@ -49,7 +49,7 @@ pub trait ChunkData: Sized {
             }
             None => vec![network_name, datatype.name(), stub],
         };
-        let filename = format!("{}.{}", pieces.join("__"), file_output.format.as_str());
+        let filename = format!("{}.{}", pieces.join("__"), file_output.extension());
         Ok(file_output.output_dir.join(filename))
     }
 
@@ -1,5 +1,8 @@
-use super::collect_generic::{fetch_partition, join_partition_handles};
-use crate::{CollectError, Datatype, Params, Partition, Query, Source, ToDataFrames};
+use super::collect_generic::{fetch_partition, fetch_partition_multicall, join_partition_handles};
+use crate::{
+    err, CollectError, Datatype, FlushThreshold, Params, Partition, Query, Source, ToDataFrames,
+};
+use ethers::prelude::*;
 use polars::prelude::*;
 use std::collections::HashMap;
 use tokio::sync::mpsc;
@@ -29,20 +32,7 @@ pub trait CollectByBlock: 'static + Send + Default + ToDataFrames {
         query: Arc<Query>,
         inner_request_size: Option<u64>,
     ) -> R<HashMap<Datatype, DataFrame>> {
-        let (sender, receiver) = mpsc::channel(1);
-        let chain_id = source.chain_id;
-        let handles = fetch_partition(
-            Self::extract,
-            partition,
-            source,
-            inner_request_size,
-            query.clone(),
-            sender,
-        )
-        .await?;
-        let columns = Self::transform_channel(receiver, &query).await?;
-        join_partition_handles(handles).await?;
-        columns.create_dfs(&query.schemas, chain_id)
+        collect_partition_by_block::<Self>(partition, source, query, inner_request_size).await
     }
 
     /// convert block-derived data to dataframe
@@ -53,15 +43,204 @@ pub trait CollectByBlock: 'static + Send + Default + ToDataFrames {
         let mut columns = Self::default();
         while let Some(message) = receiver.recv().await {
             match message {
-                Ok(message) => Self::transform(message, &mut columns, query)?,
+                Ok(message) => query
+                    .timings
+                    .time("transform", || Self::transform(message, &mut columns, query))?,
                 Err(e) => return Err(e),
             }
         }
         Ok(columns)
     }
 
+    /// collect data into batches of DataFrames, flushing the columns whenever they exceed
+    /// threshold, or into a single batch if the dataset cannot be flushed
+    async fn stream_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+        threshold: FlushThreshold,
+        sender: mpsc::Sender<HashMap<Datatype, DataFrame>>,
+    ) -> R<()> {
+        if !Self::can_flush() {
+            let dfs = Self::collect_by_block(partition, source, query, inner_request_size).await?;
+            return sender.send(dfs).await.map_err(|_| err("tokio mpsc send failure"))
+        }
+        stream_partition_by_block::<Self>(
+            partition,
+            source,
+            query,
+            inner_request_size,
+            threshold,
+            sender,
+        )
+        .await
+    }
+
+    /// whether columns can be flushed before the chunk ends, false for datasets whose rows depend
+    /// on every response of the chunk
+    fn can_flush() -> bool {
+        true
+    }
+
     /// whether data can be collected by block
     fn can_collect_by_block() -> bool {
         std::any::type_name::<Self::Response>() != "()"
     }
 }
+
+/// collect partition by extracting each request separately
+pub(crate) async fn collect_partition_by_block<T: CollectByBlock>(
+    partition: Partition,
+    source: Arc<Source>,
+    query: Arc<Query>,
+    inner_request_size: Option<u64>,
+) -> R<HashMap<Datatype, DataFrame>> {
+    let (sender, receiver) = mpsc::channel(1);
+    let chain_id = source.chain_id;
+    let handles = fetch_partition(
+        T::extract,
+        T::datatypes(),
+        partition,
+        source,
+        inner_request_size,
+        query.clone(),
+        sender,
+    )
+    .await?;
+    let columns = T::transform_channel(receiver, &query).await?;
+    join_partition_handles(handles).await?;
+    query.timings.time("transform", || columns.create_dfs(&query.schemas, chain_id))
+}
+
+/// number of rows flushed first when flushing by bytes, which sets the bytes per row
+const CALIBRATION_ROWS: u64 = 10_000;
+
+/// collect partition by extracting each request separately, sending batches of dataframes
+/// whenever the accumulated columns exceed threshold
+pub(crate) async fn stream_partition_by_block<T: CollectByBlock>(
+    partition: Partition,
+    source: Arc<Source>,
+    query: Arc<Query>,
+    inner_request_size: Option<u64>,
+    threshold: FlushThreshold,
+    sender: mpsc::Sender<HashMap<Datatype, DataFrame>>,
+) -> R<()> {
+    let (response_sender, mut receiver) = mpsc::channel(1);
+    let chain_id = source.chain_id;
+    let handles = fetch_partition(
+        T::extract,
+        T::datatypes(),
+        partition,
+        source,
+        inner_request_size,
+        query.clone(),
+        response_sender,
+    )
+    .await?;
+    let mut flush_rows = match threshold {
+        FlushThreshold::Rows(n_rows) => n_rows.max(1),
+        FlushThreshold::Bytes(_) => CALIBRATION_ROWS,
+    };
+    let mut columns = T::default();
+    while let Some(message) = receiver.recv().await {
+        query.timings.time("transform", || T::transform(message?, &mut columns, &query))?;
+        if columns.n_rows() >= flush_rows {
+            let columns = std::mem::take(&mut columns);
+            let dfs =
+                query.timings.time("transform", || columns.create_dfs(&query.schemas, chain_id))?;
+            if let FlushThreshold::Bytes(n_bytes) = threshold {
+                flush_rows = rows_per_bytes(&dfs, n_bytes).unwrap_or(flush_rows);
+            }
+            sender.send(dfs).await.map_err(|_| err("tokio mpsc send failure"))?;
+        }
+    }
+    join_partition_handles(handles).await?;
+    let dfs = query.timings.time("transform", || columns.create_dfs(&query.schemas, chain_id))?;
+    sender.send(dfs).await.map_err(|_| err("tokio mpsc send failure"))
+}
+
+/// number of rows that take up n_bytes, estimated from the rows of dfs
+fn rows_per_bytes(dfs: &HashMap<Datatype, DataFrame>, n_bytes: u64) -> Option<u64> {
+    let n_rows: usize = dfs.values().map(|df| df.height()).sum();
+    let df_bytes: usize = dfs.values().map(|df| df.estimated_size()).sum();
+    match (n_rows, df_bytes) {
+        (0, _) | (_, 0) => None,
+        (n_rows, df_bytes) => {
+            Some((n_bytes as u128 * n_rows as u128 / df_bytes as u128).max(1) as u64)
+        }
+    }
+}
+
+/// collect partition of requests that each make static calls, batched through multicall if
+/// query.multicall_batch is set
+pub(crate) async fn collect_static_calls_by_block<T: CollectByBlock>(
+    partition: Partition,
+    source: Arc<Source>,
+    query: Arc<Query>,
+    inner_request_size: Option<u64>,
+    calls: StaticCalls<T::Response>,
+) -> R<HashMap<Datatype, DataFrame>>
+where
+    T::Response: 'static,
+{
+    let batch_size = match query.multicall_batch {
+        Some(batch_size) => batch_size,
+        None => {
+            return collect_partition_by_block::<T>(partition, source, query, inner_request_size)
+                .await
+        }
+    };
+    let (sender, receiver) = mpsc::channel(1);
+    let chain_id = source.chain_id;
+    let handles = fetch_partition_multicall(
+        calls,
+        T::datatypes(),
+        partition,
+        source,
+        batch_size,
+        query.clone(),
+        sender,
+    )
+    .await?;
+    let columns = T::transform_channel(receiver, &query).await?;
+    join_partition_handles(handles).await?;
+    query.timings.time("transform", || columns.create_dfs(&query.schemas, chain_id))
+}
+
+/// contract address and call data of a static call
+pub(crate) type StaticCall = (H160, Vec<u8>);
+
+/// how requests map to static calls and call outputs map to responses
+pub(crate) struct StaticCalls<T> {
+    /// contracts and call data of the calls of request
+    pub(crate) calls: fn(&Params) -> R<Vec<StaticCall>>,
+    /// response of request given the outputs of its calls, None for calls that reverted
+    pub(crate) response: fn(Params, Vec<Option<Bytes>>) -> R<T>,
+    /// whether query.state_override applies to the calls
+    pub(crate) state_override: bool,
+}
+
+impl<T> Clone for StaticCalls<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for StaticCalls<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_per_bytes() {
+        let df = df!("block_number" => vec![1u64; 100]).unwrap();
+        let dfs: HashMap<Datatype, DataFrame> = [(Datatype::Blocks, df)].into_iter().collect();
+        assert_eq!(rows_per_bytes(&dfs, 8_000), Some(1_000));
+        assert_eq!(rows_per_bytes(&dfs, 1), Some(1));
+        let df = df!("block_number" => Vec::<u64>::new()).unwrap();
+        let dfs: HashMap<Datatype, DataFrame> = [(Datatype::Blocks, df)].into_iter().collect();
+        assert_eq!(rows_per_bytes(&dfs, 8_000), None);
+    }
+}
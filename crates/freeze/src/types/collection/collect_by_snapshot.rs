@@ -0,0 +1,59 @@
+use crate::{err, CollectError, Datatype, MetaDatatype, Query, Source, ToDataFrames};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+type R<T> = ::core::result::Result<T, CollectError>;
+
+/// defines how to collect dataset by snapshots of current node state
+#[async_trait::async_trait]
+pub trait CollectBySnapshot: 'static + Send + Default + ToDataFrames {
+    /// type of snapshot data responses
+    type Response: Send;
+
+    /// fetch a single snapshot
+    async fn extract(source: Arc<Source>, query: Arc<Query>) -> R<Self::Response>;
+
+    /// transform snapshot data response into column data
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()>;
+
+    /// collect data into DataFrame, taking query.n_snapshots snapshots spaced by
+    /// query.snapshot_interval
+    async fn collect_by_snapshot(
+        source: Arc<Source>,
+        query: Arc<Query>,
+    ) -> R<HashMap<Datatype, DataFrame>> {
+        let chain_id = source.chain_id;
+        let mut columns = Self::default();
+        for index in 0..query.n_snapshots.max(1) {
+            if index > 0 {
+                if let Some(interval) = query.snapshot_interval {
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            let response = Self::extract(source.clone(), query.clone()).await?;
+            query.timings.time("transform", || Self::transform(response, &mut columns, &query))?;
+        }
+        query.timings.time("transform", || columns.create_dfs(&query.schemas, chain_id))
+    }
+}
+
+/// collect by snapshot
+pub async fn collect_by_snapshot(
+    datatype: MetaDatatype,
+    source: Arc<Source>,
+    query: Arc<Query>,
+) -> R<HashMap<Datatype, DataFrame>> {
+    match datatype {
+        MetaDatatype::Scalar(Datatype::Mempool) => {
+            crate::Mempool::collect_by_snapshot(source, query).await
+        }
+        _ => Err(err("datatype cannot be collected by snapshot")),
+    }
+}
+
+impl Datatype {
+    /// whether datatype is collected by snapshot rather than by block or transaction
+    pub fn can_collect_by_snapshot(&self) -> bool {
+        matches!(self, Datatype::Mempool)
+    }
+}
@@ -33,6 +33,7 @@ pub trait CollectByTransaction: 'static + Send + Default + ToDataFrames {
         let chain_id = source.chain_id;
         let handles = fetch_partition(
             Self::extract,
+            Self::datatypes(),
             partition,
             source,
             inner_request_size,
@@ -42,7 +43,7 @@ pub trait CollectByTransaction: 'static + Send + Default + ToDataFrames {
         .await?;
         let columns = Self::transform_channel(receiver, &query).await?;
         join_partition_handles(handles).await?;
-        columns.create_dfs(&query.schemas, chain_id)
+        query.timings.time("transform", || columns.create_dfs(&query.schemas, chain_id))
     }
 
     /// convert transaction-derived data to dataframe
@@ -53,7 +54,9 @@ pub trait CollectByTransaction: 'static + Send + Default + ToDataFrames {
         let mut columns = Self::default();
         while let Some(message) = receiver.recv().await {
             match message {
-                Ok(message) => Self::transform(message, &mut columns, query)?,
+                Ok(message) => query
+                    .timings
+                    .time("transform", || Self::transform(message, &mut columns, query))?,
                 Err(e) => return Err(e),
             }
         }
@@ -1,7 +1,8 @@
+use super::collect_by_block::{StaticCall, StaticCalls};
 use crate::*;
 use futures::Future;
 use polars::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use tokio::{sync::mpsc, task};
 
 /// collect single partition
@@ -11,17 +12,205 @@ pub async fn collect_partition(
     query: Arc<Query>,
     source: Arc<Source>,
 ) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
-    match query.time_dimension {
-        TimeDimension::Blocks => collect_by_block(datatype, partition, source, query).await,
+    let dfs = match query.time_dimension {
+        TimeDimension::Blocks => {
+            collect_by_block(datatype, partition, source.clone(), query.clone()).await
+        }
         TimeDimension::Transactions => {
-            collect_by_transaction(datatype, partition, source, query).await
+            collect_by_transaction(datatype, partition, source.clone(), query.clone()).await
+        }
+        TimeDimension::Snapshots => {
+            collect_by_snapshot(datatype, source.clone(), query.clone()).await
         }
+    }?;
+    finish_dfs(dfs, &query, &source).await
+}
+
+/// collect single partition in batches of dataframes, flushed whenever the rows of the partition
+/// exceed threshold, partitions not collected by block are sent as a single batch
+pub(crate) async fn stream_partition(
+    datatype: MetaDatatype,
+    partition: Partition,
+    query: Arc<Query>,
+    source: Arc<Source>,
+    threshold: FlushThreshold,
+    sender: mpsc::Sender<HashMap<Datatype, DataFrame>>,
+) -> Result<(), CollectError> {
+    if !matches!(query.time_dimension, TimeDimension::Blocks) {
+        let dfs = collect_partition(datatype, partition, query, source).await?;
+        return sender.send(dfs).await.map_err(|_| err("tokio mpsc send failure"))
+    }
+    let (batch_sender, mut batch_receiver) = mpsc::channel(1);
+    let collect = stream_by_block(
+        datatype,
+        partition,
+        source.clone(),
+        query.clone(),
+        threshold,
+        batch_sender,
+    );
+    let forward = async {
+        while let Some(dfs) = batch_receiver.recv().await {
+            let dfs = finish_dfs(dfs, &query, &source).await?;
+            sender.send(dfs).await.map_err(|_| err("tokio mpsc send failure"))?;
+        }
+        Ok(())
+    };
+    // a failed forward drops the collection, which would otherwise block on the full channel
+    tokio::try_join!(collect, forward).map(|_| ())
+}
+
+/// add block times, constant columns, address labels, and output names to collected dataframes
+async fn finish_dfs(
+    dfs: HashMap<Datatype, DataFrame>,
+    query: &Query,
+    source: &Source,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    let dfs = join_block_times(dfs, query, source).await?;
+    dfs.into_iter()
+        .map(|(datatype, df)| {
+            let schema = query.schemas.get_schema(&datatype)?;
+            let df = add_constant_columns(df, schema)?;
+            let df = match (&query.address_labels, datatype.label_column()) {
+                (Some(labels), Some(column)) => label_addresses(df, column, labels)?,
+                _ => df,
+            };
+            Ok((datatype, rename_columns(df, schema)?))
+        })
+        .collect()
+}
+
+/// add the timestamp and datetime columns of schemas, from the block header of each row
+async fn join_block_times(
+    dfs: HashMap<Datatype, DataFrame>,
+    query: &Query,
+    source: &Source,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    let mut joined = HashMap::new();
+    for (datatype, df) in dfs.into_iter() {
+        let schema = query.schemas.get_schema(&datatype)?;
+        if schema.block_time_columns.is_empty() {
+            joined.insert(datatype, df);
+            continue
+        }
+        // datetimes of datasets with a timestamp column are derived from that column
+        let timestamps: Vec<Option<u32>> = match df.column("timestamp") {
+            Ok(column) if !schema.block_time_columns.iter().any(|c| c == "timestamp") => column
+                .cast(&DataType::UInt32)
+                .map_err(|_| err("invalid timestamp column"))?
+                .u32()
+                .map_err(|_| err("invalid timestamp column"))?
+                .into_iter()
+                .collect(),
+            _ => {
+                let numbers = df
+                    .column("block_number")
+                    .and_then(|column| column.cast(&DataType::UInt64))
+                    .map_err(|_| err("block_number column required to join block timestamps"))?;
+                let numbers: Vec<Option<u64>> = numbers
+                    .u64()
+                    .map_err(|_| err("invalid block_number column"))?
+                    .into_iter()
+                    .collect();
+                let unique: BTreeSet<u64> = numbers.iter().flatten().copied().collect();
+                let unique: Vec<u64> = unique.into_iter().collect();
+                let block_timestamps = source.get_block_timestamps(&unique).await?;
+                numbers
+                    .into_iter()
+                    .map(|number| number.and_then(|n| block_timestamps.get(&n).copied()))
+                    .collect()
+            }
+        };
+        joined.insert(datatype, add_block_time_columns(df, schema, timestamps)?);
     }
+    Ok(joined)
 }
 
-/// fetch data for a given partition
+/// add the block time columns of a schema given the block timestamp of each row
+pub(crate) fn add_block_time_columns(
+    mut df: DataFrame,
+    schema: &Table,
+    timestamps: Vec<Option<u32>>,
+) -> Result<DataFrame, CollectError> {
+    for name in schema.block_time_columns.iter() {
+        let series = match name.as_str() {
+            "timestamp" => Series::new(name, &timestamps),
+            "datetime" => {
+                let millis: Vec<Option<i64>> =
+                    timestamps.iter().map(|t| t.map(|t| t as i64 * 1000)).collect();
+                Series::new(name, millis)
+                    .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+                    .map_err(|_| err("could not create datetime column"))?
+            }
+            _ => continue,
+        };
+        df.with_column(series).map_err(|_| err("could not add block time column"))?;
+    }
+    Ok(df)
+}
+
+/// add the constant columns of a schema, e.g. chain_name and tags
+pub(crate) fn add_constant_columns(
+    mut df: DataFrame,
+    schema: &Table,
+) -> Result<DataFrame, CollectError> {
+    for (name, value) in schema.constant_columns.iter() {
+        let series = Series::new(name, vec![value.as_str(); df.height()]);
+        df.with_column(series).map_err(|_| err("could not add constant column"))?;
+    }
+    Ok(df)
+}
+
+/// rename the columns of a dataframe to their output names
+fn rename_columns(mut df: DataFrame, schema: &Table) -> Result<DataFrame, CollectError> {
+    if schema.column_renames.is_empty() {
+        return Ok(df)
+    }
+    let names: Vec<String> = df
+        .get_column_names()
+        .into_iter()
+        .map(|name| schema.output_name(name).to_string())
+        .collect();
+    df.set_column_names(&names).map_err(|_| err("could not rename columns"))?;
+    Ok(df)
+}
+
+/// add label column of the addresses of column, e.g. the contracts of logs
+fn label_addresses(
+    mut df: DataFrame,
+    column: &str,
+    labels: &AddressLabels,
+) -> Result<DataFrame, CollectError> {
+    let values: Vec<Option<&str>> = match df.column(column).map(|column| column.dtype().clone()) {
+        Ok(DataType::Binary) => df
+            .column(column)?
+            .binary()
+            .map_err(|_| err("could not read address column"))?
+            .into_iter()
+            .map(|address| address.and_then(|address| labels.get(address)))
+            .collect(),
+        Ok(DataType::Utf8) => df
+            .column(column)?
+            .utf8()
+            .map_err(|_| err("could not read address column"))?
+            .into_iter()
+            .map(|address| {
+                address
+                    .and_then(|address| hex::decode(address.trim_start_matches("0x")).ok())
+                    .and_then(|address| labels.get(&address))
+            })
+            .collect(),
+        // excluded address columns leave the labels empty
+        _ => vec![None; df.height()],
+    };
+    df.with_column(Series::new("label", values)).map_err(|_| err("could not add label column"))?;
+    Ok(df)
+}
+
+/// fetch data for a given partition, whose responses are converted into datatypes
 pub async fn fetch_partition<F, Fut, T>(
     f_request: F,
+    datatypes: Vec<Datatype>,
     partition: Partition,
     source: Arc<Source>,
     inner_request_size: Option<u64>,
@@ -37,13 +226,27 @@ where
     Fut: Future<Output = Result<T, CollectError>> + Send + 'static,
     T: Send + 'static,
 {
+    // failed blocks are recorded under the chunk of the partition
+    let chunk = match query.on_block_error {
+        OnBlockError::Fail => String::new(),
+        _ => partition.label(&query.partitioned_by)?,
+    };
+    let block_error_context = Arc::new((datatypes, chunk));
     let mut handles = Vec::new();
     for rpc_params in partition.param_sets(inner_request_size)?.into_iter() {
         let sender = sender.clone();
         let source = source.clone();
         let query = query.clone();
+        let block_error_context = block_error_context.clone();
         let handle = task::spawn(async move {
+            let block_number = rpc_params.block_number;
             let result = f_request(rpc_params, source.clone(), query.clone()).await;
+            if let Err(e) = &result {
+                let (datatypes, chunk) = block_error_context.as_ref();
+                if query.record_block_error(datatypes, chunk, block_number, e) {
+                    return Ok(())
+                }
+            }
             match sender.send(result).await {
                 Ok(_) => Ok(()),
                 Err(_) => Err(CollectError::CollectError("tokio mpsc send failure".to_string())),
@@ -55,6 +258,89 @@ where
     Ok(handles)
 }
 
+/// fetch static calls of a partition, grouped by block into multicall batches of at most
+/// batch_size calls, or of a single request if its calls exceed batch_size
+pub(crate) async fn fetch_partition_multicall<T: Send + 'static>(
+    calls: StaticCalls<T>,
+    datatypes: Vec<Datatype>,
+    partition: Partition,
+    source: Arc<Source>,
+    batch_size: usize,
+    query: Arc<Query>,
+    sender: mpsc::Sender<Result<T, CollectError>>,
+) -> Result<Vec<tokio::task::JoinHandle<Result<(), CollectError>>>, CollectError> {
+    type Batch = Vec<(Params, Vec<StaticCall>)>;
+    let mut batches_by_block: std::collections::BTreeMap<u64, Vec<Batch>> = Default::default();
+    for rpc_params in partition.param_sets(None)?.into_iter() {
+        let request_calls = (calls.calls)(&rpc_params)?;
+        let batches = batches_by_block.entry(rpc_params.block_number()?).or_default();
+        let n_calls = |batch: &Batch| batch.iter().map(|(_, calls)| calls.len()).sum::<usize>();
+        match batches.last_mut() {
+            Some(batch) if n_calls(batch) + request_calls.len() <= batch_size.max(1) => {
+                batch.push((rpc_params, request_calls))
+            }
+            _ => batches.push(vec![(rpc_params, request_calls)]),
+        }
+    }
+
+    // failed blocks are recorded under the chunk of the partition
+    let chunk = match query.on_block_error {
+        OnBlockError::Fail => String::new(),
+        _ => partition.label(&query.partitioned_by)?,
+    };
+    let block_error_context = Arc::new((datatypes, chunk));
+    let mut handles = Vec::new();
+    for (block_number, batches) in batches_by_block.into_iter() {
+        for batch in batches.into_iter() {
+            let sender = sender.clone();
+            let source = source.clone();
+            let query = query.clone();
+            let block_error_context = block_error_context.clone();
+            let handle = task::spawn(async move {
+                let state_override =
+                    if calls.state_override { query.state_override.as_ref() } else { None };
+                let (requests, request_calls): (Vec<Params>, Vec<Vec<StaticCall>>) =
+                    batch.into_iter().unzip();
+                let n_calls: Vec<usize> = request_calls.iter().map(Vec::len).collect();
+                let batch_calls = request_calls.into_iter().flatten().collect();
+                let results = match source
+                    .multicall(batch_calls, block_number.into(), state_override)
+                    .await
+                {
+                    Ok(outputs) => {
+                        let mut outputs = outputs.into_iter();
+                        requests
+                            .into_iter()
+                            .zip(n_calls)
+                            .map(|(request, n)| {
+                                (calls.response)(request, outputs.by_ref().take(n).collect())
+                            })
+                            .collect()
+                    }
+                    Err(e) => vec![Err(e)],
+                };
+                for result in results.into_iter() {
+                    if let Err(e) = &result {
+                        let (datatypes, chunk) = block_error_context.as_ref();
+                        if query.record_block_error(datatypes, chunk, Some(block_number), e) {
+                            continue
+                        }
+                    }
+                    if sender.send(result).await.is_err() {
+                        return Err(CollectError::CollectError(
+                            "tokio mpsc send failure".to_string(),
+                        ))
+                    }
+                }
+                Ok(())
+            });
+            handles.push(handle);
+        }
+    }
+
+    Ok(handles)
+}
+
 pub(crate) async fn join_partition_handles(
     handles: Vec<tokio::task::JoinHandle<Result<(), CollectError>>>,
 ) -> Result<(), CollectError> {
@@ -68,3 +354,234 @@ pub(crate) async fn join_partition_handles(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_addresses() {
+        let labels = AddressLabels::new([(vec![2; 20], "bob".to_string())].into_iter().collect());
+        let df = df!(
+            "address" => [vec![2u8; 20], vec![1; 20]],
+            "erc20" => [vec![1u8; 20], vec![2; 20]],
+        )
+        .unwrap();
+        // labels are joined on the label column of the dataset, whichever columns match
+        assert_eq!(Datatype::Erc20Transfers.label_column(), Some("erc20"));
+        let df = label_addresses(df, "erc20", &labels).unwrap();
+        let expected: Vec<Option<&str>> = vec![None, Some("bob")];
+        assert_eq!(
+            df.column("label").unwrap().utf8().unwrap().into_iter().collect::<Vec<_>>(),
+            expected
+        );
+        let df = df!("contract_address" => [format!("0x{}", hex::encode([2; 20]))]).unwrap();
+        let df = label_addresses(df, "contract_address", &labels).unwrap();
+        assert_eq!(df.column("label").unwrap().utf8().unwrap().get(0), Some("bob"));
+        let df = label_addresses(df!("block_number" => [1u32]).unwrap(), "address", &labels);
+        assert_eq!(df.unwrap().column("label").unwrap().utf8().unwrap().get(0), None);
+    }
+
+    #[test]
+    fn test_add_block_time_columns() {
+        assert_eq!(Datatype::Logs.block_time_columns(), vec!["timestamp", "datetime"]);
+        assert_eq!(Datatype::Blocks.block_time_columns(), vec!["datetime"]);
+        let include = Some(vec!["timestamp".to_string(), "datetime".to_string()]);
+        let schema = Datatype::Logs
+            .table_schema(&[], &ColumnEncoding::Binary, &include, &None, &None, None, None, None)
+            .unwrap();
+        assert_eq!(schema.block_time_columns, vec!["timestamp", "datetime"]);
+        assert!(schema.dynamic_columns().is_empty());
+
+        let df = df!("block_number" => [5u32, 6]).unwrap();
+        let df = add_block_time_columns(df, &schema, vec![Some(1_438_269_988), None]).unwrap();
+        let timestamps: Vec<_> =
+            df.column("timestamp").unwrap().u32().unwrap().into_iter().collect();
+        assert_eq!(timestamps, vec![Some(1_438_269_988), None]);
+        let datetime = df.column("datetime").unwrap();
+        assert_eq!(datetime.dtype(), &DataType::Datetime(TimeUnit::Milliseconds, None));
+        assert_eq!(datetime.null_count(), 1);
+    }
+
+    #[test]
+    fn test_rename_columns() {
+        let mut schema = Datatype::Logs
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None, None)
+            .unwrap();
+        schema.column_renames = vec![
+            ("address".to_string(), "log_index".to_string()),
+            ("log_index".to_string(), "address".to_string()),
+            ("block_number".to_string(), "blockNumber".to_string()),
+        ];
+        let df = df!(
+            "block_number" => [1u32],
+            "log_index" => [2u32],
+            "address" => [vec![0u8; 20]],
+        )
+        .unwrap();
+        let df = rename_columns(df, &schema).unwrap();
+        // renames are applied at once, so columns can swap names
+        assert_eq!(df.get_column_names(), vec!["blockNumber", "address", "log_index"]);
+        assert_eq!(df.column("address").unwrap().dtype(), &DataType::UInt32);
+    }
+
+    /// request that fails for block 2, and reports block 4 as unsupported
+    async fn extract_block(
+        request: Params,
+        _: Arc<Source>,
+        _: Arc<Query>,
+    ) -> Result<u64, CollectError> {
+        match request.block_number()? {
+            2 => Err(CollectError::TooManyRequestsError),
+            4 => Err(CollectError::MethodNotFound("trace_block".to_string())),
+            block_number => Ok(block_number),
+        }
+    }
+
+    /// source whose requests never reach the endpoint, so that it does not need a node
+    async fn test_source() -> Source {
+        let urls = ["http://localhost:8545".to_string()];
+        let pool = EndpointPool::connect(&urls, 0, 0, &Default::default()).await.unwrap();
+        let fetcher = Fetcher {
+            provider: ethers::providers::Provider::new(pool),
+            semaphore: None,
+            rate_limiter: None,
+            request_counts: Default::default(),
+            block_timestamps: Default::default(),
+            shared_fetches: Default::default(),
+        };
+        Source {
+            fetcher: Arc::new(fetcher),
+            chain_id: 1,
+            inner_request_size: 1,
+            max_concurrent_chunks: None,
+            rpc_url: urls[0].clone(),
+            beacon_url: None,
+            multicall_support: Default::default(),
+            beacon_genesis: Default::default(),
+            capabilities: Default::default(),
+            trace_backend: Default::default(),
+            labels: SourceLabels {
+                max_concurrent_requests: None,
+                max_requests_per_second: None,
+                max_retries: None,
+                initial_backoff: None,
+            },
+        }
+    }
+
+    async fn fetch_blocks(
+        query: Arc<Query>,
+        block_numbers: Vec<u64>,
+    ) -> (Vec<Result<u64, CollectError>>, Result<(), CollectError>) {
+        let source = test_source().await;
+        let partition = Partition {
+            block_numbers: Some(vec![BlockChunk::Numbers(block_numbers)]),
+            ..Default::default()
+        };
+        let (sender, mut receiver) = mpsc::channel(1);
+        let datatypes = vec![Datatype::Blocks, Datatype::Transactions];
+        let handles = fetch_partition(
+            extract_block,
+            datatypes,
+            partition,
+            source.into(),
+            None,
+            query,
+            sender,
+        )
+        .await
+        .unwrap();
+        let mut results = Vec::new();
+        while let Some(result) = receiver.recv().await {
+            results.push(result);
+        }
+        (results, join_partition_handles(handles).await)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_partition_block_errors() {
+        let schema = Datatype::Blocks
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None, None)
+            .unwrap();
+        let query = Query {
+            schemas: [(Datatype::Blocks, schema)].into_iter().collect(),
+            partitioned_by: vec![Dim::BlockNumber],
+            on_block_error: OnBlockError::Skip,
+            ..Default::default()
+        };
+
+        // failed requests are left out of the chunk and recorded for each requested datatype
+        let query = Arc::new(query);
+        let (results, joined) = fetch_blocks(query.clone(), vec![1, 2, 3]).await;
+        let mut blocks: Vec<u64> = results.into_iter().map(|result| result.unwrap()).collect();
+        blocks.sort();
+        assert_eq!(blocks, vec![1, 3]);
+        assert!(joined.is_ok());
+        let block_errors = query.block_errors.get();
+        assert_eq!(block_errors.len(), 1);
+        assert_eq!(block_errors[0].dataset, "blocks");
+        assert_eq!(block_errors[0].chunk, "00000001_to_00000003");
+        assert_eq!(query.block_errors.missing_blocks("blocks", "00000001_to_00000003"), vec![2]);
+        assert!(query
+            .block_errors
+            .missing_blocks("transactions", "00000001_to_00000003")
+            .is_empty());
+
+        // unsupported methods still fail the chunk
+        let (results, _) = fetch_blocks(query.clone(), vec![4]).await;
+        assert!(matches!(results[..], [Err(CollectError::MethodNotFound(_))]));
+
+        // by default the failed block fails the chunk
+        let query = Arc::new(Query { on_block_error: OnBlockError::Fail, ..(*query).clone() });
+        let (results, _) = fetch_blocks(query.clone(), vec![2]).await;
+        assert!(matches!(results[..], [Err(CollectError::TooManyRequestsError)]));
+        assert_eq!(query.block_errors.get().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_partition_multicall_block_errors() {
+        let schema = Datatype::EthCalls
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None, None)
+            .unwrap();
+        let query = Arc::new(Query {
+            schemas: [(Datatype::EthCalls, schema)].into_iter().collect(),
+            partitioned_by: vec![Dim::BlockNumber],
+            on_block_error: OnBlockError::Skip,
+            ..Default::default()
+        });
+        // requests without calls never reach the endpoint
+        let calls = StaticCalls {
+            calls: |_| Ok(vec![]),
+            response: |request, _| match request.block_number()? {
+                2 => Err(CollectError::TooManyRequestsError),
+                block_number => Ok(block_number),
+            },
+            state_override: false,
+        };
+        let partition = Partition {
+            block_numbers: Some(vec![BlockChunk::Numbers(vec![1, 2, 3])]),
+            ..Default::default()
+        };
+        let (sender, mut receiver) = mpsc::channel(1);
+        let handles = fetch_partition_multicall(
+            calls,
+            vec![Datatype::EthCalls],
+            partition,
+            test_source().await.into(),
+            10,
+            query.clone(),
+            sender,
+        )
+        .await
+        .unwrap();
+        let mut blocks = Vec::new();
+        while let Some(result) = receiver.recv().await {
+            blocks.push(result.unwrap());
+        }
+        blocks.sort();
+        assert_eq!(blocks, vec![1, 3]);
+        assert!(join_partition_handles(handles).await.is_ok());
+        assert_eq!(query.block_errors.missing_blocks("eth_calls", "00000001_to_00000003"), vec![2]);
+    }
+}
@@ -1,10 +1,15 @@
 /// collect by block
 pub mod collect_by_block;
+/// collect by snapshot
+pub mod collect_by_snapshot;
 /// collect by transaction
 pub mod collect_by_transaction;
 /// generic collection functions
 pub mod collect_generic;
 
 pub use collect_by_block::CollectByBlock;
+pub(crate) use collect_by_block::{collect_static_calls_by_block, StaticCall, StaticCalls};
+pub use collect_by_snapshot::{collect_by_snapshot, CollectBySnapshot};
 pub use collect_by_transaction::CollectByTransaction;
 pub use collect_generic::collect_partition;
+pub(crate) use collect_generic::{add_block_time_columns, add_constant_columns, stream_partition};
@@ -17,6 +17,9 @@ pub trait ColumnData: Default + crate::Dataset {
     /// column types
     fn column_types() -> indexmap::IndexMap<&'static str, ColumnType>;
 
+    /// columns that can contain null values
+    fn nullable_columns() -> Vec<&'static str>;
+
     /// default columns extracted for Dataset
     fn base_default_columns() -> Vec<&'static str> {
         match Self::default_columns() {
@@ -52,10 +55,7 @@ pub trait ColumnData: Default + crate::Dataset {
 
     /// input arg aliases
     fn base_arg_aliases() -> HashMap<Dim, Dim> {
-        match Self::arg_aliases() {
-            Some(x) => x,
-            None => HashMap::new(),
-        }
+        Self::arg_aliases().unwrap_or_default()
     }
 }
 
@@ -67,6 +67,12 @@ pub trait ToDataFrames: Sized {
         schemas: &HashMap<Datatype, Table>,
         chain_id: u64,
     ) -> Result<HashMap<Datatype, DataFrame>, CollectError>;
+
+    /// number of rows accumulated so far
+    fn n_rows(&self) -> u64;
+
+    /// datatypes that the column data is converted into
+    fn datatypes() -> Vec<Datatype>;
 }
 
 /// Dataset manages collection and management of a particular datatype
@@ -110,4 +116,9 @@ pub trait Dataset: Sync + Send {
     fn arg_aliases() -> Option<HashMap<Dim, Dim>> {
         None
     }
+
+    /// column of the addresses given as inputs, which address labels are joined on
+    fn label_column() -> Option<&'static str> {
+        None
+    }
 }
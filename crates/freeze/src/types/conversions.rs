@@ -2,6 +2,7 @@ use crate::CollectError;
 /// conversion operations
 use ethers::prelude::*;
 use prefix_hex;
+use std::collections::HashMap;
 
 /// convert Bytes to u32
 pub fn bytes_to_u32(value: Bytes) -> Result<u32, CollectError> {
@@ -87,3 +88,75 @@ impl ToVecHex for Vec<Option<Vec<u8>>> {
         self.iter().map(|opt| opt.as_ref().map(|v| prefix_hex::encode(v.clone()))).collect()
     }
 }
+
+/// encode an address as an EIP-55 checksummed hex String, other values are encoded as hex
+pub fn checksum_hex(value: &[u8]) -> String {
+    match value.len() {
+        20 => ethers::utils::to_checksum(&H160::from_slice(value), None),
+        _ => prefix_hex::encode(value),
+    }
+}
+
+/// Encodes addresses as Vec of EIP-55 checksummed hex String
+pub trait ToVecChecksumHex {
+    /// Output type
+    type Output;
+
+    /// Convert to Vec of checksummed hex String, hashing each unique address once
+    fn to_vec_checksum_hex(&self) -> Self::Output;
+}
+
+impl ToVecChecksumHex for Vec<Vec<u8>> {
+    type Output = Vec<String>;
+
+    fn to_vec_checksum_hex(&self) -> Self::Output {
+        let mut cache: HashMap<&[u8], String> = HashMap::new();
+        self.iter()
+            .map(|v| cache.entry(v.as_slice()).or_insert_with(|| checksum_hex(v)).clone())
+            .collect()
+    }
+}
+
+impl ToVecChecksumHex for Vec<Option<Vec<u8>>> {
+    type Output = Vec<Option<String>>;
+
+    fn to_vec_checksum_hex(&self) -> Self::Output {
+        let mut cache: HashMap<&[u8], String> = HashMap::new();
+        self.iter()
+            .map(|opt| {
+                opt.as_ref()
+                    .map(|v| cache.entry(v.as_slice()).or_insert_with(|| checksum_hex(v)).clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_hex() {
+        // test vectors of EIP-55
+        let addresses = [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+            "0xde709f2102306220921060314715629080e2fb77",
+            "0x27b1fdb04752bbc536007a920d24acb045561c26",
+        ];
+        for address in addresses.iter() {
+            let bytes = prefix_hex::decode::<Vec<u8>>(address.to_lowercase()).unwrap();
+            assert_eq!(checksum_hex(&bytes), *address);
+        }
+
+        let values = vec![Some(vec![0x5a; 20]), None, Some(vec![0x5a; 20]), Some(vec![0xab; 4])];
+        let encoded = values.to_vec_checksum_hex();
+        assert_eq!(encoded[0], encoded[2]);
+        assert_eq!(encoded[1], None);
+        assert_eq!(encoded[3], Some("0xabababab".to_string()));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{io::Write, path::Path};
+
+use polars::prelude::*;
+
+use crate::types::{AvroCodec, FileError};
+
+/// number of rows per avro data block
+const ROWS_PER_BLOCK: usize = 4096;
+
+/// write polars dataframe to avro object container file, with every column as a nullable field
+pub(crate) fn df_to_avro(
+    df: &mut DataFrame,
+    filename: &Path,
+    codec: &AvroCodec,
+) -> Result<(), FileError> {
+    df.align_chunks();
+    let schema = avro_schema(df, &avro_record_name(filename))?;
+    let sync_marker = sync_marker(filename);
+
+    let mut buffer = b"Obj\x01".to_vec();
+    write_long(&mut buffer, 2);
+    write_bytes(&mut buffer, b"avro.schema");
+    write_bytes(&mut buffer, schema.to_string().as_bytes());
+    write_bytes(&mut buffer, b"avro.codec");
+    write_bytes(&mut buffer, codec.as_str().as_bytes());
+    write_long(&mut buffer, 0);
+    buffer.extend(sync_marker);
+
+    let mut start = 0;
+    while start < df.height() {
+        let n_rows = ROWS_PER_BLOCK.min(df.height() - start);
+        let mut block = Vec::new();
+        for row in start..start + n_rows {
+            for series in df.get_columns() {
+                let value = series.get(row).map_err(|_| FileError::FileWriteError)?;
+                write_nullable_value(&mut block, &value, series.name())?;
+            }
+        }
+        let block = compress_block(block, codec)?;
+        write_long(&mut buffer, n_rows as i64);
+        write_long(&mut buffer, block.len() as i64);
+        buffer.extend(block);
+        buffer.extend(sync_marker);
+        start += n_rows;
+    }
+
+    std::fs::write(filename, buffer).map_err(|_| FileError::FileWriteError)
+}
+
+/// avro record schema of dataframe, erroring on columns with types that avro cannot represent
+pub(crate) fn avro_schema(df: &DataFrame, name: &str) -> Result<serde_json::Value, FileError> {
+    let fields = df
+        .get_columns()
+        .iter()
+        .map(|series| {
+            let name = series.name();
+            let valid_name =
+                name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+                    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid_name {
+                return Err(FileError::UnsupportedColumn(format!(
+                    "invalid avro field name: {}",
+                    name
+                )))
+            }
+            let avro_type = avro_type(series.dtype(), name)?;
+            Ok(serde_json::json!({"name": name, "type": ["null", avro_type], "default": null}))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(serde_json::json!({"type": "record", "name": name, "namespace": "cryo", "fields": fields}))
+}
+
+/// u64 columns are written as longs, with values above i64::MAX rejected when writing
+fn avro_type(dtype: &DataType, column: &str) -> Result<serde_json::Value, FileError> {
+    let avro_type = match dtype {
+        DataType::Boolean => "boolean",
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::UInt8 | DataType::UInt16 => {
+            "int"
+        }
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => "long",
+        DataType::Float32 => "float",
+        DataType::Float64 => "double",
+        DataType::Utf8 => "string",
+        DataType::Binary => "bytes",
+        DataType::List(inner) => {
+            let items = avro_type(inner, column)?;
+            return Ok(serde_json::json!({"type": "array", "items": ["null", items]}))
+        }
+        dtype => {
+            let message =
+                format!("column {} has type {} which avro output does not support", column, dtype);
+            return Err(FileError::UnsupportedColumn(message))
+        }
+    };
+    Ok(serde_json::json!(avro_type))
+}
+
+/// name records after the datatype in the {network}__{datatype}__{chunk} file name
+fn avro_record_name(filename: &Path) -> String {
+    filename
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.split("__").nth(1))
+        .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+        .unwrap_or("row")
+        .to_string()
+}
+
+fn sync_marker(filename: &Path) -> [u8; 16] {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let seed = format!("{}{}", filename.display(), nanos);
+    let hash = ethers::utils::keccak256(seed.as_bytes());
+    let mut marker = [0u8; 16];
+    marker.copy_from_slice(&hash[..16]);
+    marker
+}
+
+fn compress_block(block: Vec<u8>, codec: &AvroCodec) -> Result<Vec<u8>, FileError> {
+    match codec {
+        AvroCodec::Null => Ok(block),
+        AvroCodec::Deflate => {
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&block).map_err(|_| FileError::FileWriteError)?;
+            encoder.finish().map_err(|_| FileError::FileWriteError)
+        }
+        AvroCodec::Snappy => {
+            // snappy blocks are followed by the big-endian crc32 of the uncompressed data
+            let mut compressed = snap::raw::Encoder::new()
+                .compress_vec(&block)
+                .map_err(|_| FileError::FileWriteError)?;
+            compressed.extend(crc32fast::hash(&block).to_be_bytes());
+            Ok(compressed)
+        }
+    }
+}
+
+fn write_nullable_value(
+    buffer: &mut Vec<u8>,
+    value: &AnyValue<'_>,
+    column: &str,
+) -> Result<(), FileError> {
+    if let AnyValue::Null = value {
+        write_long(buffer, 0);
+        return Ok(())
+    }
+    write_long(buffer, 1);
+    match value {
+        AnyValue::Boolean(value) => buffer.push(*value as u8),
+        AnyValue::Int8(value) => write_long(buffer, *value as i64),
+        AnyValue::Int16(value) => write_long(buffer, *value as i64),
+        AnyValue::Int32(value) => write_long(buffer, *value as i64),
+        AnyValue::Int64(value) => write_long(buffer, *value),
+        AnyValue::UInt8(value) => write_long(buffer, *value as i64),
+        AnyValue::UInt16(value) => write_long(buffer, *value as i64),
+        AnyValue::UInt32(value) => write_long(buffer, *value as i64),
+        AnyValue::UInt64(value) => match i64::try_from(*value) {
+            Ok(value) => write_long(buffer, value),
+            Err(_) => {
+                let message = format!("value {} of column {} exceeds avro long", value, column);
+                return Err(FileError::UnsupportedColumn(message))
+            }
+        },
+        AnyValue::Float32(value) => buffer.extend(value.to_le_bytes()),
+        AnyValue::Float64(value) => buffer.extend(value.to_le_bytes()),
+        AnyValue::Utf8(value) => write_bytes(buffer, value.as_bytes()),
+        AnyValue::Utf8Owned(value) => write_bytes(buffer, value.as_bytes()),
+        AnyValue::Binary(value) => write_bytes(buffer, value),
+        AnyValue::BinaryOwned(value) => write_bytes(buffer, value),
+        AnyValue::List(series) => {
+            if !series.is_empty() {
+                write_long(buffer, series.len() as i64);
+                for item in series.iter() {
+                    write_nullable_value(buffer, &item, column)?;
+                }
+            }
+            write_long(buffer, 0);
+        }
+        value => {
+            let message =
+                format!("column {} has value {} which avro output does not support", column, value);
+            return Err(FileError::UnsupportedColumn(message))
+        }
+    }
+    Ok(())
+}
+
+/// zigzag varint encoding used for avro ints and longs
+fn write_long(buffer: &mut Vec<u8>, value: i64) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(buffer, bytes.len() as i64);
+    buffer.extend(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_long() {
+        let encode = |value| {
+            let mut buffer = Vec::new();
+            write_long(&mut buffer, value);
+            buffer
+        };
+        assert_eq!(encode(0), vec![0x00]);
+        assert_eq!(encode(-1), vec![0x01]);
+        assert_eq!(encode(1), vec![0x02]);
+        assert_eq!(encode(-64), vec![0x7f]);
+        assert_eq!(encode(64), vec![0x80, 0x01]);
+    }
+
+    #[test]
+    fn test_df_to_avro() {
+        let mut df = df!(
+            "block_number" => [Some(1u32), None],
+            "transaction_hash" => [b"\x01\x02".as_slice(), b"".as_slice()],
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("cryo_avro_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ethereum__transactions__00000001_to_00000002.avro");
+        df_to_avro(&mut df, &path, &AvroCodec::Null).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let schema = avro_schema(&df, "transactions").unwrap().to_string();
+        assert!(schema.contains(r#"{"default":null,"name":"block_number","type":["null","long"]}"#));
+        assert!(written.starts_with(b"Obj\x01"));
+        assert!(written.windows(schema.len()).any(|window| window == schema.as_bytes()));
+
+        // one block of two rows: [1, 0x0102] and [null, empty bytes]
+        let rows = [0x02, 0x02, 0x02, 0x04, 0x01, 0x02, 0x00, 0x02, 0x00];
+        let mut block = vec![0x04, 0x12];
+        block.extend(rows);
+        assert!(written[..written.len() - 16].ends_with(&block));
+
+        let mut unsupported = df!("nested" => [Some(1i32)]).unwrap();
+        unsupported = unsupported.select(["nested"]).unwrap();
+        let struct_df =
+            DataFrame::new(vec![unsupported.into_struct("nested").into_series()]).unwrap();
+        assert!(matches!(avro_schema(&struct_df, "row"), Err(FileError::UnsupportedColumn(_))));
+
+        // u64 values are written as longs up to i64::MAX
+        let mut buffer = Vec::new();
+        let column = "gas_used";
+        assert!(
+            write_nullable_value(&mut buffer, &AnyValue::UInt64(i64::MAX as u64), column).is_ok()
+        );
+        let above_long = AnyValue::UInt64(i64::MAX as u64 + 1);
+        let result = write_nullable_value(&mut buffer, &above_long, column);
+        assert!(matches!(result, Err(FileError::UnsupportedColumn(_))));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use polars::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::{err, CollectError, FileError, FileOutput};
+
+/// filename of the checksum manifest of an output directory
+pub const CHECKSUM_MANIFEST: &str = "manifest.jsonl";
+
+lazy_static::lazy_static! {
+    /// serializes appends to checksum manifests across concurrent chunks
+    static ref MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// entry of a checksum manifest, manifests hold one entry per line
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestEntry {
+    /// path of file, relative to the directory of the manifest
+    pub path: PathBuf,
+    /// dataset of file
+    pub dataset: Option<String>,
+    /// hex sha256 of file contents
+    pub sha256: String,
+    /// number of rows of file
+    pub n_rows: u64,
+    /// size of file in bytes
+    pub n_bytes: u64,
+    /// first block of file
+    pub min_block: Option<u64>,
+    /// last block of file
+    pub max_block: Option<u64>,
+}
+
+/// outcome of verifying a file listed in a checksum manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestCheck {
+    /// file matches its manifest entry
+    Verified(PathBuf),
+    /// file no longer exists
+    Missing(PathBuf),
+    /// file differs from its manifest entry, with the fields that differ
+    Modified(PathBuf, Vec<String>),
+}
+
+/// append the checksum of a written file to the manifest of its output directory
+pub(crate) fn record_checksum(
+    df: &DataFrame,
+    path: &Path,
+    manifest_path: &Path,
+    file_output: &FileOutput,
+) -> Result<(), FileError> {
+    let manifest_dir = manifest_path.parent().ok_or(FileError::FileWriteError)?;
+    let (sha256, n_bytes) = file_checksum(path).map_err(|_| FileError::FileWriteError)?;
+    let (min_block, max_block) = match df.column("block_number") {
+        Ok(column) => match column.cast(&DataType::UInt64) {
+            Ok(blocks) => {
+                (blocks.u64().ok().and_then(|b| b.min()), blocks.u64().ok().and_then(|b| b.max()))
+            }
+            Err(_) => (None, None),
+        },
+        Err(_) => (None, None),
+    };
+    let dataset = file_output
+        .key_value_metadata
+        .iter()
+        .find(|(key, _)| key == &format!("{}datatype", super::METADATA_PREFIX))
+        .map(|(_, value)| value.clone());
+    let entry = ManifestEntry {
+        path: path.strip_prefix(manifest_dir).unwrap_or(path).to_path_buf(),
+        dataset,
+        sha256,
+        n_rows: df.height() as u64,
+        n_bytes,
+        min_block,
+        max_block,
+    };
+    let mut line = serde_json::to_string(&entry).map_err(|_| FileError::FileWriteError)?;
+    line.push('\n');
+
+    // entries are appended with a single write, so that concurrent chunks and interrupted runs
+    // never leave partial entries
+    let _lock = MANIFEST_LOCK.lock().map_err(|_| FileError::FileWriteError)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(manifest_path)
+        .map_err(|_| FileError::FileWriteError)?;
+    file.write_all(line.as_bytes()).map_err(|_| FileError::FileWriteError)
+}
+
+/// rewrite a checksum manifest without the entries of removed files, e.g. files merged by
+/// compaction
+pub(crate) fn remove_manifest_entries(
+    manifest_path: &Path,
+    removed: &[PathBuf],
+) -> Result<(), FileError> {
+    let manifest_dir = manifest_path.parent().ok_or(FileError::FileWriteError)?;
+    let removed: Vec<&Path> =
+        removed.iter().map(|path| path.strip_prefix(manifest_dir).unwrap_or(path)).collect();
+    let _lock = MANIFEST_LOCK.lock().map_err(|_| FileError::FileWriteError)?;
+    let manifest = std::fs::read_to_string(manifest_path).map_err(|_| FileError::FileWriteError)?;
+    let mut lines = String::new();
+    for line in manifest.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: ManifestEntry =
+            serde_json::from_str(line).map_err(|_| FileError::FileWriteError)?;
+        if !removed.contains(&entry.path.as_path()) {
+            lines.push_str(line);
+            lines.push('\n');
+        }
+    }
+    // manifest is replaced with a rename so that an interrupted rewrite keeps the old manifest
+    let tmp_path = manifest_path.with_extension("jsonl_tmp");
+    std::fs::write(&tmp_path, lines).map_err(|_| FileError::FileWriteError)?;
+    std::fs::rename(&tmp_path, manifest_path).map_err(|_| FileError::FileWriteError)
+}
+
+/// hex sha256 and size of a file
+fn file_checksum(path: &Path) -> std::io::Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 16];
+    let mut n_bytes = 0;
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break
+        }
+        hasher.update(&buffer[..n]);
+        n_bytes += n as u64;
+    }
+    Ok((hex::encode(hasher.finalize()), n_bytes))
+}
+
+/// recompute the checksums of the files listed in the checksum manifest of a directory
+///
+/// later entries of a file supersede earlier ones, and the row counts of parquet files are
+/// checked against their footers
+pub fn verify_manifest(dir: &Path) -> Result<Vec<ManifestCheck>, CollectError> {
+    let manifest_path = dir.join(CHECKSUM_MANIFEST);
+    let manifest = std::fs::File::open(&manifest_path)
+        .map_err(|_| err(&format!("no checksum manifest at {}", manifest_path.display())))?;
+    let mut entries = BTreeMap::new();
+    for line in std::io::BufReader::new(manifest).lines() {
+        let line = line.map_err(|_| err("could not read checksum manifest"))?;
+        if line.trim().is_empty() {
+            continue
+        }
+        let entry: ManifestEntry = serde_json::from_str(&line)
+            .map_err(|_| err("could not parse checksum manifest entry"))?;
+        entries.insert(entry.path.clone(), entry);
+    }
+
+    let mut checks = Vec::new();
+    for (relative_path, entry) in entries.into_iter() {
+        let path = dir.join(&relative_path);
+        let (sha256, n_bytes) = match file_checksum(&path) {
+            Ok(checksum) => checksum,
+            Err(_) => {
+                checks.push(ManifestCheck::Missing(relative_path));
+                continue
+            }
+        };
+        let mut mismatches = Vec::new();
+        if sha256 != entry.sha256 {
+            mismatches.push("sha256".to_string());
+        }
+        if n_bytes != entry.n_bytes {
+            mismatches.push("n_bytes".to_string());
+        }
+        if path.extension().and_then(|extension| extension.to_str()) == Some("parquet") {
+            let n_rows = std::fs::File::open(&path)
+                .ok()
+                .and_then(|mut file| polars_parquet::read::read_metadata(&mut file).ok())
+                .map(|metadata| metadata.num_rows as u64);
+            if n_rows != Some(entry.n_rows) {
+                mismatches.push("n_rows".to_string());
+            }
+        }
+        match mismatches.is_empty() {
+            true => checks.push(ManifestCheck::Verified(relative_path)),
+            false => checks.push(ManifestCheck::Modified(relative_path, mismatches)),
+        }
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_manifest() {
+        let dir = std::env::temp_dir().join(format!("cryo_checksum_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest_path = dir.join(CHECKSUM_MANIFEST);
+        let file_output = FileOutput {
+            key_value_metadata: vec![("cryo.datatype".to_string(), "blocks".to_string())],
+            checksum_manifest: Some(manifest_path.clone()),
+            ..FileOutput::test_output(&dir)
+        };
+        let mut df = df!("block_number" => [5u32, 9]).unwrap();
+        let first = dir.join("ethereum__blocks__00000005_to_00000009.parquet");
+        let second = dir.join("ethereum__blocks__00000010_to_00000014.parquet");
+        super::super::df_to_file(&mut df, &first, &file_output).unwrap();
+        super::super::df_to_file(&mut df, &second, &file_output).unwrap();
+
+        let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+        let entry: ManifestEntry = serde_json::from_str(manifest.lines().next().unwrap()).unwrap();
+        assert_eq!(entry.path, PathBuf::from("ethereum__blocks__00000005_to_00000009.parquet"));
+        assert_eq!(entry.dataset.as_deref(), Some("blocks"));
+        assert_eq!((entry.n_rows, entry.min_block, entry.max_block), (2, Some(5), Some(9)));
+        assert!(verify_manifest(&dir)
+            .unwrap()
+            .iter()
+            .all(|check| matches!(check, ManifestCheck::Verified(_))));
+
+        // modified and removed files are reported
+        std::fs::write(&first, b"modified").unwrap();
+        std::fs::remove_file(&second).unwrap();
+        let checks = verify_manifest(&dir).unwrap();
+        assert!(matches!(&checks[0], ManifestCheck::Modified(_, fields) if fields.len() == 3));
+        assert!(matches!(&checks[1], ManifestCheck::Missing(_)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -13,7 +13,12 @@ macro_rules! with_series {
 macro_rules! with_series_binary {
     ($all_series:expr, $name:expr, $value:expr, $schema:expr) => {
         if $schema.has_column($name) {
-            if let Some(ColumnType::Hex) = $schema.column_type($name) {
+            if $schema.is_checksummed($name) {
+                $all_series.push(Series::new(
+                    $name,
+                    $crate::ToVecChecksumHex::to_vec_checksum_hex(&$value),
+                ));
+            } else if let Some(ColumnType::Hex) = $schema.column_type($name) {
                 $all_series.push(Series::new($name, $value.to_vec_hex()));
             } else {
                 $all_series.push(Series::new($name, $value));
@@ -22,156 +27,45 @@ macro_rules! with_series_binary {
     };
 }
 
-/// convert a Vec<U256> to variety of u256 Series representations
+/// convert a Vec of block numbers to a Series of the block number type of the schema
 #[macro_export]
-macro_rules! with_series_u256 {
+macro_rules! with_series_block_number {
     ($all_series:expr, $name:expr, $value:expr, $schema:expr) => {
         if $schema.has_column($name) {
-            // binary
-            if $schema.u256_types.contains(&U256Type::Binary) {
-                let name = $name.to_string() + U256Type::Binary.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Vec<u8>> = $value.iter().map(|v| v.to_vec_u8()).collect();
-                if ColumnEncoding::Hex == $schema.binary_type {
-                    $all_series.push(Series::new(name, converted.to_vec_hex()));
-                } else {
-                    $all_series.push(Series::new(name, converted));
+            let series = Series::new($name, $value);
+            match $schema.block_number_type {
+                $crate::BlockNumberType::U64 => $all_series.push(series),
+                $crate::BlockNumberType::U32 => {
+                    $all_series.push(series.strict_cast(&DataType::UInt32).map_err(|_| {
+                        $crate::err(&format!("{} exceeds u32, use --block-number-type u64", $name))
+                    })?)
                 }
             }
-
-            // string
-            if $schema.u256_types.contains(&U256Type::String) {
-                let name = $name.to_string() + U256Type::String.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<String> = $value.iter().map(|v| v.to_string()).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // float32
-            if $schema.u256_types.contains(&U256Type::F32) {
-                let name = $name.to_string() + U256Type::F32.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<f32>> =
-                    $value.iter().map(|v| v.to_string().parse::<f32>().ok()).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // float64
-            if $schema.u256_types.contains(&U256Type::F64) {
-                let name = $name.to_string() + U256Type::F64.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<f64>> =
-                    $value.iter().map(|v| v.to_string().parse::<f64>().ok()).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // u32
-            if $schema.u256_types.contains(&U256Type::U32) {
-                let name = $name.to_string() + U256Type::U32.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<u32> = $value.iter().map(|v| v.as_u32()).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // u64
-            if $schema.u256_types.contains(&U256Type::U64) {
-                let name = $name.to_string() + U256Type::U64.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<u64> = $value.iter().map(|v| v.as_u64()).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // decimal128
-            if $schema.u256_types.contains(&U256Type::Decimal128) {
-                return Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
-            }
         }
     };
 }
 
-/// convert a Vec<Option<U256>> to variety of u256 Series representations
+/// convert a Vec<U256> to the u256 Series representations of the schema
 #[macro_export]
-macro_rules! with_series_option_u256 {
+macro_rules! with_series_u256 {
     ($all_series:expr, $name:expr, $value:expr, $schema:expr) => {
         if $schema.has_column($name) {
-            // binary
-            if $schema.u256_types.contains(&U256Type::Binary) {
-                let name = $name.to_string() + U256Type::Binary.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<Vec<u8>>> =
-                    $value.iter().map(|v| v.map(|x| x.to_vec_u8())).collect();
-                if ColumnEncoding::Hex == $schema.binary_type {
-                    $all_series.push(Series::new(name, converted.to_vec_hex()));
-                } else {
-                    $all_series.push(Series::new(name, converted));
-                }
-            }
-
-            // string
-            if $schema.u256_types.contains(&U256Type::String) {
-                let name = $name.to_string() + U256Type::String.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<String>> =
-                    $value.iter().map(|v| v.map(|x| x.to_string())).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // float32
-            if $schema.u256_types.contains(&U256Type::F32) {
-                let name = $name.to_string() + U256Type::F32.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<f32>> = $value
-                    .iter()
-                    .map(|v| v.map(|x| x.to_string().parse::<f32>().ok()).flatten())
-                    .collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // float64
-            if $schema.u256_types.contains(&U256Type::F64) {
-                let name = $name.to_string() + U256Type::F64.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<f64>> = $value
-                    .iter()
-                    .map(|v| v.map(|x| x.to_string().parse::<f64>().ok()).flatten())
-                    .collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // u32
-            if $schema.u256_types.contains(&U256Type::U32) {
-                let name = $name.to_string() + U256Type::U32.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<u32>> =
-                    $value.iter().map(|v| v.map(|x| x.as_u32())).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // u64
-            if $schema.u256_types.contains(&U256Type::U64) {
-                let name = $name.to_string() + U256Type::U64.suffix().as_str();
-                let name = name.as_str();
-
-                let converted: Vec<Option<u64>> =
-                    $value.iter().map(|v| v.map(|x| x.as_u64())).collect();
-                $all_series.push(Series::new(name, converted));
-            }
-
-            // decimal128
-            if $schema.u256_types.contains(&U256Type::Decimal128) {
-                return Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+            for u256_type in $schema.u256_types.iter() {
+                $all_series.push($crate::ToU256Series::to_u256_series(
+                    &$value,
+                    $name.to_string(),
+                    u256_type.clone(),
+                    &$schema.binary_type,
+                )?);
             }
         }
     };
 }
+
+/// convert a Vec<Option<U256>> to the u256 Series representations of the schema
+#[macro_export]
+macro_rules! with_series_option_u256 {
+    ($all_series:expr, $name:expr, $value:expr, $schema:expr) => {
+        $crate::with_series_u256!($all_series, $name, $value, $schema)
+    };
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashSet, path::Path};
+
+use ::duckdb::{AccessMode, Config, Connection};
+
+use super::writer::StagedChunk;
+use crate::{err, CollectError};
+
+/// bookkeeping table recording which chunks have been loaded into the database
+const CHUNKS_TABLE: &str = "_cryo_chunks";
+
+/// names of the chunks already loaded into each table, as (table, chunk) pairs
+pub(crate) fn duckdb_loaded_chunks(db: &Path) -> Result<HashSet<(String, String)>, CollectError> {
+    if !db.exists() {
+        return Ok(HashSet::new())
+    }
+    let config = Config::default().access_mode(AccessMode::ReadOnly).map_err(duckdb_error)?;
+    let connection = Connection::open_with_flags(db, config).map_err(duckdb_error)?;
+    let sql = "SELECT count(*) FROM duckdb_tables() WHERE table_name = ? AND schema_name = 'main'";
+    let n_tables: i64 =
+        connection.query_row(sql, [CHUNKS_TABLE], |row| row.get(0)).map_err(duckdb_error)?;
+    if n_tables == 0 {
+        return Ok(HashSet::new())
+    }
+    let sql = format!("SELECT table_name, chunk FROM {}", CHUNKS_TABLE);
+    let mut statement = connection.prepare(&sql).map_err(duckdb_error)?;
+    let rows =
+        statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(duckdb_error)?;
+    rows.collect::<Result<_, _>>().map_err(duckdb_error)
+}
+
+/// load staged chunk into duckdb database
+pub(crate) fn load_duckdb_chunk(db: &Path, chunk: &StagedChunk) -> Result<(), CollectError> {
+    let path = chunk.path.to_str().ok_or(err("staged chunk path is not valid utf8"))?;
+    let connection = Connection::open(db).map_err(duckdb_error)?;
+    let sql =
+        format!("SELECT count(*) FROM parquet_schema({}) WHERE name = 'block_number'", quote(path));
+    let n_columns: i64 = connection.query_row(&sql, [], |row| row.get(0)).map_err(duckdb_error)?;
+    let sql = load_chunk_sql(chunk, path, &chunk.name(), n_columns > 0);
+    connection.execute_batch(&sql).map_err(duckdb_error)?;
+    std::fs::remove_file(&chunk.path).map_err(|_| err("could not remove staged chunk file"))
+}
+
+/// single transaction that appends a chunk and records it in the bookkeeping table
+fn load_chunk_sql(chunk: &StagedChunk, path: &str, name: &str, has_block_number: bool) -> String {
+    let table = format!("\"{}\"", chunk.table.replace('"', "\"\""));
+    let source = format!("read_parquet({})", quote(path));
+    let (min_block, max_block) = match chunk.block_range {
+        Some((min_block, max_block)) => (min_block.to_string(), max_block.to_string()),
+        None => ("NULL".to_string(), "NULL".to_string()),
+    };
+    let mut statements = vec![
+        "BEGIN TRANSACTION".to_string(),
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (table_name VARCHAR, chunk VARCHAR, min_block UBIGINT, \
+             max_block UBIGINT, n_rows UBIGINT, loaded_at TIMESTAMP)",
+            CHUNKS_TABLE
+        ),
+        format!("CREATE TABLE IF NOT EXISTS {} AS SELECT * FROM {} LIMIT 0", table, source),
+    ];
+    if chunk.overwrite {
+        // rows of earlier loads are identified by the block range that they were collected from
+        if let (Some((min_block, max_block)), true) = (chunk.block_range, has_block_number) {
+            statements.push(format!(
+                "DELETE FROM {} WHERE block_number BETWEEN {} AND {}",
+                table, min_block, max_block
+            ));
+        }
+        let [complete, incomplete] = chunk.recorded_names();
+        statements.push(format!(
+            "DELETE FROM {} WHERE table_name = {} AND chunk IN ({}, {})",
+            CHUNKS_TABLE,
+            quote(&chunk.table),
+            quote(&complete),
+            quote(&incomplete)
+        ));
+    }
+    statements.extend([
+        format!("INSERT INTO {} BY NAME SELECT * FROM {}", table, source),
+        format!(
+            "INSERT INTO {} SELECT {}, {}, {}, {}, count(*), now() FROM {}",
+            CHUNKS_TABLE,
+            quote(&chunk.table),
+            quote(name),
+            min_block,
+            max_block,
+            source
+        ),
+        "COMMIT".to_string(),
+    ]);
+    statements.join(";\n") + ";\n"
+}
+
+fn duckdb_error(e: ::duckdb::Error) -> CollectError {
+    CollectError::CollectError(format!("duckdb error: {}", e))
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_load_chunk_sql() {
+        let mut chunk = StagedChunk {
+            table: "blocks".to_string(),
+            path: PathBuf::from("/tmp/ethereum__blocks__00000000_to_00000999.parquet"),
+            block_range: Some((0, 999)),
+            overwrite: false,
+            complete: true,
+            u256_columns: vec![],
+        };
+        let path = "/tmp/it's.parquet";
+        let name = chunk.name();
+        assert_eq!(name, "ethereum__blocks__00000000_to_00000999");
+
+        let sql = load_chunk_sql(&chunk, path, &name, true);
+        assert!(sql.starts_with("BEGIN TRANSACTION;\n"));
+        assert!(sql.ends_with("COMMIT;\n"));
+        assert!(sql.contains(
+            "INSERT INTO \"blocks\" BY NAME SELECT * FROM read_parquet('/tmp/it''s.parquet')"
+        ));
+        assert!(
+            sql.contains("'blocks', 'ethereum__blocks__00000000_to_00000999', 0, 999, count(*)")
+        );
+        assert!(!sql.contains("DELETE"));
+
+        chunk.overwrite = true;
+        let sql = load_chunk_sql(&chunk, path, &name, true);
+        assert!(sql.contains("DELETE FROM \"blocks\" WHERE block_number BETWEEN 0 AND 999"));
+        assert!(sql.contains("DELETE FROM _cryo_chunks WHERE table_name = 'blocks'"));
+        let sql = load_chunk_sql(&chunk, path, &name, false);
+        assert!(!sql.contains("DELETE FROM \"blocks\""));
+
+        // chunks missing blocks are recorded under a name that is not treated as loaded
+        chunk.complete = false;
+        assert_eq!(chunk.name(), "ethereum__blocks__00000000_to_00000999.incomplete");
+        let sql = load_chunk_sql(&chunk, path, &chunk.name(), true);
+        assert!(sql.contains(
+            "chunk IN ('ethereum__blocks__00000000_to_00000999', \
+                              'ethereum__blocks__00000000_to_00000999.incomplete')"
+        ));
+    }
+
+    #[test]
+    fn test_load_duckdb_chunk() {
+        let dir = std::env::temp_dir().join(format!("cryo_duckdb_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = dir.join("cryo.duckdb");
+        let path = dir.join("ethereum__blocks__00000000_to_00000999.parquet");
+        let write_chunk = || {
+            let mut df = df!("block_number" => [1u32, 2]).unwrap();
+            let file = std::fs::File::create(&path).unwrap();
+            ParquetWriter::new(file).finish(&mut df).unwrap();
+        };
+        let mut chunk = StagedChunk {
+            table: "blocks".to_string(),
+            path: path.clone(),
+            block_range: Some((0, 999)),
+            overwrite: false,
+            complete: false,
+            u256_columns: vec![],
+        };
+        write_chunk();
+        load_duckdb_chunk(&db, &chunk).unwrap();
+        assert!(!path.exists());
+        let loaded = duckdb_loaded_chunks(&db).unwrap();
+        let name = "ethereum__blocks__00000000_to_00000999".to_string();
+        assert!(loaded.contains(&("blocks".to_string(), name.clone() + ".incomplete")));
+
+        // loading the chunk again replaces the rows of the incomplete load
+        (chunk.overwrite, chunk.complete) = (true, true);
+        write_chunk();
+        load_duckdb_chunk(&db, &chunk).unwrap();
+        let loaded = duckdb_loaded_chunks(&db).unwrap();
+        assert_eq!(loaded, [("blocks".to_string(), name)].into_iter().collect());
+        let connection = Connection::open(&db).unwrap();
+        let n_rows: i64 =
+            connection.query_row("SELECT count(*) FROM blocks", [], |row| row.get(0)).unwrap();
+        assert_eq!(n_rows, 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,8 +1,12 @@
-use std::path::Path;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
 
 use polars::prelude::*;
 
-use crate::types::{FileError, FileOutput};
+use crate::types::{FileError, FileFormat, FileOutput, ParquetStatistics, TextCompression};
 
 /// write polars dataframe to file
 pub(crate) fn df_to_file(
@@ -11,16 +15,37 @@ pub(crate) fn df_to_file(
     file_output: &FileOutput,
 ) -> Result<(), FileError> {
     let tmp_filename = filename.with_extension("_tmp");
-    let result = match filename.extension().and_then(|ex| ex.to_str()) {
+    let mut extension = filename.extension().and_then(|ex| ex.to_str());
+    let compression = extension.and_then(TextCompression::from_extension);
+    if compression.is_some() {
+        let stem = filename.file_stem().map(Path::new);
+        extension = stem.and_then(|stem| stem.extension()).and_then(|ex| ex.to_str());
+    }
+    let result = match extension {
         Some("parquet") => df_to_parquet(df, &tmp_filename, file_output),
-        Some("csv") => df_to_csv(df, &tmp_filename),
-        Some("json") => df_to_json(df, &tmp_filename),
+        Some("csv") => df_to_csv(df, &tmp_filename, compression),
+        Some("json") => df_to_json(df, &tmp_filename, JsonFormat::Json, compression),
+        Some("ndjson") => df_to_json(df, &tmp_filename, JsonFormat::JsonLines, compression),
+        Some("arrow") => df_to_ipc(df, &tmp_filename, file_output),
+        Some("avro") => super::avro::df_to_avro(df, &tmp_filename, &file_output.avro_codec),
         _ => return Err(FileError::FileWriteError),
     };
     match result {
         Ok(()) => std::fs::rename(tmp_filename, filename).map_err(|_e| FileError::FileWriteError),
+        Err(FileError::UnsupportedColumn(message)) => Err(FileError::UnsupportedColumn(message)),
         Err(_e) => Err(FileError::FileWriteError),
+    }?;
+
+    // formats without key-value metadata get a sidecar file, except for files that are staged
+    let staged =
+        file_output.stdout || file_output.duckdb.is_some() || file_output.postgres.is_some();
+    if file_output.format != FileFormat::Parquet && !staged {
+        super::metadata::write_sidecar(filename, &file_output.key_value_metadata)?;
+    }
+    if let Some(manifest_path) = &file_output.checksum_manifest {
+        super::checksums::record_checksum(df, filename, manifest_path, file_output)?;
     }
+    Ok(())
 }
 
 /// write polars dataframe to parquet file
@@ -28,13 +53,35 @@ fn df_to_parquet(
     df: &mut DataFrame,
     filename: &Path,
     file_output: &FileOutput,
+) -> Result<(), FileError> {
+    // polars writes every column with the same compression and statistics level
+    if !file_output.parquet_column_compression.is_empty() ||
+        file_output.parquet_statistics == ParquetStatistics::Chunk
+    {
+        super::parquet::df_to_parquet_by_column(df, filename, file_output)?;
+    } else {
+        let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
+        ParquetWriter::new(file)
+            .with_statistics(file_output.parquet_statistics == ParquetStatistics::Page)
+            .with_compression(file_output.parquet_compression)
+            .with_row_group_size(file_output.row_group_size)
+            .finish(df)
+            .map_err(|_e| FileError::FileWriteError)?;
+    }
+    if let Some(options) = &file_output.bloom_filters {
+        super::parquet::add_bloom_filters(df, filename, options)?;
+    }
+    super::parquet::add_key_value_metadata(filename, &file_output.key_value_metadata)
+}
+
+/// write polars dataframe to arrow ipc file
+fn df_to_ipc(
+    df: &mut DataFrame,
+    filename: &Path,
+    file_output: &FileOutput,
 ) -> Result<(), FileError> {
     let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = ParquetWriter::new(file)
-        .with_statistics(file_output.parquet_statistics)
-        .with_compression(file_output.parquet_compression)
-        .with_row_group_size(file_output.row_group_size)
-        .finish(df);
+    let result = IpcWriter::new(file).with_compression(file_output.ipc_compression).finish(df);
     match result {
         Err(_e) => Err(FileError::FileWriteError),
         _ => Ok(()),
@@ -42,21 +89,208 @@ fn df_to_parquet(
 }
 
 /// write polars dataframe to csv file
-fn df_to_csv(df: &mut DataFrame, filename: &Path) -> Result<(), FileError> {
-    let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = CsvWriter::new(file).finish(df);
+fn df_to_csv(
+    df: &mut DataFrame,
+    filename: &Path,
+    compression: Option<TextCompression>,
+) -> Result<(), FileError> {
+    let mut writer = TextWriter::create(filename, compression)?;
+    let result = CsvWriter::new(&mut writer).finish(df);
     match result {
         Err(_e) => Err(FileError::FileWriteError),
-        _ => Ok(()),
+        _ => writer.finish(),
     }
 }
 
-/// write polars dataframe to json file
-fn df_to_json(df: &mut DataFrame, filename: &Path) -> Result<(), FileError> {
-    let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = JsonWriter::new(file).with_json_format(JsonFormat::Json).finish(df);
+/// write polars dataframe to json file, as one array or as one object per line
+fn df_to_json(
+    df: &mut DataFrame,
+    filename: &Path,
+    format: JsonFormat,
+    compression: Option<TextCompression>,
+) -> Result<(), FileError> {
+    let mut writer = TextWriter::create(filename, compression)?;
+    let result = JsonWriter::new(&mut writer).with_json_format(format).finish(df);
     match result {
         Err(_e) => Err(FileError::FileWriteError),
-        _ => Ok(()),
+        _ => writer.finish(),
+    }
+}
+
+/// file writer that compresses text as it is written
+enum TextWriter {
+    Plain(BufWriter<File>),
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl TextWriter {
+    fn create(filename: &Path, compression: Option<TextCompression>) -> Result<Self, FileError> {
+        let file = File::create(filename).map_err(|_e| FileError::FileWriteError)?;
+        let file = BufWriter::new(file);
+        match compression {
+            None => Ok(TextWriter::Plain(file)),
+            Some(TextCompression::Gzip) => Ok(TextWriter::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            ))),
+            Some(TextCompression::Zstd) => zstd::Encoder::new(file, 0)
+                .map(TextWriter::Zstd)
+                .map_err(|_e| FileError::FileWriteError),
+        }
+    }
+
+    /// write the end of the compressed stream and flush it to the file
+    fn finish(self) -> Result<(), FileError> {
+        let file = match self {
+            TextWriter::Plain(file) => Ok(file),
+            TextWriter::Gzip(encoder) => encoder.finish(),
+            TextWriter::Zstd(encoder) => encoder.finish(),
+        };
+        file.and_then(|mut file| file.flush()).map_err(|_e| FileError::FileWriteError)
+    }
+}
+
+impl Write for TextWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            TextWriter::Plain(file) => file.write(buf),
+            TextWriter::Gzip(encoder) => encoder.write(buf),
+            TextWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            TextWriter::Plain(file) => file.flush(),
+            TextWriter::Gzip(encoder) => encoder.flush(),
+            TextWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_binary_column, FileFormat};
+
+    #[test]
+    fn test_ipc_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cryo_ipc_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_output = FileOutput {
+            format: FileFormat::Ipc,
+            parquet_statistics: ParquetStatistics::Page,
+            ipc_compression: Some(IpcCompression::ZSTD),
+            ..FileOutput::test_output(&dir)
+        };
+        let hashes: Vec<&[u8]> = vec![&[1, 2], &[3]];
+        let mut df = df!("transaction_hash" => hashes).unwrap();
+        let path = dir.join("ethereum__transactions__1_to_2.arrow");
+        df_to_file(&mut df, &path, &file_output).unwrap();
+
+        let mut values = read_binary_column(path.to_str().unwrap(), "transaction_hash").unwrap();
+        values.sort();
+        assert_eq!(values, vec![vec![1, 2], vec![3]]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_empty_parquet_schema() {
+        let dir = std::env::temp_dir().join(format!("cryo_empty_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_output = FileOutput {
+            parquet_statistics: ParquetStatistics::Page,
+            ..FileOutput::test_output(&dir)
+        };
+        // files of chunks without rows carry every column, so that they union with other files
+        for datatype in crate::Datatype::all() {
+            let table = datatype
+                .table_schema(
+                    &[],
+                    &crate::ColumnEncoding::Binary,
+                    &None,
+                    &None,
+                    &None,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap();
+            let mut df = datatype.empty_df(&table).unwrap();
+            let path = dir.join(format!("ethereum__{}__1_to_2.parquet", datatype.name()));
+            df_to_file(&mut df, &path, &file_output).unwrap();
+            let file = std::fs::File::open(&path).unwrap();
+            let written = ParquetReader::new(file).finish().unwrap();
+            assert_eq!(written.height(), 0);
+            assert_eq!(written.schema(), df.schema(), "{}", datatype.name());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_df_to_ndjson() {
+        let dir = std::env::temp_dir().join(format!("cryo_ndjson_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut df = df!(
+            "block_number" => [1u32, 2],
+            "transaction_hash" => ["0x0102", "0x03"],
+        )
+        .unwrap();
+        let path = dir.join("ethereum__transactions__1_to_2.ndjson");
+        df_to_json(&mut df, &path, JsonFormat::JsonLines, None).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"block_number":1,"transaction_hash":"0x0102"}"#,
+                r#"{"block_number":2,"transaction_hash":"0x03"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cryo_compress_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut df = df!(
+            "block_number" => [1u32, 2, 3],
+            "address" => ["0x01", "0x02", "0x03"],
+        )
+        .unwrap();
+        let decompress = |path: &Path, compression: TextCompression| {
+            let file = File::open(path).unwrap();
+            match compression {
+                TextCompression::Gzip => {
+                    let mut contents = Vec::new();
+                    std::io::Read::read_to_end(
+                        &mut flate2::read::GzDecoder::new(file),
+                        &mut contents,
+                    )
+                    .unwrap();
+                    contents
+                }
+                TextCompression::Zstd => zstd::decode_all(file).unwrap(),
+            }
+        };
+        for compression in [TextCompression::Gzip, TextCompression::Zstd] {
+            let csv = dir.join("ethereum__logs__1_to_3.csv");
+            let compressed =
+                dir.join(format!("ethereum__logs__1_to_3.csv.{}", compression.extension()));
+            df_to_csv(&mut df, &csv, None).unwrap();
+            df_to_csv(&mut df, &compressed, Some(compression)).unwrap();
+            assert_eq!(decompress(&compressed, compression), std::fs::read(&csv).unwrap());
+
+            let json = dir.join("ethereum__logs__1_to_3.json");
+            let compressed =
+                dir.join(format!("ethereum__logs__1_to_3.json.{}", compression.extension()));
+            df_to_json(&mut df, &json, JsonFormat::Json, None).unwrap();
+            df_to_json(&mut df, &compressed, JsonFormat::Json, Some(compression)).unwrap();
+            assert_eq!(decompress(&compressed, compression), std::fs::read(&json).unwrap());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
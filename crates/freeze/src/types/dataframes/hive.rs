@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::DateTime;
+use polars::prelude::*;
+
+use super::split;
+use crate::types::{FileError, FileOutput, HiveKey, HivePartitioning};
+
+/// directory value of rows whose partition key is null
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// split dataframe into hive partition directories, then record the part files in a manifest
+pub(crate) fn df_to_hive_partitions(
+    df: &mut DataFrame,
+    manifest_path: &Path,
+    file_output: &FileOutput,
+    hive: &HivePartitioning,
+) -> Result<(), FileError> {
+    let dataset_dir = split::manifest_dir(manifest_path)?;
+    let stem = manifest_path.file_stem().ok_or(FileError::FileWriteError)?.to_string_lossy();
+    let filename = format!("part-{}.{}", stem, file_output.extension());
+    let partitions = partition_rows(df, hive)?;
+    split::remove_manifest_files(manifest_path, dataset_dir);
+
+    let drop_columns: Vec<&str> = match hive.drop_columns {
+        true => hive
+            .keys
+            .iter()
+            .map(|key| key.name())
+            .filter(|name| df.get_column_names().contains(name))
+            .collect(),
+        false => vec![],
+    };
+    let mut files = Vec::new();
+    for (partition_dir, indices) in partitions {
+        let indices = IdxCa::from_vec("", indices);
+        let mut part = df.take(&indices).map_err(|_| FileError::FileWriteError)?;
+        for column in drop_columns.iter() {
+            let _ = part.drop_in_place(column).map_err(|_| FileError::FileWriteError)?;
+        }
+        let path = dataset_dir.join(&partition_dir).join(&filename);
+        std::fs::create_dir_all(dataset_dir.join(&partition_dir))
+            .map_err(|_| FileError::FileWriteError)?;
+        for part_filename in split::df_to_parts(&mut part, &path, file_output)? {
+            files.push(format!("{}/{}", partition_dir, part_filename));
+        }
+    }
+    split::write_manifest(manifest_path, &files, df.height())
+}
+
+/// row indices of each partition directory, relative to the dataset directory
+fn partition_rows(
+    df: &DataFrame,
+    hive: &HivePartitioning,
+) -> Result<BTreeMap<String, Vec<IdxSize>>, FileError> {
+    let values = hive
+        .keys
+        .iter()
+        .map(|key| partition_values(df, key, hive.chain_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut partitions: BTreeMap<String, Vec<IdxSize>> = BTreeMap::new();
+    for row in 0..df.height() {
+        let partition_dir = hive
+            .keys
+            .iter()
+            .zip(values.iter())
+            .map(|(key, values)| format!("{}={}", key.name(), values[row]))
+            .collect::<Vec<_>>()
+            .join("/");
+        partitions.entry(partition_dir).or_default().push(row as IdxSize);
+    }
+    // chunks without rows are written to the null partition, so that their schema is kept
+    if partitions.is_empty() {
+        let partition_dir = hive
+            .keys
+            .iter()
+            .map(|key| match key {
+                HiveKey::ChainId => format!("{}={}", key.name(), hive.chain_id),
+                _ => format!("{}={}", key.name(), NULL_PARTITION),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        partitions.insert(partition_dir, vec![]);
+    }
+    Ok(partitions)
+}
+
+/// directory value of a partition key for every row of the dataframe
+fn partition_values(
+    df: &DataFrame,
+    key: &HiveKey,
+    chain_id: u64,
+) -> Result<Vec<String>, FileError> {
+    let column = |name: &str| {
+        df.column(name).map_err(|_| {
+            let message = format!("hive partitioning by {} requires a {} column", key.name(), name);
+            FileError::UnsupportedColumn(message)
+        })
+    };
+    let integers = |name: &str, dtype: DataType| {
+        column(name)?.cast(&dtype).map_err(|_| {
+            let message = format!("column {} cannot be used for hive partitioning", name);
+            FileError::UnsupportedColumn(message)
+        })
+    };
+    let values = match key {
+        HiveKey::ChainId => vec![chain_id.to_string(); df.height()],
+        HiveKey::Date => integers("timestamp", DataType::Int64)?
+            .i64()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .map(|timestamp| match timestamp.and_then(|t| DateTime::from_timestamp(t, 0)) {
+                Some(datetime) => datetime.format("%Y-%m-%d").to_string(),
+                None => NULL_PARTITION.to_string(),
+            })
+            .collect(),
+        HiveKey::BlockBucket(size) => integers("block_number", DataType::UInt64)?
+            .u64()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .map(|block| match block {
+                Some(block) => (block - block % size).to_string(),
+                None => NULL_PARTITION.to_string(),
+            })
+            .collect(),
+        HiveKey::Address => {
+            let series = column("address")?;
+            match series.dtype() {
+                DataType::Binary => series
+                    .binary()
+                    .map_err(|_| FileError::FileWriteError)?
+                    .into_iter()
+                    .map(|address| match address {
+                        Some(address) => prefix_hex::encode(address),
+                        None => NULL_PARTITION.to_string(),
+                    })
+                    .collect(),
+                DataType::Utf8 => series
+                    .utf8()
+                    .map_err(|_| FileError::FileWriteError)?
+                    .into_iter()
+                    .map(|address| address.unwrap_or(NULL_PARTITION).to_string())
+                    .collect(),
+                _ => {
+                    let message = "column address cannot be used for hive partitioning";
+                    return Err(FileError::UnsupportedColumn(message.to_string()))
+                }
+            }
+        }
+    };
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileFormat;
+
+    #[test]
+    fn test_df_to_hive_partitions() {
+        let dir = std::env::temp_dir().join(format!("cryo_hive_test_{}", std::process::id()));
+        let hive = HivePartitioning {
+            keys: vec![HiveKey::ChainId, HiveKey::Date, HiveKey::BlockBucket(100)],
+            chain_id: 1,
+            drop_columns: true,
+        };
+        let file_output = FileOutput {
+            format: FileFormat::Csv,
+            hive_partitioning: Some(hive.clone()),
+            ..FileOutput::test_output(&dir)
+        };
+        let mut df = df!(
+            "block_number" => [99u32, 100, 101],
+            "timestamp" => [1704153599u32, 1704153600, 1704153612],
+            "chain_id" => [1u64, 1, 1],
+        )
+        .unwrap();
+        let manifest_path =
+            dir.join("dataset=blocks/_cryo_chunks/ethereum__blocks__99_to_101.json");
+        std::fs::create_dir_all(manifest_path.parent().unwrap()).unwrap();
+        df_to_hive_partitions(&mut df, &manifest_path, &file_output, &hive).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        let part =
+            "chain_id=1/date=2024-01-02/block_bucket=100/part-ethereum__blocks__99_to_101.csv";
+        assert_eq!(manifest["n_rows"], 3);
+        assert_eq!(
+            manifest["files"],
+            serde_json::json!([
+                "chain_id=1/date=2024-01-01/block_bucket=0/part-ethereum__blocks__99_to_101.csv",
+                part,
+            ])
+        );
+        let contents = std::fs::read_to_string(dir.join("dataset=blocks").join(part)).unwrap();
+        assert_eq!(contents, "block_number,timestamp\n100,1704153600\n101,1704153612\n");
+
+        // logs without timestamps cannot be partitioned by date
+        let mut logs = df!("block_number" => [1u32]).unwrap();
+        let result = df_to_hive_partitions(&mut logs, &manifest_path, &file_output, &hive);
+        assert!(matches!(result, Err(FileError::UnsupportedColumn(_))));
+
+        // chunks without rows keep their schema in the null partition
+        let mut empty = df.slice(0, 0);
+        df_to_hive_partitions(&mut empty, &manifest_path, &file_output, &hive).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        let part = "chain_id=1/date=__HIVE_DEFAULT_PARTITION__/block_bucket=__HIVE_DEFAULT_PARTITION__/part-ethereum__blocks__99_to_101.csv";
+        assert_eq!(manifest["n_rows"], 0);
+        assert_eq!(manifest["files"], serde_json::json!([part]));
+        let contents = std::fs::read_to_string(dir.join("dataset=blocks").join(part)).unwrap();
+        assert_eq!(contents, "block_number,timestamp\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,41 @@
+mod avro;
+mod checksums;
+#[cfg(feature = "duckdb")]
+mod duckdb;
 mod export;
+mod hive;
+mod metadata;
+mod object_store;
+mod parquet;
+#[cfg(feature = "postgres")]
+mod postgres;
 mod read;
 mod sort;
+mod split;
 mod u256s;
+mod writer;
 
 #[macro_use]
 mod creation;
 
+#[cfg(feature = "duckdb")]
+pub(crate) use self::duckdb::*;
+pub(crate) use avro::avro_schema;
+pub(crate) use checksums::remove_manifest_entries;
+pub use checksums::{verify_manifest, ManifestCheck, ManifestEntry, CHECKSUM_MANIFEST};
 pub(crate) use export::*;
+pub(crate) use hive::*;
+pub use metadata::read_file_metadata;
+pub(crate) use metadata::{collection_metadata, sidecar_path, METADATA_PREFIX};
+pub(crate) use object_store::*;
+pub(crate) use parquet::{add_key_value_metadata, RowGroupWriter};
+#[cfg(feature = "postgres")]
+pub(crate) use postgres::*;
 pub use read::*;
 pub(crate) use sort::SortableDataFrame;
+pub(crate) use split::{
+    df_to_split_files, empty_marker_path, incomplete_marker_path, remove_chunk_files,
+    write_empty_marker, write_incomplete_marker, written_bytes,
+};
 pub use u256s::*;
+pub(crate) use writer::*;
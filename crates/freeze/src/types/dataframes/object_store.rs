@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use object_store::{
+    aws::AmazonS3Builder, azure::MicrosoftAzureBuilder, gcp::GoogleCloudStorageBuilder,
+    path::Path as ObjectPath, ObjectStore, PutPayload, WriteMultipart,
+};
+use tokio::io::AsyncReadExt;
+
+use crate::{err, CollectError, ObjectStoreOutput, ObjectStoreScheme};
+
+/// files larger than this are streamed to the store in parts of this size
+const PART_SIZE: usize = 16 * 1024 * 1024;
+/// parts of a file uploaded concurrently
+const MAX_CONCURRENT_PARTS: usize = 4;
+
+lazy_static::lazy_static! {
+    static ref STORES: Mutex<HashMap<String, Arc<dyn ObjectStore>>> = Mutex::new(HashMap::new());
+}
+
+/// client of the bucket of an object store output, configured from the environment, e.g.
+/// AWS_ACCESS_KEY_ID, GOOGLE_APPLICATION_CREDENTIALS, or AZURE_STORAGE_ACCOUNT_NAME, and
+/// shared by all uploads so that credentials are fetched once and refreshed by the client
+fn object_store(store: &ObjectStoreOutput) -> Result<Arc<dyn ObjectStore>, CollectError> {
+    let url = format!("{:?}://{}", store.scheme, store.bucket);
+    let mut stores = STORES.lock().map_err(|_| err("object store clients poisoned"))?;
+    if let Some(client) = stores.get(&url) {
+        return Ok(client.clone())
+    }
+    let client: Arc<dyn ObjectStore> = match store.scheme {
+        ObjectStoreScheme::S3 => {
+            Arc::new(AmazonS3Builder::from_env().with_bucket_name(&store.bucket).build().map_err(
+                |e| CollectError::CollectError(format!("could not configure s3: {}", e)),
+            )?)
+        }
+        ObjectStoreScheme::Gcs => Arc::new(
+            GoogleCloudStorageBuilder::from_env().with_bucket_name(&store.bucket).build().map_err(
+                |e| CollectError::CollectError(format!("could not configure gcs: {}", e)),
+            )?,
+        ),
+        ObjectStoreScheme::Azure => Arc::new(
+            MicrosoftAzureBuilder::from_env().with_container_name(&store.bucket).build().map_err(
+                |e| CollectError::CollectError(format!("could not configure azure: {}", e)),
+            )?,
+        ),
+    };
+    stores.insert(url, client.clone());
+    Ok(client)
+}
+
+/// whether an object exists at key
+pub(crate) async fn object_exists(
+    store: &ObjectStoreOutput,
+    key: &str,
+) -> Result<bool, CollectError> {
+    match object_store(store)?.head(&ObjectPath::from(key)).await {
+        Ok(_) => Ok(true),
+        Err(object_store::Error::NotFound { .. }) => Ok(false),
+        Err(e) => Err(CollectError::CollectError(format!("could not check {}: {}", key, e))),
+    }
+}
+
+/// delete the object at key, if it exists
+pub(crate) async fn delete_object(
+    store: &ObjectStoreOutput,
+    key: &str,
+) -> Result<(), CollectError> {
+    match object_store(store)?.delete(&ObjectPath::from(key)).await {
+        Ok(_) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+        Err(e) => Err(CollectError::CollectError(format!("could not delete {}: {}", key, e))),
+    }
+}
+
+/// upload staged file to key, removing the file once uploaded
+pub(crate) async fn upload_file(
+    store: &ObjectStoreOutput,
+    key: &str,
+    path: &Path,
+) -> Result<(), CollectError> {
+    let client = object_store(store)?;
+    let location = ObjectPath::from(key);
+    let upload_error = |e: object_store::Error| {
+        CollectError::CollectError(format!("could not upload {}: {}", key, e))
+    };
+    let mut file =
+        tokio::fs::File::open(path).await.map_err(|_| err("could not read staged file"))?;
+    let first = read_part(&mut file).await?;
+    if first.len() < PART_SIZE {
+        client.put(&location, PutPayload::from(first)).await.map_err(upload_error)?;
+    } else {
+        let upload = client.put_multipart(&location).await.map_err(upload_error)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, PART_SIZE);
+        let mut part = first;
+        while !part.is_empty() {
+            writer.wait_for_capacity(MAX_CONCURRENT_PARTS).await.map_err(upload_error)?;
+            writer.write(&part);
+            part = read_part(&mut file).await?;
+        }
+        writer.finish().await.map_err(upload_error)?;
+    }
+    std::fs::remove_file(path).map_err(|_| err("could not remove staged file"))
+}
+
+/// read up to PART_SIZE bytes of file, fewer only at its end
+async fn read_part(file: &mut tokio::fs::File) -> Result<Vec<u8>, CollectError> {
+    let mut part = vec![0; PART_SIZE];
+    let mut len = 0;
+    while len < PART_SIZE {
+        let n_read =
+            file.read(&mut part[len..]).await.map_err(|_| err("could not read staged file"))?;
+        if n_read == 0 {
+            break
+        }
+        len += n_read;
+    }
+    part.truncate(len);
+    Ok(part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_keys() {
+        let store = ObjectStoreOutput::parse("s3://bucket/some/prefix/").unwrap().unwrap();
+        assert_eq!(store.url(), "s3://bucket/some/prefix");
+        let key = store.key(Path::new("/tmp/stage"), Path::new("/tmp/stage/blocks/a b.parquet"));
+        assert_eq!(key.as_deref(), Some("some/prefix/blocks/a b.parquet"));
+        assert_eq!(ObjectStoreOutput::parse("./data").unwrap(), None);
+        assert!(ObjectStoreOutput::parse("ftp://bucket").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_part() {
+        let path = std::env::temp_dir().join(format!("cryo_read_part_{}", std::process::id()));
+        std::fs::write(&path, vec![7u8; PART_SIZE + 3]).unwrap();
+        let mut file = tokio::fs::File::open(&path).await.unwrap();
+        assert_eq!(read_part(&mut file).await.unwrap().len(), PART_SIZE);
+        assert_eq!(read_part(&mut file).await.unwrap(), vec![7u8; 3]);
+        assert!(read_part(&mut file).await.unwrap().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,508 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use parquet_format_safe::{
+    self as thrift_format,
+    thrift::protocol::{TCompactInputProtocol, TCompactOutputProtocol},
+};
+use polars::{
+    export::arrow::datatypes::{ArrowDataType, ArrowSchema, PhysicalType},
+    prelude::*,
+};
+use polars_parquet::{
+    parquet::bloom_filter::{hash_byte, hash_native, insert},
+    read::ParquetError,
+    write::{
+        array_to_columns, to_parquet_schema, transverse, CompressionOptions, Compressor, DynIter,
+        DynStreamingIterator, Encoding, FallibleStreamingIterator, FileWriter, SchemaDescriptor,
+        Version, WriteOptions,
+    },
+};
+
+use crate::types::{BloomFilterOptions, FileError, FileOutput, ParquetStatistics};
+
+/// default number of rows per row group, matching polars
+const DEFAULT_ROW_GROUP_SIZE: usize = 512 * 512;
+
+/// write parquet file column by column, allowing per-column compression and chunk-only statistics
+pub(crate) fn df_to_parquet_by_column(
+    df: &mut DataFrame,
+    filename: &Path,
+    file_output: &FileOutput,
+) -> Result<(), FileError> {
+    let mut writer = RowGroupWriter::try_new(filename, &df.schema(), file_output)?;
+    writer.write(df)?;
+    writer.finish()
+}
+
+/// parquet file that is kept open while dataframes are appended to it as row groups
+pub(crate) struct RowGroupWriter {
+    writer: FileWriter<std::fs::File>,
+    schema: Schema,
+    parquet_schema: SchemaDescriptor,
+    encodings: Vec<Vec<Encoding>>,
+    compressions: Vec<CompressionOptions>,
+    page_options: WriteOptions,
+    row_group_size: usize,
+}
+
+impl RowGroupWriter {
+    /// create file of dataframes with the given schema
+    pub(crate) fn try_new(
+        filename: &Path,
+        schema: &Schema,
+        file_output: &FileOutput,
+    ) -> Result<Self, FileError> {
+        let arrow_schema = ArrowSchema::from(schema.to_arrow().fields);
+        let parquet_schema =
+            to_parquet_schema(&arrow_schema).map_err(|_| FileError::FileWriteError)?;
+        let encodings: Vec<Vec<Encoding>> = arrow_schema
+            .fields
+            .iter()
+            .map(|field| transverse(&field.data_type, encoding_map))
+            .collect();
+        let compressions: Vec<CompressionOptions> = arrow_schema
+            .fields
+            .iter()
+            .map(|field| {
+                let compression = file_output.parquet_column_compression.get(&field.name);
+                compression.unwrap_or(&file_output.parquet_compression).to_owned().into()
+            })
+            .collect();
+
+        // page statistics are always needed for chunk statistics, page indexes are written by the
+        // file
+        let page_options = WriteOptions {
+            write_statistics: file_output.parquet_statistics != ParquetStatistics::None,
+            version: Version::V2,
+            compression: CompressionOptions::Uncompressed,
+            data_pagesize_limit: None,
+        };
+        let file_options = WriteOptions {
+            write_statistics: file_output.parquet_statistics == ParquetStatistics::Page,
+            ..page_options
+        };
+
+        let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
+        let writer = FileWriter::try_new(file, arrow_schema, file_options)
+            .map_err(|_| FileError::FileWriteError)?;
+        Ok(RowGroupWriter {
+            writer,
+            schema: schema.clone(),
+            parquet_schema,
+            encodings,
+            compressions,
+            page_options,
+            row_group_size: file_output.row_group_size.unwrap_or(DEFAULT_ROW_GROUP_SIZE).max(1),
+        })
+    }
+
+    /// append dataframe as one or more row groups, casting columns to the schema of the file
+    pub(crate) fn write(&mut self, df: &mut DataFrame) -> Result<(), FileError> {
+        if df.schema() != self.schema {
+            *df = conform_to_schema(df, &self.schema)?;
+        }
+        df.as_single_chunk();
+        let mut offset = 0;
+        while offset < df.height() {
+            let mut group = df.slice(offset as i64, self.row_group_size);
+            group.as_single_chunk();
+            offset += group.height();
+            for batch in group.iter_chunks() {
+                let mut columns = Vec::new();
+                for (i, array) in batch.columns().iter().enumerate() {
+                    let type_ = self.parquet_schema.fields()[i].clone();
+                    // v2 page headers record whether their pages are compressed
+                    let options =
+                        WriteOptions { compression: self.compressions[i], ..self.page_options };
+                    let pages = array_to_columns(array, type_, options, &self.encodings[i])
+                        .map_err(|_| FileError::FileWriteError)?;
+                    for pages in pages {
+                        let pages = pages.map(|page| {
+                            page.map_err(|e| ParquetError::FeatureNotSupported(e.to_string()))
+                        });
+                        let pages = Compressor::new_from_vec(pages, self.compressions[i], vec![])
+                            .map_err(PolarsError::from);
+                        columns.push(Ok(DynStreamingIterator::new(pages)));
+                    }
+                }
+                self.writer
+                    .write(DynIter::new(columns.into_iter()))
+                    .map_err(|_| FileError::FileWriteError)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// write the footer of the file
+    pub(crate) fn finish(mut self) -> Result<(), FileError> {
+        self.writer.end(None).map_err(|_| FileError::FileWriteError)?;
+        Ok(())
+    }
+}
+
+/// select the columns of a schema from a dataframe, casting those of other types
+fn conform_to_schema(df: &DataFrame, schema: &Schema) -> Result<DataFrame, FileError> {
+    let columns = schema
+        .iter()
+        .map(|(name, dtype)| {
+            let column = df.column(name).map_err(|_| {
+                FileError::UnsupportedColumn(format!("column {} missing from flushed rows", name))
+            })?;
+            column.cast(dtype).map_err(|_| FileError::FileWriteError)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    DataFrame::new(columns).map_err(|_| FileError::FileWriteError)
+}
+
+/// bloom filter bitset sizes allowed by the parquet spec
+const BLOOM_FILTER_MIN_BYTES: usize = 32;
+const BLOOM_FILTER_MAX_BYTES: usize = 128 * 1024 * 1024;
+
+/// append split block bloom filters of columns to a written parquet file and rewrite its footer
+pub(crate) fn add_bloom_filters(
+    df: &DataFrame,
+    filename: &Path,
+    options: &BloomFilterOptions,
+) -> Result<(), FileError> {
+    let columns = options.filter_columns(&df.get_column_names());
+    if columns.is_empty() {
+        return Ok(())
+    }
+    rewrite_footer(filename, |metadata, metadata_start| {
+        let mut buffer = Vec::new();
+        let mut row_offset = 0;
+        for row_group in metadata.row_groups.iter_mut() {
+            for column in row_group.columns.iter_mut() {
+                let column_metadata = match column.meta_data.as_mut() {
+                    Some(column_metadata) => column_metadata,
+                    None => continue,
+                };
+                let name = match column_metadata.path_in_schema.as_slice() {
+                    [name] if columns.contains(&name.as_str()) => name.clone(),
+                    _ => continue,
+                };
+                let series = df
+                    .column(&name)
+                    .map_err(|_| FileError::FileWriteError)?
+                    .slice(row_offset, row_group.num_rows as usize);
+                let bitset = bloom_filter_bitset(&series, options)?;
+                column_metadata.bloom_filter_offset =
+                    Some((metadata_start as usize + buffer.len()) as i64);
+                let header = thrift_format::BloomFilterHeader::new(
+                    bitset.len() as i32,
+                    thrift_format::BloomFilterAlgorithm::BLOCK(
+                        thrift_format::SplitBlockAlgorithm {},
+                    ),
+                    thrift_format::BloomFilterHash::XXHASH(thrift_format::XxHash {}),
+                    thrift_format::BloomFilterCompression::UNCOMPRESSED(
+                        thrift_format::Uncompressed {},
+                    ),
+                );
+                header
+                    .write_to_out_protocol(&mut TCompactOutputProtocol::new(&mut buffer))
+                    .map_err(|_| FileError::FileWriteError)?;
+                buffer.extend(bitset);
+            }
+            row_offset += row_group.num_rows;
+        }
+        Ok(buffer)
+    })
+}
+
+/// set key-value metadata entries in the footer of a written parquet file
+pub(crate) fn add_key_value_metadata(
+    filename: &Path,
+    entries: &[(String, String)],
+) -> Result<(), FileError> {
+    if entries.is_empty() {
+        return Ok(())
+    }
+    rewrite_footer(filename, |metadata, _| {
+        let key_value_metadata = metadata.key_value_metadata.get_or_insert_with(Vec::new);
+        for (key, value) in entries.iter() {
+            key_value_metadata.retain(|entry| &entry.key != key);
+            key_value_metadata
+                .push(thrift_format::KeyValue { key: key.clone(), value: Some(value.clone()) });
+        }
+        Ok(vec![])
+    })
+}
+
+/// replace the footer of a written parquet file, edit returns bytes to insert before the new
+/// footer given the offset that they will be written at
+fn rewrite_footer<F>(filename: &Path, edit: F) -> Result<(), FileError>
+where
+    F: FnOnce(&mut thrift_format::FileMetaData, u64) -> Result<Vec<u8>, FileError>,
+{
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(filename)
+        .map_err(|_| FileError::FileWriteError)?;
+
+    // footer is the thrift metadata, its little-endian length, and the PAR1 magic
+    let file_length = file.seek(SeekFrom::End(0)).map_err(|_| FileError::FileWriteError)?;
+    let mut tail = [0u8; 8];
+    file.seek(SeekFrom::End(-8)).map_err(|_| FileError::FileWriteError)?;
+    file.read_exact(&mut tail).map_err(|_| FileError::FileWriteError)?;
+    let metadata_length = u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]) as u64;
+    let metadata_start = file_length - 8 - metadata_length;
+    let mut footer = vec![0u8; metadata_length as usize];
+    file.seek(SeekFrom::Start(metadata_start)).map_err(|_| FileError::FileWriteError)?;
+    file.read_exact(&mut footer).map_err(|_| FileError::FileWriteError)?;
+    let mut protocol = TCompactInputProtocol::new(footer.as_slice(), usize::MAX);
+    let mut metadata = thrift_format::FileMetaData::read_from_in_protocol(&mut protocol)
+        .map_err(|_| FileError::FileWriteError)?;
+
+    let mut buffer = edit(&mut metadata, metadata_start)?;
+    let mut metadata_bytes = Vec::new();
+    metadata
+        .write_to_out_protocol(&mut TCompactOutputProtocol::new(&mut metadata_bytes))
+        .map_err(|_| FileError::FileWriteError)?;
+    buffer.extend(&metadata_bytes);
+    buffer.extend((metadata_bytes.len() as u32).to_le_bytes());
+    buffer.extend(b"PAR1");
+    file.set_len(metadata_start).map_err(|_| FileError::FileWriteError)?;
+    file.seek(SeekFrom::Start(metadata_start)).map_err(|_| FileError::FileWriteError)?;
+    file.write_all(&buffer).map_err(|_| FileError::FileWriteError)
+}
+
+/// bloom filter of the plain encoded values of a column, sized for the requested fpp
+fn bloom_filter_bitset(
+    series: &Series,
+    options: &BloomFilterOptions,
+) -> Result<Vec<u8>, FileError> {
+    let hashes: Vec<u64> = match series.dtype() {
+        DataType::Binary => series
+            .binary()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .flatten()
+            .map(hash_byte)
+            .collect(),
+        DataType::Utf8 => series
+            .utf8()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .flatten()
+            .map(hash_byte)
+            .collect(),
+        // unsigned integers are stored with the bits of the signed physical type
+        DataType::UInt32 => series
+            .u32()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .flatten()
+            .map(|x| hash_native(x as i32))
+            .collect(),
+        DataType::UInt64 => series
+            .u64()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .flatten()
+            .map(|x| hash_native(x as i64))
+            .collect(),
+        DataType::Int32 => series
+            .i32()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .flatten()
+            .map(hash_native)
+            .collect(),
+        DataType::Int64 => series
+            .i64()
+            .map_err(|_| FileError::FileWriteError)?
+            .into_iter()
+            .flatten()
+            .map(hash_native)
+            .collect(),
+        dtype => {
+            let message = format!(
+                "column {} has type {} which bloom filters do not support",
+                series.name(),
+                dtype
+            );
+            return Err(FileError::UnsupportedColumn(message))
+        }
+    };
+    let ndv = match options.ndv {
+        Some(ndv) => ndv,
+        None => hashes.iter().collect::<std::collections::HashSet<_>>().len() as u64,
+    };
+    let mut bitset = vec![0u8; bloom_filter_bytes(ndv, options.fpp)];
+    for hash in hashes {
+        insert(&mut bitset, hash);
+    }
+    Ok(bitset)
+}
+
+/// optimal number of bytes for ndv values at fpp, as a power of two within parquet limits
+fn bloom_filter_bytes(ndv: u64, fpp: f64) -> usize {
+    let bits = -8.0 * ndv as f64 / (1.0 - fpp.powf(1.0 / 8.0)).ln();
+    let bytes = (bits / 8.0).ceil() as usize;
+    bytes.clamp(BLOOM_FILTER_MIN_BYTES, BLOOM_FILTER_MAX_BYTES).next_power_of_two()
+}
+
+fn encoding_map(data_type: &ArrowDataType) -> Encoding {
+    match data_type.to_physical_type() {
+        PhysicalType::Dictionary(_) => Encoding::RleDictionary,
+        _ => Encoding::Plain,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars_parquet::{
+        parquet::{bloom_filter, compression::Compression},
+        read::read_metadata,
+    };
+
+    #[test]
+    fn test_parquet_layout() {
+        let dir = std::env::temp_dir().join(format!("cryo_parquet_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut file_output = FileOutput {
+            row_group_size: Some(4),
+            parquet_statistics: ParquetStatistics::Chunk,
+            parquet_compression: ParquetCompression::Zstd(Some(ZstdLevel::try_new(7).unwrap())),
+            parquet_column_compression: [("data".to_string(), ParquetCompression::Uncompressed)]
+                .into_iter()
+                .collect(),
+            ..FileOutput::test_output(&dir)
+        };
+        let mut df = df!(
+            "block_number" => (0u32..10).collect::<Vec<_>>(),
+            "data" => (0u32..10).map(|i| vec![i as u8; 4]).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let path = dir.join("ethereum__logs__0_to_9.parquet");
+        super::super::df_to_file(&mut df, &path, &file_output).unwrap();
+        let metadata = read_metadata(&mut std::fs::File::open(&path).unwrap()).unwrap();
+
+        assert_eq!(metadata.row_groups.len(), 3);
+        assert_eq!(metadata.num_rows, 10);
+        for row_group in metadata.row_groups.iter() {
+            let columns = row_group.columns();
+            assert_eq!(columns[0].compression(), Compression::Zstd);
+            assert_eq!(columns[1].compression(), Compression::Uncompressed);
+            assert!(columns[0].statistics().is_some());
+            assert!(columns[0].column_chunk().column_index_offset.is_none());
+        }
+
+        // without statistics there are neither chunk statistics nor page indexes
+        file_output.parquet_statistics = ParquetStatistics::None;
+        super::super::df_to_file(&mut df, &path, &file_output).unwrap();
+        let metadata = read_metadata(&mut std::fs::File::open(&path).unwrap()).unwrap();
+        assert!(metadata.row_groups[0].columns()[0].statistics().is_none());
+
+        // page statistics also write page indexes
+        file_output.parquet_statistics = ParquetStatistics::Page;
+        super::super::df_to_file(&mut df, &path, &file_output).unwrap();
+        let metadata = read_metadata(&mut std::fs::File::open(&path).unwrap()).unwrap();
+        assert!(metadata.row_groups[0].columns()[0].column_chunk().column_index_offset.is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn test_bloom_filters() {
+        let dir = std::env::temp_dir().join(format!("cryo_bloom_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let options =
+            BloomFilterOptions { columns: Some(vec!["address".to_string()]), fpp: 0.01, ndv: None };
+        let file_output = FileOutput {
+            row_group_size: Some(4),
+            parquet_statistics: ParquetStatistics::Page,
+            bloom_filters: Some(options),
+            ..FileOutput::test_output(&dir)
+        };
+        let mut df = df!(
+            "block_number" => (0u32..10).collect::<Vec<_>>(),
+            "address" => (0u32..10).map(|i| vec![i as u8; 20]).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let path = dir.join("ethereum__logs__0_to_9.parquet");
+        super::super::df_to_file(&mut df, &path, &file_output).unwrap();
+        let mut file = std::fs::File::open(&path).unwrap();
+        let metadata = read_metadata(&mut file).unwrap();
+
+        let mut bitset = Vec::new();
+        let mut row_offset = 0;
+        for row_group in metadata.row_groups.iter() {
+            let columns = row_group.columns();
+            assert!(columns[0].metadata().bloom_filter_offset.is_none());
+            bloom_filter::read(&columns[1], &mut file, &mut bitset).unwrap();
+            assert_eq!(bitset.len(), 32);
+            for value in row_offset..row_offset + row_group.num_rows() {
+                let hash = bloom_filter::hash_byte(vec![value as u8; 20]);
+                assert!(bloom_filter::is_in_set(&bitset, hash));
+            }
+            row_offset += row_group.num_rows();
+        }
+        assert_eq!(row_offset, 10);
+        let df_read = ParquetReader::new(file).finish().unwrap();
+        assert!(df_read.frame_equal(&df));
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(bloom_filter_bytes(1, 0.01), 32);
+        assert_eq!(bloom_filter_bytes(1_000_000, 0.01), 2 * 1024 * 1024);
+    }
+    #[test]
+    fn test_key_value_metadata() {
+        let dir = std::env::temp_dir().join(format!("cryo_metadata_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ethereum__logs__0_to_9.parquet");
+        let mut df = df!("block_number" => [Some(2u32), None, Some(1)]).unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        let entries = vec![("cryo.sort_order".to_string(), "block_number".to_string())];
+        add_key_value_metadata(&path, &entries).unwrap();
+        add_key_value_metadata(&path, &entries).unwrap();
+
+        let mut file = std::fs::File::open(&path).unwrap();
+        let metadata = read_metadata(&mut file).unwrap();
+        let key_value_metadata = metadata.key_value_metadata.unwrap();
+        let sort_order: Vec<_> =
+            key_value_metadata.iter().filter(|entry| entry.key == "cryo.sort_order").collect();
+        assert_eq!(sort_order.len(), 1);
+        assert_eq!(sort_order[0].value.as_deref(), Some("block_number"));
+        assert!(ParquetReader::new(file).finish().unwrap().frame_equal_missing(&df));
+        let metadata = super::super::read_file_metadata(&path).unwrap();
+        assert_eq!(metadata.get("sort_order").map(String::as_str), Some("block_number"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_row_group_writer() {
+        let dir = std::env::temp_dir().join(format!("cryo_row_group_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_output = FileOutput {
+            parquet_statistics: ParquetStatistics::Page,
+            flush_threshold: Some(crate::FlushThreshold::Rows(3)),
+            ..FileOutput::test_output(&dir)
+        };
+        let path = dir.join("ethereum__logs__0_to_4.parquet");
+        let mut first = df!("block_number" => [0u32, 1, 2], "data" => ["a", "b", "c"]).unwrap();
+        let mut writer = RowGroupWriter::try_new(&path, &first.schema(), &file_output).unwrap();
+        writer.write(&mut first).unwrap();
+        // later batches are cast to the schema of the first batch
+        let mut second = df!("block_number" => [3u64, 4], "data" => ["d", "e"]).unwrap();
+        writer.write(&mut second).unwrap();
+        writer.finish().unwrap();
+
+        let mut file = std::fs::File::open(&path).unwrap();
+        let metadata = read_metadata(&mut file).unwrap();
+        assert_eq!(metadata.row_groups.len(), 2);
+        assert_eq!(metadata.num_rows, 5);
+        let expected =
+            df!("block_number" => [0u32, 1, 2, 3, 4], "data" => ["a", "b", "c", "d", "e"]).unwrap();
+        assert!(ParquetReader::new(file).finish().unwrap().frame_equal(&expected));
+
+        // batches missing columns of the file are rejected
+        let mut writer = RowGroupWriter::try_new(&path, &first.schema(), &file_output).unwrap();
+        let mut missing = df!("block_number" => [5u32]).unwrap();
+        assert!(matches!(writer.write(&mut missing), Err(FileError::UnsupportedColumn(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use ethers::prelude::U256;
+use futures::SinkExt;
+use polars::prelude::*;
+use tokio_postgres::{Client, NoTls};
+
+use super::writer::StagedChunk;
+use crate::{err, CollectError, PostgresOutput};
+
+/// bookkeeping table recording which chunks have been loaded into the schema
+const CHUNKS_TABLE: &str = "_cryo_chunks";
+
+/// postgres column types that chunks are loaded as
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum PgType {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Numeric,
+    Text,
+    Bytea,
+    Jsonb,
+}
+
+impl PgType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PgType::Bool => "BOOLEAN",
+            PgType::Int2 => "SMALLINT",
+            PgType::Int4 => "INTEGER",
+            PgType::Int8 => "BIGINT",
+            PgType::Float4 => "REAL",
+            PgType::Float8 => "DOUBLE PRECISION",
+            PgType::Numeric => "NUMERIC",
+            PgType::Text => "TEXT",
+            PgType::Bytea => "BYTEA",
+            PgType::Jsonb => "JSONB",
+        }
+    }
+}
+
+/// connect to the output database, driving the connection on a background task
+async fn connect(output: &PostgresOutput) -> Result<Client, CollectError> {
+    let (client, connection) =
+        tokio_postgres::connect(&output.url, NoTls).await.map_err(postgres_error)?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+    Ok(client)
+}
+
+/// names of the chunks already loaded into each dataset table, as (datatype, chunk) pairs
+pub(crate) async fn postgres_loaded_chunks(
+    output: &PostgresOutput,
+) -> Result<HashSet<(String, String)>, CollectError> {
+    let client = connect(output).await?;
+    let chunks_table = output.qualify(CHUNKS_TABLE);
+    let row = client
+        .query_one("SELECT to_regclass($1::text) IS NOT NULL", &[&chunks_table])
+        .await
+        .map_err(postgres_error)?;
+    if !row.get::<_, bool>(0) {
+        return Ok(HashSet::new())
+    }
+    let sql = format!("SELECT table_name, chunk FROM {}", chunks_table);
+    let rows = client.query(&sql, &[]).await.map_err(postgres_error)?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let (table, chunk): (String, String) = (row.get(0), row.get(1));
+            let datatype = table.strip_prefix(output.table_prefix.as_str())?;
+            Some((datatype.to_string(), chunk))
+        })
+        .collect())
+}
+
+/// load staged chunk into postgres with a binary COPY, in a single transaction
+pub(crate) async fn load_postgres_chunk(
+    output: &PostgresOutput,
+    chunk: &StagedChunk,
+) -> Result<(), CollectError> {
+    let file = std::fs::File::open(&chunk.path).map_err(|_| err("could not open staged chunk"))?;
+    let df = ParquetReader::new(file).finish().map_err(|_| err("could not read staged chunk"))?;
+    let columns = postgres_columns(&df, &chunk.u256_columns)?;
+    let copy = encode_copy(&df, &columns)?;
+    let statements = load_chunk_sql(output, chunk, &columns, df.height());
+
+    let mut client = connect(output).await?;
+    let transaction = client.transaction().await.map_err(postgres_error)?;
+    transaction.batch_execute(&statements.setup).await.map_err(postgres_error)?;
+    let sink = transaction.copy_in(&statements.copy).await.map_err(postgres_error)?;
+    futures::pin_mut!(sink);
+    sink.send(Bytes::from(copy)).await.map_err(postgres_error)?;
+    sink.as_mut().finish().await.map_err(postgres_error)?;
+    transaction.batch_execute(&statements.record).await.map_err(postgres_error)?;
+    transaction.commit().await.map_err(postgres_error)?;
+    std::fs::remove_file(&chunk.path).map_err(|_| err("could not remove staged chunk file"))
+}
+
+/// postgres types of dataframe columns, with u256 columns loaded as numeric
+fn postgres_columns(
+    df: &DataFrame,
+    u256_columns: &[String],
+) -> Result<Vec<(String, PgType)>, CollectError> {
+    df.get_columns()
+        .iter()
+        .map(|series| {
+            let name = series.name();
+            let is_u256 = u256_columns.iter().any(|column| column == name);
+            let pg_type = match series.dtype() {
+                DataType::Utf8 | DataType::Binary if is_u256 => PgType::Numeric,
+                DataType::Boolean => PgType::Bool,
+                DataType::Int8 | DataType::Int16 | DataType::UInt8 => PgType::Int2,
+                DataType::Int32 | DataType::UInt16 => PgType::Int4,
+                DataType::Int64 | DataType::UInt32 => PgType::Int8,
+                DataType::UInt64 => PgType::Numeric,
+                DataType::Float32 => PgType::Float4,
+                DataType::Float64 => PgType::Float8,
+                DataType::Utf8 => PgType::Text,
+                DataType::Binary => PgType::Bytea,
+                DataType::List(_) => PgType::Jsonb,
+                dtype => {
+                    let message = format!(
+                        "column {} has type {} which postgres output does not support",
+                        name, dtype
+                    );
+                    return Err(CollectError::CollectError(message))
+                }
+            };
+            Ok((name.to_string(), pg_type))
+        })
+        .collect()
+}
+
+/// statements of the transaction that loads a chunk, run before, as, and after its COPY
+struct LoadStatements {
+    setup: String,
+    copy: String,
+    record: String,
+}
+
+fn load_chunk_sql(
+    output: &PostgresOutput,
+    chunk: &StagedChunk,
+    columns: &[(String, PgType)],
+    n_rows: usize,
+) -> LoadStatements {
+    let table_name = format!("{}{}", output.table_prefix, chunk.table);
+    let table = output.qualify(&table_name);
+    let table_name = quote(&table_name);
+    let chunks_table = output.qualify(CHUNKS_TABLE);
+    let column_names: Vec<String> = columns.iter().map(|(name, _)| identifier(name)).collect();
+    let column_definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, pg_type)| format!("{} {}", identifier(name), pg_type.as_str()))
+        .collect();
+    let (min_block, max_block) = match chunk.block_range {
+        Some((min_block, max_block)) => (min_block.to_string(), max_block.to_string()),
+        None => ("NULL".to_string(), "NULL".to_string()),
+    };
+
+    let mut statements = vec![
+        format!("CREATE SCHEMA IF NOT EXISTS {};", identifier(&output.schema)),
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (table_name TEXT NOT NULL, chunk TEXT NOT NULL, \
+             min_block BIGINT, max_block BIGINT, n_rows BIGINT NOT NULL, \
+             loaded_at TIMESTAMPTZ NOT NULL DEFAULT now(), PRIMARY KEY (table_name, chunk));",
+            chunks_table
+        ),
+        format!("CREATE TABLE IF NOT EXISTS {} ({});", table, column_definitions.join(", ")),
+    ];
+    if chunk.overwrite {
+        // rows of earlier loads are identified by the block range that they were collected from
+        let has_block_number = columns.iter().any(|(name, _)| name == "block_number");
+        if let (Some((min_block, max_block)), true) = (chunk.block_range, has_block_number) {
+            statements.push(format!(
+                "DELETE FROM {} WHERE block_number BETWEEN {} AND {};",
+                table, min_block, max_block
+            ));
+        }
+        let [complete, incomplete] = chunk.recorded_names();
+        statements.push(format!(
+            "DELETE FROM {} WHERE table_name = {} AND chunk IN ({}, {});",
+            chunks_table,
+            table_name,
+            quote(&complete),
+            quote(&incomplete)
+        ));
+    }
+    let copy =
+        format!("COPY {} ({}) FROM STDIN WITH (FORMAT binary)", table, column_names.join(", "));
+    let record = format!(
+        "INSERT INTO {} (table_name, chunk, min_block, max_block, n_rows) \
+         VALUES ({}, {}, {}, {}, {});",
+        chunks_table,
+        table_name,
+        quote(&chunk.name()),
+        min_block,
+        max_block,
+        n_rows
+    );
+    LoadStatements { setup: statements.join("\n"), copy, record }
+}
+
+/// encode dataframe in the postgres binary copy format, encoding one column at a time
+fn encode_copy(df: &DataFrame, columns: &[(String, PgType)]) -> Result<Vec<u8>, CollectError> {
+    let fields = df
+        .get_columns()
+        .iter()
+        .zip(columns.iter())
+        .map(|(series, (name, pg_type))| encode_column(series, name, *pg_type))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut buffer = b"PGCOPY\n\xff\r\n\0".to_vec();
+    buffer.extend(0i32.to_be_bytes());
+    buffer.extend(0i32.to_be_bytes());
+    for row in 0..df.height() {
+        buffer.extend((columns.len() as i16).to_be_bytes());
+        for column in fields.iter() {
+            match &column[row] {
+                Some(field) => {
+                    buffer.extend((field.len() as i32).to_be_bytes());
+                    buffer.extend(field);
+                }
+                None => buffer.extend((-1i32).to_be_bytes()),
+            }
+        }
+    }
+    buffer.extend((-1i16).to_be_bytes());
+    Ok(buffer)
+}
+
+/// encode the fields of a column, None for null values
+fn encode_column(
+    series: &Series,
+    name: &str,
+    pg_type: PgType,
+) -> Result<Vec<Option<Vec<u8>>>, CollectError> {
+    let invalid = |value: &dyn std::fmt::Display| {
+        CollectError::CollectError(format!("could not encode value {} of column {}", value, name))
+    };
+    let fields = match pg_type {
+        PgType::Bool => {
+            series.bool()?.into_iter().map(|value| value.map(|value| vec![value as u8])).collect()
+        }
+        // integer columns are only loaded as postgres types that hold all of their values
+        PgType::Int2 | PgType::Int4 | PgType::Int8 => series
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|value| {
+                value.map(|value| match pg_type {
+                    PgType::Int2 => (value as i16).to_be_bytes().to_vec(),
+                    PgType::Int4 => (value as i32).to_be_bytes().to_vec(),
+                    _ => value.to_be_bytes().to_vec(),
+                })
+            })
+            .collect(),
+        PgType::Float4 => {
+            series.f32()?.into_iter().map(|value| value.map(|v| v.to_be_bytes().to_vec())).collect()
+        }
+        PgType::Float8 => {
+            series.f64()?.into_iter().map(|value| value.map(|v| v.to_be_bytes().to_vec())).collect()
+        }
+        // u256 values can be stored as big-endian binary, hex strings, or decimal strings
+        PgType::Numeric => match series.dtype() {
+            DataType::UInt64 => series
+                .u64()?
+                .into_iter()
+                .map(|value| value.map(|value| encode_numeric(U256::from(value))))
+                .collect(),
+            DataType::Binary => series
+                .binary()?
+                .into_iter()
+                .map(|value| match value {
+                    Some(value) if value.len() <= 32 => {
+                        Ok(Some(encode_numeric(U256::from_big_endian(value))))
+                    }
+                    Some(value) => Err(invalid(&prefix_hex::encode(value.to_vec()))),
+                    None => Ok(None),
+                })
+                .collect::<Result<_, _>>()?,
+            _ => series
+                .utf8()?
+                .into_iter()
+                .map(|value| match value {
+                    Some(value) => match u256_from_str(value) {
+                        Some(value) => Ok(Some(encode_numeric(value))),
+                        None => Err(invalid(&value)),
+                    },
+                    None => Ok(None),
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        PgType::Text => {
+            series.utf8()?.into_iter().map(|value| value.map(|v| v.as_bytes().to_vec())).collect()
+        }
+        PgType::Bytea => {
+            series.binary()?.into_iter().map(|value| value.map(|v| v.to_vec())).collect()
+        }
+        PgType::Jsonb => series
+            .iter()
+            .map(|value| match value {
+                AnyValue::Null => None,
+                value => {
+                    // jsonb binary values are a version byte followed by json text
+                    let mut field = vec![1u8];
+                    field.extend(any_to_json(&value).to_string().into_bytes());
+                    Some(field)
+                }
+            })
+            .collect(),
+    };
+    Ok(fields)
+}
+
+fn u256_from_str(value: &str) -> Option<U256> {
+    match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    }
+}
+
+fn any_to_i64(value: &AnyValue<'_>) -> Option<i64> {
+    match value {
+        AnyValue::Int8(value) => Some(*value as i64),
+        AnyValue::Int16(value) => Some(*value as i64),
+        AnyValue::Int32(value) => Some(*value as i64),
+        AnyValue::Int64(value) => Some(*value),
+        AnyValue::UInt8(value) => Some(*value as i64),
+        AnyValue::UInt16(value) => Some(*value as i64),
+        AnyValue::UInt32(value) => Some(*value as i64),
+        AnyValue::UInt64(value) => i64::try_from(*value).ok(),
+        _ => None,
+    }
+}
+
+fn any_to_json(value: &AnyValue<'_>) -> serde_json::Value {
+    match value {
+        AnyValue::Null => serde_json::Value::Null,
+        AnyValue::Boolean(value) => serde_json::json!(value),
+        AnyValue::Float32(value) => serde_json::json!(value),
+        AnyValue::Float64(value) => serde_json::json!(value),
+        AnyValue::UInt64(value) => serde_json::json!(value),
+        AnyValue::Utf8(value) => serde_json::json!(value),
+        AnyValue::Utf8Owned(value) => serde_json::json!(value.as_str()),
+        AnyValue::Binary(value) => serde_json::json!(prefix_hex::encode(value.to_vec())),
+        AnyValue::BinaryOwned(value) => serde_json::json!(prefix_hex::encode(value.clone())),
+        AnyValue::List(series) => {
+            serde_json::Value::Array(series.iter().map(|item| any_to_json(&item)).collect())
+        }
+        value => match any_to_i64(value) {
+            Some(value) => serde_json::json!(value),
+            None => serde_json::json!(value.to_string()),
+        },
+    }
+}
+
+/// binary numeric: digit count, weight, sign, and display scale, then base 10000 digits
+fn encode_numeric(value: U256) -> Vec<u8> {
+    let decimal = value.to_string();
+    let decimal = decimal.trim_start_matches('0');
+    let padded = "0".repeat((4 - decimal.len() % 4) % 4) + decimal;
+    let mut digits: Vec<i16> = padded
+        .as_bytes()
+        .chunks(4)
+        .map(|group| group.iter().fold(0i16, |acc, digit| acc * 10 + (digit - b'0') as i16))
+        .collect();
+    let weight = (digits.len() as i16 - 1).max(0);
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+
+    let mut field = Vec::with_capacity(8 + 2 * digits.len());
+    field.extend((digits.len() as i16).to_be_bytes());
+    field.extend(weight.to_be_bytes());
+    field.extend(0i16.to_be_bytes());
+    field.extend(0i16.to_be_bytes());
+    for digit in digits {
+        field.extend(digit.to_be_bytes());
+    }
+    field
+}
+
+fn postgres_error(e: tokio_postgres::Error) -> CollectError {
+    CollectError::CollectError(format!("postgres error: {}", e))
+}
+
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl PostgresOutput {
+    /// schema-qualified identifier of table
+    fn qualify(&self, table: &str) -> String {
+        format!("{}.{}", identifier(&self.schema), identifier(table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_numeric() {
+        let encode = |value: u64| encode_numeric(U256::from(value));
+        assert_eq!(encode(0), vec![0, 0, 0, 0, 0, 0, 0, 0]);
+        // 12345678 = 1234 * 10000 + 5678
+        assert_eq!(encode(12_345_678), vec![0, 2, 0, 1, 0, 0, 0, 0, 0x04, 0xd2, 0x16, 0x2e]);
+        // trailing zero digits are dropped but keep their weight
+        assert_eq!(encode(100_000_000), vec![0, 1, 0, 2, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_encode_column() {
+        let series = Series::new("value_string", [Some("0x0a"), Some("10"), None]);
+        let fields = encode_column(&series, "value_string", PgType::Numeric).unwrap();
+        assert_eq!(
+            fields,
+            vec![Some(encode_numeric(10.into())), Some(encode_numeric(10.into())), None]
+        );
+        let series = Series::new("value_string", ["zz"]);
+        assert!(encode_column(&series, "value_string", PgType::Numeric).is_err());
+
+        let series = Series::new("name", ["cryo"]);
+        assert_eq!(
+            encode_column(&series, "name", PgType::Text).unwrap(),
+            vec![Some(b"cryo".to_vec())]
+        );
+        let series = Series::new("values", [Series::new("", [1u32, 2])]);
+        assert_eq!(
+            encode_column(&series, "values", PgType::Jsonb).unwrap(),
+            vec![Some(b"\x01[1,2]".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_encode_copy() {
+        let df = df!(
+            "block_number" => [Some(1u32), None],
+            "value_binary" => [[0u8; 32].as_slice(), &[0xffu8; 1][..]],
+        )
+        .unwrap();
+        let columns = postgres_columns(&df, &["value_binary".to_string()]).unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                ("block_number".to_string(), PgType::Int8),
+                ("value_binary".to_string(), PgType::Numeric)
+            ]
+        );
+
+        let encoded = encode_copy(&df, &columns).unwrap();
+        let mut expected = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0".to_vec();
+        expected.extend([0, 2, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 1]);
+        expected.extend([0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend([0, 2, 0xff, 0xff, 0xff, 0xff]);
+        expected.extend([0, 0, 0, 10, 0, 1, 0, 0, 0, 0, 0, 0, 0, 255]);
+        expected.extend([0xff, 0xff]);
+        assert_eq!(encoded, expected);
+
+        let output = PostgresOutput {
+            url: "postgresql://localhost/cryo".to_string(),
+            schema: "chain".to_string(),
+            table_prefix: "eth_".to_string(),
+        };
+        let chunk = StagedChunk {
+            table: "blocks".to_string(),
+            path: "/tmp/ethereum__blocks__00000000_to_00000999.parquet".into(),
+            block_range: Some((0, 999)),
+            overwrite: true,
+            complete: true,
+            u256_columns: vec![],
+        };
+        let statements = load_chunk_sql(&output, &chunk, &columns, 2);
+        assert!(statements.setup.contains(
+            "CREATE TABLE IF NOT EXISTS \"chain\".\"eth_blocks\" (\"block_number\" BIGINT, \
+             \"value_binary\" NUMERIC);"
+        ));
+        assert!(statements.setup.contains(
+            "DELETE FROM \"chain\".\"eth_blocks\" WHERE block_number BETWEEN 0 AND 999;"
+        ));
+        assert_eq!(
+            statements.copy,
+            "COPY \"chain\".\"eth_blocks\" (\"block_number\", \"value_binary\") FROM STDIN \
+             WITH (FORMAT binary)"
+        );
+        assert!(statements.record.contains(
+            "VALUES ('eth_blocks', 'ethereum__blocks__00000000_to_00000999', 0, 999, 2);"
+        ));
+    }
+}
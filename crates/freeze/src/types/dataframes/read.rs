@@ -1,15 +1,24 @@
 use crate::ParseError;
 use polars::prelude::*;
 
-/// read single binary column of parquet file as Vec<u8>
-pub fn read_binary_column(path: &str, column: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+/// read single column of parquet or arrow ipc file, chosen by file extension
+pub fn read_column(path: &str, column: &str) -> Result<DataFrame, ParseError> {
     let file = std::fs::File::open(path)
         .map_err(|_e| ParseError::ParseError("could not open file path".to_string()))?;
 
-    let df = ParquetReader::new(file)
-        .with_columns(Some(vec![column.to_string()]))
-        .finish()
-        .map_err(|_e| ParseError::ParseError("could not read data from column".to_string()))?;
+    let columns = Some(vec![column.to_string()]);
+    let df = match std::path::Path::new(path).extension().and_then(|ex| ex.to_str()) {
+        Some("arrow") | Some("ipc") | Some("feather") => {
+            IpcReader::new(file).with_columns(columns).finish()
+        }
+        _ => ParquetReader::new(file).with_columns(columns).finish(),
+    };
+    df.map_err(|_e| ParseError::ParseError("could not read data from column".to_string()))
+}
+
+/// read single binary column of parquet or arrow ipc file as Vec<u8>
+pub fn read_binary_column(path: &str, column: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+    let df = read_column(path, column)?;
 
     let series = df
         .column(column)
@@ -10,7 +10,15 @@ impl SortableDataFrame for Result<DataFrame, CollectError> {
     fn sort_by_schema(self, schema: &Table) -> Self {
         match (self, &schema.sort_columns) {
             (Ok(df), Some(sort_columns)) => {
-                df.sort(sort_columns, false, false).map_err(CollectError::PolarsError)
+                // nulls are sorted last so that row order does not depend on collection order
+                let by_column = sort_columns
+                    .iter()
+                    .map(|column| df.column(column).cloned())
+                    .collect::<PolarsResult<Vec<_>>>()
+                    .map_err(CollectError::PolarsError)?;
+                let descending = vec![false; by_column.len()];
+                df.sort_impl(by_column, descending, true, true, None, true)
+                    .map_err(CollectError::PolarsError)
             }
             (df, _) => df,
         }
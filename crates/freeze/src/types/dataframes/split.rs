@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+
+use polars::prelude::*;
+
+use crate::types::{FileError, FileOutput};
+
+/// split dataframe into files of at most max_rows_per_file rows, then record them in a manifest
+pub(crate) fn df_to_split_files(
+    df: &mut DataFrame,
+    manifest_path: &Path,
+    file_output: &FileOutput,
+) -> Result<(), FileError> {
+    let output_dir = manifest_dir(manifest_path)?;
+    remove_manifest_files(manifest_path, output_dir);
+    let filename = manifest_path.with_extension(file_output.extension());
+    let filename = filename.file_name().ok_or(FileError::FileWriteError)?;
+    let files = df_to_parts(df, &output_dir.join(filename), file_output)?;
+    write_manifest(manifest_path, &files, df.height())
+}
+
+/// write file, or its parts of at most max_rows_per_file rows, returning the written names
+pub(crate) fn df_to_parts(
+    df: &mut DataFrame,
+    path: &Path,
+    file_output: &FileOutput,
+) -> Result<Vec<String>, FileError> {
+    let filename = path.file_name().ok_or(FileError::FileWriteError)?.to_string_lossy();
+    match file_output.max_rows_per_file {
+        None => {
+            super::df_to_file(df, path, file_output)?;
+            Ok(vec![filename.to_string()])
+        }
+        Some(max_rows) => {
+            let extension = file_output.extension();
+            let stem = filename
+                .strip_suffix(&format!(".{}", extension))
+                .ok_or(FileError::FileWriteError)?;
+            let mut files = Vec::new();
+            for (index, mut part) in split_rows(df, max_rows).into_iter().enumerate() {
+                let filename = format!("{}_part{}.{}", stem, index, extension);
+                super::df_to_file(&mut part, &path.with_file_name(&filename), file_output)?;
+                files.push(filename);
+            }
+            Ok(files)
+        }
+    }
+}
+
+/// consecutive slices of at most max_rows rows, with one empty slice for empty dataframes
+fn split_rows(df: &DataFrame, max_rows: usize) -> Vec<DataFrame> {
+    let max_rows = max_rows.max(1);
+    let n_parts = df.height().div_ceil(max_rows).max(1);
+    (0..n_parts).map(|index| df.slice((index * max_rows) as i64, max_rows)).collect()
+}
+
+/// directory that the files listed in a chunk manifest are relative to
+pub(crate) fn manifest_dir(manifest_path: &Path) -> Result<&Path, FileError> {
+    manifest_path
+        .parent()
+        .and_then(|chunks_dir| chunks_dir.parent())
+        .ok_or(FileError::FileWriteError)
+}
+
+/// remove files of an earlier write of a chunk, which may not be overwritten by the new files
+pub(crate) fn remove_manifest_files(manifest_path: &Path, output_dir: &Path) {
+    for file in manifest_files(manifest_path) {
+        let _ = std::fs::remove_file(output_dir.join(&file));
+        let _ = std::fs::remove_file(super::sidecar_path(&output_dir.join(&file)));
+    }
+}
+
+/// remove the files of a chunk along with its sidecar and markers
+pub(crate) fn remove_chunk_files(path: &Path, is_manifest: bool) {
+    if let (true, Ok(output_dir)) = (is_manifest, manifest_dir(path)) {
+        remove_manifest_files(path, output_dir);
+    }
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(super::sidecar_path(path));
+    let _ = std::fs::remove_file(empty_marker_path(path));
+    let _ = std::fs::remove_file(incomplete_marker_path(path));
+}
+
+/// files listed in a chunk manifest, relative to its manifest_dir
+fn manifest_files(manifest_path: &Path) -> Vec<String> {
+    let manifest = match std::fs::read(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(_) => return Vec::new(),
+    };
+    match serde_json::from_slice::<serde_json::Value>(&manifest) {
+        Ok(manifest) => manifest["files"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|file| file.as_str().map(|file| file.to_string()))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// size of a written chunk, summed over the files of its manifest if it has one
+pub(crate) fn written_bytes(path: &Path, is_manifest: bool) -> u64 {
+    let size = |path: &Path| std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    match (is_manifest, manifest_dir(path)) {
+        (true, Ok(dir)) => manifest_files(path).iter().map(|file| size(&dir.join(file))).sum(),
+        _ => size(path),
+    }
+}
+
+/// record the files of a chunk, marking the chunk as complete
+pub(crate) fn write_manifest(
+    manifest_path: &Path,
+    files: &[String],
+    n_rows: usize,
+) -> Result<(), FileError> {
+    let manifest = serde_json::json!({"files": files, "n_rows": n_rows});
+    std::fs::write(manifest_path, manifest.to_string()).map_err(|_| FileError::FileWriteError)
+}
+
+/// path of the marker that records a chunk without rows in place of its file
+pub(crate) fn empty_marker_path(path: &Path) -> PathBuf {
+    let filename = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!("{}.empty", filename))
+}
+
+/// mark a chunk without rows as complete, removing the files of an earlier write of the chunk
+pub(crate) fn write_empty_marker(path: &Path, is_manifest: bool) -> Result<(), FileError> {
+    if let (true, Ok(output_dir)) = (is_manifest, manifest_dir(path)) {
+        remove_manifest_files(path, output_dir);
+    }
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(super::sidecar_path(path));
+    let marker = serde_json::json!({"n_rows": 0});
+    std::fs::write(empty_marker_path(path), marker.to_string())
+        .map_err(|_| FileError::FileWriteError)
+}
+
+/// path of the marker that records blocks with failed requests in a chunk, so that it is collected
+/// again instead of being treated as complete
+pub(crate) fn incomplete_marker_path(path: &Path) -> PathBuf {
+    let filename = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!("{}.incomplete", filename))
+}
+
+/// mark a chunk as missing rows of blocks, or remove the marker of an earlier write once it is
+/// complete
+pub(crate) fn write_incomplete_marker(
+    path: &Path,
+    missing_blocks: &[u64],
+) -> Result<(), FileError> {
+    let marker = incomplete_marker_path(path);
+    if missing_blocks.is_empty() {
+        let _ = std::fs::remove_file(marker);
+        return Ok(())
+    }
+    let contents = serde_json::json!({"missing_blocks": missing_blocks});
+    std::fs::write(marker, contents.to_string()).map_err(|_| FileError::FileWriteError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileFormat;
+
+    #[test]
+    fn test_df_to_split_files() {
+        let dir = std::env::temp_dir().join(format!("cryo_split_test_{}", std::process::id()));
+        let mut file_output = FileOutput {
+            format: FileFormat::Csv,
+            max_rows_per_file: Some(2),
+            ..FileOutput::test_output(&dir)
+        };
+        let mut df = df!("block_number" => [1u32, 2, 3, 4, 5]).unwrap();
+        let manifest_path = dir.join("_cryo_chunks/ethereum__logs__1_to_5.json");
+        std::fs::create_dir_all(manifest_path.parent().unwrap()).unwrap();
+        df_to_split_files(&mut df, &manifest_path, &file_output).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest["n_rows"], 5);
+        assert_eq!(manifest["files"].as_array().unwrap().len(), 3);
+        let last = std::fs::read_to_string(dir.join("ethereum__logs__1_to_5_part2.csv")).unwrap();
+        assert_eq!(last, "block_number\n5\n");
+
+        // rewriting with larger files removes parts that are no longer written
+        file_output.max_rows_per_file = Some(10);
+        df_to_split_files(&mut df, &manifest_path, &file_output).unwrap();
+        assert!(dir.join("ethereum__logs__1_to_5_part0.csv").exists());
+        assert!(!dir.join("ethereum__logs__1_to_5_part1.csv").exists());
+
+        let mut empty = df.slice(0, 0);
+        assert_eq!(split_rows(&empty, 2).len(), 1);
+        df_to_split_files(&mut empty, &manifest_path, &file_output).unwrap();
+        assert!(dir.join("ethereum__logs__1_to_5_part0.csv").exists());
+
+        // skipped empty chunks replace the files of the chunk with a marker
+        write_empty_marker(&manifest_path, true).unwrap();
+        assert!(!manifest_path.exists());
+        assert!(!dir.join("ethereum__logs__1_to_5_part0.csv").exists());
+        assert!(empty_marker_path(&manifest_path).exists());
+
+        // chunks missing blocks are marked until a later write completes them
+        write_incomplete_marker(&manifest_path, &[3]).unwrap();
+        assert!(incomplete_marker_path(&manifest_path).exists());
+        write_incomplete_marker(&manifest_path, &[]).unwrap();
+        assert!(!incomplete_marker_path(&manifest_path).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -20,43 +20,8 @@ impl ToU256Series for Vec<U256> {
         dtype: U256Type,
         column_encoding: &ColumnEncoding,
     ) -> Result<Series, CollectError> {
-        let name = name + dtype.suffix().as_str();
-        let name = name.as_str();
-
-        match dtype {
-            U256Type::Binary => {
-                let converted: Vec<Vec<u8>> = self.iter().map(|v| v.to_vec_u8()).collect();
-                match column_encoding {
-                    ColumnEncoding::Hex => Ok(Series::new(name, converted.to_vec_hex())),
-                    ColumnEncoding::Binary => Ok(Series::new(name, converted)),
-                }
-            }
-            U256Type::String => {
-                let converted: Vec<String> = self.iter().map(|v| v.to_string()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::F32 => {
-                let converted: Vec<Option<f32>> =
-                    self.iter().map(|v| v.to_string().parse::<f32>().ok()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::F64 => {
-                let converted: Vec<Option<f64>> =
-                    self.iter().map(|v| v.to_string().parse::<f64>().ok()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::U32 => {
-                let converted: Vec<u32> = self.iter().map(|v| v.as_u32()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::U64 => {
-                let converted: Vec<u64> = self.iter().map(|v| v.as_u64()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::Decimal128 => {
-                Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
-            }
-        }
+        let values: Vec<Option<U256>> = self.iter().copied().map(Some).collect();
+        values.to_u256_series(name, dtype, column_encoding)
     }
 }
 
@@ -70,6 +35,7 @@ impl ToU256Series for Vec<Option<U256>> {
         let name = name + dtype.suffix().as_str();
         let name = name.as_str();
 
+        // values that do not fit into integer representations are null
         match dtype {
             U256Type::Binary => {
                 let converted: Vec<Option<Vec<u8>>> =
@@ -87,25 +53,25 @@ impl ToU256Series for Vec<Option<U256>> {
             U256Type::F32 => {
                 let converted: Vec<Option<f32>> = self
                     .iter()
-                    .map(|v| v.map(|x| x.to_string().parse::<f32>().ok()).flatten())
+                    .map(|v| v.and_then(|x| x.to_string().parse::<f32>().ok()))
                     .collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::F64 => {
                 let converted: Vec<Option<f64>> = self
                     .iter()
-                    .map(|v| v.map(|x| x.to_string().parse::<f64>().ok()).flatten())
+                    .map(|v| v.and_then(|x| x.to_string().parse::<f64>().ok()))
                     .collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::U32 => {
                 let converted: Vec<Option<u32>> =
-                    self.iter().map(|v| v.map(|x| x.as_u32())).collect();
+                    self.iter().map(|v| v.and_then(|x| u32::try_from(x).ok())).collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::U64 => {
                 let converted: Vec<Option<u64>> =
-                    self.iter().map(|v| v.map(|x| x.as_u64())).collect();
+                    self.iter().map(|v| v.and_then(|x| u64::try_from(x).ok())).collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::Decimal128 => {
@@ -122,43 +88,8 @@ impl ToU256Series for Vec<I256> {
         dtype: U256Type,
         column_encoding: &ColumnEncoding,
     ) -> Result<Series, CollectError> {
-        let name = name + dtype.suffix().as_str();
-        let name = name.as_str();
-
-        match dtype {
-            U256Type::Binary => {
-                let converted: Vec<Vec<u8>> = self.iter().map(|v| v.to_vec_u8()).collect();
-                match column_encoding {
-                    ColumnEncoding::Hex => Ok(Series::new(name, converted.to_vec_hex())),
-                    ColumnEncoding::Binary => Ok(Series::new(name, converted)),
-                }
-            }
-            U256Type::String => {
-                let converted: Vec<String> = self.iter().map(|v| v.to_string()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::F32 => {
-                let converted: Vec<Option<f32>> =
-                    self.iter().map(|v| v.to_string().parse::<f32>().ok()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::F64 => {
-                let converted: Vec<Option<f64>> =
-                    self.iter().map(|v| v.to_string().parse::<f64>().ok()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::U32 => {
-                let converted: Vec<u32> = self.iter().map(|v| v.as_u32()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::U64 => {
-                let converted: Vec<u64> = self.iter().map(|v| v.as_u64()).collect();
-                Ok(Series::new(name, converted))
-            }
-            U256Type::Decimal128 => {
-                Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
-            }
-        }
+        let values: Vec<Option<I256>> = self.iter().copied().map(Some).collect();
+        values.to_u256_series(name, dtype, column_encoding)
     }
 }
 
@@ -172,6 +103,7 @@ impl ToU256Series for Vec<Option<I256>> {
         let name = name + dtype.suffix().as_str();
         let name = name.as_str();
 
+        // values that do not fit into integer representations are null
         match dtype {
             U256Type::Binary => {
                 let converted: Vec<Option<Vec<u8>>> =
@@ -189,25 +121,25 @@ impl ToU256Series for Vec<Option<I256>> {
             U256Type::F32 => {
                 let converted: Vec<Option<f32>> = self
                     .iter()
-                    .map(|v| v.map(|x| x.to_string().parse::<f32>().ok()).flatten())
+                    .map(|v| v.and_then(|x| x.to_string().parse::<f32>().ok()))
                     .collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::F64 => {
                 let converted: Vec<Option<f64>> = self
                     .iter()
-                    .map(|v| v.map(|x| x.to_string().parse::<f64>().ok()).flatten())
+                    .map(|v| v.and_then(|x| x.to_string().parse::<f64>().ok()))
                     .collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::U32 => {
                 let converted: Vec<Option<u32>> =
-                    self.iter().map(|v| v.map(|x| x.as_u32())).collect();
+                    self.iter().map(|v| v.and_then(|x| u32::try_from(x).ok())).collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::U64 => {
                 let converted: Vec<Option<u64>> =
-                    self.iter().map(|v| v.map(|x| x.as_u64())).collect();
+                    self.iter().map(|v| v.and_then(|x| u64::try_from(x).ok())).collect();
                 Ok(Series::new(name, converted))
             }
             U256Type::Decimal128 => {
@@ -216,3 +148,28 @@ impl ToU256Series for Vec<Option<I256>> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_u256_series() {
+        let values = vec![U256::from(7), U256::MAX];
+        let series = values
+            .to_u256_series("value".to_string(), U256Type::U64, &ColumnEncoding::Hex)
+            .unwrap();
+        assert_eq!(series.name(), "value_u64");
+        assert_eq!(series.u64().unwrap().into_iter().collect::<Vec<_>>(), vec![Some(7), None]);
+
+        let series = values
+            .to_u256_series("value".to_string(), U256Type::Binary, &ColumnEncoding::Hex)
+            .unwrap();
+        assert_eq!(series.dtype(), &DataType::Utf8);
+        let values = vec![Some(U256::from(7)), None];
+        let series = values
+            .to_u256_series("value".to_string(), U256Type::F64, &ColumnEncoding::Binary)
+            .unwrap();
+        assert_eq!(series.f64().unwrap().into_iter().collect::<Vec<_>>(), vec![Some(7.0), None]);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    io::Write,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{err, CollectError};
+
+/// staged chunk file to be loaded into a database table
+#[derive(Debug)]
+#[cfg_attr(not(any(feature = "duckdb", feature = "postgres")), allow(dead_code))]
+pub(crate) struct StagedChunk {
+    /// table to append rows to, named after the datatype
+    pub(crate) table: String,
+    /// parquet file holding the rows of the chunk, removed once loaded
+    pub(crate) path: PathBuf,
+    /// inclusive block range of the chunk, if partitioned by block
+    pub(crate) block_range: Option<(u64, u64)>,
+    /// whether rows of previous loads in the block range are replaced
+    pub(crate) overwrite: bool,
+    /// whether every block of the chunk was collected, chunks missing blocks are recorded under
+    /// their incomplete name so that they are collected again
+    pub(crate) complete: bool,
+    /// output columns holding u256 values
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    pub(crate) u256_columns: Vec<String>,
+}
+
+#[cfg_attr(not(any(feature = "duckdb", feature = "postgres")), allow(dead_code))]
+impl StagedChunk {
+    /// name under which the chunk is recorded in bookkeeping tables
+    pub(crate) fn name(&self) -> String {
+        match self.complete {
+            true => staged_chunk_name(&self.path),
+            false => incomplete_chunk_name(&self.path),
+        }
+    }
+
+    /// names under which earlier loads of the chunk may be recorded, complete or not
+    pub(crate) fn recorded_names(&self) -> [String; 2] {
+        [staged_chunk_name(&self.path), incomplete_chunk_name(&self.path)]
+    }
+}
+
+/// name under which a staged chunk file is recorded
+pub(crate) fn staged_chunk_name(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// name under which a staged chunk file that is missing blocks is recorded
+pub(crate) fn incomplete_chunk_name(path: &Path) -> String {
+    staged_chunk_name(path) + ".incomplete"
+}
+
+/// write staged chunk file to stdout
+pub(crate) fn stream_chunk(chunk: &StagedChunk) -> Result<(), CollectError> {
+    let mut file =
+        std::fs::File::open(&chunk.path).map_err(|_| err("could not open staged chunk"))?;
+    let mut stdout = std::io::stdout().lock();
+    std::io::copy(&mut file, &mut stdout).map_err(|_| err("could not write chunk to stdout"))?;
+    stdout.flush().map_err(|_| err("could not write chunk to stdout"))?;
+    std::fs::remove_file(&chunk.path).map_err(|_| err("could not remove staged chunk file"))
+}
+
+type ChunkRequest = (StagedChunk, oneshot::Sender<Result<(), CollectError>>);
+
+/// handle to the task that serializes all loads into a database
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkWriter {
+    sender: mpsc::UnboundedSender<ChunkRequest>,
+}
+
+impl ChunkWriter {
+    /// spawn writer task, which exits once every handle is dropped
+    pub(crate) fn spawn<F>(load: F) -> (ChunkWriter, tokio::task::JoinHandle<()>)
+    where
+        F: Fn(&StagedChunk) -> Result<(), CollectError> + Send + Sync + 'static,
+    {
+        let load = std::sync::Arc::new(load);
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ChunkRequest>();
+        let handle = tokio::spawn(async move {
+            while let Some((chunk, reply)) = receiver.recv().await {
+                let load = load.clone();
+                let result = tokio::task::spawn_blocking(move || load(&chunk))
+                    .await
+                    .unwrap_or_else(|_| Err(err("chunk writer task failed")));
+                let _ = reply.send(result);
+            }
+        });
+        (ChunkWriter { sender }, handle)
+    }
+
+    /// load chunk into database, waiting until its transaction is committed
+    pub(crate) async fn load(&self, chunk: StagedChunk) -> Result<(), CollectError> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send((chunk, reply)).map_err(|_| err("chunk writer is closed"))?;
+        response.await.map_err(|_| err("chunk writer is closed"))?
+    }
+}
+
+type WriteJob = Box<dyn FnOnce() + Send>;
+
+/// handle to the threads that encode and write output files, so that writing chunks overlaps
+/// with collecting other chunks
+#[derive(Clone, Debug)]
+pub(crate) struct WritePool {
+    sender: mpsc::Sender<WriteJob>,
+}
+
+impl WritePool {
+    /// spawn writer threads, which exit once every handle is dropped. at most `n_threads` jobs
+    /// wait in the queue, further jobs wait until a thread is free
+    pub(crate) fn spawn(n_threads: usize) -> WritePool {
+        let n_threads = n_threads.max(1);
+        let (sender, receiver) = mpsc::channel::<WriteJob>(n_threads);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..n_threads {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || loop {
+                let job = match receiver.lock() {
+                    Ok(mut receiver) => receiver.blocking_recv(),
+                    Err(_) => None,
+                };
+                match job {
+                    Some(job) => job(),
+                    None => break,
+                }
+            });
+        }
+        WritePool { sender }
+    }
+
+    /// run write on a writer thread, waiting while the queue is full
+    pub(crate) async fn run<T, F>(&self, write: F) -> Result<T, CollectError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, CollectError> + Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        let job: WriteJob = Box::new(move || {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(write))
+                .unwrap_or_else(|_| Err(err("file writer panicked")));
+            let _ = reply.send(result);
+        });
+        self.sender.send(job).await.map_err(|_| err("file writers are closed"))?;
+        response.await.map_err(|_| err("file writers are closed"))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_pool() {
+        let pool = WritePool::spawn(2);
+        let results = futures::future::join_all((0..8).map(|i| pool.run(move || Ok(i * 2)))).await;
+        let results: Vec<_> = results.into_iter().map(|result| result.unwrap()).collect();
+        assert_eq!(results, (0..8).map(|i| i * 2).collect::<Vec<_>>());
+
+        // failures and panics are returned to the caller without stopping the threads
+        assert!(pool.run(|| Err::<(), _>(err("failed"))).await.is_err());
+        assert!(pool.run::<(), _>(|| panic!("failed")).await.is_err());
+        assert_eq!(pool.run(|| Ok(1)).await.unwrap(), 1);
+    }
+}
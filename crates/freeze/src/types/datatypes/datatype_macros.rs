@@ -62,6 +62,22 @@ macro_rules! define_datatypes {
                 }
             }
 
+            /// columns of datatype that can contain null values
+            pub fn nullable_columns(&self) -> Vec<&'static str> {
+                match *self {
+                    $(Datatype::$datatype => $datatype::nullable_columns(),)*
+                }
+            }
+
+            /// empty dataframe with the columns and dtypes that are collected for a schema
+            pub fn empty_df(&self, schema: &Table) -> Result<DataFrame, CollectError> {
+                let schemas = HashMap::from([(*self, schema.clone())]);
+                let dfs = match *self {
+                    $(Datatype::$datatype => $datatype::default().create_dfs(&schemas, 0),)*
+                }?;
+                dfs.into_values().next().ok_or(err("could not create empty dataframe"))
+            }
+
             /// whether to use block ranges instead of individual blocks
             pub fn use_block_ranges(&self) -> bool {
                 match *self {
@@ -76,6 +92,13 @@ macro_rules! define_datatypes {
                 }
             }
 
+            /// column that address labels are joined on
+            pub fn label_column(&self) -> Option<&'static str> {
+                match *self {
+                    $(Datatype::$datatype => $datatype::label_column(),)*
+                }
+            }
+
             /// required parameters of each datatype
             pub fn required_parameters(&self) -> Vec<Dim> {
                 match *self {
@@ -146,6 +169,49 @@ macro_rules! define_datatypes {
             task.await
         }
 
+        /// collect by block in batches of dataframes, flushed whenever they exceed threshold
+        pub async fn stream_by_block(
+            datatype: MetaDatatype,
+            partition: Partition,
+            source: Arc<Source>,
+            query: Arc<Query>,
+            threshold: FlushThreshold,
+            sender: tokio::sync::mpsc::Sender<HashMap<Datatype, DataFrame>>,
+        ) -> Result<(), CollectError> {
+            let task = match datatype {
+                MetaDatatype::Scalar(datatype) => {
+                    let inner_request_size = if datatype.use_block_ranges() {
+                        Some(source.inner_request_size)
+                    } else {
+                        None
+                    };
+                    match datatype {
+                    $(
+                        Datatype::$datatype => $datatype::stream_by_block(partition, source, query, inner_request_size, threshold, sender),
+                    )*
+                    }
+                },
+                MetaDatatype::Multi(datatype) => match datatype {
+                    MultiDatatype::BlocksAndTransactions => {
+                        BlocksAndTransactions::stream_by_block(partition, source, query, None, threshold, sender)
+                    }
+                    MultiDatatype::CallTraceDerivatives => {
+                        CallTraceDerivatives::stream_by_block(partition, source, query, None, threshold, sender)
+                    }
+                    MultiDatatype::GethStateDiffs => {
+                        GethStateDiffs::stream_by_block(partition, source, query, None, threshold, sender)
+                    },
+                    MultiDatatype::StateDiffs => {
+                        StateDiffs::stream_by_block(partition, source, query, None, threshold, sender)
+                    },
+                    MultiDatatype::StateReads => {
+                        StateReads::stream_by_block(partition, source, query, None, threshold, sender)
+                    },
+                },
+            };
+            task.await
+        }
+
         /// collect by transaction
         pub async fn collect_by_transaction(
             datatype: MetaDatatype,
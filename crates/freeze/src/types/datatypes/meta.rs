@@ -17,6 +17,14 @@ impl MetaDatatype {
             MetaDatatype::Multi(multi_datatype) => multi_datatype.datatypes(),
         }
     }
+
+    /// name
+    pub fn name(&self) -> String {
+        match self {
+            MetaDatatype::Scalar(datatype) => datatype.name(),
+            MetaDatatype::Multi(multi_datatype) => multi_datatype.name(),
+        }
+    }
 }
 
 /// cluster datatypes into MultiDatatype / ScalarDatatype groups
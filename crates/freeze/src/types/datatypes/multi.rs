@@ -26,7 +26,12 @@ impl MultiDatatype {
         match &self {
             MultiDatatype::BlocksAndTransactions => vec![Datatype::Blocks, Datatype::Transactions],
             MultiDatatype::CallTraceDerivatives => {
-                vec![Datatype::Contracts, Datatype::NativeTransfers, Datatype::Traces]
+                vec![
+                    Datatype::Contracts,
+                    Datatype::NativeTransfers,
+                    Datatype::Selfdestructs,
+                    Datatype::Traces,
+                ]
             }
             MultiDatatype::GethStateDiffs => vec![
                 Datatype::GethBalanceDiffs,
@@ -3,19 +3,27 @@ use polars::prelude::*;
 use std::collections::HashMap;
 
 define_datatypes!(
+    AccessLists,
     AddressAppearances,
     BalanceDiffs,
     BalanceReads,
     Balances,
+    Blobs,
+    BlockRewards,
     Blocks,
     CodeDiffs,
     CodeReads,
     Codes,
     Contracts,
+    DecodedCalls,
+    DecodedEvents,
+    Erc1155Transfers,
+    Erc20Approvals,
     Erc20Balances,
     Erc20Metadata,
     Erc20Supplies,
     Erc20Transfers,
+    Erc4626Snapshots,
     Erc721Metadata,
     Erc721Transfers,
     EthCalls,
@@ -28,17 +36,26 @@ define_datatypes!(
     GethOpcodes,
     JavascriptTraces,
     Logs,
+    Mempool,
     NativeTransfers,
     NonceDiffs,
     NonceReads,
     Nonces,
+    OpcodeCounts,
+    PrecompileCalls,
+    Receipts,
+    RevertReasons,
+    Selfdestructs,
     Slots,
     StorageDiffs,
     StorageReads,
     Traces,
     TraceCalls,
+    TransactionFees,
     Transactions,
+    Uncles,
     VmTraces,
+    Withdrawals,
 );
 
 impl Datatype {
@@ -70,3 +87,160 @@ impl std::str::FromStr for Datatype {
             .ok_or_else(|| ParseError::ParseError(format!("no datatype matches input: {}", s)))
     }
 }
+
+impl Datatype {
+    /// one-line description of datatype
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Datatype::AccessLists => "addresses and storage keys of transaction access lists",
+            Datatype::AddressAppearances => {
+                "addresses appearing in transactions, traces, and log topics"
+            }
+            Datatype::BalanceDiffs => "native balance changes of each transaction",
+            Datatype::BalanceReads => "native balances read by each transaction",
+            Datatype::Balances => "native balances of addresses at each block",
+            Datatype::Blobs => "blobs of blob transactions, with sidecars from a beacon node",
+            Datatype::BlockRewards => "miner and validator rewards of each block",
+            Datatype::Blocks => "block headers",
+            Datatype::CodeDiffs => "contract code changes of each transaction",
+            Datatype::CodeReads => "contract code read by each transaction",
+            Datatype::Codes => "contract code of addresses at each block",
+            Datatype::Contracts => "contracts deployed by each transaction",
+            Datatype::DecodedCalls => "transactions decoded by the functions of an abi",
+            Datatype::DecodedEvents => "logs decoded by the events of an abi",
+            Datatype::Erc1155Transfers => "erc1155 single and batch transfers",
+            Datatype::Erc20Approvals => "erc20 approval events",
+            Datatype::Erc20Balances => "erc20 balances of addresses at each block",
+            Datatype::Erc20Metadata => "name, symbol, and decimals of erc20 tokens",
+            Datatype::Erc20Supplies => "total supplies of erc20 tokens at each block",
+            Datatype::Erc20Transfers => "erc20 transfer events",
+            Datatype::Erc4626Snapshots => "assets, supplies, and share prices of erc4626 vaults",
+            Datatype::Erc721Metadata => "name and symbol of erc721 tokens",
+            Datatype::Erc721Transfers => "erc721 transfer events",
+            Datatype::EthCalls => "outputs of eth_call requests at each block",
+            Datatype::FourByteCounts => "number of calls of each function selector",
+            Datatype::GethCalls => "call traces from the geth call tracer",
+            Datatype::GethCodeDiffs => "contract code changes from the geth prestate tracer",
+            Datatype::GethBalanceDiffs => "native balance changes from the geth prestate tracer",
+            Datatype::GethStorageDiffs => "storage changes from the geth prestate tracer",
+            Datatype::GethNonceDiffs => "nonce changes from the geth prestate tracer",
+            Datatype::GethOpcodes => "opcode steps of transactions from the geth struct logger",
+            Datatype::JavascriptTraces => "outputs of a custom javascript tracer",
+            Datatype::Logs => "event logs",
+            Datatype::Mempool => "pending and queued transactions of the mempool",
+            Datatype::NativeTransfers => "transfers of native currency between addresses",
+            Datatype::NonceDiffs => "nonce changes of each transaction",
+            Datatype::NonceReads => "nonces read by each transaction",
+            Datatype::Nonces => "nonces of addresses at each block",
+            Datatype::OpcodeCounts => "number and gas of each opcode executed by transactions",
+            Datatype::PrecompileCalls => "calls to precompiled contracts",
+            Datatype::Receipts => "transaction receipts",
+            Datatype::RevertReasons => "revert messages and panic codes of failed transactions",
+            Datatype::Selfdestructs => "selfdestructs of contracts",
+            Datatype::Slots => "storage slot values of addresses at each block",
+            Datatype::StorageDiffs => "storage changes of each transaction",
+            Datatype::StorageReads => "storage slots read by each transaction",
+            Datatype::Traces => "parity traces of transactions",
+            Datatype::TraceCalls => "traces of trace_call requests at each block",
+            Datatype::TransactionFees => "gas, base fees, and priority fees paid by transactions",
+            Datatype::Transactions => "transactions",
+            Datatype::Uncles => "uncle block headers",
+            Datatype::VmTraces => "vm execution steps of transactions",
+            Datatype::Withdrawals => "validator withdrawals of each block",
+        }
+    }
+
+    /// columns, aliases, and collection modes of datatype, without connecting to an rpc
+    pub fn info(&self) -> DatatypeInfo {
+        let default_columns = self.default_columns();
+        let nullable_columns = self.nullable_columns();
+        let columns = self
+            .column_types()
+            .into_iter()
+            .map(|(name, column_type)| ColumnInfo {
+                name: name.to_string(),
+                column_type: column_type.as_str().to_string(),
+                default: default_columns.contains(&name),
+                nullable: nullable_columns.contains(&name),
+            })
+            .collect();
+        DatatypeInfo {
+            name: self.name(),
+            description: self.description().to_string(),
+            aliases: self.aliases().iter().map(|alias| alias.to_string()).collect(),
+            columns,
+            default_sort: self.default_sort(),
+            required_parameters: self
+                .required_parameters()
+                .iter()
+                .map(|dim| dim.to_string())
+                .collect(),
+            optional_parameters: self
+                .optional_parameters()
+                .iter()
+                .map(|dim| dim.to_string())
+                .collect(),
+            collect_by_block: self.can_collect_by_block(),
+            collect_by_transaction: self.can_collect_by_transaction(),
+        }
+    }
+}
+
+/// description of a datatype
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct DatatypeInfo {
+    /// name of datatype
+    pub name: String,
+    /// one-line description
+    pub description: String,
+    /// aliases accepted in place of the name
+    pub aliases: Vec<String>,
+    /// columns in schema order
+    pub columns: Vec<ColumnInfo>,
+    /// columns sorted by by default
+    pub default_sort: Vec<String>,
+    /// parameters that must be given, such as addresses
+    pub required_parameters: Vec<String>,
+    /// parameters that can be given
+    pub optional_parameters: Vec<String>,
+    /// whether datatype can be collected by block
+    pub collect_by_block: bool,
+    /// whether datatype can be collected by transaction
+    pub collect_by_transaction: bool,
+}
+
+/// description of a column of a datatype
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ColumnInfo {
+    /// name of column
+    pub name: String,
+    /// type of column, before u256 and binary columns are converted to their output types
+    #[serde(rename = "type")]
+    pub column_type: String,
+    /// whether column is included by default
+    pub default: bool,
+    /// whether column can contain null values
+    pub nullable: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datatype_info() {
+        for datatype in Datatype::all() {
+            let info = datatype.info();
+            assert!(!info.description.is_empty());
+            let columns: Vec<_> = info.columns.iter().map(|column| column.name.as_str()).collect();
+            for column in datatype.default_columns() {
+                assert!(columns.contains(&column), "{} {}", info.name, column);
+            }
+        }
+        assert!(!Datatype::Balances.info().collect_by_transaction);
+        let info = Datatype::Blocks.info();
+        assert!(info.collect_by_block && info.collect_by_transaction);
+        let column = info.columns.iter().find(|column| column.name == "block_hash").unwrap();
+        assert_eq!((column.column_type.as_str(), column.default), ("binary", true));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{
+    err, CollectError, ColumnType, Datatype, ParseError, Table, ToVecChecksumHex, ToVecHex,
+};
+use ethers::prelude::*;
+use ethers_core::abi::{Abi, Event, Function, ParamType, RawLog, Token};
+use indexmap::IndexMap;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// decoded item name along with a value for each of its columns
+pub type DecodedValues = (String, Vec<(String, Token)>);
+
+/// container for decoding events and function calls using a contract abi
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbiDecoder {
+    /// path of abi file
+    pub path: String,
+    /// parsed abi
+    pub abi: Abi,
+    /// events by topic0, along with the column name of each event input
+    events: HashMap<H256, (Event, Vec<String>)>,
+    /// type of each event column
+    event_columns: IndexMap<String, ColumnType>,
+    /// functions by selector, along with the column name of each function input
+    functions: HashMap<[u8; 4], (Function, Vec<String>)>,
+    /// type of each function column
+    function_columns: IndexMap<String, ColumnType>,
+}
+
+impl AbiDecoder {
+    /// load abi from a json file
+    pub fn from_file(path: &str) -> Result<Self, ParseError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ParseError::ParseError(format!("could not read abi {} ({})", path, e)))?;
+        let abi: Abi = serde_json::from_str(&contents)
+            .map_err(|e| ParseError::ParseError(format!("could not parse abi {} ({})", path, e)))?;
+        Ok(AbiDecoder::new(path.to_string(), abi))
+    }
+
+    /// create decoder from an abi
+    pub fn new(path: String, abi: Abi) -> Self {
+        let reserved = Datatype::DecodedEvents.column_types();
+        let mut events = HashMap::new();
+        let mut event_columns = IndexMap::new();
+        for event in abi.events().filter(|event| !event.anonymous) {
+            let params = event
+                .inputs
+                .iter()
+                .map(|input| {
+                    // indexed dynamic values are only available as the hash of their value
+                    if input.indexed && is_hashed_when_indexed(&input.kind) {
+                        (input.name.as_str(), "_hash", ColumnType::Binary)
+                    } else {
+                        (input.name.as_str(), "", param_column_type(&input.kind))
+                    }
+                })
+                .collect();
+            let names = add_param_columns(&event.name, params, &reserved, &mut event_columns);
+            events.insert(event.signature(), (event.clone(), names));
+        }
+
+        let reserved = Datatype::DecodedCalls.column_types();
+        let mut functions = HashMap::new();
+        let mut function_columns = IndexMap::new();
+        for function in abi.functions() {
+            let params = function
+                .inputs
+                .iter()
+                .map(|input| (input.name.as_str(), "", param_column_type(&input.kind)))
+                .collect();
+            let names = add_param_columns(&function.name, params, &reserved, &mut function_columns);
+            functions.insert(function.short_signature(), (function.clone(), names));
+        }
+
+        AbiDecoder { path, abi, events, event_columns, functions, function_columns }
+    }
+
+    /// get topic0 of each decodable event
+    pub fn event_topics(&self) -> Vec<H256> {
+        self.events.keys().cloned().collect()
+    }
+
+    /// get column types of event parameters
+    pub fn event_columns(&self) -> &IndexMap<String, ColumnType> {
+        &self.event_columns
+    }
+
+    /// get column types of function parameters
+    pub fn function_columns(&self) -> &IndexMap<String, ColumnType> {
+        &self.function_columns
+    }
+
+    /// get dynamic columns added to a datatype by the abi
+    pub fn columns(&self, datatype: &Datatype) -> IndexMap<String, ColumnType> {
+        match datatype {
+            Datatype::DecodedEvents => self.event_columns.clone(),
+            Datatype::DecodedCalls => self.function_columns.clone(),
+            _ => IndexMap::new(),
+        }
+    }
+
+    /// decode log into event name and a value for each event column,
+    /// returns None if the log is not an event of the abi
+    pub fn decode_log(&self, log: &Log) -> Option<Result<DecodedValues, String>> {
+        let (event, names) = self.events.get(log.topics.first()?)?;
+        let raw = RawLog { topics: log.topics.clone(), data: log.data.to_vec() };
+        Some(match event.parse_log(raw) {
+            Ok(parsed) => Ok((
+                event.name.clone(),
+                names.iter().cloned().zip(parsed.params.into_iter().map(|p| p.value)).collect(),
+            )),
+            Err(e) => Err(e.to_string()),
+        })
+    }
+
+    /// whether call data starts with the selector of a function of the abi
+    pub fn has_function(&self, input: &[u8]) -> bool {
+        input.get(..4).is_some_and(|selector| self.functions.contains_key(selector))
+    }
+
+    /// decode call data into function name and a value for each function column,
+    /// returns None if the selector is not a function of the abi
+    pub fn decode_call(&self, input: &[u8]) -> Option<Result<DecodedValues, String>> {
+        let (function, names) = self.functions.get(input.get(..4)?)?;
+        Some(match function.decode_input(&input[4..]) {
+            Ok(tokens) => Ok((function.name.clone(), names.iter().cloned().zip(tokens).collect())),
+            Err(e) => Err(e.to_string()),
+        })
+    }
+}
+
+/// add columns for the (name, suffix, type) params of an event or function, returning the column
+/// name of each param. names that collide with static columns, or with a column of another type,
+/// are prefixed with the name of the event or function
+fn add_param_columns(
+    item_name: &str,
+    params: Vec<(&str, &str, ColumnType)>,
+    reserved: &IndexMap<&'static str, ColumnType>,
+    columns: &mut IndexMap<String, ColumnType>,
+) -> Vec<String> {
+    let mut names = Vec::new();
+    for (index, (name, suffix, ctype)) in params.into_iter().enumerate() {
+        let name = param_name(name, index) + suffix;
+        let name = match columns.get(&name) {
+            _ if reserved.contains_key(name.as_str()) => format!("{}__{}", item_name, name),
+            Some(existing) if *existing != ctype => format!("{}__{}", item_name, name),
+            _ => name,
+        };
+        columns.insert(name.clone(), ctype);
+        names.push(name);
+    }
+    names
+}
+
+fn param_name(name: &str, index: usize) -> String {
+    if name.is_empty() {
+        format!("arg{}", index)
+    } else {
+        name.to_string()
+    }
+}
+
+fn is_hashed_when_indexed(kind: &ParamType) -> bool {
+    matches!(
+        kind,
+        ParamType::String |
+            ParamType::Bytes |
+            ParamType::Array(_) |
+            ParamType::FixedArray(_, _) |
+            ParamType::Tuple(_)
+    )
+}
+
+/// column type used for an abi parameter, nested types are stored as json strings
+pub(crate) fn param_column_type(kind: &ParamType) -> ColumnType {
+    match kind {
+        ParamType::Address | ParamType::Bytes | ParamType::FixedBytes(_) => ColumnType::Binary,
+        ParamType::Uint(bits) if *bits <= 64 => ColumnType::UInt64,
+        ParamType::Uint(_) => ColumnType::UInt256,
+        ParamType::Int(bits) if *bits <= 64 => ColumnType::Int64,
+        ParamType::Int(_) => ColumnType::String,
+        ParamType::Bool => ColumnType::Boolean,
+        ParamType::String => ColumnType::String,
+        ParamType::Array(_) | ParamType::FixedArray(_, _) | ParamType::Tuple(_) => {
+            ColumnType::String
+        }
+    }
+}
+
+/// columns whose names and types are only known at runtime, e.g. from an abi
+#[derive(Default)]
+pub struct DynamicColumns(IndexMap<String, Vec<Option<Token>>>);
+
+impl DynamicColumns {
+    /// push a row of values, schema columns missing from values are stored as null
+    pub fn push_row(&mut self, schema: &Table, values: Vec<(String, Token)>) {
+        let mut values: HashMap<String, Token> = values.into_iter().collect();
+        for name in schema.dynamic_columns() {
+            let value = values.remove(name);
+            self.0.entry(name.to_string()).or_default().push(value);
+        }
+    }
+
+    /// convert to series according to the column types of schema
+    pub fn into_series(mut self, schema: &Table) -> Result<Vec<Series>, CollectError> {
+        let mut cols = Vec::new();
+        for name in schema.dynamic_columns() {
+            let values = self.0.remove(name).unwrap_or_default();
+            match schema.column_type(name) {
+                Some(ColumnType::Binary) => {
+                    let values: Vec<Option<Vec<u8>>> = values.iter().map(token_to_bytes).collect();
+                    cols.push(Series::new(name, values))
+                }
+                Some(ColumnType::Hex) => {
+                    let is_address =
+                        values.iter().flatten().all(|v| matches!(v, Token::Address(_)));
+                    let values: Vec<Option<Vec<u8>>> = values.iter().map(token_to_bytes).collect();
+                    match schema.checksum_addresses && is_address {
+                        true => cols.push(Series::new(name, values.to_vec_checksum_hex())),
+                        false => cols.push(Series::new(name, values.to_vec_hex())),
+                    }
+                }
+                Some(ColumnType::UInt64) => {
+                    let values: Vec<Option<u64>> = values
+                        .iter()
+                        .map(|v| match v {
+                            Some(Token::Uint(v)) => u64::try_from(*v).ok(),
+                            _ => None,
+                        })
+                        .collect();
+                    cols.push(Series::new(name, values))
+                }
+                Some(ColumnType::Int64) => {
+                    let values: Vec<Option<i64>> = values
+                        .iter()
+                        .map(|v| match v {
+                            Some(Token::Int(v)) => i64::try_from(I256::from_raw(*v)).ok(),
+                            _ => None,
+                        })
+                        .collect();
+                    cols.push(Series::new(name, values))
+                }
+                Some(ColumnType::Boolean) => {
+                    let values: Vec<Option<bool>> = values
+                        .iter()
+                        .map(|v| match v {
+                            Some(Token::Bool(v)) => Some(*v),
+                            _ => None,
+                        })
+                        .collect();
+                    cols.push(Series::new(name, values))
+                }
+                Some(ColumnType::String) => {
+                    let values: Vec<Option<String>> =
+                        values.iter().map(|v| v.as_ref().map(token_to_string)).collect();
+                    cols.push(Series::new(name, values))
+                }
+                Some(ColumnType::UInt256) => {
+                    let values: Vec<Option<U256>> = values
+                        .iter()
+                        .map(|v| match v {
+                            Some(Token::Uint(v)) => Some(*v),
+                            _ => None,
+                        })
+                        .collect();
+                    with_series_option_u256!(cols, name, values, schema)
+                }
+                _ => return Err(err(format!("unsupported type for column {}", name).as_str())),
+            }
+        }
+        Ok(cols)
+    }
+}
+
+fn token_to_bytes(token: &Option<Token>) -> Option<Vec<u8>> {
+    match token {
+        Some(Token::Address(address)) => Some(address.as_bytes().to_vec()),
+        Some(Token::Bytes(bytes)) | Some(Token::FixedBytes(bytes)) => Some(bytes.clone()),
+        _ => None,
+    }
+}
+
+/// convert token to string, nested values are encoded as json
+pub(crate) fn token_to_string(token: &Token) -> String {
+    match token {
+        Token::String(s) => s.clone(),
+        Token::Int(v) => I256::from_raw(*v).to_string(),
+        token => token_to_json(token).to_string(),
+    }
+}
+
+fn token_to_json(token: &Token) -> serde_json::Value {
+    match token {
+        Token::Address(address) => serde_json::Value::String(format!("{:?}", address)),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => {
+            serde_json::Value::String(prefix_hex::encode(bytes.clone()))
+        }
+        Token::Int(v) => serde_json::Value::String(I256::from_raw(*v).to_string()),
+        Token::Uint(v) => serde_json::Value::String(v.to_string()),
+        Token::Bool(v) => serde_json::Value::Bool(*v),
+        Token::String(s) => serde_json::Value::String(s.clone()),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            serde_json::Value::Array(tokens.iter().map(token_to_json).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABI: &str = r#"[
+        {"type": "event", "name": "Transfer", "anonymous": false, "inputs": [
+            {"name": "from", "type": "address", "indexed": true},
+            {"name": "to", "type": "address", "indexed": true},
+            {"name": "value", "type": "uint256", "indexed": false}
+        ]},
+        {"type": "event", "name": "Named", "anonymous": false, "inputs": [
+            {"name": "label", "type": "string", "indexed": true},
+            {"name": "value", "type": "int8", "indexed": false},
+            {"name": "", "type": "uint64[]", "indexed": false}
+        ]}
+    ]"#;
+
+    #[test]
+    fn test_event_columns() {
+        let decoder = AbiDecoder::new("abi.json".to_string(), serde_json::from_str(ABI).unwrap());
+        let columns = decoder.event_columns();
+        assert_eq!(columns.get("from"), Some(&ColumnType::Binary));
+        assert_eq!(columns.get("label_hash"), Some(&ColumnType::Binary));
+        // events are processed in order of name, later same-named params of other types get
+        // prefixed with their event name
+        assert_eq!(columns.get("value"), Some(&ColumnType::Int64));
+        assert_eq!(columns.get("Transfer__value"), Some(&ColumnType::UInt256));
+        assert_eq!(columns.get("arg2"), Some(&ColumnType::String));
+    }
+
+    #[test]
+    fn test_decode_call() {
+        let abi = r#"[{"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+            "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+            "outputs": [{"name": "", "type": "bool"}]}]"#;
+        let decoder = AbiDecoder::new("abi.json".to_string(), serde_json::from_str(abi).unwrap());
+        let args = [Token::Address(Address::repeat_byte(1)), Token::Uint(5.into())];
+        let mut input = vec![0xa9, 0x05, 0x9c, 0xbb];
+        input.extend(ethers_core::abi::encode(&args));
+        let (name, values) = decoder.decode_call(&input).unwrap().unwrap();
+        assert_eq!(name, "transfer");
+        assert_eq!(
+            values,
+            vec![("to".to_string(), args[0].clone()), ("amount".to_string(), args[1].clone())]
+        );
+        assert!(decoder.decode_call(&[0, 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn test_nested_token_to_string() {
+        let token = Token::Array(vec![Token::Uint(1.into()), Token::Bool(true)]);
+        assert_eq!(token_to_string(&token), r#"["1",true]"#);
+    }
+}
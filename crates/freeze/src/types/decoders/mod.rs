@@ -1,3 +1,15 @@
+/// abi decoder
+pub mod abi_decoder;
+pub use abi_decoder::*;
+
 /// log decoder
 pub mod log_decoder;
 pub use log_decoder::*;
+
+/// signature resolver
+pub mod signature_resolver;
+pub use signature_resolver::*;
+
+/// revert decoder
+pub mod revert_decoder;
+pub use revert_decoder::*;
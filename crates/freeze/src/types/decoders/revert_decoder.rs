@@ -0,0 +1,74 @@
+use ethers_core::abi::{self, ParamType, Token};
+
+/// selector of `Error(string)`
+pub const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// selector of `Panic(uint256)`
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// decoded revert payload, custom errors are left undecoded
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RevertReason {
+    /// first four bytes of revert data
+    pub selector: Option<Vec<u8>>,
+    /// message of `Error(string)` reverts
+    pub message: Option<String>,
+    /// code of `Panic(uint256)` reverts
+    pub panic_code: Option<u64>,
+}
+
+impl RevertReason {
+    /// decode revert output of a failed call
+    pub fn decode(output: &[u8]) -> RevertReason {
+        if output.len() < 4 {
+            return RevertReason::default()
+        }
+        let (selector, payload) = output.split_at(4);
+        let mut reason = RevertReason { selector: Some(selector.to_vec()), ..Default::default() };
+        if selector == ERROR_STRING_SELECTOR {
+            if let Ok(Some(Token::String(message))) =
+                abi::decode(&[ParamType::String], payload).map(|tokens| tokens.into_iter().next())
+            {
+                reason.message = Some(message)
+            }
+        } else if selector == PANIC_SELECTOR {
+            if let Ok(Some(Token::Uint(code))) = abi::decode(&[ParamType::Uint(256)], payload)
+                .map(|tokens| tokens.into_iter().next())
+            {
+                reason.panic_code = u64::try_from(code).ok()
+            }
+        }
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_error_string() {
+        let mut output = ERROR_STRING_SELECTOR.to_vec();
+        output.extend(abi::encode(&[Token::String("insufficient balance".to_string())]));
+        let reason = RevertReason::decode(&output);
+        assert_eq!(reason.message, Some("insufficient balance".to_string()));
+        assert_eq!(reason.panic_code, None);
+    }
+
+    #[test]
+    fn test_decode_panic() {
+        let mut output = PANIC_SELECTOR.to_vec();
+        output.extend(abi::encode(&[Token::Uint(0x11.into())]));
+        let reason = RevertReason::decode(&output);
+        assert_eq!(reason.message, None);
+        assert_eq!(reason.panic_code, Some(0x11));
+    }
+
+    #[test]
+    fn test_decode_custom_error() {
+        let reason = RevertReason::decode(&[0xde, 0xad, 0xbe, 0xef, 0x01]);
+        assert_eq!(reason.selector, Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(reason.message, None);
+        assert_eq!(reason.panic_code, None);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{CollectError, ParseError};
+use ethers::utils::hex;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tokio::sync::Mutex;
+
+const OPENCHAIN_LOOKUP_URL: &str = "https://api.openchain.xyz/signature-database/v1/lookup";
+
+/// max number of selectors per openchain request
+const OPENCHAIN_BATCH_SIZE: usize = 100;
+
+/// resolves 4byte selectors into text signatures, e.g. 0xa9059cbb -> transfer(address,uint256)
+#[derive(Debug, Default)]
+pub struct SignatureResolver {
+    /// signatures loaded from a local database file
+    local: HashMap<Vec<u8>, String>,
+    /// path of on-disk cache for openchain lookups, api is not used if None
+    api_cache_path: Option<PathBuf>,
+    /// every selector looked up during this run, None if no match was found
+    resolved: Mutex<HashMap<Vec<u8>, Option<String>>>,
+    /// serializes writes of the api cache file
+    saving: Mutex<()>,
+}
+
+impl SignatureResolver {
+    /// load selectors from a csv file of `selector,signature` rows
+    pub fn from_csv(path: &str) -> Result<Self, ParseError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ParseError::ParseError(format!("could not read signature database {} ({})", path, e))
+        })?;
+        Ok(SignatureResolver { local: parse_signature_csv(&contents), ..Default::default() })
+    }
+
+    /// resolve selectors missing from the local database using the openchain api,
+    /// caching api results at the given path
+    pub fn with_api_cache(self, path: PathBuf) -> Result<Self, ParseError> {
+        let cached = if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(|_| {
+                ParseError::ParseError("could not read signature cache".to_string())
+            })?;
+            let cached: HashMap<String, String> =
+                serde_json::from_str(&contents).map_err(|_| {
+                    ParseError::ParseError("could not parse signature cache".to_string())
+                })?;
+            cached
+                .into_iter()
+                .filter_map(|(selector, text)| Some((decode_selector(&selector)?, Some(text))))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        Ok(SignatureResolver { api_cache_path: Some(path), resolved: Mutex::new(cached), ..self })
+    }
+
+    /// resolve selectors to text signatures, selectors without a match are omitted
+    pub async fn resolve(
+        &self,
+        selectors: &HashSet<Vec<u8>>,
+    ) -> Result<HashMap<Vec<u8>, String>, CollectError> {
+        // lock is only held to read and insert entries, so concurrent chunks may look up the same
+        // selector but never wait on each other's file or api lookups
+        let missing: Vec<Vec<u8>> = {
+            let resolved = self.resolved.lock().await;
+            selectors.iter().filter(|selector| !resolved.contains_key(*selector)).cloned().collect()
+        };
+
+        let mut results = HashMap::new();
+        let mut unresolved = Vec::new();
+        for selector in missing.into_iter() {
+            match self.local.get(&selector) {
+                Some(text) => {
+                    results.insert(selector, Some(text.clone()));
+                }
+                None => unresolved.push(selector),
+            }
+        }
+        let mut api_results = HashMap::new();
+        if let (Some(_), false) = (&self.api_cache_path, unresolved.is_empty()) {
+            for batch in unresolved.chunks(OPENCHAIN_BATCH_SIZE) {
+                api_results.extend(lookup_openchain(batch).await?);
+            }
+        }
+        for selector in unresolved.into_iter() {
+            let text = api_results.get(&selector).cloned();
+            results.insert(selector, text);
+        }
+
+        let output = {
+            let mut resolved = self.resolved.lock().await;
+            resolved.extend(results);
+            selectors
+                .iter()
+                .filter_map(|selector| {
+                    resolved.get(selector).cloned().flatten().map(|text| (selector.clone(), text))
+                })
+                .collect()
+        };
+
+        if let (Some(cache_path), false) = (&self.api_cache_path, api_results.is_empty()) {
+            // snapshot is taken while holding the save lock so the last write has every entry
+            let _saving = self.saving.lock().await;
+            let snapshot = self.resolved.lock().await.clone();
+            save_cache(cache_path, &snapshot)?;
+        }
+
+        Ok(output)
+    }
+}
+
+fn decode_selector(selector: &str) -> Option<Vec<u8>> {
+    let selector = selector.trim().trim_matches('"');
+    let bytes = hex::decode(selector.strip_prefix("0x").unwrap_or(selector)).ok()?;
+    if bytes.len() == 4 {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+/// parse `selector,signature` rows, rows that are not valid (e.g. headers) are skipped
+fn parse_signature_csv(contents: &str) -> HashMap<Vec<u8>, String> {
+    let mut signatures = HashMap::new();
+    for line in contents.lines() {
+        if let Some((selector, text)) = line.split_once(',') {
+            let text = text.trim().trim_matches('"');
+            if let (Some(selector), false) = (decode_selector(selector), text.is_empty()) {
+                // keep first entry when there are selector collisions
+                signatures.entry(selector).or_insert_with(|| text.to_string());
+            }
+        }
+    }
+    signatures
+}
+
+async fn lookup_openchain(selectors: &[Vec<u8>]) -> Result<HashMap<Vec<u8>, String>, CollectError> {
+    let query: Vec<String> =
+        selectors.iter().map(|selector| format!("0x{}", hex::encode(selector))).collect();
+    let url = format!("{}?function={}&filter=true", OPENCHAIN_LOOKUP_URL, query.join(","));
+    let response: serde_json::Value = reqwest::get(url)
+        .await
+        .map_err(|e| CollectError::CollectError(format!("openchain request failed ({})", e)))?
+        .json()
+        .await
+        .map_err(|e| CollectError::CollectError(format!("invalid openchain response ({})", e)))?;
+
+    let mut signatures = HashMap::new();
+    if let Some(functions) = response["result"]["function"].as_object() {
+        for (selector, matches) in functions.iter() {
+            let text = matches.as_array().and_then(|m| m.first()).and_then(|m| m["name"].as_str());
+            if let (Some(selector), Some(text)) = (decode_selector(selector), text) {
+                signatures.insert(selector, text.to_string());
+            }
+        }
+    }
+    Ok(signatures)
+}
+
+fn save_cache(
+    path: &Path,
+    resolved: &HashMap<Vec<u8>, Option<String>>,
+) -> Result<(), CollectError> {
+    let cache: HashMap<String, &String> = resolved
+        .iter()
+        .filter_map(|(selector, text)| {
+            text.as_ref().map(|text| (format!("0x{}", hex::encode(selector)), text))
+        })
+        .collect();
+    let json = serde_json::to_string(&cache)
+        .map_err(|_| CollectError::CollectError("could not serialize signature cache".into()))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|_| CollectError::CollectError("could not create cache dir".into()))?;
+    }
+    std::fs::write(path, json)
+        .map_err(|_| CollectError::CollectError("could not write signature cache".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signature_csv() {
+        let contents = "selector,signature\n\
+                        0xa9059cbb,transfer(address,uint256)\n\
+                        0x095ea7b3,\"approve(address,uint256)\"\n\
+                        0xa9059cbb,collision(bytes)\n\
+                        not_hex,foo()\n";
+        let signatures = parse_signature_csv(contents);
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[&vec![0xa9, 0x05, 0x9c, 0xbb]], "transfer(address,uint256)");
+        assert_eq!(signatures[&vec![0x09, 0x5e, 0xa7, 0xb3]], "approve(address,uint256)");
+    }
+}
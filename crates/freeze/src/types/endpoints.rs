@@ -0,0 +1,884 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::{mpsc, oneshot, Semaphore, SemaphorePermit};
+
+use crate::{
+    autoscale::{AutoscalePermit, AutoscaleState, Autoscaler, RequestOutcome},
+    retries::{classify, classify_provider_error, ErrorClass},
+    transports::Transport,
+    CollectError, CollectionEvent, EventHandler, EventSubscriber, ResponseCache, RetryOptions,
+};
+
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// time that calls wait for other calls to join their batch
+const BATCH_DELAY: Duration = Duration::from_millis(5);
+
+/// how requests are distributed across the endpoints of a pool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EndpointStrategy {
+    /// send requests to each endpoint in turn
+    #[default]
+    RoundRobin,
+    /// send requests to the endpoint with the fewest requests in flight
+    LeastInFlight,
+}
+
+/// options of an endpoint pool
+#[derive(Clone, Debug)]
+pub struct EndpointOptions {
+    /// how requests are distributed across endpoints
+    pub strategy: EndpointStrategy,
+    /// time that an erroring endpoint is quarantined before it is probed again
+    pub cooldown: Duration,
+    /// maximum number of concurrent requests of each endpoint
+    pub max_concurrent_requests: Option<u64>,
+    /// maximum number of calls sent together as a json-rpc batch
+    pub batch_size: Option<u64>,
+    /// adapt the number of concurrent requests to rate limits and latency, up to this maximum
+    pub autoscale: Option<u64>,
+    /// headers sent with each http request, websocket connections only send the authorization
+    /// header
+    pub headers: reqwest::header::HeaderMap,
+}
+
+impl Default for EndpointOptions {
+    fn default() -> EndpointOptions {
+        EndpointOptions {
+            strategy: EndpointStrategy::default(),
+            cooldown: DEFAULT_COOLDOWN,
+            max_concurrent_requests: None,
+            batch_size: None,
+            autoscale: None,
+            headers: Default::default(),
+        }
+    }
+}
+
+/// upper bounds of the buckets of request latency histograms, in milliseconds
+pub const LATENCY_BUCKETS_MS: [u64; 12] =
+    [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000];
+
+/// latencies of the requests of an endpoint
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct LatencyHistogram {
+    /// number of requests in each bucket of LATENCY_BUCKETS_MS, then the requests slower than
+    /// every bucket
+    pub counts: Vec<u64>,
+    /// total latency of requests, in microseconds
+    pub total_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> LatencyHistogram {
+        LatencyHistogram { counts: vec![0; LATENCY_BUCKETS_MS.len() + 1], total_us: 0 }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|bound| ms <= *bound);
+        self.counts[bucket.unwrap_or(LATENCY_BUCKETS_MS.len())] += 1;
+        self.total_us += latency.as_micros() as u64;
+    }
+
+    /// number of requests recorded
+    pub fn n_requests(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// mean latency of requests, in milliseconds
+    pub fn mean_ms(&self) -> f64 {
+        match self.n_requests() {
+            0 => 0.0,
+            n => self.total_us as f64 / n as f64 / 1000.0,
+        }
+    }
+}
+
+/// number of requests and errors of an endpoint
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct EndpointStats {
+    /// url of endpoint
+    pub url: String,
+    /// number of requests sent to endpoint
+    pub requests: u64,
+    /// number of requests that errored, including rate limited requests
+    pub errors: u64,
+    /// number of requests that were rate limited
+    pub rate_limited: u64,
+    /// number of times endpoint was quarantined
+    pub quarantines: u64,
+    /// number of requests currently waiting for a response
+    pub in_flight: u64,
+    /// latencies of requests, batches count as one request
+    pub latency: LatencyHistogram,
+}
+
+impl EndpointStats {
+    /// percent of requests that errored
+    pub fn error_rate(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            requests => 100.0 * self.errors as f64 / requests as f64,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    transport: Transport,
+    semaphore: Option<Semaphore>,
+    in_flight: AtomicU64,
+    quarantined_until: Mutex<Option<Instant>>,
+    requests: AtomicU64,
+    errors: AtomicU64,
+    rate_limited: AtomicU64,
+    quarantines: AtomicU64,
+    latency: Mutex<LatencyHistogram>,
+}
+
+/// request counted in the in flight requests of an endpoint
+struct InFlight<'a>(&'a AtomicU64);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Endpoint {
+    fn is_quarantined(&self, now: Instant) -> bool {
+        self.quarantined_until().map(|until| until > now).unwrap_or(false)
+    }
+
+    fn quarantined_until(&self) -> Option<Instant> {
+        self.quarantined_until.lock().ok().and_then(|until| *until)
+    }
+
+    /// count a request as in flight until the returned guard is dropped, including when the
+    /// request future is cancelled
+    fn track_in_flight(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(&self.in_flight)
+    }
+
+    async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.semaphore {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    async fn request<R: DeserializeOwned + Send>(
+        &self,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> Result<R, RetryClientError> {
+        let _in_flight = self.track_in_flight();
+        let _permit = self.acquire().await;
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let t_start = Instant::now();
+        let result = self.transport.request(method, params).await;
+        self.record_latency(t_start.elapsed());
+        result
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        if let Ok(mut histogram) = self.latency.lock() {
+            histogram.record(latency);
+        }
+    }
+
+    fn record_error(&self, rate_limited: bool, quarantine_until: Option<Instant>) {
+        self.errors.fetch_add(1, Ordering::SeqCst);
+        if rate_limited {
+            self.rate_limited.fetch_add(1, Ordering::SeqCst);
+        }
+        if let (Some(until), Ok(mut quarantined_until)) =
+            (quarantine_until, self.quarantined_until.lock())
+        {
+            if !quarantined_until.map(|current| current > Instant::now()).unwrap_or(false) {
+                self.quarantines.fetch_add(1, Ordering::SeqCst);
+            }
+            *quarantined_until = Some(until);
+        }
+    }
+
+    fn stats(&self) -> EndpointStats {
+        EndpointStats {
+            url: self.url.clone(),
+            requests: self.requests.load(Ordering::SeqCst),
+            errors: self.errors.load(Ordering::SeqCst),
+            rate_limited: self.rate_limited.load(Ordering::SeqCst),
+            quarantines: self.quarantines.load(Ordering::SeqCst),
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            latency: self.latency.lock().map(|latency| latency.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// json-rpc client that distributes requests across rpc endpoints
+///
+/// endpoints that fail with connection errors or rate limits are quarantined for a cooldown and
+/// their requests are retried on the other endpoints, once the cooldown has passed the next
+/// request sent to the endpoint probes whether it has recovered
+#[derive(Debug)]
+pub struct EndpointPool {
+    endpoints: Vec<Endpoint>,
+    strategy: EndpointStrategy,
+    cooldown: Duration,
+    retries: RetryOptions,
+    /// number of retries of each method
+    retry_counts: Mutex<BTreeMap<String, u64>>,
+    /// number of failed requests of each error class
+    error_counts: Mutex<BTreeMap<String, u64>>,
+    next: AtomicUsize,
+    batcher: Option<Batcher>,
+    autoscaler: Option<Autoscaler>,
+    /// cache of responses, set once the chain id is known
+    cache: OnceLock<ResponseCache>,
+    /// handler of retry events
+    events: EventSubscriber,
+}
+
+impl EndpointPool {
+    /// connect to a pool of http, websocket, or ipc endpoints
+    ///
+    /// rate limited and transient failures are retried up to max_retries times, several endpoints
+    /// retry them on other endpoints while a single endpoint retries them after a backoff
+    pub async fn connect(
+        urls: &[String],
+        max_retries: u32,
+        initial_backoff: u64,
+        options: &EndpointOptions,
+    ) -> Result<EndpointPool, CollectError> {
+        if urls.is_empty() {
+            return Err(CollectError::RPCError("no rpc urls given".to_string()))
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(options.headers.clone())
+            .build()
+            .map_err(|e| CollectError::RPCError(format!("could not build http client: {}", e)))?;
+        let mut endpoints = Vec::new();
+        for url in urls.iter() {
+            let transport = Transport::connect(url, &client, &options.headers).await?;
+            endpoints.push(Endpoint {
+                url: url.clone(),
+                transport,
+                semaphore: options.max_concurrent_requests.map(|n| Semaphore::new(n as usize)),
+                in_flight: AtomicU64::new(0),
+                quarantined_until: Mutex::new(None),
+                requests: AtomicU64::new(0),
+                errors: AtomicU64::new(0),
+                rate_limited: AtomicU64::new(0),
+                quarantines: AtomicU64::new(0),
+                latency: Default::default(),
+            });
+        }
+        Ok(EndpointPool {
+            endpoints,
+            strategy: options.strategy,
+            cooldown: options.cooldown,
+            retries: RetryOptions {
+                max_retries,
+                initial_backoff: Duration::from_millis(initial_backoff),
+            },
+            retry_counts: Default::default(),
+            error_counts: Default::default(),
+            next: AtomicUsize::new(0),
+            batcher: options.batch_size.filter(|size| *size > 1).map(|size| Batcher {
+                size: size as usize,
+                client: client.clone(),
+                queue: Mutex::new(Vec::new()),
+                next_id: AtomicU64::new(0),
+                supported: AtomicBool::new(true),
+            }),
+            autoscaler: options.autoscale.map(Autoscaler::new),
+            cache: OnceLock::new(),
+            events: Default::default(),
+        })
+    }
+
+    /// urls of endpoints
+    pub fn urls(&self) -> Vec<String> {
+        self.endpoints.iter().map(|endpoint| endpoint.url.clone()).collect()
+    }
+
+    /// number of requests and errors of each endpoint
+    pub fn stats(&self) -> Vec<EndpointStats> {
+        self.endpoints.iter().map(|endpoint| endpoint.stats()).collect()
+    }
+
+    /// answer requests for final blocks from a response cache, a cache can only be set once
+    pub fn set_cache(&self, cache: ResponseCache) {
+        let _ = self.cache.set(cache);
+    }
+
+    /// response cache of pool, if set
+    pub fn cache(&self) -> Option<&ResponseCache> {
+        self.cache.get()
+    }
+
+    /// send retry events to handler, replacing the previous handler
+    pub fn subscribe(&self, handler: Option<Arc<dyn EventHandler>>) {
+        self.events.subscribe(handler)
+    }
+
+    /// number of retries of each method
+    pub fn retry_counts(&self) -> BTreeMap<String, u64> {
+        self.retry_counts.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+
+    /// number of failed requests of each error class, such as rate_limited or transient
+    pub fn error_counts(&self) -> BTreeMap<String, u64> {
+        self.error_counts.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+
+    fn record_error_class(&self, class: ErrorClass) {
+        if let Ok(mut counts) = self.error_counts.lock() {
+            *counts.entry(class.name().to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// current settings of adaptive concurrency, if enabled
+    pub fn autoscale_state(&self) -> Option<AutoscaleState> {
+        self.autoscaler.as_ref().map(|autoscaler| autoscaler.state())
+    }
+
+    /// numbers of new blocks, from a newHeads subscription of the first websocket endpoint
+    ///
+    /// the receiver is closed if the subscription fails or ends
+    pub fn subscribe_new_heads(&self) -> Option<mpsc::UnboundedReceiver<u64>> {
+        let ws = self.endpoints.iter().find_map(|endpoint| match &endpoint.transport {
+            Transport::Ws(_, ws) => Some(ws.clone()),
+            _ => None,
+        })?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let provider = Provider::new(ws);
+            let mut blocks = match provider.subscribe_blocks().await {
+                Ok(blocks) => blocks,
+                Err(_) => return,
+            };
+            while let Some(block) = blocks.next().await {
+                let sent = block.number.map(|number| sender.send(number.as_u64()));
+                if let Some(Err(_)) = sent {
+                    return
+                }
+            }
+        });
+        Some(receiver)
+    }
+
+    /// endpoint that should receive the next request, or the end of the earliest cooldown if
+    /// every endpoint is quarantined
+    fn choose(&self, now: Instant) -> Result<&Endpoint, Instant> {
+        let n_endpoints = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::SeqCst);
+        let mut healthy = (0..n_endpoints)
+            .map(|i| &self.endpoints[(start + i) % n_endpoints])
+            .filter(|endpoint| !endpoint.is_quarantined(now));
+        let endpoint = match self.strategy {
+            EndpointStrategy::RoundRobin => healthy.next(),
+            EndpointStrategy::LeastInFlight => {
+                healthy.min_by_key(|endpoint| endpoint.in_flight.load(Ordering::SeqCst))
+            }
+        };
+        match endpoint {
+            Some(endpoint) => Ok(endpoint),
+            None => Err(self
+                .endpoints
+                .iter()
+                .filter_map(|endpoint| endpoint.quarantined_until())
+                .min()
+                .unwrap_or(now)),
+        }
+    }
+
+    async fn select(&self) -> &Endpoint {
+        loop {
+            let now = Instant::now();
+            match self.choose(now) {
+                Ok(endpoint) => return endpoint,
+                Err(until) => tokio::time::sleep(until.saturating_duration_since(now)).await,
+            }
+        }
+    }
+}
+
+impl EndpointPool {
+    async fn acquire_autoscale(&self) -> Option<AutoscalePermit<'_>> {
+        match &self.autoscaler {
+            Some(autoscaler) => Some(autoscaler.acquire().await),
+            None => None,
+        }
+    }
+
+    fn record_autoscale(&self, t_start: Instant, outcome: RequestOutcome) {
+        if let Some(autoscaler) = &self.autoscaler {
+            autoscaler.record(t_start.elapsed(), outcome);
+        }
+    }
+}
+
+/// response to a call of a batch
+#[derive(Debug)]
+enum BatchResponse {
+    Result(serde_json::Value),
+    Error(JsonRpcError),
+    /// call should be sent as a single request instead
+    Unbatched,
+}
+
+#[derive(Debug)]
+struct BatchCall {
+    id: u64,
+    method: String,
+    params: Option<serde_json::Value>,
+    sender: oneshot::Sender<BatchResponse>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BatchEntry {
+    id: u64,
+    #[serde(default)]
+    result: serde_json::Value,
+    #[serde(default)]
+    error: Option<JsonRpcError>,
+}
+
+/// calls waiting to be sent together as a json-rpc batch
+#[derive(Debug)]
+struct Batcher {
+    size: usize,
+    client: reqwest::Client,
+    queue: Mutex<Vec<BatchCall>>,
+    next_id: AtomicU64,
+    /// whether endpoints accept batches, cleared when a batch is rejected
+    supported: AtomicBool,
+}
+
+impl Batcher {
+    /// queue a call, returning the queued calls if the batch is full
+    fn push(&self, call: BatchCall) -> Option<Vec<BatchCall>> {
+        let mut queue = self.queue.lock().ok()?;
+        queue.push(call);
+        match queue.len() >= self.size {
+            true => Some(std::mem::take(&mut *queue)),
+            false => None,
+        }
+    }
+
+    fn take(&self) -> Vec<BatchCall> {
+        self.queue.lock().map(|mut queue| std::mem::take(&mut *queue)).unwrap_or_default()
+    }
+}
+
+impl EndpointPool {
+    /// send a call as part of a batch, calls wait briefly for other calls to join their batch and
+    /// whichever call flushes the queue sends the batch for all of them
+    async fn request_batched(
+        &self,
+        batcher: &Batcher,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> BatchResponse {
+        if !batcher.supported.load(Ordering::SeqCst) {
+            return BatchResponse::Unbatched
+        }
+        let (sender, mut receiver) = oneshot::channel();
+        let id = batcher.next_id.fetch_add(1, Ordering::SeqCst);
+        let call = BatchCall { id, method: method.to_string(), params: params.clone(), sender };
+        if let Some(calls) = batcher.push(call) {
+            self.send_batch(batcher, calls).await;
+        }
+        if let Ok(response) = tokio::time::timeout(BATCH_DELAY, &mut receiver).await {
+            return response.unwrap_or(BatchResponse::Unbatched)
+        }
+        let calls = batcher.take();
+        if !calls.is_empty() {
+            self.send_batch(batcher, calls).await;
+        }
+        // calls of a batch whose sender was dropped are sent as single requests
+        receiver.await.unwrap_or(BatchResponse::Unbatched)
+    }
+
+    async fn send_batch(&self, batcher: &Batcher, calls: Vec<BatchCall>) {
+        let endpoint = self.select().await;
+        // only http endpoints are sent batches
+        let is_http = matches!(endpoint.transport, Transport::Http(_));
+        if calls.len() < 2 || !is_http || !batcher.supported.load(Ordering::SeqCst) {
+            for call in calls.into_iter() {
+                let _ = call.sender.send(BatchResponse::Unbatched);
+            }
+            return
+        }
+        let autoscale_permit = self.acquire_autoscale().await;
+        let in_flight = endpoint.track_in_flight();
+        let permit = endpoint.acquire().await;
+        endpoint.requests.fetch_add(calls.len() as u64, Ordering::SeqCst);
+        let t_start = Instant::now();
+        let response = batcher.client.post(&endpoint.url).json(&batch_body(&calls)).send().await;
+        endpoint.record_latency(t_start.elapsed());
+        let entries = match response {
+            Ok(response)
+                if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS ||
+                    response.status().is_server_error() =>
+            {
+                Err(response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS)
+            }
+            Ok(response) => match response.bytes().await.map(|body| parse_batch_reply(&body)) {
+                Ok(BatchReply::Entries(entries)) => Ok(entries),
+                Ok(BatchReply::Unsupported) => {
+                    batcher.supported.store(false, Ordering::SeqCst);
+                    Ok(vec![])
+                }
+                // other invalid bodies, e.g. truncated responses, are only retried unbatched
+                Ok(BatchReply::Invalid) | Err(_) => Err(false),
+            },
+            Err(_) => Err(false),
+        };
+        drop(permit);
+        drop(in_flight);
+        let outcome = match entries {
+            Ok(_) => RequestOutcome::Success,
+            Err(true) => RequestOutcome::RateLimited,
+            Err(false) => RequestOutcome::Error,
+        };
+        self.record_autoscale(t_start, outcome);
+        drop(autoscale_permit);
+
+        let ids: Vec<u64> = calls.iter().map(|call| call.id).collect();
+        let responses = match entries {
+            Ok(entries) => batch_responses(&ids, entries),
+            Err(rate_limited) => {
+                let quarantine = self.endpoints.len() > 1;
+                endpoint
+                    .record_error(rate_limited, quarantine.then(|| Instant::now() + self.cooldown));
+                ids.iter().map(|_| BatchResponse::Unbatched).collect()
+            }
+        };
+        for (call, response) in calls.into_iter().zip(responses) {
+            if let BatchResponse::Error(_) = response {
+                endpoint.errors.fetch_add(1, Ordering::SeqCst);
+            }
+            let _ = call.sender.send(response);
+        }
+    }
+}
+
+fn batch_body(calls: &[BatchCall]) -> Vec<serde_json::Value> {
+    calls
+        .iter()
+        .map(|call| {
+            let mut request = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": call.id,
+                "method": call.method,
+            });
+            if let Some(params) = &call.params {
+                request["params"] = params.clone();
+            }
+            request
+        })
+        .collect()
+}
+
+/// body of a response to a batch
+#[derive(Debug)]
+enum BatchReply {
+    /// entries of the calls of the batch
+    Entries(Vec<BatchEntry>),
+    /// single error of an endpoint that does not accept batches
+    Unsupported,
+    /// body that is neither
+    Invalid,
+}
+
+/// parse the body of a response to a batch, endpoints that do not support batches respond with a
+/// single invalid request error or an error saying that batches are not supported
+fn parse_batch_reply(body: &[u8]) -> BatchReply {
+    if let Ok(entries) = serde_json::from_slice::<Vec<BatchEntry>>(body) {
+        return BatchReply::Entries(entries)
+    }
+    let error = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| serde_json::from_value::<JsonRpcError>(body["error"].clone()).ok());
+    match error {
+        Some(error) => {
+            let message = error.message.to_lowercase();
+            let mentions_batch = message.contains("batch") &&
+                (message.contains("not supported") ||
+                    message.contains("unsupported") ||
+                    message.contains("disabled"));
+            match error.code == -32600 || mentions_batch {
+                true => BatchReply::Unsupported,
+                false => BatchReply::Invalid,
+            }
+        }
+        None => BatchReply::Invalid,
+    }
+}
+
+/// match the entries of a batch response to the ids of its calls, calls without an entry and
+/// calls that were rate limited are sent again as single requests
+fn batch_responses(ids: &[u64], entries: Vec<BatchEntry>) -> Vec<BatchResponse> {
+    let mut entries: std::collections::HashMap<u64, BatchEntry> =
+        entries.into_iter().map(|entry| (entry.id, entry)).collect();
+    ids.iter()
+        .map(|id| match entries.remove(id) {
+            Some(BatchEntry { error: Some(error), .. }) => {
+                let retry = HttpClientError::JsonRpcError(error.clone());
+                match HttpRateLimitRetryPolicy.should_retry(&retry) {
+                    true => BatchResponse::Unbatched,
+                    false => BatchResponse::Error(error),
+                }
+            }
+            Some(BatchEntry { result, .. }) => BatchResponse::Result(result),
+            None => BatchResponse::Unbatched,
+        })
+        .collect()
+}
+
+impl EndpointPool {
+    /// send a request to the endpoints, batching it if enabled and retrying failures
+    async fn send<R: DeserializeOwned + Send>(
+        &self,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> Result<R, RetryClientError> {
+        if let Some(batcher) = &self.batcher {
+            match self.request_batched(batcher, method, params).await {
+                BatchResponse::Result(result) => {
+                    return serde_json::from_value(result).map_err(RetryClientError::SerdeJson)
+                }
+                BatchResponse::Error(error) => {
+                    let error: ProviderError = HttpClientError::JsonRpcError(error).into();
+                    self.record_error_class(classify_provider_error(&error));
+                    return Err(RetryClientError::ProviderError(error))
+                }
+                BatchResponse::Unbatched => {}
+            }
+        }
+        let mut retries = 0;
+        loop {
+            let endpoint = self.select().await;
+            let autoscale_permit = self.acquire_autoscale().await;
+            let t_start = Instant::now();
+            let error = match endpoint.request(method, params).await {
+                Ok(result) => {
+                    self.record_autoscale(t_start, RequestOutcome::Success);
+                    return Ok(result)
+                }
+                Err(error) => error,
+            };
+            let class = classify(&error);
+            self.record_error_class(class);
+            let rate_limited = class == ErrorClass::RateLimited;
+            let outcome = match class {
+                ErrorClass::RateLimited => RequestOutcome::RateLimited,
+                ErrorClass::Transient => RequestOutcome::Error,
+                _ => RequestOutcome::Success,
+            };
+            self.record_autoscale(t_start, outcome);
+            drop(autoscale_permit);
+            let failover = self.endpoints.len() > 1 && class.is_retryable();
+            endpoint.record_error(rate_limited, failover.then(|| Instant::now() + self.cooldown));
+            if !class.is_retryable() || retries >= self.retries.max_retries {
+                return Err(error)
+            }
+            if let Ok(mut counts) = self.retry_counts.lock() {
+                *counts.entry(method.to_string()).or_insert(0) += 1;
+            }
+            self.events.emit(CollectionEvent::RpcRetry {
+                method: method.to_string(),
+                error: class.name().to_string(),
+                attempt: retries + 1,
+            });
+            // failed over requests are sent to another endpoint without waiting
+            if !failover {
+                tokio::time::sleep(self.retries.backoff(retries)).await;
+            }
+            retries += 1;
+        }
+    }
+
+    /// send a request, answering it from the response cache if possible
+    async fn send_cached(
+        &self,
+        cache: &ResponseCache,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, RetryClientError> {
+        if let Some(response) = cache.get(method, params) {
+            return Ok(response)
+        }
+        let response: serde_json::Value = self.send(method, params).await?;
+        let tip = match cache.tip() {
+            Some(tip) => Some(tip),
+            None => match self.send::<U64>("eth_blockNumber", &None).await {
+                Ok(tip) => {
+                    cache.set_tip(tip.as_u64());
+                    Some(tip.as_u64())
+                }
+                Err(_) => None,
+            },
+        };
+        if let Some(tip) = tip {
+            cache.put(method, params, &response, tip);
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl JsonRpcClient for EndpointPool {
+    type Error = RetryClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // zero sized params are sent without a params field
+        let params = match std::mem::size_of::<T>() {
+            0 => None,
+            _ => Some(serde_json::to_value(params).map_err(RetryClientError::SerdeJson)?),
+        };
+        match self.cache.get().filter(|cache| cache.is_cached_method(method)) {
+            Some(cache) => {
+                let response = self.send_cached(cache, method, &params).await?;
+                serde_json::from_value(response).map_err(RetryClientError::SerdeJson)
+            }
+            None => self.send(method, &params).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn new_pool(n_endpoints: usize, strategy: EndpointStrategy) -> EndpointPool {
+        let urls: Vec<String> =
+            (0..n_endpoints).map(|i| format!("http://localhost:{}", 8545 + i)).collect();
+        let options = EndpointOptions { strategy, ..Default::default() };
+        EndpointPool::connect(&urls, 5, 500, &options).await.unwrap()
+    }
+
+    fn chosen(pool: &EndpointPool, now: Instant) -> String {
+        pool.choose(now).unwrap().url.clone()
+    }
+
+    #[tokio::test]
+    async fn test_choose_endpoint() {
+        let now = Instant::now();
+        let pool = new_pool(3, EndpointStrategy::RoundRobin).await;
+        let order: Vec<String> = (0..4).map(|_| chosen(&pool, now)).collect();
+        let urls = pool.urls();
+        assert_eq!(order, vec![urls[0].clone(), urls[1].clone(), urls[2].clone(), urls[0].clone()]);
+
+        // quarantined endpoints are skipped until their cooldown has passed
+        let until = now + pool.cooldown;
+        pool.endpoints[1].record_error(true, Some(until));
+        assert!((0..6).all(|_| chosen(&pool, now) != pool.endpoints[1].url));
+        assert_eq!(pool.stats()[1].rate_limited, 1);
+        assert_eq!(pool.stats()[1].quarantines, 1);
+        pool.endpoints[0].record_error(true, Some(until));
+        pool.endpoints[2].record_error(true, Some(until));
+        assert_eq!(pool.choose(now).err(), Some(until));
+        assert!(pool.choose(until).is_ok());
+
+        let pool = new_pool(3, EndpointStrategy::LeastInFlight).await;
+        pool.endpoints[0].in_flight.fetch_add(2, Ordering::SeqCst);
+        pool.endpoints[1].in_flight.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(chosen(&pool, now), pool.endpoints[2].url);
+        pool.endpoints[2].in_flight.fetch_add(3, Ordering::SeqCst);
+        assert_eq!(chosen(&pool, now), pool.endpoints[1].url);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_in_flight() {
+        let options = EndpointOptions { max_concurrent_requests: Some(1), ..Default::default() };
+        let urls = vec!["http://localhost:8545".to_string()];
+        let pool = EndpointPool::connect(&urls, 5, 500, &options).await.unwrap();
+        let endpoint = &pool.endpoints[0];
+        let permit = endpoint.acquire().await;
+        let request = endpoint.request::<serde_json::Value>("eth_chainId", &None);
+        assert!(tokio::time::timeout(Duration::from_millis(10), request).await.is_err());
+        assert_eq!(endpoint.in_flight.load(Ordering::SeqCst), 0);
+        drop(permit);
+    }
+
+    #[test]
+    fn test_batch_responses() {
+        let entries: Vec<BatchEntry> = serde_json::from_str(
+            r#"[
+                {"jsonrpc": "2.0", "id": 2, "result": "0x1"},
+                {"jsonrpc": "2.0", "id": 0, "result": null},
+                {"jsonrpc": "2.0", "id": 1, "error": {"code": -32000, "message": "reverted"}},
+                {"jsonrpc": "2.0", "id": 3, "error": {"code": 429, "message": "rate limited"}}
+            ]"#,
+        )
+        .unwrap();
+        let responses = batch_responses(&[0, 1, 2, 3, 4], entries);
+        assert!(matches!(&responses[0], BatchResponse::Result(serde_json::Value::Null)));
+        assert!(matches!(&responses[1], BatchResponse::Error(error) if error.code == -32000));
+        assert!(matches!(&responses[2], BatchResponse::Result(result) if result == "0x1"));
+        assert!(matches!(&responses[3], BatchResponse::Unbatched));
+        assert!(matches!(&responses[4], BatchResponse::Unbatched));
+
+        let (sender, _receiver) = oneshot::channel();
+        let call = BatchCall { id: 7, method: "eth_chainId".to_string(), params: None, sender };
+        assert_eq!(
+            batch_body(&[call]),
+            vec![serde_json::json!({"jsonrpc": "2.0", "id": 7, "method": "eth_chainId"})]
+        );
+    }
+
+    #[test]
+    fn test_parse_batch_reply() {
+        let reply = |body: &str| parse_batch_reply(body.as_bytes());
+        assert!(matches!(
+            reply(r#"[{"jsonrpc": "2.0", "id": 0, "result": "0x1"}]"#),
+            BatchReply::Entries(entries) if entries.len() == 1
+        ));
+        let invalid_request =
+            r#"{"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "invalid"}}"#;
+        assert!(matches!(reply(invalid_request), BatchReply::Unsupported));
+        let unsupported =
+            r#"{"error": {"code": -32000, "message": "Batch requests are disabled"}}"#;
+        assert!(matches!(reply(unsupported), BatchReply::Unsupported));
+
+        // bodies that are not an explicit rejection of batches keep batching enabled
+        assert!(matches!(reply("<html>bad gateway</html>"), BatchReply::Invalid));
+        assert!(matches!(reply(r#"[{"jsonrpc": "2.0", "id": 0, "res"#), BatchReply::Invalid));
+        let other = r#"{"error": {"code": -32005, "message": "limit exceeded"}}"#;
+        assert!(matches!(reply(other), BatchReply::Invalid));
+    }
+
+    #[test]
+    fn test_endpoint_failures() {
+        assert!(classify(&RetryClientError::TimeoutError).is_retryable());
+        let response =
+            JsonRpcError { code: -32000, message: "execution reverted".into(), data: None };
+        let error =
+            ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(response)));
+        assert!(!classify(&RetryClientError::ProviderError(error)).is_retryable());
+        let error = ProviderError::CustomError("connection refused".to_string());
+        assert!(classify(&RetryClientError::ProviderError(error)).is_retryable());
+    }
+}
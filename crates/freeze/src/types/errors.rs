@@ -85,6 +85,28 @@ pub enum CollectError {
     /// Generic RPC Error
     #[error("RPC call error")]
     RPCError(String),
+
+    /// Error response of a node that does not support an rpc method
+    #[error("rpc method is not supported by the node: {0}")]
+    MethodNotFound(String),
+}
+
+impl CollectError {
+    /// whether error is a node's response to a reverted call, with code 3 or an execution
+    /// reverted message, rather than a failure to execute the call such as a rate limit or
+    /// missing state
+    pub fn is_revert(&self) -> bool {
+        let response = match self {
+            CollectError::ProviderError(e) => RpcError::as_error_response(e),
+            _ => None,
+        };
+        match response {
+            Some(response) => {
+                response.code == 3 || response.message.to_lowercase().contains("execution reverted")
+            }
+            None => false,
+        }
+    }
 }
 
 /// Error related to parsing
@@ -133,4 +155,28 @@ pub enum FileError {
     /// Error in writing file
     #[error("Error writing file")]
     FileWriteError,
+
+    /// Column cannot be represented in output file format
+    #[error("Unsupported column: {0}")]
+    UnsupportedColumn(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_error(code: i64, message: &str) -> CollectError {
+        let response = JsonRpcError { code, message: message.to_string(), data: None };
+        let error = HttpClientError::JsonRpcError(response);
+        CollectError::ProviderError(ProviderError::JsonRpcClientError(Box::new(error)))
+    }
+
+    #[test]
+    fn test_is_revert() {
+        assert!(response_error(3, "execution reverted: paused").is_revert());
+        assert!(response_error(-32000, "execution reverted").is_revert());
+        assert!(!response_error(429, "too many requests").is_revert());
+        assert!(!response_error(-32000, "missing trie node").is_revert());
+        assert!(!CollectError::MethodNotFound("eth_call".to_string()).is_revert());
+    }
 }
@@ -0,0 +1,187 @@
+use crate::CollectionCounts;
+use indicatif::ProgressBar;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+/// events of a collection, sent to the event handler of its execution environment
+#[derive(Clone, Debug, PartialEq)]
+pub enum CollectionEvent {
+    /// collection of the chunks that were not skipped started
+    CollectionStarted {
+        /// number of chunks to collect, counting each dataset of a chunk
+        n_chunks: u64,
+    },
+    /// collection of a dataset of a chunk started
+    ChunkStarted {
+        /// label of chunk
+        chunk: String,
+        /// name of dataset
+        dataset: String,
+    },
+    /// files of a dataset of a chunk were written
+    ChunkCompleted {
+        /// label of chunk
+        chunk: String,
+        /// name of dataset
+        dataset: String,
+        /// number of rows written
+        rows: u64,
+        /// number of bytes written
+        bytes: u64,
+        /// first and last block of the chunk
+        block_range: Option<(u64, u64)>,
+        /// time taken to collect and write the chunk
+        duration: Duration,
+    },
+    /// collection of a dataset of a chunk failed
+    ChunkFailed {
+        /// label of chunk
+        chunk: String,
+        /// name of dataset
+        dataset: String,
+        /// error of chunk
+        error: String,
+    },
+    /// chunk was skipped because its files already exist
+    ChunkSkipped {
+        /// label of chunk
+        chunk: String,
+    },
+    /// failed rpc request is retried
+    RpcRetry {
+        /// rpc method of request
+        method: String,
+        /// class of error, such as rate_limited or transient
+        error: String,
+        /// number of the retry, starting at 1
+        attempt: u32,
+    },
+    /// output file was written
+    FileWritten {
+        /// path of file
+        path: PathBuf,
+        /// name of datatype
+        datatype: String,
+        /// number of rows written
+        rows: u64,
+        /// number of bytes written
+        bytes: u64,
+    },
+}
+
+/// receiver of the events of a collection
+///
+/// events are handled on the tasks of the collection, so handlers should return quickly
+pub trait EventHandler: Send + Sync {
+    /// handle event
+    fn handle(&self, event: &CollectionEvent);
+}
+
+impl EventHandler for mpsc::UnboundedSender<CollectionEvent> {
+    fn handle(&self, event: &CollectionEvent) {
+        let _ = self.send(event.clone());
+    }
+}
+
+impl EventHandler for CollectionCounts {
+    fn handle(&self, event: &CollectionEvent) {
+        match event {
+            CollectionEvent::FileWritten { datatype, rows, bytes, .. } => {
+                self.record_rows(datatype, *rows, *bytes)
+            }
+            CollectionEvent::ChunkCompleted { .. } => self.record_chunk(true),
+            CollectionEvent::ChunkFailed { .. } => self.record_chunk(false),
+            CollectionEvent::ChunkSkipped { .. } => self.record_skipped(1),
+            _ => {}
+        }
+    }
+}
+
+impl EventHandler for ProgressBar {
+    fn handle(&self, event: &CollectionEvent) {
+        match event {
+            CollectionEvent::CollectionStarted { n_chunks } => self.set_length(*n_chunks),
+            CollectionEvent::ChunkCompleted { .. } => self.inc(1),
+            _ => {}
+        }
+    }
+}
+
+/// event handler that can be set after construction, used by rpc clients shared between runs
+#[derive(Default)]
+pub struct EventSubscriber(Mutex<Option<Arc<dyn EventHandler>>>);
+
+impl EventSubscriber {
+    /// replace the event handler
+    pub fn subscribe(&self, handler: Option<Arc<dyn EventHandler>>) {
+        if let Ok(mut subscriber) = self.0.lock() {
+            *subscriber = handler;
+        }
+    }
+
+    /// send event to the event handler, if any
+    pub fn emit(&self, event: CollectionEvent) {
+        let handler = self.0.lock().ok().and_then(|handler| handler.clone());
+        if let Some(handler) = handler {
+            handler.handle(&event)
+        }
+    }
+}
+
+impl std::fmt::Debug for EventSubscriber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let subscribed = self.0.lock().map(|handler| handler.is_some()).unwrap_or_default();
+        f.debug_struct("EventSubscriber").field("subscribed", &subscribed).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_handlers() {
+        let counts = CollectionCounts::default();
+        let events = [
+            CollectionEvent::FileWritten {
+                path: PathBuf::from("a.parquet"),
+                datatype: "blocks".to_string(),
+                rows: 10,
+                bytes: 100,
+            },
+            CollectionEvent::ChunkCompleted {
+                chunk: "0_to_9".to_string(),
+                dataset: "blocks".to_string(),
+                rows: 10,
+                bytes: 100,
+                block_range: Some((0, 9)),
+                duration: Duration::from_secs(1),
+            },
+            CollectionEvent::ChunkFailed {
+                chunk: "10_to_19".to_string(),
+                dataset: "blocks".to_string(),
+                error: "error".to_string(),
+            },
+            CollectionEvent::ChunkSkipped { chunk: "20_to_29".to_string() },
+        ];
+        for event in events.iter() {
+            counts.handle(event);
+        }
+        assert_eq!(counts.rows().get("blocks"), Some(&10));
+        assert_eq!((counts.n_bytes(), counts.chunks()), (100, (1, 1, 1)));
+
+        let subscriber = EventSubscriber::default();
+        subscriber.emit(events[0].clone());
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        subscriber.subscribe(Some(Arc::new(sender)));
+        subscriber.emit(events[1].clone());
+        subscriber.subscribe(None);
+        subscriber.emit(events[2].clone());
+        assert_eq!(receiver.try_recv().ok(), Some(events[1].clone()));
+        assert!(receiver.try_recv().is_err());
+    }
+}
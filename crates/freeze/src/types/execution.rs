@@ -1,12 +1,22 @@
-use crate::CollectError;
+use crate::{CollectError, CollectionCounts, CollectionEvent, EventHandler};
 use indicatif::ProgressBar;
-use std::{path::PathBuf, sync::Arc, time::SystemTime};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
 
 /// configuration of execution environment
 #[derive(Clone)]
 pub struct ExecutionEnv {
     /// dry run
     pub dry: bool,
+    /// number of blocks sampled to estimate the size of dry runs
+    pub dry_run_samples: u64,
     /// verbose output
     pub verbose: u32,
     /// whether to generate report
@@ -25,6 +35,20 @@ pub struct ExecutionEnv {
     pub t_end: Option<SystemTime>,
     /// report directory
     pub report_dir: Option<PathBuf>,
+    /// report path, replacing the timestamped file of report_dir
+    pub report_file: Option<PathBuf>,
+    /// report of the run that this run resumes
+    pub resumed_from: Option<PathBuf>,
+    /// rows, bytes, and chunks written so far
+    pub counts: Arc<CollectionCounts>,
+    /// handler of collection events
+    pub events: Option<Arc<dyn EventHandler>>,
+    /// once set, no further chunks are started and chunks in flight are finished
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// address to serve prometheus metrics on
+    pub metrics_addr: Option<SocketAddr>,
+    /// number of threads encoding and writing output files
+    pub write_threads: usize,
 }
 
 impl ExecutionEnv {
@@ -37,6 +61,22 @@ impl ExecutionEnv {
     pub fn set_end_time(self) -> Self {
         ExecutionEnv { t_end: Some(SystemTime::now()), ..self }
     }
+
+    /// whether the collection was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().map(|cancel| cancel.load(Ordering::SeqCst)).unwrap_or(false)
+    }
+
+    /// send event to the counts, progress bar, and event handler of env
+    pub fn emit(&self, event: CollectionEvent) {
+        self.counts.handle(&event);
+        if let Some(bar) = &self.bar {
+            bar.handle(&event);
+        }
+        if let Some(events) = &self.events {
+            events.handle(&event);
+        }
+    }
 }
 
 impl Default for ExecutionEnv {
@@ -58,6 +98,7 @@ fn new_bar(n: u64) -> Result<Arc<ProgressBar>, CollectError> {
 /// build ExecutionEnv using builder pattern
 pub struct ExecutionEnvBuilder {
     dry: bool,
+    dry_run_samples: u64,
     verbose: u32,
     report: bool,
     bar: Option<Arc<ProgressBar>>,
@@ -67,12 +108,19 @@ pub struct ExecutionEnvBuilder {
     t_start: SystemTime,
     t_end: Option<SystemTime>,
     report_dir: Option<PathBuf>,
+    report_file: Option<PathBuf>,
+    resumed_from: Option<PathBuf>,
+    events: Option<Arc<dyn EventHandler>>,
+    cancel: Option<Arc<AtomicBool>>,
+    metrics_addr: Option<SocketAddr>,
+    write_threads: usize,
 }
 
 impl Default for ExecutionEnvBuilder {
     fn default() -> Self {
         ExecutionEnvBuilder {
             dry: false,
+            dry_run_samples: 0,
             verbose: 1,
             report: true,
             bar: None,
@@ -82,6 +130,12 @@ impl Default for ExecutionEnvBuilder {
             t_start: SystemTime::now(),
             t_end: None,
             report_dir: None,
+            report_file: None,
+            resumed_from: None,
+            events: None,
+            cancel: None,
+            metrics_addr: None,
+            write_threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
         }
     }
 }
@@ -98,6 +152,12 @@ impl ExecutionEnvBuilder {
         self
     }
 
+    /// number of blocks sampled to estimate the size of dry runs
+    pub fn dry_run_samples(mut self, dry_run_samples: u64) -> Self {
+        self.dry_run_samples = dry_run_samples;
+        self
+    }
+
     /// verbose output
     pub fn verbose(mut self, verbose: u32) -> Self {
         self.verbose = verbose;
@@ -116,6 +176,42 @@ impl ExecutionEnvBuilder {
         self
     }
 
+    /// set report path
+    pub fn report_file(mut self, report_file: Option<PathBuf>) -> Self {
+        self.report_file = report_file;
+        self
+    }
+
+    /// set report of the resumed run
+    pub fn resumed_from(mut self, resumed_from: Option<PathBuf>) -> Self {
+        self.resumed_from = resumed_from;
+        self
+    }
+
+    /// send collection events to handler
+    pub fn events(mut self, events: Arc<dyn EventHandler>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// stop starting chunks once cancel is set
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// serve prometheus metrics on address
+    pub fn metrics_addr(mut self, metrics_addr: Option<SocketAddr>) -> Self {
+        self.metrics_addr = metrics_addr;
+        self
+    }
+
+    /// number of threads encoding and writing output files
+    pub fn write_threads(mut self, write_threads: usize) -> Self {
+        self.write_threads = write_threads;
+        self
+    }
+
     /// progress bar size
     pub fn bar(mut self, n: u64) -> Result<Self, CollectError> {
         self.bar = Some(new_bar(n)?);
@@ -138,6 +234,7 @@ impl ExecutionEnvBuilder {
     pub fn build(self) -> ExecutionEnv {
         ExecutionEnv {
             dry: self.dry,
+            dry_run_samples: self.dry_run_samples,
             verbose: self.verbose,
             report: self.report,
             bar: self.bar,
@@ -147,6 +244,13 @@ impl ExecutionEnvBuilder {
             t_start: self.t_start,
             t_end: self.t_end,
             report_dir: self.report_dir,
+            report_file: self.report_file,
+            resumed_from: self.resumed_from,
+            counts: Arc::new(CollectionCounts::default()),
+            events: self.events,
+            cancel: self.cancel,
+            metrics_addr: self.metrics_addr,
+            write_threads: self.write_threads,
         }
     }
 }
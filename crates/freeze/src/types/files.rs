@@ -1,5 +1,8 @@
 use crate::{CollectError, Datatype, MetaDatatype, ParseError, Partition, Query};
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 /// Options for file output
 #[derive(Clone, Debug)]
@@ -18,10 +21,196 @@ pub struct FileOutput {
     pub format: FileFormat,
     /// Number of rows per parquet row group
     pub row_group_size: Option<usize>,
-    /// Parquet statistics recording flag
-    pub parquet_statistics: bool,
+    /// Level of parquet statistics to record
+    pub parquet_statistics: ParquetStatistics,
     /// Parquet compression options
     pub parquet_compression: polars::prelude::ParquetCompression,
+    /// Parquet compression overrides of individual columns
+    pub parquet_column_compression: HashMap<String, polars::prelude::ParquetCompression>,
+    /// Bloom filters to add to parquet files
+    pub bloom_filters: Option<BloomFilterOptions>,
+    /// Compression of arrow ipc record batches, None for uncompressed
+    pub ipc_compression: Option<polars::prelude::IpcCompression>,
+    /// Compression codec of avro data blocks
+    pub avro_codec: AvroCodec,
+    /// Compression of csv and json files, None for uncompressed
+    pub text_compression: Option<TextCompression>,
+    /// DuckDB database that chunks are loaded into, with output files only used for staging
+    pub duckdb: Option<PathBuf>,
+    /// PostgreSQL database that chunks are loaded into, requires the postgres feature
+    pub postgres: Option<PostgresOutput>,
+    /// Whether chunks are streamed to stdout, with output files only used for staging
+    pub stdout: bool,
+    /// Hive-style partition directories that the rows of each chunk are split into
+    pub hive_partitioning: Option<HivePartitioning>,
+    /// Object store that files are uploaded to, with output files only used for staging
+    pub object_store: Option<ObjectStoreOutput>,
+    /// Maximum number of rows per file, with larger chunks split into numbered parts
+    pub max_rows_per_file: Option<usize>,
+    /// Key-value metadata added to parquet files
+    pub key_value_metadata: Vec<(String, String)>,
+    /// Manifest that the checksums of written files are appended to
+    pub checksum_manifest: Option<PathBuf>,
+    /// Size of the rows of a chunk that are flushed to its file as row groups while collecting
+    pub flush_threshold: Option<FlushThreshold>,
+    /// Whether chunks without rows are written as files or only marked as complete
+    pub empty_chunks: EmptyChunks,
+}
+
+#[cfg(test)]
+impl FileOutput {
+    /// parquet output of tests, writing files to dir with every optional feature disabled
+    pub(crate) fn test_output(dir: &Path) -> FileOutput {
+        FileOutput {
+            output_dir: dir.to_path_buf(),
+            prefix: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: true,
+            format: FileFormat::Parquet,
+            row_group_size: None,
+            parquet_statistics: Default::default(),
+            parquet_compression: polars::prelude::ParquetCompression::Lz4Raw,
+            parquet_column_compression: Default::default(),
+            bloom_filters: None,
+            ipc_compression: None,
+            avro_codec: Default::default(),
+            text_compression: None,
+            duckdb: None,
+            postgres: None,
+            stdout: false,
+            hive_partitioning: None,
+            object_store: None,
+            max_rows_per_file: None,
+            key_value_metadata: vec![],
+            checksum_manifest: None,
+            flush_threshold: None,
+            empty_chunks: Default::default(),
+        }
+    }
+}
+
+/// Size of the rows accumulated by a chunk before they are flushed to its file
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FlushThreshold {
+    /// number of rows
+    Rows(u64),
+    /// approximate number of bytes of dataframes
+    Bytes(u64),
+}
+
+/// Location in an object store that output files are uploaded to
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObjectStoreOutput {
+    /// kind of object store
+    pub scheme: ObjectStoreScheme,
+    /// bucket, or container of azure blob storage
+    pub bucket: String,
+    /// key prefix of uploaded files, without leading or trailing slashes
+    pub prefix: String,
+}
+
+/// Kind of object store
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectStoreScheme {
+    /// amazon s3, or a compatible store at AWS_ENDPOINT_URL
+    S3,
+    /// google cloud storage
+    Gcs,
+    /// azure blob storage
+    Azure,
+}
+
+impl ObjectStoreOutput {
+    /// parse s3://, gs://, or az:// url, returning None for local paths
+    pub fn parse(url: &str) -> Result<Option<ObjectStoreOutput>, ParseError> {
+        let (scheme, location) = match url.split_once("://") {
+            Some((scheme, location)) => (scheme, location),
+            None => return Ok(None),
+        };
+        let scheme = match scheme {
+            "s3" => ObjectStoreScheme::S3,
+            "gs" => ObjectStoreScheme::Gcs,
+            "az" => ObjectStoreScheme::Azure,
+            _ => return Err(ParseError::ParseError(format!("unsupported output url: {}", url))),
+        };
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        if bucket.is_empty() {
+            return Err(ParseError::ParseError(format!("output url has no bucket: {}", url)))
+        }
+        let prefix = prefix.trim_matches('/').to_string();
+        Ok(Some(ObjectStoreOutput { scheme, bucket: bucket.to_string(), prefix }))
+    }
+
+    /// url of the object store location
+    pub fn url(&self) -> String {
+        let scheme = match self.scheme {
+            ObjectStoreScheme::S3 => "s3",
+            ObjectStoreScheme::Gcs => "gs",
+            ObjectStoreScheme::Azure => "az",
+        };
+        format!("{}://{}/{}", scheme, self.bucket, self.prefix)
+    }
+
+    /// object key of a file in the staging directory
+    pub fn key(&self, staging_dir: &Path, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(staging_dir).ok()?;
+        let mut parts: Vec<String> = match self.prefix.as_str() {
+            "" => vec![],
+            prefix => vec![prefix.to_string()],
+        };
+        for component in relative.components() {
+            parts.push(component.as_os_str().to_str()?.to_string());
+        }
+        Some(parts.join("/"))
+    }
+}
+
+/// Options for hive-style partitioned output
+#[derive(Clone, Debug)]
+pub struct HivePartitioning {
+    /// keys of the partition directories, outermost first
+    pub keys: Vec<HiveKey>,
+    /// chain id of the collected network
+    pub chain_id: u64,
+    /// whether columns named after a partition key are dropped from written files
+    pub drop_columns: bool,
+}
+
+/// Key of a hive-style partition directory
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HiveKey {
+    /// chain id of the network
+    ChainId,
+    /// utc date of the timestamp column
+    Date,
+    /// block number rounded down to a multiple of the bucket size
+    BlockBucket(u64),
+    /// contract address of each row
+    Address,
+}
+
+impl HiveKey {
+    /// name of the key in partition directories
+    pub fn name(&self) -> &'static str {
+        match self {
+            HiveKey::ChainId => "chain_id",
+            HiveKey::Date => "date",
+            HiveKey::BlockBucket(_) => "block_bucket",
+            HiveKey::Address => "address",
+        }
+    }
+}
+
+/// Options for loading chunks into PostgreSQL
+#[derive(Clone, Debug)]
+pub struct PostgresOutput {
+    /// connection url, as accepted by psql
+    pub url: String,
+    /// schema of dataset tables and the chunk bookkeeping table
+    pub schema: String,
+    /// prefix added to the datatype name of each table
+    pub table_prefix: String,
 }
 
 /// Possible item to use as subdirectory
@@ -36,6 +225,23 @@ pub enum SubDir {
 }
 
 impl FileOutput {
+    /// extension of output files, including the compression extension of compressed files
+    pub fn extension(&self) -> String {
+        match &self.text_compression {
+            Some(compression) => format!("{}.{}", self.format.as_str(), compression.extension()),
+            None => self.format.as_str().to_string(),
+        }
+    }
+
+    /// whether chunks without rows are marked as complete instead of written, which only
+    /// applies to file output, since database and stdout output always load every chunk
+    pub fn skips_empty_chunks(&self) -> bool {
+        self.empty_chunks == EmptyChunks::Skip &&
+            !self.stdout &&
+            self.duckdb.is_none() &&
+            self.postgres.is_none()
+    }
+
     /// get output file paths
     pub fn get_paths(
         &self,
@@ -64,26 +270,47 @@ impl FileOutput {
         partition: &Partition,
         datatype: Datatype,
     ) -> Result<PathBuf, CollectError> {
-        let filename = if let Some(suffix) = self.suffix.clone() {
-            format!(
-                "{}__{}__{}__{}.{}",
-                self.prefix.clone(),
-                datatype.name(),
-                suffix,
-                partition.label(&query.partitioned_by)?,
-                self.format.as_str(),
-            )
-        } else {
-            format!(
-                "{}__{}__{}.{}",
-                self.prefix.clone(),
-                datatype.name(),
-                partition.label(&query.partitioned_by)?,
-                self.format.as_str(),
-            )
-        };
-        let filename = std::path::Path::new(&filename).to_path_buf();
-        let mut output_dir = std::path::Path::new(&self.output_dir).to_path_buf();
+        let filename = format!(
+            "{}__{}.{}",
+            self.chunk_prefix(&datatype),
+            partition.label(&query.partitioned_by)?,
+            self.chunk_extension(),
+        );
+        let output_dir = self.chunk_dir(&datatype);
+        std::fs::create_dir_all(&output_dir)
+            .map_err(|_| ParseError::ParseError("could not create dir".to_string()))?;
+        Ok(output_dir.join(filename))
+    }
+
+    /// filename prefix of the chunks of a datatype, e.g. ethereum__blocks
+    pub fn chunk_prefix(&self, datatype: &Datatype) -> String {
+        match &self.suffix {
+            Some(suffix) => format!("{}__{}__{}", self.prefix, datatype.name(), suffix),
+            None => format!("{}__{}", self.prefix, datatype.name()),
+        }
+    }
+
+    /// extension of chunk paths, with partitioned and split chunks recorded by json manifests
+    pub fn chunk_extension(&self) -> String {
+        match self.hive_partitioning.is_some() || self.max_rows_per_file.is_some() {
+            true => "json".to_string(),
+            false => self.extension(),
+        }
+    }
+
+    /// directory of the chunk paths of a datatype
+    pub fn chunk_dir(&self, datatype: &Datatype) -> PathBuf {
+        let mut output_dir = self.output_dir.clone();
+
+        // partitioned chunks are recorded by a manifest of the part files they were split into
+        if self.hive_partitioning.is_some() {
+            let dataset = match &self.suffix {
+                Some(suffix) => datatype.name() + "__" + suffix.as_str(),
+                None => datatype.name(),
+            };
+            return output_dir.join(format!("dataset={}", dataset)).join("_cryo_chunks")
+        }
+
         for subdir in self.subdirs.iter() {
             let subdir_str: String = match subdir {
                 SubDir::Network => self.prefix.clone(),
@@ -96,10 +323,11 @@ impl FileOutput {
             output_dir = output_dir.join(std::path::Path::new(&subdir_str));
         }
 
-        std::fs::create_dir_all(output_dir.clone())
-            .map_err(|_| ParseError::ParseError("could not create dir".to_string()))?;
-
-        Ok(output_dir.join(filename))
+        // split chunks are recorded by a manifest of their parts
+        if self.max_rows_per_file.is_some() {
+            output_dir = output_dir.join("_cryo_chunks");
+        }
+        output_dir
     }
 }
 
@@ -112,6 +340,12 @@ pub enum FileFormat {
     Csv,
     /// Json file format
     Json,
+    /// Arrow ipc file format, also known as feather v2
+    Ipc,
+    /// Avro object container file format
+    Avro,
+    /// Newline-delimited json file format
+    Ndjson,
 }
 
 impl FileFormat {
@@ -121,6 +355,118 @@ impl FileFormat {
             FileFormat::Parquet => "parquet",
             FileFormat::Csv => "csv",
             FileFormat::Json => "json",
+            FileFormat::Ipc => "arrow",
+            FileFormat::Avro => "avro",
+            FileFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Compression of csv and json output files
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextCompression {
+    /// gzip compression
+    Gzip,
+    /// zstandard compression
+    Zstd,
+}
+
+impl TextCompression {
+    /// extension appended to the extension of compressed files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TextCompression::Gzip => "gz",
+            TextCompression::Zstd => "zst",
+        }
+    }
+
+    /// compression of a file with the given extension
+    pub fn from_extension(extension: &str) -> Option<TextCompression> {
+        match extension {
+            "gz" => Some(TextCompression::Gzip),
+            "zst" => Some(TextCompression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// columns that get bloom filters by default, when present in a dataset
+const DEFAULT_BLOOM_FILTER_COLUMNS: [&str; 7] = [
+    "transaction_hash",
+    "block_hash",
+    "address",
+    "from_address",
+    "to_address",
+    "contract_address",
+    "token_address",
+];
+
+/// Options for parquet bloom filters
+#[derive(Clone, Debug)]
+pub struct BloomFilterOptions {
+    /// columns to add bloom filters to, None for the default hash and address columns
+    pub columns: Option<Vec<String>>,
+    /// target false positive probability
+    pub fpp: f64,
+    /// expected number of distinct values per row group, None to count them
+    pub ndv: Option<u64>,
+}
+
+impl BloomFilterOptions {
+    /// columns of a table that get bloom filters
+    pub fn filter_columns<'a>(&self, columns: &[&'a str]) -> Vec<&'a str> {
+        columns
+            .iter()
+            .filter(|column| match &self.columns {
+                Some(bloom_columns) => bloom_columns.iter().any(|c| c == *column),
+                None => DEFAULT_BLOOM_FILTER_COLUMNS.contains(column),
+            })
+            .copied()
+            .collect()
+    }
+}
+
+/// Statistics recorded in parquet files
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ParquetStatistics {
+    /// No statistics
+    None,
+    /// Statistics of each column chunk
+    Chunk,
+    /// Statistics of each column chunk and page, including page indexes
+    #[default]
+    Page,
+}
+
+/// Handling of chunks without rows
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum EmptyChunks {
+    /// Write files without rows, carrying the schema of the dataset
+    #[default]
+    Write,
+    /// Write an empty chunk marker instead of a file
+    Skip,
+}
+
+/// Compression codec of avro files
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub enum AvroCodec {
+    /// Uncompressed
+    #[default]
+    Null,
+    /// Deflate compression
+    Deflate,
+    /// Snappy compression
+    Snappy,
+}
+
+impl AvroCodec {
+    /// convert AvroCodec to its name in avro file metadata
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            AvroCodec::Null => "null",
+            AvroCodec::Deflate => "deflate",
+            AvroCodec::Snappy => "snappy",
         }
     }
 }
@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{Source, LATENCY_BUCKETS_MS};
+
+/// rows, bytes, and chunks of a collection, updated as each chunk finishes
+#[derive(Debug, Default)]
+pub struct CollectionCounts {
+    rows: Mutex<BTreeMap<String, u64>>,
+    n_bytes: AtomicU64,
+    chunks_completed: AtomicU64,
+    chunks_errored: AtomicU64,
+    chunks_skipped: AtomicU64,
+}
+
+impl CollectionCounts {
+    pub(crate) fn record_rows(&self, datatype: &str, n_rows: u64, n_bytes: u64) {
+        if let Ok(mut rows) = self.rows.lock() {
+            *rows.entry(datatype.to_string()).or_insert(0) += n_rows;
+        }
+        self.n_bytes.fetch_add(n_bytes, Ordering::SeqCst);
+    }
+
+    pub(crate) fn record_chunk(&self, completed: bool) {
+        match completed {
+            true => self.chunks_completed.fetch_add(1, Ordering::SeqCst),
+            false => self.chunks_errored.fetch_add(1, Ordering::SeqCst),
+        };
+    }
+
+    pub(crate) fn record_skipped(&self, n_chunks: u64) {
+        self.chunks_skipped.fetch_add(n_chunks, Ordering::SeqCst);
+    }
+
+    /// number of rows written of each datatype
+    pub fn rows(&self) -> BTreeMap<String, u64> {
+        self.rows.lock().map(|rows| rows.clone()).unwrap_or_default()
+    }
+
+    /// number of bytes of the files written
+    pub fn n_bytes(&self) -> u64 {
+        self.n_bytes.load(Ordering::SeqCst)
+    }
+
+    /// number of chunks completed, errored, and skipped
+    pub fn chunks(&self) -> (u64, u64, u64) {
+        (
+            self.chunks_completed.load(Ordering::SeqCst),
+            self.chunks_errored.load(Ordering::SeqCst),
+            self.chunks_skipped.load(Ordering::SeqCst),
+        )
+    }
+}
+
+/// current counters of a collection in the prometheus text format
+pub fn render_metrics(source: &Source, counts: &CollectionCounts) -> String {
+    let mut metrics = Metrics::default();
+    let (requests, _) = source.method_counts();
+    let requests = requests.iter().map(|(method, n)| (vec![("method", method.as_str())], *n));
+    metrics.add("cryo_rpc_requests_total", "counter", "rpc requests of each method", requests);
+    let errors = source.error_counts();
+    let errors = errors.iter().map(|(class, n)| (vec![("class", class.as_str())], *n));
+    metrics.add("cryo_rpc_errors_total", "counter", "failed rpc requests of each class", errors);
+    let retries = source.retry_counts();
+    let retries = retries.iter().map(|(method, n)| (vec![("method", method.as_str())], *n));
+    metrics.add("cryo_rpc_retries_total", "counter", "retried rpc requests", retries);
+    if let Some((hits, misses)) = source.cache_hits_and_misses() {
+        let cache = [(vec![("result", "hit")], hits), (vec![("result", "miss")], misses)];
+        metrics.add("cryo_rpc_cache_requests_total", "counter", "response cache lookups", cache);
+    }
+
+    let rows = counts.rows();
+    let rows = rows.iter().map(|(datatype, n)| (vec![("dataset", datatype.as_str())], *n));
+    metrics.add("cryo_rows_total", "counter", "rows written of each dataset", rows);
+    let n_bytes = [(vec![], counts.n_bytes())];
+    metrics.add("cryo_written_bytes_total", "counter", "bytes of files written", n_bytes);
+    let (completed, errored, skipped) = counts.chunks();
+    let chunks = [
+        (vec![("status", "completed")], completed),
+        (vec![("status", "errored")], errored),
+        (vec![("status", "skipped")], skipped),
+    ];
+    metrics.add("cryo_chunks_total", "counter", "chunks of each status", chunks);
+
+    let endpoints = source.endpoint_stats();
+    let in_flight = endpoints.iter().map(|e| (vec![("endpoint", e.url.as_str())], e.in_flight));
+    metrics.add("cryo_rpc_in_flight_requests", "gauge", "requests awaiting a response", in_flight);
+    if let Some(state) = source.autoscale_state() {
+        let concurrency = [(vec![], state.concurrency)];
+        metrics.add("cryo_autoscale_concurrency", "gauge", "autoscaled concurrency", concurrency);
+    }
+
+    let name = "cryo_rpc_request_duration_seconds";
+    metrics.header(name, "histogram", "latency of rpc requests of each endpoint");
+    for endpoint in endpoints.iter() {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS_MS.iter().zip(&endpoint.latency.counts) {
+            cumulative += count;
+            let le = (*bound as f64 / 1000.0).to_string();
+            let labels = [("endpoint", endpoint.url.as_str()), ("le", le.as_str())];
+            metrics.sample(&format!("{}_bucket", name), &labels, cumulative);
+        }
+        let n_requests = endpoint.latency.n_requests();
+        let labels = [("endpoint", endpoint.url.as_str()), ("le", "+Inf")];
+        metrics.sample(&format!("{}_bucket", name), &labels, n_requests);
+        let labels = [("endpoint", endpoint.url.as_str())];
+        let seconds = endpoint.latency.total_us as f64 / 1e6;
+        metrics.sample(&format!("{}_sum", name), &labels, seconds);
+        metrics.sample(&format!("{}_count", name), &labels, n_requests);
+    }
+
+    metrics.0
+}
+
+#[derive(Default)]
+struct Metrics(String);
+
+impl Metrics {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    }
+
+    fn sample<T: std::fmt::Display>(&mut self, name: &str, labels: &[(&str, &str)], value: T) {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+            .collect();
+        match labels.is_empty() {
+            true => writeln!(self.0, "{} {}", name, value),
+            false => writeln!(self.0, "{}{{{}}} {}", name, labels.join(","), value),
+        }
+        .unwrap_or_default()
+    }
+
+    fn add<'a>(
+        &mut self,
+        name: &str,
+        kind: &str,
+        help: &str,
+        samples: impl IntoIterator<Item = (Vec<(&'a str, &'a str)>, u64)>,
+    ) {
+        self.header(name, kind, help);
+        for (labels, value) in samples {
+            self.sample(name, &labels, value);
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// serve the metrics of a collection over http until the returned task is aborted
+#[cfg(feature = "metrics")]
+pub async fn spawn_metrics_server(
+    addr: std::net::SocketAddr,
+    source: std::sync::Arc<Source>,
+    counts: std::sync::Arc<CollectionCounts>,
+) -> Result<tokio::task::JoinHandle<()>, crate::CollectError> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| crate::err(&format!("could not serve metrics on {}: {}", addr, e)))?;
+    Ok(tokio::spawn(async move {
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            };
+            let body = render_metrics(&source, &counts);
+            tokio::spawn(async move {
+                // every path is answered with the metrics
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: \
+                     {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_format() {
+        let mut metrics = Metrics::default();
+        let samples = [(vec![("dataset", "logs")], 3), (vec![("dataset", "a\"b")], 1)];
+        metrics.add("cryo_rows_total", "counter", "rows written", samples);
+        metrics.add("cryo_written_bytes_total", "counter", "bytes written", [(vec![], 10)]);
+        let expected = "# HELP cryo_rows_total rows written\n# TYPE cryo_rows_total counter\n\
+                        cryo_rows_total{dataset=\"logs\"} 3\ncryo_rows_total{dataset=\"a\\\"b\"} 1\n\
+                        # HELP cryo_written_bytes_total bytes written\n\
+                        # TYPE cryo_written_bytes_total counter\ncryo_written_bytes_total 10\n";
+        assert_eq!(metrics.0, expected);
+    }
+
+    #[test]
+    fn test_collection_counts() {
+        let counts = CollectionCounts::default();
+        counts.record_rows("logs", 5, 100);
+        counts.record_rows("logs", 2, 50);
+        counts.record_chunk(true);
+        counts.record_chunk(false);
+        counts.record_skipped(3);
+        assert_eq!(counts.rows(), [("logs".to_string(), 7)].into_iter().collect());
+        assert_eq!(counts.n_bytes(), 150);
+        assert_eq!(counts.chunks(), (1, 1, 3));
+    }
+}
@@ -1,13 +1,31 @@
-/// type specifications for cryo_freeze crate
+//! type specifications for cryo_freeze crate
 
+/// adaptive concurrency of rpc requests
+pub mod autoscale;
+/// on-disk cache of rpc responses
+pub mod cache;
 /// type specifications for chunk types
 pub mod chunks;
 /// conversion operations
 pub mod conversions;
 /// type specifications for collectable types
 pub mod datatypes;
+/// rpc endpoint pools
+pub mod endpoints;
+/// events of collections, for progress reporting of embedders
+pub mod events;
+/// counters of collections, served as prometheus metrics
+pub mod metrics;
+/// retries of failed rpc requests
+pub mod retries;
+/// block responses shared by the datasets of a run
+pub mod shared_fetches;
 /// type specifications for data sources
 pub mod sources;
+/// conversion of geth traces into parity traces
+pub mod trace_backends;
+/// http, websocket, and ipc connections to rpc endpoints
+pub mod transports;
 
 /// column data specification
 pub mod columns;
@@ -49,20 +67,48 @@ pub mod schemas;
 /// types related to summaries
 pub mod summaries;
 
+pub use autoscale::{AutoscaleState, Autoscaler};
+pub use cache::{cache_stats, prune_cache, CacheEntries, CacheStats, ResponseCache};
 pub use chunks::{
     AddressChunk, BlockChunk, CallDataChunk, Chunk, ChunkData, ChunkStats, SlotChunk, Subchunk,
     TopicChunk, TransactionChunk,
 };
-pub use conversions::{bytes_to_u32, ToVecHex, ToVecU8};
+pub use conversions::{bytes_to_u32, checksum_hex, ToVecChecksumHex, ToVecHex, ToVecU8};
 pub use dataframes::*;
 pub use datatypes::*;
-pub use files::{ColumnEncoding, FileFormat, FileOutput, SubDir};
-pub use queries::{Query, QueryLabels, TimeDimension};
-pub use schemas::{ColumnType, SchemaFunctions, Schemas, Table, U256Type};
-pub use sources::{Fetcher, RateLimiter, Source, SourceLabels};
+pub use endpoints::{
+    EndpointOptions, EndpointPool, EndpointStats, EndpointStrategy, LatencyHistogram,
+    LATENCY_BUCKETS_MS,
+};
+pub use events::{CollectionEvent, EventHandler, EventSubscriber};
+pub use files::{
+    AvroCodec, BloomFilterOptions, ColumnEncoding, EmptyChunks, FileFormat, FileOutput,
+    FlushThreshold, HiveKey, HivePartitioning, ObjectStoreOutput, ObjectStoreScheme,
+    ParquetStatistics, PostgresOutput, SubDir, TextCompression,
+};
+pub use metrics::{render_metrics, CollectionCounts};
+pub use queries::{
+    AddressLabels, BlockError, BlockErrors, CollectWarnings, FourByteAggregation, LogFilter,
+    OnBlockError, PhaseTimings, Query, QueryLabels, ResolvedTopic, SharedError, SlotFilter,
+    TimeBucket, TimeDimension, TimestampRange, TokenDecimals, TraceContractFilter, TraceFilterSide,
+};
+pub use retries::RetryOptions;
+pub use schemas::{
+    BlockNumberType, ColumnType, OutputColumn, OutputSchema, SchemaFunctions, Schemas, Table,
+    U256Type, BLOCK_NUMBER_COLUMNS,
+};
+pub use shared_fetches::SharedFetches;
+pub use sources::{
+    chain_name, BlobSidecar, Capabilities, Capability, CreationMethods, Fetcher, RateLimiter,
+    Source, SourceLabels,
+};
+pub use trace_backends::TraceBackend;
+pub use transports::{is_ipc_path, normalize_rpc_url, ReconnectingIpc};
 // pub(crate) use summaries::FreezeSummaryAgg;
 // pub use summaries::{FreezeChunkSummary, FreezeSummary};
-pub use summaries::{print_all_datasets, print_dataset_info, FreezeSummary};
+pub use summaries::{
+    format_bytes, print_all_datasets, print_dataset_info, FreezeSummary, UnsupportedDataset,
+};
 
 pub use errors::{err, ChunkError, CollectError, FileError, FreezeError, ParseError, R};
 
@@ -1,8 +1,15 @@
-use crate::{CollectError, Datatype, Dim, MetaDatatype, Partition, Table};
-use std::collections::{HashMap, HashSet};
+use crate::{
+    BlockHashes, CollectError, Datatype, Dim, HashVerification, MetaDatatype, Partition,
+    SignatureResolver, Table,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 /// Query
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Query {
     /// MetaDatatype
     pub datatypes: Vec<MetaDatatype>,
@@ -18,17 +25,123 @@ pub struct Query {
     pub exclude_failed: bool,
     /// Javascript tracer
     pub js_tracer: Option<String>,
+    /// Resolver for 4byte signature text
+    pub signature_resolver: Option<Arc<SignatureResolver>>,
+    /// Block at which static block rewards end, defaults to merge block of known chains
+    pub merge_block: Option<u64>,
+    /// Addresses used by precompile_calls, defaults to precompiles of chain's fork at each block
+    pub precompiles: Option<Vec<Vec<u8>>>,
+    /// Whether four_byte_counts emits rows per transaction or per selector of each chunk
+    pub four_byte_aggregation: FourByteAggregation,
+    /// Only keep balances that changed since the previous block of their chunk
+    pub changes_only: bool,
+    /// Time between snapshots of snapshot datasets
+    pub snapshot_interval: Option<std::time::Duration>,
+    /// Number of snapshots taken by snapshot datasets
+    pub n_snapshots: u64,
+    /// Slots that storage_diffs and storage_reads are restricted to
+    pub slot_filter: Option<SlotFilter>,
+    /// Contracts that traces, vm_traces, and geth_calls are restricted to
+    pub trace_contract_filter: Option<TraceContractFilter>,
+    /// Filter sets of logs, each requested separately for every block range
+    pub log_filters: Option<Vec<LogFilter>>,
+    /// Labels of addresses, added to outputs as a label column
+    pub address_labels: Option<AddressLabels>,
+    /// Number of static calls packed into each Multicall3 request, None to make calls
+    /// individually
+    pub multicall_batch: Option<usize>,
+    /// Types of eth_calls return values, used to decode call outputs
+    pub eth_call_return_types: Option<Vec<ethers::abi::ParamType>>,
+    /// State override set applied to every call of eth_calls and trace_calls
+    pub state_override: Option<ethers::types::spoof::State>,
+    /// Whether block hashes are checked to link within and across chunks
+    pub verify_hashes: Option<HashVerification>,
+    /// Headers at the boundaries of verified chunks
+    pub block_hashes: BlockHashes,
+    /// Warnings raised during collection
+    pub warnings: CollectWarnings,
+    /// Decimals of erc20 tokens, fetched once per token and shared by all chunks
+    pub token_decimals: TokenDecimals,
+    /// Compile error of js_tracer, reused by remaining chunks instead of re-sending the tracer
+    pub tracer_error: SharedError,
+    /// What to do with the chunk of a block whose request fails
+    pub on_block_error: OnBlockError,
+    /// Failed requests of blocks whose chunks are left incomplete
+    pub block_errors: BlockErrors,
+    /// Time spent in each phase of collecting chunks
+    pub timings: PhaseTimings,
     /// Labels (these are non-functional)
     pub labels: QueryLabels,
 }
 
 /// query labels (non-functional)
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct QueryLabels {
     /// align
     pub align: bool,
     /// reorg buffer
     pub reorg_buffer: u64,
+    /// order in which chunks are collected: normal, reverse, or random
+    pub chunk_order: String,
+    /// block ranges resolved from timestamps
+    pub timestamp_ranges: Vec<TimestampRange>,
+    /// calendar buckets that chunks are aligned to
+    pub time_buckets: Vec<TimeBucket>,
+    /// topics resolved from event signatures and addresses
+    pub resolved_topics: Vec<ResolvedTopic>,
+}
+
+/// topic resolved from an event signature or an address
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ResolvedTopic {
+    /// name of topic, e.g. topic0
+    pub topic: String,
+    /// topic input, as given
+    pub input: String,
+    /// hex of resolved 32 byte topic
+    pub value: String,
+}
+
+/// block range resolved from a timestamp input
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct TimestampRange {
+    /// timestamp input, as given
+    pub input: String,
+    /// first timestamp of range
+    pub start_timestamp: u64,
+    /// last timestamp of range
+    pub end_timestamp: u64,
+    /// first block at or after start timestamp
+    pub start_block: u64,
+    /// last block at or before end timestamp
+    pub end_block: u64,
+}
+
+/// block range of a calendar bucket of block timestamps that a chunk covers
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct TimeBucket {
+    /// name of bucket, e.g. 2024-01-31, 2024-01-31T13, or 2024-W05
+    pub bucket: String,
+    /// whether the chunk only covers part of the blocks of the bucket
+    pub partial: bool,
+    /// first timestamp of bucket
+    pub start_timestamp: u64,
+    /// last timestamp of bucket
+    pub end_timestamp: u64,
+    /// first block of chunk
+    pub start_block: u64,
+    /// last block of chunk
+    pub end_block: u64,
+}
+
+impl TimeBucket {
+    /// name of the bucket in chunk labels, suffixed with _partial for partial buckets
+    pub fn label(&self) -> String {
+        match self.partial {
+            true => format!("{}_partial", self.bucket),
+            false => self.bucket.clone(),
+        }
+    }
 }
 
 impl Query {
@@ -68,13 +181,355 @@ impl Query {
         }
         Ok(())
     }
+
+    /// note in the report that transaction indices of a dataset are positions of its traces,
+    /// for traces whose source does not report the index of their transaction
+    pub(crate) fn note_trace_positions(&self, datatype: &Datatype, indices: &[Option<u64>]) {
+        let requested = self
+            .schemas
+            .get(datatype)
+            .map(|schema| schema.has_column("transaction_index"))
+            .unwrap_or(false);
+        if requested && indices.iter().any(|index| index.is_none()) {
+            self.warnings.increment(&format!(
+                "{}: transaction_index derived from the order of traces",
+                datatype.name()
+            ));
+        }
+    }
+
+    /// record the failed request of a block in each requested datatype of a chunk when failed
+    /// requests are skipped, returning whether the error was recorded instead of failing the
+    /// chunk, only the rows of the failed request are missing from datasets that make several
+    /// requests per block
+    pub(crate) fn record_block_error(
+        &self,
+        datatypes: &[Datatype],
+        chunk: &str,
+        block_number: Option<u64>,
+        error: &CollectError,
+    ) -> bool {
+        let block_number = match (self.on_block_error, &self.time_dimension, block_number) {
+            (OnBlockError::Fail, _, _) => return false,
+            (_, TimeDimension::Blocks, Some(block_number)) => block_number,
+            _ => return false,
+        };
+        // unsupported methods fail every block, so they are still reported per dataset
+        if matches!(error, CollectError::MethodNotFound(_)) {
+            return false
+        }
+        for datatype in datatypes.iter().filter(|datatype| self.schemas.contains_key(datatype)) {
+            self.block_errors.push(datatype.name(), chunk.to_string(), block_number, error);
+        }
+        true
+    }
+}
+
+/// counts of recoverable problems encountered during collection, e.g. skipped malformed logs
+#[derive(Clone, Debug, Default)]
+pub struct CollectWarnings(Arc<Mutex<BTreeMap<String, u64>>>);
+
+impl CollectWarnings {
+    /// increment count of warning
+    pub fn increment(&self, warning: &str) {
+        self.add(warning, 1)
+    }
+
+    /// add to count of warning
+    pub fn add(&self, warning: &str, count: u64) {
+        if let Ok(mut counts) = self.0.lock() {
+            *counts.entry(warning.to_string()).or_insert(0) += count;
+        }
+    }
+
+    /// current count of each warning
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        self.0.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+}
+
+/// time spent in each phase of collection, summed over the chunks of a query
+#[derive(Clone, Debug, Default)]
+pub struct PhaseTimings(Arc<Mutex<BTreeMap<String, Duration>>>);
+
+impl PhaseTimings {
+    /// add to time spent in phase
+    pub fn add(&self, phase: &str, duration: Duration) {
+        if let Ok(mut timings) = self.0.lock() {
+            *timings.entry(phase.to_string()).or_default() += duration;
+        }
+    }
+
+    /// run f, adding its duration to phase
+    pub(crate) fn time<T>(&self, phase: &str, f: impl FnOnce() -> T) -> T {
+        let t_start = Instant::now();
+        let output = f();
+        self.add(phase, t_start.elapsed());
+        output
+    }
+
+    /// time spent in phase so far
+    pub fn get(&self, phase: &str) -> Duration {
+        self.0.lock().ok().and_then(|timings| timings.get(phase).copied()).unwrap_or_default()
+    }
+}
+
+/// error shared by all chunks of a query once any chunk encounters it
+#[derive(Clone, Debug, Default)]
+pub struct SharedError(Arc<Mutex<Option<String>>>);
+
+impl SharedError {
+    /// record error, keeping the first one if already set
+    pub fn set(&self, message: &str) {
+        if let Ok(mut error) = self.0.lock() {
+            error.get_or_insert_with(|| message.to_string());
+        }
+    }
+
+    /// recorded error, if any
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|error| error.clone())
+    }
+}
+
+/// what to do with the chunk of a block whose request fails
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnBlockError {
+    /// fail the chunk
+    #[default]
+    Fail,
+    /// leave the rows of the failed request out of its chunk, recording it in the report, or in
+    /// the errors file of the run when no report is written
+    Skip,
+    /// leave the rows of the failed request out of its chunk, recording a placeholder in the
+    /// errors file of the run
+    Null,
+}
+
+/// failed request of a block, whose rows are left out of its chunk
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct BlockError {
+    /// name of dataset
+    pub dataset: String,
+    /// label of chunk
+    pub chunk: String,
+    /// number of block
+    pub block_number: u64,
+    /// error of request
+    pub error: String,
+}
+
+/// failed requests of blocks, shared by all chunks of a query
+#[derive(Clone, Debug, Default)]
+pub struct BlockErrors(Arc<Mutex<Vec<BlockError>>>);
+
+impl BlockErrors {
+    /// record failed request of a block of a chunk of a dataset
+    pub fn push(&self, dataset: String, chunk: String, block_number: u64, error: &CollectError) {
+        if let Ok(mut errors) = self.0.lock() {
+            errors.push(BlockError { dataset, chunk, block_number, error: error.to_string() });
+        }
+    }
+
+    /// failed requests so far, ordered by block number
+    pub fn get(&self) -> Vec<BlockError> {
+        let mut errors = self.0.lock().map(|errors| errors.clone()).unwrap_or_default();
+        errors.sort_by(|a, b| (a.block_number, &a.dataset).cmp(&(b.block_number, &b.dataset)));
+        errors
+    }
+
+    /// blocks with failed requests in a chunk of a dataset, ordered by block number
+    pub fn missing_blocks(&self, dataset: &str, chunk: &str) -> Vec<u64> {
+        let mut blocks: Vec<u64> = self
+            .get()
+            .into_iter()
+            .filter(|error| error.dataset == dataset && error.chunk == chunk)
+            .map(|error| error.block_number)
+            .collect();
+        blocks.dedup();
+        blocks
+    }
+}
+
+/// decimals of erc20 tokens encountered during collection, None if decimals() reverted
+#[derive(Clone, Debug, Default)]
+pub struct TokenDecimals(Arc<Mutex<HashMap<Vec<u8>, Option<u32>>>>);
+
+impl TokenDecimals {
+    /// decimals of token, outer None if token has not been fetched yet
+    pub fn get(&self, token: &[u8]) -> Option<Option<u32>> {
+        self.0.lock().ok().and_then(|decimals| decimals.get(token).copied())
+    }
+
+    /// record decimals of token
+    pub fn insert(&self, token: Vec<u8>, decimals: Option<u32>) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.insert(token, decimals);
+        }
+    }
+}
+
+/// granularity of four_byte_counts rows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FourByteAggregation {
+    /// one row per selector of each transaction
+    #[default]
+    Transaction,
+    /// one row per selector summed over each chunk
+    Range,
+}
+
+/// storage slots to keep, given either exactly or as a prefix of precomputed mapping keys
+#[derive(Clone, Debug, Default)]
+pub struct SlotFilter {
+    slots: HashSet<Vec<u8>>,
+    prefixes: Vec<Vec<u8>>,
+}
+
+impl SlotFilter {
+    /// create filter, slots shorter than 32 bytes are left padded with zeros
+    pub fn new(slots: Vec<Vec<u8>>, prefixes: Vec<Vec<u8>>) -> Self {
+        let slots = slots
+            .into_iter()
+            .map(|slot| {
+                let mut padded = vec![0; 32usize.saturating_sub(slot.len())];
+                padded.extend(slot);
+                padded
+            })
+            .collect();
+        SlotFilter { slots, prefixes }
+    }
+
+    /// whether slot passes the filter
+    pub fn matches(&self, slot: &[u8]) -> bool {
+        self.slots.contains(slot) || self.prefixes.iter().any(|prefix| slot.starts_with(prefix))
+    }
+}
+
+/// which address of a trace entry is matched against a TraceContractFilter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceFilterSide {
+    /// sender of call
+    From,
+    /// recipient of call
+    To,
+    /// either sender or recipient of call
+    #[default]
+    Either,
+}
+
+/// contracts to keep trace entries of
+#[derive(Clone, Debug, Default)]
+pub struct TraceContractFilter {
+    /// contract addresses
+    pub contracts: HashSet<Vec<u8>>,
+    /// side of trace entries that contracts are matched against
+    pub side: TraceFilterSide,
+}
+
+impl TraceContractFilter {
+    /// whether trace entry with given from and to addresses passes the filter
+    pub fn matches(&self, from: Option<&[u8]>, to: Option<&[u8]>) -> bool {
+        let from = from.is_some_and(|from| self.contracts.contains(from));
+        let to = to.is_some_and(|to| self.contracts.contains(to));
+        match self.side {
+            TraceFilterSide::From => from,
+            TraceFilterSide::To => to,
+            TraceFilterSide::Either => from || to,
+        }
+    }
+}
+
+/// addresses and topics of logs matched by one eth_getLogs request, empty positions match any
+/// value
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// contract addresses, any of which match
+    pub addresses: Vec<Vec<u8>>,
+    /// topics at each position, any of which match
+    pub topics: [Vec<Vec<u8>>; 4],
+}
+
+impl LogFilter {
+    /// whether filter matches every log
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.topics.iter().all(|topics| topics.is_empty())
+    }
+}
+
+/// labels of addresses, joined to outputs by their address columns
+#[derive(Clone, Debug, Default)]
+pub struct AddressLabels(HashMap<Vec<u8>, String>);
+
+impl AddressLabels {
+    /// create labels from address and label pairs
+    pub fn new(labels: HashMap<Vec<u8>, String>) -> Self {
+        AddressLabels(labels)
+    }
+
+    /// label of address
+    pub fn get(&self, address: &[u8]) -> Option<&str> {
+        self.0.get(address).map(|label| label.as_str())
+    }
 }
 
 /// Time dimension for queries
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub enum TimeDimension {
     /// Blocks
+    #[default]
     Blocks,
     /// Transactions
     Transactions,
+    /// Snapshots of node state taken at collection time
+    Snapshots,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_filter() {
+        let filter = SlotFilter::new(vec![vec![0]], vec![vec![0xab, 0xcd]]);
+        assert!(filter.matches(&[0; 32]));
+        let mut mapping_key = vec![0xab, 0xcd];
+        mapping_key.extend([7; 30]);
+        assert!(filter.matches(&mapping_key));
+        assert!(!filter.matches(&[1; 32]));
+    }
+
+    #[test]
+    fn test_block_errors() {
+        let errors = BlockErrors::default();
+        let error = CollectError::CollectError("trace failed".to_string());
+        errors.push("traces".to_string(), "0_to_99".to_string(), 12, &error);
+        errors.clone().push(
+            "traces".to_string(),
+            "0_to_99".to_string(),
+            10,
+            &CollectError::TooManyRequestsError,
+        );
+        errors.push("logs".to_string(), "0_to_99".to_string(), 11, &error);
+        let numbers: Vec<u64> = errors.get().iter().map(|error| error.block_number).collect();
+        assert_eq!(numbers, vec![10, 11, 12]);
+        assert_eq!(errors.get()[2].error, "Collect failed: trace failed");
+        assert_eq!(errors.missing_blocks("traces", "0_to_99"), vec![10, 12]);
+        assert!(errors.missing_blocks("traces", "100_to_199").is_empty());
+    }
+
+    #[test]
+    fn test_trace_contract_filter() {
+        let contract = vec![1; 20];
+        let other = vec![2; 20];
+        let mut filter = TraceContractFilter {
+            contracts: [contract.clone()].into_iter().collect(),
+            side: TraceFilterSide::Either,
+        };
+        assert!(filter.matches(Some(&other), Some(&contract)));
+        assert!(!filter.matches(Some(&other), None));
+        filter.side = TraceFilterSide::From;
+        assert!(!filter.matches(Some(&other), Some(&contract)));
+        assert!(filter.matches(Some(&contract), None));
+    }
 }
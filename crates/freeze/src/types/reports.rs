@@ -1,9 +1,15 @@
-use crate::{err, CollectError, ExecutionEnv, FileOutput, FreezeSummary, Query};
+use crate::{
+    dataframes, err, AutoscaleState, BlockError, CollectError, Datatype, EndpointStats,
+    ExecutionEnv, FileFormat, FileOutput, FreezeSummary, MetaDatatype, OnBlockError, OutputSchema,
+    Partition, Query, ResolvedTopic, Source, Table, TimeBucket, TimestampRange, TraceBackend,
+};
 use chrono::{DateTime, Local};
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::Write,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 #[derive(serde::Serialize, Debug)]
@@ -11,8 +17,31 @@ struct FreezeReport {
     cryo_version: String,
     // node_client: String,
     cli_command: Option<Vec<String>>,
+    resumed_from: Option<PathBuf>,
     results: Option<SerializedFreezeSummary>,
     args: Option<String>,
+    notes: Vec<String>,
+    bloom_filter_columns: BTreeMap<String, Vec<String>>,
+    checksum_manifest: Option<PathBuf>,
+    errors_file: Option<PathBuf>,
+    timestamp_ranges: Vec<TimestampRange>,
+    time_buckets: Vec<TimeBucket>,
+    resolved_topics: Vec<ResolvedTopic>,
+    schemas: BTreeMap<String, OutputSchema>,
+    schema_options: BTreeMap<String, SchemaOptions>,
+}
+
+/// options that shape the columns of the files of a datatype
+#[derive(serde::Serialize, Debug)]
+struct SchemaOptions {
+    sort_columns: Option<Vec<String>>,
+    u256_types: Vec<String>,
+    binary_type: String,
+    checksum_addresses: bool,
+    block_number_type: String,
+    block_time_columns: Vec<String>,
+    constant_columns: BTreeMap<String, String>,
+    column_renames: BTreeMap<String, String>,
 }
 
 #[derive(serde::Serialize, Debug)]
@@ -20,6 +49,57 @@ struct SerializedFreezeSummary {
     completed_paths: Vec<PathBuf>,
     errored_paths: Vec<PathBuf>,
     n_skipped: u64,
+    n_cancelled: u64,
+    warnings: BTreeMap<String, u64>,
+    endpoints: Vec<EndpointStats>,
+    autoscale: Option<AutoscaleState>,
+    retries: BTreeMap<String, u64>,
+    errors: BTreeMap<String, u64>,
+    rows: BTreeMap<String, u64>,
+    n_bytes: u64,
+    rpc_methods: BTreeMap<String, u64>,
+    shared_responses: BTreeMap<String, u64>,
+    capabilities: BTreeMap<String, bool>,
+    unsupported_datasets: Vec<UnsupportedDatasetReport>,
+    block_errors: Vec<BlockError>,
+    chunks: Vec<ChunkReport>,
+    files: Vec<OutputFile>,
+    wall_time: WallTime,
+}
+
+/// outcome of one chunk of one dataset
+#[derive(serde::Serialize, Debug)]
+struct ChunkReport {
+    chunk: Option<String>,
+    dataset: Option<String>,
+    status: &'static str,
+    error: Option<String>,
+    paths: Vec<PathBuf>,
+}
+
+/// dataset that was not collected because the endpoint does not support a method it needs
+#[derive(serde::Serialize, Debug)]
+struct UnsupportedDatasetReport {
+    dataset: String,
+    status: &'static str,
+    error: String,
+    n_chunks: u64,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct OutputFile {
+    path: PathBuf,
+    n_bytes: u64,
+}
+
+/// seconds of the run, with the time of each phase summed over chunks, so phases of concurrent
+/// chunks can add up to more than the total
+#[derive(serde::Serialize, Debug)]
+struct WallTime {
+    total: f64,
+    fetch: f64,
+    transform: f64,
+    write: f64,
 }
 
 pub(crate) fn get_report_path(
@@ -27,6 +107,10 @@ pub(crate) fn get_report_path(
     sink: &FileOutput,
     is_complete: bool,
 ) -> Result<PathBuf, CollectError> {
+    if let Some(report_file) = &env.report_file {
+        return report_file_path(report_file, is_complete)
+    }
+
     // create directory
     let report_dir = match &env.report_dir {
         Some(report_dir) => Path::new(&report_dir).into(),
@@ -48,30 +132,70 @@ pub(crate) fn get_report_path(
     Ok(report_dir.join(filename))
 }
 
+/// path of --report-file, with the report of an unfinished run next to it
+fn report_file_path(report_file: &Path, is_complete: bool) -> Result<PathBuf, CollectError> {
+    if let Some(parent) = report_file.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|_| CollectError::CollectError("could not create report dir".to_string()))?;
+    }
+    match (is_complete, report_file.file_name()) {
+        (true, _) => Ok(report_file.to_path_buf()),
+        (false, Some(name)) => {
+            Ok(report_file.with_file_name(format!("incomplete_{}", name.to_string_lossy())))
+        }
+        (false, None) => Err(err("invalid report file")),
+    }
+}
+
 pub(crate) fn write_report(
     env: &ExecutionEnv,
     query: &Query,
+    source: &Source,
     sink: &FileOutput,
     freeze_summary: Option<&FreezeSummary>,
 ) -> Result<PathBuf, CollectError> {
     // determine version
     let cryo_version = CRYO_VERSION.to_string();
     let serialized_summary = match freeze_summary {
-        Some(x) => Some(serialize_summary(x, query, sink)?),
+        Some(x) => Some(serialize_summary(x, query, source, sink, env)?),
         None => None,
     };
+    // create path
+    let path = get_report_path(env, sink, freeze_summary.is_some())?;
+
+    let errors_file = match freeze_summary {
+        Some(_) if writes_errors_file(query, env) && !query.block_errors.get().is_empty() => {
+            Some(errors_file_path(env, &sink.output_dir))
+        }
+        _ => None,
+    };
     let report = FreezeReport {
         cryo_version,
         cli_command: env.cli_command.clone(),
+        resumed_from: env.resumed_from.clone(),
         args: env.args.clone(),
         results: serialized_summary,
+        notes: report_notes(query, source),
+        bloom_filter_columns: bloom_filter_columns(query, sink),
+        checksum_manifest: sink.checksum_manifest.clone(),
+        errors_file,
+        timestamp_ranges: query.labels.timestamp_ranges.clone(),
+        time_buckets: query.labels.time_buckets.clone(),
+        resolved_topics: query.labels.resolved_topics.clone(),
+        schemas: query
+            .schemas
+            .iter()
+            .map(|(datatype, table)| Ok((datatype.name(), table.output_schema()?)))
+            .collect::<Result<_, CollectError>>()?,
+        schema_options: query
+            .schemas
+            .iter()
+            .map(|(datatype, table)| (datatype.name(), schema_options(table)))
+            .collect(),
     };
     let serialized = serde_json::to_string(&report)
         .map_err(|_| CollectError::CollectError("could not serialize report".to_string()))?;
 
-    // create path
-    let path = get_report_path(env, sink, freeze_summary.is_some())?;
-
     // save to file
     let mut file = File::create(&path)
         .map_err(|_| CollectError::CollectError("could not create report file".to_string()))?;
@@ -88,43 +212,273 @@ pub(crate) fn write_report(
     Ok(path)
 }
 
+/// path of the errors file of a run, under the output directory so that it is uploaded along
+/// with the files of the run
+pub fn errors_file_path(env: &ExecutionEnv, output_dir: &Path) -> PathBuf {
+    let t_start: DateTime<Local> = env.t_start.into();
+    let timestamp: String = t_start.format("%Y-%m-%d_%H-%M-%S%.6f").to_string();
+    output_dir.join(".cryo/errors").join(timestamp + ".jsonl")
+}
+
+/// whether failed block requests are written to an errors file, always with
+/// --on-block-error null, and with skip when no report records them
+fn writes_errors_file(query: &Query, env: &ExecutionEnv) -> bool {
+    match query.on_block_error {
+        OnBlockError::Fail => false,
+        OnBlockError::Skip => !env.report,
+        OnBlockError::Null => true,
+    }
+}
+
+/// write a line for each failed block request, returning the path of the errors file if any
+/// requests failed
+pub fn write_errors_file(
+    query: &Query,
+    output_dir: &Path,
+    env: &ExecutionEnv,
+) -> Result<Option<PathBuf>, CollectError> {
+    let block_errors = query.block_errors.get();
+    if !writes_errors_file(query, env) || block_errors.is_empty() {
+        return Ok(None)
+    }
+    let mut lines = String::new();
+    for block_error in block_errors.iter() {
+        let line = serde_json::to_string(block_error)
+            .map_err(|_| err("could not serialize block error"))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    let path = errors_file_path(env, output_dir);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|_| err("could not create errors dir"))?;
+    }
+    std::fs::write(&path, lines).map_err(|_| err("could not write errors file"))?;
+    Ok(Some(path))
+}
+
+/// caveats about how the output files should be interpreted
+fn report_notes(query: &Query, source: &Source) -> Vec<String> {
+    let mut notes = Vec::new();
+    let has_balances = [Datatype::Balances, Datatype::Erc20Balances]
+        .iter()
+        .any(|datatype| query.schemas.contains_key(datatype));
+    if query.changes_only && has_balances {
+        notes.push(
+            "changes_only: balances are only emitted when they differ from the previous block of \
+             the same chunk, the first block of every chunk is always emitted, so consecutive files \
+             can repeat a balance at their boundary and should be de-duplicated by keeping rows \
+             whose balance differs from the previous row of the same address"
+                .to_string(),
+        );
+    }
+    if source.resolved_trace_backend() == Some(TraceBackend::Geth) {
+        notes.push(
+            "trace_backend: traces and state diffs were converted from geth debug traces, which \
+             have no block reward entries, so the reward rows of traces and native_transfers are \
+             missing and action_reward_type is null, and errors use geth messages such as \
+             \"execution reverted\" instead of \"Reverted\""
+                .to_string(),
+        );
+    }
+    notes
+}
+
+/// columns of each datatype that parquet bloom filters are written for
+fn bloom_filter_columns(query: &Query, sink: &FileOutput) -> BTreeMap<String, Vec<String>> {
+    match (&sink.bloom_filters, &sink.format) {
+        (Some(options), FileFormat::Parquet) => query
+            .schemas
+            .iter()
+            .map(|(datatype, table)| {
+                let columns = options.filter_columns(&table.columns());
+                (datatype.name(), columns.into_iter().map(|c| c.to_string()).collect())
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+fn schema_options(table: &Table) -> SchemaOptions {
+    SchemaOptions {
+        sort_columns: table.sort_columns.clone(),
+        u256_types: table
+            .u256_types
+            .iter()
+            .map(|u256_type| u256_type.suffix().trim_start_matches('_').to_string())
+            .collect(),
+        binary_type: table.binary_type.as_str().to_string(),
+        checksum_addresses: table.checksum_addresses,
+        block_number_type: table.block_number_type.to_columntype().as_str().to_string(),
+        block_time_columns: table.block_time_columns.clone(),
+        constant_columns: table.constant_columns.iter().cloned().collect(),
+        column_renames: table.column_renames.iter().cloned().collect(),
+    }
+}
+
 fn serialize_summary(
     summary: &FreezeSummary,
     query: &Query,
+    source: &Source,
     sink: &FileOutput,
+    env: &ExecutionEnv,
 ) -> Result<SerializedFreezeSummary, CollectError> {
-    let completed_paths: Vec<PathBuf> = summary
-        .completed
+    let mut chunks = Vec::new();
+    for (partition, datatype) in summary.completed.iter() {
+        chunks.push(chunk_report(partition, datatype, "completed", None, query, sink)?);
+    }
+    for (partition, error) in summary.errored.iter() {
+        let chunk = match partition {
+            Some((partition, datatype)) => {
+                chunk_report(partition, datatype, "failed", Some(error), query, sink)?
+            }
+            None => ChunkReport {
+                chunk: None,
+                dataset: None,
+                status: "failed",
+                error: Some(error.to_string()),
+                paths: vec![],
+            },
+        };
+        chunks.push(chunk);
+    }
+    for (partition, datatype) in summary.skipped.iter() {
+        chunks.push(chunk_report(partition, datatype, "skipped", None, query, sink)?);
+    }
+    for (partition, datatype) in summary.cancelled.iter() {
+        chunks.push(chunk_report(partition, datatype, "cancelled", None, query, sink)?);
+    }
+
+    let paths_of = |status: &str| -> Vec<PathBuf> {
+        chunks
+            .iter()
+            .filter(|chunk| chunk.status == status)
+            .flat_map(|chunk| chunk.paths.clone())
+            .collect()
+    };
+    let completed_paths = paths_of("completed");
+    let errored_paths = paths_of("failed");
+    let is_manifest = sink.hive_partitioning.is_some() || sink.max_rows_per_file.is_some();
+    let files = completed_paths
         .iter()
-        .map(|partition| {
-            sink.get_paths(query, partition, None)
-                .map(|paths| paths.values().cloned().collect::<Vec<_>>())
+        .map(|path| OutputFile {
+            path: path.clone(),
+            n_bytes: dataframes::written_bytes(path, is_manifest),
         })
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .flatten()
         .collect();
 
-    let errored_paths: Vec<PathBuf> = summary
-        .errored
+    let seconds = |phase: &str| query.timings.get(phase).as_secs_f64();
+    let wall_time = WallTime {
+        total: env
+            .t_end
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(env.t_start)
+            .map(|duration| duration.as_secs_f64())
+            .unwrap_or_default(),
+        fetch: (seconds("chunk") - seconds("transform") - seconds("write")).max(0.0),
+        transform: seconds("transform"),
+        write: seconds("write"),
+    };
+
+    let unsupported_datasets = summary
+        .unsupported
         .iter()
-        .filter_map(|(partition_option, _error)| {
-            partition_option.as_ref().map(|partition| {
-                sink.get_paths(query, partition, None)
-                    .map(|paths| paths.values().cloned().collect::<Vec<_>>())
-            })
+        .map(|unsupported| UnsupportedDatasetReport {
+            dataset: unsupported.dataset.clone(),
+            status: "unsupported by endpoint",
+            error: unsupported.error.to_string(),
+            n_chunks: unsupported.partitions.len() as u64,
         })
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .flatten()
         .collect();
 
+    let (rpc_methods, capabilities) = source.method_counts();
     Ok(SerializedFreezeSummary {
         completed_paths,
         errored_paths,
         n_skipped: summary.skipped.len() as u64,
+        n_cancelled: summary.cancelled.len() as u64,
+        warnings: query.warnings.counts(),
+        endpoints: source.endpoint_stats(),
+        autoscale: source.autoscale_state(),
+        retries: source.retry_counts(),
+        errors: source.error_counts(),
+        rows: env.counts.rows(),
+        n_bytes: env.counts.n_bytes(),
+        rpc_methods,
+        shared_responses: source.fetcher.shared_fetches.hits(),
+        capabilities,
+        unsupported_datasets,
+        block_errors: query.block_errors.get(),
+        chunks,
+        files,
+        wall_time,
+    })
+}
+
+fn chunk_report(
+    partition: &Partition,
+    datatype: &MetaDatatype,
+    status: &'static str,
+    error: Option<&CollectError>,
+    query: &Query,
+    sink: &FileOutput,
+) -> Result<ChunkReport, CollectError> {
+    let paths = sink.get_paths(query, partition, Some(vec![datatype.clone()]))?;
+    let mut paths: Vec<PathBuf> = paths.into_values().collect();
+    paths.sort();
+    Ok(ChunkReport {
+        chunk: Some(partition.label(&query.partitioned_by)?),
+        dataset: Some(datatype.name()),
+        status,
+        error: error.map(|error| error.to_string()),
+        paths,
     })
 }
 
 /// cryo version
 pub const CRYO_VERSION: &str = env!("GIT_DESCRIPTION");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_file_path() {
+        let path = Path::new("report.json");
+        assert_eq!(report_file_path(path, true).unwrap(), PathBuf::from("report.json"));
+        assert_eq!(report_file_path(path, false).unwrap(), PathBuf::from("incomplete_report.json"));
+        let dir = std::env::temp_dir().join(format!("cryo_report_{}", std::process::id()));
+        let path = dir.join("runs/report.json");
+        assert_eq!(
+            report_file_path(&path, false).unwrap(),
+            dir.join("runs/incomplete_report.json")
+        );
+        assert!(dir.join("runs").is_dir());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_errors_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_errors_file_{}", std::process::id()));
+        let env = ExecutionEnv { report: false, ..Default::default() };
+        let mut query = Query { on_block_error: OnBlockError::Skip, ..Default::default() };
+        assert_eq!(write_errors_file(&query, &dir, &env).unwrap(), None);
+
+        let error = CollectError::CollectError("trace failed".to_string());
+        query.block_errors.push("traces".to_string(), "0_to_99".to_string(), 7, &error);
+        let path = write_errors_file(&query, &dir, &env).unwrap().unwrap();
+        assert!(path.starts_with(dir.join(".cryo/errors")));
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            "{\"dataset\":\"traces\",\"chunk\":\"0_to_99\",\"block_number\":7,\
+             \"error\":\"Collect failed: trace failed\"}\n"
+        );
+
+        // skipped blocks are recorded by the report when one is written
+        let env = ExecutionEnv { report: true, ..env };
+        assert_eq!(write_errors_file(&query, &dir, &env).unwrap(), None);
+        query.on_block_error = OnBlockError::Null;
+        assert!(write_errors_file(&query, &dir, &env).unwrap().is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
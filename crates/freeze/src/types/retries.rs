@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use ::rand::Rng;
+use ethers::prelude::*;
+
+use crate::{err, CollectError};
+
+/// longest time waited before retrying a request
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// how failed requests are retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryOptions {
+    /// maximum number of times a request is retried
+    pub max_retries: u32,
+    /// time waited before the first retry, doubling for each later retry
+    pub initial_backoff: Duration,
+}
+
+impl RetryOptions {
+    /// time to wait before a retry, exponential in the number of previous retries with jitter so
+    /// that concurrent requests do not retry in lockstep
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff =
+            self.initial_backoff.saturating_mul(2u32.saturating_pow(retry)).min(MAX_BACKOFF);
+        backoff.mul_f64(::rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// how a failed request is handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ErrorClass {
+    /// request was rate limited, retried after a backoff
+    RateLimited,
+    /// timeouts, connection errors, gateway errors, and blocks not yet known near the tip, retried
+    /// after a backoff
+    Transient,
+    /// node does not support the method, failed without retrying
+    MethodNotFound,
+    /// node has pruned the state of the block, failed without retrying
+    MissingState,
+    /// error caused by the request itself, such as a reverted call
+    Fatal,
+}
+
+impl ErrorClass {
+    pub(crate) fn is_retryable(&self) -> bool {
+        matches!(self, ErrorClass::RateLimited | ErrorClass::Transient)
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ErrorClass::RateLimited => "rate_limited",
+            ErrorClass::Transient => "transient",
+            ErrorClass::MethodNotFound => "method_not_found",
+            ErrorClass::MissingState => "missing_state",
+            ErrorClass::Fatal => "fatal",
+        }
+    }
+}
+
+/// classify an error of a retry client, requests that time out are transient rather than rate
+/// limited since slow responses are not a sign that the endpoint is throttling requests
+pub(crate) fn classify(error: &RetryClientError) -> ErrorClass {
+    match error {
+        RetryClientError::TimeoutError => ErrorClass::Transient,
+        RetryClientError::SerdeJson(_) => ErrorClass::Fatal,
+        RetryClientError::ProviderError(error) => classify_provider_error(error),
+    }
+}
+
+pub(crate) fn classify_provider_error(error: &ProviderError) -> ErrorClass {
+    match (RpcError::as_error_response(error), RpcError::as_serde_error(error)) {
+        (Some(response), _) => classify_error_response(response),
+        // gateways respond to failed requests with html or empty bodies
+        (None, Some(error)) if error.is_syntax() || error.is_eof() => ErrorClass::Transient,
+        (None, Some(_)) => ErrorClass::Fatal,
+        (None, None) => ErrorClass::Transient,
+    }
+}
+
+fn classify_error_response(response: &JsonRpcError) -> ErrorClass {
+    let message = response.message.to_lowercase();
+    if response.code == -32601 ||
+        message.contains("method not found") ||
+        message.contains("does not exist/is not available")
+    {
+        ErrorClass::MethodNotFound
+    } else if message.contains("missing trie node") || message.contains("historical state") {
+        ErrorClass::MissingState
+    } else if message.contains("header not found") || message.contains("unknown block") {
+        ErrorClass::Transient
+    } else if HttpRateLimitRetryPolicy
+        .should_retry(&HttpClientError::JsonRpcError(response.clone()))
+    {
+        ErrorClass::RateLimited
+    } else {
+        ErrorClass::Fatal
+    }
+}
+
+/// convert a provider error, explaining errors that retrying cannot resolve
+pub(crate) fn provider_error(error: ProviderError) -> CollectError {
+    let message = match RpcError::as_error_response(&error) {
+        Some(response) => response.message.clone(),
+        None => return CollectError::ProviderError(error),
+    };
+    match classify_provider_error(&error) {
+        ErrorClass::MethodNotFound => CollectError::MethodNotFound(message),
+        ErrorClass::MissingState => err(&format!(
+            "node does not have the state of the requested block, it may be pruned, try an \
+             archive node: {}",
+            message
+        )),
+        _ => CollectError::ProviderError(error),
+    }
+}
+
+/// convert a provider error of an rpc method, naming the method if the node does not support it
+pub(crate) fn method_error(method: &'static str) -> impl Fn(ProviderError) -> CollectError {
+    move |error| match provider_error(error) {
+        CollectError::MethodNotFound(message) if !message.contains(method) => {
+            CollectError::MethodNotFound(format!("{} ({})", method, message))
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_error(code: i64, message: &str) -> RetryClientError {
+        let response = JsonRpcError { code, message: message.to_string(), data: None };
+        let error = HttpClientError::JsonRpcError(response);
+        RetryClientError::ProviderError(ProviderError::JsonRpcClientError(Box::new(error)))
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&RetryClientError::TimeoutError), ErrorClass::Transient);
+        assert_eq!(classify(&response_error(429, "too many requests")), ErrorClass::RateLimited);
+        assert_eq!(classify(&response_error(-32000, "header not found")), ErrorClass::Transient);
+        let error = ProviderError::CustomError("connection reset".to_string());
+        assert_eq!(classify(&RetryClientError::ProviderError(error)), ErrorClass::Transient);
+        let text = "<html>502 Bad Gateway</html>".to_string();
+        let gateway = HttpClientError::SerdeJson {
+            err: serde_json::from_str::<serde_json::Value>(&text).unwrap_err(),
+            text,
+        };
+        let error = ProviderError::JsonRpcClientError(Box::new(gateway));
+        assert_eq!(classify(&RetryClientError::ProviderError(error)), ErrorClass::Transient);
+
+        let not_found = response_error(-32601, "the method trace_block does not exist");
+        assert_eq!(classify(&not_found), ErrorClass::MethodNotFound);
+        let pruned =
+            response_error(-32000, "missing trie node 1f2e (path ) state is not available");
+        assert_eq!(classify(&pruned), ErrorClass::MissingState);
+        assert_eq!(classify(&response_error(3, "execution reverted")), ErrorClass::Fatal);
+    }
+
+    #[test]
+    fn test_method_error() {
+        let response =
+            JsonRpcError { code: -32601, message: "Method not found".into(), data: None };
+        let error = HttpClientError::JsonRpcError(response);
+        let error = ProviderError::JsonRpcClientError(Box::new(error));
+        let error = method_error("debug_traceBlockByNumber")(error);
+        assert_eq!(
+            error.to_string(),
+            "rpc method is not supported by the node: debug_traceBlockByNumber (Method not found)"
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        let options = RetryOptions { max_retries: 5, initial_backoff: Duration::from_millis(100) };
+        for retry in 0..4 {
+            let backoff = options.backoff(retry);
+            let max = Duration::from_millis(100 * 2u64.pow(retry));
+            assert!(backoff >= max / 2 && backoff <= max);
+        }
+        assert!(options.backoff(20) <= MAX_BACKOFF);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{err, CollectError};
+use crate::{err, CollectError, LogFilter};
 use ethers::prelude::*;
 
 /// represents parameters for a single rpc call
@@ -109,4 +109,26 @@ impl Params {
         };
         Ok(filter)
     }
+
+    /// log filter matching any of the addresses and any of the topics at each position of a
+    /// filter set
+    pub fn ethers_log_filter_set(&self, filter_set: &LogFilter) -> Result<Filter, CollectError> {
+        let mut filter = self.ethers_log_filter()?;
+        filter.address =
+            value_or_array(filter_set.addresses.iter().map(|x| H160::from_slice(x)).collect());
+        for (position, topics) in filter_set.topics.iter().enumerate() {
+            filter.topics[position] =
+                value_or_array(topics.iter().map(|x| Some(H256::from_slice(x))).collect());
+        }
+        Ok(filter)
+    }
+}
+
+/// single values are sent as values rather than arrays, for nodes without array support
+fn value_or_array<T>(mut values: Vec<T>) -> Option<ValueOrArray<T>> {
+    match values.len() {
+        0 => None,
+        1 => values.pop().map(ValueOrArray::Value),
+        _ => Some(ValueOrArray::Array(values)),
+    }
 }
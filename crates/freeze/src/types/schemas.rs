@@ -1,7 +1,7 @@
 /// types and functions related to schemas
 use std::collections::HashMap;
 
-use crate::{err, CollectError, ColumnEncoding, Datatype, LogDecoder};
+use crate::{err, AbiDecoder, CollectError, ColumnEncoding, Datatype, LogDecoder};
 use indexmap::{IndexMap, IndexSet};
 use thiserror::Error;
 
@@ -37,8 +37,26 @@ pub struct Table {
     /// representation to use for binary columns
     pub binary_type: ColumnEncoding,
 
+    /// whether hex address columns use EIP-55 checksummed encoding
+    pub checksum_addresses: bool,
+
+    /// representation to use for block number columns
+    pub block_number_type: BlockNumberType,
+
+    /// columns that are joined from the block header of each row after collection
+    pub block_time_columns: Vec<String>,
+
+    /// columns with the same value in every row, added after collection
+    pub constant_columns: Vec<(String, String)>,
+
+    /// output columns that are renamed in written files, as (column, new name)
+    pub column_renames: Vec<(String, String)>,
+
     /// log decoder for table
     pub log_decoder: Option<LogDecoder>,
+
+    /// abi decoder for table
+    pub abi_decoder: Option<AbiDecoder>,
 }
 
 impl Table {
@@ -52,10 +70,160 @@ impl Table {
         self.columns.get(column).cloned()
     }
 
+    /// whether column is an address column that is encoded as checksummed hex
+    pub fn is_checksummed(&self, column: &str) -> bool {
+        self.checksum_addresses &&
+            self.column_type(column) == Some(ColumnType::Hex) &&
+            is_address_column(column)
+    }
+
+    /// set the representation of block number columns
+    pub fn set_block_number_type(&mut self, block_number_type: BlockNumberType) {
+        self.block_number_type = block_number_type;
+        for column in BLOCK_NUMBER_COLUMNS.iter() {
+            if let Some(ctype) = self.columns.get_mut(*column) {
+                *ctype = block_number_type.to_columntype();
+            }
+        }
+    }
+
+    /// set the value of a constant string column, adding the column if missing
+    pub fn set_constant_column(&mut self, name: &str, value: &str) {
+        self.columns.entry(name.to_string()).or_insert(ColumnType::String);
+        match self.constant_columns.iter_mut().find(|(column, _)| column == name) {
+            Some((_, column_value)) => *column_value = value.to_string(),
+            None => self.constant_columns.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// name of an output column in written files
+    pub fn output_name<'a>(&'a self, column: &'a str) -> &'a str {
+        match self.column_renames.iter().find(|(renamed, _)| renamed == column) {
+            Some((_, name)) => name.as_str(),
+            None => column,
+        }
+    }
+
+    /// whether column is added to the rows of Table after collection
+    fn is_added_column(&self, column: &str) -> bool {
+        self.block_time_columns.iter().any(|c| c == column) ||
+            self.constant_columns.iter().any(|(c, _)| c == column)
+    }
+
     /// get columns of Table
     pub fn columns(&self) -> Vec<&str> {
         self.columns.keys().map(|x| x.as_str()).collect()
     }
+
+    /// get columns of Table that are not static columns of its datatype
+    pub fn dynamic_columns(&self) -> Vec<&str> {
+        let static_columns = self.datatype.column_types();
+        self.columns()
+            .into_iter()
+            .filter(|c| !static_columns.contains_key(c) && !self.is_added_column(c))
+            .collect()
+    }
+
+    /// columns and dtypes of the files that are written for Table, derived from an empty
+    /// dataframe of its datatype
+    pub fn output_schema(&self) -> Result<OutputSchema, CollectError> {
+        let df = crate::add_block_time_columns(self.datatype.empty_df(self)?, self, vec![])?;
+        let df = crate::add_constant_columns(df, self)?;
+        let column_types = self.datatype.column_types();
+        let nullable_columns = self.datatype.nullable_columns();
+        let default_columns = self.datatype.default_columns();
+        let mut columns = Vec::new();
+        for series in df.get_columns().iter() {
+            let name = series.name();
+            // u256 columns are written once per u256 type, with the suffix of the type
+            let base_name = self
+                .u256_types
+                .iter()
+                .filter_map(|u256_type| name.strip_suffix(u256_type.suffix().as_str()))
+                .find(|base_name| column_types.get(base_name) == Some(&ColumnType::UInt256))
+                .unwrap_or(name);
+            let (nullable, default) = match column_types.contains_key(base_name) {
+                _ if self.is_added_column(name) => (false, false),
+                true => {
+                    (nullable_columns.contains(&base_name), default_columns.contains(&base_name))
+                }
+                // decoded columns are null when values cannot be decoded
+                false => (true, true),
+            };
+            let output_name = self.output_name(name);
+            columns.push(OutputColumn {
+                name: output_name.to_string(),
+                renamed_from: (output_name != name).then(|| name.to_string()),
+                polars_type: series.dtype().to_string(),
+                arrow_type: format!("{:?}", series.dtype().to_arrow()),
+                nullable,
+                default,
+            });
+        }
+        let added_columns = self.datatype.added_columns();
+        let optional_columns = column_types
+            .keys()
+            .chain(added_columns.iter())
+            .filter(|column| !self.has_column(column))
+            .map(|column| column.to_string())
+            .collect();
+        Ok(OutputSchema {
+            dataset: self.datatype.name(),
+            columns,
+            optional_columns,
+            sort_columns: self
+                .sort_columns
+                .as_ref()
+                .map(|columns| columns.iter().map(|c| self.output_name(c).to_string()).collect()),
+        })
+    }
+
+    /// check that the columns of the files written for Table can be represented in avro, so
+    /// that unsupported columns fail before any data is collected
+    pub fn check_avro_output(&self) -> Result<(), CollectError> {
+        let df = crate::add_block_time_columns(self.datatype.empty_df(self)?, self, vec![])?;
+        let mut df = crate::add_constant_columns(df, self)?;
+        let names: Vec<String> = df
+            .get_column_names()
+            .into_iter()
+            .map(|name| self.output_name(name).to_string())
+            .collect();
+        df.set_column_names(&names).map_err(|_| err("could not rename columns"))?;
+        match crate::types::dataframes::avro_schema(&df, "row") {
+            Ok(_) => Ok(()),
+            Err(e) => Err(err(&e.to_string())),
+        }
+    }
+}
+
+/// columns of the files written for a dataset
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct OutputSchema {
+    /// name of dataset
+    pub dataset: String,
+    /// columns written, in output order
+    pub columns: Vec<OutputColumn>,
+    /// columns of dataset that are not written, but can be added with --include-columns
+    pub optional_columns: Vec<String>,
+    /// columns that rows are sorted by
+    pub sort_columns: Option<Vec<String>>,
+}
+
+/// column of the files written for a dataset
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct OutputColumn {
+    /// name of column
+    pub name: String,
+    /// name of column before renaming
+    pub renamed_from: Option<String>,
+    /// polars dtype of column
+    pub polars_type: String,
+    /// arrow dtype of column
+    pub arrow_type: String,
+    /// whether column can contain null values
+    pub nullable: bool,
+    /// whether column is collected by default
+    pub default: bool,
 }
 
 /// representation of a U256 datum
@@ -105,6 +273,29 @@ impl U256Type {
     }
 }
 
+/// representation of block number columns, which are collected as u64
+#[derive(Hash, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BlockNumberType {
+    /// UInt32 representation, collection fails for block numbers above u32::MAX
+    #[default]
+    U32,
+    /// UInt64 representation
+    U64,
+}
+
+impl BlockNumberType {
+    /// convert BlockNumberType to ColumnType
+    pub fn to_columntype(&self) -> ColumnType {
+        match self {
+            BlockNumberType::U32 => ColumnType::UInt32,
+            BlockNumberType::U64 => ColumnType::UInt64,
+        }
+    }
+}
+
+/// columns of datasets that contain block numbers
+pub const BLOCK_NUMBER_COLUMNS: [&str; 3] = ["block_number", "first_block", "last_block"];
+
 /// datatype of column
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ColumnType {
@@ -132,6 +323,8 @@ pub enum ColumnType {
     Binary,
     /// Hex column type
     Hex,
+    /// Datetime column type
+    Datetime,
 }
 
 impl ColumnType {
@@ -147,6 +340,7 @@ impl ColumnType {
             ColumnType::Float32 => "float32",
             ColumnType::Float64 => "float64",
             ColumnType::Decimal128 => "decimal128",
+            ColumnType::Datetime => "datetime",
             ColumnType::String => "string",
             ColumnType::Binary => "binary",
             ColumnType::Hex => "hex",
@@ -162,7 +356,37 @@ pub enum SchemaError {
     InvalidColumn,
 }
 
+/// columns joined from the block header of each row, for datatypes with a block_number column
+const BLOCK_TIME_COLUMNS: [(&str, ColumnType); 2] =
+    [("timestamp", ColumnType::UInt32), ("datetime", ColumnType::Datetime)];
+
+/// column of the name of the chain, resolved from the chain registry or an override
+const CHAIN_NAME_COLUMN: &str = "chain_name";
+
 impl Datatype {
+    /// columns that can be joined from the block header of each row, if not already columns of
+    /// the datatype
+    pub fn block_time_columns(&self) -> Vec<&'static str> {
+        let column_types = self.column_types();
+        if !column_types.contains_key("block_number") {
+            return vec![]
+        }
+        BLOCK_TIME_COLUMNS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| !column_types.contains_key(name))
+            .collect()
+    }
+
+    /// columns that can be added to rows after collection, if not already columns of the datatype
+    pub fn added_columns(&self) -> Vec<&'static str> {
+        let mut columns = self.block_time_columns();
+        if !self.column_types().contains_key(CHAIN_NAME_COLUMN) {
+            columns.push(CHAIN_NAME_COLUMN);
+        }
+        columns
+    }
+
     /// get schema for a particular datatype
     #[allow(clippy::too_many_arguments)]
     pub fn table_schema(
@@ -174,12 +398,34 @@ impl Datatype {
         columns: &Option<Vec<String>>,
         sort: Option<Vec<String>>,
         log_decoder: Option<LogDecoder>,
+        abi_decoder: Option<AbiDecoder>,
     ) -> Result<Table, SchemaError> {
-        let column_types = self.column_types();
-        let all_columns = column_types.keys().map(|k| k.to_string()).collect();
-        let default_columns = self.default_columns();
+        let mut column_types: IndexMap<String, ColumnType> =
+            self.column_types().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        let mut default_columns = self.default_columns();
+        let dynamic_columns = abi_decoder.as_ref().map(|d| d.columns(self)).unwrap_or_default();
+        for (name, ctype) in dynamic_columns.iter() {
+            column_types.insert(name.clone(), *ctype);
+            default_columns.push(name.as_str());
+        }
+        let mut block_time_columns = Vec::new();
+        for (name, ctype) in BLOCK_TIME_COLUMNS.iter() {
+            if self.block_time_columns().contains(name) && !column_types.contains_key(*name) {
+                column_types.insert(name.to_string(), *ctype);
+                block_time_columns.push(name.to_string());
+            }
+        }
+        let mut opt_in_columns = block_time_columns.clone();
+        if self.added_columns().contains(&CHAIN_NAME_COLUMN) &&
+            !column_types.contains_key(CHAIN_NAME_COLUMN)
+        {
+            column_types.insert(CHAIN_NAME_COLUMN.to_string(), ColumnType::String);
+            opt_in_columns.push(CHAIN_NAME_COLUMN.to_string());
+        }
+        let all_columns = column_types.keys().cloned().collect();
         let used_columns = compute_used_columns(
             all_columns,
+            &opt_in_columns,
             default_columns,
             include_columns,
             exclude_columns,
@@ -193,6 +439,14 @@ impl Datatype {
             }
             columns.insert((*column.clone()).to_string(), *ctype);
         }
+        block_time_columns.retain(|column| columns.contains_key(column));
+        // the chain name is only known once the source is, see set_constant_column
+        let constant_columns = match opt_in_columns.iter().any(|c| c == CHAIN_NAME_COLUMN) &&
+            columns.contains_key(CHAIN_NAME_COLUMN)
+        {
+            true => vec![(CHAIN_NAME_COLUMN.to_string(), String::new())],
+            false => vec![],
+        };
 
         let schema = Table {
             datatype: *self,
@@ -200,29 +454,63 @@ impl Datatype {
             columns,
             u256_types: u256_types.to_owned(),
             binary_type: binary_column_format.clone(),
+            checksum_addresses: false,
+            block_number_type: BlockNumberType::default(),
+            block_time_columns,
+            constant_columns,
+            column_renames: vec![],
             log_decoder,
+            abi_decoder,
         };
         Ok(schema)
     }
 }
 
+/// columns of datasets that contain addresses, besides columns ending in `address`
+const ADDRESS_COLUMNS: [&str; 14] = [
+    "action_from",
+    "action_to",
+    "author",
+    "deployer",
+    "erc1155",
+    "erc20",
+    "erc721",
+    "factory",
+    "fee_recipient",
+    "operator",
+    "owner",
+    "sender",
+    "spender",
+    "uncle_miner",
+];
+
+/// whether a column of a dataset contains addresses
+pub(crate) fn is_address_column(column: &str) -> bool {
+    column.ends_with("address") || ADDRESS_COLUMNS.contains(&column)
+}
+
 fn compute_used_columns(
     all_columns: IndexSet<String>,
+    opt_in_columns: &[String],
     default_columns: Vec<&str>,
     include_columns: &Option<Vec<String>>,
     exclude_columns: &Option<Vec<String>>,
     columns: &Option<Vec<String>>,
 ) -> IndexSet<String> {
+    // columns that need extra requests are only used when named explicitly
+    let without_opt_in = |all_columns: IndexSet<String>| {
+        all_columns.into_iter().filter(|column| !opt_in_columns.contains(column)).collect()
+    };
     if let Some(columns) = columns {
         if (columns.len() == 1) & columns.contains(&"all".to_string()) {
-            return all_columns
+            return without_opt_in(all_columns)
         }
         return columns.iter().map(|x| x.to_string()).collect()
     }
     let mut result_set = IndexSet::from_iter(default_columns.iter().map(|s| s.to_string()));
     if let Some(include) = include_columns {
         if (include.len() == 1) & include.contains(&"all".to_string()) {
-            return all_columns
+            return without_opt_in(all_columns)
         }
         // Permissively skip `include` columns that are not in this dataset (they might apply to
         // other dataset)
@@ -248,16 +536,34 @@ mod tests {
     fn test_table_schema_explicit_cols() {
         let cols = Some(vec!["block_number".to_string(), "block_hash".to_string()]);
         let table = Datatype::Blocks
-            .table_schema(&get_u256_types(), &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .table_schema(
+                &get_u256_types(),
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &cols,
+                None,
+                None,
+                None,
+            )
             .unwrap();
         assert_eq!(vec!["block_number", "block_hash"], table.columns());
 
         // "all" marker support
         let cols = Some(vec!["all".to_string()]);
         let table = Datatype::Blocks
-            .table_schema(&get_u256_types(), &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .table_schema(
+                &get_u256_types(),
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &cols,
+                None,
+                None,
+                None,
+            )
             .unwrap();
-        assert_eq!(15, table.columns().len());
+        assert_eq!(20, table.columns().len());
         assert!(table.columns().contains(&"block_hash"));
         assert!(table.columns().contains(&"transactions_root"));
     }
@@ -274,6 +580,7 @@ mod tests {
                 &None,
                 None,
                 None,
+                None,
             )
             .unwrap();
         assert_eq!(9, table.columns().len());
@@ -290,6 +597,7 @@ mod tests {
                 &None,
                 None,
                 None,
+                None,
             )
             .unwrap();
         assert_eq!(Some(&"chain_id"), table.columns().last());
@@ -306,9 +614,10 @@ mod tests {
                 &None,
                 None,
                 None,
+                None,
             )
             .unwrap();
-        assert_eq!(15, table.columns().len());
+        assert_eq!(20, table.columns().len());
         assert!(table.columns().contains(&"block_hash"));
         assert!(table.columns().contains(&"transactions_root"));
     }
@@ -317,7 +626,16 @@ mod tests {
     fn test_table_schema_exclude_cols() {
         // defaults
         let table = Datatype::Blocks
-            .table_schema(&get_u256_types(), &ColumnEncoding::Hex, &None, &None, &None, None, None)
+            .table_schema(
+                &get_u256_types(),
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &None,
+                None,
+                None,
+                None,
+            )
             .unwrap();
         assert_eq!(8, table.columns().len());
         assert!(table.columns().contains(&"author"));
@@ -333,6 +651,7 @@ mod tests {
                 &None,
                 None,
                 None,
+                None,
             )
             .unwrap();
         assert_eq!(6, table.columns().len());
@@ -350,6 +669,7 @@ mod tests {
                 &None,
                 None,
                 None,
+                None,
             )
             .unwrap();
         assert_eq!(7, table.columns().len());
@@ -370,6 +690,7 @@ mod tests {
                 &None,
                 None,
                 None,
+                None,
             )
             .unwrap();
         assert!(!table.columns().contains(&"author"));
@@ -377,4 +698,88 @@ mod tests {
         assert_eq!(7, table.columns().len());
         assert_eq!(["chain_id", "receipts_root"], table.columns()[5..7]);
     }
+
+    #[test]
+    fn test_output_schema() {
+        let inc_cols = Some(vec!["total_difficulty".to_string()]);
+        let table = Datatype::Blocks
+            .table_schema(
+                &get_u256_types(),
+                &ColumnEncoding::Binary,
+                &inc_cols,
+                &None,
+                &None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let schema = table.output_schema().unwrap();
+        let column = |name: &str| schema.columns.iter().find(|c| c.name == name).unwrap().clone();
+        assert_eq!(schema.columns.len(), 11);
+        assert_eq!(column("block_hash").polars_type, "binary");
+        assert!(column("block_hash").nullable && column("block_hash").default);
+        assert_eq!(column("timestamp").polars_type, "u32");
+        assert!(!column("timestamp").nullable);
+        assert_eq!(column("total_difficulty_f64").polars_type, "f64");
+        assert!(column("total_difficulty_f64").nullable && !column("total_difficulty_f64").default);
+        assert!(schema.optional_columns.contains(&"receipts_root".to_string()));
+
+        // hex encoded binary columns are written as strings
+        let table = Datatype::Blocks
+            .table_schema(&[], &ColumnEncoding::Hex, &None, &None, &None, None, None, None)
+            .unwrap();
+        let schema = table.output_schema().unwrap();
+        assert_eq!(
+            schema.columns.iter().find(|c| c.name == "block_hash").unwrap().polars_type,
+            "str"
+        );
+    }
+
+    #[test]
+    fn test_check_avro_output() {
+        let inc_cols = Some(vec!["total_difficulty".to_string()]);
+        let table = |u256_types: &[U256Type]| {
+            Datatype::Blocks
+                .table_schema(
+                    u256_types,
+                    &ColumnEncoding::Binary,
+                    &inc_cols,
+                    &None,
+                    &None,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap()
+        };
+        assert!(table(&get_u256_types()).check_avro_output().is_ok());
+        assert!(table(&[U256Type::Decimal128]).check_avro_output().is_err());
+    }
+
+    #[test]
+    fn test_block_number_type() {
+        use crate::{FourByteCounts, ToDataFrames};
+        use polars::prelude::DataType;
+
+        let columns = Some(vec!["block_number".to_string()]);
+        let mut table = Datatype::FourByteCounts
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &columns, None, None, None)
+            .unwrap();
+        assert_eq!(table.column_type("block_number"), Some(ColumnType::UInt32));
+        let block_above_u32 = || FourByteCounts {
+            n_rows: 1,
+            block_number: vec![Some(u32::MAX as u64 + 1)],
+            ..Default::default()
+        };
+        let schemas = |table: &Table| HashMap::from([(Datatype::FourByteCounts, table.clone())]);
+        assert!(block_above_u32().create_dfs(&schemas(&table), 1).is_err());
+
+        table.set_block_number_type(BlockNumberType::U64);
+        assert_eq!(table.column_type("block_number"), Some(ColumnType::UInt64));
+        let dfs = block_above_u32().create_dfs(&schemas(&table), 1).unwrap();
+        let block_number = dfs[&Datatype::FourByteCounts].column("block_number").unwrap().clone();
+        assert_eq!(block_number.dtype(), &DataType::UInt64);
+        assert_eq!(block_number.u64().unwrap().get(0), Some(u32::MAX as u64 + 1));
+    }
 }
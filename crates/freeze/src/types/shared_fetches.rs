@@ -0,0 +1,251 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use ethers::prelude::*;
+use tokio::sync::OnceCell;
+
+use crate::{CollectError, Datatype, MetaDatatype};
+
+/// datatypes that fetch blocks with full transactions
+const FULL_BLOCK_DATATYPES: [Datatype; 6] = [
+    Datatype::Transactions,
+    Datatype::AccessLists,
+    Datatype::BlockRewards,
+    Datatype::Blobs,
+    Datatype::DecodedCalls,
+    Datatype::TransactionFees,
+];
+
+/// responses of block requests shared by the datasets of a run, so that each block, its receipts,
+/// and its traces are fetched once per run instead of once per dataset
+#[derive(Debug, Default)]
+pub struct SharedFetches {
+    enabled: AtomicBool,
+    full_blocks: AtomicBool,
+    blocks: SharedResponses<Option<Block<TxHash>>>,
+    blocks_with_txs: SharedResponses<Option<Block<Transaction>>>,
+    receipts: SharedResponses<Vec<TransactionReceipt>>,
+    traces: SharedResponses<Vec<Trace>>,
+    hits: Mutex<BTreeMap<String, u64>>,
+}
+
+#[derive(Debug)]
+struct SharedResponses<T>(Mutex<HashMap<u64, Arc<OnceCell<T>>>>);
+
+impl<T> Default for SharedResponses<T> {
+    fn default() -> Self {
+        SharedResponses(Mutex::new(HashMap::new()))
+    }
+}
+
+impl<T> SharedResponses<T> {
+    fn cell(&self, block_number: u64) -> Option<Arc<OnceCell<T>>> {
+        self.0.lock().ok().map(|mut cells| cells.entry(block_number).or_default().clone())
+    }
+
+    fn release(&self, block_numbers: &RangeInclusive<u64>) {
+        if let Ok(mut cells) = self.0.lock() {
+            cells.retain(|block_number, _| !block_numbers.contains(block_number));
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().map(|cells| cells.len()).unwrap_or_default()
+    }
+}
+
+impl SharedFetches {
+    /// share responses between the datasets of a run if it collects several datasets, serving
+    /// blocks without transactions from blocks with transactions if any dataset needs them
+    pub fn enable(&self, datatypes: &[MetaDatatype]) {
+        let full_blocks = datatypes
+            .iter()
+            .flat_map(|datatype| datatype.datatypes())
+            .any(|datatype| FULL_BLOCK_DATATYPES.contains(&datatype));
+        self.enabled.store(datatypes.len() > 1, Ordering::SeqCst);
+        self.full_blocks.store(full_blocks, Ordering::SeqCst);
+    }
+
+    /// whether blocks without transactions are served from blocks with transactions
+    pub(crate) fn shares_full_blocks(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst) && self.full_blocks.load(Ordering::SeqCst)
+    }
+
+    /// drop the responses of blocks whose datasets have all been collected
+    pub fn release(&self, block_numbers: RangeInclusive<u64>) {
+        self.blocks.release(&block_numbers);
+        self.blocks_with_txs.release(&block_numbers);
+        self.receipts.release(&block_numbers);
+        self.traces.release(&block_numbers);
+    }
+
+    /// drop all responses
+    pub fn clear(&self) {
+        self.release(0..=u64::MAX)
+    }
+
+    /// number of blocks with responses held for datasets that have not been collected yet
+    pub fn n_held(&self) -> usize {
+        self.blocks.len() + self.blocks_with_txs.len() + self.receipts.len() + self.traces.len()
+    }
+
+    /// number of requests of each method served by a response fetched for another dataset
+    pub fn hits(&self) -> BTreeMap<String, u64> {
+        self.hits.lock().map(|hits| hits.clone()).unwrap_or_default()
+    }
+
+    pub(crate) async fn block<F>(&self, n: u64, fetch: F) -> Result<Option<Block<TxHash>>>
+    where
+        F: Future<Output = Result<Option<Block<TxHash>>>>,
+    {
+        self.share(&self.blocks, "get_block", n, fetch).await
+    }
+
+    pub(crate) async fn block_with_txs<F>(
+        &self,
+        n: u64,
+        fetch: F,
+    ) -> Result<Option<Block<Transaction>>>
+    where
+        F: Future<Output = Result<Option<Block<Transaction>>>>,
+    {
+        self.share(&self.blocks_with_txs, "get_block_with_txs", n, fetch).await
+    }
+
+    pub(crate) async fn receipts<F>(&self, n: u64, fetch: F) -> Result<Vec<TransactionReceipt>>
+    where
+        F: Future<Output = Result<Vec<TransactionReceipt>>>,
+    {
+        self.share(&self.receipts, "get_block_receipts", n, fetch).await
+    }
+
+    pub(crate) async fn traces<F>(&self, n: u64, fetch: F) -> Result<Vec<Trace>>
+    where
+        F: Future<Output = Result<Vec<Trace>>>,
+    {
+        self.share(&self.traces, "trace_block", n, fetch).await
+    }
+
+    /// fetch a response, or wait for the response of a request already made by another dataset.
+    /// errors are not shared, the next dataset makes the request again
+    async fn share<T, F>(
+        &self,
+        responses: &SharedResponses<T>,
+        method: &str,
+        block_number: u64,
+        fetch: F,
+    ) -> Result<T>
+    where
+        T: Clone,
+        F: Future<Output = Result<T>>,
+    {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return fetch.await
+        }
+        let cell = match responses.cell(block_number) {
+            Some(cell) => cell,
+            None => return fetch.await,
+        };
+        let mut fetched = false;
+        let response = cell
+            .get_or_try_init(|| {
+                fetched = true;
+                fetch
+            })
+            .await?
+            .clone();
+        if !fetched {
+            if let Ok(mut hits) = self.hits.lock() {
+                *hits.entry(method.to_string()).or_insert(0) += 1;
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// drop the transactions of a block, keeping their hashes
+pub(crate) fn without_transactions(block: Block<Transaction>) -> Block<TxHash> {
+    Block {
+        hash: block.hash,
+        parent_hash: block.parent_hash,
+        uncles_hash: block.uncles_hash,
+        author: block.author,
+        state_root: block.state_root,
+        transactions_root: block.transactions_root,
+        receipts_root: block.receipts_root,
+        number: block.number,
+        gas_used: block.gas_used,
+        gas_limit: block.gas_limit,
+        extra_data: block.extra_data,
+        logs_bloom: block.logs_bloom,
+        timestamp: block.timestamp,
+        difficulty: block.difficulty,
+        total_difficulty: block.total_difficulty,
+        seal_fields: block.seal_fields,
+        uncles: block.uncles,
+        transactions: block.transactions.iter().map(|tx| tx.hash).collect(),
+        size: block.size,
+        mix_hash: block.mix_hash,
+        nonce: block.nonce,
+        base_fee_per_gas: block.base_fee_per_gas,
+        withdrawals_root: block.withdrawals_root,
+        withdrawals: block.withdrawals,
+        other: block.other,
+    }
+}
+
+type Result<T> = ::core::result::Result<T, CollectError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::err;
+
+    #[tokio::test]
+    async fn test_shared_fetches() {
+        let shared = SharedFetches::default();
+        let fetch = |n: u64| async move {
+            Ok(vec![TransactionReceipt { block_number: Some(n.into()), ..Default::default() }])
+        };
+
+        // disabled for runs of a single dataset
+        shared.enable(&[MetaDatatype::Scalar(Datatype::Traces)]);
+        shared.receipts(1, fetch(1)).await.unwrap();
+        assert_eq!(shared.n_held(), 0);
+
+        let datatypes = [Datatype::Traces, Datatype::NativeTransfers];
+        shared.enable(&datatypes.map(MetaDatatype::Scalar));
+        assert!(!shared.shares_full_blocks());
+        for n in [1, 1, 2, 1] {
+            let receipts = shared.receipts(n, fetch(n)).await.unwrap();
+            assert_eq!(receipts[0].block_number, Some(n.into()));
+        }
+        assert!(shared.receipts(3, async { Err(err("failed")) }).await.is_err());
+        assert_eq!(shared.receipts(3, fetch(3)).await.unwrap()[0].block_number, Some(3.into()));
+        assert_eq!(shared.hits(), [("get_block_receipts".to_string(), 2)].into_iter().collect());
+
+        shared.release(1..=2);
+        assert_eq!(shared.n_held(), 1);
+        shared.clear();
+        assert_eq!(shared.n_held(), 0);
+    }
+
+    #[test]
+    fn test_without_transactions() {
+        let tx = Transaction { hash: H256::repeat_byte(1), ..Default::default() };
+        let mut block =
+            Block { number: Some(5.into()), transactions: vec![tx], ..Default::default() };
+        block.other.insert("blobGasUsed".to_string(), serde_json::json!("0x20000"));
+        let block = without_transactions(block);
+        assert_eq!(block.number, Some(5.into()));
+        assert_eq!(block.other.get("blobGasUsed"), Some(&serde_json::json!("0x20000")));
+        assert_eq!(block.transactions, vec![H256::repeat_byte(1)]);
+    }
+}
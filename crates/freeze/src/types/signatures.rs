@@ -17,12 +17,39 @@ lazy_static::lazy_static! {
     /// function signature of FUNCTION_ERC20_TOTAL_SUPPLY
     pub static ref FUNCTION_ERC20_TOTAL_SUPPLY: Vec<u8> = prefix_hex::decode("0x18160ddd").expect("Decoding failed");
 
+    /// function signature of FUNCTION_ERC4626_ASSET
+    pub static ref FUNCTION_ERC4626_ASSET: Vec<u8> = prefix_hex::decode("0x38d52e0f").expect("Decoding failed");
+
+    /// function signature of FUNCTION_ERC4626_TOTAL_ASSETS
+    pub static ref FUNCTION_ERC4626_TOTAL_ASSETS: Vec<u8> = prefix_hex::decode("0x01e1d114").expect("Decoding failed");
+
+    /// function signature of FUNCTION_ERC4626_CONVERT_TO_ASSETS
+    pub static ref FUNCTION_ERC4626_CONVERT_TO_ASSETS: Vec<u8> = prefix_hex::decode("0x07a2d13a").expect("Decoding failed");
+
     /// event hash of EVENT_ERC20_TRANSFER
     pub static ref EVENT_ERC20_TRANSFER: H256 = H256(
         prefix_hex::decode("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
             .expect("Decoding failed"),
     );
 
+    /// event hash of EVENT_ERC20_APPROVAL
+    pub static ref EVENT_ERC20_APPROVAL: H256 = H256(
+        prefix_hex::decode("0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925")
+            .expect("Decoding failed"),
+    );
+
+    /// event hash of EVENT_ERC1155_TRANSFER_SINGLE
+    pub static ref EVENT_ERC1155_TRANSFER_SINGLE: H256 = H256(
+        prefix_hex::decode("0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62")
+            .expect("Decoding failed"),
+    );
+
+    /// event hash of EVENT_ERC1155_TRANSFER_BATCH
+    pub static ref EVENT_ERC1155_TRANSFER_BATCH: H256 = H256(
+        prefix_hex::decode("0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb")
+            .expect("Decoding failed"),
+    );
+
     /// event hash of EVENT_ERC721_TRANSFER
     pub static ref EVENT_ERC721_TRANSFER: H256 = H256(
         prefix_hex::decode("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
@@ -1,4 +1,11 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use ethers::prelude::*;
 use governor::{
@@ -11,7 +18,12 @@ use tokio::{
     task,
 };
 
-use crate::CollectError;
+use crate::{
+    normalize_rpc_url,
+    retries::{method_error, provider_error},
+    shared_fetches, trace_backends, AutoscaleState, CollectError, Datatype, EndpointOptions,
+    EndpointPool, EndpointStats, EventHandler, SharedFetches, Table, TraceBackend,
+};
 
 /// RateLimiter based on governor crate
 pub type RateLimiter = governor::RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>;
@@ -20,7 +32,7 @@ pub type RateLimiter = governor::RateLimiter<NotKeyed, InMemoryState, DefaultClo
 #[derive(Clone, Debug)]
 pub struct Source {
     /// Shared provider for rpc data
-    pub fetcher: Arc<Fetcher<RetryClient<Http>>>,
+    pub fetcher: Arc<Fetcher<EndpointPool>>,
     /// chain_id of network
     pub chain_id: u64,
     /// number of blocks per log request
@@ -29,26 +41,416 @@ pub struct Source {
     pub max_concurrent_chunks: Option<u64>,
     /// Rpc Url
     pub rpc_url: String,
+    /// Beacon node url, used for blob sidecars
+    pub beacon_url: Option<String>,
+    /// whether Multicall3 is deployed on the chain, checked on first use
+    pub multicall_support: tokio::sync::OnceCell<bool>,
+    /// genesis time and seconds per slot of the beacon chain, fetched on first use
+    pub beacon_genesis: tokio::sync::OnceCell<(u64, u64)>,
+    /// whether the node supports eth_getBlockReceipts and otterscan methods, probed on first use
+    pub capabilities: Capabilities,
+    /// rpc namespace of traces and state diffs
+    pub trace_backend: TraceBackend,
     /// Labels (these are non-functional)
     pub labels: SourceLabels,
 }
 
+/// whether a node supports a method, unknown until the method is first used
+#[derive(Clone, Debug, Default)]
+pub struct Capability {
+    supported: Arc<Mutex<Option<bool>>>,
+    /// held while probing so that concurrent requests do not probe the method again
+    probing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Capability {
+    /// whether method is supported, if known
+    pub fn get(&self) -> Option<bool> {
+        self.supported.lock().ok().and_then(|supported| *supported)
+    }
+
+    fn set(&self, supported: bool) {
+        if let Ok(mut current) = self.supported.lock() {
+            *current = Some(supported);
+        }
+    }
+
+    /// whether method is supported, waiting for a probe in progress, or None if the caller should
+    /// probe it while holding the returned guard
+    async fn get_or_probe(&self) -> (Option<bool>, Option<tokio::sync::MutexGuard<'_, ()>>) {
+        if let Some(supported) = self.get() {
+            return (Some(supported), None)
+        }
+        let guard = self.probing.lock().await;
+        match self.get() {
+            Some(supported) => (Some(supported), None),
+            None => (None, Some(guard)),
+        }
+    }
+}
+
+/// optional methods of a node, which replace several requests with one
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    /// `eth_getBlockReceipts`, replacing a receipt request per transaction
+    pub block_receipts: Capability,
+    /// otterscan `ots_` methods of erigon, such as `ots_getBlockTransactions`
+    pub otterscan: Capability,
+    /// parity `trace_` methods, used instead of geth `debug_` methods by the auto trace backend
+    pub parity_traces: Capability,
+}
+
+impl Capabilities {
+    /// support of each probed method
+    pub fn probed(&self) -> BTreeMap<String, bool> {
+        [
+            ("eth_getBlockReceipts", &self.block_receipts),
+            ("ots", &self.otterscan),
+            ("trace", &self.parity_traces),
+        ]
+        .into_iter()
+        .filter_map(|(name, capability)| capability.get().map(|x| (name.to_string(), x)))
+        .collect()
+    }
+}
+
 impl Source {
     /// Returns all receipts for a block.
-    /// Tries to use `eth_getBlockReceipts` first, and falls back to `eth_getTransactionReceipt`
+    /// Tries to use `eth_getBlockReceipts` first, then otterscan's `ots_getBlockTransactions`,
+    /// and falls back to `eth_getTransactionReceipt`. Receipts fetched from otterscan have no
+    /// logs or logs bloom.
     pub async fn get_tx_receipts_in_block(
         &self,
         block: &Block<Transaction>,
     ) -> Result<Vec<TransactionReceipt>> {
         let block_number =
             block.number.ok_or(CollectError::CollectError("no block number".to_string()))?.as_u64();
-        if let Ok(receipts) = self.fetcher.get_block_receipts(block_number).await {
-            return Ok(receipts);
+        if let Some(receipts) = self.get_block_receipts(block_number).await? {
+            return Ok(receipts)
+        }
+        if let Some(receipts) = self.get_otterscan_receipts(block).await? {
+            return Ok(receipts)
         }
 
         self.get_tx_receipts(&block.transactions).await
     }
 
+    /// Returns all receipts for a block using `eth_getBlockReceipts`, or None if the node does
+    /// not support it
+    pub async fn get_block_receipts(
+        &self,
+        block_number: u64,
+    ) -> Result<Option<Vec<TransactionReceipt>>> {
+        let support = &self.capabilities.block_receipts;
+        let (supported, _probing) = support.get_or_probe().await;
+        if supported == Some(false) {
+            return Ok(None)
+        }
+        match self.fetcher.get_block_receipts(block_number).await {
+            Ok(receipts) => {
+                support.set(true);
+                Ok(Some(receipts))
+            }
+            // other errors, such as rate limits, say nothing about whether the node supports it
+            Err(CollectError::MethodNotFound(_)) if supported.is_none() => {
+                support.set(false);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns receipts of the transactions of a block using `ots_getBlockTransactions`, or None
+    /// if the node does not support otterscan methods
+    async fn get_otterscan_receipts(
+        &self,
+        block: &Block<Transaction>,
+    ) -> Result<Option<Vec<TransactionReceipt>>> {
+        let support = &self.capabilities.otterscan;
+        let (supported, _probing) = support.get_or_probe().await;
+        let supported = match supported {
+            Some(supported) => supported,
+            None => {
+                let supported = match self.fetcher.ots_get_api_level().await {
+                    Ok(_) => true,
+                    Err(CollectError::MethodNotFound(_)) => false,
+                    Err(e) => return Err(e),
+                };
+                support.set(supported);
+                supported
+            }
+        };
+        let block_number = match (supported, block.number) {
+            (true, Some(block_number)) => block_number.as_u64(),
+            _ => return Ok(None),
+        };
+        let mut receipts: HashMap<H256, TransactionReceipt> = self
+            .fetcher
+            .ots_get_block_receipts(block_number, block.transactions.len() as u64)
+            .await?
+            .into_iter()
+            .map(|receipt| (receipt.transaction_hash, receipt))
+            .collect();
+        block
+            .transactions
+            .iter()
+            .map(|tx| {
+                receipts
+                    .remove(&tx.hash)
+                    .ok_or(CollectError::CollectError("could not find tx receipt".to_string()))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// Returns parity style traces of a block, converted from geth call traces if the trace
+    /// backend is geth. Geth traces have no block reward entries.
+    pub async fn trace_block(&self, block_number: u64) -> Result<Vec<Trace>> {
+        let parity = self.fetcher.trace_block(block_number.into());
+        let geth = async {
+            let (_, _, _, frames) =
+                self.fetcher.geth_debug_trace_block_calls(block_number, false).await?;
+            let block =
+                self.fetcher.get_block(block_number).await?.ok_or(err("block not found"))?;
+            let block_hash = block.hash.ok_or(err("block has no hash"))?;
+            trace_backends::call_frames_to_traces(
+                frames,
+                &block.transactions,
+                None,
+                block_number,
+                block_hash,
+            )
+        };
+        // converted traces are shared with other datasets like the traces of trace_block
+        let geth = self.fetcher.shared_fetches.traces(block_number, geth);
+        self.with_trace_backend(parity, geth).await
+    }
+
+    /// Returns parity style traces of a transaction, converted from geth call traces if the
+    /// trace backend is geth
+    pub async fn trace_transaction(&self, transaction_hash: H256) -> Result<Vec<Trace>> {
+        let parity = self.fetcher.trace_transaction(transaction_hash);
+        let geth = async {
+            let hash = transaction_hash.as_bytes().to_vec();
+            let (_, _, _, frames) =
+                self.fetcher.geth_debug_trace_transaction_calls(hash, false).await?;
+            let transaction = self
+                .fetcher
+                .get_transaction(transaction_hash)
+                .await?
+                .ok_or(err("transaction not found"))?;
+            let block_number = transaction.block_number.ok_or(err("transaction is pending"))?;
+            trace_backends::call_frames_to_traces(
+                frames,
+                &[transaction_hash],
+                transaction.transaction_index.map(|index| index.as_usize()),
+                block_number.as_u64(),
+                transaction.block_hash.unwrap_or_default(),
+            )
+        };
+        self.with_trace_backend(parity, geth).await
+    }
+
+    /// Returns parity style traces of a block along with the creation method of each create
+    /// trace, which geth reports as the type of the call frame
+    pub async fn trace_block_with_creation_methods(
+        &self,
+        block_number: u64,
+    ) -> Result<(Vec<Trace>, CreationMethods)> {
+        let parity = self.fetcher.trace_block_with_creation_methods(block_number.into());
+        let geth = async {
+            let (_, _, _, frames) =
+                self.fetcher.geth_debug_trace_block_calls(block_number, false).await?;
+            let creation_methods = trace_backends::call_frames_creation_methods(&frames, None);
+            let block =
+                self.fetcher.get_block(block_number).await?.ok_or(err("block not found"))?;
+            let block_hash = block.hash.ok_or(err("block has no hash"))?;
+            let traces = trace_backends::call_frames_to_traces(
+                frames,
+                &block.transactions,
+                None,
+                block_number,
+                block_hash,
+            )?;
+            Ok((traces, creation_methods))
+        };
+        self.with_trace_backend(parity, geth).await
+    }
+
+    /// Returns parity style traces of a transaction along with the creation method of each
+    /// create trace, which geth reports as the type of the call frame
+    pub async fn trace_transaction_with_creation_methods(
+        &self,
+        transaction_hash: H256,
+    ) -> Result<(Vec<Trace>, CreationMethods)> {
+        let parity = self.fetcher.trace_transaction_with_creation_methods(transaction_hash);
+        let geth = async {
+            let hash = transaction_hash.as_bytes().to_vec();
+            let (_, _, _, frames) =
+                self.fetcher.geth_debug_trace_transaction_calls(hash, false).await?;
+            let transaction = self
+                .fetcher
+                .get_transaction(transaction_hash)
+                .await?
+                .ok_or(err("transaction not found"))?;
+            let block_number = transaction.block_number.ok_or(err("transaction is pending"))?;
+            let transaction_index = transaction.transaction_index.map(|index| index.as_usize());
+            let creation_methods =
+                trace_backends::call_frames_creation_methods(&frames, transaction_index);
+            let traces = trace_backends::call_frames_to_traces(
+                frames,
+                &[transaction_hash],
+                transaction_index,
+                block_number.as_u64(),
+                transaction.block_hash.unwrap_or_default(),
+            )?;
+            Ok((traces, creation_methods))
+        };
+        self.with_trace_backend(parity, geth).await
+    }
+
+    /// Returns parity style state diffs of each transaction of a block, converted from geth
+    /// prestate diffs if the trace backend is geth
+    pub async fn trace_block_state_diffs(
+        &self,
+        block_number: u64,
+        include_transaction_hashes: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
+        let parity = self.fetcher.trace_block_state_diffs(block_number, include_transaction_hashes);
+        let geth = async {
+            let (block, indices, txs, diffs) = self
+                .fetcher
+                .geth_debug_trace_block_diffs(block_number, include_transaction_hashes)
+                .await?;
+            let diffs = diffs.into_iter().map(trace_backends::diff_mode_to_block_trace);
+            Ok((block, indices, txs, diffs.collect::<Result<_>>()?))
+        };
+        self.with_trace_backend(parity, geth).await
+    }
+
+    /// Returns parity style state diffs of a transaction, converted from geth prestate diffs if
+    /// the trace backend is geth
+    pub async fn trace_transaction_state_diffs(
+        &self,
+        transaction_hash: Vec<u8>,
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
+        let parity = self
+            .fetcher
+            .trace_transaction_state_diffs(transaction_hash.clone(), include_transaction);
+        let geth = async {
+            let (block, indices, txs, diffs) = self
+                .fetcher
+                .geth_debug_trace_transaction_diffs(transaction_hash, include_transaction)
+                .await?;
+            let diffs = diffs.into_iter().map(trace_backends::diff_mode_to_block_trace);
+            Ok((block, indices, txs, diffs.collect::<Result<_>>()?))
+        };
+        self.with_trace_backend(parity, geth).await
+    }
+
+    /// Returns the trace backend that traces are collected with, None if not yet probed
+    pub fn resolved_trace_backend(&self) -> Option<TraceBackend> {
+        match self.trace_backend {
+            TraceBackend::Auto => match self.capabilities.parity_traces.get() {
+                Some(true) => Some(TraceBackend::Parity),
+                Some(false) => Some(TraceBackend::Geth),
+                None => None,
+            },
+            backend => Some(backend),
+        }
+    }
+
+    /// run the request of the trace backend, the auto backend probes parity methods on the
+    /// first request and uses geth methods if the node does not have them
+    async fn with_trace_backend<T>(
+        &self,
+        parity: impl std::future::Future<Output = Result<T>>,
+        geth: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match self.trace_backend {
+            TraceBackend::Parity => return parity.await,
+            TraceBackend::Geth => return geth.await,
+            TraceBackend::Auto => {}
+        }
+        let support = &self.capabilities.parity_traces;
+        let (supported, _probing) = support.get_or_probe().await;
+        match supported {
+            Some(true) => parity.await,
+            Some(false) => geth.await,
+            None => match parity.await {
+                Ok(result) => {
+                    support.set(true);
+                    Ok(result)
+                }
+                Err(CollectError::MethodNotFound(_)) => {
+                    support.set(false);
+                    geth.await
+                }
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /// Returns number of requests and errors of each rpc endpoint
+    pub fn endpoint_stats(&self) -> Vec<EndpointStats> {
+        self.fetcher.provider.as_ref().stats()
+    }
+
+    /// Send rpc retry events to handler, replacing the previous handler
+    pub fn subscribe(&self, handler: Option<Arc<dyn EventHandler>>) {
+        self.fetcher.provider.as_ref().subscribe(handler)
+    }
+
+    /// Returns number of retries of each rpc method
+    pub fn retry_counts(&self) -> BTreeMap<String, u64> {
+        self.fetcher.provider.as_ref().retry_counts()
+    }
+
+    /// Returns number of failed requests of each error class
+    pub fn error_counts(&self) -> BTreeMap<String, u64> {
+        self.fetcher.provider.as_ref().error_counts()
+    }
+
+    /// Returns number of requests answered from the response cache and not, if enabled
+    pub fn cache_hits_and_misses(&self) -> Option<(u64, u64)> {
+        self.fetcher.provider.as_ref().cache().map(|cache| cache.hits_and_misses())
+    }
+
+    /// Returns number of requests of each method, and the optional methods that were probed
+    pub fn method_counts(&self) -> (BTreeMap<String, u64>, BTreeMap<String, bool>) {
+        (self.fetcher.request_counts.counts(), self.capabilities.probed())
+    }
+
+    /// Returns current settings of adaptive concurrency, if enabled
+    pub fn autoscale_state(&self) -> Option<AutoscaleState> {
+        self.fetcher.provider.as_ref().autoscale_state()
+    }
+
+    /// Returns timestamps of blocks, only fetching headers that have not been fetched in the run
+    pub async fn get_block_timestamps(&self, block_numbers: &[u64]) -> Result<HashMap<u64, u32>> {
+        let mut timestamps = self.fetcher.block_timestamps.get(block_numbers);
+        let missing: Vec<u64> =
+            block_numbers.iter().filter(|n| !timestamps.contains_key(n)).copied().collect();
+        let blocks =
+            futures::future::try_join_all(missing.iter().map(|n| self.fetcher.get_block(*n)))
+                .await?;
+        for (number, block) in missing.into_iter().zip(blocks) {
+            match block {
+                Some(block) => {
+                    let timestamp = u32::try_from(block.timestamp).map_err(|_| {
+                        CollectError::CollectError(format!("invalid timestamp of block {}", number))
+                    })?;
+                    timestamps.insert(number, timestamp)
+                }
+                None => {
+                    return Err(CollectError::CollectError(format!("block {} not found", number)))
+                }
+            };
+        }
+        Ok(timestamps)
+    }
+
     /// Returns all receipts for vector of transactions using `eth_getTransactionReceipt`
     pub async fn get_tx_receipts(
         &self,
@@ -78,6 +480,277 @@ impl Source {
 
         Ok(receipts)
     }
+
+    /// Returns blob sidecars of a block, read from the beacon node if one is configured and
+    /// otherwise from `eth_getBlobSidecars`. Blob bodies are only kept if `include_blobs` is set,
+    /// and the beacon node is only asked for them in that case.
+    pub async fn get_blob_sidecars(
+        &self,
+        block_number: u64,
+        timestamp: u64,
+        include_blobs: bool,
+    ) -> Result<Vec<BlobSidecar>> {
+        let beacon_url = match &self.beacon_url {
+            Some(beacon_url) => beacon_url.trim_end_matches('/'),
+            None => {
+                let mut sidecars = self.fetcher.get_blob_sidecars(block_number).await?;
+                if !include_blobs {
+                    sidecars.iter_mut().for_each(|sidecar| sidecar.blob = None);
+                }
+                return Ok(sidecars)
+            }
+        };
+
+        // blocks are requested by the slot of their timestamp, so that blocks at the tip can be
+        // collected before their child block exists
+        let (genesis_time, seconds_per_slot) = self.beacon_genesis(beacon_url).await?;
+        let slot = beacon_slot(timestamp, genesis_time, seconds_per_slot).ok_or_else(|| {
+            CollectError::CollectError(format!("block {} is not in a beacon slot", block_number))
+        })?;
+
+        if include_blobs {
+            let url = format!("{}/eth/v1/beacon/blob_sidecars/{}", beacon_url, slot);
+            let response: serde_json::Value = self.beacon_request(url).await?;
+            serde_json::from_value(response["data"].clone())
+                .map_err(|_| CollectError::CollectError("invalid blob sidecars".to_string()))
+        } else {
+            let url = format!("{}/eth/v2/beacon/blocks/{}", beacon_url, slot);
+            let response: serde_json::Value = self.beacon_request(url).await?;
+            let body = &response["data"]["message"]["body"];
+            if body["execution_payload"]["block_number"].as_str() != Some(&block_number.to_string())
+            {
+                return Err(CollectError::CollectError(format!(
+                    "beacon block of slot {} is not block {}",
+                    slot, block_number
+                )))
+            }
+            let commitments: Vec<Bytes> =
+                serde_json::from_value(body["blob_kzg_commitments"].clone()).map_err(|_| {
+                    CollectError::CollectError("invalid blob commitments".to_string())
+                })?;
+            Ok(commitments
+                .into_iter()
+                .enumerate()
+                .map(|(index, kzg_commitment)| BlobSidecar {
+                    index: index as u64,
+                    kzg_commitment,
+                    ..Default::default()
+                })
+                .collect())
+        }
+    }
+
+    async fn beacon_genesis(&self, beacon_url: &str) -> Result<(u64, u64)> {
+        self.beacon_genesis
+            .get_or_try_init(|| async {
+                let parse = |value: &serde_json::Value, name: &str| {
+                    value.as_str().and_then(|value| value.parse::<u64>().ok()).ok_or_else(|| {
+                        CollectError::CollectError(format!("invalid beacon {}", name))
+                    })
+                };
+                let url = format!("{}/eth/v1/beacon/genesis", beacon_url);
+                let genesis = self.beacon_request(url).await?;
+                let genesis_time = parse(&genesis["data"]["genesis_time"], "genesis time")?;
+                let url = format!("{}/eth/v1/config/spec", beacon_url);
+                let spec = self.beacon_request(url).await?;
+                let seconds_per_slot = parse(&spec["data"]["SECONDS_PER_SLOT"], "slot time")?;
+                Ok((genesis_time, seconds_per_slot))
+            })
+            .await
+            .copied()
+    }
+
+    /// Returns outputs of static calls at a block, None for calls that revert. Calls are packed
+    /// into a single aggregate3 call if Multicall3 is deployed, and made individually otherwise
+    /// or if the aggregate call cannot be executed at that block
+    pub async fn multicall(
+        &self,
+        calls: Vec<(H160, Vec<u8>)>,
+        block_number: BlockNumber,
+        state_override: Option<&spoof::State>,
+    ) -> Result<Vec<Option<Bytes>>> {
+        if calls.len() > 1 && self.has_multicall().await? {
+            let call_data = encode_aggregate3(&calls);
+            match self.static_call(*MULTICALL3, call_data, block_number, state_override).await {
+                Ok(output) => {
+                    if let Some(outputs) = decode_aggregate3(&output, calls.len()) {
+                        return Ok(outputs)
+                    }
+                }
+                // aggregate call may revert or be rejected by the node, e.g. when the state
+                // override cannot be applied, other errors such as rate limits fail the request
+                Err(e) if e.is_revert() || matches!(e, CollectError::MethodNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let results = futures::future::join_all(calls.into_iter().map(|(address, call_data)| {
+            self.static_call(address, call_data, block_number, state_override)
+        }))
+        .await;
+        results
+            .into_iter()
+            .map(|result| match result {
+                Ok(output) => Ok(Some(output)),
+                Err(e) if e.is_revert() => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect()
+    }
+
+    async fn has_multicall(&self) -> Result<bool> {
+        self.multicall_support
+            .get_or_try_init(|| async {
+                let code = self.fetcher.get_code(*MULTICALL3, BlockNumber::Latest).await?;
+                Ok(!code.is_empty())
+            })
+            .await
+            .copied()
+    }
+
+    async fn static_call(
+        &self,
+        address: H160,
+        call_data: Vec<u8>,
+        block_number: BlockNumber,
+        state_override: Option<&spoof::State>,
+    ) -> Result<Bytes> {
+        match state_override {
+            Some(state_override) => {
+                let transaction = TransactionRequest {
+                    to: Some(address.into()),
+                    data: Some(call_data.into()),
+                    ..Default::default()
+                };
+                self.fetcher
+                    .call_with_state_override(transaction, block_number, state_override)
+                    .await
+            }
+            None => self.fetcher.call2(address, call_data, block_number).await,
+        }
+    }
+
+    async fn beacon_request(&self, url: String) -> Result<serde_json::Value> {
+        let _permit = self.fetcher.permit_request("beacon_request").await;
+        reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| CollectError::CollectError(format!("beacon request failed ({})", e)))?
+            .json()
+            .await
+            .map_err(|e| CollectError::CollectError(format!("invalid beacon response ({})", e)))
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Multicall3, deployed at the same address on most chains
+    static ref MULTICALL3: H160 =
+        "0xcA11bde05977b3631167028862bE2a173976CA11".parse().expect("invalid address");
+}
+
+/// selector of aggregate3((address,bool,bytes)[])
+const AGGREGATE3_SELECTOR: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+
+/// encode aggregate3 call data that allows each call to fail independently
+fn encode_aggregate3(calls: &[(H160, Vec<u8>)]) -> Vec<u8> {
+    use ethers::abi::Token;
+    let calls = calls
+        .iter()
+        .map(|(address, call_data)| {
+            Token::Tuple(vec![
+                Token::Address(*address),
+                Token::Bool(true),
+                Token::Bytes(call_data.clone()),
+            ])
+        })
+        .collect();
+    let mut encoded = AGGREGATE3_SELECTOR.to_vec();
+    encoded.extend(ethers::abi::encode(&[Token::Array(calls)]));
+    encoded
+}
+
+/// decode aggregate3 output into return data of each call, None for failed calls
+fn decode_aggregate3(output: &[u8], n_calls: usize) -> Option<Vec<Option<Bytes>>> {
+    use ethers::abi::{ParamType, Token};
+    let result_type = ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes]);
+    let tokens = ethers::abi::decode(&[ParamType::Array(Box::new(result_type))], output).ok()?;
+    let results = match tokens.into_iter().next() {
+        Some(Token::Array(results)) if results.len() == n_calls => results,
+        _ => return None,
+    };
+    results
+        .into_iter()
+        .map(|result| match result {
+            Token::Tuple(result) => match result.as_slice() {
+                [Token::Bool(true), Token::Bytes(data)] => Some(Some(data.clone().into())),
+                [Token::Bool(false), Token::Bytes(_)] => Some(None),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Creation method of create traces, keyed by transaction position and trace address
+pub type CreationMethods = std::collections::HashMap<(Option<usize>, Vec<usize>), String>;
+
+fn split_creation_methods(traces: Vec<serde_json::Value>) -> Result<(Vec<Trace>, CreationMethods)> {
+    let mut creation_methods = CreationMethods::new();
+    let mut parsed = Vec::with_capacity(traces.len());
+    for trace in traces.into_iter() {
+        let creation_method = trace["action"]["creationMethod"].as_str().map(str::to_lowercase);
+        let trace: Trace = serde_json::from_value(trace)
+            .map_err(|e| CollectError::CollectError(format!("invalid trace ({})", e)))?;
+        if let Some(creation_method) = creation_method {
+            creation_methods
+                .insert((trace.transaction_position, trace.trace_address.clone()), creation_method);
+        }
+        parsed.push(trace);
+    }
+    Ok((parsed, creation_methods))
+}
+
+/// slot of the beacon block that includes an execution block with the given timestamp
+fn beacon_slot(timestamp: u64, genesis_time: u64, seconds_per_slot: u64) -> Option<u64> {
+    let elapsed = timestamp.checked_sub(genesis_time)?;
+    if seconds_per_slot == 0 || elapsed % seconds_per_slot != 0 {
+        return None
+    }
+    Some(elapsed / seconds_per_slot)
+}
+
+/// Blob sidecar of a block, as returned by beacon nodes or `eth_getBlobSidecars`
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct BlobSidecar {
+    /// index of blob within block
+    #[serde(alias = "blobIndex", deserialize_with = "deserialize_blob_index")]
+    pub index: u64,
+    /// kzg commitment of blob
+    #[serde(alias = "kzgCommitment")]
+    pub kzg_commitment: Bytes,
+    /// kzg proof of blob
+    #[serde(alias = "kzgProof", default)]
+    pub kzg_proof: Option<Bytes>,
+    /// blob body
+    #[serde(default)]
+    pub blob: Option<Bytes>,
+}
+
+/// beacon nodes encode indices as decimal strings, execution clients as hex quantities
+fn deserialize_blob_index<'de, D>(deserializer: D) -> ::core::result::Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <serde_json::Value as serde::Deserialize>::deserialize(deserializer)?;
+    let index = match &value {
+        serde_json::Value::Number(number) => number.as_u64(),
+        serde_json::Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse::<u64>().ok(),
+        },
+        _ => None,
+    };
+    index.ok_or_else(|| serde::de::Error::custom("invalid blob index"))
 }
 
 const DEFAULT_INNER_REQUEST_SIZE: u64 = 100;
@@ -91,12 +764,14 @@ impl Source {
     /// initialize source
     pub async fn init(rpc_url: Option<String>) -> Result<Source> {
         let rpc_url = parse_rpc_url(rpc_url);
-        let provider = Provider::<RetryClient<Http>>::new_client(
-            &rpc_url,
+        let pool = EndpointPool::connect(
+            std::slice::from_ref(&rpc_url),
             DEFAULT_MAX_RETRIES,
             DEFAULT_INTIAL_BACKOFF,
+            &EndpointOptions::default(),
         )
-        .map_err(|_| CollectError::RPCError("could not connect to provider".to_string()))?;
+        .await?;
+        let provider = Provider::new(pool);
         let chain_id = provider
             .get_chainid()
             .await
@@ -105,7 +780,14 @@ impl Source {
 
         let rate_limiter = None;
         let semaphore = None;
-        let fetcher = Fetcher { provider, semaphore, rate_limiter };
+        let fetcher = Fetcher {
+            provider,
+            semaphore,
+            rate_limiter,
+            request_counts: Default::default(),
+            block_timestamps: Default::default(),
+            shared_fetches: Default::default(),
+        };
 
         let source = Source {
             fetcher: Arc::new(fetcher),
@@ -113,6 +795,11 @@ impl Source {
             inner_request_size: DEFAULT_INNER_REQUEST_SIZE,
             max_concurrent_chunks: Some(DEFAULT_MAX_CONCURRENT_CHUNKS),
             rpc_url,
+            beacon_url: None,
+            multicall_support: Default::default(),
+            beacon_genesis: Default::default(),
+            capabilities: Default::default(),
+            trace_backend: TraceBackend::default(),
             labels: SourceLabels {
                 max_concurrent_requests: Some(DEFAULT_MAX_CONCURRENT_REQUESTS),
                 max_requests_per_second: Some(0),
@@ -131,7 +818,7 @@ impl Source {
 }
 
 fn parse_rpc_url(rpc_url: Option<String>) -> String {
-    let mut url = match rpc_url {
+    let url = match rpc_url {
         Some(url) => url.clone(),
         _ => match std::env::var("ETH_RPC_URL") {
             Ok(url) => url,
@@ -141,10 +828,7 @@ fn parse_rpc_url(rpc_url: Option<String>) -> String {
             }
         },
     };
-    if !url.starts_with("http") {
-        url = "http://".to_string() + url.as_str();
-    };
-    url
+    normalize_rpc_url(&url)
 }
 
 // builder
@@ -172,6 +856,33 @@ fn parse_rpc_url(rpc_url: Option<String>) -> String {
 //     }
 // }
 
+/// name of a chain in the chain registry, or network_<CHAIN_ID> for unknown chains
+pub fn chain_name(chain_id: u64) -> String {
+    match chain_id {
+        1 => "ethereum".to_string(),
+        5 => "goerli".to_string(),
+        10 => "optimism".to_string(),
+        56 => "bnb".to_string(),
+        69 => "optimism_kovan".to_string(),
+        100 => "gnosis".to_string(),
+        137 => "polygon".to_string(),
+        420 => "optimism_goerli".to_string(),
+        1101 => "polygon_zkevm".to_string(),
+        1442 => "polygon_zkevm_testnet".to_string(),
+        8453 => "base".to_string(),
+        10200 => "gnosis_chidao".to_string(),
+        17000 => "holesky".to_string(),
+        42161 => "arbitrum".to_string(),
+        42170 => "arbitrum_nova".to_string(),
+        43114 => "avalanche".to_string(),
+        80001 => "polygon_mumbai".to_string(),
+        84531 => "base_goerli".to_string(),
+        7777777 => "zora".to_string(),
+        11155111 => "sepolia".to_string(),
+        chain_id => "network_".to_string() + chain_id.to_string().as_str(),
+    }
+}
+
 /// source labels (non-functional)
 #[derive(Clone, Debug)]
 pub struct SourceLabels {
@@ -194,6 +905,78 @@ pub struct Fetcher<P> {
     pub semaphore: Option<Semaphore>,
     /// rate limiter for controlling request rate
     pub rate_limiter: Option<RateLimiter>,
+    /// number of requests made by each method
+    pub request_counts: RequestCounts,
+    /// timestamps of the block headers fetched during the run
+    pub block_timestamps: BlockTimestamps,
+    /// block responses shared by the datasets of the run
+    pub shared_fetches: SharedFetches,
+}
+
+/// number of requests made by each method of a fetcher
+#[derive(Clone, Debug, Default)]
+pub struct RequestCounts(Arc<Mutex<BTreeMap<String, u64>>>);
+
+impl RequestCounts {
+    fn increment(&self, method: &str) {
+        if let Ok(mut counts) = self.0.lock() {
+            *counts.entry(method.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// current number of requests of each method
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        self.0.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+}
+
+/// timestamps of the block headers fetched by a fetcher, shared by all datasets of a run
+#[derive(Clone, Debug, Default)]
+pub struct BlockTimestamps {
+    enabled: Arc<AtomicBool>,
+    timestamps: Arc<Mutex<HashMap<u64, u32>>>,
+}
+
+impl BlockTimestamps {
+    /// record timestamps of fetched headers only if a dataset of the run has block time columns
+    pub fn enable(&self, schemas: &HashMap<Datatype, Table>) {
+        let enabled = schemas.values().any(|schema| !schema.block_time_columns.is_empty());
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    fn insert<T>(&self, block: &Block<T>) {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return
+        }
+        if let (Some(number), Ok(timestamp), Ok(mut timestamps)) =
+            (block.number, u32::try_from(block.timestamp), self.timestamps.lock())
+        {
+            timestamps.insert(number.as_u64(), timestamp);
+        }
+    }
+
+    /// timestamps of the blocks whose headers have been fetched
+    pub fn get(&self, block_numbers: &[u64]) -> HashMap<u64, u32> {
+        match self.timestamps.lock() {
+            Ok(timestamps) => block_numbers
+                .iter()
+                .filter_map(|number| timestamps.get(number).map(|timestamp| (*number, *timestamp)))
+                .collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    /// drop timestamps of blocks whose chunks have been collected
+    pub fn release(&self, block_numbers: RangeInclusive<u64>) {
+        if let Ok(mut timestamps) = self.timestamps.lock() {
+            timestamps.retain(|block_number, _| !block_numbers.contains(block_number));
+        }
+    }
+
+    /// drop all timestamps
+    pub fn clear(&self) {
+        self.release(0..=u64::MAX)
+    }
 }
 
 type Result<T> = ::core::result::Result<T, CollectError>;
@@ -201,7 +984,7 @@ type Result<T> = ::core::result::Result<T, CollectError>;
 impl<P: JsonRpcClient> Fetcher<P> {
     /// Returns an array (possibly empty) of logs that match the filter
     pub async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("get_logs").await;
         Self::map_err(self.provider.get_logs(filter).await)
     }
 
@@ -211,16 +994,16 @@ impl<P: JsonRpcClient> Fetcher<P> {
         block: BlockNumber,
         trace_types: Vec<TraceType>,
     ) -> Result<Vec<BlockTrace>> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("trace_replay_block_transactions").await;
         Self::map_err(self.provider.trace_replay_block_transactions(block, trace_types).await)
     }
 
     /// Get state diff traces of block
     pub async fn trace_block_state_diffs(
         &self,
-        block: u32,
+        block: u64,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
         // get traces
         let result = self
             .trace_replay_block_transactions(
@@ -230,30 +1013,72 @@ impl<P: JsonRpcClient> Fetcher<P> {
             .await?;
 
         // get transactions
-        let txs = if include_transaction_hashes {
-            self.get_block(block as u64)
-                .await?
-                .ok_or(CollectError::CollectError("could not find block".to_string()))?
-                .transactions
-                .iter()
-                .map(|tx| Some(tx.0.to_vec()))
-                .collect()
-        } else {
-            vec![None; result.len()]
-        };
+        let (indices, txs) =
+            self.replayed_transactions(block, &result, include_transaction_hashes).await?;
+
+        Ok((Some(block), indices, txs, result))
+    }
+
+    /// Get call trace replays of block, including the output of each transaction
+    pub async fn trace_block_call_replays(
+        &self,
+        block: u64,
+        include_transaction_hashes: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
+        let result = self
+            .trace_replay_block_transactions(block.into(), vec![ethers::types::TraceType::Trace])
+            .await?;
+
+        let (indices, txs) =
+            self.replayed_transactions(block, &result, include_transaction_hashes).await?;
 
-        Ok((Some(block), txs, result))
+        Ok((Some(block), indices, txs, result))
+    }
+
+    /// indices and hashes of the transactions of replayed traces of a block, matching each replay
+    /// to a transaction of the block by its hash, or by its position for replays without a hash
+    async fn replayed_transactions(
+        &self,
+        block: u64,
+        replays: &[BlockTrace],
+        include_transaction_hashes: bool,
+    ) -> Result<(Vec<Option<u64>>, Vec<Option<Vec<u8>>>)> {
+        if !include_transaction_hashes {
+            return Ok((vec![None; replays.len()], vec![None; replays.len()]))
+        }
+        let transactions = self
+            .get_block(block)
+            .await?
+            .ok_or(CollectError::CollectError("could not find block".to_string()))?
+            .transactions;
+        let positions: HashMap<H256, usize> =
+            transactions.iter().enumerate().map(|(index, hash)| (*hash, index)).collect();
+        let mut indices = Vec::new();
+        let mut txs = Vec::new();
+        for (position, replay) in replays.iter().enumerate() {
+            let index = match replay.transaction_hash {
+                Some(hash) => positions.get(&hash).copied(),
+                None => Some(position).filter(|position| *position < transactions.len()),
+            };
+            indices.push(index.map(|index| index as u64));
+            txs.push(index.map(|index| transactions[index].as_bytes().to_vec()));
+        }
+        Ok((indices, txs))
     }
 
     /// Get VM traces of block
     pub async fn trace_block_vm_traces(
         &self,
-        block: u32,
-    ) -> Result<(Option<u32>, Option<Vec<u8>>, Vec<BlockTrace>)> {
+        block: u64,
+        include_call_traces: bool,
+        include_transaction_indices: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Option<Vec<u8>>, Vec<BlockTrace>)> {
         let result = self
-            .trace_replay_block_transactions(block.into(), vec![ethers::types::TraceType::VmTrace])
-            .await;
-        Ok((Some(block), None, result?))
+            .trace_replay_block_transactions(block.into(), vm_trace_types(include_call_traces))
+            .await?;
+        let (indices, _) =
+            self.replayed_transactions(block, &result, include_transaction_indices).await?;
+        Ok((Some(block), indices, None, result))
     }
 
     /// Replays a transaction, returning the traces
@@ -262,7 +1087,7 @@ impl<P: JsonRpcClient> Fetcher<P> {
         tx_hash: TxHash,
         trace_types: Vec<TraceType>,
     ) -> Result<BlockTrace> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("trace_replay_transaction").await;
         Self::map_err(self.provider.trace_replay_transaction(tx_hash, trace_types).await)
     }
 
@@ -270,33 +1095,57 @@ impl<P: JsonRpcClient> Fetcher<P> {
     pub async fn trace_transaction_state_diffs(
         &self,
         transaction_hash: Vec<u8>,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BlockTrace>)> {
         let result = self
             .trace_replay_transaction(
                 H256::from_slice(&transaction_hash),
                 vec![ethers::types::TraceType::StateDiff],
             )
-            .await;
-        Ok((None, vec![Some(transaction_hash)], vec![result?]))
+            .await?;
+        let (block_number, index) =
+            self.transaction_position(&transaction_hash, include_transaction).await?;
+        Ok((block_number, vec![index], vec![Some(transaction_hash)], vec![result]))
     }
 
     /// Get VM traces of transaction
     pub async fn trace_transaction_vm_traces(
         &self,
         transaction_hash: Vec<u8>,
-    ) -> Result<(Option<u32>, Option<Vec<u8>>, Vec<BlockTrace>)> {
+        include_call_traces: bool,
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Option<Vec<u8>>, Vec<BlockTrace>)> {
         let result = self
             .trace_replay_transaction(
                 H256::from_slice(&transaction_hash),
-                vec![ethers::types::TraceType::VmTrace],
+                vm_trace_types(include_call_traces),
             )
-            .await;
-        Ok((None, Some(transaction_hash), vec![result?]))
+            .await?;
+        let (block_number, index) =
+            self.transaction_position(&transaction_hash, include_transaction).await?;
+        Ok((block_number, vec![index], Some(transaction_hash), vec![result]))
+    }
+
+    /// block number and index of a traced transaction, None unless include_transaction
+    async fn transaction_position(
+        &self,
+        transaction_hash: &[u8],
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Option<u64>)> {
+        if !include_transaction {
+            return Ok((None, None))
+        }
+        match self.get_transaction(H256::from_slice(transaction_hash)).await? {
+            Some(tx) => {
+                Ok((tx.block_number.map(|x| x.as_u64()), tx.transaction_index.map(|x| x.as_u64())))
+            }
+            None => Err(CollectError::CollectError("could not get block for txs".to_string())),
+        }
     }
 
     /// Gets the transaction with transaction_hash
     pub async fn get_transaction(&self, tx_hash: TxHash) -> Result<Option<Transaction>> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("get_transaction").await;
         Self::map_err(self.provider.get_transaction(tx_hash).await)
     }
 
@@ -305,26 +1154,77 @@ impl<P: JsonRpcClient> Fetcher<P> {
         &self,
         tx_hash: TxHash,
     ) -> Result<Option<TransactionReceipt>> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("get_transaction_receipt").await;
         Self::map_err(self.provider.get_transaction_receipt(tx_hash).await)
     }
 
+    /// Gets the uncle at `index` of the block at `block_num`
+    pub async fn get_uncle(&self, block_num: u64, index: u64) -> Result<Option<Block<TxHash>>> {
+        let _permit = self.permit_request("get_uncle").await;
+        Self::map_err(self.provider.get_uncle(block_num, index.into()).await)
+    }
+
+    /// Gets pending and queued transactions of the node's txpool
+    pub async fn txpool_content(&self) -> Result<TxpoolContent> {
+        let _permit = self.permit_request("txpool_content").await;
+        Self::map_err(self.provider.txpool_content().await)
+    }
+
+    /// Installs a filter of newly received pending transaction hashes
+    pub async fn new_pending_transaction_filter(&self) -> Result<U256> {
+        let _permit = self.permit_request("new_pending_transaction_filter").await;
+        Self::map_err(self.provider.new_filter(FilterKind::PendingTransactions).await)
+    }
+
+    /// Gets the transaction hashes received by a pending transaction filter since its last poll
+    pub async fn get_pending_transaction_filter_changes(
+        &self,
+        filter_id: U256,
+    ) -> Result<Vec<H256>> {
+        let _permit = self.permit_request("get_pending_transaction_filter_changes").await;
+        Self::map_err(self.provider.get_filter_changes(filter_id).await)
+    }
+
+    /// Uninstalls a filter
+    pub async fn uninstall_filter(&self, filter_id: U256) -> Result<bool> {
+        let _permit = self.permit_request("uninstall_filter").await;
+        Self::map_err(self.provider.uninstall_filter(filter_id).await)
+    }
+
     /// Gets the block at `block_num` (transaction hashes only)
     pub async fn get_block(&self, block_num: u64) -> Result<Option<Block<TxHash>>> {
-        let _permit = self.permit_request().await;
-        Self::map_err(self.provider.get_block(block_num).await)
+        if self.shared_fetches.shares_full_blocks() {
+            let block = self.get_block_with_txs(block_num).await?;
+            return Ok(block.map(shared_fetches::without_transactions))
+        }
+        let fetch = async {
+            let _permit = self.permit_request("get_block").await;
+            Self::map_err(self.provider.get_block(block_num).await)
+        };
+        let block = self.shared_fetches.block(block_num, fetch).await?;
+        if let Some(block) = &block {
+            self.block_timestamps.insert(block);
+        }
+        Ok(block)
     }
 
     /// Gets the block at `block_num` (transaction hashes only)
     pub async fn get_block_by_hash(&self, block_hash: H256) -> Result<Option<Block<TxHash>>> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("get_block_by_hash").await;
         Self::map_err(self.provider.get_block(BlockId::Hash(block_hash)).await)
     }
 
     /// Gets the block at `block_num` (full transactions included)
     pub async fn get_block_with_txs(&self, block_num: u64) -> Result<Option<Block<Transaction>>> {
-        let _permit = self.permit_request().await;
-        Self::map_err(self.provider.get_block_with_txs(block_num).await)
+        let fetch = async {
+            let _permit = self.permit_request("get_block_with_txs").await;
+            Self::map_err(self.provider.get_block_with_txs(block_num).await)
+        };
+        let block = self.shared_fetches.block_with_txs(block_num, fetch).await?;
+        if let Some(block) = &block {
+            self.block_timestamps.insert(block);
+        }
+        Ok(block)
     }
 
     /// Returns all receipts for a block.
@@ -332,20 +1232,103 @@ impl<P: JsonRpcClient> Fetcher<P> {
     /// Consider using `FetcherExt::get_tx_receipts_in_block` which takes a block, and falls back to
     /// `eth_getTransactionReceipt` if `eth_getBlockReceipts` is not supported.
     pub async fn get_block_receipts(&self, block_num: u64) -> Result<Vec<TransactionReceipt>> {
-        let _permit = self.permit_request().await;
-        Self::map_err(self.provider.get_block_receipts(block_num).await)
+        let fetch = async {
+            let _permit = self.permit_request("get_block_receipts").await;
+            Self::map_err(self.provider.get_block_receipts(block_num).await)
+        };
+        self.shared_fetches.receipts(block_num, fetch).await
+    }
+
+    /// Returns otterscan api level of node
+    pub async fn ots_get_api_level(&self) -> Result<u64> {
+        let _permit = self.permit_request("ots_get_api_level").await;
+        Self::map_err(self.provider.request("ots_getApiLevel", ()).await)
+    }
+
+    /// Returns receipts of the transactions of a block using `ots_getBlockTransactions`, whose
+    /// receipts leave out logs and logs blooms
+    pub async fn ots_get_block_receipts(
+        &self,
+        block_num: u64,
+        n_transactions: u64,
+    ) -> Result<Vec<TransactionReceipt>> {
+        let _permit = self.permit_request("ots_get_block_receipts").await;
+        let page_size = n_transactions.max(1);
+        let response: serde_json::Value = Self::map_err(
+            self.provider.request("ots_getBlockTransactions", (block_num, 0, page_size)).await,
+        )?;
+        let receipts = match response.get("receipts") {
+            Some(serde_json::Value::Array(receipts)) => receipts.clone(),
+            _ => return Err(CollectError::CollectError("invalid otterscan receipts".to_string())),
+        };
+        receipts
+            .into_iter()
+            .map(|mut receipt| {
+                receipt["logs"] = serde_json::json!([]);
+                receipt["logsBloom"] = serde_json::to_value(Bloom::zero()).unwrap_or_default();
+                serde_json::from_value(receipt).map_err(|_| {
+                    CollectError::CollectError("invalid otterscan receipt".to_string())
+                })
+            })
+            .collect()
+    }
+
+    /// Returns blob sidecars of a block.
+    /// Note that `eth_getBlobSidecars` is not supported by all nodes, see
+    /// `Source::get_blob_sidecars`
+    pub async fn get_blob_sidecars(&self, block_num: u64) -> Result<Vec<BlobSidecar>> {
+        let _permit = self.permit_request("get_blob_sidecars").await;
+        Self::map_err(
+            self.provider.request("eth_getBlobSidecars", [BlockNumber::from(block_num)]).await,
+        )
     }
 
     /// Returns traces created at given block
     pub async fn trace_block(&self, block_num: BlockNumber) -> Result<Vec<Trace>> {
-        let _permit = self.permit_request().await;
-        Self::map_err(self.provider.trace_block(block_num).await)
+        let fetch = async {
+            let _permit = self.permit_request("trace_block").await;
+            Self::map_err(self.provider.trace_block(block_num).await)
+        };
+        match block_num {
+            BlockNumber::Number(number) => self.shared_fetches.traces(number.as_u64(), fetch).await,
+            _ => fetch.await,
+        }
+    }
+
+    /// Returns traces matching filter
+    pub async fn trace_filter(&self, filter: TraceFilter) -> Result<Vec<Trace>> {
+        let _permit = self.permit_request("trace_filter").await;
+        Self::map_err(self.provider.trace_filter(filter).await)
     }
 
     /// Returns all traces of a given transaction
     pub async fn trace_transaction(&self, tx_hash: TxHash) -> Result<Vec<Trace>> {
-        let _permit = self.permit_request().await;
-        self.provider.trace_transaction(tx_hash).await.map_err(CollectError::ProviderError)
+        let _permit = self.permit_request("trace_transaction").await;
+        self.provider.trace_transaction(tx_hash).await.map_err(provider_error)
+    }
+
+    /// Returns all traces of a block, along with the creation method that nodes such as reth and
+    /// erigon report for each create trace
+    pub async fn trace_block_with_creation_methods(
+        &self,
+        block_num: BlockNumber,
+    ) -> Result<(Vec<Trace>, CreationMethods)> {
+        let _permit = self.permit_request("trace_block_with_creation_methods").await;
+        let traces: Vec<serde_json::Value> =
+            Self::map_err(self.provider.request("trace_block", [block_num]).await)?;
+        split_creation_methods(traces)
+    }
+
+    /// Returns all traces of a transaction, along with the creation method that nodes such as
+    /// reth and erigon report for each create trace
+    pub async fn trace_transaction_with_creation_methods(
+        &self,
+        tx_hash: TxHash,
+    ) -> Result<(Vec<Trace>, CreationMethods)> {
+        let _permit = self.permit_request("trace_transaction_with_creation_methods").await;
+        let traces: Vec<serde_json::Value> =
+            Self::map_err(self.provider.request("trace_transaction", [tx_hash]).await)?;
+        split_creation_methods(traces)
     }
 
     /// Deprecated
@@ -354,11 +1337,60 @@ impl<P: JsonRpcClient> Fetcher<P> {
         transaction: TransactionRequest,
         block_number: BlockNumber,
     ) -> Result<Bytes> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("call").await;
         self.provider
             .call(&transaction.into(), Some(block_number.into()))
             .await
-            .map_err(CollectError::ProviderError)
+            .map_err(provider_error)
+    }
+
+    /// Returns output of calling a contract under a state override set
+    pub async fn call_with_state_override(
+        &self,
+        transaction: TransactionRequest,
+        block_number: BlockNumber,
+        state_override: &spoof::State,
+    ) -> Result<Bytes> {
+        let _permit = self.permit_request("call_with_state_override").await;
+        let transaction: ethers::types::transaction::eip2718::TypedTransaction = transaction.into();
+        let params = (transaction, block_number, state_override);
+        Self::map_state_override_err(self.provider.request("eth_call", params).await, "eth_call")
+    }
+
+    /// Returns call trace of calling a contract under a state override set using debug_traceCall
+    pub async fn geth_debug_trace_call_with_state_override(
+        &self,
+        address: H160,
+        call_data: Vec<u8>,
+        block_number: BlockNumber,
+        state_override: &spoof::State,
+    ) -> Result<CallFrame> {
+        let transaction = TransactionRequest {
+            to: Some(address.into()),
+            data: Some(call_data.into()),
+            ..Default::default()
+        };
+        let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer);
+        let config = GethDebugTracerConfig::BuiltInTracer(
+            GethDebugBuiltInTracerConfig::CallTracer(CallConfig { ..Default::default() }),
+        );
+        let options = GethDebugTracingCallOptions {
+            tracing_options: GethDebugTracingOptions {
+                tracer: Some(tracer),
+                tracer_config: Some(config),
+                ..Default::default()
+            },
+            state_overrides: Some(state_override.clone()),
+        };
+        let _permit = self.permit_request("geth_debug_trace_call_with_state_override").await;
+        let trace = Self::map_state_override_err(
+            self.provider.debug_trace_call(transaction, Some(block_number.into()), options).await,
+            "debug_traceCall",
+        )?;
+        match trace {
+            GethTrace::Known(GethTraceFrame::CallTracer(call_frame)) => Ok(call_frame),
+            _ => Err(CollectError::CollectError("invalid trace result".to_string())),
+        }
     }
 
     /// Returns traces for given call data
@@ -368,11 +1400,11 @@ impl<P: JsonRpcClient> Fetcher<P> {
         trace_type: Vec<TraceType>,
         block_number: Option<BlockNumber>,
     ) -> Result<BlockTrace> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("trace_call").await;
         self.provider
             .trace_call(transaction, trace_type, block_number)
             .await
-            .map_err(CollectError::ProviderError)
+            .map_err(provider_error)
     }
 
     /// Get nonce of address
@@ -381,29 +1413,23 @@ impl<P: JsonRpcClient> Fetcher<P> {
         address: H160,
         block_number: BlockNumber,
     ) -> Result<U256> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("get_transaction_count").await;
         self.provider
             .get_transaction_count(address, Some(block_number.into()))
             .await
-            .map_err(CollectError::ProviderError)
+            .map_err(provider_error)
     }
 
     /// Get code at address
     pub async fn get_balance(&self, address: H160, block_number: BlockNumber) -> Result<U256> {
-        let _permit = self.permit_request().await;
-        self.provider
-            .get_balance(address, Some(block_number.into()))
-            .await
-            .map_err(CollectError::ProviderError)
+        let _permit = self.permit_request("get_balance").await;
+        self.provider.get_balance(address, Some(block_number.into())).await.map_err(provider_error)
     }
 
     /// Get code at address
     pub async fn get_code(&self, address: H160, block_number: BlockNumber) -> Result<Bytes> {
-        let _permit = self.permit_request().await;
-        self.provider
-            .get_code(address, Some(block_number.into()))
-            .await
-            .map_err(CollectError::ProviderError)
+        let _permit = self.permit_request("get_code").await;
+        self.provider.get_code(address, Some(block_number.into())).await.map_err(provider_error)
     }
 
     /// Get stored data at given location
@@ -413,11 +1439,11 @@ impl<P: JsonRpcClient> Fetcher<P> {
         slot: H256,
         block_number: BlockNumber,
     ) -> Result<H256> {
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("get_storage_at").await;
         self.provider
             .get_storage_at(address, slot, Some(block_number.into()))
             .await
-            .map_err(CollectError::ProviderError)
+            .map_err(provider_error)
     }
 
     /// Get the block number
@@ -428,13 +1454,13 @@ impl<P: JsonRpcClient> Fetcher<P> {
     // extra helpers below
 
     /// block number of transaction
-    pub async fn get_transaction_block_number(&self, transaction_hash: Vec<u8>) -> Result<u32> {
+    pub async fn get_transaction_block_number(&self, transaction_hash: Vec<u8>) -> Result<u64> {
         let block = self.get_transaction(H256::from_slice(&transaction_hash)).await?;
         let block = block.ok_or(CollectError::CollectError("could not get block".to_string()))?;
         Ok(block
             .block_number
             .ok_or(CollectError::CollectError("could not get block number".to_string()))?
-            .as_u32())
+            .as_u64())
     }
 
     /// block number of transaction
@@ -458,11 +1484,11 @@ impl<P: JsonRpcClient> Fetcher<P> {
             data: Some(call_data.into()),
             ..Default::default()
         };
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("call2").await;
         self.provider
             .call(&transaction.into(), Some(block_number.into()))
             .await
-            .map_err(CollectError::ProviderError)
+            .map_err(provider_error)
     }
 
     /// Return output data of a contract call
@@ -478,33 +1504,31 @@ impl<P: JsonRpcClient> Fetcher<P> {
             data: Some(call_data.into()),
             ..Default::default()
         };
-        let _permit = self.permit_request().await;
+        let _permit = self.permit_request("trace_call2").await;
         self.provider
             .trace_call(transaction, trace_type, block_number)
             .await
-            .map_err(CollectError::ProviderError)
+            .map_err(provider_error)
     }
 
     /// get geth debug block traces
     pub async fn geth_debug_trace_block(
         &self,
-        block_number: u32,
+        block_number: u64,
         options: GethDebugTracingOptions,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<GethTrace>)> {
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<GethTrace>)> {
         let traces = {
-            let _permit = self.permit_request().await;
+            let _permit = self.permit_request("geth_debug_trace_block").await;
             self.provider
                 .debug_trace_block_by_number(Some(block_number.into()), options)
                 .await
-                .map_err(CollectError::ProviderError)?
+                .map_err(method_error("debug_traceBlockByNumber"))?
         };
 
-        let txs = if include_transaction_hashes {
-            match self.get_block(block_number as u64).await? {
-                Some(block) => {
-                    block.transactions.iter().map(|x| Some(x.as_bytes().to_vec())).collect()
-                }
+        let transactions = if include_transaction_hashes {
+            match self.get_block(block_number).await? {
+                Some(block) => block.transactions,
                 None => {
                     return Err(CollectError::CollectError(
                         "could not get block for txs".to_string(),
@@ -512,22 +1536,31 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 }
             }
         } else {
-            vec![None; traces.len()]
+            vec![]
+        };
+        let txs = match transactions.is_empty() {
+            false => transactions.iter().map(|x| Some(x.as_bytes().to_vec())).collect(),
+            true => vec![None; traces.len()],
+        };
+        // traces are in the order of the transactions of the block, one trace per transaction
+        let indices = match transactions.len() == traces.len() {
+            true => (0..traces.len()).map(|index| Some(index as u64)).collect(),
+            false => vec![None; traces.len()],
         };
 
-        Ok((Some(block_number), txs, traces))
+        Ok((Some(block_number), indices, txs, traces))
     }
 
     /// get geth debug block call traces
     pub async fn geth_debug_trace_block_javascript_traces(
         &self,
         js_tracer: String,
-        block_number: u32,
+        block_number: u64,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<serde_json::Value>)> {
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<serde_json::Value>)> {
         let tracer = GethDebugTracerType::JsTracer(js_tracer);
         let options = GethDebugTracingOptions { tracer: Some(tracer), ..Default::default() };
-        let (block, txs, traces) =
+        let (block, indices, txs, traces) =
             self.geth_debug_trace_block(block_number, options, include_transaction_hashes).await?;
 
         let mut calls = Vec::new();
@@ -537,17 +1570,17 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block opcode traces
     pub async fn geth_debug_trace_block_opcodes(
         &self,
-        block_number: u32,
+        block_number: u64,
         include_transaction_hashes: bool,
         options: GethDebugTracingOptions,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>)> {
-        let (block, txs, traces) =
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>)> {
+        let (block, indices, txs, traces) =
             self.geth_debug_trace_block(block_number, options, include_transaction_hashes).await?;
 
         let mut calls = Vec::new();
@@ -557,18 +1590,19 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block 4byte traces
     pub async fn geth_debug_trace_block_4byte_traces(
         &self,
-        block_number: u32,
+        block_number: u64,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<String, u64>>)> {
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<String, u64>>)>
+    {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::FourByteTracer);
         let options = GethDebugTracingOptions { tracer: Some(tracer), ..Default::default() };
-        let (block, txs, traces) =
+        let (block, indices, txs, traces) =
             self.geth_debug_trace_block(block_number, options, include_transaction_hashes).await?;
 
         let mut calls = Vec::new();
@@ -577,22 +1611,28 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 GethTrace::Known(GethTraceFrame::FourByteTracer(FourByteFrame(frame))) => {
                     calls.push(frame)
                 }
-                GethTrace::Known(GethTraceFrame::NoopTracer(_)) => {}
+                // transactions without calls keep their place, so traces stay aligned with txs
+                GethTrace::Known(GethTraceFrame::NoopTracer(_)) => calls.push(BTreeMap::new()),
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block call traces
     pub async fn geth_debug_trace_block_prestate(
         &self,
-        block_number: u32,
+        block_number: u64,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<H160, AccountState>>)> {
+    ) -> Result<(
+        Option<u64>,
+        Vec<Option<u64>>,
+        Vec<Option<Vec<u8>>>,
+        Vec<BTreeMap<H160, AccountState>>,
+    )> {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer);
         let options = GethDebugTracingOptions { tracer: Some(tracer), ..Default::default() };
-        let (block, txs, traces) =
+        let (block, indices, txs, traces) =
             self.geth_debug_trace_block(block_number, options, include_transaction_hashes).await?;
 
         let mut calls = Vec::new();
@@ -604,15 +1644,15 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block call traces
     pub async fn geth_debug_trace_block_calls(
         &self,
-        block_number: u32,
+        block_number: u64,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>)> {
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<CallFrame>)> {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer);
         let config = GethDebugTracerConfig::BuiltInTracer(
             GethDebugBuiltInTracerConfig::CallTracer(CallConfig { ..Default::default() }),
@@ -622,7 +1662,7 @@ impl<P: JsonRpcClient> Fetcher<P> {
             tracer_config: Some(config),
             ..Default::default()
         };
-        let (block, txs, traces) =
+        let (block, indices, txs, traces) =
             self.geth_debug_trace_block(block_number, options, include_transaction_hashes).await?;
 
         let mut calls = Vec::new();
@@ -632,15 +1672,15 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block diff traces
     pub async fn geth_debug_trace_block_diffs(
         &self,
-        block_number: u32,
+        block_number: u64,
         include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<DiffMode>)> {
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<DiffMode>)> {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer);
         let config = GethDebugTracerConfig::BuiltInTracer(
             GethDebugBuiltInTracerConfig::PreStateTracer(PreStateConfig { diff_mode: Some(true) }),
@@ -650,7 +1690,7 @@ impl<P: JsonRpcClient> Fetcher<P> {
             tracer_config: Some(config),
             ..Default::default()
         };
-        let (block, txs, traces) =
+        let (block, indices, txs, traces) =
             self.geth_debug_trace_block(block_number, options, include_transaction_hashes).await?;
 
         let mut diffs = Vec::new();
@@ -669,7 +1709,7 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 }
             }
         }
-        Ok((block, txs, diffs))
+        Ok((block, indices, txs, diffs))
     }
 
     /// get geth debug transaction traces
@@ -677,33 +1717,23 @@ impl<P: JsonRpcClient> Fetcher<P> {
         &self,
         transaction_hash: Vec<u8>,
         options: GethDebugTracingOptions,
-        include_block_number: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<GethTrace>)> {
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<GethTrace>)> {
         let ethers_tx = H256::from_slice(&transaction_hash);
 
         let trace = {
-            let _permit = self.permit_request().await;
+            let _permit = self.permit_request("geth_debug_trace_transaction").await;
             self.provider
                 .debug_trace_transaction(ethers_tx, options)
                 .await
-                .map_err(CollectError::ProviderError)?
+                .map_err(method_error("debug_traceTransaction"))?
         };
         let traces = vec![trace];
 
-        let block_number = if include_block_number {
-            match self.get_transaction(ethers_tx).await? {
-                Some(tx) => tx.block_number.map(|x| x.as_u32()),
-                None => {
-                    return Err(CollectError::CollectError(
-                        "could not get block for txs".to_string(),
-                    ))
-                }
-            }
-        } else {
-            None
-        };
+        let (block_number, index) =
+            self.transaction_position(&transaction_hash, include_transaction).await?;
 
-        Ok((block_number, vec![Some(transaction_hash)], traces))
+        Ok((block_number, vec![index], vec![Some(transaction_hash)], traces))
     }
 
     /// get geth debug block javascript traces
@@ -711,12 +1741,12 @@ impl<P: JsonRpcClient> Fetcher<P> {
         &self,
         js_tracer: String,
         transaction_hash: Vec<u8>,
-        include_block_number: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<serde_json::Value>)> {
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<serde_json::Value>)> {
         let tracer = GethDebugTracerType::JsTracer(js_tracer);
         let options = GethDebugTracingOptions { tracer: Some(tracer), ..Default::default() };
-        let (block, txs, traces) = self
-            .geth_debug_trace_transaction(transaction_hash, options, include_block_number)
+        let (block, indices, txs, traces) = self
+            .geth_debug_trace_transaction(transaction_hash, options, include_transaction)
             .await?;
 
         let mut calls = Vec::new();
@@ -726,18 +1756,18 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block opcode traces
     pub async fn geth_debug_trace_transaction_opcodes(
         &self,
         transaction_hash: Vec<u8>,
-        include_block_number: bool,
+        include_transaction: bool,
         options: GethDebugTracingOptions,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>)> {
-        let (block, txs, traces) = self
-            .geth_debug_trace_transaction(transaction_hash, options, include_block_number)
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<DefaultFrame>)> {
+        let (block, indices, txs, traces) = self
+            .geth_debug_trace_transaction(transaction_hash, options, include_transaction)
             .await?;
 
         let mut calls = Vec::new();
@@ -747,19 +1777,20 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block 4byte traces
     pub async fn geth_debug_trace_transaction_4byte_traces(
         &self,
         transaction_hash: Vec<u8>,
-        include_block_number: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<String, u64>>)> {
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<String, u64>>)>
+    {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::FourByteTracer);
         let options = GethDebugTracingOptions { tracer: Some(tracer), ..Default::default() };
-        let (block, txs, traces) = self
-            .geth_debug_trace_transaction(transaction_hash, options, include_block_number)
+        let (block, indices, txs, traces) = self
+            .geth_debug_trace_transaction(transaction_hash, options, include_transaction)
             .await?;
 
         let mut calls = Vec::new();
@@ -768,22 +1799,28 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 GethTrace::Known(GethTraceFrame::FourByteTracer(FourByteFrame(frame))) => {
                     calls.push(frame)
                 }
+                GethTrace::Known(GethTraceFrame::NoopTracer(_)) => calls.push(BTreeMap::new()),
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block call traces
     pub async fn geth_debug_trace_transaction_prestate(
         &self,
         transaction_hash: Vec<u8>,
-        include_block_number: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<BTreeMap<H160, AccountState>>)> {
+        include_transaction: bool,
+    ) -> Result<(
+        Option<u64>,
+        Vec<Option<u64>>,
+        Vec<Option<Vec<u8>>>,
+        Vec<BTreeMap<H160, AccountState>>,
+    )> {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer);
         let options = GethDebugTracingOptions { tracer: Some(tracer), ..Default::default() };
-        let (block, txs, traces) = self
-            .geth_debug_trace_transaction(transaction_hash, options, include_block_number)
+        let (block, indices, txs, traces) = self
+            .geth_debug_trace_transaction(transaction_hash, options, include_transaction)
             .await?;
 
         let mut calls = Vec::new();
@@ -795,15 +1832,15 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block call traces
     pub async fn geth_debug_trace_transaction_calls(
         &self,
         transaction_hash: Vec<u8>,
-        include_block_number: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>)> {
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<CallFrame>)> {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer);
         let config = GethDebugTracerConfig::BuiltInTracer(
             GethDebugBuiltInTracerConfig::CallTracer(CallConfig { ..Default::default() }),
@@ -813,8 +1850,8 @@ impl<P: JsonRpcClient> Fetcher<P> {
             tracer_config: Some(config),
             ..Default::default()
         };
-        let (block, txs, traces) = self
-            .geth_debug_trace_transaction(transaction_hash, options, include_block_number)
+        let (block, indices, txs, traces) = self
+            .geth_debug_trace_transaction(transaction_hash, options, include_transaction)
             .await?;
 
         let mut calls = Vec::new();
@@ -824,15 +1861,15 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, calls))
+        Ok((block, indices, txs, calls))
     }
 
     /// get geth debug block diff traces
     pub async fn geth_debug_trace_transaction_diffs(
         &self,
         transaction_hash: Vec<u8>,
-        include_transaction_hashes: bool,
-    ) -> Result<(Option<u32>, Vec<Option<Vec<u8>>>, Vec<DiffMode>)> {
+        include_transaction: bool,
+    ) -> Result<(Option<u64>, Vec<Option<u64>>, Vec<Option<Vec<u8>>>, Vec<DiffMode>)> {
         let tracer = GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer);
         let config = GethDebugTracerConfig::BuiltInTracer(
             GethDebugBuiltInTracerConfig::PreStateTracer(PreStateConfig { diff_mode: Some(true) }),
@@ -842,8 +1879,8 @@ impl<P: JsonRpcClient> Fetcher<P> {
             tracer_config: Some(config),
             ..Default::default()
         };
-        let (block, txs, traces) = self
-            .geth_debug_trace_transaction(transaction_hash, options, include_transaction_hashes)
+        let (block, indices, txs, traces) = self
+            .geth_debug_trace_transaction(transaction_hash, options, include_transaction)
             .await?;
 
         let mut diffs = Vec::new();
@@ -855,12 +1892,14 @@ impl<P: JsonRpcClient> Fetcher<P> {
                 _ => return Err(CollectError::CollectError("invalid trace result".to_string())),
             }
         }
-        Ok((block, txs, diffs))
+        Ok((block, indices, txs, diffs))
     }
 
     async fn permit_request(
         &self,
+        method: &str,
     ) -> Option<::core::result::Result<SemaphorePermit<'_>, AcquireError>> {
+        self.request_counts.increment(method);
         let permit = match &self.semaphore {
             Some(semaphore) => Some(semaphore.acquire().await),
             _ => None,
@@ -872,12 +1911,39 @@ impl<P: JsonRpcClient> Fetcher<P> {
     }
 
     fn map_err<T>(res: ::core::result::Result<T, ProviderError>) -> Result<T> {
-        res.map_err(CollectError::ProviderError)
+        res.map_err(provider_error)
+    }
+
+    /// invalid params and unknown argument errors are reported as missing override support
+    fn map_state_override_err<T>(
+        res: ::core::result::Result<T, ProviderError>,
+        method: &str,
+    ) -> Result<T> {
+        res.map_err(|e| match RpcError::as_error_response(&e) {
+            Some(error)
+                if error.code == -32602 ||
+                    error.message.contains("too many arguments") ||
+                    error.message.contains("unknown field") =>
+            {
+                CollectError::CollectError(format!(
+                    "node does not support state overrides for {} ({})",
+                    method, error.message
+                ))
+            }
+            _ => CollectError::ProviderError(e),
+        })
     }
 }
 
 use crate::err;
-use std::collections::BTreeMap;
+
+fn vm_trace_types(include_call_traces: bool) -> Vec<TraceType> {
+    if include_call_traces {
+        vec![TraceType::VmTrace, TraceType::Trace]
+    } else {
+        vec![TraceType::VmTrace]
+    }
+}
 
 fn parse_geth_diff_object(
     map: ethers::utils::__serde_json::Map<String, ethers::utils::__serde_json::Value>,
@@ -890,3 +1956,74 @@ fn parse_geth_diff_object(
 
     Ok(DiffMode { pre, post })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Token;
+
+    #[test]
+    fn test_aggregate3() {
+        assert_eq!(AGGREGATE3_SELECTOR, ethers::utils::id("aggregate3((address,bool,bytes)[])"));
+
+        let calls = vec![(H160::repeat_byte(1), vec![1, 2]), (H160::repeat_byte(2), vec![])];
+        let encoded = encode_aggregate3(&calls);
+        assert_eq!(encoded[..4], AGGREGATE3_SELECTOR);
+        assert_eq!(encoded.len(), 4 + 32 * 13);
+
+        let result =
+            |success, data: Vec<u8>| Token::Tuple(vec![Token::Bool(success), Token::Bytes(data)]);
+        let output = ethers::abi::encode(&[Token::Array(vec![
+            result(true, vec![0xab; 32]),
+            result(false, vec![0x08, 0xc3, 0x79, 0xa0]),
+        ])]);
+        let expected = vec![Some(Bytes::from(vec![0xab; 32])), None];
+        assert_eq!(decode_aggregate3(&output, 2), Some(expected));
+
+        // outputs that do not cover every call, e.g. from a chain without multicall, are rejected
+        assert_eq!(decode_aggregate3(&output, 3), None);
+        assert_eq!(decode_aggregate3(&[], 2), None);
+    }
+
+    #[test]
+    fn test_block_timestamps() {
+        let block = |number: u64, timestamp: U256| Block::<TxHash> {
+            number: Some(number.into()),
+            timestamp,
+            ..Default::default()
+        };
+        let timestamps = BlockTimestamps::default();
+        timestamps.insert(&block(1, 10.into()));
+        assert!(timestamps.get(&[1]).is_empty());
+
+        let include = Some(vec!["timestamp".to_string()]);
+        let schema = Datatype::Logs
+            .table_schema(
+                &[],
+                &crate::ColumnEncoding::Binary,
+                &include,
+                &None,
+                &None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        timestamps.enable(&[(Datatype::Logs, schema)].into_iter().collect());
+        for (number, timestamp) in [(1, 10.into()), (2, 20.into()), (3, U256::MAX)] {
+            timestamps.insert(&block(number, timestamp));
+        }
+        assert_eq!(timestamps.get(&[1, 2, 3]), [(1, 10), (2, 20)].into_iter().collect());
+        timestamps.release(0..=1);
+        assert_eq!(timestamps.get(&[1, 2]).keys().collect::<Vec<_>>(), vec![&2]);
+    }
+
+    #[test]
+    fn test_beacon_slot() {
+        // mainnet genesis and the first block after the merge
+        assert_eq!(beacon_slot(1663224179, 1606824023, 12), Some(4700013));
+        assert_eq!(beacon_slot(1606824023, 1606824023, 12), Some(0));
+        assert_eq!(beacon_slot(1663224180, 1606824023, 12), None);
+        assert_eq!(beacon_slot(1606824000, 1606824023, 12), None);
+    }
+}
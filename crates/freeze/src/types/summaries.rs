@@ -5,9 +5,11 @@ use colored::Colorize;
 use thousands::Separable;
 
 use crate::{
-    chunks::chunk_ops::ValueToString, err, ChunkData, ChunkStats, CollectError, ColumnType,
-    Datatype, Dim, ExecutionEnv, FileOutput, MetaDatatype, MultiDatatype, Partition, Query, Source,
-    Table,
+    chunks::chunk_ops::ValueToString,
+    err,
+    estimate::{self, DryRunEstimate},
+    ChunkData, ChunkStats, CollectError, ColumnType, Datatype, Dim, ExecutionEnv, FileOutput,
+    MetaDatatype, MultiDatatype, Partition, Query, Source, Table,
 };
 use std::path::PathBuf;
 
@@ -21,12 +23,27 @@ const ERROR_B: u8 = 0;
 /// summary of a freeze
 #[derive(Debug, Default)]
 pub struct FreezeSummary {
-    /// partitions completed
-    pub completed: Vec<Partition>,
-    /// partitions skipped
-    pub skipped: Vec<Partition>,
-    /// partitions errored
-    pub errored: Vec<(Option<Partition>, CollectError)>,
+    /// partitions completed, with their dataset
+    pub completed: Vec<(Partition, MetaDatatype)>,
+    /// partitions skipped, with their dataset
+    pub skipped: Vec<(Partition, MetaDatatype)>,
+    /// partitions errored, with their dataset
+    pub errored: Vec<(Option<(Partition, MetaDatatype)>, CollectError)>,
+    /// partitions not started because the collection was cancelled, with their dataset
+    pub cancelled: Vec<(Partition, MetaDatatype)>,
+    /// datasets not collected because the rpc endpoint does not support a method they need
+    pub unsupported: Vec<UnsupportedDataset>,
+}
+
+/// dataset not collected because the rpc endpoint does not support a method it needs
+#[derive(Debug)]
+pub struct UnsupportedDataset {
+    /// name of dataset
+    pub dataset: String,
+    /// error of the first chunk of the dataset, naming the unsupported method
+    pub error: CollectError,
+    /// partitions of the dataset that were not collected
+    pub partitions: Vec<Partition>,
 }
 
 /// print all datasets
@@ -158,6 +175,7 @@ pub(crate) fn print_cryo_intro(
     sink: &FileOutput,
     env: &ExecutionEnv,
     n_chunks_remaining: u64,
+    output_paths: &[PathBuf],
 ) -> Result<(), CollectError> {
     print_header("cryo parameters");
     print_bullet("version", super::reports::CRYO_VERSION);
@@ -169,6 +187,33 @@ pub(crate) fn print_cryo_intro(
     let align = Some(query.labels.align);
     let reorg_buffer = Some(query.labels.reorg_buffer);
     print_chunks(&query.partitions, align, reorg_buffer);
+    for range in query.labels.timestamp_ranges.iter() {
+        let blocks = match range.start_block == range.end_block {
+            true => format!("block {}", range.start_block.separate_with_commas()),
+            false => format!(
+                "blocks {} to {}",
+                range.start_block.separate_with_commas(),
+                range.end_block.separate_with_commas()
+            ),
+        };
+        print_bullet_indent(format!("timestamps {}", range.input), blocks, 4);
+    }
+    for (index, filter_set) in query.log_filters.iter().flatten().enumerate() {
+        let mut counts = Vec::new();
+        if !filter_set.addresses.is_empty() {
+            counts.push(format!("{} addresses", filter_set.addresses.len()));
+        }
+        for (position, topics) in filter_set.topics.iter().enumerate() {
+            if !topics.is_empty() {
+                counts.push(format!("{} topic{}s", topics.len(), position));
+            }
+        }
+        let counts = if counts.is_empty() { "all logs".to_string() } else { counts.join(", ") };
+        print_bullet_indent(format!("filter set {}", index), counts, 4);
+    }
+    for topic in query.labels.resolved_topics.iter() {
+        print_bullet_indent(format!("{} {}", topic.topic, topic.input), &topic.value, 4);
+    }
 
     if env.verbose > 1 {
         print_bullet_indent("exclude failed items", query.exclude_failed.to_string(), 4);
@@ -176,7 +221,11 @@ pub(crate) fn print_cryo_intro(
 
     print_bullet("source", "");
     print_bullet_indent("network", &sink.prefix, 4);
-    print_bullet_indent("rpc url", &source.rpc_url, 4);
+    let rpc_urls = source.fetcher.provider.as_ref().urls();
+    match rpc_urls.len() {
+        1 => print_bullet_indent("rpc url", &source.rpc_url, 4),
+        _ => print_bullet_indent("rpc urls", rpc_urls.join(", "), 4),
+    }
     match source.labels.max_requests_per_second {
         Some(max_requests_per_second) => print_bullet_indent(
             "max requests per second",
@@ -241,8 +290,28 @@ pub(crate) fn print_cryo_intro(
         (n_datatypes * query.partitions.len()).separate_with_commas()
     );
     print_bullet_indent("chunks to collect", chunk_text, 4);
-    print_bullet_indent("output format", sink.format.as_str(), 4);
+    let time_buckets = &query.labels.time_buckets;
+    if !time_buckets.is_empty() {
+        let n_partial = time_buckets.iter().filter(|bucket| bucket.partial).count();
+        let text = format!("{} buckets, {} partial", time_buckets.len(), n_partial);
+        print_bullet_indent("time buckets", text, 4);
+    }
+    match query.labels.chunk_order.as_str() {
+        "reverse" => print_bullet_indent("chunk order", "newest first", 4),
+        "random" => print_bullet_indent("chunk order", "random", 4),
+        _ => {}
+    }
+    print_bullet_indent("output format", sink.extension(), 4);
     print_bullet_indent("output dir", sink.output_dir.clone().to_string_lossy(), 4);
+    if let Some(store) = &sink.object_store {
+        print_bullet_indent("upload to", store.url(), 4);
+    }
+    if let Some(db) = &sink.duckdb {
+        print_bullet_indent("duckdb database", db.to_string_lossy(), 4);
+    }
+    if let Some(postgres) = &sink.postgres {
+        print_bullet_indent("postgres schema", &postgres.schema, 4);
+    }
 
     // print report path
     let report_path = if env.report && n_chunks_remaining > 0 {
@@ -269,7 +338,10 @@ pub(crate) fn print_cryo_intro(
     print_schemas(&query.datatypes, &query.schemas)?;
 
     if env.dry {
-        println!("\n\n[dry run, exiting]");
+        print_output_files(sink, output_paths);
+        if !estimate::is_estimated(query, env) {
+            println!("\n\n[dry run, exiting]");
+        }
     } else {
         println!();
         println!();
@@ -288,7 +360,7 @@ fn print_chunks(chunks: &[Partition], align: Option<bool>, reorg_buffer: Option<
     }
 
     // NOTE: this branch is not exhaustive
-    for (dim, dim_stats) in vec![
+    for (dim, dim_stats) in [
         (Dim::TransactionHash, stats.transactions),
         (Dim::CallData, stats.call_datas),
         (Dim::Address, stats.addresses),
@@ -351,6 +423,90 @@ fn print_chunk<T: Ord + ValueToString>(
     }
 }
 
+/// number of output files listed by dry runs
+const MAX_LISTED_FILES: usize = 20;
+
+fn print_output_files(sink: &FileOutput, output_paths: &[PathBuf]) {
+    println!();
+    println!();
+    print_header("output files");
+    let mut paths = output_paths.to_vec();
+    paths.sort();
+    for path in paths.iter().take(MAX_LISTED_FILES) {
+        let path = match path.strip_prefix(&sink.output_dir) {
+            Ok(stripped) => PathBuf::from("$OUTPUT_DIR").join(stripped),
+            Err(_) => path.clone(),
+        };
+        print_bullet_key(path.to_string_lossy());
+    }
+    if paths.len() > MAX_LISTED_FILES {
+        println!("...and {} more files", (paths.len() - MAX_LISTED_FILES).separate_with_commas());
+    }
+}
+
+pub(crate) fn print_dry_run_estimate(estimate: &DryRunEstimate) {
+    println!();
+    println!();
+    print_header("estimated collection");
+    print_bullet(
+        "sampled blocks",
+        format!(
+            "{} of {}",
+            estimate.n_samples.separate_with_commas(),
+            estimate.n_blocks.separate_with_commas()
+        ),
+    );
+    println!();
+    println!("{:<24} {:>10} {:>16} {:>12}", "dataset", "files", "rows", "size");
+    println!("{:<24} {:>10} {:>16} {:>12}", "───────", "─────", "────", "────");
+    for dataset in estimate.datasets.iter() {
+        println!(
+            "{:<24} {:>10} {:>16} {:>12}",
+            dataset.datatype.name(),
+            dataset.n_files.separate_with_commas(),
+            dataset.n_rows.separate_with_commas(),
+            format_bytes(dataset.n_bytes),
+        );
+    }
+    println!();
+    let n_requests: u64 = estimate.requests.values().sum();
+    print_bullet("rpc requests", n_requests.separate_with_commas());
+    for (method, count) in estimate.requests.iter() {
+        print_bullet_indent(method, count.separate_with_commas(), 4);
+    }
+    if let Some(latency) = estimate.request_latency {
+        print_bullet("request latency", format!("{:.1}ms", latency.as_secs_f64() * 1000.0));
+    }
+    if let Some(wall_time) = estimate.wall_time {
+        print_bullet("estimated time", format_duration(wall_time.as_secs()));
+    }
+    println!("\n\n[dry run, exiting]");
+}
+
+/// format a number of bytes with binary units, such as 1.5 MB
+pub fn format_bytes(n_bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = n_bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", n_bytes),
+        _ => format!("{:.1} {}", size, units[unit]),
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        3600..=86399 => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+        _ => format!("{}d {}h", seconds / 86400, seconds % 86400 / 3600),
+    }
+}
+
 fn print_schemas(
     datatypes: &[MetaDatatype],
     schemas: &HashMap<Datatype, Table>,
@@ -401,6 +557,7 @@ fn print_schema(name: &Datatype, schema: &Table) {
 pub(crate) fn print_cryo_conclusion(
     freeze_summary: &FreezeSummary,
     query: &Query,
+    source: &Source,
     env: &ExecutionEnv,
 ) {
     let new_env = match env.t_end {
@@ -440,6 +597,50 @@ pub(crate) fn print_cryo_conclusion(
         println!();
     }
 
+    if !freeze_summary.unsupported.is_empty() {
+        print_header_error("unsupported by endpoint");
+        for unsupported in freeze_summary.unsupported.iter() {
+            println!(
+                "- {}: {} ({} chunks)",
+                unsupported.dataset,
+                unsupported.error,
+                unsupported.partitions.len()
+            );
+        }
+        println!();
+        println!();
+    }
+
+    let block_errors = query.block_errors.get();
+    if !block_errors.is_empty() {
+        print_header_error("failed block requests");
+        println!(
+            "(errors in {} requests, their chunks are collected again on the next run)",
+            block_errors.len()
+        );
+        for block_error in block_errors.iter().take(10) {
+            println!(
+                "- {} block {}: {}",
+                block_error.dataset, block_error.block_number, block_error.error
+            );
+        }
+        if block_errors.len() > 10 {
+            println!("...")
+        }
+        println!();
+        println!();
+    }
+
+    let warnings = query.warnings.counts();
+    if !warnings.is_empty() {
+        print_header_error("warning summary");
+        for (warning, count) in warnings.iter() {
+            println!("- {} ({}x)", warning, count);
+        }
+        println!();
+        println!();
+    }
+
     let duration = match t_end.duration_since(env.t_start) {
         Ok(duration) => duration,
         Err(_e) => {
@@ -480,6 +681,34 @@ pub(crate) fn print_cryo_conclusion(
         ),
         4,
     );
+    if !freeze_summary.cancelled.is_empty() {
+        print_bullet_indent(
+            "chunks cancelled",
+            format!(
+                "{:>width$} / {} ({}%)",
+                freeze_summary.cancelled.len().separate_with_commas(),
+                n_chunks_str,
+                format_float((100 * freeze_summary.cancelled.len() / n_chunks) as f64),
+                width = width
+            ),
+            4,
+        );
+    }
+    let n_unsupported: usize =
+        freeze_summary.unsupported.iter().map(|unsupported| unsupported.partitions.len()).sum();
+    if n_unsupported > 0 {
+        print_bullet_indent(
+            "chunks unsupported",
+            format!(
+                "{:>width$} / {} ({}%)",
+                n_unsupported.separate_with_commas(),
+                n_chunks_str,
+                format_float((100 * n_unsupported / n_chunks) as f64),
+                width = width
+            ),
+            4,
+        );
+    }
     print_bullet_indent(
         "chunks collected",
         format!(
@@ -492,8 +721,95 @@ pub(crate) fn print_cryo_conclusion(
         4,
     );
 
+    let rows = env.counts.rows();
+    if !rows.is_empty() {
+        print_bullet("rows written", rows.values().sum::<u64>().separate_with_commas());
+        if rows.len() > 1 {
+            for (datatype, n_rows) in rows.iter() {
+                print_bullet_indent(datatype, n_rows.separate_with_commas(), 4);
+            }
+        }
+        print_bullet("bytes written", format_bytes(env.counts.n_bytes()));
+    }
+
+    let endpoints = source.endpoint_stats();
+    if endpoints.len() > 1 {
+        print_bullet("rpc endpoints", endpoints.len().to_string());
+        for endpoint in endpoints.iter() {
+            print_bullet_indent(
+                &endpoint.url,
+                format!(
+                    "{} requests, {} errors ({}%), {} rate limited, {} quarantines, {:.0}ms mean \
+                     latency",
+                    endpoint.requests.separate_with_commas(),
+                    endpoint.errors.separate_with_commas(),
+                    format_float(endpoint.error_rate()),
+                    endpoint.rate_limited.separate_with_commas(),
+                    endpoint.quarantines.separate_with_commas(),
+                    endpoint.latency.mean_ms(),
+                ),
+                4,
+            );
+        }
+    }
+
+    if let Some((hits, misses)) = source.cache_hits_and_misses() {
+        print_bullet(
+            "rpc cache",
+            format!(
+                "{} hits, {} misses",
+                hits.separate_with_commas(),
+                misses.separate_with_commas()
+            ),
+        );
+    }
+
+    let shared = source.fetcher.shared_fetches.hits();
+    if !shared.is_empty() {
+        let n_shared = shared.values().sum::<u64>().separate_with_commas();
+        print_bullet("shared rpc responses", n_shared);
+        for (method, count) in shared.iter() {
+            print_bullet_indent(method, count.separate_with_commas(), 4);
+        }
+    }
+
+    let errors = source.error_counts();
+    if !errors.is_empty() {
+        print_bullet("rpc errors", errors.values().sum::<u64>().separate_with_commas());
+        for (class, count) in errors.iter() {
+            print_bullet_indent(class, count.separate_with_commas(), 4);
+        }
+    }
+
+    let retries = source.retry_counts();
+    if !retries.is_empty() {
+        print_bullet("rpc retries", retries.values().sum::<u64>().separate_with_commas());
+        for (method, count) in retries.iter() {
+            print_bullet_indent(method, count.separate_with_commas(), 4);
+        }
+    }
+
+    if let Some(state) = source.autoscale_state() {
+        print_bullet(
+            "autoscaled concurrency",
+            format!(
+                "{} requests (max {}), {} decreases",
+                state.concurrency, state.max_concurrency, state.decreases
+            ),
+        );
+        print_bullet_indent(
+            "settled values",
+            format!(
+                "--max-concurrent-requests {} --requests-per-second {}",
+                state.concurrency,
+                (state.requests_per_second as u64).max(1)
+            ),
+            4,
+        );
+    }
+
     print_chunks_speeds(
-        freeze_summary.completed.clone(),
+        freeze_summary.completed.iter().map(|(partition, _)| partition.clone()).collect(),
         &query.partitioned_by,
         total_time,
         query.datatypes.len() as u64,
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+
+use ethers::prelude::*;
+
+use crate::{err, CollectError, CreationMethods};
+
+/// rpc namespace that traces and state diffs are collected from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceBackend {
+    /// use `trace_` methods when the node has them, otherwise `debug_` methods
+    #[default]
+    Auto,
+    /// parity `trace_` methods, such as `trace_block`
+    Parity,
+    /// geth `debug_` methods, such as `debug_traceBlockByNumber`
+    Geth,
+}
+
+impl std::str::FromStr for TraceBackend {
+    type Err = CollectError;
+
+    fn from_str(s: &str) -> Result<TraceBackend, CollectError> {
+        match s {
+            "auto" => Ok(TraceBackend::Auto),
+            "parity" => Ok(TraceBackend::Parity),
+            "geth" => Ok(TraceBackend::Geth),
+            _ => Err(err(&format!("invalid trace backend {}, use auto, parity, or geth", s))),
+        }
+    }
+}
+
+/// convert geth callTracer frames of transactions into parity style traces, in the order that
+/// trace_block returns them
+pub(crate) fn call_frames_to_traces(
+    frames: Vec<CallFrame>,
+    transaction_hashes: &[H256],
+    transaction_index: Option<usize>,
+    block_number: u64,
+    block_hash: H256,
+) -> Result<Vec<Trace>, CollectError> {
+    let mut traces = Vec::new();
+    for (index, (frame, transaction_hash)) in frames.into_iter().zip(transaction_hashes).enumerate()
+    {
+        let template = Trace {
+            action: Action::Call(ethers::types::Call::default()),
+            result: None,
+            trace_address: Vec::new(),
+            subtraces: 0,
+            transaction_position: Some(transaction_index.unwrap_or(index)),
+            transaction_hash: Some(*transaction_hash),
+            block_number,
+            block_hash,
+            action_type: ActionType::Call,
+            error: None,
+        };
+        flatten_call_frame(frame, Vec::new(), &template, &mut traces)?;
+    }
+    Ok(traces)
+}
+
+/// creation method of each create frame of the geth callTracer frames of transactions, keyed like
+/// the parity style traces that the frames are converted into
+pub(crate) fn call_frames_creation_methods(
+    frames: &[CallFrame],
+    transaction_index: Option<usize>,
+) -> CreationMethods {
+    fn visit(
+        frame: &CallFrame,
+        position: usize,
+        trace_address: Vec<usize>,
+        creation_methods: &mut CreationMethods,
+    ) {
+        if let "CREATE" | "CREATE2" = frame.typ.as_str() {
+            let key = (Some(position), trace_address.clone());
+            creation_methods.insert(key, frame.typ.to_lowercase());
+        }
+        for (index, subcall) in frame.calls.iter().flatten().enumerate() {
+            let mut subcall_address = trace_address.clone();
+            subcall_address.push(index);
+            visit(subcall, position, subcall_address, creation_methods);
+        }
+    }
+    let mut creation_methods = CreationMethods::new();
+    for (index, frame) in frames.iter().enumerate() {
+        visit(frame, transaction_index.unwrap_or(index), Vec::new(), &mut creation_methods);
+    }
+    creation_methods
+}
+
+fn flatten_call_frame(
+    mut frame: CallFrame,
+    trace_address: Vec<usize>,
+    template: &Trace,
+    traces: &mut Vec<Trace>,
+) -> Result<(), CollectError> {
+    let subcalls = frame.calls.take().unwrap_or_default();
+    let to = match frame.to {
+        Some(NameOrAddress::Address(address)) => Some(address),
+        Some(NameOrAddress::Name(_)) => return Err(err("name not allowed as call recipient")),
+        None => None,
+    };
+    let value = frame.value.unwrap_or_default();
+    // parity leaves the result of failed calls empty, unless they have output
+    let failed = frame.error.is_some() && frame.output.is_none();
+    let output = frame.output.unwrap_or_default();
+    let (action, result, action_type) = match frame.typ.as_str() {
+        "CREATE" | "CREATE2" => {
+            let action =
+                Create { from: frame.from, value, gas: frame.gas, init: frame.input.clone() };
+            let result = match to {
+                Some(address) if !failed => Some(Res::Create(CreateResult {
+                    gas_used: frame.gas_used,
+                    code: output,
+                    address,
+                })),
+                _ => None,
+            };
+            (Action::Create(action), result, ActionType::Create)
+        }
+        "SELFDESTRUCT" => {
+            let action = Suicide {
+                address: frame.from,
+                refund_address: to.unwrap_or_default(),
+                balance: value,
+            };
+            (Action::Suicide(action), None, ActionType::Suicide)
+        }
+        typ => {
+            let call_type = match typ {
+                "CALL" => CallType::Call,
+                "CALLCODE" => CallType::CallCode,
+                "DELEGATECALL" => CallType::DelegateCall,
+                "STATICCALL" => CallType::StaticCall,
+                _ => CallType::None,
+            };
+            let action = ethers::types::Call {
+                from: frame.from,
+                to: to.unwrap_or_default(),
+                value,
+                gas: frame.gas,
+                input: frame.input.clone(),
+                call_type,
+            };
+            let result = match failed {
+                true => None,
+                false => Some(Res::Call(CallResult { gas_used: frame.gas_used, output })),
+            };
+            (Action::Call(action), result, ActionType::Call)
+        }
+    };
+    traces.push(Trace {
+        action,
+        result,
+        trace_address: trace_address.clone(),
+        subtraces: subcalls.len(),
+        action_type,
+        error: frame.error,
+        ..template.clone()
+    });
+    for (index, subcall) in subcalls.into_iter().enumerate() {
+        let mut subcall_address = trace_address.clone();
+        subcall_address.push(index);
+        flatten_call_frame(subcall, subcall_address, template, traces)?;
+    }
+    Ok(())
+}
+
+/// convert a geth prestateTracer diff into a parity style state diff
+///
+/// in diff mode, accounts only in pre were deleted, accounts only in post were created, and post
+/// only has the fields and slots that changed
+pub(crate) fn diff_mode_to_block_trace(diff: DiffMode) -> Result<BlockTrace, CollectError> {
+    let mut accounts = BTreeMap::new();
+    for address in diff.pre.keys().chain(diff.post.keys()) {
+        if accounts.contains_key(address) {
+            continue
+        }
+        let account = match (diff.pre.get(address), diff.post.get(address)) {
+            (Some(pre), Some(post)) => AccountDiff {
+                balance: changed_diff(pre.balance, post.balance),
+                nonce: changed_diff(pre.nonce, post.nonce),
+                code: changed_diff(parse_code(&pre.code)?, parse_code(&post.code)?),
+                storage: changed_storage(&pre.storage, &post.storage),
+            },
+            (Some(pre), None) => AccountDiff {
+                balance: Diff::Died(pre.balance.unwrap_or_default()),
+                nonce: Diff::Died(pre.nonce.unwrap_or_default()),
+                code: Diff::Died(parse_code(&pre.code)?.unwrap_or_default()),
+                storage: slots(&pre.storage)
+                    .map(|(slot, value)| (slot, Diff::Died(value)))
+                    .collect(),
+            },
+            (None, Some(post)) => AccountDiff {
+                balance: Diff::Born(post.balance.unwrap_or_default()),
+                nonce: Diff::Born(post.nonce.unwrap_or_default()),
+                code: Diff::Born(parse_code(&post.code)?.unwrap_or_default()),
+                storage: slots(&post.storage)
+                    .map(|(slot, value)| (slot, Diff::Born(value)))
+                    .collect(),
+            },
+            (None, None) => continue,
+        };
+        accounts.insert(*address, account);
+    }
+    Ok(BlockTrace {
+        output: Bytes::new(),
+        trace: None,
+        vm_trace: None,
+        state_diff: Some(StateDiff(accounts)),
+        transaction_hash: None,
+    })
+}
+
+/// diff of a field of an account that exists before and after, post omits unchanged fields
+fn changed_diff<T: Default + PartialEq>(pre: Option<T>, post: Option<T>) -> Diff<T> {
+    match (pre, post) {
+        (_, None) => Diff::Same,
+        (pre, Some(post)) => {
+            let from = pre.unwrap_or_default();
+            if from == post {
+                Diff::Same
+            } else {
+                Diff::Changed(ChangedType { from, to: post })
+            }
+        }
+    }
+}
+
+/// diff of the slots of an account that exists before and after, post omits cleared slots
+fn changed_storage(
+    pre: &Option<BTreeMap<H256, H256>>,
+    post: &Option<BTreeMap<H256, H256>>,
+) -> BTreeMap<H256, Diff<H256>> {
+    let mut storage = BTreeMap::new();
+    for (slot, from) in slots(pre) {
+        let to = post.as_ref().and_then(|post| post.get(&slot)).copied().unwrap_or_default();
+        if from != to {
+            storage.insert(slot, Diff::Changed(ChangedType { from, to }));
+        }
+    }
+    for (slot, to) in slots(post) {
+        storage.entry(slot).or_insert(Diff::Changed(ChangedType { from: H256::zero(), to }));
+    }
+    storage
+}
+
+fn slots(storage: &Option<BTreeMap<H256, H256>>) -> impl Iterator<Item = (H256, H256)> + '_ {
+    storage.iter().flatten().map(|(slot, value)| (*slot, *value))
+}
+
+fn parse_code(code: &Option<String>) -> Result<Option<Bytes>, CollectError> {
+    match code {
+        Some(code) => {
+            code.parse::<Bytes>().map(Some).map_err(|_| err("could not decode code contents"))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call_frame(typ: &str, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            typ: typ.to_string(),
+            from: Address::repeat_byte(1),
+            to: Some(NameOrAddress::Address(Address::repeat_byte(2))),
+            value: Some(7.into()),
+            gas: 100.into(),
+            gas_used: 50.into(),
+            output: Some(vec![1, 2].into()),
+            calls: Some(calls),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_call_frames_to_traces() {
+        let reverted = CallFrame {
+            error: Some("execution reverted".to_string()),
+            output: None,
+            ..call_frame("STATICCALL", vec![])
+        };
+        let nested = call_frame("DELEGATECALL", vec![reverted, call_frame("SELFDESTRUCT", vec![])]);
+        let frames = vec![call_frame("CREATE", vec![]), call_frame("CALL", vec![nested])];
+        let hashes = [H256::repeat_byte(3), H256::repeat_byte(4)];
+        let traces =
+            call_frames_to_traces(frames, &hashes, None, 10, H256::repeat_byte(5)).unwrap();
+
+        let addresses: Vec<_> = traces.iter().map(|trace| trace.trace_address.clone()).collect();
+        assert_eq!(addresses, vec![vec![], vec![], vec![0], vec![0, 0], vec![0, 1]]);
+        let subtraces: Vec<_> = traces.iter().map(|trace| trace.subtraces).collect();
+        assert_eq!(subtraces, vec![0, 1, 2, 0, 0]);
+        assert_eq!(traces[0].transaction_position, Some(0));
+        assert_eq!(traces[2].transaction_position, Some(1));
+        assert_eq!(traces[2].transaction_hash, Some(H256::repeat_byte(4)));
+        assert_eq!(traces[2].block_hash, H256::repeat_byte(5));
+
+        let create = CreateResult {
+            gas_used: 50.into(),
+            code: vec![1, 2].into(),
+            address: Address::repeat_byte(2),
+        };
+        assert_eq!(traces[0].result, Some(Res::Create(create)));
+        assert_eq!(traces[0].action_type, ActionType::Create);
+        match &traces[2].action {
+            Action::Call(call) => assert_eq!(call.call_type, CallType::DelegateCall),
+            action => panic!("unexpected action {:?}", action),
+        }
+        assert_eq!(traces[3].result, None);
+        assert_eq!(traces[3].error, Some("execution reverted".to_string()));
+        let suicide = Suicide {
+            address: Address::repeat_byte(1),
+            refund_address: Address::repeat_byte(2),
+            balance: 7.into(),
+        };
+        assert_eq!(traces[4].action, Action::Suicide(suicide));
+    }
+
+    #[test]
+    fn test_call_frames_creation_methods() {
+        let nested =
+            call_frame("CALL", vec![call_frame("CALL", vec![]), call_frame("CREATE2", vec![])]);
+        let frames = vec![call_frame("CREATE", vec![]), nested];
+        let creation_methods = call_frames_creation_methods(&frames, None);
+        let expected = [
+            ((Some(0), vec![]), "create".to_string()),
+            ((Some(1), vec![1]), "create2".to_string()),
+        ];
+        assert_eq!(creation_methods, expected.into_iter().collect());
+        let creation_methods = call_frames_creation_methods(&frames[..1], Some(4));
+        assert_eq!(creation_methods.keys().collect::<Vec<_>>(), vec![&(Some(4), vec![])]);
+    }
+
+    #[test]
+    fn test_diff_mode_to_block_trace() {
+        let account =
+            |balance: Option<u64>, nonce: Option<u64>, storage: Vec<(u8, u8)>| AccountState {
+                balance: balance.map(U256::from),
+                nonce: nonce.map(U256::from),
+                code: None,
+                storage: Some(
+                    storage
+                        .into_iter()
+                        .map(|(slot, value)| (H256::repeat_byte(slot), H256::repeat_byte(value)))
+                        .collect(),
+                ),
+            };
+        let (changed, created, deleted) =
+            (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let diff = DiffMode {
+            pre: [
+                (changed, account(Some(10), Some(1), vec![(1, 1), (2, 2)])),
+                (deleted, account(Some(5), Some(1), vec![])),
+            ]
+            .into_iter()
+            .collect(),
+            post: [
+                (changed, account(Some(8), None, vec![(1, 3)])),
+                (created, account(Some(3), Some(1), vec![])),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let trace = diff_mode_to_block_trace(diff).unwrap();
+        let StateDiff(accounts) = trace.state_diff.unwrap();
+
+        let changed = &accounts[&changed];
+        assert_eq!(changed.balance, Diff::Changed(ChangedType { from: 10.into(), to: 8.into() }));
+        assert_eq!(changed.nonce, Diff::Same);
+        assert_eq!(changed.code, Diff::Same);
+        let expected = [
+            (H256::repeat_byte(1), H256::repeat_byte(1), H256::repeat_byte(3)),
+            (H256::repeat_byte(2), H256::repeat_byte(2), H256::zero()),
+        ]
+        .into_iter()
+        .map(|(slot, from, to)| (slot, Diff::Changed(ChangedType { from, to })))
+        .collect::<BTreeMap<_, _>>();
+        assert_eq!(changed.storage, expected);
+        assert_eq!(accounts[&created].balance, Diff::Born(3.into()));
+        assert_eq!(accounts[&deleted].balance, Diff::Died(5.into()));
+    }
+}
@@ -0,0 +1,235 @@
+use std::{fmt::Debug, path::Path, sync::Mutex, time::Duration};
+
+use ethers::prelude::*;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::CollectError;
+
+/// number of times a dropped websocket or ipc connection is re-established
+const MAX_RECONNECTS: usize = 5;
+
+/// time waited before re-establishing a dropped ipc connection
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// whether an rpc url is the path of an ipc socket
+pub fn is_ipc_path(url: &str) -> bool {
+    url.ends_with(".ipc") || Path::new(url).is_absolute()
+}
+
+/// rpc url with a scheme, urls without one are http urls
+pub fn normalize_rpc_url(url: &str) -> String {
+    if url.starts_with("http") || url.starts_with("ws") || is_ipc_path(url) {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    }
+}
+
+/// connection to an rpc endpoint
+#[derive(Debug)]
+pub(crate) enum Transport {
+    Http(RetryClient<Http>),
+    /// websocket clients are kept alongside their retrying client for subscriptions
+    Ws(RetryClient<Ws>, Ws),
+    Ipc(RetryClient<ReconnectingIpc>),
+}
+
+/// retrying client that turns rate limits into timeout errors without retrying them, retries are
+/// instead left to the retry options of the endpoint pool
+fn retry_client<T>(client: T, policy: Box<dyn RetryPolicy<T::Error>>) -> RetryClient<T>
+where
+    T: JsonRpcClient,
+    T::Error: Sync + Send + 'static,
+{
+    RetryClientBuilder::default().rate_limit_retries(0).timeout_retries(0).build(client, policy)
+}
+
+impl Transport {
+    /// connect to an http, websocket, or ipc endpoint, http requests are sent with the client
+    pub(crate) async fn connect(
+        url: &str,
+        client: &reqwest::Client,
+        headers: &HeaderMap,
+    ) -> Result<Transport, CollectError> {
+        let connect_error = |_| CollectError::RPCError(format!("could not connect to {}", url));
+        if url.starts_with("ws://") || url.starts_with("wss://") {
+            let connection = ConnectionDetails::new(url, ws_authorization(headers)?);
+            let ws = Ws::connect_with_reconnects(connection, MAX_RECONNECTS)
+                .await
+                .map_err(connect_error)?;
+            Ok(Transport::Ws(retry_client(ws.clone(), Box::new(RateLimitRetryPolicy)), ws))
+        } else if is_ipc_path(url) {
+            let ipc = ReconnectingIpc::connect(url).await?;
+            Ok(Transport::Ipc(retry_client(ipc, Box::new(RateLimitRetryPolicy))))
+        } else {
+            let url = url
+                .parse::<reqwest::Url>()
+                .map_err(|_| CollectError::RPCError(format!("invalid rpc url: {}", url)))?;
+            let http = Http::new_with_client(url, client.clone());
+            Ok(Transport::Http(retry_client(http, Box::new(HttpRateLimitRetryPolicy))))
+        }
+    }
+
+    pub(crate) async fn request<R: DeserializeOwned + Send>(
+        &self,
+        method: &str,
+        params: &Option<serde_json::Value>,
+    ) -> Result<R, RetryClientError> {
+        match self {
+            Transport::Http(client) => send(client, method, params).await,
+            Transport::Ws(client, _) => send(client, method, params).await,
+            Transport::Ipc(client) => send(client, method, params).await,
+        }
+    }
+}
+
+/// authorization of websocket handshakes, which cannot send other headers
+fn ws_authorization(headers: &HeaderMap) -> Result<Option<Authorization>, CollectError> {
+    if headers.keys().any(|name| name != AUTHORIZATION) {
+        return Err(CollectError::RPCError(
+            "websocket rpc urls only support the authorization header".to_string(),
+        ))
+    }
+    match headers.get(AUTHORIZATION).map(|value| value.to_str()) {
+        Some(Ok(value)) => Ok(Some(Authorization::raw(value))),
+        Some(Err(_)) => Err(CollectError::RPCError("invalid authorization header".to_string())),
+        None => Ok(None),
+    }
+}
+
+/// send request, params are omitted if there are none
+async fn send<C, R>(
+    client: &C,
+    method: &str,
+    params: &Option<serde_json::Value>,
+) -> Result<R, C::Error>
+where
+    C: JsonRpcClient,
+    R: DeserializeOwned + Send,
+{
+    match params {
+        Some(params) => client.request(method, params).await,
+        None => client.request(method, ()).await,
+    }
+}
+
+/// retry policy of websocket and ipc clients, which retries the same json-rpc errors as http
+#[derive(Debug, Default)]
+struct RateLimitRetryPolicy;
+
+impl<E: RpcError> RetryPolicy<E> for RateLimitRetryPolicy {
+    fn should_retry(&self, error: &E) -> bool {
+        match error.as_error_response() {
+            Some(error) => {
+                HttpRateLimitRetryPolicy.should_retry(&HttpClientError::JsonRpcError(error.clone()))
+            }
+            None => false,
+        }
+    }
+
+    fn backoff_hint(&self, error: &E) -> Option<Duration> {
+        let error = HttpClientError::JsonRpcError(error.as_error_response()?.clone());
+        HttpRateLimitRetryPolicy.backoff_hint(&error)
+    }
+}
+
+/// ipc client that reconnects when its connection to the node is lost, sending the requests that
+/// were in flight again
+#[derive(Debug)]
+pub struct ReconnectingIpc {
+    path: String,
+    /// current connection, with the number of times it has been re-established
+    ipc: Mutex<(usize, Ipc)>,
+    reconnecting: tokio::sync::Mutex<()>,
+}
+
+impl ReconnectingIpc {
+    /// connect to the ipc socket of a node
+    pub async fn connect(path: &str) -> Result<ReconnectingIpc, CollectError> {
+        let ipc = Ipc::connect(path)
+            .await
+            .map_err(|_| CollectError::RPCError(format!("could not connect to {}", path)))?;
+        Ok(ReconnectingIpc {
+            path: path.to_string(),
+            ipc: Mutex::new((0, ipc)),
+            reconnecting: Default::default(),
+        })
+    }
+
+    fn client(&self) -> Result<(usize, Ipc), IpcError> {
+        self.ipc
+            .lock()
+            .map(|ipc| ipc.clone())
+            .map_err(|_| IpcError::ChannelError("ipc client poisoned".to_string()))
+    }
+
+    /// replace a dropped connection, unless another request has already replaced it
+    async fn reconnect(&self, dropped: usize) -> Result<(), IpcError> {
+        let _reconnecting = self.reconnecting.lock().await;
+        if self.client()?.0 != dropped {
+            return Ok(())
+        }
+        tokio::time::sleep(RECONNECT_BACKOFF).await;
+        let ipc = Ipc::connect(&self.path).await?;
+        if let Ok(mut current) = self.ipc.lock() {
+            *current = (dropped + 1, ipc);
+        }
+        Ok(())
+    }
+}
+
+/// whether an ipc error was caused by losing the connection to the node
+fn is_disconnect(error: &IpcError) -> bool {
+    matches!(
+        error,
+        IpcError::IoError(_) |
+            IpcError::ChannelError(_) |
+            IpcError::RequestCancelled(_) |
+            IpcError::ServerExit
+    )
+}
+
+#[async_trait::async_trait]
+impl JsonRpcClient for ReconnectingIpc {
+    type Error = IpcError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = match std::mem::size_of::<T>() {
+            0 => None,
+            _ => Some(serde_json::to_value(params)?),
+        };
+        let mut reconnects = 0;
+        loop {
+            let (generation, ipc) = self.client()?;
+            match send(&ipc, method, &params).await {
+                Err(error) if is_disconnect(&error) && reconnects < MAX_RECONNECTS => {
+                    reconnects += 1;
+                    // requests are sent again once the connection is replaced
+                    if self.reconnect(generation).await.is_err() && reconnects == MAX_RECONNECTS {
+                        return Err(error)
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_rpc_url() {
+        assert_eq!(normalize_rpc_url("localhost:8545"), "http://localhost:8545");
+        assert_eq!(normalize_rpc_url("https://eth.example.com"), "https://eth.example.com");
+        assert_eq!(normalize_rpc_url("wss://eth.example.com"), "wss://eth.example.com");
+        assert_eq!(normalize_rpc_url("/data/geth.ipc"), "/data/geth.ipc");
+        assert_eq!(normalize_rpc_url("geth.ipc"), "geth.ipc");
+    }
+}
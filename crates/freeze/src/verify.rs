@@ -0,0 +1,322 @@
+use crate::{
+    coverage::list_range_files, dataframes, err, ChunkData, CollectError, Datatype, Partition,
+    Query, SchemaFunctions, Source,
+};
+use ethers::types::H256;
+use polars::prelude::*;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// names of the block_number and block_hash columns of files that are not renamed
+const BLOCK_COLUMNS: (&str, &str) = ("block_number", "block_hash");
+
+/// number of mismatched blocks listed by verification errors
+const MAX_LISTED_MISMATCHES: usize = 10;
+
+/// how block hash mismatches found during collection are handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashVerification {
+    /// fail chunks containing mismatched block hashes
+    Error,
+    /// count a warning for chunks containing mismatched block hashes
+    Warn,
+}
+
+/// outcome of checking a collected file against the canonical chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CanonicalCheck {
+    /// blocks of file are canonical
+    Canonical(PathBuf),
+    /// file contains a block that is no longer canonical, the first such block is given
+    Orphaned(PathBuf, u64),
+    /// file has no recorded block hashes
+    Unverified(PathBuf),
+}
+
+/// block header fields used to verify hash continuity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BlockHeader {
+    pub(crate) number: u64,
+    pub(crate) hash: H256,
+    pub(crate) parent_hash: H256,
+}
+
+/// headers at the boundaries of verified chunks, shared by all chunks of a query
+#[derive(Clone, Debug, Default)]
+pub struct BlockHashes(Arc<Mutex<BTreeMap<u64, BlockHeader>>>);
+
+impl BlockHashes {
+    /// record the first and last headers of a chunk, returning blocks that do not connect to
+    /// recorded neighbors
+    pub(crate) fn connect(
+        &self,
+        first: &BlockHeader,
+        last: &BlockHeader,
+    ) -> Result<Vec<u64>, CollectError> {
+        let mut headers = self.0.lock().map_err(|_| err("block hashes poisoned"))?;
+        let mut mismatches = Vec::new();
+        if let Some(previous) = first.number.checked_sub(1).and_then(|n| headers.get(&n)) {
+            if previous.hash != first.parent_hash {
+                mismatches.push(first.number);
+            }
+        }
+        if let Some(next) = headers.get(&(last.number + 1)) {
+            if next.parent_hash != last.hash {
+                mismatches.push(next.number);
+            }
+        }
+        headers.insert(first.number, *first);
+        headers.insert(last.number, *last);
+        Ok(mismatches)
+    }
+}
+
+/// check that the blocks of a chunk link to each other and to neighboring chunks, and that the
+/// rows of its dataframes belong to those blocks, returning the hash of the last block
+pub(crate) async fn verify_block_hashes(
+    partition: &Partition,
+    dfs: &HashMap<Datatype, DataFrame>,
+    query: &Query,
+    source: &Source,
+    mode: HashVerification,
+) -> Result<Option<H256>, CollectError> {
+    let block_numbers: BTreeSet<u64> = match &partition.block_numbers {
+        Some(chunks) => chunks.values().into_iter().collect(),
+        None => return Ok(None),
+    };
+    let headers = fetch_headers(source, block_numbers.into_iter().collect()).await?;
+    let (first, last) = match (headers.first(), headers.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Ok(None),
+    };
+
+    let mut mismatches: Vec<u64> = headers
+        .windows(2)
+        .filter(|pair| pair[1].number == pair[0].number + 1 && pair[1].parent_hash != pair[0].hash)
+        .map(|pair| pair[1].number)
+        .collect();
+    mismatches.extend(query.block_hashes.connect(first, last)?);
+    let hashes: HashMap<u64, H256> =
+        headers.iter().map(|header| (header.number, header.hash)).collect();
+    for (datatype, df) in dfs.iter() {
+        let schema = query.schemas.get_schema(datatype)?;
+        let columns = (schema.output_name("block_number"), schema.output_name("block_hash"));
+        for (number, hash) in row_block_hashes(df, columns)?.into_iter() {
+            if hashes.get(&number).map(|header_hash| header_hash != &hash).unwrap_or(false) {
+                mismatches.push(number);
+            }
+        }
+    }
+    if mismatches.is_empty() {
+        return Ok(Some(last.hash))
+    }
+
+    mismatches.sort();
+    mismatches.dedup();
+    let listed: Vec<String> =
+        mismatches.iter().take(MAX_LISTED_MISMATCHES).map(|n| n.to_string()).collect();
+    match mode {
+        HashVerification::Error => Err(err(&format!(
+            "block hashes do not link at blocks {}, blocks may have been reorged during collection",
+            listed.join(", ")
+        ))),
+        HashVerification::Warn => {
+            query.warnings.increment("block hashes do not link, blocks may have been reorged");
+            Ok(None)
+        }
+    }
+}
+
+/// headers of blocks, sorted by block number
+async fn fetch_headers(
+    source: &Source,
+    block_numbers: Vec<u64>,
+) -> Result<Vec<BlockHeader>, CollectError> {
+    let blocks = futures::future::try_join_all(
+        block_numbers.iter().map(|number| source.fetcher.get_block(*number)),
+    )
+    .await?;
+    block_numbers
+        .into_iter()
+        .zip(blocks)
+        .map(|(number, block)| match block {
+            Some(ethers::types::Block { hash: Some(hash), parent_hash, .. }) => {
+                Ok(BlockHeader { number, hash, parent_hash })
+            }
+            _ => Err(err(&format!("could not fetch header of block {}", number))),
+        })
+        .collect()
+}
+
+/// distinct block numbers and block hashes of the rows of a dataframe, given the names of the
+/// block_number and block_hash columns
+fn row_block_hashes(
+    df: &DataFrame,
+    (number_column, hash_column): (&str, &str),
+) -> Result<BTreeSet<(u64, H256)>, CollectError> {
+    let (numbers, hashes) = match (df.column(number_column), df.column(hash_column)) {
+        (Ok(numbers), Ok(hashes)) => (numbers, hashes),
+        _ => return Ok(BTreeSet::new()),
+    };
+    let numbers = numbers.cast(&DataType::UInt64).map_err(|_| err("invalid block_number"))?;
+    let numbers = numbers.u64().map_err(|_| err("invalid block_number"))?;
+    let hashes: Vec<Option<Vec<u8>>> = match hashes.dtype() {
+        DataType::Binary => hashes
+            .binary()
+            .map_err(|_| err("invalid block_hash"))?
+            .into_iter()
+            .map(|hash| hash.map(|hash| hash.to_vec()))
+            .collect(),
+        DataType::Utf8 => hashes
+            .utf8()
+            .map_err(|_| err("invalid block_hash"))?
+            .into_iter()
+            .map(|hash| hash.and_then(|hash| hex::decode(hash.trim_start_matches("0x")).ok()))
+            .collect(),
+        _ => return Ok(BTreeSet::new()),
+    };
+    Ok(numbers
+        .into_iter()
+        .zip(hashes)
+        .filter_map(|(number, hash)| match (number, hash) {
+            (Some(number), Some(hash)) if hash.len() == 32 => {
+                Some((number, H256::from_slice(&hash)))
+            }
+            _ => None,
+        })
+        .collect())
+}
+
+/// compare the blocks of each collected file of a directory with the canonical chain
+///
+/// every distinct block number and hash of the block_hash column of parquet files is checked,
+/// files without the column are checked by the max_block_hash metadata recorded when collecting
+/// with hash verification, since every block commits to its parent, a canonical last block
+/// implies that the earlier blocks of a verified file are canonical
+pub async fn verify_canonical(
+    dir: &Path,
+    source: &Source,
+) -> Result<Vec<CanonicalCheck>, CollectError> {
+    let mut files = list_range_files(dir);
+    files.retain(|file| {
+        !file.extension.ends_with("meta.json") && !file.extension.ends_with("empty")
+    });
+    files.sort_by(|a, b| (&a.group, a.start).cmp(&(&b.group, b.start)));
+    let mut checks = Vec::new();
+    for file in files.into_iter() {
+        let block_hashes = match read_block_hashes(&file.path)? {
+            Some(block_hashes) => block_hashes,
+            None => {
+                checks.push(CanonicalCheck::Unverified(file.path));
+                continue
+            }
+        };
+        let block_numbers: BTreeSet<u64> = block_hashes.iter().map(|(number, _)| *number).collect();
+        let canonical = fetch_headers(source, block_numbers.into_iter().collect()).await?;
+        let canonical: HashMap<u64, H256> =
+            canonical.into_iter().map(|header| (header.number, header.hash)).collect();
+        let orphaned = block_hashes
+            .iter()
+            .find(|(number, hash)| canonical.get(number) != Some(hash))
+            .map(|(number, _)| *number);
+        match orphaned {
+            Some(number) => checks.push(CanonicalCheck::Orphaned(file.path, number)),
+            None => checks.push(CanonicalCheck::Canonical(file.path)),
+        }
+    }
+    Ok(checks)
+}
+
+/// distinct block numbers and hashes of the rows of a file, or the number and hash of its last
+/// block if only recorded in its metadata
+fn read_block_hashes(path: &Path) -> Result<Option<BTreeSet<(u64, H256)>>, CollectError> {
+    if path.extension().and_then(|extension| extension.to_str()) == Some("parquet") {
+        let message = format!("could not read block hashes of {}", path.display());
+        let file = std::fs::File::open(path).map_err(|_| err(&message))?;
+        let schema = ParquetReader::new(file).schema().map_err(|_| err(&message))?;
+        let has_column = |name: &str| schema.fields.iter().any(|field| field.name == name);
+        if has_column("block_number") && has_column("block_hash") {
+            let file = std::fs::File::open(path).map_err(|_| err(&message))?;
+            let df = ParquetReader::new(file)
+                .with_columns(Some(vec!["block_number".to_string(), "block_hash".to_string()]))
+                .finish()
+                .map_err(|_| err(&message))?;
+            let block_hashes = row_block_hashes(&df, BLOCK_COLUMNS)?;
+            if !block_hashes.is_empty() {
+                return Ok(Some(block_hashes))
+            }
+        }
+    }
+    let metadata = match dataframes::read_file_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(None),
+    };
+    let number = metadata.get("max_block").and_then(|n| n.parse::<u64>().ok());
+    let hash = metadata
+        .get("max_block_hash")
+        .and_then(|hash| hex::decode(hash.trim_start_matches("0x")).ok())
+        .filter(|hash| hash.len() == 32)
+        .map(|hash| H256::from_slice(&hash));
+    Ok(number.zip(hash).map(|last_block| BTreeSet::from([last_block])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(number: u64, hash: u8, parent_hash: u8) -> BlockHeader {
+        BlockHeader {
+            number,
+            hash: H256::repeat_byte(hash),
+            parent_hash: H256::repeat_byte(parent_hash),
+        }
+    }
+
+    #[test]
+    fn test_connect_chunks() {
+        let block_hashes = BlockHashes::default();
+        assert!(block_hashes.connect(&header(0, 1, 0), &header(9, 2, 3)).unwrap().is_empty());
+        assert!(block_hashes.connect(&header(20, 5, 6), &header(29, 7, 8)).unwrap().is_empty());
+        // next chunk links to the last block of the first chunk, but not to the first block of
+        // the third chunk
+        let mismatches = block_hashes.connect(&header(10, 4, 2), &header(19, 9, 4)).unwrap();
+        assert_eq!(mismatches, vec![20]);
+    }
+
+    #[test]
+    fn test_row_block_hashes() {
+        let hash = H256::repeat_byte(1);
+        let df = df!(
+            "block_number" => [5u32, 5, 6],
+            "block_hash" => [hash.as_bytes(), hash.as_bytes(), H256::zero().as_bytes()],
+        )
+        .unwrap();
+        let hashes = row_block_hashes(&df, BLOCK_COLUMNS).unwrap();
+        assert_eq!(hashes, BTreeSet::from([(5, hash), (6, H256::zero())]));
+        let df = df!(
+            "block_number" => [5u64],
+            "block_hash" => [format!("0x{}", hex::encode(hash.as_bytes()))],
+        )
+        .unwrap();
+        assert_eq!(row_block_hashes(&df, BLOCK_COLUMNS).unwrap(), BTreeSet::from([(5, hash)]));
+    }
+
+    #[test]
+    fn test_read_block_hashes() {
+        let path = std::env::temp_dir().join(format!("cryo_verify_{}.parquet", std::process::id()));
+        let (hash, orphaned) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        let mut df = df!(
+            "block_number" => [5u32, 6, 6, 7],
+            "block_hash" => [hash.as_bytes(), orphaned.as_bytes(), orphaned.as_bytes(), hash.as_bytes()],
+        )
+        .unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        // every distinct block is checked, not only the last block of the file
+        let block_hashes = read_block_hashes(&path).unwrap().unwrap();
+        assert_eq!(block_hashes, BTreeSet::from([(5, hash), (6, orphaned), (7, hash)]));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -6,4 +6,7 @@ from ._freeze import async_freeze
 from ._freeze import freeze
 from ._collect import async_collect
 from ._collect import collect
+from ._stream import stream
 
+from ._schemas import datasets
+from ._schemas import schemas
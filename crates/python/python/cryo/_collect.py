@@ -14,42 +14,110 @@ if typing.TYPE_CHECKING:
 
 
 async def async_collect(
-    datatype: _spec.Datatype,
+    datatype: _spec.Datatype | typing.Sequence[_spec.Datatype],
     output_format: _spec.PythonOutput = 'polars',
     **kwargs: Unpack[_spec.CryoCliArgs],
-) -> pl.DataFrame | pd.DataFrame | ListOfDicts | DictOfLists:
-    """asynchronously collect data and return as dataframe"""
+) -> (
+    pl.DataFrame
+    | pl.LazyFrame
+    | pd.DataFrame
+    | ListOfDicts
+    | DictOfLists
+    | dict[str, Any]
+):
+    """asynchronously collect data and return as dataframe
+
+    dataframes are passed from rust without copying through the arrow c data
+    interface, several datatypes return a dict of outputs keyed by dataset name
+
+    output_format 'lazy' writes parquet files to output_dir and scans them
+    """
 
     from . import _args
     from . import _cryo_rust  # type: ignore
 
-    # parse inputs
-    cli_args = _args.parse_cli_args(**kwargs)
+    if isinstance(datatype, str):
+        datatypes = [datatype]
+    elif isinstance(datatype, (list, tuple)):
+        datatypes = list(datatype)
+    else:
+        raise Exception('invalid format for datatype(s)')
 
-    # fix chunk size
-    cli_args['chunk_size'] = 20_000_000
+    if output_format == 'lazy':
+        results = await _scan_frozen(datatypes, **kwargs)
+    else:
+        # parse inputs
+        cli_args = _args.parse_cli_args(**kwargs)
+
+        # fix chunk size
+        cli_args['chunk_size'] = 20_000_000
+
+        # collect data
+        dfs: dict[str, pl.DataFrame] = await _cryo_rust._collect(
+            datatypes, **cli_args
+        )
+        results = {
+            name: _format_output(df, output_format) for name, df in dfs.items()
+        }
+
+    if isinstance(datatype, str) and len(results) == 1:
+        return next(iter(results.values()))
+    else:
+        return results
 
-    # collect data
-    result: pl.DataFrame = await _cryo_rust._collect(datatype, **cli_args)
 
-    # format output
+def _format_output(
+    df: pl.DataFrame, output_format: _spec.PythonOutput
+) -> pl.DataFrame | pd.DataFrame | ListOfDicts | DictOfLists:
     if output_format == 'polars':
-        return result
+        return df
     elif output_format == 'pandas':
-        return result.to_pandas()
+        return df.to_pandas()
     elif output_format == 'list':
-        return result.to_dicts()
+        return df.to_dicts()
     elif output_format == 'dict':
-        return result.to_dict(as_series=False)
+        return df.to_dict(as_series=False)
     else:
         raise Exception('unknown output format')
 
 
+async def _scan_frozen(
+    datatypes: list[str], **kwargs: Unpack[_spec.CryoCliArgs]
+) -> dict[str, pl.LazyFrame]:
+    import os
+    import polars as pl
+    from . import _freeze
+
+    output_dir = kwargs.get('output_dir')
+    if output_dir is None:
+        raise Exception('output_format lazy requires output_dir')
+    if kwargs.get('file_format', 'parquet') != 'parquet':
+        raise Exception('output_format lazy requires parquet files')
+    await _freeze.async_freeze(datatypes, **kwargs)
+
+    names = set()
+    for filename in os.listdir(output_dir):
+        pieces = filename.split('__')
+        if len(pieces) >= 3 and filename.endswith('.parquet'):
+            names.add(pieces[1])
+    return {
+        name: pl.scan_parquet(os.path.join(output_dir, '*__' + name + '__*.parquet'))
+        for name in sorted(names)
+    }
+
+
 def collect(
-    datatype: _spec.Datatype,
+    datatype: _spec.Datatype | typing.Sequence[_spec.Datatype],
     output_format: _spec.PythonOutput = 'polars',
     **kwargs: Unpack[_spec.CryoCliArgs],
-) -> pl.DataFrame | pd.DataFrame | ListOfDicts | DictOfLists:
+) -> (
+    pl.DataFrame
+    | pl.LazyFrame
+    | pd.DataFrame
+    | ListOfDicts
+    | DictOfLists
+    | dict[str, Any]
+):
     """collect data and return as dataframe"""
 
     import asyncio
@@ -10,11 +10,21 @@ if typing.TYPE_CHECKING:
 
 async def async_freeze(
     datatype: str | typing.Sequence[str],
+    progress_callback: typing.Callable[[dict[str, typing.Any]], None]
+    | None = None,
+    progress_interval: float = 1.0,
+    cancel_event: _spec.CancelEvent | None = None,
     **kwargs: Unpack[_spec.CryoCliArgs],
-) -> None:
+) -> dict[str, int] | None:
     """asynchronously collect data and save to files
 
     see cryo.parse_kwargs() for descriptions of arguments
+
+    progress_callback is called every progress_interval seconds with a dict of
+    chunks_done, chunks_total, rows, current_block, and errors
+
+    once cancel_event is set, no further chunks are started, chunks in flight
+    are finished, and the results and report of the finished chunks are kept
     """
 
     from . import _cryo_rust  # type: ignore
@@ -28,18 +38,40 @@ async def async_freeze(
         raise Exception('invalid format for datatype(s)')
 
     cli_args = _args.parse_cli_args(**kwargs)
-    return await _cryo_rust._freeze(datatypes, **cli_args)  # type: ignore
+    return await _cryo_rust._freeze(  # type: ignore
+        datatypes,
+        progress_callback=progress_callback,
+        progress_interval=progress_interval,
+        cancel_event=cancel_event,
+        **cli_args,
+    )
 
 
 def freeze(
     datatype: str | typing.Sequence[str],
+    progress_callback: typing.Callable[[dict[str, typing.Any]], None]
+    | None = None,
+    progress_interval: float = 1.0,
+    cancel_event: _spec.CancelEvent | None = None,
     **kwargs: Unpack[_spec.CryoCliArgs],
-) -> None:
-    """collect data and save to files"""
+) -> dict[str, int] | None:
+    """collect data and save to files
 
-    import asyncio
+    a KeyboardInterrupt cancels the collection like setting cancel_event
+    """
 
-    coroutine = async_freeze(datatype, **kwargs)
+    import asyncio
+    import threading
+
+    if cancel_event is None:
+        cancel_event = threading.Event()
+    coroutine = async_freeze(
+        datatype,
+        progress_callback=progress_callback,
+        progress_interval=progress_interval,
+        cancel_event=cancel_event,
+        **kwargs,
+    )
 
     try:
         import concurrent.futures
@@ -48,7 +80,12 @@ def freeze(
         asyncio.set_event_loop(loop)
         with concurrent.futures.ThreadPoolExecutor() as executor:
             future = executor.submit(loop.run_until_complete, coroutine)  # type: ignore
-            return future.result()  # type: ignore
+            try:
+                return future.result()  # type: ignore
+            except KeyboardInterrupt:
+                # finish the chunks in flight and return their results
+                cancel_event.set()
+                return future.result()  # type: ignore
     except RuntimeError:
         return asyncio.run(coroutine)
 
@@ -0,0 +1,26 @@
+from __future__ import annotations
+
+import typing
+
+if typing.TYPE_CHECKING:
+    from . import _spec
+
+
+def schemas() -> dict[_spec.Datatype, _spec.DatasetSchema]:
+    """get the columns, aliases, and collection modes of each dataset
+
+    column types are given before u256 and binary columns are converted to
+    their output types, no rpc connection is needed
+    """
+
+    from . import _cryo_rust  # type: ignore
+
+    return _cryo_rust._schemas()  # type: ignore
+
+
+def datasets() -> dict[_spec.Datatype, str]:
+    """get the one-line description of each dataset"""
+
+    from . import _cryo_rust  # type: ignore
+
+    return _cryo_rust._datasets()  # type: ignore
@@ -13,13 +13,37 @@ if typing.TYPE_CHECKING:
         Literal['json'],
         Literal['avro'],
     ]
+    class CancelEvent(typing.Protocol):
+        """threading.Event, asyncio.Event, or any object with is_set()"""
+
+        def is_set(self) -> bool:
+            ...
+
     PythonOutput = Union[
         Literal['polars'],
         Literal['pandas'],
+        Literal['lazy'],
         Literal['list'],
         Literal['dict'],
     ]
 
+    class ColumnSchema(TypedDict):
+        name: str
+        type: str
+        default: bool
+        nullable: bool
+
+    class DatasetSchema(TypedDict):
+        name: Datatype
+        description: str
+        aliases: list[str]
+        columns: list[ColumnSchema]
+        default_sort: list[str]
+        required_parameters: list[str]
+        optional_parameters: list[str]
+        collect_by_block: bool
+        collect_by_transaction: bool
+
     class CryoCliArgs(TypedDict, total=False):
         datatypes: typing.Sequence[Datatype]
         blocks: typing.Sequence[str] | None
@@ -28,13 +52,16 @@ if typing.TYPE_CHECKING:
         include_columns: typing.Sequence[str] | None
         exclude_columns: typing.Sequence[str] | None
         columns: typing.Sequence[str] | None
+        u256_types: typing.Sequence[str] | None
         hex: bool
+        block_number_type: typing.Literal['u32', 'u64'] | None
         sort: typing.Sequence[str] | None
         rpc: str | None
         network_name: str | None
         requests_per_second: int | None
         max_concurrent_requests: int | None
         max_concurrent_chunks: int | None
+        on_block_error: typing.Literal['fail', 'skip', 'null'] | None
         dry: bool
         chunk_size: int | None
         n_chunks: int | None
@@ -0,0 +1,49 @@
+from __future__ import annotations
+
+import typing
+
+if typing.TYPE_CHECKING:
+    from typing_extensions import Unpack
+
+    import pyarrow as pa
+    from . import _spec
+
+
+async def stream(
+    datatype: _spec.Datatype | typing.Sequence[_spec.Datatype],
+    batch_rows: int | None = None,
+    **kwargs: Unpack[_spec.CryoCliArgs],
+) -> typing.AsyncIterator[pa.RecordBatch]:
+    """collect data as arrow record batches, yielding batches as chunks complete
+
+    each batch has at most batch_rows rows, its schema metadata holds the dataset,
+    chunk, start_block, and end_block of its chunk
+
+    collection stops once the iterator is closed
+    """
+
+    from . import _args
+    from . import _cryo_rust  # type: ignore
+
+    if isinstance(datatype, str):
+        datatypes = [datatype]
+    elif isinstance(datatype, (list, tuple)):
+        datatypes = list(datatype)
+    else:
+        raise Exception('invalid format for datatype(s)')
+
+    cli_args = _args.parse_cli_args(**kwargs)
+    chunks = await _cryo_rust._collect(
+        datatypes, stream=True, batch_rows=batch_rows, **cli_args
+    )
+    try:
+        async for dataset, chunk, block_range, dfs in chunks:
+            metadata = {'dataset': dataset, 'chunk': chunk}
+            if block_range is not None:
+                metadata['start_block'] = str(block_range[0])
+                metadata['end_block'] = str(block_range[1])
+            for df in dfs:
+                for batch in df.to_arrow().to_batches():
+                    yield batch.replace_schema_metadata(metadata)
+    finally:
+        await chunks.close()
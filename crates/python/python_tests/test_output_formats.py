@@ -56,3 +56,75 @@ def python_output_python_formats(query, format):
     df = cryo.collect(output_format=output_format, **query)
     assert isinstance(df, output_type)
 
+
+
+def test_binary_columns():
+    df = cryo.collect('blocks', start_block=17000000, end_block=17000010)
+    assert df['block_hash'].dtype == pl.Binary
+    assert all(len(value) == 32 for value in df['block_hash'])
+
+    pandas_df = cryo.collect(
+        'blocks', output_format='pandas', start_block=17000000, end_block=17000010
+    )
+    assert list(pandas_df['block_hash']) == list(df['block_hash'])
+
+
+def test_u256_types():
+    df = cryo.collect(
+        'transactions',
+        start_block=17000000,
+        end_block=17000001,
+        u256_types=['binary', 'string', 'f64'],
+    )
+    assert df['value_binary'].dtype == pl.Binary
+    assert df['value_string'].dtype == pl.Utf8
+    assert df['value_f64'].dtype == pl.Float64
+    for binary, string in zip(df['value_binary'], df['value_string']):
+        assert int.from_bytes(binary, 'big') == int(string)
+
+
+def test_multiple_datatypes():
+    dfs = cryo.collect(
+        ['blocks', 'transactions'], start_block=17000000, end_block=17000001
+    )
+    assert set(dfs.keys()) == {'blocks', 'transactions'}
+    assert all(isinstance(df, pl.DataFrame) for df in dfs.values())
+    assert len(dfs['blocks']) == 1
+
+
+def test_lazy_output():
+    output_dir = tempfile.mkdtemp()
+    query = {'start_block': 17000000, 'end_block': 17000010}
+    lazy = cryo.collect('blocks', output_format='lazy', output_dir=output_dir, **query)
+    assert isinstance(lazy, pl.LazyFrame)
+    assert lazy.collect().frame_equal(cryo.collect('blocks', **query))
+    with pytest.raises(Exception):
+        cryo.collect('blocks', output_format='lazy', **query)
+
+
+def test_stream():
+    import asyncio
+
+    query = {'start_block': 17000000, 'end_block': 17000100, 'chunk_size': 50}
+
+    async def collect_batches():
+        return [
+            batch
+            async for batch in cryo.stream('blocks', batch_rows=20, **query)
+        ]
+
+    batches = asyncio.run(collect_batches())
+    assert all(batch.num_rows <= 20 for batch in batches)
+    assert sum(batch.num_rows for batch in batches) == 100
+    metadata = [batch.schema.metadata for batch in batches]
+    assert {m[b'dataset'] for m in metadata} == {b'blocks'}
+    assert {(m[b'start_block'], m[b'end_block']) for m in metadata} == {
+        (b'17000000', b'17000049'),
+        (b'17000050', b'17000099'),
+    }
+
+    async def first_batch():
+        async for batch in cryo.stream('blocks', **query):
+            return batch
+
+    assert asyncio.run(first_batch()).num_rows == 50
@@ -0,0 +1,53 @@
+import tempfile
+import threading
+
+import cryo
+
+
+query = {
+    'start_block': 17000000,
+    'end_block': 17000100,
+    'chunk_size': 20,
+}
+
+
+def test_progress_callback():
+    progress = []
+    result = cryo.freeze(
+        'blocks',
+        output_dir=tempfile.mkdtemp(),
+        progress_callback=progress.append,
+        progress_interval=0.1,
+        **query,
+    )
+    assert result['n_completed'] == 5
+    final = progress[-1]
+    assert (final['chunks_done'], final['chunks_total']) == (5, 5)
+    assert (final['rows'], final['errors']) == (100, 0)
+    assert final['current_block'] == 17000099
+
+
+def test_cancel_event():
+    cancel_event = threading.Event()
+    cancel_event.set()
+    result = cryo.freeze(
+        'blocks', output_dir=tempfile.mkdtemp(), cancel_event=cancel_event, **query
+    )
+    assert (result['n_completed'], result['n_cancelled']) == (0, 5)
+
+    def cancel_after_first_chunk(progress):
+        if progress['chunks_done'] > 0:
+            cancel_event.set()
+
+    cancel_event = threading.Event()
+    result = cryo.freeze(
+        'blocks',
+        output_dir=tempfile.mkdtemp(),
+        progress_callback=cancel_after_first_chunk,
+        progress_interval=0.1,
+        cancel_event=cancel_event,
+        max_concurrent_chunks=1,
+        **query,
+    )
+    assert result['n_completed'] >= 1
+    assert result['n_completed'] + result['n_cancelled'] == 5
@@ -0,0 +1,18 @@
+import cryo
+
+
+def test_schemas():
+    schemas = cryo.schemas()
+    assert set(schemas.keys()) == set(cryo.datasets().keys())
+    blocks = schemas['blocks']
+    assert blocks['collect_by_block'] and blocks['collect_by_transaction']
+    columns = {column['name']: column for column in blocks['columns']}
+    assert columns['block_hash']['type'] == 'binary'
+    assert columns['block_hash']['default']
+    assert not schemas['balances']['collect_by_transaction']
+    assert 'txs' in schemas['transactions']['aliases']
+
+
+def test_datasets():
+    datasets = cryo.datasets()
+    assert all(description for description in datasets.values())
@@ -1,9 +1,17 @@
 use polars::prelude::*;
-use pyo3::{exceptions::PyTypeError, prelude::*};
+use pyo3::{
+    exceptions::{PyStopAsyncIteration, PyTypeError},
+    prelude::*,
+};
 use pyo3_polars::PyDataFrame;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{mpsc, Mutex};
 
 use cryo_cli::{parse_args, Args};
-use cryo_freeze::collect;
+use cryo_freeze::{
+    collect_dataframes, reports, stream_dataframes, CollectError, CollectedChunk, Dim,
+    ExecutionEnv, Query,
+};
 
 #[pyfunction(
     signature = (
@@ -28,6 +36,7 @@ use cryo_freeze::collect;
         requests_per_second = None,
         max_concurrent_requests = None,
         max_concurrent_chunks = None,
+        on_block_error = None,
         chunk_order = None,
         max_retries = 10,
         initial_backoff = 500,
@@ -64,12 +73,93 @@ use cryo_freeze::collect;
         verbose = false,
         no_verbose = false,
         event_signature = None,
+        sig_db = None,
+        sig_api = false,
+        beacon_url = None,
+        abi = None,
+        merge_block = None,
+        precompiles = None,
+        interval = None,
+        snapshots = 1,
+        slots_file = None,
+        trace_filter_side = None,
+        state_override = None,
+        changes_only = false,
+        agg = None,
+        args_file = None,
+        return_type = None,
+        multicall_batch = None,
+        ipc = false,
+        ipc_compression = None,
+        avro = false,
+        avro_codec = None,
+        duckdb = None,
+        postgres = None,
+        postgres_schema = "public".to_string(),
+        postgres_table_prefix = "".to_string(),
+        ndjson = None,
+        statistics = None,
+        compression_columns = vec![],
+        bloom_filter_columns = None,
+        bloom_filter_fpp = 0.01,
+        bloom_filter_ndv = None,
+        hive_partitioning = None,
+        drop_partition_columns = false,
+        max_rows_per_file = None,
+        empty_chunks = None,
+        no_sort = false,
+        target_rows = None,
+        target_size = None,
+        csv_compression = None,
+        json_compression = None,
+        append = false,
+        checksum_manifest = false,
+        follow = false,
+        poll_interval = 12,
+        flush_interval = None,
+        verify_hashes = None,
+        txs_file = None,
+        txs_column = None,
+        address_file = None,
+        contract_file = None,
+        filter_set = None,
+        dry_run_samples = 5,
+        checksum_addresses = false,
+        block_number_type = None,
+        chain_name_override = None,
+        tag = None,
+        rename = None,
+        rpc_file = None,
+        rpc_strategy = None,
+        rpc_cooldown = 30,
+        rpc_concurrency = None,
+        rpc_batch_size = None,
+        autoscale = false,
+        cache_dir = None,
+        cache_size = None,
+        trace_backend = None,
+        rpc_header = None,
+        rpc_auth = None,
+        metrics_addr = None,
+        memory_budget = None,
+        flush_rows = None,
+        write_threads = None,
+        reverse = false,
+        chunk_by = None,
+        report_file = None,
+        resume = None,
+        force = false,
+        config = None,
+        profile = None,
+        chain_subdirs = false,
+        stream = false,
+        batch_rows = None,
     )
 )]
 #[allow(clippy::too_many_arguments)]
 pub fn _collect(
     py: Python<'_>,
-    datatype: Option<String>,
+    datatype: Option<Vec<String>>,
     blocks: Option<Vec<String>>,
     remember: bool,
     command: Option<String>,
@@ -89,6 +179,7 @@ pub fn _collect(
     requests_per_second: Option<u32>,
     max_concurrent_requests: Option<u64>,
     max_concurrent_chunks: Option<u64>,
+    on_block_error: Option<String>,
     chunk_order: Option<String>,
     max_retries: u32,
     initial_backoff: u64,
@@ -125,17 +216,98 @@ pub fn _collect(
     verbose: bool,
     no_verbose: bool,
     event_signature: Option<String>,
+    sig_db: Option<String>,
+    sig_api: bool,
+    beacon_url: Option<String>,
+    abi: Option<String>,
+    merge_block: Option<u64>,
+    precompiles: Option<Vec<String>>,
+    interval: Option<String>,
+    snapshots: u64,
+    slots_file: Option<String>,
+    trace_filter_side: Option<String>,
+    state_override: Option<String>,
+    changes_only: bool,
+    agg: Option<String>,
+    args_file: Option<String>,
+    return_type: Option<String>,
+    multicall_batch: Option<usize>,
+    ipc: bool,
+    ipc_compression: Option<String>,
+    avro: bool,
+    avro_codec: Option<String>,
+    duckdb: Option<String>,
+    postgres: Option<String>,
+    postgres_schema: String,
+    postgres_table_prefix: String,
+    ndjson: Option<String>,
+    statistics: Option<String>,
+    compression_columns: Vec<String>,
+    bloom_filter_columns: Option<Vec<String>>,
+    bloom_filter_fpp: f64,
+    bloom_filter_ndv: Option<u64>,
+    hive_partitioning: Option<Vec<String>>,
+    drop_partition_columns: bool,
+    max_rows_per_file: Option<usize>,
+    empty_chunks: Option<String>,
+    no_sort: bool,
+    target_rows: Option<String>,
+    target_size: Option<String>,
+    csv_compression: Option<String>,
+    json_compression: Option<String>,
+    append: bool,
+    checksum_manifest: bool,
+    follow: bool,
+    poll_interval: u64,
+    flush_interval: Option<u64>,
+    verify_hashes: Option<String>,
+    txs_file: Option<String>,
+    txs_column: Option<String>,
+    address_file: Option<String>,
+    contract_file: Option<String>,
+    filter_set: Option<Vec<String>>,
+    dry_run_samples: u64,
+    checksum_addresses: bool,
+    block_number_type: Option<String>,
+    chain_name_override: Option<String>,
+    tag: Option<Vec<String>>,
+    rename: Option<Vec<String>>,
+    rpc_file: Option<String>,
+    rpc_strategy: Option<String>,
+    rpc_cooldown: u64,
+    rpc_concurrency: Option<u64>,
+    rpc_batch_size: Option<u64>,
+    autoscale: bool,
+    cache_dir: Option<String>,
+    cache_size: Option<String>,
+    trace_backend: Option<String>,
+    rpc_header: Option<Vec<String>>,
+    rpc_auth: Option<String>,
+    metrics_addr: Option<String>,
+    memory_budget: Option<String>,
+    flush_rows: Option<u64>,
+    write_threads: Option<usize>,
+    reverse: bool,
+    chunk_by: Option<String>,
+    report_file: Option<String>,
+    resume: Option<String>,
+    force: bool,
+    config: Option<String>,
+    profile: Option<String>,
+    chain_subdirs: bool,
+    stream: bool,
+    batch_rows: Option<usize>,
 ) -> PyResult<&PyAny> {
     if let Some(command) = command {
         pyo3_asyncio::tokio::future_into_py(py, async move {
             match run_execute(command).await {
-                Ok(df) => Ok(PyDataFrame(df)),
+                Ok(dfs) => Ok(dfs),
                 Err(_e) => Err(PyErr::new::<PyTypeError, _>("failed")),
             }
         })
     } else if let Some(datatype) = datatype {
         let args = Args {
-            datatype: vec![datatype],
+            datatype,
             blocks,
             remember,
             timestamps,
@@ -149,11 +321,12 @@ pub fn _collect(
             hex,
             sort,
             exclude_failed,
-            rpc,
+            rpc: rpc.map(|rpc| vec![rpc]),
             network_name,
             requests_per_second,
             max_concurrent_requests,
             max_concurrent_chunks,
+            on_block_error,
             chunk_order,
             max_retries,
             initial_backoff,
@@ -190,34 +363,218 @@ pub fn _collect(
             verbose,
             no_verbose,
             event_signature,
+            sig_db,
+            sig_api,
+            beacon_url,
+            abi,
+            merge_block,
+            precompiles,
+            interval,
+            snapshots,
+            slots_file,
+            trace_filter_side,
+            state_override,
+            changes_only,
+            agg,
+            args_file,
+            return_type,
+            multicall_batch,
+            ipc,
+            ipc_compression,
+            avro,
+            avro_codec,
+            duckdb,
+            postgres,
+            postgres_schema,
+            postgres_table_prefix,
+            ndjson,
+            statistics,
+            compression_columns,
+            bloom_filter_columns,
+            bloom_filter_fpp,
+            bloom_filter_ndv,
+            hive_partitioning,
+            drop_partition_columns,
+            max_rows_per_file,
+            empty_chunks,
+            no_sort,
+            target_rows,
+            target_size,
+            csv_compression,
+            json_compression,
+            append,
+            checksum_manifest,
+            follow,
+            poll_interval,
+            flush_interval,
+            verify_hashes,
+            txs_file,
+            txs_column,
+            address_file,
+            contract_file,
+            filter_set,
+            dry_run_samples,
+            checksum_addresses,
+            block_number_type,
+            chain_name_override,
+            tag,
+            rename,
+            rpc_file,
+            rpc_strategy,
+            rpc_cooldown,
+            rpc_concurrency,
+            rpc_batch_size,
+            autoscale,
+            cache_dir,
+            cache_size,
+            trace_backend,
+            rpc_header,
+            rpc_auth,
+            metrics_addr,
+            memory_budget,
+            flush_rows,
+            write_threads,
+            reverse,
+            chunk_by,
+            report_file: report_file.map(std::path::PathBuf::from),
+            resume: resume.map(std::path::PathBuf::from),
+            force,
+            config: config.map(std::path::PathBuf::from),
+            profile,
+            chain_subdirs,
         };
+        if stream {
+            return pyo3_asyncio::tokio::future_into_py(py, async move {
+                match run_stream(args, batch_rows).await {
+                    Ok(chunks) => Ok(chunks),
+                    Err(e) => Err(PyErr::new::<PyTypeError, _>(e.to_string())),
+                }
+            })
+        }
         pyo3_asyncio::tokio::future_into_py(py, async move {
             match run_collect(args).await {
-                // Ok(df) => Ok(Python::with_gil(|py| py.None())),
-                Ok(df) => Ok(PyDataFrame(df)),
+                Ok(dfs) => Ok(dfs),
                 Err(_e) => Err(PyErr::new::<PyTypeError, _>("failed")),
             }
         })
     } else {
-        return Err(PyErr::new::<PyTypeError, _>("must specify datatype or command"))
+        Err(PyErr::new::<PyTypeError, _>("must specify datatype or command"))
     }
 }
 
-async fn run_collect(args: Args) -> PolarsResult<DataFrame> {
-    let (query, source, _sink, _env) = match parse_args(&args).await {
+/// dataframes of each dataset, handed to python through the arrow c data interface
+async fn run_collect(args: Args) -> PolarsResult<HashMap<String, PyDataFrame>> {
+    let (query, source, sink, env) = match parse_args(&args).await {
         Ok(opts) => opts,
         Err(e) => panic!("error parsing opts {:?}", e),
     };
-    match collect(query.into(), source.into()).await {
-        Ok(df) => Ok(df),
+    let query = Arc::new(query);
+    let dfs = match collect_dataframes(query.clone(), source.into()).await {
+        Ok(dfs) => dfs,
         Err(e) => panic!("error collecting {:?}", e),
+    };
+    // no report is written, so failed block requests are recorded in an errors file
+    let env = ExecutionEnv { report: false, ..env };
+    if let Err(e) = reports::write_errors_file(&query, &sink.output_dir, &env) {
+        panic!("error writing errors file {:?}", e)
     }
+    Ok(dfs.into_iter().map(|(datatype, df)| (datatype.name(), PyDataFrame(df))).collect())
 }
 
-async fn run_execute(command: String) -> PolarsResult<DataFrame> {
+async fn run_execute(command: String) -> PolarsResult<HashMap<String, PyDataFrame>> {
     let args = match cryo_cli::parse_str(command.as_str()).await {
         Ok(opts) => opts,
         Err(e) => panic!("error parsing opts {:?}", e),
     };
     run_collect(args).await
 }
+
+/// dataset, chunk label, block range, and dataframes of a collected chunk
+type StreamedChunk = (String, String, Option<(u64, u64)>, Vec<PyDataFrame>);
+
+/// async iterator over the chunks of a collection, collection stops once it is closed or dropped
+#[pyclass]
+pub struct ChunkStream {
+    receiver: Arc<Mutex<mpsc::Receiver<Result<CollectedChunk, CollectError>>>>,
+    partitioned_by: Vec<Dim>,
+    batch_rows: Option<usize>,
+    /// query of the collection, whose failed blocks are recorded once the stream finishes
+    query: Arc<Query>,
+    output_dir: std::path::PathBuf,
+    env: ExecutionEnv,
+}
+
+#[pymethods]
+impl ChunkStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyAny>> {
+        let receiver = self.receiver.clone();
+        let partitioned_by = self.partitioned_by.clone();
+        let batch_rows = self.batch_rows;
+        let (query, output_dir, env) =
+            (self.query.clone(), self.output_dir.clone(), self.env.clone());
+        let next = pyo3_asyncio::tokio::future_into_py(py, async move {
+            let chunk = receiver.lock().await.recv().await;
+            match chunk {
+                Some(Ok((partition, datatype, df))) => {
+                    let chunk = partition.label(&partitioned_by).unwrap_or_default();
+                    let block_range = partition.stats().block_numbers.and_then(|stats| {
+                        stats.min_value.and_then(|min| stats.max_value.map(|max| (min, max)))
+                    });
+                    let batches = split_rows(df, batch_rows).into_iter().map(PyDataFrame);
+                    let chunk: StreamedChunk =
+                        (datatype.name(), chunk, block_range, batches.collect());
+                    Ok(chunk)
+                }
+                Some(Err(e)) => Err(PyErr::new::<PyTypeError, _>(e.to_string())),
+                None => {
+                    reports::write_errors_file(&query, &output_dir, &env)
+                        .map_err(|e| PyErr::new::<PyTypeError, _>(e.to_string()))?;
+                    Err(PyStopAsyncIteration::new_err("collection finished"))
+                }
+            }
+        })?;
+        Ok(Some(next))
+    }
+
+    /// stop collecting chunks
+    fn close<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let receiver = self.receiver.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            receiver.lock().await.close();
+            Ok(())
+        })
+    }
+}
+
+async fn run_stream(args: Args, batch_rows: Option<usize>) -> Result<ChunkStream, CollectError> {
+    let (query, source, sink, env) = parse_args(&args)
+        .await
+        .map_err(|e| CollectError::CollectError(format!("error parsing opts {:?}", e)))?;
+    let partitioned_by = query.partitioned_by.clone();
+    let query = Arc::new(query);
+    let receiver = stream_dataframes(query.clone(), source.into());
+    Ok(ChunkStream {
+        receiver: Arc::new(Mutex::new(receiver)),
+        partitioned_by,
+        batch_rows,
+        query,
+        output_dir: sink.output_dir,
+        env: ExecutionEnv { report: false, ..env },
+    })
+}
+
+/// contiguous slices of df with at most batch_rows rows each
+fn split_rows(mut df: DataFrame, batch_rows: Option<usize>) -> Vec<DataFrame> {
+    df.as_single_chunk_par();
+    match batch_rows {
+        Some(batch_rows) if batch_rows > 0 && df.height() > batch_rows => (0..df.height())
+            .step_by(batch_rows)
+            .map(|offset| df.slice(offset as i64, batch_rows))
+            .collect(),
+        _ => vec![df],
+    }
+}
@@ -1,6 +1,10 @@
 use pyo3::{exceptions::PyTypeError, prelude::*, types::IntoPyDict};
 
-use cryo_cli::{run, Args};
+use std::sync::Arc;
+
+use cryo_cli::{run_with_hooks, Args};
+
+use crate::progress::Hooks;
 
 #[pyfunction(
     signature = (
@@ -25,6 +29,7 @@ use cryo_cli::{run, Args};
         requests_per_second = None,
         max_concurrent_requests = None,
         max_concurrent_chunks = None,
+        on_block_error = None,
         chunk_order = None,
         max_retries = 10,
         initial_backoff = 500,
@@ -61,6 +66,88 @@ use cryo_cli::{run, Args};
         verbose = false,
         no_verbose = false,
         event_signature = None,
+        sig_db = None,
+        sig_api = false,
+        beacon_url = None,
+        abi = None,
+        merge_block = None,
+        precompiles = None,
+        interval = None,
+        snapshots = 1,
+        slots_file = None,
+        trace_filter_side = None,
+        state_override = None,
+        changes_only = false,
+        agg = None,
+        args_file = None,
+        return_type = None,
+        multicall_batch = None,
+        ipc = false,
+        ipc_compression = None,
+        avro = false,
+        avro_codec = None,
+        duckdb = None,
+        postgres = None,
+        postgres_schema = "public".to_string(),
+        postgres_table_prefix = "".to_string(),
+        ndjson = None,
+        statistics = None,
+        compression_columns = vec![],
+        bloom_filter_columns = None,
+        bloom_filter_fpp = 0.01,
+        bloom_filter_ndv = None,
+        hive_partitioning = None,
+        drop_partition_columns = false,
+        max_rows_per_file = None,
+        empty_chunks = None,
+        no_sort = false,
+        target_rows = None,
+        target_size = None,
+        csv_compression = None,
+        json_compression = None,
+        append = false,
+        checksum_manifest = false,
+        follow = false,
+        poll_interval = 12,
+        flush_interval = None,
+        verify_hashes = None,
+        txs_file = None,
+        txs_column = None,
+        address_file = None,
+        contract_file = None,
+        filter_set = None,
+        dry_run_samples = 5,
+        checksum_addresses = false,
+        block_number_type = None,
+        chain_name_override = None,
+        tag = None,
+        rename = None,
+        rpc_file = None,
+        rpc_strategy = None,
+        rpc_cooldown = 30,
+        rpc_concurrency = None,
+        rpc_batch_size = None,
+        autoscale = false,
+        cache_dir = None,
+        cache_size = None,
+        trace_backend = None,
+        rpc_header = None,
+        rpc_auth = None,
+        metrics_addr = None,
+        memory_budget = None,
+        flush_rows = None,
+        write_threads = None,
+        reverse = false,
+        chunk_by = None,
+        report_file = None,
+        resume = None,
+        force = false,
+        config = None,
+        profile = None,
+        chain_subdirs = false,
+        progress_callback = None,
+        progress_interval = 1.0,
+        cancel_event = None,
     )
 )]
 #[allow(clippy::too_many_arguments)]
@@ -86,6 +173,7 @@ pub fn _freeze(
     requests_per_second: Option<u32>,
     max_concurrent_requests: Option<u64>,
     max_concurrent_chunks: Option<u64>,
+    on_block_error: Option<String>,
     chunk_order: Option<String>,
     max_retries: u32,
     initial_backoff: u64,
@@ -122,9 +210,92 @@ pub fn _freeze(
     verbose: bool,
     no_verbose: bool,
     event_signature: Option<String>,
+    sig_db: Option<String>,
+    sig_api: bool,
+    beacon_url: Option<String>,
+    abi: Option<String>,
+    merge_block: Option<u64>,
+    precompiles: Option<Vec<String>>,
+    interval: Option<String>,
+    snapshots: u64,
+    slots_file: Option<String>,
+    trace_filter_side: Option<String>,
+    state_override: Option<String>,
+    changes_only: bool,
+    agg: Option<String>,
+    args_file: Option<String>,
+    return_type: Option<String>,
+    multicall_batch: Option<usize>,
+    ipc: bool,
+    ipc_compression: Option<String>,
+    avro: bool,
+    avro_codec: Option<String>,
+    duckdb: Option<String>,
+    postgres: Option<String>,
+    postgres_schema: String,
+    postgres_table_prefix: String,
+    ndjson: Option<String>,
+    statistics: Option<String>,
+    compression_columns: Vec<String>,
+    bloom_filter_columns: Option<Vec<String>>,
+    bloom_filter_fpp: f64,
+    bloom_filter_ndv: Option<u64>,
+    hive_partitioning: Option<Vec<String>>,
+    drop_partition_columns: bool,
+    max_rows_per_file: Option<usize>,
+    empty_chunks: Option<String>,
+    no_sort: bool,
+    target_rows: Option<String>,
+    target_size: Option<String>,
+    csv_compression: Option<String>,
+    json_compression: Option<String>,
+    append: bool,
+    checksum_manifest: bool,
+    follow: bool,
+    poll_interval: u64,
+    flush_interval: Option<u64>,
+    verify_hashes: Option<String>,
+    txs_file: Option<String>,
+    txs_column: Option<String>,
+    address_file: Option<String>,
+    contract_file: Option<String>,
+    filter_set: Option<Vec<String>>,
+    dry_run_samples: u64,
+    checksum_addresses: bool,
+    block_number_type: Option<String>,
+    chain_name_override: Option<String>,
+    tag: Option<Vec<String>>,
+    rename: Option<Vec<String>>,
+    rpc_file: Option<String>,
+    rpc_strategy: Option<String>,
+    rpc_cooldown: u64,
+    rpc_concurrency: Option<u64>,
+    rpc_batch_size: Option<u64>,
+    autoscale: bool,
+    cache_dir: Option<String>,
+    cache_size: Option<String>,
+    trace_backend: Option<String>,
+    rpc_header: Option<Vec<String>>,
+    rpc_auth: Option<String>,
+    metrics_addr: Option<String>,
+    memory_budget: Option<String>,
+    flush_rows: Option<u64>,
+    write_threads: Option<usize>,
+    reverse: bool,
+    chunk_by: Option<String>,
+    report_file: Option<String>,
+    resume: Option<String>,
+    force: bool,
+    config: Option<String>,
+    profile: Option<String>,
+    chain_subdirs: bool,
+    progress_callback: Option<PyObject>,
+    progress_interval: f64,
+    cancel_event: Option<PyObject>,
 ) -> PyResult<&PyAny> {
+    let hooks = Arc::new(Hooks::new(progress_callback, progress_interval, cancel_event));
     if let Some(command) = command {
-        freeze_command(py, command)
+        freeze_command(py, command, hooks)
     } else if let Some(datatype) = datatype {
         let args = Args {
             datatype,
@@ -141,11 +312,12 @@ pub fn _freeze(
             hex,
             sort,
             exclude_failed,
-            rpc,
+            rpc: rpc.map(|rpc| vec![rpc]),
             network_name,
             requests_per_second,
             max_concurrent_requests,
             max_concurrent_chunks,
+            on_block_error,
             chunk_order,
             max_retries,
             initial_backoff,
@@ -182,61 +354,125 @@ pub fn _freeze(
             verbose,
             no_verbose,
             event_signature,
+            sig_db,
+            sig_api,
+            beacon_url,
+            abi,
+            merge_block,
+            precompiles,
+            interval,
+            snapshots,
+            slots_file,
+            trace_filter_side,
+            state_override,
+            changes_only,
+            agg,
+            args_file,
+            return_type,
+            multicall_batch,
+            ipc,
+            ipc_compression,
+            avro,
+            avro_codec,
+            duckdb,
+            postgres,
+            postgres_schema,
+            postgres_table_prefix,
+            ndjson,
+            statistics,
+            compression_columns,
+            bloom_filter_columns,
+            bloom_filter_fpp,
+            bloom_filter_ndv,
+            hive_partitioning,
+            drop_partition_columns,
+            max_rows_per_file,
+            empty_chunks,
+            no_sort,
+            target_rows,
+            target_size,
+            csv_compression,
+            json_compression,
+            append,
+            checksum_manifest,
+            follow,
+            poll_interval,
+            flush_interval,
+            verify_hashes,
+            txs_file,
+            txs_column,
+            address_file,
+            contract_file,
+            filter_set,
+            dry_run_samples,
+            checksum_addresses,
+            block_number_type,
+            chain_name_override,
+            tag,
+            rename,
+            rpc_file,
+            rpc_strategy,
+            rpc_cooldown,
+            rpc_concurrency,
+            rpc_batch_size,
+            autoscale,
+            cache_dir,
+            cache_size,
+            trace_backend,
+            rpc_header,
+            rpc_auth,
+            metrics_addr,
+            memory_budget,
+            flush_rows,
+            write_threads,
+            reverse,
+            chunk_by,
+            report_file: report_file.map(std::path::PathBuf::from),
+            resume: resume.map(std::path::PathBuf::from),
+            force,
+            config: config.map(std::path::PathBuf::from),
+            profile,
+            chain_subdirs,
         };
 
-        pyo3_asyncio::tokio::future_into_py(py, async move {
-            match run(args).await {
-                Ok(Some(result)) => Python::with_gil(|py| {
-                    // let paths = PyDict::new(py);
-                    // for (key, values) in &result.paths {
-                    //     let key = key.dataset().name();
-                    //     let values: Vec<&str> = values.iter().filter_map(|p|
-                    // p.to_str()).collect();     paths.set_item(key,
-                    // values).unwrap(); }
-                    // let paths = paths.to_object(py);
-
-                    let dict = [
-                        ("n_completed".to_string(), result.completed.len().into_py(py)),
-                        ("n_skipped".to_string(), result.skipped.len().into_py(py)),
-                        ("n_errored".to_string(), result.errored.len().into_py(py)),
-                        // ("paths".to_string(), paths),
-                    ]
-                    .into_py_dict(py);
-                    Ok(dict.to_object(py))
-                }),
-                Ok(None) => Ok(Python::with_gil(|py| py.None())),
-                _ => Err(PyErr::new::<PyTypeError, _>("failed")),
-            }
-        })
+        pyo3_asyncio::tokio::future_into_py(py, freeze_with_hooks(args, hooks))
     } else {
-        return Err(PyErr::new::<PyTypeError, _>("must specify datatypes or command"))
+        Err(PyErr::new::<PyTypeError, _>("must specify datatypes or command"))
     }
 }
 
-fn freeze_command(py: Python<'_>, command: String) -> PyResult<&PyAny> {
+fn freeze_command(py: Python<'_>, command: String, hooks: Arc<Hooks>) -> PyResult<&PyAny> {
     pyo3_asyncio::tokio::future_into_py(py, async move {
         let args = cryo_cli::parse_str(command.as_str()).await.expect("could not parse inputs");
-        match run(args).await {
-            Ok(Some(result)) => Python::with_gil(|py| {
-                // let paths = PyDict::new(py);
-                // for (key, values) in &result.paths {
-                //     let key = key.dataset().name();
-                //     let values: Vec<&str> = values.iter().filter_map(|p| p.to_str()).collect();
-                //     paths.set_item(key, values).unwrap();
-                // }
-                // let paths = paths.to_object(py);
-
-                let dict = [
-                    ("n_completed".to_string(), result.completed.len().into_py(py)),
-                    ("n_skipped".to_string(), result.skipped.len().into_py(py)),
-                    ("n_errored".to_string(), result.errored.len().into_py(py)),
-                    // ("paths".to_string(), paths),
-                ]
-                .into_py_dict(py);
-                Ok(dict.to_object(py))
-            }),
-            Ok(None) => Ok(Python::with_gil(|py| py.None())),
-            _ => Err(PyErr::new::<PyTypeError, _>("failed")),
-        }
+        freeze_with_hooks(args, hooks).await
     })
 }
+
+/// freeze args, reporting progress and stopping once cancelled, returning the results of the
+/// chunks that were collected before cancelling
+async fn freeze_with_hooks(args: Args, hooks: Arc<Hooks>) -> PyResult<PyObject> {
+    // an event set before the call cancels the collection before its first chunk starts
+    hooks.poll_cancel();
+    let monitor = hooks.spawn_monitor();
+    let result =
+        run_with_hooks(args, Some(hooks.progress.clone()), Some(hooks.cancel.clone())).await;
+    monitor.abort();
+    hooks.report();
+    match result {
+        Ok(Some(result)) => Python::with_gil(|py| {
+            let n_unsupported: usize =
+                result.unsupported.iter().map(|unsupported| unsupported.partitions.len()).sum();
+            let dict = [
+                ("n_completed".to_string(), result.completed.len().into_py(py)),
+                ("n_skipped".to_string(), result.skipped.len().into_py(py)),
+                ("n_errored".to_string(), result.errored.len().into_py(py)),
+                ("n_cancelled".to_string(), result.cancelled.len().into_py(py)),
+                ("n_unsupported".to_string(), n_unsupported.into_py(py)),
+            ]
+            .into_py_dict(py);
+            Ok(dict.to_object(py))
+        }),
+        Ok(None) => Ok(Python::with_gil(|py| py.None())),
+        Err(e) => Err(PyErr::new::<PyTypeError, _>(e.to_string())),
+    }
+}
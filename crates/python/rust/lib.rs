@@ -1,5 +1,7 @@
 mod collect_adapter;
 mod freeze_adapter;
+mod progress;
+mod schema_adapter;
 
 use pyo3::prelude::*;
 // use crate::freeze_adapter;
@@ -17,5 +19,8 @@ fn cryo_rust(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(sum_as_string, m)?)?;
     m.add_function(wrap_pyfunction!(freeze_adapter::_freeze, m)?)?;
     m.add_function(wrap_pyfunction!(collect_adapter::_collect, m)?)?;
+    m.add_class::<collect_adapter::ChunkStream>()?;
+    m.add_function(wrap_pyfunction!(schema_adapter::_schemas, m)?)?;
+    m.add_function(wrap_pyfunction!(schema_adapter::_datasets, m)?)?;
     Ok(())
 }
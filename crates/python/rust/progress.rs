@@ -0,0 +1,137 @@
+use pyo3::{prelude::*, types::PyDict};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use cryo_freeze::{CollectionEvent, EventHandler};
+
+/// time between checks of the cancel event
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// progress of a collection, counted from its events
+#[derive(Default)]
+pub(crate) struct Progress {
+    chunks_total: AtomicU64,
+    chunks_done: AtomicU64,
+    rows: AtomicU64,
+    errors: AtomicU64,
+    current_block: AtomicU64,
+    has_block: AtomicBool,
+}
+
+impl EventHandler for Progress {
+    fn handle(&self, event: &CollectionEvent) {
+        match event {
+            CollectionEvent::CollectionStarted { n_chunks } => {
+                self.chunks_total.fetch_add(*n_chunks, Ordering::SeqCst);
+            }
+            CollectionEvent::ChunkCompleted { rows, block_range, .. } => {
+                self.chunks_done.fetch_add(1, Ordering::SeqCst);
+                self.rows.fetch_add(*rows, Ordering::SeqCst);
+                if let Some((_, end_block)) = block_range {
+                    self.current_block.fetch_max(*end_block, Ordering::SeqCst);
+                    self.has_block.store(true, Ordering::SeqCst);
+                }
+            }
+            CollectionEvent::ChunkFailed { .. } => {
+                self.chunks_done.fetch_add(1, Ordering::SeqCst);
+                self.errors.fetch_add(1, Ordering::SeqCst);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Progress {
+    fn to_dict<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("chunks_done", self.chunks_done.load(Ordering::SeqCst))?;
+        dict.set_item("chunks_total", self.chunks_total.load(Ordering::SeqCst))?;
+        dict.set_item("rows", self.rows.load(Ordering::SeqCst))?;
+        let current_block = match self.has_block.load(Ordering::SeqCst) {
+            true => Some(self.current_block.load(Ordering::SeqCst)),
+            false => None,
+        };
+        dict.set_item("current_block", current_block)?;
+        dict.set_item("errors", self.errors.load(Ordering::SeqCst))?;
+        Ok(dict)
+    }
+}
+
+/// python progress callback and cancel event of a collection
+pub(crate) struct Hooks {
+    pub(crate) progress: Arc<Progress>,
+    pub(crate) cancel: Arc<AtomicBool>,
+    callback: Option<PyObject>,
+    interval: Duration,
+    cancel_event: Option<PyObject>,
+}
+
+impl Hooks {
+    pub(crate) fn new(
+        callback: Option<PyObject>,
+        interval: f64,
+        cancel_event: Option<PyObject>,
+    ) -> Hooks {
+        Hooks {
+            progress: Default::default(),
+            cancel: Default::default(),
+            callback,
+            interval: Duration::from_secs_f64(interval.max(0.0)).max(CANCEL_POLL_INTERVAL),
+            cancel_event,
+        }
+    }
+
+    /// report progress every interval and poll the cancel event, until the task is aborted
+    ///
+    /// the gil is only held while the callback runs or the cancel event is checked, never by the
+    /// tasks of the collection
+    pub(crate) fn spawn_monitor(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let hooks = self.clone();
+        tokio::spawn(async move {
+            let mut since_report = Duration::ZERO;
+            loop {
+                let sleep = match hooks.cancel_event {
+                    Some(_) => CANCEL_POLL_INTERVAL,
+                    None => hooks.interval,
+                };
+                tokio::time::sleep(sleep).await;
+                since_report += sleep;
+                if since_report >= hooks.interval {
+                    since_report = Duration::ZERO;
+                    hooks.report();
+                }
+                hooks.poll_cancel();
+            }
+        })
+    }
+
+    /// call the progress callback with the current progress
+    pub(crate) fn report(&self) {
+        if let Some(callback) = &self.callback {
+            Python::with_gil(|py| {
+                let result =
+                    self.progress.to_dict(py).and_then(|progress| callback.call1(py, (progress,)));
+                if let Err(e) = result {
+                    e.print(py)
+                }
+            })
+        }
+    }
+
+    /// mark the collection as cancelled if the cancel event is set
+    pub(crate) fn poll_cancel(&self) {
+        if let Some(cancel_event) = &self.cancel_event {
+            let is_set = Python::with_gil(|py| {
+                cancel_event.call_method0(py, "is_set").and_then(|is_set| is_set.is_true(py))
+            });
+            if is_set.unwrap_or(false) {
+                self.cancel.store(true, Ordering::SeqCst)
+            }
+        }
+    }
+}
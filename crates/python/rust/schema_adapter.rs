@@ -0,0 +1,52 @@
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
+};
+
+use cryo_freeze::{ColumnInfo, Datatype, DatatypeInfo};
+
+#[pyfunction]
+pub fn _schemas(py: Python<'_>) -> PyResult<&PyDict> {
+    let schemas = PyDict::new(py);
+    for datatype in Datatype::all() {
+        let info = datatype.info();
+        schemas.set_item(info.name.clone(), datatype_to_dict(py, &info)?)?;
+    }
+    Ok(schemas)
+}
+
+#[pyfunction]
+pub fn _datasets(py: Python<'_>) -> PyResult<&PyDict> {
+    let datasets = PyDict::new(py);
+    for datatype in Datatype::all() {
+        datasets.set_item(datatype.name(), datatype.description())?;
+    }
+    Ok(datasets)
+}
+
+fn datatype_to_dict<'p>(py: Python<'p>, info: &DatatypeInfo) -> PyResult<&'p PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("name", &info.name)?;
+    dict.set_item("description", &info.description)?;
+    dict.set_item("aliases", &info.aliases)?;
+    let columns = PyList::empty(py);
+    for column in info.columns.iter() {
+        columns.append(column_to_dict(py, column)?)?;
+    }
+    dict.set_item("columns", columns)?;
+    dict.set_item("default_sort", &info.default_sort)?;
+    dict.set_item("required_parameters", &info.required_parameters)?;
+    dict.set_item("optional_parameters", &info.optional_parameters)?;
+    dict.set_item("collect_by_block", info.collect_by_block)?;
+    dict.set_item("collect_by_transaction", info.collect_by_transaction)?;
+    Ok(dict)
+}
+
+fn column_to_dict<'p>(py: Python<'p>, column: &ColumnInfo) -> PyResult<&'p PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("name", &column.name)?;
+    dict.set_item("type", &column.column_type)?;
+    dict.set_item("default", column.default)?;
+    dict.set_item("nullable", column.nullable)?;
+    Ok(dict)
+}
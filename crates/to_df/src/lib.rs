@@ -5,6 +5,9 @@ use proc_macro2::Span;
 use quote::quote;
 use syn::{parse_macro_input, ItemStruct};
 
+/// columns of datasets that contain block numbers, kept in sync with cryo_freeze
+const BLOCK_NUMBER_COLUMNS: [&str; 3] = ["block_number", "first_block", "last_block"];
+
 /// implements ToDataFrames and ColumnData for struct
 #[proc_macro_attribute]
 pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
@@ -37,7 +40,11 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
         .filter(|(_, value)| format!("{}", quote!(#value)).starts_with("Vec"))
         .filter(|(name, _)| name != "chain_id")
         .map(|(name, ty)| {
+            let is_block_number = BLOCK_NUMBER_COLUMNS.contains(&name.to_string().as_str());
             let macro_name = match quote!(#ty).to_string().as_str() {
+                _ if is_block_number => {
+                    syn::Ident::new("with_series_block_number", Span::call_site())
+                }
                 "Vec < Vec < u8 > >" => syn::Ident::new("with_series_binary", Span::call_site()),
                 "Vec < Option < Vec < u8 > > >" => {
                     syn::Ident::new("with_series_binary", Span::call_site())
@@ -171,6 +178,15 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
         quote! {}
     };
 
+    let has_dynamic_cols = field_names_and_types.iter().any(|(name, _)| name == "dynamic_cols");
+    let dynamic_code = if has_dynamic_cols {
+        quote! {
+            cols.extend(self.dynamic_cols.into_series(schema)?);
+        }
+    } else {
+        quote! {}
+    };
+
     fn map_type_to_column_type(ty: &syn::Type) -> Option<proc_macro2::TokenStream> {
         match quote!(#ty).to_string().as_str() {
             "Vec < bool >" => Some(quote! { ColumnType::Boolean }),
@@ -203,12 +219,23 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
         datatypes[0].segments.iter().map(|seg| seg.ident.to_string()).collect::<Vec<_>>();
     let datatype_str = datatype_str.iter().last().unwrap();
 
+    let nullable_columns: Vec<_> = field_names_and_types
+        .iter()
+        .filter(|(_, ty)| quote!(#ty).to_string().starts_with("Vec < Option <"))
+        .map(|(name, _)| format!("{}", quote!(#name)))
+        .collect();
+
     let mut column_types = Vec::new();
     for (name, ty) in field_names_and_types.iter() {
-        if let Some(column_type) = map_type_to_column_type(ty) {
+        // block numbers are collected as u64 and converted to the block number type of the schema
+        if BLOCK_NUMBER_COLUMNS.contains(&name.to_string().as_str()) {
+            let field_name_str = format!("{}", quote!(#name));
+            column_types
+                .push(quote! { (#field_name_str, BlockNumberType::default().to_columntype()) });
+        } else if let Some(column_type) = map_type_to_column_type(ty) {
             let field_name_str = format!("{}", quote!(#name));
             column_types.push(quote! { (#field_name_str, #column_type) });
-        } else if name != "n_rows" && name != "event_cols" {
+        } else if name != "n_rows" && name != "event_cols" && name != "dynamic_cols" {
             println!("invalid column type for {name} in table {}", datatype_str);
         }
     }
@@ -242,11 +269,21 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
 
                 #event_code
 
+                #dynamic_code
+
                 let df = DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)?;
                 let mut output = std::collections::HashMap::new();
                 output.insert(datatype, df);
                 Ok(output)
             }
+
+            fn n_rows(&self) -> u64 {
+                self.n_rows as u64
+            }
+
+            fn datatypes() -> Vec<Datatype> {
+                vec![#(#datatypes),*]
+            }
         }
 
         impl ColumnData for #name {
@@ -256,6 +293,10 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
                     #(#column_types),*
                 ])
             }
+
+            fn nullable_columns() -> Vec<&'static str> {
+                vec![#(#nullable_columns),*]
+            }
         }
     };
 